@@ -1,7 +1,11 @@
-use engine::App;
+use engine::{App, AppConfig};
 
 fn main() {
-    App::run();
+    // --headless skips the window and only initializes the device
+    match std::env::args().any(|arg| arg == "--headless") {
+        true => App::run_headless(AppConfig::default()),
+        false => App::run(),
+    }
 
     println!("Engine: shutdown");
 }
@@ -0,0 +1,13 @@
+#ifndef COMMON_GLSL
+#define COMMON_GLSL
+
+// Constants shared by every shader that includes this file
+
+const float PI = 3.14159265359;
+const float TAU = 6.28318530718;
+const float INV_PI = 0.31830988618;
+
+// Guards divisions and normalizations against zero
+const float EPSILON = 1e-5;
+
+#endif
@@ -0,0 +1,43 @@
+#ifndef MATH_GLSL
+#define MATH_GLSL
+
+#include "common.glsl"
+
+// Schlick's approximation, f0 is the reflectance at normal incidence
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Maps HDR color into 0..1, keeps hue but washes out highlights
+vec3 tonemap_reinhard(vec3 color) {
+    return color / (color + vec3(1.0));
+}
+
+// Narkowicz's fit of the ACES filmic curve
+vec3 tonemap_aces(vec3 color) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+}
+
+// Normal map texels store each component remapped to 0..1
+vec3 unpack_normal(vec3 texel) {
+    return normalize(texel * 2.0 - 1.0);
+}
+
+// Two channel normal maps (BC5 and similar) drop Z, it is rebuilt from the unit length
+vec3 unpack_normal_xy(vec2 texel) {
+    vec2 xy = texel * 2.0 - 1.0;
+    return vec3(xy, sqrt(max(1.0 - dot(xy, xy), 0.0)));
+}
+
+// z * z for complex numbers stored as (real, imaginary)
+vec2 complex_square(vec2 z) {
+    return vec2(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y);
+}
+
+#endif
@@ -0,0 +1,13 @@
+#ifndef SDF_GLSL
+#define SDF_GLSL
+
+#include "common.glsl"
+
+// Coverage of a glyph from a distance sampled out of SdfGenerator's output, in atlas texels
+// and negative inside. fwidth keeps the edge about a screen pixel wide at any scale
+float sdf_coverage(float distance) {
+    float edge_width = max(fwidth(distance), EPSILON);
+    return 1.0 - smoothstep(-edge_width * 0.5, edge_width * 0.5, distance);
+}
+
+#endif
@@ -0,0 +1,4 @@
+fn main() {
+    // Shaders #include files from here, recompile them when one changes
+    println!("cargo:rerun-if-changed=assets/shaders/include");
+}
@@ -0,0 +1,62 @@
+use std::{cell::Cell, rc::Rc, sync::Arc};
+
+use engine::{render::{bloom::BloomConfig, post_process::{OutputTonemapParams, PostProcessChain}}, vulkan::vertex::Triangle, Engine};
+use vulkano::{format::Format, pipeline::GraphicsPipeline};
+use winit::event::VirtualKeyCode;
+
+// Well past the bloom threshold, so the triangle glows onto the background
+mod bright_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                f_color = vec4(8.0, 3.0, 1.0, 1.0);
+            }
+        ",
+    }
+}
+
+fn main() {
+    let mut triangle : Option<Triangle> = None;
+    let mut pipeline : Option<Arc<GraphicsPipeline>> = None;
+
+    // Held B turns the bloom off, shared from update to render
+    let disabled = Rc::new(Cell::new(false));
+    let held = disabled.clone();
+
+    Engine::builder()
+    .window_title("Bloom")
+    .with_post_process(|toolset| {
+        let mut chain = PostProcessChain::for_window(toolset, Format::R16G16B16A16_SFLOAT).expect("failed to create post processing chain");
+        chain.add_bloom(toolset, BloomConfig { intensity : 0.8, radius : 1.5, ..Default::default() }).expect("failed to add bloom");
+
+        let output_mode = toolset.get_vulkan_window().output_mode();
+        chain.add_output_tonemap(toolset, OutputTonemapParams::new(1.0, output_mode)).expect("failed to add tonemap");
+        chain
+    })
+    .with_update(move |context, _| held.set(context.is_key_pressed(VirtualKeyCode::B)))
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+        let resized = frame.resized();
+        let chain = frame.post_process().unwrap();
+        chain.bloom_mut().unwrap().enabled = !disabled.get();
+
+        let triangle = triangle.get_or_insert_with(|| {
+            Triangle::new(toolset.memory_allocator.general_allocator.clone(), &toolset.logical_device)
+            .expect("failed to create triangle")
+        });
+
+        if pipeline.is_none() || resized {
+            let fs = bright_fs::load(toolset.logical_device.clone()).expect("failed to create shader module");
+            let viewport = toolset.get_vulkan_window().get_window_viewport();
+            pipeline = Some(toolset.create_graphics_pipeline_for(&triangle.vertex_shader, Some(&fs), chain.scene_subpass(), viewport));
+        }
+
+        frame.draw(pipeline.clone().unwrap(), triangle.vertex_buffer.clone());
+    })
+    .run();
+}
@@ -0,0 +1,48 @@
+use std::{cell::Cell, rc::Rc};
+
+use engine::{AppConfig, Engine};
+
+// Only ten steps a second, so the stepped box visibly jumps
+const FIXED_TIMESTEP : f32 = 0.1;
+
+// Logical pixels per second and the length of the track the boxes bounce along
+const SPEED : f32 = 400.0;
+const TRACK : f32 = 600.0;
+
+const BOX_SIZE : f32 = 40.0;
+
+fn main() {
+    // Box position before and after the latest fixed step, shared from fixed update to render
+    let positions = Rc::new(Cell::new((0.0f32, 0.0f32)));
+    let stepped = positions.clone();
+    let mut direction = 1.0;
+
+    let config = AppConfig {
+        fixed_timestep : FIXED_TIMESTEP,
+        ..AppConfig::default()
+    };
+
+    Engine::builder()
+    .config(config)
+    .window_title("Fixed timestep")
+    .with_fixed_update(move |_, step| {
+        let (_, current) = stepped.get();
+        let next = current + direction * SPEED * step;
+        if !(0.0..=TRACK).contains(&next) {
+            direction = -direction;
+        }
+        stepped.set((current, next.clamp(0.0, TRACK)));
+    })
+    .with_render(move |frame| {
+        // The same speed at any frame rate, blended by the fraction of a step left over
+        let (previous, current) = positions.get();
+        let interpolated = previous + (current - previous) * frame.alpha();
+
+        let overlay = frame.overlay();
+        overlay.text(20.0, 20.0, "stepped");
+        overlay.rect(20.0 + current, 40.0, BOX_SIZE, BOX_SIZE, [1.0, 0.4, 0.3, 1.0]);
+        overlay.text(20.0, 100.0, "interpolated");
+        overlay.rect(20.0 + interpolated, 120.0, BOX_SIZE, BOX_SIZE, [0.3, 0.8, 1.0, 1.0]);
+    })
+    .run();
+}
@@ -0,0 +1,236 @@
+use std::{path::Path, sync::Arc};
+
+use engine::{assets::gltf_loader::{GltfLoader, GltfScene}, scene::camera::Camera, vulkan::{storage_buffer::StorageBuffer, texture::Texture2D}, Engine};
+use vulkano::{buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage}, descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet}, memory::allocator::{AllocationCreateInfo, MemoryTypeFilter}, pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint}};
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 position;
+            layout(location = 1) in vec3 normal;
+            layout(location = 2) in vec2 uv;
+
+            layout(set = 0, binding = 0) uniform CameraData {
+                mat4 view_projection;
+                vec4 eye;
+            } camera;
+
+            struct Object {
+                mat4 model;
+                uint material;
+            };
+
+            // Indexed by the draw's first instance
+            layout(set = 0, binding = 2) readonly buffer Objects {
+                Object objects[];
+            };
+
+            layout(location = 0) out vec3 v_position;
+            layout(location = 1) out vec3 v_normal;
+            layout(location = 2) out vec2 v_uv;
+            layout(location = 3) flat out uint v_material;
+
+            void main() {
+                Object object = objects[gl_InstanceIndex];
+                vec4 world = object.model * vec4(position, 1.0);
+                gl_Position = camera.view_projection * world;
+                v_position = world.xyz;
+                v_normal = mat3(object.model) * normal;
+                v_uv = uv;
+                v_material = object.material;
+            }
+        ",
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 v_position;
+            layout(location = 1) in vec3 v_normal;
+            layout(location = 2) in vec2 v_uv;
+            layout(location = 3) flat in uint v_material;
+
+            layout(set = 0, binding = 0) uniform CameraData {
+                mat4 view_projection;
+                vec4 eye;
+            } camera;
+            layout(set = 0, binding = 1) uniform sampler2D base_color_texture;
+
+            struct Material {
+                vec4 base_color;
+            };
+
+            layout(set = 0, binding = 3) readonly buffer Materials {
+                Material materials[];
+            };
+
+            layout(location = 0) out vec4 f_color;
+
+            // Blinn-Phong with a single directional light
+            void main() {
+                vec4 base_color = materials[v_material].base_color;
+                vec3 albedo = texture(base_color_texture, v_uv).rgb * base_color.rgb;
+                vec3 normal = normalize(v_normal);
+                vec3 light = normalize(vec3(0.4, 0.8, 0.6));
+                vec3 view = normalize(camera.eye.xyz - v_position);
+                vec3 halfway = normalize(light + view);
+
+                float diffuse = max(dot(normal, light), 0.0);
+                float specular = pow(max(dot(normal, halfway), 0.0), 32.0) * float(diffuse > 0.0);
+                f_color = vec4(albedo * (0.15 + diffuse) + vec3(0.3 * specular), base_color.a);
+            }
+        ",
+    }
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct CameraData {
+    view_projection : [[f32; 4]; 4],
+    eye : [f32; 4],
+}
+
+// std430 pads the struct to a multiple of its 16 byte alignment
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct ObjectData {
+    model : [[f32; 4]; 4],
+    material : u32,
+    _padding : [u32; 3],
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct MaterialData {
+    base_color : [f32; 4],
+}
+
+fn rotation_y(angle : f32) -> [[f32; 4]; 4] {
+    let (sin, cos) = angle.sin_cos();
+
+    [
+        [cos, 0.0, -sin, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [sin, 0.0, cos, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+fn main() {
+    let mut scene : Option<GltfScene> = None;
+    let mut white : Option<Arc<Texture2D>> = None;
+    let mut pipeline : Option<Arc<GraphicsPipeline>> = None;
+    // Objects change every frame, so each frame in flight writes a buffer of its own
+    let mut objects : Vec<StorageBuffer<ObjectData>> = Vec::new();
+    let mut materials : Option<StorageBuffer<MaterialData>> = None;
+    let camera = Camera::perspective([3.0, 2.5, 4.0], [0.0, 0.0, 0.0], 1.0);
+    let mut time = 0.0;
+
+    Engine::builder()
+    .window_title("glTF scene")
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+        let scene = scene.get_or_insert_with(|| {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/cube.glb");
+
+            GltfLoader::load(&path, toolset).expect("failed to load cube.glb")
+        });
+        // Stands in for materials without a base color texture
+        let white = white.get_or_insert_with(|| Arc::new(Texture2D::from_rgba_bytes(toolset, 1, 1, &[255; 4])));
+        // The last material is the default for meshes without one
+        let default_material = scene.materials.len() as u32;
+        let materials = materials.get_or_insert_with(|| {
+            let data = scene.materials.iter()
+            .map(|material| MaterialData { base_color : material.base_color_factor })
+            .chain([MaterialData { base_color : [1.0; 4] }])
+            .collect::<Vec<_>>();
+
+            StorageBuffer::new(&toolset.memory_allocator, data.len(), Some(&data)).expect("failed to create the material buffer")
+        });
+        if objects.is_empty() {
+            objects = (0..toolset.config.frames_in_flight)
+            .map(|_| StorageBuffer::new(&toolset.memory_allocator, scene.meshes.len(), None).expect("failed to create an object buffer"))
+            .collect();
+        }
+        // The frame that last used this buffer finished before this one started
+        let objects = &objects[frame.frame_index()];
+
+        // Viewport is baked into the pipeline, so rebuild it after a resize
+        if pipeline.is_none() || frame.resized() {
+            let device = &toolset.logical_device;
+            let vs = vs::load(device.clone()).expect("failed to create shader module");
+            let fs = fs::load(device.clone()).expect("failed to create shader module");
+            pipeline = Some(toolset.create_mesh_pipeline(&vs, &fs));
+        }
+        let pipeline = pipeline.clone().unwrap();
+
+        time += frame.delta();
+        let extent = toolset.get_vulkan_window().get_window_viewport().extent;
+        let [x, y, z] = camera.position;
+        let camera_buffer = Buffer::from_data(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            CameraData {
+                view_projection : camera.view_projection(extent[0] / extent[1]),
+                eye : [x, y, z, 1.0],
+            },
+        ).unwrap();
+
+        for (object, (mesh, material)) in scene.meshes.iter().zip(&scene.mesh_materials).enumerate() {
+            objects.write_element(object, ObjectData {
+                model : rotation_y(time * 0.5),
+                material : material.map_or(default_material, |index| index as u32),
+                _padding : [0; 3],
+            }).unwrap();
+
+            let material = material.map(|index| &scene.materials[index]);
+            let texture = material.and_then(|material| material.base_color_texture.clone()).unwrap_or_else(|| white.clone());
+
+            let descriptor_set = PersistentDescriptorSet::new(
+                &toolset.memory_allocator.descriptor_set_allocator,
+                pipeline.layout().set_layouts()[0].clone(),
+                [
+                    WriteDescriptorSet::buffer(0, camera_buffer.clone()),
+                    texture.write_descriptor(1),
+                    objects.write_descriptor(2),
+                    materials.write_descriptor(3),
+                ],
+                [],
+            ).unwrap();
+
+            let pipeline = pipeline.clone();
+            let vertex_buffer = mesh.vertex_buffer.clone();
+            let index_buffer = mesh.index_buffer.clone();
+            let index_count = mesh.index_count();
+
+            frame.record(move |builder| {
+                builder.bind_pipeline_graphics(pipeline.clone())
+                .unwrap()
+                .bind_descriptor_sets(PipelineBindPoint::Graphics, pipeline.layout().clone(), 0, descriptor_set)
+                .unwrap()
+                .bind_vertex_buffers(0, vertex_buffer)
+                .unwrap()
+                .bind_index_buffer(index_buffer)
+                .unwrap()
+                .draw_indexed(index_count, 1, 0, 0, object as u32)
+                .unwrap();
+            });
+        }
+    })
+    .run();
+}
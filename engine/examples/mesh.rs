@@ -0,0 +1,205 @@
+use std::{cell::RefCell, path::Path, rc::Rc, sync::Arc};
+
+use engine::{assets::obj_loader::ObjLoader, render::skybox::{CameraUniform, Skybox}, scene::{camera::Camera, fly_camera::FlyCameraController}, vulkan::{mesh::Mesh, texture::{split_cube_faces, Texture2D}}, AppConfig, Engine, VulkanToolset};
+use image::{Rgba, RgbaImage};
+use vulkano::{buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage}, memory::allocator::{AllocationCreateInfo, MemoryTypeFilter}, pipeline::{GraphicsPipeline, Pipeline}};
+use winit::event::{MouseButton, VirtualKeyCode};
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 position;
+            layout(location = 1) in vec3 normal;
+            layout(location = 2) in vec2 uv;
+
+            layout(push_constant) uniform Transform {
+                mat4 model;
+                mat4 view_projection;
+            } transform;
+
+            layout(location = 0) out vec3 v_normal;
+            layout(location = 1) out vec2 v_uv;
+
+            void main() {
+                gl_Position = transform.view_projection * transform.model * vec4(position, 1.0);
+                v_normal = mat3(transform.model) * normal;
+                v_uv = uv;
+            }
+        ",
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 v_normal;
+            layout(location = 1) in vec2 v_uv;
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                float light = max(dot(normalize(v_normal), normalize(vec3(0.4, 0.8, 0.6))), 0.15);
+                f_color = vec4(vec3(v_uv, 1.0) * light, 1.0);
+            }
+        ",
+    }
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct Transform {
+    model : [[f32; 4]; 4],
+    view_projection : [[f32; 4]; 4],
+}
+
+// Rotation around Y followed by a tilt around X, so three faces are visible
+fn tumble(angle : f32) -> [[f32; 4]; 4] {
+    let (sin_y, cos_y) = angle.sin_cos();
+    let (sin_x, cos_x) = 0.5f32.sin_cos();
+
+    [
+        [cos_y, sin_x * sin_y, -cos_x * sin_y, 0.0],
+        [0.0, cos_x, sin_x, 0.0],
+        [sin_y, -sin_x * cos_y, cos_x * cos_y, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+// Sky fading from the horizon to the zenith over a dark ground, laid out as a +X, -X, +Y, -Y, +Z, -Z
+// strip the way a sky image on disk would be
+fn sky_cubemap(toolset : &VulkanToolset) -> Texture2D {
+    const SIZE : u32 = 64;
+    let zenith = [60.0, 110.0, 220.0];
+    let horizon = [210.0, 225.0, 250.0];
+    let ground = [70.0, 62.0, 52.0];
+
+    let strip = RgbaImage::from_fn(SIZE * 6, SIZE, |x, y| {
+        let face = x / SIZE;
+        let s = 2.0 * ((x % SIZE) as f32 + 0.5) / SIZE as f32 - 1.0;
+        let t = 2.0 * (y as f32 + 0.5) / SIZE as f32 - 1.0;
+
+        // Height of the direction through this texel, the side faces have +Y at the top
+        let height = match face {
+            2 => 1.0,
+            3 => -1.0,
+            _ => -t,
+        } / (1.0 + s * s + t * t).sqrt();
+
+        let [r, g, b] = if height >= 0.0 {
+            [0, 1, 2].map(|i| horizon[i] + (zenith[i] - horizon[i]) * height.sqrt())
+        } else {
+            ground
+        };
+        Rgba([r as u8, g as u8, b as u8, 255])
+    });
+
+    let faces = split_cube_faces(&strip).expect("failed to split the sky strip");
+    Texture2D::cube_from_face_images(toolset, &faces).expect("failed to create the sky cube map")
+}
+
+fn main() {
+    let mut meshes : Option<Vec<Mesh>> = None;
+    let mut pipeline : Option<Arc<GraphicsPipeline>> = None;
+    let mut skybox : Option<Skybox> = None;
+    let mut time = 0.0;
+
+    // Shared between the update callback, which flies it around, and the render callback
+    let camera = Rc::new(RefCell::new(Camera::perspective([0.0, 0.0, 5.0], [0.0, 0.0, 0.0], 1.0)));
+    let mut controller = FlyCameraController::new(&camera.borrow());
+    let render_camera = camera.clone();
+
+    // Escape releases the cursor here, so quit with Q instead
+    let config = AppConfig {
+        exit_key : Some(VirtualKeyCode::Q),
+        ..AppConfig::default()
+    };
+
+    Engine::builder()
+    .config(config)
+    .window_title("Mesh")
+    .with_update(move |context, delta| {
+        // Click to look around, escape gives the cursor back
+        if context.input().is_mouse_pressed(MouseButton::Left) {
+            context.input_mut().set_cursor_grabbed(true);
+        }
+        if context.is_key_pressed(VirtualKeyCode::Escape) {
+            context.input_mut().set_cursor_grabbed(false);
+        }
+
+        controller.update(&mut camera.borrow_mut(), context.input(), delta);
+    })
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+        let meshes = meshes.get_or_insert_with(|| {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/cube.obj");
+
+            ObjLoader::load(&path, &toolset.memory_allocator, &toolset.device_queue)
+            .expect("failed to load cube.obj")
+        });
+        let skybox = skybox.get_or_insert_with(|| Skybox::new(toolset, sky_cubemap(toolset)));
+
+        // Viewport is baked into the pipeline, so rebuild it after a resize
+        if pipeline.is_none() || frame.resized() {
+            let device = &toolset.logical_device;
+            let vs = vs::load(device.clone()).expect("failed to create shader module");
+            let fs = fs::load(device.clone()).expect("failed to create shader module");
+            pipeline = Some(toolset.create_mesh_pipeline(&vs, &fs));
+            skybox.recreate_pipeline(toolset);
+        }
+
+        time += frame.delta();
+        let extent = toolset.get_vulkan_window().get_window_viewport().extent;
+        let aspect = extent[0] / extent[1];
+        let transform = Transform {
+            model : tumble(time),
+            view_projection : render_camera.borrow().view_projection(aspect),
+        };
+
+        for mesh in meshes.iter() {
+            let pipeline = pipeline.clone().unwrap();
+            let vertex_buffer = mesh.vertex_buffer.clone();
+            let index_buffer = mesh.index_buffer.clone();
+            let index_count = mesh.index_count();
+
+            frame.record(move |builder| {
+                builder.bind_pipeline_graphics(pipeline.clone())
+                .unwrap()
+                .push_constants(pipeline.layout().clone(), 0, transform)
+                .unwrap()
+                .bind_vertex_buffers(0, vertex_buffer)
+                .unwrap()
+                .bind_index_buffer(index_buffer)
+                .unwrap()
+                .draw_indexed(index_count, 1, 0, 0, 0)
+                .unwrap();
+            });
+        }
+
+        // After the cube, so the sky only fills what it left at the far plane
+        let camera = Buffer::from_data(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            CameraUniform {
+                view : render_camera.borrow().view_matrix(),
+                projection : render_camera.borrow().projection_matrix(aspect),
+            },
+        ).unwrap();
+
+        let sky = skybox.clone();
+        frame.record(move |builder| sky.record(builder, camera));
+    })
+    .run();
+}
@@ -0,0 +1,31 @@
+use engine::{render::particles::ParticleSystem, Engine};
+
+const PARTICLE_COUNT : u32 = 10_000;
+
+fn main() {
+    let mut particles : Option<ParticleSystem> = None;
+    let mut time = 0.0f32;
+
+    Engine::builder()
+    .window_title("Particles")
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+        let particles = particles.get_or_insert_with(|| ParticleSystem::new(toolset, PARTICLE_COUNT, [0.0, 0.0, 0.0]));
+
+        // Viewport is baked into the pipeline, so rebuild it after a resize
+        if frame.resized() {
+            particles.recreate_pipeline(toolset);
+        }
+
+        // The emitter circles the center, new particles trail behind it
+        let delta = frame.delta();
+        time += delta;
+        particles.emitter = [time.cos() * 0.5, time.sin() * 0.3 - 0.2, 0.0];
+
+        // The compute passes update the buffer before the render pass draws it as points
+        frame.clear([0.02, 0.02, 0.05, 1.0]);
+        particles.simulate(frame, delta);
+        particles.draw(frame);
+    })
+    .run();
+}
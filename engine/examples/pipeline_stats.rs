@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use engine::{vulkan::vertex::Triangle, Engine};
+use vulkano::{pipeline::GraphicsPipeline, query::QueryPipelineStatisticFlags};
+
+// The counters the engine queries around every frame
+const COUNTERS : [(&str, QueryPipelineStatisticFlags); 5] = [
+    ("vertices", QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES),
+    ("primitives", QueryPipelineStatisticFlags::INPUT_ASSEMBLY_PRIMITIVES),
+    ("vertex shader", QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS),
+    ("clipping", QueryPipelineStatisticFlags::CLIPPING_INVOCATIONS),
+    ("fragment shader", QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS),
+];
+
+fn main() {
+    let mut triangle : Option<Triangle> = None;
+    let mut pipeline : Option<Arc<GraphicsPipeline>> = None;
+
+    Engine::builder()
+    .window_title("Pipeline statistics")
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+        let triangle = triangle.get_or_insert_with(|| {
+            Triangle::new(toolset.memory_allocator.general_allocator.clone(), &toolset.logical_device)
+            .expect("failed to create triangle")
+        });
+
+        // Viewport is baked into the pipeline, so rebuild it after a resize
+        if pipeline.is_none() || frame.resized() {
+            pipeline = Some(toolset.create_graphics_pipeline(&triangle.vertex_shader, &triangle.fragment_shader));
+        }
+
+        frame.draw(pipeline.clone().unwrap(), triangle.vertex_buffer.clone());
+
+        // Counters of the last completed frame, none without pipeline_statistics_query
+        let text = match frame.pipeline_stats() {
+            Some(stats) => COUNTERS
+            .iter()
+            .map(|(name, flag)| format!("{name} {}", stats.get(flag).copied().unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+            None => "no pipeline statistics".to_owned(),
+        };
+        frame.overlay().text(10.0, 10.0, &text);
+    })
+    .run();
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use engine::{vulkan::point_cloud::{PointCloud, PointSizeSource}, Engine};
+use vulkano::pipeline::GraphicsPipeline;
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(constant_id = 0) const float POINT_SIZE = 1.0;
+
+            layout(location = 0) in vec3 position;
+            layout(location = 1) in vec4 color;
+
+            layout(location = 0) out vec4 v_color;
+
+            void main() {
+                gl_Position = vec4(position, 1.0);
+                gl_PointSize = POINT_SIZE;
+                v_color = color;
+            }
+        ",
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec4 v_color;
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                f_color = v_color;
+            }
+        ",
+    }
+}
+
+const POINT_COUNT : usize = 100_000;
+
+// Xorshift generator, good enough for scattering test points
+fn next_random(state : &mut u32) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+
+    *state as f32 / u32::MAX as f32
+}
+
+fn main() {
+    let mut cloud : Option<PointCloud> = None;
+    let mut pipeline : Option<Arc<GraphicsPipeline>> = None;
+
+    Engine::builder()
+    .window_title("Point cloud")
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+        let cloud = cloud.get_or_insert_with(|| {
+            // Scatter random points in clip space
+            let mut seed = 0x9E37_79B9;
+            let positions = (0..POINT_COUNT)
+            .map(|_| [next_random(&mut seed) * 2.0 - 1.0, next_random(&mut seed) * 2.0 - 1.0, next_random(&mut seed)])
+            .collect::<Vec<_>>();
+            let colors = (0..POINT_COUNT)
+            .map(|_| [next_random(&mut seed), next_random(&mut seed), next_random(&mut seed), 1.0])
+            .collect::<Vec<_>>();
+
+            PointCloud::from_positions_colors(&positions, &colors, toolset.memory_allocator.general_allocator.clone())
+        });
+
+        // Viewport is baked into the pipeline, so rebuild it after a resize
+        if pipeline.is_none() || frame.resized() {
+            let device = &toolset.logical_device;
+            let vs = vs::load(device.clone()).expect("failed to create shader module");
+            let fs = fs::load(device.clone()).expect("failed to create shader module");
+            pipeline = Some(toolset.create_point_pipeline(&vs, &fs, PointSizeSource::Fixed(2.0)));
+        }
+
+        let pipeline = pipeline.clone().unwrap();
+        let vertex_buffer = cloud.vertex_buffer.clone();
+        let point_count = cloud.len();
+
+        frame.record(move |builder| {
+            builder.bind_pipeline_graphics(pipeline)
+            .unwrap()
+            .bind_vertex_buffers(0, vertex_buffer)
+            .unwrap()
+            .draw(point_count, 1, 0, 0)
+            .unwrap();
+        });
+    })
+    .run();
+}
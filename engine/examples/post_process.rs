@@ -0,0 +1,118 @@
+use std::{cell::Cell, rc::Rc, sync::Arc};
+
+use engine::{render::post_process::{OutputTonemapParams, PostProcessChain, TONEMAP}, vulkan::vertex::Triangle, AppConfig, Engine};
+use vulkano::{format::Format, pipeline::GraphicsPipeline};
+use winit::event::VirtualKeyCode;
+
+// Brighter than 1.0, the tonemap brings it back into the displayable range
+mod hdr_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                f_color = vec4(6.0, 2.0, 0.5, 1.0);
+            }
+        ",
+    }
+}
+
+// User effect, darkens the corners by params.x
+mod vignette_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec2 v_uv;
+            layout(location = 0) out vec4 f_color;
+
+            layout(set = 0, binding = 0) uniform sampler2D source;
+
+            layout(push_constant) uniform Params {
+                vec4 params;
+            };
+
+            void main() {
+                vec2 centered = v_uv * 2.0 - 1.0;
+                float falloff = 1.0 - params.x * dot(centered, centered) * 0.5;
+
+                f_color = texture(source, v_uv) * vec4(vec3(clamp(falloff, 0.0, 1.0)), 1.0);
+            }
+        ",
+    }
+}
+
+fn main() {
+    let mut triangle : Option<Triangle> = None;
+    let mut pipeline : Option<Arc<GraphicsPipeline>> = None;
+
+    // Held keys turn the effects off, shared from update to render
+    let disabled = Rc::new(Cell::new((false, false)));
+    let held = disabled.clone();
+    // Held H shows the HDR output clipped like SDR for comparison
+    let sdr_preview = Rc::new(Cell::new(false));
+    let held_preview = sdr_preview.clone();
+
+    // Falls back to SDR when the display can't do HDR
+    let config = AppConfig {
+        hdr : true,
+        ..AppConfig::default()
+    };
+
+    Engine::builder()
+    .config(config)
+    .window_title("Post processing")
+    .with_post_process(|toolset| {
+        let mut chain = PostProcessChain::for_window(toolset, Format::R16G16B16A16_SFLOAT).expect("failed to create post processing chain");
+
+        let vignette = vignette_fs::load(toolset.logical_device.clone()).expect("failed to create shader module");
+        chain.add_effect(toolset, "vignette", &vignette).expect("failed to add vignette");
+        chain.effect_mut("vignette").unwrap().params = [0.8, 0.0, 0.0, 0.0];
+
+        // Last, the window's format can't hold the HDR values or expects them encoded
+        let output_mode = toolset.get_vulkan_window().output_mode();
+        chain.add_output_tonemap(toolset, OutputTonemapParams::new(1.0, output_mode)).expect("failed to add tonemap");
+        chain
+    })
+    .with_update(move |context, _| {
+        held.set((context.is_key_pressed(VirtualKeyCode::V), context.is_key_pressed(VirtualKeyCode::T)));
+        held_preview.set(context.is_key_pressed(VirtualKeyCode::H));
+    })
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+        let resized = frame.resized();
+        let chain = frame.post_process().unwrap();
+
+        let (vignette_off, tonemap_off) = disabled.get();
+        chain.effect_mut("vignette").unwrap().enabled = !vignette_off;
+        chain.effect_mut(TONEMAP).unwrap().enabled = !tonemap_off;
+
+        let output_mode = toolset.get_vulkan_window().output_mode();
+        if output_mode.is_hdr() {
+            let params = OutputTonemapParams::new(1.0, output_mode);
+            chain.effect_mut(TONEMAP).unwrap().params = match sdr_preview.get() {
+                true => params.sdr_preview(),
+                false => params,
+            }.into();
+        }
+
+        let triangle = triangle.get_or_insert_with(|| {
+            Triangle::new(toolset.memory_allocator.general_allocator.clone(), &toolset.logical_device)
+            .expect("failed to create triangle")
+        });
+
+        // The scene is drawn into the chain's target, so the pipeline is built for its subpass
+        if pipeline.is_none() || resized {
+            let fs = hdr_fs::load(toolset.logical_device.clone()).expect("failed to create shader module");
+            let viewport = toolset.get_vulkan_window().get_window_viewport();
+            pipeline = Some(toolset.create_graphics_pipeline_for(&triangle.vertex_shader, Some(&fs), chain.scene_subpass(), viewport));
+        }
+
+        frame.draw(pipeline.clone().unwrap(), triangle.vertex_buffer.clone());
+    })
+    .run();
+}
@@ -0,0 +1,47 @@
+use std::{cell::Cell, path::Path, rc::Rc, sync::Arc};
+
+use engine::{vulkan::vertex::Triangle, Engine};
+use vulkano::pipeline::GraphicsPipeline;
+use winit::event::VirtualKeyCode;
+
+fn main() {
+    let mut triangle : Option<Triangle> = None;
+    let mut pipeline : Option<Arc<GraphicsPipeline>> = None;
+
+    // R starts and stops recording every other frame into ./recording
+    let toggle_recording = Rc::new(Cell::new(false));
+    let toggle_requested = toggle_recording.clone();
+    let mut record_key_held = false;
+
+    Engine::builder()
+    .window_title("Recording")
+    .with_update(move |context, _| {
+        let held = context.is_key_pressed(VirtualKeyCode::R);
+        if held && !record_key_held {
+            toggle_requested.set(true);
+        }
+        record_key_held = held;
+    })
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+
+        if toggle_recording.replace(false) {
+            match frame.is_recording() {
+                true => frame.stop_recording(),
+                false => frame.start_recording(Path::new("recording"), 2).unwrap_or_else(|e| println!("can't record: {e}")),
+            }
+        }
+        let triangle = triangle.get_or_insert_with(|| {
+            Triangle::new(toolset.memory_allocator.general_allocator.clone(), &toolset.logical_device)
+            .expect("failed to create triangle")
+        });
+
+        // Viewport is baked into the pipeline, so rebuild it after a resize
+        if pipeline.is_none() || frame.resized() {
+            pipeline = Some(toolset.create_graphics_pipeline(&triangle.vertex_shader, &triangle.fragment_shader));
+        }
+
+        frame.draw(pipeline.clone().unwrap(), triangle.vertex_buffer.clone());
+    })
+    .run();
+}
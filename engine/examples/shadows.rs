@@ -0,0 +1,258 @@
+use std::{path::Path, sync::Arc};
+
+use engine::{assets::obj_loader::ObjLoader, render::shadow_map::ShadowMapPass, scene::camera::{Camera, Matrix4}, vulkan::{mesh::{Mesh, Vertex3D}, vulkan::{CullConfig, DepthBias}}, Engine};
+use vulkano::{buffer::{Buffer, BufferCreateInfo, BufferUsage}, descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet}, memory::allocator::{AllocationCreateInfo, MemoryTypeFilter}, pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint}};
+
+// Into the scene, from above and to the side so the shadow falls next to the cube
+const LIGHT_DIRECTION : [f32; 3] = [0.5, -1.0, 0.35];
+const SHADOW_MAP_SIZE : u32 = 2048;
+const GROUND_HALF_EXTENT : f32 = 6.0;
+
+mod caster_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 position;
+
+            layout(push_constant) uniform Caster {
+                mat4 model;
+                mat4 light_view_projection;
+            } caster;
+
+            void main() {
+                gl_Position = caster.light_view_projection * caster.model * vec4(position, 1.0);
+            }
+        ",
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 position;
+            layout(location = 1) in vec3 normal;
+            layout(location = 2) in vec2 uv;
+
+            layout(set = 0, binding = 0) uniform Scene {
+                mat4 view_projection;
+                mat4 light_view_projection;
+                vec4 light_direction;
+            } scene;
+
+            layout(push_constant) uniform Object {
+                mat4 model;
+                vec4 color;
+            } object;
+
+            layout(location = 0) out vec3 v_normal;
+            layout(location = 1) out vec4 v_light_position;
+
+            void main() {
+                vec4 world = object.model * vec4(position, 1.0);
+                gl_Position = scene.view_projection * world;
+                v_normal = mat3(object.model) * normal;
+                v_light_position = scene.light_view_projection * world;
+            }
+        ",
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 v_normal;
+            layout(location = 1) in vec4 v_light_position;
+            layout(location = 0) out vec4 f_color;
+
+            layout(set = 0, binding = 0) uniform Scene {
+                mat4 view_projection;
+                mat4 light_view_projection;
+                vec4 light_direction;
+            } scene;
+
+            layout(set = 0, binding = 1) uniform sampler2DShadow shadow_map;
+
+            layout(push_constant) uniform Object {
+                mat4 model;
+                vec4 color;
+            } object;
+
+            // Nine filtered comparisons around the texel, 1.0 is fully lit
+            float shadow(vec3 position) {
+                vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0));
+                float lit = 0.0;
+                for (int x = -1; x <= 1; x++) {
+                    for (int y = -1; y <= 1; y++) {
+                        lit += texture(shadow_map, vec3(position.xy + vec2(x, y) * texel, position.z));
+                    }
+                }
+
+                return lit / 9.0;
+            }
+
+            void main() {
+                // Clip space of the light to shadow map coordinates, its projection already points Y down
+                vec3 light_position = v_light_position.xyz / v_light_position.w;
+                vec3 position = vec3(light_position.xy * 0.5 + 0.5, light_position.z);
+
+                float diffuse = max(dot(normalize(v_normal), -normalize(scene.light_direction.xyz)), 0.0);
+                float light = 0.2 + 0.8 * diffuse * shadow(position);
+                f_color = vec4(object.color.rgb * light, 1.0);
+            }
+        ",
+    }
+}
+
+// Rotation around Y followed by a tilt around X, raised above the ground
+fn tumble(angle : f32, height : f32) -> Matrix4 {
+    let (sin_y, cos_y) = angle.sin_cos();
+    let (sin_x, cos_x) = 0.5f32.sin_cos();
+
+    [
+        [cos_y, sin_x * sin_y, -cos_x * sin_y, 0.0],
+        [0.0, cos_x, sin_x, 0.0],
+        [sin_y, -sin_x * cos_y, cos_x * cos_y, 0.0],
+        [0.0, height, 0.0, 1.0],
+    ]
+}
+
+const IDENTITY : Matrix4 = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+// A square at y = 0 facing up
+fn ground_vertices() -> Vec<Vertex3D> {
+    let s = GROUND_HALF_EXTENT;
+
+    [[-s, -s], [-s, s], [s, s], [s, -s]]
+    .into_iter()
+    .map(|[x, z]| Vertex3D { position : [x, 0.0, z], normal : [0.0, 1.0, 0.0], uv : [0.0, 0.0] })
+    .collect()
+}
+
+// Both meshes with where they are drawn this frame
+struct SceneObject {
+    mesh : Arc<Mesh>,
+    model : Matrix4,
+    color : [f32; 4],
+}
+
+fn main() {
+    let mut cube : Option<Arc<Mesh>> = None;
+    let mut ground : Option<Arc<Mesh>> = None;
+    let mut shadow_map : Option<ShadowMapPass> = None;
+    let mut caster_pipeline : Option<Arc<GraphicsPipeline>> = None;
+    let mut pipeline : Option<Arc<GraphicsPipeline>> = None;
+    let mut time = 0.0;
+
+    let camera = Camera::perspective([0.0, 5.0, 10.0], [0.0, 1.0, 0.0], 0.9);
+    // Covers the whole ground, so every shadow it receives is inside the map
+    let light = Camera::directional_light(LIGHT_DIRECTION, [0.0, 0.0, 0.0], GROUND_HALF_EXTENT * 1.5);
+    let light_view_projection = light.view_projection(1.0);
+
+    Engine::builder()
+    .window_title("Shadows")
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+        let cube = cube.get_or_insert_with(|| {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/cube.obj");
+            let mut meshes = ObjLoader::load(&path, &toolset.memory_allocator, &toolset.device_queue)
+            .expect("failed to load cube.obj");
+
+            Arc::new(meshes.remove(0))
+        });
+        let ground = ground.get_or_insert_with(|| {
+            let mesh = Mesh::new(&toolset.memory_allocator, &toolset.device_queue, ground_vertices(), vec![0u32, 1, 2, 0, 2, 3])
+            .expect("failed to create the ground");
+
+            Arc::new(mesh)
+        });
+
+        // The shadow map and its pipeline keep their size when the window resizes
+        let shadow_map = shadow_map.get_or_insert_with(|| ShadowMapPass::new(toolset, SHADOW_MAP_SIZE));
+        let caster_pipeline = caster_pipeline.get_or_insert_with(|| {
+            let vs = caster_vs::load(toolset.logical_device.clone()).expect("failed to create shader module");
+
+            shadow_map.create_caster_pipeline(toolset, &vs, CullConfig::back_faces(), DepthBias::shadow_caster())
+            .expect("failed to create the shadow caster pipeline")
+        }).clone();
+
+        // The window's viewport is baked into the lit pipeline, so it is rebuilt after a resize
+        if pipeline.is_none() || frame.resized() {
+            let device = &toolset.logical_device;
+            let vs = vs::load(device.clone()).expect("failed to create shader module");
+            let fs = fs::load(device.clone()).expect("failed to create shader module");
+            pipeline = Some(toolset.create_mesh_pipeline(&vs, &fs));
+        }
+        let pipeline = pipeline.clone().unwrap();
+
+        time += frame.delta();
+        let objects = [
+            SceneObject { mesh : cube.clone(), model : tumble(time, 2.0), color : [0.9, 0.45, 0.2, 1.0] },
+            SceneObject { mesh : ground.clone(), model : IDENTITY, color : [0.6, 0.65, 0.7, 1.0] },
+        ];
+
+        // The ground only receives shadows, the cube is the one caster
+        let caster = caster_vs::Caster { model : objects[0].model, light_view_projection };
+        let caster_mesh = objects[0].mesh.clone();
+        frame.render_shadow_map(shadow_map, move |builder| {
+            builder.bind_pipeline_graphics(caster_pipeline.clone())
+            .unwrap()
+            .push_constants(caster_pipeline.layout().clone(), 0, caster)
+            .unwrap();
+            caster_mesh.bind(builder).unwrap();
+            builder.draw_indexed(caster_mesh.index_count(), 1, 0, 0, 0).unwrap();
+        });
+
+        let extent = toolset.get_vulkan_window().get_window_viewport().extent;
+        let scene = Buffer::from_data(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            vs::Scene {
+                view_projection : camera.view_projection(extent[0] / extent[1]),
+                light_view_projection,
+                light_direction : [LIGHT_DIRECTION[0], LIGHT_DIRECTION[1], LIGHT_DIRECTION[2], 0.0],
+            },
+        ).unwrap();
+        let descriptor_set = PersistentDescriptorSet::new(
+            &toolset.memory_allocator.descriptor_set_allocator,
+            pipeline.layout().set_layouts()[0].clone(),
+            [WriteDescriptorSet::buffer(0, scene), shadow_map.write_compare_descriptor(1)],
+            [],
+        ).unwrap();
+
+        frame.record(move |builder| {
+            builder.bind_pipeline_graphics(pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(PipelineBindPoint::Graphics, pipeline.layout().clone(), 0, descriptor_set)
+            .unwrap();
+
+            for object in objects {
+                builder.push_constants(pipeline.layout().clone(), 0, vs::Object { model : object.model, color : object.color })
+                .unwrap();
+                object.mesh.bind(builder).unwrap();
+                builder.draw_indexed(object.mesh.index_count(), 1, 0, 0, 0).unwrap();
+            }
+        });
+    })
+    .run();
+}
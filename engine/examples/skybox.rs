@@ -0,0 +1,84 @@
+use engine::{render::skybox::{CameraUniform, Skybox}, vulkan::texture::Texture2D, Engine, VulkanToolset};
+use vulkano::{buffer::{Buffer, BufferCreateInfo, BufferUsage}, memory::allocator::{AllocationCreateInfo, MemoryTypeFilter}};
+
+// Column major Vulkan projection with Y pointing down and depth in 0..1
+fn perspective(fov_y : f32, aspect : f32, near : f32, far : f32) -> [[f32; 4]; 4] {
+    let f = 1.0 / (fov_y / 2.0).tan();
+
+    [
+        [f / aspect, 0.0, 0.0, 0.0],
+        [0.0, -f, 0.0, 0.0],
+        [0.0, 0.0, far / (near - far), -1.0],
+        [0.0, 0.0, near * far / (near - far), 0.0],
+    ]
+}
+
+fn rotation_y(angle : f32) -> [[f32; 4]; 4] {
+    let (sin, cos) = angle.sin_cos();
+
+    [
+        [cos, 0.0, -sin, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [sin, 0.0, cos, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+// Each face gets its own tint with a vertical gradient
+fn gradient_cubemap(toolset : &VulkanToolset) -> Texture2D {
+    const SIZE : u32 = 64;
+    let tints : [[u8; 3]; 6] = [[255, 80, 80], [80, 255, 80], [120, 170, 255], [60, 50, 40], [255, 255, 120], [200, 120, 255]];
+
+    let faces = tints.map(|tint| {
+        (0..SIZE * SIZE)
+        .flat_map(|i| {
+            let shade = 255 - (i / SIZE * 160 / SIZE) as u16;
+            let channel = |c : u8| (c as u16 * shade / 255) as u8;
+
+            [channel(tint[0]), channel(tint[1]), channel(tint[2]), 255]
+        }).collect::<Vec<u8>>()
+    });
+
+    Texture2D::cube_from_rgba_faces(toolset, SIZE, faces.each_ref().map(|face| face.as_slice()))
+}
+
+fn main() {
+    let mut skybox : Option<Skybox> = None;
+    let mut angle = 0.0f32;
+
+    Engine::builder()
+    .window_title("Skybox")
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+        let skybox = skybox.get_or_insert_with(|| Skybox::new(toolset, gradient_cubemap(toolset)));
+
+        // Viewport is baked into the pipeline, so rebuild it after a resize
+        if frame.resized() {
+            skybox.recreate_pipeline(toolset);
+        }
+
+        // Slowly spinning camera looking around the skybox
+        angle += frame.delta() * 0.2;
+        let extent = toolset.get_vulkan_window().get_window_viewport().extent;
+        let camera = Buffer::from_data(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            CameraUniform {
+                view : rotation_y(angle),
+                projection : perspective(1.2, extent[0] / extent[1], 0.1, 100.0),
+            },
+        ).unwrap();
+
+        let sky = skybox.clone();
+        frame.record(move |builder| sky.record(builder, camera));
+    })
+    .run();
+}
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use engine::{render::split_screen::ViewportRegion, scene::camera::Camera, vulkan::vertex::Triangle, Engine};
+use vulkano::pipeline::GraphicsPipeline;
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec2 position;
+
+            // Pushed per viewport region by the engine
+            layout(push_constant) uniform Region {
+                mat4 view_projection;
+            } region;
+
+            void main() {
+                gl_Position = region.view_projection * vec4(position, 0.0, 1.0);
+            }
+        ",
+    }
+}
+
+fn main() {
+    let mut triangle : Option<Triangle> = None;
+    let mut pipeline : Option<Arc<GraphicsPipeline>> = None;
+    let mut time = 0.0;
+
+    Engine::builder()
+    .window_title("Split screen")
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+        let triangle = triangle.get_or_insert_with(|| {
+            Triangle::new(toolset.memory_allocator.general_allocator.clone(), &toolset.logical_device)
+            .expect("failed to create triangle")
+        });
+
+        // The viewport is dynamic, so resizes don't need a new pipeline
+        let pipeline = pipeline.get_or_insert_with(|| {
+            let vs = vs::load(toolset.logical_device.clone()).expect("failed to create shader module");
+            toolset.create_dynamic_viewport_pipeline(&vs, &triangle.fragment_shader)
+        });
+
+        time += frame.delta();
+
+        // Two players orbiting the triangle from opposite sides, at different speeds
+        let orbit = |angle : f32| Camera::perspective([angle.sin() * 2.0, 0.0, angle.cos() * 2.0], [0.0, 0.0, 0.0], 1.0);
+        let cameras = [orbit(time * 0.5), orbit(std::f32::consts::PI - time)];
+
+        // Recomputed every frame, so the halves follow the window size
+        let extent = toolset.get_vulkan_window().physical_size();
+        frame.set_viewport_regions(ViewportRegion::columns([extent.width, extent.height], &cameras));
+
+        frame.draw(pipeline.clone(), triangle.vertex_buffer.clone());
+    })
+    .run();
+}
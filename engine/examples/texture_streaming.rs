@@ -0,0 +1,349 @@
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+use engine::{render::texture_streaming::{average_color, TextureStreamer}, vulkan::{render_pass::{create_framebuffer, RenderPassBuilder}, staging::upload_buffer, vertex::VulkanVertex, vulkan_window::AttachmentConfig}, Engine, EngineError, VulkanToolset};
+use vulkano::{
+    buffer::{BufferContents, BufferUsage, Subbuffer},
+    command_buffer::{RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    format::{ClearValue, Format},
+    image::{sampler::{Sampler, SamplerCreateInfo}, view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage},
+    memory::allocator::AllocationCreateInfo,
+    pipeline::{graphics::viewport::Viewport, GraphicsPipeline, Pipeline, PipelineBindPoint},
+    render_pass::{Framebuffer, Subpass}
+};
+
+const TILE_SIZE : u32 = 128;
+const TILE_COUNT : u32 = 24;
+// Fewer slots than tiles, close objects compete for them
+const CACHE_SLOTS : u32 = 8;
+const OBJECTS : usize = 96;
+
+// Unit quad placed by the push constants, depth is only used for drawing far objects first
+mod scene_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec2 position;
+            layout(location = 0) out vec2 v_uv;
+
+            layout(push_constant) uniform Object {
+                vec2 center;
+                vec2 half_size;
+                uint tile;
+            } object;
+
+            void main() {
+                gl_Position = vec4(object.center + (position * 2.0 - 1.0) * object.half_size, 0.0, 1.0);
+                v_uv = position;
+            }
+        ",
+    }
+}
+
+// Full resolution tiles where they are resident, their average color everywhere else. Objects large
+// enough on screen for the tile's texels to show ask for them through the feedback attachment
+mod scene_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec2 v_uv;
+            layout(location = 0) out vec4 f_color;
+            layout(location = 1) out uint f_tile_id;
+
+            layout(set = 0, binding = 0) uniform sampler2DArray cache;
+            layout(set = 0, binding = 1) readonly buffer PageTable {
+                uint slots[];
+            };
+            layout(set = 0, binding = 2) readonly buffer Fallback {
+                vec4 fallback_colors[];
+            };
+
+            layout(push_constant) uniform Object {
+                vec2 center;
+                vec2 half_size;
+                uint tile;
+            } object;
+
+            void main() {
+                // Past a few texels per pixel the average color looks the same as the tile
+                vec2 texels = v_uv * vec2(textureSize(cache, 0).xy);
+                float footprint = max(length(dFdx(texels)), length(dFdy(texels)));
+                f_tile_id = footprint < 4.0 ? object.tile + 1 : 0;
+
+                uint slot = slots[object.tile];
+                f_color = slot == 0xffffffffu ? fallback_colors[object.tile] : texture(cache, vec3(v_uv, float(slot)));
+            }
+        ",
+    }
+}
+
+// Copies the scene into the window
+mod present_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec2 position;
+            layout(location = 0) out vec2 v_uv;
+
+            void main() {
+                gl_Position = vec4(position, 0.0, 1.0);
+                v_uv = position * 0.5 + 0.5;
+            }
+        ",
+    }
+}
+
+mod present_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec2 v_uv;
+            layout(location = 0) out vec4 f_color;
+
+            layout(set = 0, binding = 0) uniform sampler2D scene;
+
+            void main() {
+                f_color = texture(scene, v_uv);
+            }
+        ",
+    }
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct ObjectConstants {
+    center : [f32; 2],
+    half_size : [f32; 2],
+    tile : u32,
+}
+
+// Checkerboard in a color of its own per tile
+fn tile_texels(tile : u32) -> Vec<u8> {
+    let hue = tile as f32 / TILE_COUNT as f32 * std::f32::consts::TAU;
+    let color = [0.0, 2.1, 4.2].map(|offset : f32| ((hue + offset).cos() * 0.5 + 0.5) * 255.0);
+
+    (0..TILE_SIZE * TILE_SIZE).flat_map(|i| {
+        let (x, y) = (i % TILE_SIZE / 16, i / TILE_SIZE / 16);
+        let shade = if (x + y) % 2 == 0 { 1.0 } else { 0.35 };
+        [color[0] * shade, color[1] * shade, color[2] * shade, 255.0].map(|channel| channel as u8)
+    }).collect()
+}
+
+// Objects on a grid receding from the camera, drifting closer and further over time
+fn objects(time : f32) -> Vec<(f32, ObjectConstants)> {
+    let mut objects = (0..OBJECTS).map(|i| {
+        let (column, row) = ((i % 8) as f32 - 3.5, (i / 8) as f32);
+        let distance = 1.5 + row * 3.0 + (time * 0.4 + column).sin() * 1.2;
+
+        (distance, ObjectConstants {
+            center : [column * 0.6 / distance, 0.4 / distance - 0.1],
+            half_size : [0.25 / distance; 2],
+            tile : i as u32 % TILE_COUNT,
+        })
+    }).collect::<Vec<_>>();
+
+    // Far objects first, near ones cover them
+    objects.sort_by(|a, b| b.0.total_cmp(&a.0));
+    objects
+}
+
+// Offscreen targets the scene is drawn into, sized like the window
+struct SceneTarget {
+    framebuffer : Arc<Framebuffer>,
+    color : Arc<ImageView>,
+    tile_ids : Arc<ImageView>,
+}
+
+impl SceneTarget {
+    fn new(toolset : &VulkanToolset, subpass : &Subpass) -> Result<SceneTarget, EngineError> {
+        let extent = toolset.get_vulkan_window().get_window_viewport().extent;
+        let image = |format, usage| -> Result<Arc<ImageView>, EngineError> {
+            let image = Image::new(
+                toolset.memory_allocator.general_allocator.clone(),
+                ImageCreateInfo {
+                    image_type: ImageType::Dim2d,
+                    format,
+                    extent: [extent[0] as u32, extent[1] as u32, 1],
+                    usage: ImageUsage::COLOR_ATTACHMENT | usage,
+                    ..Default::default()
+                },
+                AllocationCreateInfo::default(),
+            )?;
+            Ok(ImageView::new_default(image)?)
+        };
+
+        let color = image(Format::R8G8B8A8_SRGB, ImageUsage::SAMPLED)?;
+        let tile_ids = image(Format::R32_UINT, ImageUsage::STORAGE)?;
+        let framebuffer = create_framebuffer(subpass.render_pass(), vec![color.clone(), tile_ids.clone()])?;
+
+        Ok(SceneTarget { framebuffer, color, tile_ids })
+    }
+}
+
+struct Streaming {
+    streamer : Rc<RefCell<TextureStreamer>>,
+    subpass : Subpass,
+    quad : Subbuffer<[VulkanVertex]>,
+    fullscreen : Subbuffer<[VulkanVertex]>,
+    sampler : Arc<Sampler>,
+    scene_set : Arc<PersistentDescriptorSet>,
+    // Rebuilt with the targets after a resize
+    scene_pipeline : Arc<GraphicsPipeline>,
+    present_pipeline : Arc<GraphicsPipeline>,
+    target : Arc<SceneTarget>,
+}
+
+impl Streaming {
+    fn new(toolset : &VulkanToolset) -> Result<Streaming, EngineError> {
+        let device = &toolset.logical_device;
+        let fallback_colors = (0..TILE_COUNT).map(|tile| average_color(&tile_texels(tile))).collect::<Vec<_>>();
+        let streamer = TextureStreamer::new(toolset, [TILE_SIZE; 2], CACHE_SLOTS, &fallback_colors, |tile| Ok(tile_texels(tile)))?;
+
+        // Color for the window, tile ids for the feedback pass
+        let render_pass = RenderPassBuilder::new()
+        .attachment(AttachmentConfig {
+            initial_layout : ImageLayout::Undefined,
+            final_layout : ImageLayout::ShaderReadOnlyOptimal,
+            ..AttachmentConfig::color(Format::R8G8B8A8_SRGB)
+        })
+        .attachment(AttachmentConfig {
+            initial_layout : ImageLayout::Undefined,
+            final_layout : ImageLayout::General,
+            ..AttachmentConfig::color(Format::R32_UINT)
+        })
+        .subpass(&[0, 1], None)
+        .build(device)?;
+        let subpass = Subpass::from(render_pass, 0).unwrap();
+
+        let quad = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].map(|(x, y)| VulkanVertex::new(x, y));
+        let quad = upload_buffer(&toolset.memory_allocator, &toolset.device_queue, BufferUsage::VERTEX_BUFFER, quad)?;
+        let fullscreen = [(-1.0, -1.0), (3.0, -1.0), (-1.0, 3.0)].map(|(x, y)| VulkanVertex::new(x, y));
+        let fullscreen = upload_buffer(&toolset.memory_allocator, &toolset.device_queue, BufferUsage::VERTEX_BUFFER, fullscreen)?;
+
+        let sampler = Sampler::new(device.clone(), SamplerCreateInfo::simple_repeat_linear_no_mipmap())?;
+        let target = Arc::new(SceneTarget::new(toolset, &subpass)?);
+        let (scene_pipeline, present_pipeline) = Self::create_pipelines(toolset, &subpass)?;
+
+        let scene_set = PersistentDescriptorSet::new(
+            &toolset.memory_allocator.descriptor_set_allocator,
+            scene_pipeline.layout().set_layouts()[0].clone(),
+            streamer.write_descriptors(0),
+            [],
+        )?;
+
+        Ok(Streaming {
+            streamer : Rc::new(RefCell::new(streamer)),
+            subpass,
+            quad,
+            fullscreen,
+            sampler,
+            scene_set,
+            scene_pipeline,
+            present_pipeline,
+            target,
+        })
+    }
+
+    fn resize(&mut self, toolset : &VulkanToolset) -> Result<(), EngineError> {
+        self.target = Arc::new(SceneTarget::new(toolset, &self.subpass)?);
+        (self.scene_pipeline, self.present_pipeline) = Self::create_pipelines(toolset, &self.subpass)?;
+
+        Ok(())
+    }
+
+    fn create_pipelines(toolset : &VulkanToolset, subpass : &Subpass) -> Result<(Arc<GraphicsPipeline>, Arc<GraphicsPipeline>), EngineError> {
+        let device = &toolset.logical_device;
+        let viewport : Viewport = toolset.get_vulkan_window().get_window_viewport();
+
+        let scene = toolset.create_graphics_pipeline_for(&scene_vs::load(device.clone())?, Some(&scene_fs::load(device.clone())?), subpass.clone(), viewport);
+        let present = toolset.create_graphics_pipeline(&present_vs::load(device.clone())?, &present_fs::load(device.clone())?);
+
+        Ok((scene, present))
+    }
+}
+
+fn main() {
+    let mut streaming : Option<Streaming> = None;
+    let mut time = 0.0f32;
+
+    Engine::builder()
+    .window_title("Texture streaming")
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+        let streaming = streaming.get_or_insert_with(|| Streaming::new(toolset).expect("failed to set up texture streaming"));
+        if frame.resized() {
+            streaming.resize(toolset).expect("failed to resize the scene target");
+        }
+        time += frame.delta();
+
+        // Streaming, the scene and its feedback all happen before the window's render pass
+        let streamer = streaming.streamer.clone();
+        let target = streaming.target.clone();
+        let scene_pipeline = streaming.scene_pipeline.clone();
+        let scene_set = streaming.scene_set.clone();
+        let quad = streaming.quad.clone();
+        let objects = objects(time);
+
+        frame.compute(move |builder| {
+            let mut streamer = streamer.borrow_mut();
+            streamer.process_feedback(builder).expect("failed to process tile feedback");
+
+            builder.begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![Some([0.05, 0.05, 0.08, 1.0].into()), Some(ClearValue::Uint([0; 4]))],
+                    ..RenderPassBeginInfo::framebuffer(target.framebuffer.clone())
+                },
+                SubpassBeginInfo {
+                    contents: SubpassContents::Inline,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .bind_pipeline_graphics(scene_pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(PipelineBindPoint::Graphics, scene_pipeline.layout().clone(), 0, scene_set)
+            .unwrap()
+            .bind_vertex_buffers(0, quad)
+            .unwrap();
+
+            for (_, constants) in objects {
+                builder.push_constants(scene_pipeline.layout().clone(), 0, constants)
+                .unwrap()
+                .draw(6, 1, 0, 0)
+                .unwrap();
+            }
+            builder.end_render_pass(SubpassEndInfo::default()).unwrap();
+
+            streamer.record_feedback(builder, &target.tile_ids).expect("failed to record tile feedback");
+        });
+
+        let present_set = PersistentDescriptorSet::new(
+            &toolset.memory_allocator.descriptor_set_allocator,
+            streaming.present_pipeline.layout().set_layouts()[0].clone(),
+            [WriteDescriptorSet::image_view_sampler(0, streaming.target.color.clone(), streaming.sampler.clone())],
+            [],
+        ).expect("failed to create descriptor set");
+        let present_pipeline = streaming.present_pipeline.clone();
+        let fullscreen = streaming.fullscreen.clone();
+
+        frame.record(move |builder| {
+            builder.bind_pipeline_graphics(present_pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(PipelineBindPoint::Graphics, present_pipeline.layout().clone(), 0, present_set)
+            .unwrap()
+            .bind_vertex_buffers(0, fullscreen)
+            .unwrap()
+            .draw(3, 1, 0, 0)
+            .unwrap();
+        });
+    })
+    .run();
+}
@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use engine::{vulkan::vertex::Triangle, Engine};
+use vulkano::pipeline::GraphicsPipeline;
+
+fn main() {
+    let mut triangle : Option<Triangle> = None;
+    let mut pipeline : Option<Arc<GraphicsPipeline>> = None;
+
+    Engine::builder()
+    .window_title("Triangle")
+    .with_render(move |frame| {
+        let toolset = frame.toolset();
+        let triangle = triangle.get_or_insert_with(|| {
+            Triangle::new(toolset.memory_allocator.general_allocator.clone(), &toolset.logical_device)
+            .expect("failed to create triangle")
+        });
+
+        // Viewport is baked into the pipeline, so rebuild it after a resize
+        if pipeline.is_none() || frame.resized() {
+            pipeline = Some(toolset.create_graphics_pipeline(&triangle.vertex_shader, &triangle.fragment_shader));
+        }
+
+        frame.draw(pipeline.clone().unwrap(), triangle.vertex_buffer.clone());
+    })
+    .run();
+}
@@ -0,0 +1,116 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use gltf::{image::Format as GltfFormat, mesh::Mode};
+
+use crate::{error::EngineError, vulkan::{mesh::{Mesh, MeshIndices, Vertex3D}, texture::Texture2D, vulkan::VulkanToolset}};
+
+// Base color of a glTF metallic-roughness material. Other PBR inputs aren't loaded yet
+pub struct Material {
+    // Linear RGBA, pushed as is as a vec4 push constant and multiplied with the texture
+    pub base_color_factor : [f32; 4],
+    // Shared between materials that use the same image
+    pub base_color_texture : Option<Arc<Texture2D>>,
+}
+
+pub struct GltfScene {
+    // One per primitive, in the order of the meshes in the file
+    pub meshes : Vec<Mesh>,
+    // Index into materials for every mesh, None for primitives without a material
+    pub mesh_materials : Vec<Option<usize>>,
+    pub materials : Vec<Material>,
+}
+
+pub struct GltfLoader;
+
+impl GltfLoader {
+    // Reads .gltf files with their external buffers and images as well as self contained .glb files.
+    // Node transforms aren't applied, every mesh stays in its own coordinates
+    pub fn load(path : &Path, toolset : &VulkanToolset) -> Result<GltfScene, EngineError> {
+        let (document, buffers, images) = gltf::import(path)?;
+
+        let mut textures = HashMap::new();
+        let materials = document.materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            let base_color_texture = match pbr.base_color_texture() {
+                Some(info) => {
+                    let index = info.texture().source().index();
+                    let texture = match textures.get(&index) {
+                        Some(texture) => Arc::clone(texture),
+                        None => {
+                            let texture = Arc::new(Self::load_texture(toolset, &images[index])?);
+                            textures.insert(index, texture.clone());
+                            texture
+                        },
+                    };
+                    Some(texture)
+                },
+                None => None,
+            };
+
+            Ok(Material {
+                base_color_factor : pbr.base_color_factor(),
+                base_color_texture,
+            })
+        }).collect::<Result<Vec<_>, EngineError>>()?;
+
+        let mut meshes = Vec::new();
+        let mut mesh_materials = Vec::new();
+        for primitive in document.meshes().flat_map(|mesh| mesh.primitives()) {
+            if primitive.mode() != Mode::Triangles {
+                return Err(EngineError::UnsupportedFeature(format!("{:?} glTF primitives, only triangle lists are loaded", primitive.mode())));
+            }
+
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let positions = reader.read_positions()
+            .ok_or_else(|| EngineError::UnsupportedFeature("glTF primitives without positions".to_owned()))?;
+            let mut normals = reader.read_normals();
+            let mut uvs = reader.read_tex_coords(0).map(|uvs| uvs.into_f32());
+
+            let vertices = positions
+            .map(|position| Vertex3D {
+                position,
+                normal : normals.as_mut().and_then(Iterator::next).unwrap_or([0.0; 3]),
+                uv : uvs.as_mut().and_then(Iterator::next).unwrap_or([0.0; 2]),
+            }).collect::<Vec<_>>();
+
+            // Non-indexed primitives draw their vertices in order
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..vertices.len() as u32).collect(),
+            };
+            let indices = MeshIndices::fit(indices, vertices.len());
+
+            meshes.push(Mesh::new(&toolset.memory_allocator, &toolset.device_queue, vertices, indices)?);
+            mesh_materials.push(primitive.material().index());
+        }
+
+        Ok(GltfScene {
+            meshes,
+            mesh_materials,
+            materials,
+        })
+    }
+
+    // glTF images come decoded with as many channels as the file had, Texture2D takes RGBA8
+    fn load_texture(toolset : &VulkanToolset, image : &gltf::image::Data) -> Result<Texture2D, EngineError> {
+        let channels = match image.format {
+            GltfFormat::R8 => 1,
+            GltfFormat::R8G8 => 2,
+            GltfFormat::R8G8B8 => 3,
+            GltfFormat::R8G8B8A8 => 4,
+            format => return Err(EngineError::UnsupportedFeature(format!("{format:?} glTF images, only 8 bit images are loaded"))),
+        };
+
+        let rgba = image.pixels.chunks_exact(channels)
+        .flat_map(|texel| match *texel {
+            [r] => [r, r, r, 255],
+            [r, a] => [r, r, r, a],
+            [r, g, b] => [r, g, b, 255],
+            [r, g, b, a] => [r, g, b, a],
+            _ => unreachable!(),
+        }).collect::<Vec<_>>();
+
+        Ok(Texture2D::from_rgba_bytes(toolset, image.width, image.height, &rgba))
+    }
+}
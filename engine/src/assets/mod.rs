@@ -0,0 +1,2 @@
+pub mod gltf_loader;
+pub mod obj_loader;
@@ -0,0 +1,62 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use vulkano::device::Queue;
+
+use crate::{error::EngineError, vulkan::{mesh::{Mesh, MeshIndices, Vertex3D}, vulkan::VulkanAllocation}};
+
+pub struct ObjLoader;
+
+impl ObjLoader {
+    // Every model in the file becomes its own mesh, materials are ignored.
+    // Meshes with few enough vertices get 16 bit indices
+    pub fn load(path : &Path, allocator : &VulkanAllocation, queue : &Arc<Queue>) -> Result<Vec<Mesh>, EngineError> {
+        let options = tobj::LoadOptions {
+            triangulate: true,
+            ..Default::default()
+        };
+        let (models, _) = tobj::load_obj(path, &options)?;
+
+        models.iter()
+        .map(|model| {
+            let (vertices, indices) = Self::build_indexed(&model.mesh);
+            let indices = MeshIndices::fit(indices, vertices.len());
+
+            Mesh::new(allocator, queue, vertices, indices)
+        }).collect()
+    }
+
+    // OBJ indexes positions, normals and UVs separately, so each unique triple becomes one vertex
+    fn build_indexed(mesh : &tobj::Mesh) -> (Vec<Vertex3D>, Vec<u32>) {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::with_capacity(mesh.indices.len());
+        let mut unique = HashMap::new();
+
+        for (i, &position_index) in mesh.indices.iter().enumerate() {
+            let normal_index = mesh.normal_indices.get(i).copied();
+            let texcoord_index = mesh.texcoord_indices.get(i).copied();
+
+            let index = *unique.entry((position_index, normal_index, texcoord_index))
+            .or_insert_with(|| {
+                let p = position_index as usize * 3;
+                let normal = normal_index
+                .map(|n| n as usize * 3)
+                .map_or([0.0; 3], |n| [mesh.normals[n], mesh.normals[n + 1], mesh.normals[n + 2]]);
+                let uv = texcoord_index
+                .map(|t| t as usize * 2)
+                .map_or([0.0; 2], |t| [mesh.texcoords[t], mesh.texcoords[t + 1]]);
+
+                vertices.push(Vertex3D {
+                    position : [mesh.positions[p], mesh.positions[p + 1], mesh.positions[p + 2]],
+                    normal,
+                    uv,
+                });
+
+                vertices.len() as u32 - 1
+            });
+
+            indices.push(index);
+        }
+
+        (vertices, indices)
+    }
+}
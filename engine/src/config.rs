@@ -0,0 +1,238 @@
+use std::time::Duration;
+
+use vulkano::Version;
+use winit::event::VirtualKeyCode;
+
+#[derive(Clone, Debug)]
+pub struct WindowConfig {
+    pub title : String,
+    pub width : u32,
+    pub height : u32,
+    pub resizable : bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> WindowConfig {
+        WindowConfig {
+            title : String::from("RustEngine"),
+            width : 800,
+            height : 600,
+            resizable : true,
+        }
+    }
+}
+
+// What the instance tells the driver about the application, shown by tools like RenderDoc
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstanceConfig {
+    // None uses the window title
+    pub application_name : Option<String>,
+    pub application_version : Version,
+    pub engine_name : String,
+    pub engine_version : Version,
+    // Highest Vulkan version the engine may use, None takes whatever the library supports.
+    // Requesting more than the library supports fails instance creation
+    pub max_api_version : Option<Version>,
+}
+
+impl Default for InstanceConfig {
+    fn default() -> InstanceConfig {
+        InstanceConfig {
+            application_name : None,
+            application_version : Version::major_minor(0, 0),
+            engine_name : String::from("RustEngine"),
+            engine_version : Version {
+                major : env!("CARGO_PKG_VERSION_MAJOR").parse().unwrap(),
+                minor : env!("CARGO_PKG_VERSION_MINOR").parse().unwrap(),
+                patch : env!("CARGO_PKG_VERSION_PATCH").parse().unwrap(),
+            },
+            max_api_version : None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum DeviceSelection {
+    // Discrete GPU first, then integrated, virtual and CPU implementations
+    #[default]
+    HighPerformance,
+    // Integrated GPU first, useful on laptops
+    LowPower,
+    // First device whose name contains the given string
+    Named(String),
+    // Index into the physical devices able to present to the window
+    Index(usize),
+}
+
+// Work a device queue is created for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum QueueRole {
+    Graphics,
+    // Async compute next to rendering
+    Compute,
+    // Background uploads, usually at a low priority so they don't starve rendering
+    Transfer,
+}
+
+// One queue of the graphics family, its priority ranges from 0.0 to 1.0
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueueRequest {
+    pub role : QueueRole,
+    pub priority : f32,
+}
+
+impl QueueRequest {
+    pub fn new(role : QueueRole, priority : f32) -> QueueRequest {
+        QueueRequest { role, priority }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PresentPreference {
+    // Always supported, waits for vertical blank
+    #[default]
+    Fifo,
+    // Low latency without tearing, falls back to Fifo
+    Mailbox,
+    // No vertical sync, falls back to Mailbox and then Fifo
+    Immediate,
+}
+
+// Command buffers a pool allocates at once, see StandardCommandBufferAllocatorCreateInfo.
+// Too few and pools get created or reset more often, too many waste memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommandBufferOptions {
+    pub primary_buffer_count : usize,
+    pub secondary_buffer_count : usize,
+}
+
+impl Default for CommandBufferOptions {
+    fn default() -> CommandBufferOptions {
+        CommandBufferOptions {
+            primary_buffer_count : 32,
+            secondary_buffer_count : 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RunMode {
+    // Renders continuously, for games
+    #[default]
+    Poll,
+    // Sleeps until an event arrives, for tools that should idle
+    Wait,
+    // Sleeps until an event arrives or the interval passed since the last frame
+    WaitUntil(Duration),
+}
+
+// How a frame limited loop waits out the rest of a frame, see FramePacer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PacingWait {
+    // Sleeps, then spins through the last millisecond. Accurate but keeps a core busy briefly every frame
+    #[default]
+    SleepThenSpin,
+    // Returns to the event loop with ControlFlow::WaitUntil, no busy waiting but only as accurate as the OS timer
+    EventLoop,
+}
+
+// Upper bound on the frame rate, for present modes that don't wait for vertical blank
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameLimit {
+    pub target_frame_time : Duration,
+    pub wait : PacingWait,
+}
+
+impl FrameLimit {
+    pub fn fps(fps : f32) -> FrameLimit {
+        assert!(fps > 0.0, "the frame rate limit must be positive");
+
+        FrameLimit {
+            target_frame_time : Duration::from_secs_f32(1.0 / fps),
+            wait : PacingWait::default(),
+        }
+    }
+}
+
+// When the loop compacts relocatable buffers, see VulkanAllocation::defragment
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DefragPolicy {
+    // Time between fragmentation checks, the first one happens this long after startup
+    pub interval : Duration,
+    // Fragmentation from 0.0 to 1.0 that triggers a pass
+    pub threshold : f32,
+}
+
+impl Default for DefragPolicy {
+    fn default() -> DefragPolicy {
+        DefragPolicy {
+            interval : Duration::from_secs(60),
+            threshold : 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AppConfig {
+    pub window : WindowConfig,
+    pub instance : InstanceConfig,
+    pub device : DeviceSelection,
+    // Queues created along with the device, see VulkanToolset::queue
+    pub queues : Vec<QueueRequest>,
+    pub present : PresentPreference,
+    // Present in HDR when the display supports it, see VulkanWindow::output_mode. Falls back to SDR otherwise
+    pub hdr : bool,
+    pub clear_color : [f32; 4],
+    pub msaa_samples : u32,
+    pub validation : bool,
+    pub frames_in_flight : u32,
+    // For the shared allocator in VulkanAllocation. Every frame in flight records from a pool of its own
+    // that only needs one primary buffer, see FrameSync::with_command_allocators
+    pub command_buffers : CommandBufferOptions,
+    pub run_mode : RunMode,
+    // Only applies to RunMode::Poll, the other modes sleep between events anyway. FIFO presentation
+    // that is already slower than the limit makes it a no-op
+    pub frame_limit : Option<FrameLimit>,
+    // Seconds per fixed update, see EngineBuilder::with_fixed_update
+    pub fixed_timestep : f32,
+    // Keep rendering while another window has focus. Occluded or minimized windows never render
+    pub render_when_unfocused : bool,
+    // How long resize events have to stop before the swapchain follows, see ResizeDebouncer
+    pub resize_debounce : Duration,
+    // Longest wait for a swapchain image, frames that time out are skipped
+    pub acquire_timeout : Duration,
+    // Frame rate, pipeline statistics and tracked VRAM in the top left corner, see RenderStatsOverlay
+    pub stats_overlay : bool,
+    // None never defragments, a pass waits for every frame in flight first
+    pub defragment : Option<DefragPolicy>,
+    // Pressing it exits like UpdateContext::exit, None leaves the key to the game
+    pub exit_key : Option<VirtualKeyCode>,
+}
+
+impl Default for AppConfig {
+    fn default() -> AppConfig {
+        AppConfig {
+            window : WindowConfig::default(),
+            instance : InstanceConfig::default(),
+            device : DeviceSelection::default(),
+            queues : vec![QueueRequest::new(QueueRole::Graphics, 1.0)],
+            present : PresentPreference::default(),
+            hdr : false,
+            clear_color : [0.1, 0.1, 0.1, 1.0],
+            msaa_samples : 1,
+            validation : cfg!(debug_assertions),
+            frames_in_flight : 2,
+            command_buffers : CommandBufferOptions::default(),
+            run_mode : RunMode::default(),
+            frame_limit : None,
+            fixed_timestep : 1.0 / 60.0,
+            render_when_unfocused : false,
+            resize_debounce : Duration::from_millis(100),
+            acquire_timeout : Duration::from_secs(1),
+            stats_overlay : false,
+            defragment : Some(DefragPolicy::default()),
+            exit_key : Some(VirtualKeyCode::Escape),
+        }
+    }
+}
@@ -0,0 +1,832 @@
+use std::{any::Any, collections::HashMap, mem::{self, size_of}, path::Path, process, sync::Arc, time::{Duration, Instant}};
+
+use log::{error, info, warn};
+use vulkano::{buffer::Subbuffer, command_buffer::{AutoCommandBufferBuilder, DrawIndirectCommand, PrimaryAutoCommandBuffer}, descriptor_set::PersistentDescriptorSet, image::ImageUsage, pipeline::{graphics::{vertex_input::Vertex, viewport::Scissor}, GraphicsPipeline}, query::QueryPipelineStatisticFlags, render_pass::Subpass, swapchain::{PresentMode, Swapchain}};
+use winit::{event::{Event, VirtualKeyCode, WindowEvent}, event_loop::{ControlFlow, EventLoop}, platform::run_return::EventLoopExtRunReturn, window::{CursorGrabMode, Window}};
+
+use crate::{config::{AppConfig, CommandBufferOptions, PacingWait, RunMode}, error::EngineError, frame_pacer::{FramePacer, Pacing}, input::InputState, render::{overlay::{Overlay, OverlayRenderer}, post_process::PostProcessChain, shadow_map::ShadowMapPass, split_screen::ViewportRegion, stats_overlay::RenderStatsOverlay}, resize_debounce::ResizeDebouncer, timestep::FixedTimestep, vulkan::{frame_sync::FrameSync, occlusion::OcclusionQuerySet, pipeline_stats::{PipelineStats, PipelineStatsPool}, recording::FrameRecorder, swapchain::{AcquireFailure, PresentResult, SwapchainManager}, vulkan::{ComputePass, DrawCall, FrameTarget, IndirectDraws, RenderCommand, VulkanToolset}}};
+
+type SetupCallback = Box<dyn FnOnce(&VulkanToolset)>;
+type ExitCallback = Box<dyn FnOnce(&VulkanToolset)>;
+type UpdateCallback = Box<dyn FnMut(&mut UpdateContext, f32)>;
+type RenderCallback = Box<dyn FnMut(&mut Frame)>;
+type PostProcessCallback = Box<dyn FnOnce(&VulkanToolset) -> PostProcessChain>;
+
+pub struct UpdateContext<'a> {
+    toolset : &'a VulkanToolset,
+    elapsed : Duration,
+    input : &'a mut InputState,
+    exit_requested : bool,
+}
+
+impl<'a> UpdateContext<'a> {
+    pub fn toolset(&self) -> &'a VulkanToolset {
+        self.toolset
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn is_key_pressed(&self, key : VirtualKeyCode) -> bool {
+        self.input.is_key_pressed(key)
+    }
+
+    pub fn input(&self) -> &InputState {
+        self.input
+    }
+
+    // Mutable to grab or release the cursor
+    pub fn input_mut(&mut self) -> &mut InputState {
+        self.input
+    }
+
+    // The current frame is still rendered and presented, the loop exits once the GPU finished it
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+}
+
+// What the GPU reported about earlier frames, each value from the most recent frame it was available for
+#[derive(Clone, Debug, Default)]
+pub struct FrameStats {
+    // None without pipeline_statistics_query support
+    pub pipeline : Option<PipelineStats>,
+    // Samples that passed the depth test per id of Frame::draw_queried. Without
+    // DeviceCapabilities::precise_occlusion_queries any positive value only means visible
+    pub occlusion_samples : HashMap<u32, u64>,
+    // Time between the starts of the last two frames, waiting for the frame limit included
+    pub frame_time : Duration,
+    // AppConfig::frame_limit while it paces the loop, None without a limit or when vsync is already slower
+    pub target_frame_time : Option<Duration>,
+    // Times the swapchain was recreated since the loop started, resizes are coalesced by ResizeDebouncer
+    pub swapchain_recreations : u64,
+}
+
+pub struct Frame<'a> {
+    toolset : &'a VulkanToolset,
+    delta : f32,
+    alpha : f32,
+    clear_color : [f32; 4],
+    compute_passes : Vec<ComputePass>,
+    commands : Vec<RenderCommand>,
+    resized : bool,
+    frame_index : usize,
+    stats : FrameStats,
+    exit_requested : bool,
+    viewport_regions : Vec<ViewportRegion>,
+    post_process : Option<&'a mut PostProcessChain>,
+    recorder : &'a mut FrameRecorder,
+    overlay : &'a mut Overlay,
+    // Handed to FrameSync::retire once the render callback returned
+    retired : Vec<Box<dyn Any>>,
+}
+
+impl<'a> Frame<'a> {
+    pub fn toolset(&self) -> &'a VulkanToolset {
+        self.toolset
+    }
+
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    // Fraction of a fixed step left over after this frame's fixed updates, in 0..1.
+    // Interpolate between the previous and current fixed state by it for smooth motion
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    // True when the swapchain was recreated since the previous frame
+    pub fn resized(&self) -> bool {
+        self.resized
+    }
+
+    // Frame in flight slot from FrameSync, resources indexed by it are no longer used by the GPU
+    pub fn frame_index(&self) -> usize {
+        self.frame_index
+    }
+
+    // Statistics of the most recently completed frame, None without pipeline_statistics_query support
+    pub fn pipeline_stats(&self) -> Option<&PipelineStats> {
+        self.stats.pipeline.as_ref()
+    }
+
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    // Draws and recorded closures queued so far this frame, a closure may draw several times
+    pub fn command_count(&self) -> usize {
+        self.commands.len()
+    }
+
+    // Same as UpdateContext::exit
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    // Chain from EngineBuilder::with_post_process, changes to its effects apply to this frame.
+    // Draws go into its scene target, build their pipelines for its scene_subpass
+    pub fn post_process(&mut self) -> Option<&mut PostProcessChain> {
+        self.post_process.as_deref_mut()
+    }
+
+    // Keeps a resource that was replaced this frame alive until the GPU finished every frame that may still
+    // use it. Dropping it right away would only be safe once nothing in flight references it
+    pub fn retire<T : 'static>(&mut self, resource : T) {
+        self.retired.push(Box::new(resource));
+    }
+
+    // Swaps in a new mesh, texture or buffer and retires the old one, draws from this frame on use `replacement`
+    pub fn replace<T : 'static>(&mut self, current : &mut T, replacement : T) {
+        let old = mem::replace(current, replacement);
+        self.retire(old);
+    }
+
+    // Screen space text, rectangles and lines in logical pixels, drawn after every other draw of the frame
+    // in the same render pass. Cleared before the next frame
+    pub fn overlay(&mut self) -> &mut Overlay {
+        self.overlay
+    }
+
+    // Writes this frame and every `every_nth_frame`th after it to numbered PNGs in `dir`, on a background
+    // thread. Frames are dropped with a warning when the disk can't keep up. A resized window keeps
+    // recording, later files have the new size
+    pub fn start_recording(&mut self, dir : &Path, every_nth_frame : u32) -> Result<(), EngineError> {
+        let usage = self.toolset.get_vulkan_window().get_swapchain().image_usage();
+        if !usage.intersects(ImageUsage::TRANSFER_SRC) {
+            return Err(EngineError::UnsupportedFeature("the surface doesn't allow copying presented images".to_owned()));
+        }
+
+        self.recorder.start(dir, every_nth_frame)
+    }
+
+    // Frames still in flight are written as they finish
+    pub fn stop_recording(&mut self) {
+        self.recorder.stop();
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_recording()
+    }
+
+    pub fn clear(&mut self, color : [f32; 4]) {
+        self.clear_color = color;
+    }
+
+    // Recorded before the render pass begins, in submission order. Buffers a pass writes can be drawn
+    // from in the same frame, the barrier between the compute write and the vertex read is inserted for it
+    pub fn compute<F : FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) + 'static>(&mut self, pass : F) {
+        self.compute_passes.push(Box::new(pass));
+    }
+
+    // Renders `shadow_map` before the render pass begins, in order with the compute passes. `draw` records
+    // the casters with pipelines from ShadowMapPass::create_caster_pipeline, the draws of this frame can then
+    // sample the map
+    pub fn render_shadow_map<F : FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) + 'static>(&mut self, shadow_map : &ShadowMapPass, draw : F) {
+        let shadow_map = shadow_map.clone();
+        self.compute_passes.push(Box::new(move |builder| shadow_map.record(builder, draw)));
+    }
+
+    // `V` has to match the pipeline's vertex input
+    pub fn draw<V : Vertex>(&mut self, pipeline : Arc<GraphicsPipeline>, vertex_buffer : Subbuffer<[V]>) {
+        self.commands.push(RenderCommand::Draw(DrawCall {
+            vertex_count : vertex_buffer.len() as u32,
+            pipeline,
+            vertex_buffer : Some(vertex_buffer.into_bytes()),
+            descriptor_sets : Vec::new(),
+            scissor : None,
+            indirect : None,
+            occlusion_query : None,
+        }));
+    }
+
+    // Like draw, the samples passing the depth test show up under `id` in FrameStats::occlusion_samples
+    // a few frames later. Draws sharing an id add up
+    pub fn draw_queried<V : Vertex>(&mut self, pipeline : Arc<GraphicsPipeline>, vertex_buffer : Subbuffer<[V]>, id : u32) {
+        self.commands.push(RenderCommand::Draw(DrawCall {
+            vertex_count : vertex_buffer.len() as u32,
+            pipeline,
+            vertex_buffer : Some(vertex_buffer.into_bytes()),
+            descriptor_sets : Vec::new(),
+            scissor : None,
+            indirect : None,
+            occlusion_query : Some(id),
+        }));
+    }
+
+    // Only the part of the draw inside `scissor` is kept, clamped to the framebuffer or viewport region.
+    // The pipeline needs a dynamic scissor, see ScissorState::Dynamic
+    pub fn draw_clipped<V : Vertex>(&mut self, pipeline : Arc<GraphicsPipeline>, vertex_buffer : Subbuffer<[V]>, scissor : Scissor) {
+        self.commands.push(RenderCommand::Draw(DrawCall {
+            vertex_count : vertex_buffer.len() as u32,
+            pipeline,
+            vertex_buffer : Some(vertex_buffer.into_bytes()),
+            descriptor_sets : Vec::new(),
+            scissor : Some(scissor),
+            indirect : None,
+            occlusion_query : None,
+        }));
+    }
+
+    // Draw without vertex buffers, the vertex shader fetches its data through gl_VertexIndex
+    pub fn draw_pulled(&mut self, pipeline : Arc<GraphicsPipeline>, descriptor_set : Arc<PersistentDescriptorSet>, vertex_count : u32) {
+        self.commands.push(RenderCommand::Draw(DrawCall {
+            pipeline,
+            vertex_buffer : None,
+            descriptor_sets : vec![descriptor_set],
+            vertex_count,
+            scissor : None,
+            indirect : None,
+            occlusion_query : None,
+        }));
+    }
+
+    // Draw parameters come from `commands`, see VulkanAllocation::create_indirect_buffer. A compute pass
+    // can write them earlier in the frame. Without multi_draw_indirect each command is drawn on its own
+    pub fn draw_indirect<V : Vertex>(&mut self, pipeline : Arc<GraphicsPipeline>, vertex_buffer : Subbuffer<[V]>, commands : Subbuffer<[DrawIndirectCommand]>, draw_count : u32) -> Result<(), EngineError> {
+        if self.toolset.capabilities.multi_draw_indirect {
+            let stride = size_of::<DrawIndirectCommand>() as u32;
+            self.toolset.limits.check_indirect_draws(draw_count.min(commands.len() as u32), stride, stride)?;
+        }
+
+        self.commands.push(RenderCommand::Draw(DrawCall {
+            pipeline,
+            vertex_buffer : Some(vertex_buffer.into_bytes()),
+            descriptor_sets : Vec::new(),
+            vertex_count : 0,
+            scissor : None,
+            indirect : Some(IndirectDraws { commands, draw_count }),
+            occlusion_query : None,
+        }));
+
+        Ok(())
+    }
+
+    // Replays the draws once per region, such as the halves of a split screen. Their pipelines need
+    // a dynamic viewport, see VulkanToolset::create_dynamic_viewport_pipeline. Closures passed to record
+    // still run once, see VulkanToolset::create_frame_command_buffer
+    pub fn set_viewport_regions(&mut self, regions : Vec<ViewportRegion>) {
+        self.viewport_regions = regions;
+    }
+
+    // Recorded inside the render pass, in order with the other draws
+    pub fn record<F : FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) + 'static>(&mut self, record : F) {
+        self.commands.push(RenderCommand::Record(Box::new(record)));
+    }
+}
+
+// What the window manager reported about the window, nothing is drawn while it can't be seen
+#[derive(Clone, Copy, Debug)]
+struct WindowActivity {
+    focused : bool,
+    occluded : bool,
+    minimized : bool,
+}
+
+impl WindowActivity {
+    fn should_render(&self, render_when_unfocused : bool) -> bool {
+        !self.occluded && !self.minimized && (self.focused || render_when_unfocused)
+    }
+}
+
+pub struct Engine;
+
+impl Engine {
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
+    }
+}
+
+#[derive(Default)]
+pub struct EngineBuilder {
+    config : AppConfig,
+    setup : Option<SetupCallback>,
+    fixed_update : Option<UpdateCallback>,
+    update : Option<UpdateCallback>,
+    render : Option<RenderCallback>,
+    exit : Option<ExitCallback>,
+    post_process : Option<PostProcessCallback>,
+}
+
+impl EngineBuilder {
+    pub fn config(mut self, config : AppConfig) -> EngineBuilder {
+        self.config = config;
+        self
+    }
+
+    pub fn window_title(mut self, title : &str) -> EngineBuilder {
+        self.config.window.title = title.to_owned();
+        self
+    }
+
+    // Runs once after the toolset is created, before the first frame
+    pub fn with_setup<F : FnOnce(&VulkanToolset) + 'static>(mut self, setup : F) -> EngineBuilder {
+        self.setup = Some(Box::new(setup));
+        self
+    }
+
+    // Callbacks are moved into the event loop, so they must own their state:
+    // move a struct (or an Rc<RefCell<_>> shared with the render callback) into the closure
+    pub fn with_update<F : FnMut(&mut UpdateContext, f32) + 'static>(mut self, update : F) -> EngineBuilder {
+        self.update = Some(Box::new(update));
+        self
+    }
+
+    // Called zero or more times per frame with AppConfig::fixed_timestep as the delta,
+    // before the per-frame update. Frame::alpha tells render how far into the next step it is
+    pub fn with_fixed_update<F : FnMut(&mut UpdateContext, f32) + 'static>(mut self, update : F) -> EngineBuilder {
+        self.fixed_update = Some(Box::new(update));
+        self
+    }
+
+    pub fn with_render<F : FnMut(&mut Frame) + 'static>(mut self, render : F) -> EngineBuilder {
+        self.render = Some(Box::new(render));
+        self
+    }
+
+    // Renders every frame through the chain `create` returns, it runs once after setup. The engine
+    // resizes the chain with the swapchain and passes it to render, see Frame::post_process
+    pub fn with_post_process<F : FnOnce(&VulkanToolset) -> PostProcessChain + 'static>(mut self, create : F) -> EngineBuilder {
+        self.post_process = Some(Box::new(create));
+        self
+    }
+
+    // Runs once when the loop exits, after every frame in flight finished on the GPU
+    pub fn with_exit<F : FnOnce(&VulkanToolset) + 'static>(mut self, exit : F) -> EngineBuilder {
+        self.exit = Some(Box::new(exit));
+        self
+    }
+
+    // Exits the process with an error code when rendering can't go on, see try_run
+    pub fn run(self) {
+        if let Err(e) = self.try_run() {
+            error!("rendering stopped: {e}");
+            process::exit(1);
+        }
+    }
+
+    // Returns once the loop exited, with the error that ended it when rendering couldn't go on, such as
+    // EngineError::DeviceLost. The exit callback runs either way
+    pub fn try_run(self) -> Result<(), EngineError> {
+        let event_loop = EventLoop::new();
+        let toolset = VulkanToolset::try_with_config(&event_loop, self.config)?;
+
+        if let Some(setup) = self.setup {
+            setup(&toolset);
+        }
+        let post_process = self.post_process.map(|create| create(&toolset));
+
+        let fixed_update = self.fixed_update.unwrap_or_else(|| Box::new(|_, _| {}));
+        let update = self.update.unwrap_or_else(|| Box::new(|_, _| {}));
+        let render = self.render.unwrap_or_else(|| Box::new(|_| {}));
+
+        let callbacks = Callbacks {
+            fixed_update,
+            update,
+            render,
+            exit : self.exit,
+        };
+        run_event_loop(toolset, event_loop, callbacks, post_process)
+    }
+}
+
+struct Callbacks {
+    fixed_update : UpdateCallback,
+    update : UpdateCallback,
+    render : RenderCallback,
+    exit : Option<ExitCallback>,
+}
+
+fn run_event_loop(toolset : VulkanToolset, mut event_loop : EventLoop<()>, mut callbacks : Callbacks, mut post_process : Option<PostProcessChain>) -> Result<(), EngineError> {
+    let window = toolset.get_vulkan_window().clone();
+    let mut swapchains = SwapchainManager::new(&toolset, window.get_swapchain(), window.get_swapchain_images().to_vec());
+
+    let device = toolset.logical_device.clone();
+
+    let mut resize = ResizeDebouncer::new(toolset.config.resize_debounce);
+    let mut swapchain_recreated = true;
+
+    // A frame records its command buffer and, while recording, the copy of the presented image
+    let mut frame_sync = FrameSync::with_command_allocators(
+        toolset.config.frames_in_flight as usize,
+        &device,
+        toolset.device_queue.queue_family_index(),
+        CommandBufferOptions { primary_buffer_count : 2, secondary_buffer_count : 0 },
+    );
+    // Otherwise every slot keeps waiting on its fence
+    if toolset.capabilities.timeline_semaphores {
+        if let Err(e) = frame_sync.enable_timeline(&toolset.device_queue) {
+            warn!("frames are waited for through fences: {e}");
+        }
+    }
+
+    // Each frame in flight gets its own query, read back once the slot's fence signaled
+    let stats_pool = device.enabled_features().pipeline_statistics_query.then(|| PipelineStatsPool::new(
+        device.clone(),
+        QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES
+            | QueryPipelineStatisticFlags::INPUT_ASSEMBLY_PRIMITIVES
+            | QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS
+            | QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS
+            | QueryPipelineStatisticFlags::CLIPPING_INVOCATIONS,
+    ));
+    let occlusion_queries = OcclusionQuerySet::new(device.clone(), toolset.config.frames_in_flight)?;
+    let mut last_stats = FrameStats::default();
+    let mut recorder = FrameRecorder::new(toolset.config.frames_in_flight as usize);
+    let mut overlay = Overlay::new();
+    let mut overlay_renderer = OverlayRenderer::new(&toolset)?;
+    let mut stats_overlay = toolset.config.stats_overlay.then(RenderStatsOverlay::new);
+
+    let start = Instant::now();
+    let mut last_frame = start;
+    let mut input = InputState::new();
+    let mut cursor_grabbed = false;
+    let mut timestep = FixedTimestep::new(toolset.config.fixed_timestep);
+    let run_mode = toolset.config.run_mode;
+    let render_when_unfocused = toolset.config.render_when_unfocused;
+    let mut activity = WindowActivity { focused : true, occluded : false, minimized : false };
+    let mut paused = false;
+    let exit_key = toolset.config.exit_key;
+    let mut exit_requested = false;
+
+    let mut pacer = toolset.config.frame_limit
+    .filter(|_| run_mode == RunMode::Poll)
+    .map(|limit| (FramePacer::new(limit.target_frame_time), limit.wait));
+    let mut vsync_paces = pacer.as_ref().is_some_and(|(pacer, _)| vsync_is_slower(&window.get_native_window(), swapchains.swapchain(), pacer.target()));
+    if vsync_paces {
+        info!("vsync is slower than the frame limit, not pacing frames");
+    }
+    // Set by PacingWait::EventLoop, events that wake the loop earlier don't start a frame
+    let mut next_frame_at : Option<Instant> = None;
+
+    let acquire_timeout = toolset.config.acquire_timeout;
+    let defrag_policy = toolset.config.defragment;
+    let mut next_defrag_check = defrag_policy.map(|policy| start + policy.interval);
+    // What ended the loop early, returned once it exited
+    let mut failure : Option<EngineError> = None;
+    let failure_slot = &mut failure;
+
+    event_loop.run_return(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => {
+                exit_requested = true;
+            },
+            Event::WindowEvent {
+                event : WindowEvent::Resized(size),
+                ..
+            } => {
+                activity.minimized = size.width == 0 || size.height == 0;
+                resize.request(size.into(), Instant::now());
+            },
+            Event::WindowEvent {
+                event : WindowEvent::ScaleFactorChanged { new_inner_size, .. },
+                ..
+            } => {
+                resize.request((*new_inner_size).into(), Instant::now());
+            },
+            Event::WindowEvent { event, .. } => {
+                match event {
+                    WindowEvent::Focused(focused) => activity.focused = focused,
+                    WindowEvent::Occluded(occluded) => activity.occluded = occluded,
+                    _ => (),
+                }
+                input.handle_window_event(&event);
+            },
+            Event::DeviceEvent { event, .. } => input.handle_device_event(&event),
+            Event::MainEventsCleared => {
+                // Close requests and frames that were skipped after an exit request end up here
+                if exit_requested {
+                    if let Err(e) = exit(&toolset, &mut frame_sync, &mut recorder, &mut callbacks.exit) {
+                        *failure_slot = Some(e);
+                    }
+                    control_flow.set_exit();
+                    return;
+                }
+
+                // Hidden windows sleep until the next event and skip acquire and submit entirely.
+                // Swapchain recreation waits too, a minimized window has no extent to create it with
+                if !activity.should_render(render_when_unfocused) {
+                    control_flow.set_wait();
+                    paused = true;
+                    return;
+                }
+
+                // The time spent paused would otherwise arrive as one huge delta
+                if paused {
+                    paused = false;
+                    last_frame = Instant::now();
+                    if let Some((pacer, _)) = pacer.as_mut() {
+                        pacer.reset();
+                    }
+                }
+
+                if let Some(deadline) = next_frame_at {
+                    if Instant::now() < deadline {
+                        control_flow.set_wait_until(deadline);
+                        return;
+                    }
+                    next_frame_at = None;
+                }
+
+                // An exit requested earlier in this iteration is kept by winit
+                match run_mode {
+                    RunMode::Poll => control_flow.set_poll(),
+                    RunMode::Wait => control_flow.set_wait(),
+                    RunMode::WaitUntil(interval) => control_flow.set_wait_until(Instant::now() + interval),
+                }
+
+                if resize.poll(Instant::now()) {
+                    // Events may have been coalesced, the window's current size is the one to follow.
+                    // Frames in flight keep the old targets alive until they finished
+                    let recreated = swapchains.handle_resize(window.physical_size(), |swapchains| {
+                        // The window may have moved to a monitor with another refresh rate
+                        vsync_paces = pacer.as_ref().is_some_and(|(pacer, _)| vsync_is_slower(&window.get_native_window(), swapchains.swapchain(), pacer.target()));
+
+                        match post_process.as_mut() {
+                            Some(chain) => chain.resize(&toolset, swapchains.swapchain().image_extent()),
+                            None => Ok(()),
+                        }
+                    });
+                    if let Err(e) = recreated {
+                        abort(&toolset, &mut frame_sync, &mut recorder, &mut callbacks.exit);
+                        *failure_slot = Some(e);
+                        control_flow.set_exit();
+                        return;
+                    }
+                    swapchain_recreated = true;
+                }
+                // A loop that sleeps between events still has to carry out the resize once it settled
+                if let Some(deadline) = resize.deadline() {
+                    match *control_flow {
+                        ControlFlow::Wait => control_flow.set_wait_until(deadline),
+                        ControlFlow::WaitUntil(wake_up) if deadline < wake_up => control_flow.set_wait_until(deadline),
+                        _ => (),
+                    }
+                }
+
+                if let (Some(policy), Some(check_at)) = (defrag_policy, next_defrag_check) {
+                    let now = Instant::now();
+                    if now >= check_at {
+                        next_defrag_check = Some(now + policy.interval);
+                        let fragmentation = toolset.memory_allocator.fragmentation();
+                        if fragmentation >= policy.threshold {
+                            // Buffers may only move once no frame reads them anymore
+                            let defragmented = frame_sync.wait_all()
+                            .and_then(|_| toolset.memory_allocator.defragment(&toolset.device_queue));
+                            match defragmented {
+                                Ok(stats) => info!("defragmented at {:.0}% fragmentation, moved {} bytes and freed {} blocks", fragmentation * 100.0, stats.bytes_moved, stats.blocks_freed),
+                                Err(e) => warn!("failed to defragment: {e}"),
+                            }
+                        }
+                    }
+                }
+
+                // Waits until the GPU is done with this slot's previous frame
+                let frame_index = match frame_sync.try_begin_frame() {
+                    Ok(frame_index) => frame_index,
+                    Err(e) => {
+                        abort(&toolset, &mut frame_sync, &mut recorder, &mut callbacks.exit);
+                        *failure_slot = Some(e);
+                        control_flow.set_exit();
+                        return;
+                    },
+                };
+                if let Some(stats) = stats_pool.as_ref().and_then(|pool| pool.read(frame_index as u32)) {
+                    last_stats.pipeline = Some(stats);
+                }
+                // Results that aren't in yet keep their previous value rather than stalling the loop
+                match occlusion_queries.read(frame_index as u32) {
+                    Ok(samples) => last_stats.occlusion_samples.extend(samples),
+                    Err(e) => warn!("failed to read occlusion queries: {e}"),
+                }
+                if let Err(e) = recorder.collect(frame_index) {
+                    warn!("failed to read back a recorded frame: {e}");
+                }
+
+                // Advance user state
+                let now = Instant::now();
+                last_stats.frame_time = now.duration_since(last_frame);
+                last_stats.target_frame_time = pacer.as_ref().filter(|_| !vsync_paces).map(|(pacer, _)| pacer.target());
+                last_stats.swapchain_recreations = resize.recreations();
+                let delta = last_stats.frame_time.as_secs_f32();
+                last_frame = now;
+
+                let mut context = UpdateContext {
+                    toolset : &toolset,
+                    elapsed : now.duration_since(start),
+                    input : &mut input,
+                    exit_requested : false,
+                };
+                for _ in 0..timestep.advance(delta) {
+                    (callbacks.fixed_update)(&mut context, timestep.step());
+                }
+                (callbacks.update)(&mut context, delta);
+                exit_requested |= context.exit_requested || exit_key.is_some_and(|key| input.is_key_pressed(key));
+                input.end_frame();
+
+                if input.is_cursor_grabbed() != cursor_grabbed {
+                    cursor_grabbed = input.is_cursor_grabbed();
+                    apply_cursor_grab(&window.get_native_window(), cursor_grabbed);
+                }
+
+                // Collect this frame's draw calls
+                let mut frame = Frame {
+                    toolset : &toolset,
+                    delta,
+                    alpha : timestep.alpha(),
+                    clear_color : toolset.config.clear_color,
+                    compute_passes : Vec::new(),
+                    commands : Vec::new(),
+                    resized : swapchain_recreated,
+                    frame_index,
+                    stats : last_stats.clone(),
+                    exit_requested : false,
+                    viewport_regions : Vec::new(),
+                    post_process : post_process.as_mut(),
+                    recorder : &mut recorder,
+                    overlay : &mut overlay,
+                    retired : Vec::new(),
+                };
+                frame.overlay.clear();
+                (callbacks.render)(&mut frame);
+                if let Some(stats_overlay) = &mut stats_overlay {
+                    stats_overlay.draw(&mut frame);
+                }
+                // Even when the frame is skipped below, earlier frames may still use what it replaced
+                for resource in frame.retired.drain(..) {
+                    frame_sync.retire(resource);
+                }
+                swapchain_recreated = false;
+                exit_requested |= frame.exit_requested;
+
+                // Wake up right away if this frame gets skipped, so the exit isn't delayed until the next event
+                if exit_requested {
+                    control_flow.set_poll();
+                }
+
+                let acquired = match swapchains.acquire(Some(acquire_timeout)) {
+                    Ok(acquired) => acquired,
+                    // Nothing can be presented until the swapchain was recreated, so that happens right away
+                    Err(AcquireFailure::OutOfDate) => {
+                        resize.force();
+                        control_flow.set_poll();
+                        return;
+                    },
+                    // The next frame tries again
+                    Err(AcquireFailure::Timeout) => {
+                        warn!("no swapchain image within {acquire_timeout:?}, skipped a frame");
+                        control_flow.set_poll();
+                        return;
+                    },
+                    Err(e) => {
+                        abort(&toolset, &mut frame_sync, &mut recorder, &mut callbacks.exit);
+                        *failure_slot = Some(e.into());
+                        control_flow.set_exit();
+                        return;
+                    },
+                };
+                let image_i = acquired.index;
+
+                // Last in the pass, on top of everything the render callback drew
+                let (subpass, extent) = match frame.post_process.as_deref() {
+                    Some(chain) => (chain.scene_subpass(), chain.extent()),
+                    None => (Subpass::from(window.get_render_pass(), 0).unwrap(), swapchains.swapchain().image_extent()),
+                };
+                let logical_size = window.logical_size();
+                let overlay_command = overlay_renderer.record_command(&toolset, frame.overlay, subpass, extent, [logical_size.width as f32, logical_size.height as f32]);
+                match overlay_command {
+                    Ok(Some(record)) => frame.record(record),
+                    Ok(None) => (),
+                    Err(e) => warn!("failed to draw the overlay: {e}"),
+                }
+
+                let stats = stats_pool.as_ref().map(|pool| (pool, frame_index as u32));
+                let Some(command_allocator) = frame_sync.command_allocator() else {
+                    abort(&toolset, &mut frame_sync, &mut recorder, &mut callbacks.exit);
+                    *failure_slot = Some(EngineError::MissingCommandAllocator);
+                    control_flow.set_exit();
+                    return;
+                };
+                let framebuffer = &swapchains.framebuffers()[image_i as usize];
+                let target = match frame.post_process.as_deref() {
+                    Some(chain) => FrameTarget::PostProcessed(chain, framebuffer),
+                    None => FrameTarget::Framebuffer(framebuffer),
+                };
+                let command_buffer = toolset.create_frame_command_buffer(command_allocator, target, frame.clear_color, frame.compute_passes, frame.commands, &frame.viewport_regions, stats, Some((&occlusion_queries, frame_index as u32)));
+
+                // Copied out after the frame is drawn, on the same fence
+                let capture = recorder.capture(&toolset, command_allocator, &swapchains.images()[image_i as usize], frame_index)
+                .unwrap_or_else(|e| {
+                    warn!("stopped recording: {e}");
+                    recorder.stop();
+                    None
+                });
+
+                let command_buffers = [Some(command_buffer), capture].into_iter().flatten().collect();
+                let presented = swapchains.try_present(acquired, frame_sync.previous_future(&device), command_buffers, &toolset.device_queue);
+                let fence = match presented {
+                    Ok(PresentResult::Ok(fence)) => Some(fence),
+                    // Still presentable, waits like a resize so dragging the window doesn't recreate every frame
+                    Ok(PresentResult::Resized(fence)) => {
+                        if !resize.is_pending() {
+                            resize.request(window.physical_size().into(), Instant::now());
+                        }
+                        Some(fence)
+                    },
+                    Ok(PresentResult::OutOfDate) => {
+                        resize.force();
+                        None
+                    },
+                    Err(EngineError::DeviceLost) => {
+                        abort(&toolset, &mut frame_sync, &mut recorder, &mut callbacks.exit);
+                        *failure_slot = Some(EngineError::DeviceLost);
+                        control_flow.set_exit();
+                        return;
+                    },
+                    Err(e) => {
+                        warn!("failed to submit a frame: {e}");
+                        None
+                    },
+                };
+                frame_sync.end_frame(fence);
+
+                // The wait ends up in the next frame's delta, so fixed updates still see real time
+                if let Some((pacer, wait)) = pacer.as_mut().filter(|_| !exit_requested && !vsync_paces) {
+                    if let Pacing::WaitUntil(deadline) = pacer.end_frame(Instant::now()) {
+                        match wait {
+                            PacingWait::SleepThenSpin => FramePacer::sleep_until(deadline),
+                            PacingWait::EventLoop => {
+                                next_frame_at = Some(deadline);
+                                control_flow.set_wait_until(deadline);
+                            },
+                        }
+                    }
+                }
+
+                if exit_requested {
+                    if let Err(e) = exit(&toolset, &mut frame_sync, &mut recorder, &mut callbacks.exit) {
+                        *failure_slot = Some(e);
+                    }
+                    control_flow.set_exit();
+                }
+            },
+            _ => ()
+        }
+    });
+
+    failure.map_or(Ok(()), Err)
+}
+
+// Resources used by frames in flight must outlive them, so wait before handing over to the exit callback.
+// The callback runs even when the wait failed, the error is returned afterwards
+fn exit(toolset : &VulkanToolset, frame_sync : &mut FrameSync, recorder : &mut FrameRecorder, callback : &mut Option<ExitCallback>) -> Result<(), EngineError> {
+    let waited = frame_sync.wait_all();
+    if waited.is_err() {
+        frame_sync.abandon_frames();
+    }
+    if let Err(e) = recorder.finish() {
+        warn!("failed to finish frame recording: {e}");
+    }
+
+    if let Some(callback) = callback.take() {
+        callback(toolset);
+    }
+
+    // Leak hunting aid, what the callbacks still hold shows up too so compare runs of different lengths
+    info!("memory at exit: {}", toolset.memory_allocator.stats());
+
+    waited
+}
+
+// Like exit for a device that can't render anymore, its frames in flight are abandoned rather than waited for
+fn abort(toolset : &VulkanToolset, frame_sync : &mut FrameSync, recorder : &mut FrameRecorder, callback : &mut Option<ExitCallback>) {
+    frame_sync.abandon_frames();
+    // Nothing is left in flight, so exit has nothing to wait for that could fail
+    if let Err(e) = exit(toolset, frame_sync, recorder, callback) {
+        warn!("failed to wait for frames at exit: {e}");
+    }
+}
+
+// FIFO presentation holds the loop to the refresh rate of the window's monitor
+fn vsync_is_slower(window : &Window, swapchain : &Swapchain, target : Duration) -> bool {
+    matches!(swapchain.present_mode(), PresentMode::Fifo | PresentMode::FifoRelaxed)
+        && FramePacer::vsync_is_slower(window.current_monitor().and_then(|monitor| monitor.refresh_rate_millihertz()), target)
+}
+
+// Locked keeps the cursor in place, platforms without it fall back to confining it to the window
+fn apply_cursor_grab(window : &Window, grabbed : bool) {
+    let result = match grabbed {
+        true => window.set_cursor_grab(CursorGrabMode::Locked)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined)),
+        false => window.set_cursor_grab(CursorGrabMode::None),
+    };
+
+    if let Err(e) = result {
+        warn!("failed to change cursor grab: {e}");
+    }
+    window.set_cursor_visible(!grabbed);
+}
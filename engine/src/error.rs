@@ -0,0 +1,203 @@
+use std::{error::Error, fmt::{Display, Formatter, Result as FmtResult}};
+
+use vulkano::{buffer::{AllocateBufferError, IndexType}, command_buffer::CommandBufferExecError, image::AllocateImageError, sync::HostAccessError, Validated, ValidationError, Version, VulkanError};
+
+use crate::vulkan::{pipeline_desc::PipelineDescError, render_pass::RenderPassMismatch, shader_interface::DescriptorBindingInfo};
+
+#[derive(Debug)]
+pub enum EngineError {
+    Vulkan(VulkanError),
+    Validation(Box<ValidationError>),
+    BufferAllocation(AllocateBufferError),
+    ImageAllocation(AllocateImageError),
+    Execution(CommandBufferExecError),
+    HostAccess(HostAccessError),
+    Image(image::ImageError),
+    Io(std::io::Error),
+    ObjLoad(tobj::LoadError),
+    GltfLoad(gltf::Error),
+    UnsupportedFeature(String),
+    // Pixel data does not match the requested image size
+    PixelCount { width : u32, height : u32, len : usize },
+    // Sets and bindings the pipeline declares but no write provided
+    MissingDescriptors { sets : Vec<u32>, bindings : Vec<DescriptorBindingInfo> },
+    UnknownDescriptorSet(u32),
+    // Element range outside of the buffer, or empty
+    BufferRange { offset : u64, len : u64, buffer_len : u64 },
+    // Pixel data shorter than the image region it is copied into
+    ImageDataSize { expected : u64, len : usize },
+    // Image that must match another one in size
+    ImageExtent { expected : [u32; 3], actual : [u32; 3] },
+    // Shader module has no entry point of this name, `available` lists the ones it has
+    MissingEntryPoint { name : String, available : Vec<String> },
+    // Cube map face that isn't square or doesn't match the first face, faces count from +X
+    CubeFaceSize { face : usize, size : [u32; 2], expected : [u32; 2] },
+    // Index too large for the index type of the mesh
+    IndexOverflow { index : u32, index_type : IndexType },
+    // Vulkan buffers can't be zero sized, so there must be at least one element to upload
+    EmptyBuffer,
+    // Vulkan version the instance was asked for that the library can't provide
+    ApiVersion { requested : Version, supported : Version },
+    // Layout changes for a binding the pipeline's shaders don't declare
+    UndeclaredBinding { set : u32, binding : u32 },
+    // Request past one of the device's limits, named as in the Vulkan spec
+    DeviceLimit { limit : &'static str, requested : u32, max : u32 },
+    // Mip level or array layer past the ones the image has
+    ImageSubresource { mip_level : u32, array_layer : u32, mip_levels : u32, array_layers : u32 },
+    // The device stopped working, nothing created from it can be used anymore
+    DeviceLost,
+    // Pipeline that can't draw into a framebuffer, its render pass isn't compatible with the framebuffer's
+    IncompatibleRenderPass(RenderPassMismatch),
+    // Pipeline description the subpass it draws in can't satisfy, see GraphicsPipelineDesc::check
+    InvalidPipeline(PipelineDescError),
+    // Frame slot without a command allocator, FrameSync only has them when created with_command_allocators
+    MissingCommandAllocator,
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f : &mut Formatter<'_>) -> FmtResult {
+        match self {
+            EngineError::Vulkan(e) => write!(f, "vulkan error: {e}"),
+            EngineError::Validation(e) => write!(f, "validation error: {e}"),
+            EngineError::BufferAllocation(e) => write!(f, "failed to allocate buffer: {e}"),
+            EngineError::ImageAllocation(e) => write!(f, "failed to allocate image: {e}"),
+            EngineError::Execution(e) => write!(f, "failed to execute command buffer: {e}"),
+            EngineError::HostAccess(e) => write!(f, "failed to access buffer from host: {e}"),
+            EngineError::Image(e) => write!(f, "image error: {e}"),
+            EngineError::Io(e) => write!(f, "io error: {e}"),
+            EngineError::ObjLoad(e) => write!(f, "failed to load OBJ file: {e}"),
+            EngineError::GltfLoad(e) => write!(f, "failed to load glTF file: {e}"),
+            EngineError::UnsupportedFeature(reason) => write!(f, "unsupported feature: {reason}"),
+            EngineError::PixelCount { width, height, len } => write!(f, "{len} bytes do not form a {width}x{height} RGBA8 image"),
+            EngineError::MissingDescriptors { sets, bindings } => {
+                write!(f, "missing descriptor sets {sets:?}")?;
+                bindings.iter().try_for_each(|binding| write!(f, ", {binding}"))
+            },
+            EngineError::UnknownDescriptorSet(set) => write!(f, "pipeline layout declares no descriptor set {set}"),
+            EngineError::BufferRange { offset, len, buffer_len } => write!(f, "{len} elements at offset {offset} do not fit a buffer of {buffer_len} elements"),
+            EngineError::ImageDataSize { expected, len } => write!(f, "image region needs {expected} bytes, got {len}"),
+            EngineError::ImageExtent { expected, actual } => write!(f, "image extent {actual:?} does not match {expected:?}"),
+            EngineError::MissingEntryPoint { name, available } => write!(f, "shader module has no entry point named {name:?}, available entry points are {available:?}"),
+            EngineError::CubeFaceSize { face, size, expected } => write!(f, "cube map face {face} is {}x{}, expected {}x{}", size[0], size[1], expected[0], expected[1]),
+            EngineError::IndexOverflow { index, index_type } => write!(f, "index {index} does not fit in a {index_type:?} index buffer"),
+            EngineError::EmptyBuffer => write!(f, "cannot create a buffer without elements"),
+            EngineError::UndeclaredBinding { set, binding } => write!(f, "set {set} binding {binding} is not declared by the shaders"),
+            EngineError::ApiVersion { requested, supported } => write!(f, "Vulkan {requested} was requested, but the library only supports up to {supported}"),
+            EngineError::DeviceLimit { limit, requested, max } => write!(f, "{requested} exceeds the device's {limit} of {max}"),
+            EngineError::ImageSubresource { mip_level, array_layer, mip_levels, array_layers } => write!(f, "mip level {mip_level} of layer {array_layer} is outside of an image with {mip_levels} levels and {array_layers} layers"),
+            EngineError::DeviceLost => write!(f, "the device was lost"),
+            EngineError::IncompatibleRenderPass(mismatch) => write!(f, "incompatible render passes: {mismatch}"),
+            EngineError::InvalidPipeline(e) => write!(f, "invalid pipeline: {e}"),
+            EngineError::MissingCommandAllocator => write!(f, "the current frame has no command allocator"),
+        }
+    }
+}
+
+impl Error for EngineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EngineError::Vulkan(e) => Some(e),
+            EngineError::Validation(e) => Some(e.as_ref()),
+            EngineError::BufferAllocation(e) => Some(e),
+            EngineError::ImageAllocation(e) => Some(e),
+            EngineError::Execution(e) => Some(e),
+            EngineError::HostAccess(e) => Some(e),
+            EngineError::Image(e) => Some(e),
+            EngineError::Io(e) => Some(e),
+            EngineError::ObjLoad(e) => Some(e),
+            EngineError::GltfLoad(e) => Some(e),
+            EngineError::UnsupportedFeature(_)
+            | EngineError::PixelCount { .. }
+            | EngineError::MissingDescriptors { .. }
+            | EngineError::UnknownDescriptorSet(_)
+            | EngineError::BufferRange { .. }
+            | EngineError::ImageDataSize { .. }
+            | EngineError::ImageExtent { .. }
+            | EngineError::MissingEntryPoint { .. }
+            | EngineError::CubeFaceSize { .. }
+            | EngineError::IndexOverflow { .. }
+            | EngineError::EmptyBuffer
+            | EngineError::ApiVersion { .. }
+            | EngineError::UndeclaredBinding { .. }
+            | EngineError::DeviceLimit { .. }
+            | EngineError::ImageSubresource { .. }
+            | EngineError::DeviceLost
+            | EngineError::IncompatibleRenderPass(_)
+            | EngineError::InvalidPipeline(_)
+            | EngineError::MissingCommandAllocator => None,
+        }
+    }
+}
+
+impl From<VulkanError> for EngineError {
+    fn from(e : VulkanError) -> Self {
+        match e {
+            VulkanError::DeviceLost => EngineError::DeviceLost,
+            e => EngineError::Vulkan(e),
+        }
+    }
+}
+
+impl From<Box<ValidationError>> for EngineError {
+    fn from(e : Box<ValidationError>) -> Self {
+        EngineError::Validation(e)
+    }
+}
+
+impl From<AllocateBufferError> for EngineError {
+    fn from(e : AllocateBufferError) -> Self {
+        EngineError::BufferAllocation(e)
+    }
+}
+
+impl From<AllocateImageError> for EngineError {
+    fn from(e : AllocateImageError) -> Self {
+        EngineError::ImageAllocation(e)
+    }
+}
+
+impl From<CommandBufferExecError> for EngineError {
+    fn from(e : CommandBufferExecError) -> Self {
+        EngineError::Execution(e)
+    }
+}
+
+impl From<HostAccessError> for EngineError {
+    fn from(e : HostAccessError) -> Self {
+        EngineError::HostAccess(e)
+    }
+}
+
+impl From<image::ImageError> for EngineError {
+    fn from(e : image::ImageError) -> Self {
+        EngineError::Image(e)
+    }
+}
+
+impl From<std::io::Error> for EngineError {
+    fn from(e : std::io::Error) -> Self {
+        EngineError::Io(e)
+    }
+}
+
+impl From<tobj::LoadError> for EngineError {
+    fn from(e : tobj::LoadError) -> Self {
+        EngineError::ObjLoad(e)
+    }
+}
+
+impl From<gltf::Error> for EngineError {
+    fn from(e : gltf::Error) -> Self {
+        EngineError::GltfLoad(e)
+    }
+}
+
+// Lets `?` unwrap vulkano's validated results directly
+impl<E : Into<EngineError>> From<Validated<E>> for EngineError {
+    fn from(e : Validated<E>) -> Self {
+        match e {
+            Validated::Error(e) => e.into(),
+            Validated::ValidationError(e) => EngineError::Validation(e),
+        }
+    }
+}
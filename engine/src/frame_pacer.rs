@@ -0,0 +1,92 @@
+use std::{hint, thread, time::{Duration, Instant}};
+
+// Sleeping wakes up late by up to a scheduler tick, the rest of the wait is spun
+pub const SPIN_THRESHOLD : Duration = Duration::from_millis(1);
+
+// When the next frame may start, from FramePacer::end_frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pacing {
+    // Ahead of the target, wait until the instant before starting the next frame
+    WaitUntil(Instant),
+    // On time or late, start right away
+    Now,
+}
+
+// Caps the frame rate at a target frame time. Deadlines are kept on a fixed cadence, so a frame that is
+// a little late is made up by a shorter next one. Frames that missed a whole target are dropped rather
+// than rendered back to back, the cadence restarts from the late frame instead
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FramePacer {
+    target : Duration,
+    // When the current frame is due to end, None before the first frame
+    deadline : Option<Instant>,
+    missed : u32,
+}
+
+impl FramePacer {
+    pub fn new(target : Duration) -> FramePacer {
+        assert!(!target.is_zero(), "the target frame time must be positive");
+
+        FramePacer {
+            target,
+            deadline : None,
+            missed : 0,
+        }
+    }
+
+    pub fn target(&self) -> Duration {
+        self.target
+    }
+
+    // Frames that ended a whole target or more after their deadline
+    pub fn missed_frames(&self) -> u32 {
+        self.missed
+    }
+
+    // Call once a frame was submitted. The first frame only starts the cadence
+    pub fn end_frame(&mut self, now : Instant) -> Pacing {
+        let Some(deadline) = self.deadline else {
+            self.deadline = Some(now + self.target);
+            return Pacing::Now;
+        };
+
+        if now < deadline {
+            self.deadline = Some(deadline + self.target);
+            return Pacing::WaitUntil(deadline);
+        }
+
+        if now - deadline < self.target {
+            self.deadline = Some(deadline + self.target);
+        } else {
+            self.missed += 1;
+            self.deadline = Some(now + self.target);
+        }
+
+        Pacing::Now
+    }
+
+    // Pausing breaks the cadence, the next frame starts a new one instead of being late
+    pub fn reset(&mut self) {
+        self.deadline = None;
+    }
+
+    // True when FIFO presentation at this refresh rate already holds frames longer than the target,
+    // so pacing on top of it would only add latency. Unknown refresh rates never count as slower
+    pub fn vsync_is_slower(refresh_rate_millihertz : Option<u32>, target : Duration) -> bool {
+        refresh_rate_millihertz
+        .filter(|&rate| rate > 0)
+        .is_some_and(|rate| Duration::from_secs_f64(1000.0 / rate as f64) >= target)
+    }
+
+    // Sleeps most of the way, then spins through the last SPIN_THRESHOLD for an accurate wake up
+    pub fn sleep_until(deadline : Instant) {
+        let now = Instant::now();
+        if let Some(coarse) = deadline.checked_duration_since(now).and_then(|left| left.checked_sub(SPIN_THRESHOLD)) {
+            thread::sleep(coarse);
+        }
+
+        while Instant::now() < deadline {
+            hint::spin_loop();
+        }
+    }
+}
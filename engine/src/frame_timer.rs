@@ -0,0 +1,46 @@
+// Averages frame times over an interval, a counter updated every frame changes too fast to read
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameTimer {
+    interval : f32,
+    elapsed : f32,
+    frames : u32,
+    // Seconds per frame over the last full interval
+    average : Option<f32>,
+}
+
+impl FrameTimer {
+    pub fn new(interval : f32) -> FrameTimer {
+        assert!(interval > 0.0, "the averaging interval must be positive");
+
+        FrameTimer {
+            interval,
+            elapsed : 0.0,
+            frames : 0,
+            average : None,
+        }
+    }
+
+    // Adds a frame, returns true when it completed an interval and the average changed
+    pub fn tick(&mut self, delta : f32) -> bool {
+        self.elapsed += delta.max(0.0);
+        self.frames += 1;
+
+        if self.elapsed < self.interval {
+            return false;
+        }
+
+        self.average = Some(self.elapsed / self.frames as f32);
+        self.elapsed = 0.0;
+        self.frames = 0;
+        true
+    }
+
+    // None until the first interval has passed
+    pub fn frame_time(&self) -> Option<f32> {
+        self.average
+    }
+
+    pub fn fps(&self) -> Option<f32> {
+        self.average.map(|frame_time| 1.0 / frame_time)
+    }
+}
@@ -0,0 +1,13 @@
+pub trait Game {
+    // Called once per frame before the frame is submitted, delta is in seconds
+    fn update(&mut self, _delta : f32) {}
+
+    // Called zero or more times per frame with the constant AppConfig::fixed_timestep,
+    // before update. Motion advanced here doesn't depend on the frame rate
+    fn update_fixed(&mut self, _step : f32) {}
+
+    // Called once when the engine exits, the GPU no longer uses anything at this point
+    fn on_exit(&mut self) {}
+}
+
+impl Game for () {}
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+
+use winit::event::{DeviceEvent, ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
+
+// Keyboard and mouse state collected from winit events, mouse motion is accumulated per frame
+#[derive(Default)]
+pub struct InputState {
+    pressed_keys : HashSet<VirtualKeyCode>,
+    pressed_buttons : HashSet<MouseButton>,
+    mouse_delta : (f64, f64),
+    cursor_grabbed : bool,
+}
+
+impl InputState {
+    pub fn new() -> InputState {
+        InputState::default()
+    }
+
+    pub fn handle_window_event(&mut self, event : &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                input : KeyboardInput { virtual_keycode : Some(key), state, .. },
+                ..
+            } => {
+                match state {
+                    ElementState::Pressed => self.pressed_keys.insert(*key),
+                    ElementState::Released => self.pressed_keys.remove(key),
+                };
+            },
+            WindowEvent::MouseInput { button, state, .. } => {
+                match state {
+                    ElementState::Pressed => self.pressed_buttons.insert(*button),
+                    ElementState::Released => self.pressed_buttons.remove(button),
+                };
+            },
+            // Release events are not delivered to unfocused windows, drop everything held
+            WindowEvent::Focused(false) => {
+                self.pressed_keys.clear();
+                self.pressed_buttons.clear();
+                self.mouse_delta = (0.0, 0.0);
+                self.cursor_grabbed = false;
+            },
+            _ => (),
+        }
+    }
+
+    // Raw motion keeps arriving at the cursor's edge, so it drives mouse-look while grabbed
+    pub fn handle_device_event(&mut self, event : &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            if self.cursor_grabbed {
+                self.mouse_delta.0 += delta.0;
+                self.mouse_delta.1 += delta.1;
+            }
+        }
+    }
+
+    // Called by the engine once a frame was updated
+    pub fn end_frame(&mut self) {
+        self.mouse_delta = (0.0, 0.0);
+    }
+
+    pub fn is_key_pressed(&self, key : VirtualKeyCode) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    pub fn is_mouse_pressed(&self, button : MouseButton) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+
+    // Raw motion since the previous frame, always zero while the cursor is free
+    pub fn mouse_delta(&self) -> (f64, f64) {
+        self.mouse_delta
+    }
+
+    pub fn is_cursor_grabbed(&self) -> bool {
+        self.cursor_grabbed
+    }
+
+    // The engine applies the change to the window after the update callback
+    pub fn set_cursor_grabbed(&mut self, grabbed : bool) {
+        self.cursor_grabbed = grabbed;
+        if !grabbed {
+            self.mouse_delta = (0.0, 0.0);
+        }
+    }
+}
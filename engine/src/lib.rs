@@ -1,29 +1,56 @@
-mod vulkan;
-mod tests;
+use std::{cell::RefCell, rc::Rc};
 
-use tests::{compute_test::compute_test, image_test::image_test, window_test::window_test};
-use vulkan::vulkan::VulkanToolset;
-use winit::event_loop::EventLoop;
+use log::info;
+
+pub mod assets;
+mod config;
+mod engine;
+mod error;
+mod frame_pacer;
+mod frame_timer;
+mod game;
+pub mod input;
+pub mod render;
+mod resize_debounce;
+pub mod scene;
+mod timestep;
+pub mod vulkan;
+
+pub use config::{AppConfig, CommandBufferOptions, DefragPolicy, DeviceSelection, FrameLimit, InstanceConfig, PacingWait, PresentPreference, QueueRequest, QueueRole, RunMode, WindowConfig};
+pub use engine::{Engine, EngineBuilder, Frame, FrameStats, UpdateContext};
+pub use error::EngineError;
+pub use frame_pacer::{FramePacer, Pacing};
+pub use frame_timer::FrameTimer;
+pub use game::Game;
+pub use resize_debounce::ResizeDebouncer;
+pub use timestep::FixedTimestep;
+pub use vulkan::{screenshot::{save_png, ImageData, SaveFormat}, vulkan::VulkanToolset};
 
 pub struct App;
 
 impl App {
     pub fn run() {
-        // Setup Vulkan toolset
-        let event_loop = EventLoop::new();
+        Self::run_with(AppConfig::default(), ());
+    }
 
-        let toolset = VulkanToolset::new(&event_loop);
-        let device = &toolset.logical_device;
-        let queue = &toolset.device_queue;
-        let allocator = &toolset.memory_allocator;
+    pub fn run_with<G : Game + 'static>(config : AppConfig, game : G) {
+        let game = Rc::new(RefCell::new(game));
+        let fixed_game = game.clone();
+        let exit_game = game.clone();
 
-        // Test basic shader workability
-        compute_test(&device, &queue, &allocator);
+        Engine::builder()
+        .config(config)
+        .with_fixed_update(move |_, step| fixed_game.borrow_mut().update_fixed(step))
+        .with_update(move |_, delta| game.borrow_mut().update(delta))
+        .with_exit(move |_| exit_game.borrow_mut().on_exit())
+        .run();
+    }
 
-        // Test basic image workability
-        image_test(&device, &queue, &allocator);
+    // Brings the device up without opening a window
+    pub fn run_headless(config : AppConfig) {
+        let toolset = VulkanToolset::headless(config);
+        let properties = toolset.logical_device.physical_device().properties();
 
-        // Vertex test
-        window_test(toolset, event_loop);
+        info!("headless on {}", properties.device_name);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::BufferContents,
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
+    image::{sampler::{Sampler, SamplerAddressMode, SamplerCreateInfo}, view::ImageView, Image, ImageUsage, SampleCount},
+    pipeline::{Pipeline, PipelineBindPoint}
+};
+
+use crate::{error::EngineError, vulkan::{render_target_pool::PooledRenderTarget, vulkan::{ComputeShader, VulkanToolset}}};
+
+mod bloom_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        include: ["assets/shaders/include"],
+        src: r#"
+            #version 460
+
+            #include "common.glsl"
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D source;
+            layout(set = 0, binding = 1) uniform sampler2D hdr;
+            layout(set = 0, binding = 2, rgba16f) uniform image2D destination;
+
+            layout(push_constant) uniform Bloom {
+                int stage;
+                float threshold;
+                float intensity;
+                float radius;
+            } bloom;
+
+            const int STAGE_THRESHOLD = 0;
+            const int STAGE_DOWNSAMPLE = 1;
+            const int STAGE_UPSAMPLE = 2;
+
+            // 3x3 tent filter, the bilinear taps `spread` source texels apart
+            vec3 tent(vec2 uv, float spread) {
+                vec2 step = spread / vec2(textureSize(source, 0));
+                vec3 sum = vec3(0.0);
+
+                for (int y = -1; y <= 1; y++) {
+                    for (int x = -1; x <= 1; x++) {
+                        float weight = float((2 - abs(x)) * (2 - abs(y)));
+                        sum += texture(source, uv + vec2(x, y) * step).rgb * weight;
+                    }
+                }
+
+                return sum / 16.0;
+            }
+
+            void main() {
+                ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 extent = imageSize(destination);
+                if (any(greaterThanEqual(pixel, extent))) {
+                    return;
+                }
+                vec2 uv = (vec2(pixel) + 0.5) / vec2(extent);
+
+                vec3 color;
+                if (bloom.stage == STAGE_THRESHOLD) {
+                    // The bilinear tap averages the 2x2 source pixels under this one
+                    vec3 source_color = texture(source, uv).rgb;
+                    float luminance = dot(source_color, vec3(0.2126, 0.7152, 0.0722));
+                    color = source_color * max(luminance - bloom.threshold, 0.0) / max(luminance, EPSILON);
+                } else if (bloom.stage == STAGE_DOWNSAMPLE) {
+                    color = tent(uv, 1.0);
+                } else if (bloom.stage == STAGE_UPSAMPLE) {
+                    color = imageLoad(destination, pixel).rgb + tent(uv, bloom.radius);
+                } else {
+                    color = texture(hdr, uv).rgb + tent(uv, bloom.radius) * bloom.intensity;
+                }
+
+                imageStore(destination, pixel, vec4(color, 1.0));
+            }
+        "#,
+    }
+}
+
+const LOCAL_SIZE : [u32; 3] = [8, 8, 1];
+
+// The half resolution threshold level, then four halvings
+const LEVEL_COUNT : usize = 5;
+
+const STAGE_THRESHOLD : i32 = 0;
+const STAGE_DOWNSAMPLE : i32 = 1;
+const STAGE_UPSAMPLE : i32 = 2;
+const STAGE_COMPOSITE : i32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BloomConfig {
+    // Luminance above which pixels start to glow
+    pub threshold : f32,
+    // Scale of the glow added back onto the image
+    pub intensity : f32,
+    // Spread of the upsampling filter in texels of each level, larger values blur wider
+    pub radius : f32,
+}
+
+impl Default for BloomConfig {
+    fn default() -> Self {
+        BloomConfig {
+            threshold : 1.0,
+            intensity : 0.5,
+            radius : 1.0,
+        }
+    }
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct BloomConstants {
+    stage : i32,
+    threshold : f32,
+    intensity : f32,
+    radius : f32,
+}
+
+// Extents of the bloom levels for an image of this size, from half resolution down
+pub fn bloom_level_extents(extent : [u32; 2]) -> Vec<[u32; 2]> {
+    std::iter::successors(Some(extent), |&[width, height]| Some([(width / 2).max(1), (height / 2).max(1)]))
+    .skip(1)
+    .take(LEVEL_COUNT)
+    .collect()
+}
+
+// Glow around bright parts of an HDR image. Pixels above the threshold are downsampled into
+// a chain of ever smaller levels, blurred back up through them and added onto the source.
+// The levels are sized for one extent, create a new pass after a resize
+pub struct BloomPass {
+    shader : ComputeShader,
+    descriptor_set_allocator : StandardDescriptorSetAllocator,
+    sampler : Arc<Sampler>,
+    // From the render target pool, they go back to it with the pass
+    levels : Vec<PooledRenderTarget>,
+    extent : [u32; 2],
+}
+
+impl BloomPass {
+    pub fn new(toolset : &VulkanToolset, extent : [u32; 2]) -> Result<BloomPass, EngineError> {
+        let device = &toolset.logical_device;
+        let module = bloom_cs::load(device.clone()).expect("failed to create shader module");
+
+        let levels = bloom_level_extents(extent)
+        .into_iter()
+        .map(|level| toolset.memory_allocator.render_targets.acquire(Format::R16G16B16A16_SFLOAT, level, ImageUsage::STORAGE | ImageUsage::SAMPLED, SampleCount::Sample1))
+        .collect::<Result<Vec<_>, EngineError>>()?;
+
+        // Taps past the edge repeat it instead of wrapping bright pixels around
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+            },
+        )?;
+
+        Ok(BloomPass {
+            shader : ComputeShader::new(&module, LOCAL_SIZE, device.clone()),
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+            sampler,
+            levels,
+            extent,
+        })
+    }
+
+    // Record outside of a render pass. `hdr_src` needs SAMPLED usage, `bloom_out` gets the source
+    // with the glow added and needs STORAGE usage, the R16G16B16A16_SFLOAT format and the pass extent
+    pub fn record(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, hdr_src : &Arc<Image>, bloom_out : &Arc<Image>, config : BloomConfig) -> Result<(), EngineError> {
+        if bloom_out.format() != Format::R16G16B16A16_SFLOAT {
+            return Err(EngineError::UnsupportedFeature(format!("bloom is written as R16G16B16A16_SFLOAT, not {:?}", bloom_out.format())));
+        }
+
+        let expected = [self.extent[0], self.extent[1], 1];
+        for image in [hdr_src, bloom_out] {
+            if image.extent() != expected {
+                return Err(EngineError::ImageExtent { expected, actual : image.extent() });
+            }
+        }
+
+        let hdr_view = ImageView::new_default(hdr_src.clone())?;
+        let out_view = ImageView::new_default(bloom_out.clone())?;
+
+        let levels = self.levels.iter().map(PooledRenderTarget::view).collect::<Vec<_>>();
+        let last = LEVEL_COUNT - 1;
+
+        // (stage, sampled source, written destination) of every pass in order
+        let passes = [(STAGE_THRESHOLD, &hdr_view, levels[0])].into_iter()
+        .chain((1..=last).map(|level| (STAGE_DOWNSAMPLE, levels[level - 1], levels[level])))
+        .chain((0..last).rev().map(|level| (STAGE_UPSAMPLE, levels[level + 1], levels[level])))
+        .chain([(STAGE_COMPOSITE, levels[0], &out_view)]);
+
+        let pipeline = &self.shader.pipeline;
+        let layout = pipeline.layout();
+
+        builder.bind_pipeline_compute(pipeline.clone())?;
+
+        for (stage, source, destination) in passes {
+            let descriptor_set = PersistentDescriptorSet::new(
+                &self.descriptor_set_allocator,
+                layout.set_layouts()[0].clone(),
+                [
+                    WriteDescriptorSet::image_view_sampler(0, source.clone(), self.sampler.clone()),
+                    WriteDescriptorSet::image_view_sampler(1, hdr_view.clone(), self.sampler.clone()),
+                    WriteDescriptorSet::image_view(2, destination.clone()),
+                ],
+                [],
+            )?;
+
+            let constants = BloomConstants {
+                stage,
+                threshold : config.threshold,
+                intensity : config.intensity,
+                radius : config.radius,
+            };
+
+            let [width, height, _] = destination.image().extent();
+
+            builder.bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?
+            .push_constants(layout.clone(), 0, constants)?
+            .dispatch(self.shader.group_counts([width, height, 1]))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,342 @@
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, DrawIndirectCommand, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{Pipeline, PipelineBindPoint},
+    DeviceSize
+};
+
+use crate::{error::EngineError, scene::frustum::Frustum, vulkan::{memory_stats::AllocationCategory, occlusion::OcclusionQueryPool, vulkan::{ComputeShader, VulkanToolset}}};
+
+mod cull_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            struct BoundingSphere {
+                float center[3];
+                float radius;
+            };
+
+            struct DrawCommand {
+                uint vertex_count;
+                uint instance_count;
+                uint first_vertex;
+                uint first_instance;
+            };
+
+            layout(set = 0, binding = 0) readonly buffer Spheres {
+                BoundingSphere spheres[];
+            };
+
+            layout(set = 0, binding = 1) buffer Draws {
+                DrawCommand draws[];
+            };
+
+            layout(set = 0, binding = 2) buffer Count {
+                uint visible_count;
+            };
+
+            layout(push_constant) uniform FrustumPlanes {
+                vec4 planes[6];
+            } frustum;
+
+            void main() {
+                uint idx = gl_GlobalInvocationID.x;
+                if (idx >= spheres.length() || idx >= draws.length()) {
+                    return;
+                }
+
+                BoundingSphere sphere = spheres[idx];
+                vec3 center = vec3(sphere.center[0], sphere.center[1], sphere.center[2]);
+
+                uint visible = 1;
+                for (int i = 0; i < 6; i++) {
+                    if (dot(frustum.planes[i].xyz, center) + frustum.planes[i].w < -sphere.radius) {
+                        visible = 0;
+                    }
+                }
+
+                draws[idx].instance_count = visible;
+                if (visible == 1) {
+                    atomicAdd(visible_count, 1);
+                }
+            }
+        ",
+    }
+}
+
+mod occlusion_cull_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            struct BoundingSphere {
+                float center[3];
+                float radius;
+            };
+
+            struct DrawCommand {
+                uint vertex_count;
+                uint instance_count;
+                uint first_vertex;
+                uint first_instance;
+            };
+
+            layout(set = 0, binding = 0) readonly buffer Spheres {
+                BoundingSphere spheres[];
+            };
+
+            layout(set = 0, binding = 1) buffer Draws {
+                DrawCommand draws[];
+            };
+
+            layout(set = 0, binding = 2) buffer Count {
+                uint visible_count;
+            };
+
+            // Occlusion query results of the previous frame
+            layout(set = 0, binding = 3) readonly buffer Samples {
+                uint samples[];
+            };
+
+            // Consecutive frames without a passing sample
+            layout(set = 0, binding = 4) buffer Hidden {
+                uint hidden_frames[];
+            };
+
+            layout(push_constant) uniform FrustumPlanes {
+                vec4 planes[6];
+            } frustum;
+
+            const uint HIDDEN_FRAMES = 2;
+            // Hidden objects are drawn again this often to find out whether they came back into view
+            const uint RETEST_INTERVAL = 8;
+
+            void main() {
+                uint idx = gl_GlobalInvocationID.x;
+                if (idx >= spheres.length() || idx >= draws.length()) {
+                    return;
+                }
+
+                BoundingSphere sphere = spheres[idx];
+                vec3 center = vec3(sphere.center[0], sphere.center[1], sphere.center[2]);
+
+                bool in_frustum = true;
+                for (int i = 0; i < 6; i++) {
+                    if (dot(frustum.planes[i].xyz, center) + frustum.planes[i].w < -sphere.radius) {
+                        in_frustum = false;
+                    }
+                }
+
+                // Nothing is known about objects outside the frustum, they are drawn as soon as they enter it
+                uint hidden = 0;
+                if (in_frustum) {
+                    bool drawn = draws[idx].instance_count != 0;
+                    hidden = drawn && samples[idx] != 0 ? 0 : hidden_frames[idx] + 1;
+                }
+                hidden_frames[idx] = hidden;
+
+                uint visible = in_frustum && (hidden < HIDDEN_FRAMES || hidden % RETEST_INTERVAL == 0) ? 1 : 0;
+                draws[idx].instance_count = visible;
+                if (visible == 1) {
+                    atomicAdd(visible_count, 1);
+                }
+            }
+        ",
+    }
+}
+
+const LOCAL_SIZE : [u32; 3] = [64, 1, 1];
+
+#[derive(BufferContents, Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct BoundingSphere {
+    pub center : [f32; 3],
+    pub radius : f32,
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct FrustumPlanes {
+    planes : [[f32; 4]; 6],
+}
+
+// Culls in place: object i keeps draw slot i, which gets one instance when its sphere is visible
+// and none otherwise. Unlike IndirectCulling nothing moves, so the other command fields are set once
+// by the caller. Vulkano has no draw_indirect_count yet, so draw every slot with
+// VulkanToolset::record_multi_draw_indirect, the culled ones are no-ops. The count is for statistics
+pub struct FrustumCuller {
+    shader : ComputeShader,
+    descriptor_set_allocator : StandardDescriptorSetAllocator,
+}
+
+impl FrustumCuller {
+    pub fn new(toolset : &VulkanToolset) -> FrustumCuller {
+        let device = &toolset.logical_device;
+        let module = cull_cs::load(device.clone()).expect("failed to create shader module");
+
+        FrustumCuller {
+            shader : ComputeShader::new(&module, LOCAL_SIZE, device.clone()),
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+        }
+    }
+
+    // Record outside of a render pass. `draws` needs STORAGE_BUFFER usage and one slot per sphere,
+    // `count` needs TRANSFER_DST as it is reset first, see IndirectCulling::create_count_buffer
+    pub fn record(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, spheres : &Subbuffer<[BoundingSphere]>, frustum : &Frustum, draws : &Subbuffer<[DrawIndirectCommand]>, count : &Subbuffer<u32>) -> Result<(), EngineError> {
+        let pipeline = &self.shader.pipeline;
+        let layout = pipeline.layout();
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::buffer(0, spheres.clone()),
+                WriteDescriptorSet::buffer(1, draws.clone()),
+                WriteDescriptorSet::buffer(2, count.clone()),
+            ],
+            [],
+        )?;
+
+        builder.fill_buffer(count.clone().reinterpret(), 0)?
+        .bind_pipeline_compute(pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?
+        .push_constants(layout.clone(), 0, FrustumPlanes { planes : frustum.planes })?
+        .dispatch(self.shader.group_counts([spheres.len() as u32, 1, 1]))?;
+
+        Ok(())
+    }
+}
+
+// FrustumCuller that also drops objects whose draws passed no samples for two frames in a row, from
+// occlusion queries around each slot's draw. Draw slots work the same way. Per frame: record_cull
+// outside of the render pass, then record_draws inside it. Hidden objects are drawn every few frames
+// to notice when they become visible again. The queries need one draw per slot, so unlike
+// VulkanToolset::record_multi_draw_indirect the slots are never merged into a single command
+pub struct OcclusionCuller {
+    shader : ComputeShader,
+    descriptor_set_allocator : StandardDescriptorSetAllocator,
+    queries : OcclusionQueryPool,
+    samples : Subbuffer<[u32]>,
+    hidden_frames : Subbuffer<[u32]>,
+    // Whether every query was ended since the pool was last reset
+    queried : bool,
+}
+
+impl OcclusionCuller {
+    pub fn new(toolset : &VulkanToolset, object_count : u32) -> Result<OcclusionCuller, EngineError> {
+        let device = &toolset.logical_device;
+        let module = occlusion_cull_cs::load(device.clone())?;
+
+        Ok(OcclusionCuller {
+            shader : ComputeShader::new(&module, LOCAL_SIZE, device.clone()),
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+            queries : OcclusionQueryPool::new(device.clone(), object_count)?,
+            samples : Self::create_buffer(toolset, object_count, Self::predicate_usage(toolset))?,
+            hidden_frames : Self::create_buffer(toolset, object_count, BufferUsage::empty())?,
+            queried : false,
+        })
+    }
+
+    // Record outside of a render pass. Same buffers as FrustumCuller::record, with one slot per query
+    pub fn record_cull(&mut self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, spheres : &Subbuffer<[BoundingSphere]>, frustum : &Frustum, draws : &Subbuffer<[DrawIndirectCommand]>, count : &Subbuffer<u32>) -> Result<(), EngineError> {
+        if spheres.len() > self.queries.query_count() as DeviceSize {
+            return Err(EngineError::BufferRange { offset : 0, len : spheres.len(), buffer_len : self.queries.query_count() as u64 });
+        }
+
+        // Without results from a previous frame every object counts as seen
+        match self.queried {
+            true => self.queries.copy_results(builder, &self.samples)?,
+            false => {
+                builder.fill_buffer(self.samples.clone(), 1)?;
+            },
+        }
+        self.queries.reset(builder)?;
+        self.queried = false;
+
+        let pipeline = &self.shader.pipeline;
+        let layout = pipeline.layout();
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::buffer(0, spheres.clone()),
+                WriteDescriptorSet::buffer(1, draws.clone()),
+                WriteDescriptorSet::buffer(2, count.clone()),
+                WriteDescriptorSet::buffer(3, self.samples.clone()),
+                WriteDescriptorSet::buffer(4, self.hidden_frames.clone()),
+            ],
+            [],
+        )?;
+
+        builder.fill_buffer(count.clone().reinterpret(), 0)?
+        .bind_pipeline_compute(pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?
+        .push_constants(layout.clone(), 0, FrustumPlanes { planes : frustum.planes })?
+        .dispatch(self.shader.group_counts([spheres.len() as u32, 1, 1]))?;
+
+        Ok(())
+    }
+
+    // Record inside a render pass with the pipeline and its buffers bound. Every query is used,
+    // culled slots draw nothing and report no samples
+    pub fn record_draws(&mut self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, draws : &Subbuffer<[DrawIndirectCommand]>) -> Result<(), EngineError> {
+        let slots = draws.len().min(self.queries.query_count() as DeviceSize);
+
+        for slot in 0..self.queries.query_count() {
+            self.queries.begin(builder, slot)?;
+            if (slot as DeviceSize) < slots {
+                builder.draw_indirect(draws.clone().slice(slot as DeviceSize..slot as DeviceSize + 1))?;
+            }
+            self.queries.end(builder, slot)?;
+        }
+        self.queried = true;
+
+        Ok(())
+    }
+
+    // Samples `slot` passed when its query results were last copied, at least 1 before the first
+    // copy. Nonzero while the object is visible, so it can predicate the object's draws with
+    // ConditionalRenderingExt::begin at offset 0 on devices with conditional rendering. That takes a raw
+    // command buffer, vulkano 0.34 has no way to add the block to the frame's AutoCommandBufferBuilder,
+    // so record_draws leaves hidden objects to the instance counts record_cull wrote
+    pub fn predicate(&self, slot : u32) -> Subbuffer<u32> {
+        self.samples.clone().index(slot as DeviceSize)
+    }
+
+    // Query results double as predicates when the device can read them as such
+    fn predicate_usage(toolset : &VulkanToolset) -> BufferUsage {
+        match toolset.conditional_rendering.is_present() {
+            true => BufferUsage::CONDITIONAL_RENDERING,
+            false => BufferUsage::empty(),
+        }
+    }
+
+    fn create_buffer(toolset : &VulkanToolset, len : u32, extra_usage : BufferUsage) -> Result<Subbuffer<[u32]>, EngineError> {
+        let buffer = Buffer::from_iter(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_DST | extra_usage,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            (0..len.max(1)).map(|_| 0u32),
+        )?;
+        toolset.memory_allocator.tracker.track_buffer(AllocationCategory::Storage, buffer.buffer());
+
+        Ok(buffer)
+    }
+}
@@ -0,0 +1,291 @@
+use std::{f32::consts::TAU, sync::Arc};
+
+use vulkano::{
+    buffer::{BufferContents, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
+    image::{sampler::{Sampler, SamplerAddressMode, SamplerCreateInfo}, view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{Pipeline, PipelineBindPoint}
+};
+
+use crate::{error::EngineError, vulkan::{staging::upload_buffer, vulkan::{ComputeShader, VulkanToolset}}};
+
+mod coc_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D depth_map;
+            layout(set = 0, binding = 1, r32f) uniform writeonly image2D coc;
+
+            layout(push_constant) uniform Dof {
+                float focus_distance;
+                float aperture;
+                float max_radius;
+                float near;
+                float far;
+            } dof;
+
+            void main() {
+                ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                if (any(greaterThanEqual(pixel, imageSize(coc)))) {
+                    return;
+                }
+
+                // Same as circle_of_confusion on the CPU
+                float depth = texelFetch(depth_map, pixel, 0).r;
+                float distance = dof.near * dof.far / (dof.far - depth * (dof.far - dof.near));
+                float radius = dof.aperture * abs(distance - dof.focus_distance) / distance;
+
+                imageStore(coc, pixel, vec4(min(radius, dof.max_radius)));
+            }
+        ",
+    }
+}
+
+mod bokeh_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D color;
+            layout(set = 0, binding = 1) uniform sampler2D coc;
+
+            // BOKEH_SAMPLES offsets in the unit disc, center first
+            layout(set = 0, binding = 2) uniform Bokeh {
+                vec4 bokeh_kernel[49];
+            };
+
+            layout(set = 0, binding = 3, rgba16f) uniform writeonly image2D blurred;
+
+            void main() {
+                ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 extent = imageSize(blurred);
+                if (any(greaterThanEqual(pixel, extent))) {
+                    return;
+                }
+
+                float radius = texelFetch(coc, pixel, 0).r;
+                vec2 center = vec2(pixel) + 0.5;
+
+                vec3 sum = vec3(0.0);
+                float weight_sum = 0.0;
+                for (int i = 0; i < bokeh_kernel.length(); i++) {
+                    vec2 offset = bokeh_kernel[i].xy * radius;
+                    vec2 uv = (center + offset) / vec2(extent);
+
+                    // Only samples blurred far enough to reach this pixel count, so sharp
+                    // pixels next to a blurred one don't bleed into it
+                    float sample_radius = textureLod(coc, uv, 0.0).r;
+                    float weight = clamp(sample_radius - length(offset) + 1.0, 0.0, 1.0);
+
+                    sum += textureLod(color, uv, 0.0).rgb * weight;
+                    weight_sum += weight;
+                }
+
+                // The center sample always counts, the sum is never empty
+                imageStore(blurred, pixel, vec4(sum / weight_sum, 1.0));
+            }
+        ",
+    }
+}
+
+const LOCAL_SIZE : [u32; 3] = [8, 8, 1];
+
+// Rings of the bokeh kernel, ring r holds 8 * r samples
+const BOKEH_RINGS : usize = 3;
+
+// Size of the kernel array the blur shader declares
+pub const BOKEH_SAMPLES : usize = 1 + 4 * BOKEH_RINGS * (BOKEH_RINGS + 1);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DofParams {
+    // View distance that stays sharp
+    pub focus_distance : f32,
+    // Blur radius in pixels far behind the focus distance, bigger apertures blur more
+    pub aperture : f32,
+    // Largest blur radius in pixels, reached in front of the focus distance as well
+    pub max_radius : f32,
+    // Planes of the perspective projection the depth was rendered with
+    pub near : f32,
+    pub far : f32,
+}
+
+impl Default for DofParams {
+    fn default() -> Self {
+        DofParams {
+            focus_distance : 5.0,
+            aperture : 6.0,
+            max_radius : 8.0,
+            near : 0.1,
+            far : 100.0,
+        }
+    }
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct DofConstants {
+    focus_distance : f32,
+    aperture : f32,
+    max_radius : f32,
+    near : f32,
+    far : f32,
+}
+
+// Blur radius in pixels for a 0..1 depth buffer value, as the CoC pass computes it
+pub fn circle_of_confusion(depth : f32, params : &DofParams) -> f32 {
+    let distance = params.near * params.far / (params.far - depth * (params.far - params.near));
+    let radius = params.aperture * (distance - params.focus_distance).abs() / distance;
+
+    radius.min(params.max_radius)
+}
+
+// Offsets in the unit disc the blur gathers from, scaled by each pixel's blur radius.
+// Concentric rings spread the samples evenly over the disc, each ring turned half a step against the last
+pub fn bokeh_kernel() -> Vec<[f32; 4]> {
+    let rings = (1..=BOKEH_RINGS).flat_map(|ring| {
+        let count = 8 * ring;
+        let radius = ring as f32 / BOKEH_RINGS as f32;
+
+        (0..count).map(move |i| {
+            let angle = (i as f32 + 0.5 * (ring % 2) as f32) / count as f32 * TAU;
+            [angle.cos() * radius, angle.sin() * radius, 0.0, 0.0]
+        })
+    });
+
+    [[0.0; 4]].into_iter().chain(rings).collect()
+}
+
+// Blurs an HDR image by how far each pixel is from the focus distance. A compute pass turns depth
+// into a circle of confusion radius, a second one gathers a disc of that radius around each pixel.
+// The CoC map is sized for one extent, create a new pass after a resize
+pub struct DepthOfFieldPass {
+    coc_shader : ComputeShader,
+    bokeh_shader : ComputeShader,
+    descriptor_set_allocator : StandardDescriptorSetAllocator,
+    color_sampler : Arc<Sampler>,
+    texel_sampler : Arc<Sampler>,
+    kernel : Subbuffer<[[f32; 4]]>,
+    coc : Arc<ImageView>,
+}
+
+impl DepthOfFieldPass {
+    pub fn new(toolset : &VulkanToolset, extent : [u32; 2]) -> Result<DepthOfFieldPass, EngineError> {
+        let device = &toolset.logical_device;
+        let coc_module = coc_cs::load(device.clone()).expect("failed to create shader module");
+        let bokeh_module = bokeh_cs::load(device.clone()).expect("failed to create shader module");
+
+        let kernel = upload_buffer(&toolset.memory_allocator, &toolset.device_queue, BufferUsage::UNIFORM_BUFFER, bokeh_kernel())?;
+
+        let coc_image = Image::new(
+            toolset.memory_allocator.general_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R32_SFLOAT,
+                extent: [extent[0], extent[1], 1],
+                usage: ImageUsage::STORAGE | ImageUsage::SAMPLED,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        )?;
+
+        // Disc samples of the color are bilinear between pixels. Float formats such as the CoC map
+        // may not support linear filtering, they are read per texel. Samples past the edge repeat it
+        let color_sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+            },
+        )?;
+        let texel_sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )?;
+
+        Ok(DepthOfFieldPass {
+            coc_shader : ComputeShader::new(&coc_module, LOCAL_SIZE, device.clone()),
+            bokeh_shader : ComputeShader::new(&bokeh_module, LOCAL_SIZE, device.clone()),
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+            color_sampler,
+            texel_sampler,
+            kernel,
+            coc : ImageView::new_default(coc_image)?,
+        })
+    }
+
+    // Record outside of a render pass. `color_src` and `depth_src` need SAMPLED usage, `color_dst`
+    // needs STORAGE usage and the R16G16B16A16_SFLOAT format. All three have the pass extent
+    pub fn record(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, color_src : &Arc<Image>, depth_src : &Arc<Image>, color_dst : &Arc<Image>, params : DofParams) -> Result<(), EngineError> {
+        if color_dst.format() != Format::R16G16B16A16_SFLOAT {
+            return Err(EngineError::UnsupportedFeature(format!("depth of field is written as R16G16B16A16_SFLOAT, not {:?}", color_dst.format())));
+        }
+
+        let expected = self.coc.image().extent();
+        for image in [color_src, depth_src, color_dst] {
+            if image.extent() != expected {
+                return Err(EngineError::ImageExtent { expected, actual : image.extent() });
+            }
+        }
+
+        let coc_layout = self.coc_shader.pipeline.layout();
+        let coc_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            coc_layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view_sampler(0, ImageView::new_default(depth_src.clone())?, self.texel_sampler.clone()),
+                WriteDescriptorSet::image_view(1, self.coc.clone()),
+            ],
+            [],
+        )?;
+
+        let bokeh_layout = self.bokeh_shader.pipeline.layout();
+        let bokeh_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            bokeh_layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view_sampler(0, ImageView::new_default(color_src.clone())?, self.color_sampler.clone()),
+                WriteDescriptorSet::image_view_sampler(1, self.coc.clone(), self.texel_sampler.clone()),
+                WriteDescriptorSet::buffer(2, self.kernel.clone()),
+                WriteDescriptorSet::image_view(3, ImageView::new_default(color_dst.clone())?),
+            ],
+            [],
+        )?;
+
+        let constants = DofConstants {
+            focus_distance : params.focus_distance,
+            aperture : params.aperture,
+            max_radius : params.max_radius,
+            near : params.near,
+            far : params.far,
+        };
+
+        let [width, height, _] = expected;
+        let group_counts = self.coc_shader.group_counts([width, height, 1]);
+
+        builder.bind_pipeline_compute(self.coc_shader.pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, coc_layout.clone(), 0, coc_set)?
+        .push_constants(coc_layout.clone(), 0, constants)?
+        .dispatch(group_counts)?
+        .bind_pipeline_compute(self.bokeh_shader.pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, bokeh_layout.clone(), 0, bokeh_set)?
+        .dispatch(group_counts)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, DrawIndirectCommand, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{Pipeline, PipelineBindPoint}
+};
+
+use crate::{error::EngineError, scene::frustum::Frustum, vulkan::vulkan::{ComputeShader, VulkanToolset}};
+
+mod cull_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            struct DrawCommand {
+                uint vertex_count;
+                uint instance_count;
+                uint first_vertex;
+                uint first_instance;
+            };
+
+            struct CullObject {
+                float center[3];
+                float radius;
+                DrawCommand command;
+            };
+
+            layout(set = 0, binding = 0) readonly buffer Objects {
+                CullObject objects[];
+            };
+
+            layout(set = 0, binding = 1) writeonly buffer Draws {
+                DrawCommand draws[];
+            };
+
+            layout(set = 0, binding = 2) buffer Count {
+                uint draw_count;
+            };
+
+            layout(push_constant) uniform Cull {
+                uint object_count;
+                vec4 planes[6];
+            } cull;
+
+            void main() {
+                uint idx = gl_GlobalInvocationID.x;
+                if (idx >= cull.object_count) {
+                    return;
+                }
+
+                CullObject object = objects[idx];
+                vec3 center = vec3(object.center[0], object.center[1], object.center[2]);
+                for (int i = 0; i < 6; i++) {
+                    if (dot(cull.planes[i].xyz, center) + cull.planes[i].w < -object.radius) {
+                        return;
+                    }
+                }
+
+                draws[atomicAdd(draw_count, 1)] = object.command;
+            }
+        ",
+    }
+}
+
+const LOCAL_SIZE : [u32; 3] = [64, 1, 1];
+
+// Bounding sphere and the draw issued when it is inside the frustum
+#[derive(BufferContents, Clone, Copy, Debug)]
+#[repr(C)]
+pub struct CullObject {
+    pub center : [f32; 3],
+    pub radius : f32,
+    pub command : DrawIndirectCommand,
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct CullConstants {
+    object_count : u32,
+    _padding : [u32; 3],
+    planes : [[f32; 4]; 6],
+}
+
+// Compacts the draws of visible objects on the GPU, draw them with VulkanToolset::record_multi_draw_indirect
+pub struct IndirectCulling {
+    shader : ComputeShader,
+    descriptor_set_allocator : StandardDescriptorSetAllocator,
+}
+
+impl IndirectCulling {
+    pub fn new(toolset : &VulkanToolset) -> IndirectCulling {
+        let device = &toolset.logical_device;
+        let module = cull_cs::load(device.clone()).expect("failed to create shader module");
+
+        IndirectCulling {
+            shader : ComputeShader::new(&module, LOCAL_SIZE, device.clone()),
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+        }
+    }
+
+    // Room for one draw per object, the slots past the visible count are cleared every pass
+    pub fn create_draw_buffer(toolset : &VulkanToolset, max_draw_count : u64) -> Result<Subbuffer<[DrawIndirectCommand]>, EngineError> {
+        Ok(Buffer::new_slice(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER | BufferUsage::INDIRECT_BUFFER | BufferUsage::TRANSFER_DST | BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+            max_draw_count,
+        )?)
+    }
+
+    pub fn create_count_buffer(toolset : &VulkanToolset) -> Result<Subbuffer<u32>, EngineError> {
+        Ok(Buffer::new_sized(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_DST | BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        )?)
+    }
+
+    // Record outside of a render pass, before the draws that read the buffers
+    pub fn record(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, objects : &Subbuffer<[CullObject]>, frustum : &Frustum, draws : &Subbuffer<[DrawIndirectCommand]>, count : &Subbuffer<u32>) -> Result<(), EngineError> {
+        let pipeline = &self.shader.pipeline;
+        let layout = pipeline.layout();
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::buffer(0, objects.clone()),
+                WriteDescriptorSet::buffer(1, draws.clone()),
+                WriteDescriptorSet::buffer(2, count.clone()),
+            ],
+            [],
+        )?;
+
+        let constants = CullConstants {
+            object_count : objects.len() as u32,
+            _padding : [0; 3],
+            planes : frustum.planes,
+        };
+
+        // Zero instance counts turn the unused slots into no-ops
+        builder.fill_buffer(draws.clone().reinterpret(), 0)?
+        .fill_buffer(count.clone().reinterpret(), 0)?
+        .bind_pipeline_compute(pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?
+        .push_constants(layout.clone(), 0, constants)?
+        .dispatch(self.shader.group_counts([objects.len() as u32, 1, 1]))?;
+
+        Ok(())
+    }
+}
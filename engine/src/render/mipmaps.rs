@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    device::Device,
+    format::Format,
+    image::{sampler::{Sampler, SamplerAddressMode, SamplerCreateInfo}, Image, ImageLayout},
+    pipeline::{graphics::{depth_stencil::DepthStencilState, input_assembly::InputAssemblyState, rasterization::RasterizationState, vertex_input::VertexInputState, viewport::{Scissor, Viewport}}, GraphicsPipeline, Pipeline, PipelineBindPoint},
+    render_pass::{AttachmentLoadOp, Framebuffer, RenderPass, Subpass}
+};
+
+use crate::{error::EngineError, vulkan::{debug_utils::DebugUtils, render_pass::{create_framebuffer, RenderPassBuilder}, scissor::ScissorState, vulkan::{find_entry_point, MultisampleConfig, PipelineStates, VulkanAllocation, VulkanToolset}, vulkan_window::AttachmentConfig}};
+use super::post_process::fullscreen_vs;
+
+mod downsample_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec2 v_uv;
+            layout(location = 0) out vec4 f_color;
+
+            // A view of the level above only, so lod 0 is that level
+            layout(set = 0, binding = 0) uniform sampler2D source;
+
+            // Each pixel's center lies between four texels of the level above, one linear sample averages them
+            void main() {
+                f_color = textureLod(source, v_uv, 0.0);
+            }
+        ",
+    }
+}
+
+// Fills mip levels by rendering each one from the level above, through views of single levels. An
+// alternative to blitting for formats without blit support, and the render pass and framebuffers
+// also serve for drawing into levels by hand
+pub struct MipmapGenerator {
+    render_pass : Arc<RenderPass>,
+    pipeline : Arc<GraphicsPipeline>,
+    sampler : Arc<Sampler>,
+}
+
+impl MipmapGenerator {
+    // For images of `format`, which needs color attachment and linear filtering support
+    pub fn new(toolset : &VulkanToolset, format : Format) -> Result<MipmapGenerator, EngineError> {
+        let device = &toolset.logical_device;
+        let render_pass = Self::create_render_pass(device, format)?;
+
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+            },
+        )?;
+
+        let vs = find_entry_point(&fullscreen_vs::load(device.clone())?, "main")?;
+        let fs = find_entry_point(&downsample_fs::load(device.clone())?, "main")?;
+        let pipeline = toolset.build_pipeline_for(vec![vs, fs], PipelineStates {
+            // The triangle is generated from gl_VertexIndex
+            vertex_input_state : VertexInputState::new(),
+            input_assembly_state : InputAssemblyState::default(),
+            tessellation_state : None,
+            rasterization_state : RasterizationState::default(),
+            depth_stencil_state : DepthStencilState::default(),
+            push_descriptor_set : None,
+            conservative_raster : None,
+            multisample : MultisampleConfig::default(),
+            // Every level has its own extent
+            dynamic_viewport : true,
+            scissor : ScissorState::Dynamic,
+            color_write_masks : Vec::new(),
+            blend : None,
+            immutable_samplers : Vec::new(),
+            inline_uniform_blocks : Vec::new(),
+        }, Subpass::from(render_pass.clone(), 0).unwrap(), Viewport::default());
+        DebugUtils::name_object(device, &pipeline, "mipmap downsample");
+
+        Ok(MipmapGenerator {
+            render_pass,
+            pipeline,
+            sampler,
+        })
+    }
+
+    // One color attachment that is written in full, so the level's previous contents are never loaded
+    pub fn create_render_pass(device : &Arc<Device>, format : Format) -> Result<Arc<RenderPass>, EngineError> {
+        RenderPassBuilder::new()
+        .attachment(AttachmentConfig {
+            load_op : AttachmentLoadOp::DontCare,
+            initial_layout : ImageLayout::Undefined,
+            ..AttachmentConfig::color(format)
+        })
+        .subpass(&[0], None)
+        .build(device)
+    }
+
+    pub fn render_pass(&self) -> &Arc<RenderPass> {
+        &self.render_pass
+    }
+
+    // Renders into one level of one layer with the level's extent. The image needs COLOR_ATTACHMENT usage
+    pub fn mip_framebuffer(&self, image : &Arc<Image>, mip_level : u32, array_layer : u32) -> Result<Arc<Framebuffer>, EngineError> {
+        create_framebuffer(&self.render_pass, vec![VulkanAllocation::create_mip_view(image, mip_level, array_layer)?])
+    }
+
+    // Writes every level past the first of every layer from the level above it. Record outside of a render
+    // pass once level 0 holds the image. The image needs COLOR_ATTACHMENT and SAMPLED usage
+    pub fn record(&self, toolset : &VulkanToolset, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, image : &Arc<Image>) -> Result<(), EngineError> {
+        let layout = self.pipeline.layout();
+
+        for array_layer in 0..image.array_layers() {
+            for mip_level in 1..image.mip_levels() {
+                let source = VulkanAllocation::create_mip_view(image, mip_level - 1, array_layer)?;
+                let framebuffer = self.mip_framebuffer(image, mip_level, array_layer)?;
+                let descriptor_set = PersistentDescriptorSet::new(
+                    &toolset.memory_allocator.descriptor_set_allocator,
+                    layout.set_layouts()[0].clone(),
+                    [WriteDescriptorSet::image_view_sampler(0, source, self.sampler.clone())],
+                    [],
+                )?;
+
+                let [width, height] = framebuffer.extent();
+                let viewport = Viewport {
+                    offset: [0.0, 0.0],
+                    extent: [width as f32, height as f32],
+                    depth_range: 0.0..=1.0,
+                };
+
+                builder.begin_render_pass(
+                    RenderPassBeginInfo {
+                        clear_values: vec![None],
+                        ..RenderPassBeginInfo::framebuffer(framebuffer)
+                    },
+                    SubpassBeginInfo {
+                        contents: SubpassContents::Inline,
+                        ..Default::default()
+                    },
+                )?
+                .bind_pipeline_graphics(self.pipeline.clone())?
+                .set_viewport(0, [viewport].into_iter().collect())?
+                .set_scissor(0, [Scissor { offset : [0, 0], extent : [width, height] }].into_iter().collect())?
+                .bind_descriptor_sets(PipelineBindPoint::Graphics, layout.clone(), 0, descriptor_set)?
+                .draw(3, 1, 0, 0)?
+                .end_render_pass(SubpassEndInfo::default())?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+pub mod bloom;
+pub mod culling;
+pub mod depth_of_field;
+pub mod indirect;
+pub mod mipmaps;
+pub mod overlay;
+pub mod particles;
+pub mod post_process;
+pub mod sdf;
+pub mod shadow_map;
+pub mod skinning;
+pub mod skybox;
+pub mod split_screen;
+pub mod ssao;
+pub mod ssr;
+pub mod stats_overlay;
+pub mod texture_streaming;
\ No newline at end of file
@@ -0,0 +1,386 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo}, AllocateBufferError, BufferContents, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    image::SampleCount,
+    memory::allocator::MemoryTypeFilter,
+    pipeline::{graphics::{color_blend::{AttachmentBlend, ColorBlendAttachmentState, ColorBlendState}, depth_stencil::DepthStencilState, input_assembly::InputAssemblyState, multisample::MultisampleState, rasterization::RasterizationState, vertex_input::{Vertex, VertexDefinition}, viewport::{Viewport, ViewportState}, GraphicsPipelineCreateInfo}, DynamicState, GraphicsPipeline, Pipeline, PipelineShaderStageCreateInfo},
+    render_pass::{RenderPass, Subpass},
+    shader::EntryPoint
+};
+
+use crate::{error::EngineError, vulkan::vulkan::{find_entry_point, VulkanToolset}};
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec2 position;
+            layout(location = 1) in vec4 color;
+
+            layout(push_constant) uniform Screen {
+                vec2 logical_size;
+            } screen;
+
+            layout(location = 0) out vec4 v_color;
+
+            void main() {
+                // Logical pixels from the top left corner, clip space already points Y down
+                gl_Position = vec4(position / screen.logical_size * 2.0 - 1.0, 0.0, 1.0);
+                v_color = color;
+            }
+        ",
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec4 v_color;
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                f_color = v_color;
+            }
+        ",
+    }
+}
+
+// Font pixels of a glyph, text is drawn with TEXT_SCALE logical pixels per font pixel
+pub const GLYPH_WIDTH : u32 = 5;
+pub const GLYPH_HEIGHT : u32 = 7;
+pub const TEXT_SCALE : f32 = 2.0;
+// One font pixel between glyphs and two between lines
+pub const GLYPH_ADVANCE : f32 = (GLYPH_WIDTH + 1) as f32 * TEXT_SCALE;
+pub const LINE_HEIGHT : f32 = (GLYPH_HEIGHT + 2) as f32 * TEXT_SCALE;
+pub const LINE_WIDTH : f32 = 1.0;
+
+const WHITE : [f32; 4] = [1.0; 4];
+
+// Position in logical pixels from the top left corner of the window
+#[derive(BufferContents, Vertex, Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct OverlayVertex {
+    #[format(R32G32_SFLOAT)]
+    pub position : [f32; 2],
+    #[format(R32G32B32A32_SFLOAT)]
+    pub color : [f32; 4],
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct ScreenConstants {
+    logical_size : [f32; 2],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OverlayPrimitive {
+    // Top left corner of the first line
+    Text { position : [f32; 2], text : String, color : [f32; 4] },
+    Rect { position : [f32; 2], size : [f32; 2], color : [f32; 4] },
+    Line { from : [f32; 2], to : [f32; 2], color : [f32; 4] },
+}
+
+// Screen space text, rectangles and lines drawn over the scene, in logical pixels so they keep their size
+// on HiDPI displays. The engine clears it every frame, see Frame::overlay. Primitives are drawn in order,
+// later ones on top
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Overlay {
+    primitives : Vec<OverlayPrimitive>,
+}
+
+impl Overlay {
+    pub fn new() -> Overlay {
+        Overlay::default()
+    }
+
+    // White text, lines are separated by '\n'. The built-in font only has upper case letters, digits and
+    // common punctuation, lower case is drawn as upper case and anything else as a box
+    pub fn text(&mut self, x : f32, y : f32, text : &str) {
+        self.text_colored(x, y, text, WHITE);
+    }
+
+    pub fn text_colored(&mut self, x : f32, y : f32, text : &str, color : [f32; 4]) {
+        self.primitives.push(OverlayPrimitive::Text { position : [x, y], text : text.to_owned(), color });
+    }
+
+    pub fn rect(&mut self, x : f32, y : f32, width : f32, height : f32, color : [f32; 4]) {
+        self.primitives.push(OverlayPrimitive::Rect { position : [x, y], size : [width, height], color });
+    }
+
+    // LINE_WIDTH wide
+    pub fn line(&mut self, from : [f32; 2], to : [f32; 2], color : [f32; 4]) {
+        self.primitives.push(OverlayPrimitive::Line { from, to, color });
+    }
+
+    pub fn primitives(&self) -> &[OverlayPrimitive] {
+        &self.primitives
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primitives.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.primitives.clear();
+    }
+
+    // Extent of `text` in logical pixels, for a background behind it
+    pub fn text_size(text : &str) -> [f32; 2] {
+        let columns = text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+        let lines = text.lines().count().max(1);
+
+        [
+            (columns as f32 * GLYPH_ADVANCE - TEXT_SCALE).max(0.0),
+            lines as f32 * LINE_HEIGHT - 2.0 * TEXT_SCALE,
+        ]
+    }
+
+    // Triangle list of every primitive in order
+    pub fn vertices(&self) -> Vec<OverlayVertex> {
+        let mut vertices = Vec::new();
+        for primitive in &self.primitives {
+            match primitive {
+                OverlayPrimitive::Text { position, text, color } => push_text(&mut vertices, *position, text, *color),
+                OverlayPrimitive::Rect { position, size, color } => push_quad(&mut vertices, *position, [position[0] + size[0], position[1] + size[1]], *color),
+                OverlayPrimitive::Line { from, to, color } => push_line(&mut vertices, *from, *to, *color),
+            }
+        }
+
+        vertices
+    }
+}
+
+fn push_quad(vertices : &mut Vec<OverlayVertex>, min : [f32; 2], max : [f32; 2], color : [f32; 4]) {
+    let corners = [[min[0], min[1]], [max[0], min[1]], [max[0], max[1]], [min[0], max[1]]];
+    vertices.extend([0, 1, 2, 2, 3, 0].map(|i| OverlayVertex { position : corners[i], color }));
+}
+
+fn push_line(vertices : &mut Vec<OverlayVertex>, from : [f32; 2], to : [f32; 2], color : [f32; 4]) {
+    let direction = [to[0] - from[0], to[1] - from[1]];
+    let length = direction[0].hypot(direction[1]);
+    if length == 0.0 {
+        return;
+    }
+
+    // Half the width to either side of the segment
+    let offset = [-direction[1] / length * LINE_WIDTH * 0.5, direction[0] / length * LINE_WIDTH * 0.5];
+    let corners = [
+        [from[0] + offset[0], from[1] + offset[1]],
+        [to[0] + offset[0], to[1] + offset[1]],
+        [to[0] - offset[0], to[1] - offset[1]],
+        [from[0] - offset[0], from[1] - offset[1]],
+    ];
+    vertices.extend([0, 1, 2, 2, 3, 0].map(|i| OverlayVertex { position : corners[i], color }));
+}
+
+// One quad per horizontal run of set font pixels
+fn push_text(vertices : &mut Vec<OverlayVertex>, position : [f32; 2], text : &str, color : [f32; 4]) {
+    for (line_index, line) in text.lines().enumerate() {
+        let top = position[1] + line_index as f32 * LINE_HEIGHT;
+        for (column, character) in line.chars().enumerate() {
+            let left = position[0] + column as f32 * GLYPH_ADVANCE;
+
+            for (row, bits) in glyph(character).into_iter().enumerate() {
+                let y = top + row as f32 * TEXT_SCALE;
+                let mut x = 0;
+                while x < GLYPH_WIDTH {
+                    let set = |x : u32| bits & (1 << (GLYPH_WIDTH - 1 - x)) != 0;
+                    if !set(x) {
+                        x += 1;
+                        continue;
+                    }
+
+                    let start = x;
+                    while x < GLYPH_WIDTH && set(x) {
+                        x += 1;
+                    }
+                    push_quad(vertices, [left + start as f32 * TEXT_SCALE, y], [left + x as f32 * TEXT_SCALE, y + TEXT_SCALE], color);
+                }
+            }
+        }
+    }
+}
+
+// Rows from the top, the lowest GLYPH_WIDTH bits are the columns with the leftmost first
+pub fn glyph(character : char) -> [u8; GLYPH_HEIGHT as usize] {
+    match character.to_ascii_uppercase() {
+        ' ' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '|' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        '?' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+        _ => [0x1F, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1F],
+    }
+}
+
+// Draws an Overlay at the end of the render pass the scene is drawn in, alpha blended and without a depth
+// test. Vertices go into a transient buffer every frame, the allocator reuses its memory once frames finished
+pub struct OverlayRenderer {
+    vertices : SubbufferAllocator,
+    vs : EntryPoint,
+    fs : EntryPoint,
+    // Built for the render pass drawn into last, post processing draws the scene into another one
+    pipeline : Option<(Arc<RenderPass>, Arc<GraphicsPipeline>)>,
+}
+
+impl OverlayRenderer {
+    pub fn new(toolset : &VulkanToolset) -> Result<OverlayRenderer, EngineError> {
+        let device = &toolset.logical_device;
+        let vertices = SubbufferAllocator::new(
+            toolset.memory_allocator.general_allocator.clone(),
+            SubbufferAllocatorCreateInfo {
+                buffer_usage: BufferUsage::VERTEX_BUFFER,
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+        );
+
+        Ok(OverlayRenderer {
+            vertices,
+            vs : find_entry_point(&vs::load(device.clone())?, "main")?,
+            fs : find_entry_point(&fs::load(device.clone())?, "main")?,
+            pipeline : None,
+        })
+    }
+
+    // Recording for the end of `subpass`, None when there is nothing to draw. The overlay spans
+    // `extent` in framebuffer pixels and `logical_size` in its own coordinates
+    pub fn record_command(&mut self, toolset : &VulkanToolset, overlay : &Overlay, subpass : Subpass, extent : [u32; 2], logical_size : [f32; 2]) -> Result<Option<impl FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) + 'static>, EngineError> {
+        let vertices = overlay.vertices();
+        if vertices.is_empty() {
+            return Ok(None);
+        }
+
+        let buffer = self.vertices.allocate_slice::<OverlayVertex>(vertices.len() as u64)
+        .map_err(AllocateBufferError::AllocateMemory)?;
+        buffer.write()?.copy_from_slice(&vertices);
+
+        let pipeline = self.pipeline(toolset, subpass)?;
+        let viewport = Viewport {
+            offset: [0.0, 0.0],
+            extent: [extent[0] as f32, extent[1] as f32],
+            depth_range: 0.0..=1.0,
+        };
+        let screen = ScreenConstants { logical_size : logical_size.map(|size| size.max(1.0)) };
+
+        Ok(Some(move |builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>| {
+            builder.bind_pipeline_graphics(pipeline.clone())
+            .unwrap()
+            .set_viewport(0, [viewport].into_iter().collect())
+            .unwrap()
+            .push_constants(pipeline.layout().clone(), 0, screen)
+            .unwrap()
+            .bind_vertex_buffers(0, buffer.clone())
+            .unwrap()
+            .draw(buffer.len() as u32, 1, 0, 0)
+            .unwrap();
+        }))
+    }
+
+    fn pipeline(&mut self, toolset : &VulkanToolset, subpass : Subpass) -> Result<Arc<GraphicsPipeline>, EngineError> {
+        if let Some((render_pass, pipeline)) = &self.pipeline {
+            if Arc::ptr_eq(render_pass, subpass.render_pass()) {
+                return Ok(pipeline.clone());
+            }
+        }
+
+        let stages = [self.vs.clone(), self.fs.clone()]
+        .map(PipelineShaderStageCreateInfo::new);
+        let layout = toolset.create_pipeline_layout(&stages, None, &[], &[])?;
+        let vertex_input_state = OverlayVertex::per_vertex()
+        .definition(&self.vs.info().input_interface)?;
+
+        // Depth is neither tested nor written, the overlay always ends up on top
+        let depth_stencil_state = subpass.subpass_desc()
+        .depth_stencil_attachment
+        .as_ref()
+        .map(|_| DepthStencilState::default());
+        let color_blend_state = ColorBlendState {
+            attachments: (0..subpass.num_color_attachments())
+            .map(|_| ColorBlendAttachmentState {
+                blend: Some(AttachmentBlend::alpha()),
+                ..Default::default()
+            })
+            .collect(),
+            ..Default::default()
+        };
+
+        let pipeline = GraphicsPipeline::new(
+            toolset.logical_device.clone(),
+            None,
+            GraphicsPipelineCreateInfo {
+                stages: stages.into_iter().collect(),
+                vertex_input_state: Some(vertex_input_state),
+                input_assembly_state: Some(InputAssemblyState::default()),
+                viewport_state: Some(ViewportState::default()),
+                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                rasterization_state: Some(RasterizationState::default()),
+                depth_stencil_state,
+                multisample_state: Some(MultisampleState {
+                    rasterization_samples: subpass.num_samples().unwrap_or(SampleCount::Sample1),
+                    ..Default::default()
+                }),
+                color_blend_state: Some(color_blend_state),
+                subpass: Some(subpass.clone().into()),
+                ..GraphicsPipelineCreateInfo::layout(layout)
+            },
+        )?;
+        self.pipeline = Some((subpass.render_pass().clone(), pipeline.clone()));
+
+        Ok(pipeline)
+    }
+}
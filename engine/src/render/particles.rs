@@ -0,0 +1,337 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{ComputePipeline, Pipeline, PipelineBindPoint},
+    render_pass::Subpass,
+    shader::ShaderModule
+};
+
+use crate::{engine::Frame, vulkan::{vertex_pull::VertexPullPipeline, vulkan::{ComputeShader, VulkanToolset}}};
+
+mod spawn_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        include: ["assets/shaders/include"],
+        src: r#"
+            #version 460
+
+            #include "common.glsl"
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            struct Particle {
+                float position[3];
+                float velocity[3];
+                float lifetime;
+                float age;
+            };
+
+            layout(set = 0, binding = 0) buffer Particles {
+                Particle particles[];
+            };
+
+            layout(push_constant) uniform Spawn {
+                vec3 emitter;
+                uint seed;
+                float lifetime;
+                float speed;
+            } spawn;
+
+            uint hash(uint x) {
+                x ^= x >> 16;
+                x *= 0x7feb352du;
+                x ^= x >> 15;
+                x *= 0x846ca68bu;
+                x ^= x >> 16;
+                return x;
+            }
+
+            float random(inout uint state) {
+                state = hash(state);
+                return float(state) / 4294967295.0;
+            }
+
+            void main() {
+                uint idx = gl_GlobalInvocationID.x;
+                if (idx >= particles.length() || particles[idx].age > 0.0) {
+                    return;
+                }
+
+                uint state = hash(idx) ^ hash(spawn.seed);
+                float angle = random(state) * TAU;
+                float speed = spawn.speed * (0.25 + random(state) * 0.75);
+
+                particles[idx].position = float[3](spawn.emitter.x, spawn.emitter.y, spawn.emitter.z);
+                particles[idx].velocity = float[3](cos(angle) * speed, sin(angle) * speed - spawn.speed, 0.0);
+                particles[idx].lifetime = spawn.lifetime * (0.5 + random(state) * 0.5);
+                particles[idx].age = particles[idx].lifetime;
+            }
+        "#,
+    }
+}
+
+mod update_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            struct Particle {
+                float position[3];
+                float velocity[3];
+                float lifetime;
+                float age;
+            };
+
+            layout(set = 0, binding = 0) buffer Particles {
+                Particle particles[];
+            };
+
+            layout(push_constant) uniform Update {
+                float delta;
+                float gravity;
+            } update;
+
+            void main() {
+                uint idx = gl_GlobalInvocationID.x;
+                if (idx >= particles.length() || particles[idx].age <= 0.0) {
+                    return;
+                }
+
+                for (int i = 0; i < 3; i++) {
+                    particles[idx].position[i] += particles[idx].velocity[i] * update.delta;
+                }
+                particles[idx].velocity[1] += update.gravity * update.delta;
+                particles[idx].age -= update.delta;
+            }
+        ",
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            struct Particle {
+                float position[3];
+                float velocity[3];
+                float lifetime;
+                float age;
+            };
+
+            layout(set = 0, binding = 0) readonly buffer Particles {
+                Particle particles[];
+            };
+
+            layout(location = 0) out vec4 v_color;
+
+            void main() {
+                Particle particle = particles[gl_VertexIndex];
+                vec3 position = vec3(particle.position[0], particle.position[1], particle.position[2]);
+                float life = clamp(particle.age / particle.lifetime, 0.0, 1.0);
+
+                // Dead particles are moved outside of the clip volume
+                gl_Position = particle.age > 0.0 ? vec4(position, 1.0) : vec4(2.0, 2.0, 2.0, 1.0);
+                gl_PointSize = 1.0;
+                v_color = vec4(1.0, life, 0.2, 1.0);
+            }
+        ",
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec4 v_color;
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                f_color = v_color;
+            }
+        ",
+    }
+}
+
+const LOCAL_SIZE : [u32; 3] = [256, 1, 1];
+
+// Matches the std430 layout of the shaders
+#[derive(BufferContents, Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct Particle {
+    pub position : [f32; 3],
+    pub velocity : [f32; 3],
+    pub lifetime : f32,
+    pub age : f32,
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct SpawnConstants {
+    emitter : [f32; 3],
+    seed : u32,
+    lifetime : f32,
+    speed : f32,
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct UpdateConstants {
+    delta : f32,
+    gravity : f32,
+}
+
+pub struct ParticleSystem {
+    pub particles : Subbuffer<[Particle]>,
+    pub emitter : [f32; 3],
+    pub lifetime : f32,
+    pub speed : f32,
+    pub gravity : f32,
+    spawn_pipeline : Arc<ComputePipeline>,
+    update_pipeline : Arc<ComputePipeline>,
+    group_counts : [u32; 3],
+    spawn_set : Arc<PersistentDescriptorSet>,
+    update_set : Arc<PersistentDescriptorSet>,
+    vertex_shader : Arc<ShaderModule>,
+    fragment_shader : Arc<ShaderModule>,
+    draw_pipeline : VertexPullPipeline,
+    draw_set : Arc<PersistentDescriptorSet>,
+    seed : u32,
+}
+
+impl ParticleSystem {
+    pub fn new(toolset : &VulkanToolset, count : u32, emitter : [f32; 3]) -> ParticleSystem {
+        let device = &toolset.logical_device;
+
+        // Every particle starts dead, so the first spawn pass initializes all of them.
+        // Written by the compute passes and pulled by the vertex shader of the draw
+        let particles = Buffer::from_iter(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            (0..count).map(|_| Particle::default()),
+        ).expect("failed to create particle buffer");
+
+        let spawn_shader = spawn_cs::load(device.clone()).expect("failed to create shader module");
+        let update_shader = update_cs::load(device.clone()).expect("failed to create shader module");
+        let spawn = ComputeShader::new(&spawn_shader, LOCAL_SIZE, device.clone());
+        let update = ComputeShader::new(&update_shader, LOCAL_SIZE, device.clone());
+        let group_counts = spawn.group_counts([count, 1, 1]);
+        let spawn_pipeline = spawn.pipeline;
+        let update_pipeline = update.pipeline;
+
+        let vertex_shader = vs::load(device.clone()).expect("failed to create shader module");
+        let fragment_shader = fs::load(device.clone()).expect("failed to create shader module");
+        let draw_pipeline = Self::create_draw_pipeline(toolset, count, &vertex_shader, &fragment_shader);
+
+        let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone(), Default::default());
+        let spawn_set = Self::storage_set(&descriptor_set_allocator, spawn_pipeline.as_ref(), &particles);
+        let update_set = Self::storage_set(&descriptor_set_allocator, update_pipeline.as_ref(), &particles);
+        let draw_set = Self::storage_set(&descriptor_set_allocator, draw_pipeline.pipeline.as_ref(), &particles);
+
+        ParticleSystem {
+            particles,
+            emitter,
+            lifetime : 2.0,
+            speed : 0.6,
+            gravity : 0.8,
+            spawn_pipeline,
+            update_pipeline,
+            group_counts,
+            spawn_set,
+            update_set,
+            vertex_shader,
+            fragment_shader,
+            draw_pipeline,
+            draw_set,
+            seed : 0,
+        }
+    }
+
+    // Viewport is baked into the pipeline, call after the swapchain was recreated.
+    // The layout stays the same, so the descriptor set is kept
+    pub fn recreate_pipeline(&mut self, toolset : &VulkanToolset) {
+        self.draw_pipeline = Self::create_draw_pipeline(toolset, self.draw_pipeline.count, &self.vertex_shader, &self.fragment_shader);
+    }
+
+    // Queued as compute passes, so a draw later in the same frame sees the updated particles
+    pub fn simulate(&mut self, frame : &mut Frame, delta : f32) {
+        self.seed = self.seed.wrapping_add(1);
+
+        let spawn = SpawnConstants {
+            emitter : self.emitter,
+            seed : self.seed,
+            lifetime : self.lifetime,
+            speed : self.speed,
+        };
+        let update = UpdateConstants {
+            delta,
+            gravity : self.gravity,
+        };
+
+        let group_counts = self.group_counts;
+        let spawn_pipeline = self.spawn_pipeline.clone();
+        let spawn_set = self.spawn_set.clone();
+        let update_pipeline = self.update_pipeline.clone();
+        let update_set = self.update_set.clone();
+
+        frame.compute(move |builder| {
+            builder.bind_pipeline_compute(spawn_pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(PipelineBindPoint::Compute, spawn_pipeline.layout().clone(), 0, spawn_set)
+            .unwrap()
+            .push_constants(spawn_pipeline.layout().clone(), 0, spawn)
+            .unwrap()
+            .dispatch(group_counts)
+            .unwrap()
+            .bind_pipeline_compute(update_pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(PipelineBindPoint::Compute, update_pipeline.layout().clone(), 0, update_set)
+            .unwrap()
+            .push_constants(update_pipeline.layout().clone(), 0, update)
+            .unwrap()
+            .dispatch(group_counts)
+            .unwrap();
+        });
+    }
+
+    // One point per particle, read straight from the buffer the compute passes wrote
+    pub fn draw(&self, frame : &mut Frame) {
+        self.draw_pipeline.draw(frame, self.draw_set.clone());
+    }
+
+    fn create_draw_pipeline(toolset : &VulkanToolset, count : u32, vs : &Arc<ShaderModule>, fs : &Arc<ShaderModule>) -> VertexPullPipeline {
+        let window = toolset.get_vulkan_window();
+        let subpass = Subpass::from(window.get_render_pass(), 0).unwrap();
+
+        VertexPullPipeline::new(toolset, subpass, window.get_window_viewport(), 0, count, vs, fs)
+        .expect("failed to create particle pipeline")
+    }
+
+    fn storage_set(allocator : &StandardDescriptorSetAllocator, pipeline : &dyn Pipeline, particles : &Subbuffer<[Particle]>) -> Arc<PersistentDescriptorSet> {
+        let layout = pipeline.layout().set_layouts().first().unwrap();
+
+        PersistentDescriptorSet::new(
+            allocator,
+            layout.clone(),
+            [WriteDescriptorSet::buffer(0, particles.clone())],
+            [],
+        ).unwrap()
+    }
+}
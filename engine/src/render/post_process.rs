@@ -0,0 +1,598 @@
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    descriptor_set::{layout::DescriptorType, PersistentDescriptorSet, WriteDescriptorSet},
+    format::{ClearValue, Format, NumericFormat},
+    image::{sampler::{Sampler, SamplerAddressMode, SamplerCreateInfo}, view::ImageView, ImageAspects, ImageLayout, ImageUsage, SampleCount},
+    pipeline::{graphics::{depth_stencil::DepthStencilState, input_assembly::InputAssemblyState, rasterization::RasterizationState, vertex_input::VertexInputState, viewport::{Scissor, Viewport}}, GraphicsPipeline, Pipeline, PipelineBindPoint},
+    render_pass::{AttachmentLoadOp, Framebuffer, RenderPass, Subpass},
+    shader::ShaderModule
+};
+
+use crate::{error::EngineError, render::bloom::{BloomConfig, BloomPass}, vulkan::{debug_utils::DebugUtils, format_utils::{FormatNegotiator, OutputMode}, render_pass::{create_framebuffer, RenderPassBuilder}, render_target_pool::PooledRenderTarget, scissor::ScissorState, shader_interface::ShaderInterface, vulkan::{find_entry_point, MultisampleConfig, PipelineStates, VulkanToolset}, vulkan_window::{AttachmentConfig, VulkanWindow}}};
+
+pub(super) mod fullscreen_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) out vec2 v_uv;
+
+            // One triangle covering the screen, uv is 0..1 across it
+            void main() {
+                v_uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+                gl_Position = vec4(v_uv * 2.0 - 1.0, 0.0, 1.0);
+            }
+        ",
+    }
+}
+
+mod copy_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec2 v_uv;
+            layout(location = 0) out vec4 f_color;
+
+            layout(set = 0, binding = 0) uniform sampler2D source;
+
+            void main() {
+                f_color = texture(source, v_uv);
+            }
+        ",
+    }
+}
+
+mod tonemap_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec2 v_uv;
+            layout(location = 0) out vec4 f_color;
+
+            layout(set = 0, binding = 0) uniform sampler2D source;
+
+            // x is the exposure, y the gamma
+            layout(push_constant) uniform Params {
+                vec4 params;
+            };
+
+            // Same as tonemap on the CPU
+            void main() {
+                vec4 color = texture(source, v_uv);
+                vec3 x = color.rgb * params.x;
+                vec3 mapped = clamp(x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+
+                f_color = vec4(pow(mapped, vec3(1.0 / params.y)), color.a);
+            }
+        ",
+    }
+}
+
+mod output_tonemap_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec2 v_uv;
+            layout(location = 0) out vec4 f_color;
+
+            layout(set = 0, binding = 0) uniform sampler2D source;
+
+            // x is the exposure, y paper white and z the peak in nits, w 1 for HDR10 and 2 for scRGB
+            layout(push_constant) uniform Params {
+                vec4 params;
+            };
+
+            // Columns of the Rec. 709 to Rec. 2020 matrix
+            const mat3 REC709_TO_REC2020 = mat3(
+                0.6274, 0.0691, 0.0164,
+                0.3293, 0.9195, 0.0880,
+                0.0433, 0.0114, 0.8956
+            );
+
+            vec3 pq(vec3 nits) {
+                vec3 y = pow(clamp(nits / 10000.0, 0.0, 1.0), vec3(0.1593017578125));
+                return pow((0.8359375 + 18.8515625 * y) / (1.0 + 18.6875 * y), vec3(78.84375));
+            }
+
+            // Same as output_tonemap on the CPU
+            void main() {
+                vec4 color = texture(source, v_uv);
+                float range = params.z / params.y;
+                vec3 x = color.rgb * params.x / range;
+                vec3 nits = clamp(x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0) * range * params.y;
+
+                f_color = vec4(params.w < 1.5 ? pq(REC709_TO_REC2020 * nits) : nits / 80.0, color.a);
+            }
+        ",
+    }
+}
+
+// Name the built-in tonemap effect is added under, see PostProcessChain::add_tonemap
+pub const TONEMAP : &str = "tonemap";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TonemapParams {
+    // Scene color is multiplied by it before the curve is applied
+    pub exposure : f32,
+    // 1.0 leaves the curve's output linear
+    pub gamma : f32,
+}
+
+impl TonemapParams {
+    // sRGB formats encode on write, only linear UNORM targets need the gamma applied in the shader
+    pub fn for_format(exposure : f32, format : Format) -> TonemapParams {
+        TonemapParams {
+            exposure,
+            gamma : match format.numeric_format_color() {
+                Some(NumericFormat::SRGB) => 1.0,
+                _ => 2.2,
+            },
+        }
+    }
+}
+
+impl From<TonemapParams> for [f32; 4] {
+    fn from(params : TonemapParams) -> Self {
+        [params.exposure, params.gamma, 0.0, 0.0]
+    }
+}
+
+// ACES filmic curve fit by Krzysztof Narkowicz, as the tonemap effect applies it per channel
+pub fn tonemap(color : [f32; 3], params : &TonemapParams) -> [f32; 3] {
+    color.map(|channel| {
+        let x = channel * params.exposure;
+        let mapped = (x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0);
+
+        mapped.powf(1.0 / params.gamma)
+    })
+}
+
+// Brightness scRGB encodes as 1.0, in nits
+pub const SCRGB_WHITE_NITS : f32 = 80.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutputTonemapParams {
+    pub exposure : f32,
+    // Brightness tonemapped white is shown at on an HDR display, in nits
+    pub paper_white_nits : f32,
+    // Brightest the display gets, highlights roll off towards it. Setting it to paper white gives the SDR look
+    pub peak_nits : f32,
+    pub mode : OutputMode,
+}
+
+impl OutputTonemapParams {
+    // Vulkan doesn't report the display's peak brightness, 1000 nits suits most HDR displays
+    pub fn new(exposure : f32, mode : OutputMode) -> OutputTonemapParams {
+        OutputTonemapParams {
+            exposure,
+            paper_white_nits : 200.0,
+            peak_nits : 1000.0,
+            mode,
+        }
+    }
+
+    // Highlights clipped at paper white with the same encoding, to compare against SDR on an HDR display
+    pub fn sdr_preview(self) -> OutputTonemapParams {
+        OutputTonemapParams { peak_nits : self.paper_white_nits, ..self }
+    }
+}
+
+impl From<OutputTonemapParams> for [f32; 4] {
+    fn from(params : OutputTonemapParams) -> Self {
+        let mode = match params.mode {
+            OutputMode::Sdr => 0.0,
+            OutputMode::Hdr10 => 1.0,
+            OutputMode::ScRgb => 2.0,
+        };
+
+        [params.exposure, params.paper_white_nits, params.peak_nits, mode]
+    }
+}
+
+// SMPTE ST 2084 (PQ) encoding of an absolute brightness, 10000 nits is 1.0
+pub fn pq_encode(nits : f32) -> f32 {
+    let y = (nits / 10000.0).clamp(0.0, 1.0).powf(0.159_301_76);
+    ((0.835_937_5 + 18.851_563 * y) / (1.0 + 18.6875 * y)).powf(78.84375)
+}
+
+// HDR10 takes Rec. 2020 primaries, the engine renders in Rec. 709
+pub fn rec709_to_rec2020(color : [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = color;
+    [
+        0.6274 * r + 0.3293 * g + 0.0433 * b,
+        0.0691 * r + 0.9195 * g + 0.0114 * b,
+        0.0164 * r + 0.0880 * g + 0.8956 * b,
+    ]
+}
+
+// The tonemap curve stretched so it saturates at the peak instead of paper white, then encoded for the
+// output mode. SDR output is the plain curve, gamma is up to the format as with TonemapParams
+pub fn output_tonemap(color : [f32; 3], params : &OutputTonemapParams) -> [f32; 3] {
+    let range = match params.mode {
+        OutputMode::Sdr => 1.0,
+        _ => params.peak_nits / params.paper_white_nits,
+    };
+    let mapped = tonemap(color, &TonemapParams { exposure : params.exposure / range, gamma : 1.0 });
+    let nits = mapped.map(|channel| channel * range * params.paper_white_nits);
+
+    match params.mode {
+        OutputMode::Sdr => mapped,
+        OutputMode::Hdr10 => rec709_to_rec2020(nits).map(pq_encode),
+        OutputMode::ScRgb => nits.map(|channel| channel / SCRGB_WHITE_NITS),
+    }
+}
+
+// Fullscreen pass of a chain. The fragment shader gets `v_uv` at location 0 and samples the
+// previous pass from a sampler2D at set 0 binding 0. A push constant block of up to one vec4 gets `params`
+pub struct PostEffect {
+    name : String,
+    pub enabled : bool,
+    pub params : [f32; 4],
+    // Writes the next intermediate target
+    intermediate : Arc<GraphicsPipeline>,
+    // Writes the chain's output, used while this is the last enabled effect
+    output : Arc<GraphicsPipeline>,
+}
+
+impl PostEffect {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+// Bloom over the scene before the first effect, see PostProcessChain::add_bloom
+pub struct PostBloom {
+    pub enabled : bool,
+    pub config : BloomConfig,
+    // Sized for the chain's extent, recreated by PostProcessChain::resize
+    pass : BloomPass,
+    // Scene with the glow added, what the effects sample instead of the scene
+    output : PooledRenderTarget,
+}
+
+// Images of one extent, recreated by PostProcessChain::resize
+// Their color images come from the render target pool and go back to it when replaced
+struct PostTargets {
+    scene : Arc<Framebuffer>,
+    // Single sampled scene color, multisampled scenes are resolved into it
+    scene_color : PooledRenderTarget,
+    // Effects ping-pong between these, the last one writes the output instead
+    intermediates : [Arc<Framebuffer>; 2],
+    intermediate_colors : [PooledRenderTarget; 2],
+}
+
+// Renders the scene into an offscreen target, then runs fullscreen effects over it in order,
+// each one sampling the previous result, with the last enabled effect writing the output.
+// Pipelines have a dynamic viewport, so toggling effects or resizing never rebuilds them
+pub struct PostProcessChain {
+    format : Format,
+    depth_format : Format,
+    samples : u32,
+    extent : [u32; 2],
+    scene_render_pass : Arc<RenderPass>,
+    intermediate_render_pass : Arc<RenderPass>,
+    output : Subpass,
+    vertex_shader : Arc<ShaderModule>,
+    sampler : Arc<Sampler>,
+    effects : Vec<PostEffect>,
+    bloom : Option<PostBloom>,
+    // Copies the scene to the output when no effect is enabled
+    passthrough : PostEffect,
+    targets : PostTargets,
+}
+
+impl PostProcessChain {
+    // The scene and the intermediate targets use `format`, which needs color attachment and
+    // linear filtering support, such as R16G16B16A16_SFLOAT for HDR. The scene pass has a depth
+    // attachment and the sample count of `output`, like the window's pass
+    pub fn new(toolset : &VulkanToolset, output : Subpass, extent : [u32; 2], format : Format) -> Result<PostProcessChain, EngineError> {
+        let device = &toolset.logical_device;
+        let depth_format = FormatNegotiator::pick_depth_format(device.physical_device());
+        let samples = output.num_samples().map_or(1, |samples| samples as u32);
+
+        let scene_render_pass = VulkanWindow::render_pass_builder(format, depth_format, samples, ImageLayout::ShaderReadOnlyOptimal)
+        .build(device)?;
+
+        // Effects cover every pixel, so the previous contents are never loaded
+        let intermediate_render_pass = RenderPassBuilder::new()
+        .attachment(AttachmentConfig {
+            load_op : AttachmentLoadOp::DontCare,
+            initial_layout : ImageLayout::Undefined,
+            ..AttachmentConfig::color(format)
+        })
+        .subpass(&[0], None)
+        .build(device)?;
+
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+            },
+        )?;
+
+        let vertex_shader = fullscreen_vs::load(device.clone())?;
+        let copy = copy_fs::load(device.clone())?;
+        let passthrough = Self::create_effect(toolset, &vertex_shader, "passthrough", &copy, &intermediate_render_pass, &output)?;
+        let targets = Self::create_targets(toolset, &scene_render_pass, &intermediate_render_pass, format, depth_format, samples, extent)?;
+
+        Ok(PostProcessChain {
+            format,
+            depth_format,
+            samples,
+            extent,
+            scene_render_pass,
+            intermediate_render_pass,
+            output,
+            vertex_shader,
+            sampler,
+            effects : Vec::new(),
+            bloom : None,
+            passthrough,
+            targets,
+        })
+    }
+
+    // Chain presenting into the window's framebuffers at the window's size
+    pub fn for_window(toolset : &VulkanToolset, format : Format) -> Result<PostProcessChain, EngineError> {
+        let window = toolset.get_vulkan_window();
+        let size = window.physical_size();
+
+        Self::new(toolset, Subpass::from(window.get_render_pass(), 0).unwrap(), [size.width, size.height], format)
+    }
+
+    // Appended enabled, after the effects added before it. Fails when `fs` doesn't sample set 0 binding 0
+    pub fn add_effect(&mut self, toolset : &VulkanToolset, name : &str, fs : &Arc<ShaderModule>) -> Result<(), EngineError> {
+        let effect = Self::create_effect(toolset, &self.vertex_shader, name, fs, &self.intermediate_render_pass, &self.output)?;
+        self.effects.push(effect);
+
+        Ok(())
+    }
+
+    // Built-in ACES tonemap with gamma, named TONEMAP. Add it last when the output is an 8 bit format
+    pub fn add_tonemap(&mut self, toolset : &VulkanToolset, exposure : f32) -> Result<(), EngineError> {
+        let fs = tonemap_fs::load(toolset.logical_device.clone())?;
+        self.add_effect(toolset, TONEMAP, &fs)?;
+
+        let color = self.output.subpass_desc().color_attachments[0].as_ref().unwrap().attachment;
+        let output_format = self.output.render_pass().attachments()[color as usize].format;
+        self.effect_mut(TONEMAP).unwrap().params = TonemapParams::for_format(exposure, output_format).into();
+
+        Ok(())
+    }
+
+    // Tonemap for the display the chain presents to, such as VulkanWindow::output_mode, also named TONEMAP.
+    // SDR output gets add_tonemap instead. Assign OutputTonemapParams to the effect's params to change them
+    pub fn add_output_tonemap(&mut self, toolset : &VulkanToolset, params : OutputTonemapParams) -> Result<(), EngineError> {
+        if !params.mode.is_hdr() {
+            return self.add_tonemap(toolset, params.exposure);
+        }
+
+        let fs = output_tonemap_fs::load(toolset.logical_device.clone())?;
+        self.add_effect(toolset, TONEMAP, &fs)?;
+        self.effect_mut(TONEMAP).unwrap().params = params.into();
+
+        Ok(())
+    }
+
+    // Glow around the scene's pixels brighter than config.threshold, added before the first effect runs.
+    // Put a tonemap after it, the glow pushes values further above 1.0
+    pub fn add_bloom(&mut self, toolset : &VulkanToolset, config : BloomConfig) -> Result<(), EngineError> {
+        let (pass, output) = Self::create_bloom_targets(toolset, self.extent)?;
+        self.bloom = Some(PostBloom {
+            enabled : true,
+            config,
+            pass,
+            output,
+        });
+
+        Ok(())
+    }
+
+    // Toggle the bloom or change its config through this, None until add_bloom
+    pub fn bloom_mut(&mut self) -> Option<&mut PostBloom> {
+        self.bloom.as_mut()
+    }
+
+    pub fn effects(&self) -> &[PostEffect] {
+        &self.effects
+    }
+
+    // First effect called `name`, toggle it or change its params through this
+    pub fn effect_mut(&mut self, name : &str) -> Option<&mut PostEffect> {
+        self.effects.iter_mut().find(|effect| effect.name == name)
+    }
+
+    // Recreates the targets at the new size, effects and pipelines are kept. Trims the render target pool
+    pub fn resize(&mut self, toolset : &VulkanToolset, extent : [u32; 2]) -> Result<(), EngineError> {
+        self.targets = Self::create_targets(toolset, &self.scene_render_pass, &self.intermediate_render_pass, self.format, self.depth_format, self.samples, extent)?;
+        if let Some(bloom) = self.bloom.as_mut() {
+            (bloom.pass, bloom.output) = Self::create_bloom_targets(toolset, extent)?;
+        }
+        self.extent = extent;
+        // The replaced targets went back to the pool, nothing acquires them at the old size again
+        toolset.memory_allocator.render_targets.trim();
+
+        Ok(())
+    }
+
+    pub fn extent(&self) -> [u32; 2] {
+        self.extent
+    }
+
+    // Subpass scene pipelines are built for, it matches the window's pass apart from the color format
+    pub fn scene_subpass(&self) -> Subpass {
+        Subpass::from(self.scene_render_pass.clone(), 0).unwrap()
+    }
+
+    // Attachments are laid out like the window's framebuffers, so the same clear values apply
+    pub fn scene_framebuffer(&self) -> &Arc<Framebuffer> {
+        &self.targets.scene
+    }
+
+    // Record after the scene pass ended, outside of a render pass. `output` is a framebuffer of the
+    // output subpass's render pass with the chain's extent, its cleared attachments start out black
+    pub fn record(&self, toolset : &VulkanToolset, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, output : &Arc<Framebuffer>) -> Result<(), EngineError> {
+        let expected = [self.extent[0], self.extent[1], 1];
+        if output.extent() != self.extent {
+            return Err(EngineError::ImageExtent { expected, actual : [output.extent()[0], output.extent()[1], 1] });
+        }
+
+        let mut enabled = self.effects.iter().filter(|effect| effect.enabled).collect::<Vec<_>>();
+        if enabled.is_empty() {
+            enabled.push(&self.passthrough);
+        }
+
+        let mut source = self.targets.scene_color.view().clone();
+        if let Some(bloom) = self.bloom.as_ref().filter(|bloom| bloom.enabled) {
+            toolset.debug_labels.labeled(builder, "bloom", |builder| bloom.pass.record(builder, source.image(), bloom.output.image(), bloom.config))?;
+            source = bloom.output.view().clone();
+        }
+
+        let last = enabled.len() - 1;
+        for (i, effect) in enabled.into_iter().enumerate() {
+            let (pipeline, framebuffer) = match i == last {
+                true => (&effect.output, output),
+                false => (&effect.intermediate, &self.targets.intermediates[i % 2]),
+            };
+
+            toolset.debug_labels.labeled(builder, &effect.name, |builder| self.record_effect(toolset, builder, effect, pipeline, framebuffer, source))?;
+            source = self.targets.intermediate_colors[i % 2].view().clone();
+        }
+
+        Ok(())
+    }
+
+    fn record_effect(&self, toolset : &VulkanToolset, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, effect : &PostEffect, pipeline : &Arc<GraphicsPipeline>, framebuffer : &Arc<Framebuffer>, source : Arc<ImageView>) -> Result<(), EngineError> {
+        let layout = pipeline.layout();
+        let descriptor_set = PersistentDescriptorSet::new(
+            &toolset.memory_allocator.descriptor_set_allocator,
+            layout.set_layouts()[0].clone(),
+            [WriteDescriptorSet::image_view_sampler(0, source, self.sampler.clone())],
+            [],
+        )?;
+
+        let [width, height] = framebuffer.extent();
+        let viewport = Viewport {
+            offset: [0.0, 0.0],
+            extent: [width as f32, height as f32],
+            depth_range: 0.0..=1.0,
+        };
+
+        builder.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: clear_values(framebuffer.render_pass()),
+                ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
+            },
+            SubpassBeginInfo {
+                contents: SubpassContents::Inline,
+                ..Default::default()
+            },
+        )?
+        .bind_pipeline_graphics(pipeline.clone())?
+        .set_viewport(0, [viewport].into_iter().collect())?
+        .set_scissor(0, [Scissor { offset : [0, 0], extent : [width, height] }].into_iter().collect())?
+        .bind_descriptor_sets(PipelineBindPoint::Graphics, layout.clone(), 0, descriptor_set)?;
+
+        if !layout.push_constant_ranges().is_empty() {
+            builder.push_constants(layout.clone(), 0, effect.params)?;
+        }
+
+        builder.draw(3, 1, 0, 0)?
+        .end_render_pass(SubpassEndInfo::default())?;
+
+        Ok(())
+    }
+
+    fn create_effect(toolset : &VulkanToolset, vertex_shader : &Arc<ShaderModule>, name : &str, fs : &Arc<ShaderModule>, intermediate_render_pass : &Arc<RenderPass>, output : &Subpass) -> Result<PostEffect, EngineError> {
+        let vs = find_entry_point(vertex_shader, "main")?;
+        let fs = find_entry_point(fs, "main")?;
+
+        let samples_source = ShaderInterface::inspect_entry_point(&fs).descriptor_bindings.iter()
+        .any(|info| info.set == 0 && info.binding == 0 && info.descriptor_types.contains(&DescriptorType::CombinedImageSampler));
+        if !samples_source {
+            return Err(EngineError::UndeclaredBinding { set : 0, binding : 0 });
+        }
+
+        let pipeline = |subpass : Subpass| {
+            let pipeline = toolset.build_pipeline_for(vec![vs.clone(), fs.clone()], PipelineStates {
+                // The triangle is generated from gl_VertexIndex
+                vertex_input_state : VertexInputState::new(),
+                input_assembly_state : InputAssemblyState::default(),
+                tessellation_state : None,
+                rasterization_state : RasterizationState::default(),
+                depth_stencil_state : DepthStencilState::default(),
+                push_descriptor_set : None,
+                conservative_raster : None,
+                multisample : MultisampleConfig::default(),
+                dynamic_viewport : true,
+                scissor : ScissorState::Dynamic,
+                color_write_masks : Vec::new(),
+                blend : None,
+                immutable_samplers : Vec::new(),
+                inline_uniform_blocks : Vec::new(),
+            }, subpass, Viewport::default());
+            DebugUtils::name_object(&toolset.logical_device, &pipeline, &format!("{name} post effect"));
+
+            pipeline
+        };
+
+        Ok(PostEffect {
+            name : name.to_owned(),
+            enabled : true,
+            params : [0.0; 4],
+            intermediate : pipeline(Subpass::from(intermediate_render_pass.clone(), 0).unwrap()),
+            output : pipeline(output.clone()),
+        })
+    }
+
+    fn create_targets(toolset : &VulkanToolset, scene_render_pass : &Arc<RenderPass>, intermediate_render_pass : &Arc<RenderPass>, format : Format, depth_format : Format, samples : u32, extent : [u32; 2]) -> Result<PostTargets, EngineError> {
+        let allocator = &toolset.memory_allocator.general_allocator;
+        let color_target = |name : &str| -> Result<PooledRenderTarget, EngineError> {
+            let target = toolset.memory_allocator.render_targets.acquire(format, extent, ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED, SampleCount::Sample1)?;
+            DebugUtils::name_object(&toolset.logical_device, target.image().as_ref(), name);
+
+            Ok(target)
+        };
+
+        let scene_color = color_target("post process scene target")?;
+        let scene = create_framebuffer(scene_render_pass, VulkanWindow::create_attachments(allocator, scene_color.image(), depth_format, samples))?;
+
+        let intermediate_colors = [color_target("post process intermediate target")?, color_target("post process intermediate target")?];
+        let intermediate = |target : &PooledRenderTarget| create_framebuffer(intermediate_render_pass, vec![target.view().clone()]);
+
+        Ok(PostTargets {
+            scene,
+            scene_color,
+            intermediates : [intermediate(&intermediate_colors[0])?, intermediate(&intermediate_colors[1])?],
+            intermediate_colors,
+        })
+    }
+
+    fn create_bloom_targets(toolset : &VulkanToolset, extent : [u32; 2]) -> Result<(BloomPass, PooledRenderTarget), EngineError> {
+        let output = toolset.memory_allocator.render_targets.acquire(Format::R16G16B16A16_SFLOAT, extent, ImageUsage::STORAGE | ImageUsage::SAMPLED, SampleCount::Sample1)?;
+        DebugUtils::name_object(&toolset.logical_device, output.image().as_ref(), "post process bloom target");
+
+        Ok((BloomPass::new(toolset, extent)?, output))
+    }
+}
+
+// Black for cleared color attachments and the far plane for cleared depth, nothing for the rest
+fn clear_values(render_pass : &Arc<RenderPass>) -> Vec<Option<ClearValue>> {
+    render_pass.attachments().iter()
+    .map(|attachment| match attachment.load_op {
+        AttachmentLoadOp::Clear if attachment.format.aspects().intersects(ImageAspects::DEPTH | ImageAspects::STENCIL) => Some(ClearValue::DepthStencil((1.0, 0))),
+        AttachmentLoadOp::Clear => Some([0.0, 0.0, 0.0, 1.0].into()),
+        _ => None,
+    })
+    .collect()
+}
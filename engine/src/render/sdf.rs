@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
+    image::{sampler::{Sampler, SamplerCreateInfo}, view::ImageView, Image},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    pipeline::{Pipeline, PipelineBindPoint}
+};
+
+use crate::{error::EngineError, vulkan::vulkan::{ComputeShader, VulkanToolset}};
+
+mod jump_flood_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D glyphs;
+
+            // Nearest glyph pixel in xy and nearest empty pixel in zw, -1 until one is found
+            layout(set = 0, binding = 1) readonly buffer Source {
+                ivec4 source[];
+            };
+
+            layout(set = 0, binding = 2) writeonly buffer Destination {
+                ivec4 destination[];
+            };
+
+            layout(set = 0, binding = 3, r32f) uniform writeonly image2D sdf;
+
+            layout(push_constant) uniform Pass {
+                ivec2 extent;
+                int stage;
+                int jump;
+            } pass;
+
+            const int STAGE_SEED = 0;
+            const int STAGE_JUMP = 1;
+
+            const ivec2 NO_SEED = ivec2(-1);
+
+            ivec2 nearer(ivec2 pixel, ivec2 current, ivec2 candidate) {
+                if (candidate.x < 0) {
+                    return current;
+                }
+                if (current.x < 0) {
+                    return candidate;
+                }
+
+                ivec2 to_current = current - pixel;
+                ivec2 to_candidate = candidate - pixel;
+                return dot(to_candidate, to_candidate) < dot(to_current, to_current) ? candidate : current;
+            }
+
+            // Distance to the edge half way between a pixel center and the seed
+            float edge_distance(ivec2 pixel, ivec2 seed) {
+                return seed.x < 0 ? float(max(pass.extent.x, pass.extent.y)) : length(vec2(seed - pixel)) - 0.5;
+            }
+
+            void main() {
+                ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                if (any(greaterThanEqual(pixel, pass.extent))) {
+                    return;
+                }
+                int idx = pixel.y * pass.extent.x + pixel.x;
+
+                if (pass.stage == STAGE_SEED) {
+                    bool inside = texelFetch(glyphs, pixel, 0).r >= 0.5;
+                    destination[idx] = inside ? ivec4(pixel, NO_SEED) : ivec4(NO_SEED, pixel);
+                    return;
+                }
+
+                ivec4 seeds = source[idx];
+
+                if (pass.stage == STAGE_JUMP) {
+                    ivec2 inside = seeds.xy;
+                    ivec2 outside = seeds.zw;
+
+                    for (int y = -1; y <= 1; y++) {
+                        for (int x = -1; x <= 1; x++) {
+                            ivec2 neighbour = pixel + ivec2(x, y) * pass.jump;
+                            if (any(lessThan(neighbour, ivec2(0))) || any(greaterThanEqual(neighbour, pass.extent))) {
+                                continue;
+                            }
+
+                            ivec4 candidate = source[neighbour.y * pass.extent.x + neighbour.x];
+                            inside = nearer(pixel, inside, candidate.xy);
+                            outside = nearer(pixel, outside, candidate.zw);
+                        }
+                    }
+
+                    destination[idx] = ivec4(inside, outside);
+                    return;
+                }
+
+                // Glyph pixels are their own nearest glyph pixel
+                bool inside = seeds.xy == pixel;
+                float distance = inside ? -edge_distance(pixel, seeds.zw) : edge_distance(pixel, seeds.xy);
+                imageStore(sdf, pixel, vec4(distance));
+            }
+        ",
+    }
+}
+
+const LOCAL_SIZE : [u32; 3] = [8, 8, 1];
+
+const STAGE_SEED : i32 = 0;
+const STAGE_JUMP : i32 = 1;
+const STAGE_RESOLVE : i32 = 2;
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct PassConstants {
+    extent : [i32; 2],
+    stage : i32,
+    jump : i32,
+}
+
+// Jump distances of the flood passes for an image of this size: halving from half the
+// next power of two down to 1, then one more pass at 1 to fix the rare seeds JFA misses
+pub fn jump_distances(extent : [u32; 2]) -> Vec<u32> {
+    let size = extent[0].max(extent[1]).max(1).next_power_of_two();
+
+    std::iter::successors(Some(size / 2), |jump| Some(jump / 2))
+    .take_while(|&jump| jump > 0)
+    .chain([1])
+    .collect()
+}
+
+// Turns a glyph coverage image into a signed distance field with the Jump Flood Algorithm,
+// O(log N) passes for an N pixel wide image. Distances are in texels, negative inside glyphs,
+// sample them with sdf_coverage from sdf.glsl for text that stays sharp when scaled up
+pub struct SdfGenerator {
+    shader : ComputeShader,
+    descriptor_set_allocator : StandardDescriptorSetAllocator,
+    memory_allocator : Arc<StandardMemoryAllocator>,
+    sampler : Arc<Sampler>,
+}
+
+impl SdfGenerator {
+    pub fn new(toolset : &VulkanToolset) -> SdfGenerator {
+        let device = &toolset.logical_device;
+        let module = jump_flood_cs::load(device.clone()).expect("failed to create shader module");
+
+        SdfGenerator {
+            shader : ComputeShader::new(&module, LOCAL_SIZE, device.clone()),
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+            memory_allocator : toolset.memory_allocator.general_allocator.clone(),
+            // Only read with texelFetch, filtering never applies
+            sampler : Sampler::new(device.clone(), SamplerCreateInfo::default()).unwrap(),
+        }
+    }
+
+    // Record outside of a render pass. Pixels whose red channel is at least 0.5 belong to a glyph.
+    // The atlas needs SAMPLED usage, the output STORAGE usage, the R32_SFLOAT format and the same extent
+    pub fn run(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, glyph_atlas_image : &Arc<Image>, output_sdf_image : &Arc<Image>) -> Result<(), EngineError> {
+        if output_sdf_image.format() != Format::R32_SFLOAT {
+            return Err(EngineError::UnsupportedFeature(format!("signed distance fields are written as R32_SFLOAT, not {:?}", output_sdf_image.format())));
+        }
+        if output_sdf_image.extent() != glyph_atlas_image.extent() {
+            return Err(EngineError::ImageExtent { expected : glyph_atlas_image.extent(), actual : output_sdf_image.extent() });
+        }
+
+        let [width, height, _] = glyph_atlas_image.extent();
+
+        // Seeds ping-pong between the two buffers, every pass reads one and writes the other
+        let seed_buffer = || Buffer::new_slice::<[i32; 4]>(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+            width as u64 * height as u64,
+        );
+        let seeds = [seed_buffer()?, seed_buffer()?];
+
+        let atlas_view = ImageView::new_default(glyph_atlas_image.clone())?;
+        let sdf_view = ImageView::new_default(output_sdf_image.clone())?;
+
+        let pipeline = &self.shader.pipeline;
+        let layout = pipeline.layout();
+
+        // Set i reads seeds[i] and writes the other buffer
+        let [even, odd] = [(0, 1), (1, 0)].map(|(source, destination)| PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view_sampler(0, atlas_view.clone(), self.sampler.clone()),
+                WriteDescriptorSet::buffer(1, seeds[source].clone()),
+                WriteDescriptorSet::buffer(2, seeds[destination].clone()),
+                WriteDescriptorSet::image_view(3, sdf_view.clone()),
+            ],
+            [],
+        ));
+        let descriptor_sets = [even?, odd?];
+
+        let group_counts = self.shader.group_counts([width, height, 1]);
+        let passes = [(STAGE_SEED, 0)].into_iter()
+        .chain(jump_distances([width, height]).into_iter().map(|jump| (STAGE_JUMP, jump as i32)))
+        .chain([(STAGE_RESOLVE, 0)]);
+
+        builder.bind_pipeline_compute(pipeline.clone())?;
+
+        // Alternating the sets makes every pass read what the one before it wrote
+        for (index, (stage, jump)) in passes.enumerate() {
+            let constants = PassConstants {
+                extent : [width as i32, height as i32],
+                stage,
+                jump,
+            };
+
+            builder.bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_sets[index % 2].clone())?
+            .push_constants(layout.clone(), 0, constants)?
+            .dispatch(group_counts)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    descriptor_set::WriteDescriptorSet,
+    format::{Format, FormatFeatures},
+    image::{sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo}, view::ImageView, Image, ImageLayout, ImageTiling, ImageUsage, SampleCount},
+    pipeline::{graphics::{depth_stencil::{CompareOp, DepthState, DepthStencilState}, input_assembly::InputAssemblyState, rasterization::RasterizationState, vertex_input::{Vertex, VertexDefinition}, viewport::Viewport}, GraphicsPipeline},
+    render_pass::{AttachmentLoadOp, AttachmentStoreOp, Framebuffer, RenderPass, Subpass},
+    shader::ShaderModule,
+    sync::{self, GpuFuture}
+};
+
+use crate::{error::EngineError, vulkan::{debug_utils::DebugUtils, format_utils::FormatNegotiator, mesh::Vertex3D, render_pass::{create_framebuffer, RenderPassBuilder}, render_target_pool::PooledRenderTarget, scissor::ScissorState, vulkan::{find_entry_point, CullConfig, DepthBias, MultisampleConfig, PipelineStates, VulkanAllocation, VulkanToolset}, vulkan_window::AttachmentConfig}};
+
+// Both can be sampled on every device that supports them as attachments, D16_UNORM always can
+const SHADOW_FORMATS : [Format; 2] = [Format::D32_SFLOAT, Format::D16_UNORM];
+
+// Depth-only pass rendered from the light, its attachment is kept for sampling afterwards. The map has a
+// fixed resolution independent of the window, so neither it nor its caster pipelines change on a resize
+#[derive(Clone)]
+pub struct ShadowMapPass {
+    pub render_pass : Arc<RenderPass>,
+    pub framebuffer : Arc<Framebuffer>,
+    // From the render target pool, it goes back with the last clone of the pass
+    depth_target : Arc<PooledRenderTarget>,
+    // Reads the stored depth, for sampler2D
+    pub sampler : Arc<Sampler>,
+    // Compares against the stored depth, for sampler2DShadow. Filters the results of the four nearest
+    // texels where the format allows, which softens the shadow edges
+    pub compare_sampler : Arc<Sampler>,
+    pub size : u32,
+}
+
+impl ShadowMapPass {
+    pub fn new(toolset : &VulkanToolset, size : u32) -> ShadowMapPass {
+        let device = &toolset.logical_device;
+
+        let format = SHADOW_FORMATS.into_iter()
+        .find(|&format| FormatNegotiator::is_format_supported(
+            device.physical_device(),
+            format,
+            ImageTiling::Optimal,
+            FormatFeatures::DEPTH_STENCIL_ATTACHMENT | FormatFeatures::SAMPLED_IMAGE,
+        ))
+        .unwrap_or(Format::D16_UNORM);
+
+        let render_pass = RenderPassBuilder::new()
+        .attachment(AttachmentConfig {
+            format,
+            samples : 1,
+            load_op : AttachmentLoadOp::Clear,
+            store_op : AttachmentStoreOp::Store,
+            initial_layout : ImageLayout::Undefined,
+            final_layout : ImageLayout::DepthStencilReadOnlyOptimal,
+        })
+        .subpass(&[], Some(0))
+        .build(device)
+        .expect("failed to create shadow map render pass");
+
+        let depth_target = toolset.memory_allocator.render_targets.acquire(
+            format,
+            [size, size],
+            ImageUsage::DEPTH_STENCIL_ATTACHMENT | ImageUsage::SAMPLED | ImageUsage::TRANSFER_DST,
+            SampleCount::Sample1,
+        ).expect("failed to create shadow map");
+        Self::clear_to_far_plane(toolset, depth_target.image());
+
+        let framebuffer = create_framebuffer(&render_pass, vec![depth_target.view().clone()]).unwrap();
+
+        // Lookups outside the map read the border depth instead of wrapping around
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+            },
+        ).unwrap();
+
+        // Depth at or nearer than the stored one is lit, outside the map nothing is in shadow
+        let filter = match FormatNegotiator::is_format_supported(device.physical_device(), format, ImageTiling::Optimal, FormatFeatures::SAMPLED_IMAGE_FILTER_LINEAR) {
+            true => Filter::Linear,
+            false => Filter::Nearest,
+        };
+        let compare_sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: filter,
+                min_filter: filter,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                compare: Some(CompareOp::LessOrEqual),
+                ..Default::default()
+            },
+        ).unwrap();
+
+        ShadowMapPass {
+            render_pass,
+            framebuffer,
+            depth_target : Arc::new(depth_target),
+            sampler,
+            compare_sampler,
+            size,
+        }
+    }
+
+    // Sampling the map before the first record sees nothing in shadow instead of undefined depth
+    fn clear_to_far_plane(toolset : &VulkanToolset, image : &Arc<Image>) {
+        let queue = &toolset.device_queue;
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &toolset.memory_allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+
+        VulkanAllocation::record_clear_depth_stencil(&mut builder, image, ImageLayout::TransferDstOptimal, 1.0, 0)
+        .expect("failed to clear the shadow map");
+
+        sync::now(toolset.logical_device.clone())
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+    }
+
+    // Shadow caster pipelines are built against this subpass and viewport
+    pub fn subpass(&self) -> Subpass {
+        Subpass::from(self.render_pass.clone(), 0).unwrap()
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        Viewport {
+            offset: [0.0, 0.0],
+            extent: [self.size as f32, self.size as f32],
+            depth_range: 0.0..=1.0,
+        }
+    }
+
+    // Depth-only variant of a mesh pipeline, `vs` takes Vertex3D input and writes gl_Position in the light's
+    // clip space. The bias is applied while rendering the map, the lit pass compares without offsets
+    pub fn create_caster_pipeline(&self, toolset : &VulkanToolset, vs : &Arc<ShaderModule>, cull : CullConfig, depth_bias : DepthBias) -> Result<Arc<GraphicsPipeline>, EngineError> {
+        let device = &toolset.logical_device;
+        let vs = find_entry_point(vs, "main")?;
+
+        let vertex_input_state = Vertex3D::per_vertex()
+        .definition(&vs.info().input_interface)?;
+
+        let pipeline = toolset.build_pipeline_for(vec![vs], PipelineStates {
+            vertex_input_state,
+            input_assembly_state : InputAssemblyState::default(),
+            tessellation_state : None,
+            rasterization_state : RasterizationState {
+                depth_bias: Some(depth_bias.state(device.enabled_features().depth_bias_clamp)),
+                ..cull.rasterization_state()
+            },
+            depth_stencil_state : DepthStencilState {
+                depth: Some(DepthState::simple()),
+                ..Default::default()
+            },
+            push_descriptor_set : None,
+            conservative_raster : None,
+            multisample : MultisampleConfig::default(),
+            dynamic_viewport : false,
+            scissor : ScissorState::default(),
+            color_write_masks : Vec::new(),
+            blend : None,
+            immutable_samplers : Vec::new(),
+            inline_uniform_blocks : Vec::new(),
+        }, self.subpass(), self.viewport());
+        DebugUtils::name_object(device, &pipeline, "shadow caster pipeline");
+
+        Ok(pipeline)
+    }
+
+    // Clears the map to the far plane and records the casters in between
+    pub fn record<F : FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>)>(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, draw : F) {
+        builder.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![Some(1f32.into())],
+                ..RenderPassBeginInfo::framebuffer(self.framebuffer.clone())
+            },
+            SubpassBeginInfo {
+                contents: SubpassContents::Inline,
+                ..Default::default()
+            },
+        ).unwrap();
+
+        draw(builder);
+
+        builder.end_render_pass(SubpassEndInfo::default()).unwrap();
+    }
+
+    pub fn depth_view(&self) -> &Arc<ImageView> {
+        self.depth_target.view()
+    }
+
+    pub fn write_descriptor(&self, binding : u32) -> WriteDescriptorSet {
+        WriteDescriptorSet::image_view_sampler(binding, self.depth_view().clone(), self.sampler.clone())
+    }
+
+    // Binds the map with compare_sampler, `texture(shadow_map, vec3(uv, depth))` is then 1.0 where lit
+    pub fn write_compare_descriptor(&self, binding : u32) -> WriteDescriptorSet {
+        WriteDescriptorSet::image_view_sampler(binding, self.depth_view().clone(), self.compare_sampler.clone())
+    }
+}
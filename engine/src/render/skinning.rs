@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    device::Queue,
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    pipeline::{Pipeline, PipelineBindPoint}
+};
+
+use crate::{error::EngineError, scene::camera::Matrix4, vulkan::{mesh::{Mesh, MeshIndices, Vertex3D}, staging::upload_buffer, vulkan::{ComputeShader, VulkanAllocation, VulkanToolset}}};
+
+mod skinning_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            struct SkinnedVertex {
+                float position[3];
+                float normal[3];
+                float uv[2];
+                // Four bone indices, one per byte
+                uint bone_indices;
+                float bone_weights[4];
+            };
+
+            struct Vertex {
+                float position[3];
+                float normal[3];
+                float uv[2];
+            };
+
+            layout(set = 0, binding = 0) readonly buffer Matrices {
+                mat4 skinning_matrices[];
+            };
+
+            layout(set = 0, binding = 1) readonly buffer Source {
+                SkinnedVertex source[];
+            };
+
+            layout(set = 0, binding = 2) writeonly buffer Destination {
+                Vertex destination[];
+            };
+
+            void main() {
+                uint idx = gl_GlobalInvocationID.x;
+                if (idx >= source.length()) {
+                    return;
+                }
+
+                SkinnedVertex vertex = source[idx];
+                uint last_bone = uint(skinning_matrices.length()) - 1u;
+
+                mat4 skin = mat4(0.0);
+                for (uint i = 0u; i < 4u; i++) {
+                    uint bone = min((vertex.bone_indices >> (8u * i)) & 0xffu, last_bone);
+                    skin += skinning_matrices[bone] * vertex.bone_weights[i];
+                }
+
+                vec3 position = (skin * vec4(vertex.position[0], vertex.position[1], vertex.position[2], 1.0)).xyz;
+                vec3 normal = mat3(skin) * vec3(vertex.normal[0], vertex.normal[1], vertex.normal[2]);
+                if (dot(normal, normal) > 0.0) {
+                    normal = normalize(normal);
+                }
+
+                destination[idx].position = float[3](position.x, position.y, position.z);
+                destination[idx].normal = float[3](normal.x, normal.y, normal.z);
+                destination[idx].uv = vertex.uv;
+            }
+        ",
+    }
+}
+
+const LOCAL_SIZE : [u32; 3] = [64, 1, 1];
+
+// Bind pose vertex with up to four influencing bones, whose weights should add up to 1
+#[derive(BufferContents, Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct SkinnedVertex {
+    pub position : [f32; 3],
+    pub normal : [f32; 3],
+    pub uv : [f32; 2],
+    // Indices into Skeleton::bones, past the last bone means the last bone
+    pub bone_indices : [u8; 4],
+    pub bone_weights : [f32; 4],
+}
+
+// Bind pose vertices in, posed vertices out. `mesh` is drawn like any other Mesh once
+// SkinningPass::record filled its vertex buffer for the frame
+pub struct SkinnedMesh {
+    pub source : Subbuffer<[SkinnedVertex]>,
+    pub mesh : Mesh,
+}
+
+impl SkinnedMesh {
+    pub fn new(allocator : &VulkanAllocation, queue : &Arc<Queue>, vertices : Vec<SkinnedVertex>, indices : Vec<u32>) -> Result<SkinnedMesh, EngineError> {
+        let vertex_count = vertices.len() as u64;
+        let vertex_buffer = Buffer::new_slice::<Vertex3D>(
+            allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER | BufferUsage::VERTEX_BUFFER | BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+            vertex_count,
+        )?;
+
+        Ok(SkinnedMesh {
+            source : upload_buffer(allocator, queue, BufferUsage::STORAGE_BUFFER, vertices)?,
+            mesh : Mesh {
+                vertex_buffer,
+                index_buffer : MeshIndices::fit(indices, vertex_count as usize).upload(allocator, queue)?,
+            },
+        })
+    }
+}
+
+// Poses skinned meshes on the GPU with linear blend skinning. Normals use the upper 3x3
+// of the blended matrix, exact for rotations and uniform scale
+pub struct SkinningPass {
+    shader : ComputeShader,
+    descriptor_set_allocator : StandardDescriptorSetAllocator,
+    memory_allocator : Arc<StandardMemoryAllocator>,
+}
+
+impl SkinningPass {
+    pub fn new(toolset : &VulkanToolset) -> SkinningPass {
+        let device = &toolset.logical_device;
+        let module = skinning_cs::load(device.clone()).expect("failed to create shader module");
+
+        SkinningPass {
+            shader : ComputeShader::new(&module, LOCAL_SIZE, device.clone()),
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+            memory_allocator : toolset.memory_allocator.general_allocator.clone(),
+        }
+    }
+
+    // Record outside of a render pass, before the frame draws `skinned.mesh`.
+    // The matrices come from Skeleton::compute_skinning_matrices
+    pub fn record(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, skinned : &SkinnedMesh, skinning_matrices : &[Matrix4]) -> Result<(), EngineError> {
+        assert!(!skinning_matrices.is_empty(), "skinning needs at least one bone");
+
+        // A fresh buffer per record, earlier frames may still be reading theirs
+        let matrices = Buffer::from_iter(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            skinning_matrices.iter().copied(),
+        )?;
+
+        let pipeline = &self.shader.pipeline;
+        let layout = pipeline.layout();
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::buffer(0, matrices),
+                WriteDescriptorSet::buffer(1, skinned.source.clone()),
+                WriteDescriptorSet::buffer(2, skinned.mesh.vertex_buffer.clone()),
+            ],
+            [],
+        )?;
+
+        builder.bind_pipeline_compute(pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?
+        .dispatch(self.shader.group_counts([skinned.source.len() as u32, 1, 1]))?;
+
+        Ok(())
+    }
+}
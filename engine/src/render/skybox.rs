@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{graphics::{depth_stencil::{CompareOp, DepthState, DepthStencilState}, input_assembly::InputAssemblyState, rasterization::{CullMode, RasterizationState}, vertex_input::{Vertex, VertexDefinition}}, GraphicsPipeline, Pipeline, PipelineBindPoint},
+    render_pass::Subpass
+};
+
+use crate::vulkan::{immutable_samplers::ImmutableSamplers, scissor::ScissorState, texture::Texture2D, vulkan::{MultisampleConfig, PipelineStates, VulkanToolset}};
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 position;
+
+            layout(set = 0, binding = 0) uniform Camera {
+                mat4 view;
+                mat4 projection;
+            } camera;
+
+            layout(location = 0) out vec3 v_direction;
+
+            void main() {
+                // Zero W drops the camera translation, xyww pins the cube to max depth
+                vec4 clip = camera.projection * camera.view * vec4(position, 0.0);
+                gl_Position = clip.xyww;
+                v_direction = position;
+            }
+        ",
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 v_direction;
+            layout(location = 0) out vec4 f_color;
+
+            layout(set = 0, binding = 1) uniform samplerCube skybox;
+
+            void main() {
+                f_color = texture(skybox, v_direction);
+            }
+        ",
+    }
+}
+
+#[derive(BufferContents, Vertex, Clone, Copy)]
+#[repr(C)]
+pub struct SkyboxVertex {
+    #[format(R32G32B32_SFLOAT)]
+    pub position : [f32; 3],
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+pub struct CameraUniform {
+    pub view : [[f32; 4]; 4],
+    pub projection : [[f32; 4]; 4],
+}
+
+#[derive(Clone)]
+pub struct Skybox {
+    pub vertex_buffer : Subbuffer<[SkyboxVertex]>,
+    pub pipeline : Arc<GraphicsPipeline>,
+    cubemap : Arc<Texture2D>,
+    descriptor_set_allocator : Arc<StandardDescriptorSetAllocator>,
+}
+
+impl Skybox {
+    pub fn new(toolset : &VulkanToolset, cubemap : Texture2D) -> Skybox {
+        let device = &toolset.logical_device;
+
+        let vertex_buffer = Buffer::from_iter(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            Self::cube_vertices(),
+        ).expect("failed to create skybox buffer");
+
+        let descriptor_set_allocator = Arc::new(StandardDescriptorSetAllocator::new(device.clone(), Default::default()));
+
+        Skybox {
+            vertex_buffer,
+            pipeline : Self::create_pipeline(toolset, &cubemap),
+            cubemap : Arc::new(cubemap),
+            descriptor_set_allocator,
+        }
+    }
+
+    // Viewport is baked into the pipeline, call after the swapchain was recreated
+    pub fn recreate_pipeline(&mut self, toolset : &VulkanToolset) {
+        self.pipeline = Self::create_pipeline(toolset, &self.cubemap);
+    }
+
+    // Record after opaque geometry, the sky sits at the far plane and only passes the depth test
+    // where nothing was drawn. Anything that doesn't write depth has to come after it
+    pub fn record(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, camera_ubo : Subbuffer<CameraUniform>) {
+        let layout = self.pipeline.layout().set_layouts().first().unwrap();
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.clone(),
+            [
+                WriteDescriptorSet::buffer(0, camera_ubo),
+                self.cubemap.write_view_descriptor(1),
+            ],
+            [],
+        ).unwrap();
+
+        builder.bind_pipeline_graphics(self.pipeline.clone())
+        .unwrap()
+        .bind_descriptor_sets(PipelineBindPoint::Graphics, self.pipeline.layout().clone(), 0, descriptor_set)
+        .unwrap()
+        .bind_vertex_buffers(0, self.vertex_buffer.clone())
+        .unwrap()
+        .draw(self.vertex_buffer.len() as u32, 1, 0, 0)
+        .unwrap();
+    }
+
+    fn create_pipeline(toolset : &VulkanToolset, cubemap : &Texture2D) -> Arc<GraphicsPipeline> {
+        let device = &toolset.logical_device;
+        let vs = vs::load(device.clone()).expect("failed to create shader module").entry_point("main").unwrap();
+        let fs = fs::load(device.clone()).expect("failed to create shader module").entry_point("main").unwrap();
+
+        let vertex_input_state = SkyboxVertex::per_vertex()
+        .definition(&vs.info().input_interface)
+        .unwrap();
+
+        // The camera sits inside the cube, so both faces must be rasterized
+        let rasterization_state = RasterizationState {
+            cull_mode: CullMode::None,
+            depth_clamp_enable: device.enabled_features().depth_clamp,
+            ..Default::default()
+        };
+
+        let depth_stencil_state = DepthStencilState {
+            depth: Some(DepthState {
+                write_enable: false,
+                compare_op: CompareOp::LessOrEqual,
+            }),
+            ..Default::default()
+        };
+
+        // The sky is always sampled the same way, so its sampler is baked into the layout
+        let window = toolset.get_vulkan_window();
+        toolset.build_pipeline_for(vec![vs, fs], PipelineStates {
+            vertex_input_state,
+            input_assembly_state : InputAssemblyState::default(),
+            tessellation_state : None,
+            rasterization_state,
+            depth_stencil_state,
+            push_descriptor_set : None,
+            conservative_raster : None,
+            multisample : MultisampleConfig::default(),
+            dynamic_viewport : false,
+            scissor : ScissorState::default(),
+            color_write_masks : Vec::new(),
+            blend : None,
+            immutable_samplers : vec![ImmutableSamplers::new(0, 1, &[cubemap.sampler.clone()])],
+            inline_uniform_blocks : Vec::new(),
+        }, Subpass::from(window.get_render_pass(), 0).unwrap(), window.get_window_viewport())
+    }
+
+    fn cube_vertices() -> Vec<SkyboxVertex> {
+        let corners = [
+            [-1.0, -1.0, -1.0], [1.0, -1.0, -1.0], [1.0, 1.0, -1.0], [-1.0, 1.0, -1.0],
+            [-1.0, -1.0, 1.0], [1.0, -1.0, 1.0], [1.0, 1.0, 1.0], [-1.0, 1.0, 1.0],
+        ];
+
+        // Two triangles per face
+        let indices = [
+            1, 5, 6, 6, 2, 1, // +X
+            4, 0, 3, 3, 7, 4, // -X
+            3, 2, 6, 6, 7, 3, // +Y
+            4, 5, 1, 1, 0, 4, // -Y
+            5, 4, 7, 7, 6, 5, // +Z
+            0, 1, 2, 2, 3, 0, // -Z
+        ];
+
+        indices.iter()
+        .map(|&i| SkyboxVertex { position : corners[i] })
+        .collect()
+    }
+}
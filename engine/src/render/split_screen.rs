@@ -0,0 +1,65 @@
+use std::mem::size_of;
+
+use vulkano::{buffer::BufferContents, pipeline::{graphics::viewport::{Scissor, Viewport}, layout::PushConstantRange}, shader::ShaderStages};
+
+use crate::scene::camera::{Camera, Matrix4};
+
+// One view of the scene within the frame. Every draw is replayed once per region, with its view-projection
+// pushed at offset 0 to pipelines that can take it, see takes_region_constants
+#[derive(Clone, Debug)]
+pub struct ViewportRegion {
+    pub viewport : Viewport,
+    pub scissor : Scissor,
+    pub camera_uniform : Matrix4,
+}
+
+impl ViewportRegion {
+    pub fn new(offset : [u32; 2], extent : [u32; 2], camera_uniform : Matrix4) -> ViewportRegion {
+        ViewportRegion {
+            viewport : Viewport {
+                offset : [offset[0] as f32, offset[1] as f32],
+                extent : [extent[0] as f32, extent[1] as f32],
+                depth_range : 0.0..=1.0,
+            },
+            scissor : Scissor { offset, extent },
+            camera_uniform,
+        }
+    }
+
+    // Side by side columns of `extent`, one per camera, each with its own aspect ratio.
+    // Recompute after a resize, the columns follow the current extent
+    pub fn columns(extent : [u32; 2], cameras : &[Camera]) -> Vec<ViewportRegion> {
+        split_columns(extent, cameras.len() as u32)
+        .into_iter()
+        .zip(cameras)
+        .map(|((offset, size), camera)| {
+            let aspect = size[0] as f32 / size[1].max(1) as f32;
+            ViewportRegion::new(offset, size, camera.view_projection(aspect))
+        })
+        .collect()
+    }
+}
+
+// Offsets and extents of `count` columns covering `extent`. Odd widths give some columns
+// one more pixel, neighbours always share an edge so there are no gaps or overlaps
+pub fn split_columns(extent : [u32; 2], count : u32) -> Vec<([u32; 2], [u32; 2])> {
+    let edge = |i : u32| (extent[0] as u64 * i as u64 / count.max(1) as u64) as u32;
+
+    (0..count)
+    .map(|i| ([edge(i), 0], [edge(i + 1) - edge(i), extent[1]]))
+    .collect()
+}
+
+// Whether a pipeline with these push constant ranges gets the region's view-projection, a vertex stage
+// range at offset 0 it fits in. Other pipelines are drawn in every region without it
+pub fn takes_region_constants(ranges : &[PushConstantRange]) -> bool {
+    ranges.iter().any(|range| range.offset == 0
+        && range.stages.intersects(ShaderStages::VERTEX)
+        && range.size as usize >= size_of::<RegionConstants>())
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+pub(crate) struct RegionConstants {
+    pub view_projection : Matrix4,
+}
@@ -0,0 +1,328 @@
+use std::sync::Arc;
+
+use glam::{Mat4, Vec3};
+use vulkano::{
+    buffer::{BufferContents, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
+    image::{sampler::{Sampler, SamplerAddressMode, SamplerCreateInfo}, view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{Pipeline, PipelineBindPoint}
+};
+
+use crate::{error::EngineError, scene::camera::Matrix4, vulkan::{staging::upload_buffer, texture::upload_image_view, vulkan::{ComputeShader, VulkanToolset}}};
+
+mod ssao_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        include: ["assets/shaders/include"],
+        src: r#"
+            #version 460
+
+            #include "math.glsl"
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D depth_map;
+            // View space normals packed into 0..1
+            layout(set = 0, binding = 1) uniform sampler2D normal_map;
+
+            layout(set = 0, binding = 2) uniform Camera {
+                mat4 projection;
+                mat4 inverse_projection;
+            } camera;
+
+            layout(set = 0, binding = 3) readonly buffer Kernel {
+                vec4 kernel[];
+            };
+
+            layout(set = 0, binding = 4) uniform sampler2D noise;
+            layout(set = 0, binding = 5, r32f) uniform writeonly image2D occlusion;
+
+            layout(push_constant) uniform Ssao {
+                float radius;
+                float bias;
+            } ssao;
+
+            vec3 view_position(vec2 uv) {
+                float depth = textureLod(depth_map, uv, 0.0).r;
+                vec4 position = camera.inverse_projection * vec4(uv * 2.0 - 1.0, depth, 1.0);
+                return position.xyz / position.w;
+            }
+
+            void main() {
+                ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 extent = imageSize(occlusion);
+                if (any(greaterThanEqual(pixel, extent))) {
+                    return;
+                }
+                vec2 uv = (vec2(pixel) + 0.5) / vec2(extent);
+
+                // Nothing was drawn at the far plane
+                if (textureLod(depth_map, uv, 0.0).r >= 1.0) {
+                    imageStore(occlusion, pixel, vec4(1.0));
+                    return;
+                }
+
+                vec3 position = view_position(uv);
+                vec3 normal = unpack_normal(textureLod(normal_map, uv, 0.0).rgb);
+
+                // The tiled noise rotates the kernel around the normal, the blur pass hides the pattern
+                vec3 random = texelFetch(noise, pixel % 4, 0).xyz;
+                vec3 tangent = normalize(random - normal * dot(random, normal));
+                mat3 tbn = mat3(tangent, cross(normal, tangent), normal);
+
+                float occluded = 0.0;
+                for (int i = 0; i < kernel.length(); i++) {
+                    vec3 sample_position = position + tbn * kernel[i].xyz * ssao.radius;
+
+                    vec4 clip = camera.projection * vec4(sample_position, 1.0);
+                    vec2 sample_uv = clip.xy / clip.w * 0.5 + 0.5;
+                    float scene_z = view_position(sample_uv).z;
+
+                    // The view looks down -Z, surfaces in front of the sample have a larger z.
+                    // Occluders much further away than the radius fade out instead of darkening edges
+                    float in_range = smoothstep(0.0, 1.0, ssao.radius / max(abs(position.z - scene_z), EPSILON));
+                    occluded += (scene_z >= sample_position.z + ssao.bias ? 1.0 : 0.0) * in_range;
+                }
+
+                imageStore(occlusion, pixel, vec4(1.0 - occluded / float(kernel.length())));
+            }
+        "#,
+    }
+}
+
+mod blur_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D occlusion;
+            layout(set = 0, binding = 1, r32f) uniform writeonly image2D blurred;
+
+            void main() {
+                ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 extent = imageSize(blurred);
+                if (any(greaterThanEqual(pixel, extent))) {
+                    return;
+                }
+
+                // Box blur over the size of the noise tile
+                float sum = 0.0;
+                for (int y = -2; y < 2; y++) {
+                    for (int x = -2; x < 2; x++) {
+                        sum += texelFetch(occlusion, clamp(pixel + ivec2(x, y), ivec2(0), extent - 1), 0).r;
+                    }
+                }
+
+                imageStore(blurred, pixel, vec4(sum / 16.0));
+            }
+        ",
+    }
+}
+
+const LOCAL_SIZE : [u32; 3] = [8, 8, 1];
+
+const NOISE_SIZE : u32 = 4;
+
+// Projection of the camera the depth buffer was rendered with, bound as a uniform buffer
+#[derive(BufferContents, Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct SsaoCamera {
+    pub projection : Matrix4,
+    pub inverse_projection : Matrix4,
+}
+
+impl SsaoCamera {
+    pub fn new(projection : Matrix4) -> SsaoCamera {
+        SsaoCamera {
+            projection,
+            inverse_projection : Mat4::from_cols_array_2d(&projection).inverse().to_cols_array_2d(),
+        }
+    }
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct SsaoConstants {
+    radius : f32,
+    bias : f32,
+}
+
+// Sample offsets in the +Z hemisphere within the unit sphere, packed into vec4s.
+// More of them lie close to the center, where occluders matter most
+pub fn hemisphere_kernel(size : u32, seed : u32) -> Vec<[f32; 4]> {
+    let mut random = Random::new(seed);
+
+    (0..size)
+    .map(|i| {
+        let direction = Vec3::new(random.next() * 2.0 - 1.0, random.next() * 2.0 - 1.0, random.next())
+        .try_normalize()
+        .unwrap_or(Vec3::Z);
+
+        let t = i as f32 / size as f32;
+        let scale = 0.1 + 0.9 * t * t;
+        let offset = direction * random.next() * scale;
+
+        [offset.x, offset.y, offset.z, 0.0]
+    })
+    .collect()
+}
+
+// Screen space ambient occlusion from a depth buffer and view space normals. 1.0 is unoccluded,
+// multiply the ambient term of the lighting by the map returned from record
+pub struct SsaoPass {
+    // View space distance around each pixel searched for occluders
+    pub radius : f32,
+    // Keeps flat surfaces from occluding themselves through depth precision
+    pub bias : f32,
+    ssao_shader : ComputeShader,
+    blur_shader : ComputeShader,
+    descriptor_set_allocator : StandardDescriptorSetAllocator,
+    sampler : Arc<Sampler>,
+    kernel : Subbuffer<[[f32; 4]]>,
+    noise : Arc<ImageView>,
+    occlusion : Arc<ImageView>,
+    blurred : Arc<ImageView>,
+}
+
+impl SsaoPass {
+    // The maps are sized for one extent, create a new pass after a resize
+    pub fn new(toolset : &VulkanToolset, width : u32, height : u32, kernel_size : u32) -> Result<SsaoPass, EngineError> {
+        assert!(kernel_size > 0, "the SSAO kernel needs at least one sample");
+
+        let device = &toolset.logical_device;
+        let ssao_module = ssao_cs::load(device.clone()).expect("failed to create shader module");
+        let blur_module = blur_cs::load(device.clone()).expect("failed to create shader module");
+
+        let kernel = upload_buffer(&toolset.memory_allocator, &toolset.device_queue, BufferUsage::STORAGE_BUFFER, hemisphere_kernel(kernel_size, 0))?;
+
+        // Random rotations around Z, tiled over the screen
+        let mut random = Random::new(1);
+        let noise_bytes = (0..NOISE_SIZE * NOISE_SIZE)
+        .flat_map(|_| {
+            let [x, y] = [random.next(), random.next()].map(|value| ((value * 2.0 - 1.0) * 127.0) as i8);
+            [x, y, 0, 0].map(|value| value as u8)
+        })
+        .collect::<Vec<_>>();
+        let noise = upload_image_view(toolset, Format::R8G8B8A8_SNORM, [NOISE_SIZE; 2], &noise_bytes, None)?;
+
+        let occlusion_map = || -> Result<Arc<ImageView>, EngineError> {
+            let image = Image::new(
+                toolset.memory_allocator.general_allocator.clone(),
+                ImageCreateInfo {
+                    image_type: ImageType::Dim2d,
+                    format: Format::R32_SFLOAT,
+                    extent: [width, height, 1],
+                    usage: ImageUsage::STORAGE | ImageUsage::SAMPLED | ImageUsage::TRANSFER_SRC,
+                    ..Default::default()
+                },
+                AllocationCreateInfo {
+                    memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                    ..Default::default()
+                },
+            )?;
+
+            Ok(ImageView::new_default(image)?)
+        };
+
+        // Depth and normals are read per pixel, lookups past the edge repeat it
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )?;
+
+        Ok(SsaoPass {
+            radius : 0.5,
+            bias : 0.025,
+            ssao_shader : ComputeShader::new(&ssao_module, LOCAL_SIZE, device.clone()),
+            blur_shader : ComputeShader::new(&blur_module, LOCAL_SIZE, device.clone()),
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+            sampler,
+            kernel,
+            noise,
+            occlusion : occlusion_map()?,
+            blurred : occlusion_map()?,
+        })
+    }
+
+    // Record outside of a render pass, after the depth and normals were rendered with SAMPLED usage.
+    // Returns the blurred occlusion map, it stays valid until the next record
+    pub fn record(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, depth_view : &Arc<ImageView>, normal_view : &Arc<ImageView>, camera_ubo : &Subbuffer<SsaoCamera>) -> Result<Arc<ImageView>, EngineError> {
+        let ssao_layout = self.ssao_shader.pipeline.layout();
+        let ssao_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            ssao_layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view_sampler(0, depth_view.clone(), self.sampler.clone()),
+                WriteDescriptorSet::image_view_sampler(1, normal_view.clone(), self.sampler.clone()),
+                WriteDescriptorSet::buffer(2, camera_ubo.clone()),
+                WriteDescriptorSet::buffer(3, self.kernel.clone()),
+                WriteDescriptorSet::image_view_sampler(4, self.noise.clone(), self.sampler.clone()),
+                WriteDescriptorSet::image_view(5, self.occlusion.clone()),
+            ],
+            [],
+        )?;
+
+        let blur_layout = self.blur_shader.pipeline.layout();
+        let blur_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            blur_layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view_sampler(0, self.occlusion.clone(), self.sampler.clone()),
+                WriteDescriptorSet::image_view(1, self.blurred.clone()),
+            ],
+            [],
+        )?;
+
+        let constants = SsaoConstants {
+            radius : self.radius,
+            bias : self.bias,
+        };
+
+        let [width, height, _] = self.occlusion.image().extent();
+        let group_counts = self.ssao_shader.group_counts([width, height, 1]);
+
+        builder.bind_pipeline_compute(self.ssao_shader.pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, ssao_layout.clone(), 0, ssao_set)?
+        .push_constants(ssao_layout.clone(), 0, constants)?
+        .dispatch(group_counts)?
+        .bind_pipeline_compute(self.blur_shader.pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, blur_layout.clone(), 0, blur_set)?
+        .dispatch(group_counts)?;
+
+        Ok(self.blurred.clone())
+    }
+}
+
+// Small deterministic generator, the kernel and noise only need to look random
+struct Random(u32);
+
+impl Random {
+    fn new(seed : u32) -> Random {
+        // Spreads neighbouring seeds apart, so they don't give shifted copies of one sequence
+        Random(seed.wrapping_mul(0x9e3779b9))
+    }
+
+    // 0..1
+    fn next(&mut self) -> f32 {
+        // lowbias32 hash of a counter
+        let mut x = self.0;
+        self.0 = self.0.wrapping_add(1);
+
+        x ^= x >> 16;
+        x = x.wrapping_mul(0x7feb352d);
+        x ^= x >> 15;
+        x = x.wrapping_mul(0x846ca68b);
+        x ^= x >> 16;
+
+        x as f32 / u32::MAX as f32
+    }
+}
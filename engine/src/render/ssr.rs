@@ -0,0 +1,342 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{BufferContents, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
+    image::{sampler::{Sampler, SamplerAddressMode, SamplerCreateInfo}, view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{Pipeline, PipelineBindPoint}
+};
+
+use crate::{error::EngineError, vulkan::vulkan::{ComputeShader, VulkanToolset}};
+use super::ssao::SsaoCamera;
+
+mod ssr_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        include: ["assets/shaders/include"],
+        src: r#"
+            #version 460
+
+            #include "math.glsl"
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D color_map;
+            layout(set = 0, binding = 1) uniform sampler2D depth_map;
+            // View space normals packed into 0..1
+            layout(set = 0, binding = 2) uniform sampler2D normal_map;
+
+            layout(set = 0, binding = 3) uniform Camera {
+                mat4 projection;
+                mat4 inverse_projection;
+            } camera;
+
+            layout(set = 0, binding = 4, rgba16f) uniform writeonly image2D reflection;
+
+            layout(push_constant) uniform Ssr {
+                float max_distance;
+                float thickness;
+                uint steps;
+            } ssr;
+
+            const int REFINE_STEPS = 6;
+
+            vec3 view_position(vec2 uv) {
+                float depth = textureLod(depth_map, uv, 0.0).r;
+                vec4 position = camera.inverse_projection * vec4(uv * 2.0 - 1.0, depth, 1.0);
+                return position.xyz / position.w;
+            }
+
+            vec2 project(vec3 position) {
+                vec4 clip = camera.projection * vec4(position, 1.0);
+                return clip.xy / clip.w * 0.5 + 0.5;
+            }
+
+            // Same as screen_edge_fade on the CPU
+            float screen_edge_fade(vec2 uv) {
+                vec2 edge = min(uv, 1.0 - uv);
+                return smoothstep(0.0, 0.1, min(edge.x, edge.y));
+            }
+
+            void main() {
+                ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 extent = imageSize(reflection);
+                if (any(greaterThanEqual(pixel, extent))) {
+                    return;
+                }
+                vec2 uv = (vec2(pixel) + 0.5) / vec2(extent);
+
+                // Nothing was drawn at the far plane, so there is nothing to reflect from
+                if (textureLod(depth_map, uv, 0.0).r >= 1.0) {
+                    imageStore(reflection, pixel, vec4(0.0));
+                    return;
+                }
+
+                vec3 position = view_position(uv);
+                vec3 normal = unpack_normal(textureLod(normal_map, uv, 0.0).rgb);
+                vec3 direction = reflect(normalize(position), normal);
+
+                // Rays towards the camera end before the near plane, projecting past it flips them
+                float near = camera.projection[3][2] / camera.projection[2][2];
+                float ray_length = ssr.max_distance;
+                if (direction.z > 0.0) {
+                    ray_length = min(ray_length, (-near - position.z) / direction.z * 0.99);
+                }
+
+                // Lifted off the surface, so the first steps don't hit the pixel itself
+                vec3 start = position + normal * 0.01;
+                vec3 end = start + direction * ray_length;
+
+                // Stepped evenly on screen, 1/z interpolates linearly there
+                vec2 start_uv = project(start);
+                vec2 end_uv = project(end);
+                float start_inv_z = 1.0 / start.z;
+                float end_inv_z = 1.0 / end.z;
+
+                float previous = 0.0;
+                float hit = -1.0;
+                for (uint i = 1; i <= ssr.steps; i++) {
+                    float t = float(i) / float(ssr.steps);
+                    vec2 ray_uv = mix(start_uv, end_uv, t);
+                    if (any(lessThan(ray_uv, vec2(0.0))) || any(greaterThan(ray_uv, vec2(1.0)))) {
+                        break;
+                    }
+
+                    // The view looks down -Z, the ray is behind the surface once its z is smaller
+                    float ray_z = 1.0 / mix(start_inv_z, end_inv_z, t);
+                    float scene_z = view_position(ray_uv).z;
+                    if (ray_z <= scene_z) {
+                        if (scene_z - ray_z <= ssr.thickness) {
+                            hit = t;
+                        }
+                        break;
+                    }
+                    previous = t;
+                }
+
+                if (hit < 0.0) {
+                    imageStore(reflection, pixel, vec4(0.0));
+                    return;
+                }
+
+                // Bisect the last step for the crossing
+                float front = previous;
+                for (int i = 0; i < REFINE_STEPS; i++) {
+                    float t = (front + hit) * 0.5;
+                    float ray_z = 1.0 / mix(start_inv_z, end_inv_z, t);
+                    if (ray_z <= view_position(mix(start_uv, end_uv, t)).z) {
+                        hit = t;
+                    } else {
+                        front = t;
+                    }
+                }
+
+                // Fades out towards the screen edges and the end of the ray, where hits may be missing
+                vec2 hit_uv = mix(start_uv, end_uv, hit);
+                float weight = screen_edge_fade(hit_uv) * (1.0 - hit * hit);
+
+                imageStore(reflection, pixel, vec4(textureLod(color_map, hit_uv, 0.0).rgb, weight));
+            }
+        "#,
+    }
+}
+
+mod composite_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D color_map;
+            layout(set = 0, binding = 1) uniform sampler2D reflection;
+            layout(set = 0, binding = 2, rgba16f) uniform writeonly image2D composited;
+
+            layout(push_constant) uniform Composite {
+                float strength;
+            } composite;
+
+            void main() {
+                ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                if (any(greaterThanEqual(pixel, imageSize(composited)))) {
+                    return;
+                }
+
+                vec4 color = texelFetch(color_map, pixel, 0);
+                vec4 reflected = texelFetch(reflection, pixel, 0);
+
+                imageStore(composited, pixel, vec4(mix(color.rgb, reflected.rgb, reflected.a * composite.strength), color.a));
+            }
+        ",
+    }
+}
+
+const LOCAL_SIZE : [u32; 3] = [8, 8, 1];
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct SsrConstants {
+    max_distance : f32,
+    thickness : f32,
+    steps : u32,
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct CompositeConstants {
+    strength : f32,
+}
+
+// Weight of a reflection hit at `uv`, falling to zero over the outer tenth of the screen
+pub fn screen_edge_fade(uv : [f32; 2]) -> f32 {
+    let edge = uv.iter().map(|&value| value.min(1.0 - value)).fold(f32::INFINITY, f32::min);
+    let t = (edge / 0.1).clamp(0.0, 1.0);
+
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Screen space reflections from the lit color, depth and view space normals. Each pixel marches its
+// reflected view ray across the screen until it passes behind the depth buffer, reflections of what
+// is off screen or hidden are missing. The alpha of the reflection buffer weights the hit
+pub struct SsrPass {
+    // View space length of the reflected rays
+    pub max_distance : f32,
+    // How far behind the depth buffer a ray may be and still hit, thin objects need less
+    pub thickness : f32,
+    // Screen space steps along each ray, before the hit is refined
+    pub steps : u32,
+    // Blend factor of the reflections in composite, scaled by their weight
+    pub strength : f32,
+    ssr_shader : ComputeShader,
+    composite_shader : ComputeShader,
+    descriptor_set_allocator : StandardDescriptorSetAllocator,
+    color_sampler : Arc<Sampler>,
+    texel_sampler : Arc<Sampler>,
+    reflection : Arc<ImageView>,
+}
+
+impl SsrPass {
+    // The reflection buffer is sized for one extent, create a new pass after a resize
+    pub fn new(toolset : &VulkanToolset, width : u32, height : u32) -> Result<SsrPass, EngineError> {
+        let device = &toolset.logical_device;
+        let ssr_module = ssr_cs::load(device.clone()).expect("failed to create shader module");
+        let composite_module = composite_cs::load(device.clone()).expect("failed to create shader module");
+
+        let reflection = Image::new(
+            toolset.memory_allocator.general_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R16G16B16A16_SFLOAT,
+                extent: [width, height, 1],
+                usage: ImageUsage::STORAGE | ImageUsage::SAMPLED | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        )?;
+
+        // Hits land between pixels, their color is filtered. Depth and normals may not support
+        // linear filtering and shouldn't be blended across edges anyway. Lookups past the edge repeat it
+        let color_sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+            },
+        )?;
+        let texel_sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )?;
+
+        Ok(SsrPass {
+            max_distance : 20.0,
+            thickness : 0.5,
+            steps : 64,
+            strength : 0.5,
+            ssr_shader : ComputeShader::new(&ssr_module, LOCAL_SIZE, device.clone()),
+            composite_shader : ComputeShader::new(&composite_module, LOCAL_SIZE, device.clone()),
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+            color_sampler,
+            texel_sampler,
+            reflection : ImageView::new_default(reflection)?,
+        })
+    }
+
+    // Record outside of a render pass, after the scene was lit and its depth and normals were rendered
+    // with SAMPLED usage. `camera_ubo` is the same uniform SsaoPass reads. Returns the reflection buffer,
+    // it stays valid until the next record
+    pub fn record(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, color_view : &Arc<ImageView>, depth_view : &Arc<ImageView>, normal_view : &Arc<ImageView>, camera_ubo : &Subbuffer<SsaoCamera>) -> Result<Arc<ImageView>, EngineError> {
+        let layout = self.ssr_shader.pipeline.layout();
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view_sampler(0, color_view.clone(), self.color_sampler.clone()),
+                WriteDescriptorSet::image_view_sampler(1, depth_view.clone(), self.texel_sampler.clone()),
+                WriteDescriptorSet::image_view_sampler(2, normal_view.clone(), self.texel_sampler.clone()),
+                WriteDescriptorSet::buffer(3, camera_ubo.clone()),
+                WriteDescriptorSet::image_view(4, self.reflection.clone()),
+            ],
+            [],
+        )?;
+
+        let constants = SsrConstants {
+            max_distance : self.max_distance,
+            thickness : self.thickness,
+            steps : self.steps.max(1),
+        };
+
+        let [width, height, _] = self.reflection.image().extent();
+        builder.bind_pipeline_compute(self.ssr_shader.pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?
+        .push_constants(layout.clone(), 0, constants)?
+        .dispatch(self.ssr_shader.group_counts([width, height, 1]))?;
+
+        Ok(self.reflection.clone())
+    }
+
+    // Blends the reflections of the last record over `color_view` into `dst`, which needs STORAGE
+    // usage and the R16G16B16A16_SFLOAT format. Both have the pass extent
+    pub fn composite(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, color_view : &Arc<ImageView>, dst : &Arc<ImageView>) -> Result<(), EngineError> {
+        if dst.format() != Format::R16G16B16A16_SFLOAT {
+            return Err(EngineError::UnsupportedFeature(format!("reflections are composited as R16G16B16A16_SFLOAT, not {:?}", dst.format())));
+        }
+
+        let expected = self.reflection.image().extent();
+        for view in [color_view, dst] {
+            if view.image().extent() != expected {
+                return Err(EngineError::ImageExtent { expected, actual : view.image().extent() });
+            }
+        }
+
+        let layout = self.composite_shader.pipeline.layout();
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view_sampler(0, color_view.clone(), self.texel_sampler.clone()),
+                WriteDescriptorSet::image_view_sampler(1, self.reflection.clone(), self.texel_sampler.clone()),
+                WriteDescriptorSet::image_view(2, dst.clone()),
+            ],
+            [],
+        )?;
+
+        let [width, height, _] = expected;
+        builder.bind_pipeline_compute(self.composite_shader.pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?
+        .push_constants(layout.clone(), 0, CompositeConstants { strength : self.strength })?
+        .dispatch(self.composite_shader.group_counts([width, height, 1]))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use vulkano::query::QueryPipelineStatisticFlags;
+
+use crate::{engine::Frame, frame_timer::FrameTimer, vulkan::{memory_stats::MemoryStats, pipeline_stats::PipelineStats}};
+
+use super::overlay::Overlay;
+
+const MIB : f64 = (1 << 20) as f64;
+
+// Frame rate, the engine's pipeline statistics and the tracked VRAM as one line of text
+pub fn stats_text(timer : &FrameTimer, command_count : usize, stats : Option<&PipelineStats>, memory : &MemoryStats) -> String {
+    let mut text = match (timer.fps(), timer.frame_time()) {
+        (Some(fps), Some(frame_time)) => format!("{fps:.0} fps ({:.2} ms)", frame_time * 1000.0),
+        _ => "-- fps".to_owned(),
+    };
+    text += &format!(" | draws {command_count}");
+
+    if let Some(stats) = stats {
+        let stat = |flag| stats.get(&flag).copied().unwrap_or_default();
+        text += &format!(
+            " | triangles {} | fragments {}",
+            stat(QueryPipelineStatisticFlags::INPUT_ASSEMBLY_PRIMITIVES),
+            stat(QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS),
+        );
+    }
+
+    // Only what went through VulkanAllocation is tracked, the swapchain and vulkano's own allocations aren't
+    let (tracked, size) = memory.device_local();
+    if size > 0 {
+        text += &format!(" | vram {:.1} of {:.0} MiB", tracked as f64 / MIB, size as f64 / MIB);
+    }
+
+    text
+}
+
+// Draws the stats into the frame's overlay in the top left corner. The text refreshes once per averaging
+// interval, often enough to follow and slow enough to read. AppConfig::stats_overlay has the engine draw one
+pub struct RenderStatsOverlay {
+    text : String,
+    timer : FrameTimer,
+}
+
+impl RenderStatsOverlay {
+    pub fn new() -> RenderStatsOverlay {
+        RenderStatsOverlay {
+            text : "-- fps".to_owned(),
+            timer : FrameTimer::new(0.5),
+        }
+    }
+
+    // Call last in the render callback, so every draw of the frame is counted
+    pub fn draw(&mut self, frame : &mut Frame) {
+        if self.timer.tick(frame.delta()) {
+            let memory = frame.toolset().memory_allocator.stats();
+            self.text = stats_text(&self.timer, frame.command_count(), frame.pipeline_stats(), &memory);
+        }
+
+        // Darkened background so the text stays readable over bright scenes
+        let [width, height] = Overlay::text_size(&self.text);
+        let overlay = frame.overlay();
+        overlay.rect(4.0, 4.0, width + 8.0, height + 8.0, [0.0, 0.0, 0.0, 0.6]);
+        overlay.text(8.0, 8.0, &self.text);
+    }
+}
+
+impl Default for RenderStatsOverlay {
+    fn default() -> RenderStatsOverlay {
+        RenderStatsOverlay::new()
+    }
+}
@@ -0,0 +1,390 @@
+use std::{collections::HashMap, sync::Arc};
+
+use vulkano::{
+    buffer::{view::BufferView, Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, CopyBufferInfo, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
+    image::{sampler::{Sampler, SamplerAddressMode, SamplerCreateInfo}, view::{ImageView, ImageViewCreateInfo, ImageViewType}, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{Pipeline, PipelineBindPoint},
+    DeviceSize
+};
+
+use crate::{error::EngineError, vulkan::{memory_stats::AllocationCategory, staging::{upload_buffer, UploadQueue}, texture::ImageRegion, vulkan::{ComputeShader, VulkanAllocation, VulkanToolset}}};
+
+mod feedback_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            // Tile index + 1 of the full resolution tile each pixel wanted, 0 where the fallback was enough
+            layout(set = 0, binding = 0, r32ui) uniform readonly uimage2D tile_ids;
+
+            // One bit per tile
+            layout(set = 0, binding = 1, r32ui) uniform uimageBuffer feedback;
+
+            layout(push_constant) uniform Tiles {
+                uint tile_count;
+            };
+
+            void main() {
+                ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                if (any(greaterThanEqual(pixel, imageSize(tile_ids)))) {
+                    return;
+                }
+
+                uint id = imageLoad(tile_ids, pixel).r;
+                if (id == 0 || id > tile_count) {
+                    return;
+                }
+
+                uint tile = id - 1;
+                imageAtomicOr(feedback, int(tile / 32), 1u << (tile % 32));
+            }
+        ",
+    }
+}
+
+const LOCAL_SIZE : [u32; 3] = [8, 8, 1];
+
+// Page table entry of a tile without a slot in the cache
+pub const NOT_RESIDENT : u32 = u32::MAX;
+
+// Page tables are written with update_buffer, which takes at most 65536 bytes
+pub const MAX_TILES : u32 = 16384;
+
+// Tile `tile` was given cache slot `slot`, replacing `evicted`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileLoad {
+    pub tile : u32,
+    pub slot : u32,
+    pub evicted : Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CachedTile {
+    tile : u32,
+    last_used : u64,
+}
+
+// Which tile sits in which slot of a fixed size cache. Slots go to the tiles the GPU asked for,
+// taken from the tiles that went unused the longest
+#[derive(Clone, Debug)]
+pub struct TileCache {
+    slots : Vec<Option<CachedTile>>,
+    resident : HashMap<u32, u32>,
+    frame : u64,
+}
+
+impl TileCache {
+    pub fn new(slot_count : u32) -> TileCache {
+        TileCache {
+            slots : vec![None; slot_count as usize],
+            resident : HashMap::new(),
+            frame : 0,
+        }
+    }
+
+    pub fn slot_count(&self) -> u32 {
+        self.slots.len() as u32
+    }
+
+    pub fn slot_of(&self, tile : u32) -> Option<u32> {
+        self.resident.get(&tile).copied()
+    }
+
+    pub fn resident_count(&self) -> usize {
+        self.resident.len()
+    }
+
+    // One frame of feedback. Resident tiles in `hot` count as used this frame, missing ones are given
+    // a free slot or the least recently used one, at most `max_loads` of them. Tiles used this frame are
+    // never evicted, so hot tiles past the cache's capacity stay missing
+    pub fn update(&mut self, hot : &[u32], max_loads : usize) -> Vec<TileLoad> {
+        self.frame += 1;
+
+        let mut missing = Vec::new();
+        for &tile in hot {
+            match self.resident.get(&tile) {
+                Some(&slot) => self.slots[slot as usize] = Some(CachedTile { tile, last_used : self.frame }),
+                None if !missing.contains(&tile) => missing.push(tile),
+                None => (),
+            }
+        }
+
+        let mut loads = Vec::new();
+        for tile in missing.into_iter().take(max_loads) {
+            // Empty slots sort before any used one
+            let coldest = self.slots.iter()
+            .enumerate()
+            .filter(|(_, cached)| cached.map_or(true, |cached| cached.last_used < self.frame))
+            .min_by_key(|(_, cached)| cached.map(|cached| cached.last_used))
+            .map(|(slot, cached)| (slot as u32, cached.map(|cached| cached.tile)));
+            let Some((slot, evicted)) = coldest else { break };
+
+            if let Some(evicted) = evicted {
+                self.resident.remove(&evicted);
+            }
+            self.slots[slot as usize] = Some(CachedTile { tile, last_used : self.frame });
+            self.resident.insert(tile, slot);
+            loads.push(TileLoad { tile, slot, evicted });
+        }
+
+        loads
+    }
+
+    // Slot of every tile, NOT_RESIDENT for the missing ones
+    pub fn page_table(&self, tile_count : u32) -> Vec<u32> {
+        (0..tile_count).map(|tile| self.slot_of(tile).unwrap_or(NOT_RESIDENT)).collect()
+    }
+}
+
+// Tiles whose bit is set in the feedback words, ascending
+pub fn feedback_tiles(words : &[u32], tile_count : u32) -> Vec<u32> {
+    words.iter()
+    .enumerate()
+    .flat_map(|(word, &bits)| (0..32).filter(move |bit| bits & (1 << bit) != 0).map(move |bit| word as u32 * 32 + bit))
+    .filter(|&tile| tile < tile_count)
+    .collect()
+}
+
+// Mean of tightly packed RGBA8 texels, a stand-in while a tile isn't resident
+pub fn average_color(bytes : &[u8]) -> [f32; 4] {
+    let texels = (bytes.len() / 4).max(1) as f32;
+    let mut sum = [0.0; 4];
+    for texel in bytes.chunks_exact(4) {
+        for (channel, &value) in sum.iter_mut().zip(texel) {
+            *channel += value as f32 / 255.0;
+        }
+    }
+
+    sum.map(|channel| channel / texels)
+}
+
+// Keeps the tiles the GPU samples at full resolution in a fixed size texture array, every other tile
+// is drawn with its fallback color. Per frame, outside of a render pass:
+// - process_feedback, before the draws, uploads tiles requested a few frames ago
+// - the draws sample through the page table and write the tiles they want into an R32_UINT image
+// - record_feedback turns that image into one bit per tile
+// Tiles are RGBA8 sRGB of a single size, loaded through `load` when a slot opens up for them
+pub struct TextureStreamer {
+    // Tiles loaded per frame at most, the rest wait for a later frame
+    pub max_uploads_per_frame : usize,
+    allocator : Arc<VulkanAllocation>,
+    shader : ComputeShader,
+    descriptor_set_allocator : StandardDescriptorSetAllocator,
+    tile_count : u32,
+    tile_extent : [u32; 2],
+    cache : TileCache,
+    cache_image : Arc<Image>,
+    cache_view : Arc<ImageView>,
+    sampler : Arc<Sampler>,
+    page_table : Subbuffer<[u32]>,
+    fallback_colors : Subbuffer<[[f32; 4]]>,
+    feedback : Subbuffer<[u32]>,
+    feedback_view : Arc<BufferView>,
+    // Copies of the feedback, read once the frame that wrote them finished
+    readbacks : Vec<Subbuffer<[u32]>>,
+    next_readback : usize,
+    uploads : UploadQueue,
+    load : Box<dyn FnMut(u32) -> Result<Vec<u8>, EngineError>>,
+}
+
+impl TextureStreamer {
+    // One tile per fallback color. `load` returns the tightly packed texels of a tile
+    pub fn new(toolset : &VulkanToolset, tile_extent : [u32; 2], slot_count : u32, fallback_colors : &[[f32; 4]], load : impl FnMut(u32) -> Result<Vec<u8>, EngineError> + 'static) -> Result<TextureStreamer, EngineError> {
+        let tile_count = fallback_colors.len() as u32;
+        if tile_count > MAX_TILES {
+            return Err(EngineError::BufferRange { offset : 0, len : tile_count as u64, buffer_len : MAX_TILES as u64 });
+        }
+
+        let device = &toolset.logical_device;
+        let allocator = &toolset.memory_allocator;
+        let module = feedback_cs::load(device.clone())?;
+
+        let cache_image = Image::new(
+            allocator.general_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R8G8B8A8_SRGB,
+                extent: [tile_extent[0], tile_extent[1], 1],
+                array_layers: slot_count.max(1),
+                usage: ImageUsage::TRANSFER_DST | ImageUsage::TRANSFER_SRC | ImageUsage::SAMPLED,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        )?;
+        allocator.tracker.track_image(AllocationCategory::Texture, &cache_image);
+
+        // A single slot would otherwise get a plain 2D view
+        let cache_view = ImageView::new(
+            cache_image.clone(),
+            ImageViewCreateInfo {
+                view_type: ImageViewType::Dim2dArray,
+                ..ImageViewCreateInfo::from_image(&cache_image)
+            },
+        )?;
+
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+            },
+        )?;
+
+        let tiles = tile_count.max(1) as DeviceSize;
+        let page_table = upload_buffer(allocator, &toolset.device_queue, BufferUsage::STORAGE_BUFFER, (0..tiles).map(|_| NOT_RESIDENT))?;
+        let fallback_colors = match fallback_colors.is_empty() {
+            true => upload_buffer(allocator, &toolset.device_queue, BufferUsage::STORAGE_BUFFER, [[0.0; 4]])?,
+            false => upload_buffer(allocator, &toolset.device_queue, BufferUsage::STORAGE_BUFFER, fallback_colors.iter().copied())?,
+        };
+
+        let words = tiles.div_ceil(32);
+        let feedback = upload_buffer(allocator, &toolset.device_queue, BufferUsage::STORAGE_TEXEL_BUFFER, (0..words).map(|_| 0u32))?;
+        let feedback_view = allocator.create_buffer_view(&feedback, Format::R32_UINT)?;
+
+        // Zeroed, so frames before the first copy report nothing
+        let readbacks = (0..toolset.config.frames_in_flight as usize + 1)
+        .map(|_| Self::create_readback(allocator, words))
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TextureStreamer {
+            max_uploads_per_frame : 4,
+            allocator : allocator.clone(),
+            shader : ComputeShader::new(&module, LOCAL_SIZE, device.clone()),
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+            tile_count,
+            tile_extent,
+            cache : TileCache::new(slot_count.max(1)),
+            cache_image,
+            cache_view,
+            sampler,
+            page_table,
+            fallback_colors,
+            feedback,
+            feedback_view,
+            readbacks,
+            next_readback : 0,
+            uploads : UploadQueue::new(),
+            load : Box::new(load),
+        })
+    }
+
+    pub fn tile_count(&self) -> u32 {
+        self.tile_count
+    }
+
+    pub fn tile_extent(&self) -> [u32; 2] {
+        self.tile_extent
+    }
+
+    pub fn cache(&self) -> &TileCache {
+        &self.cache
+    }
+
+    // Layer `slot` holds the tile the page table points at
+    pub fn cache_image(&self) -> &Arc<Image> {
+        &self.cache_image
+    }
+
+    // Cache slot per tile, NOT_RESIDENT when the fallback color has to do
+    pub fn page_table(&self) -> &Subbuffer<[u32]> {
+        &self.page_table
+    }
+
+    // Bindings for the shaders that sample the tiles, from `first_binding` on:
+    //  sampler2DArray cache, readonly buffer { uint slots[]; }, readonly buffer { vec4 fallback_colors[]; }
+    pub fn write_descriptors(&self, first_binding : u32) -> [WriteDescriptorSet; 3] {
+        [
+            WriteDescriptorSet::image_view_sampler(first_binding, self.cache_view.clone(), self.sampler.clone()),
+            WriteDescriptorSet::buffer(first_binding + 1, self.page_table.clone()),
+            WriteDescriptorSet::buffer(first_binding + 2, self.fallback_colors.clone()),
+        ]
+    }
+
+    // Record outside of a render pass, before the draws that sample the tiles. Reads the feedback copied
+    // frames_in_flight frames ago, updates the cache from it, then records the tile uploads, the new page
+    // table and a copy of the last frame's feedback. Feedback still in use by the GPU is skipped
+    pub fn process_feedback(&mut self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) -> Result<(), EngineError> {
+        let readback = self.readbacks[self.next_readback].clone();
+        let hot = match readback.read() {
+            Ok(words) => feedback_tiles(&words, self.tile_count),
+            Err(_) => Vec::new(),
+        };
+
+        for load in self.cache.update(&hot, self.max_uploads_per_frame) {
+            let bytes = (self.load)(load.tile)?;
+            let region = ImageRegion {
+                array_layers : load.slot..load.slot + 1,
+                ..ImageRegion::new([self.tile_extent[0], self.tile_extent[1], 1])
+            };
+            self.uploads.push_image(&self.allocator, &self.cache_image, &bytes, region)?;
+        }
+        self.uploads.record(builder)?;
+
+        if self.tile_count > 0 {
+            builder.update_buffer(self.page_table.clone().slice(..self.tile_count as DeviceSize), self.cache.page_table(self.tile_count).into_boxed_slice())?;
+        }
+
+        builder.copy_buffer(CopyBufferInfo::buffers(self.feedback.clone(), readback))?
+        .fill_buffer(self.feedback.clone(), 0)?;
+        self.next_readback = (self.next_readback + 1) % self.readbacks.len();
+
+        Ok(())
+    }
+
+    // Record outside of a render pass, after the draws wrote `tile_ids`. The image is R32_UINT with
+    // STORAGE usage and holds tile index + 1 where a draw wanted a tile at full resolution, 0 elsewhere
+    pub fn record_feedback(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, tile_ids : &Arc<ImageView>) -> Result<(), EngineError> {
+        let format = tile_ids.format();
+        if format != Format::R32_UINT {
+            return Err(EngineError::UnsupportedFeature(format!("tile feedback is read as R32_UINT, not {format:?}")));
+        }
+
+        let layout = self.shader.pipeline.layout();
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view(0, tile_ids.clone()),
+                WriteDescriptorSet::buffer_view(1, self.feedback_view.clone()),
+            ],
+            [],
+        )?;
+
+        let [width, height, _] = tile_ids.image().extent();
+        builder.bind_pipeline_compute(self.shader.pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?
+        .push_constants(layout.clone(), 0, self.tile_count)?
+        .dispatch(self.shader.group_counts([width, height, 1]))?;
+
+        Ok(())
+    }
+
+    fn create_readback(allocator : &VulkanAllocation, words : DeviceSize) -> Result<Subbuffer<[u32]>, EngineError> {
+        let buffer = Buffer::from_iter(
+            allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            (0..words).map(|_| 0u32),
+        )?;
+        allocator.tracker.track_buffer(AllocationCategory::Staging, buffer.buffer());
+
+        Ok(buffer)
+    }
+}
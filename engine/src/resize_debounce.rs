@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+// Coalesces resize events so dragging a window edge recreates the swapchain a few times instead of once
+// per event. A resize is carried out once no event arrived for the quiet period, or once the requested
+// extent stayed the same for a frame. Out of date swapchains can't be presented to and skip the wait
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResizeDebouncer {
+    quiet : Duration,
+    // Latest requested extent and when it was requested, None once it was carried out
+    pending : Option<([u32; 2], Instant)>,
+    // Requested extent at the previous poll, to tell whether it was stable for a frame
+    polled : Option<[u32; 2]>,
+    forced : bool,
+    recreations : u64,
+}
+
+impl ResizeDebouncer {
+    pub fn new(quiet : Duration) -> ResizeDebouncer {
+        ResizeDebouncer {
+            quiet,
+            pending : None,
+            polled : None,
+            forced : false,
+            recreations : 0,
+        }
+    }
+
+    // Swapchain recreations poll agreed to so far
+    pub fn recreations(&self) -> u64 {
+        self.recreations
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.forced || self.pending.is_some()
+    }
+
+    // Call for every resize event, and for suboptimal swapchains which can still be presented to
+    pub fn request(&mut self, extent : [u32; 2], now : Instant) {
+        self.pending = Some((extent, now));
+    }
+
+    // For out of date swapchains, the next poll recreates regardless of timing
+    pub fn force(&mut self) {
+        self.forced = true;
+    }
+
+    // When the quiet period of the latest request ends, for event loops that would otherwise sleep past it
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending.map(|(_, requested_at)| requested_at + self.quiet)
+    }
+
+    // Call once per frame before acquiring, true when the swapchain should be recreated now. The recreation
+    // should use the window's size at that point rather than the requested extent, which may already be stale
+    pub fn poll(&mut self, now : Instant) -> bool {
+        let due = match self.pending {
+            _ if self.forced => true,
+            Some((extent, requested_at)) => now.duration_since(requested_at) >= self.quiet || self.polled == Some(extent),
+            None => false,
+        };
+
+        if due {
+            self.pending = None;
+            self.polled = None;
+            self.forced = false;
+            self.recreations += 1;
+        } else {
+            self.polled = self.pending.map(|(extent, _)| extent);
+        }
+
+        due
+    }
+}
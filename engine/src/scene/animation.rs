@@ -0,0 +1,61 @@
+use glam::Mat4;
+
+use super::{camera::Matrix4, transform::Transform};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bone {
+    // -1 for root bones
+    pub parent_index : i32,
+    // Takes mesh space into bone space at bind time
+    pub bind_pose_inverse : Matrix4,
+    // Relative to the parent, or to the mesh for root bones
+    pub local_transform : Transform,
+}
+
+impl Bone {
+    pub fn new(parent_index : i32, bind_pose_inverse : Matrix4, local_transform : Transform) -> Bone {
+        Bone { parent_index, bind_pose_inverse, local_transform }
+    }
+}
+
+// Flat bone hierarchy, parents always come before their children so one pass in order
+// resolves every chain. Animate by writing the local transforms
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Skeleton {
+    pub bones : Vec<Bone>,
+}
+
+impl Skeleton {
+    pub fn new(bones : Vec<Bone>) -> Skeleton {
+        for (index, bone) in bones.iter().enumerate() {
+            assert!(bone.parent_index < index as i32, "bone {index} must come after its parent {}", bone.parent_index);
+        }
+
+        Skeleton { bones }
+    }
+
+    // Mesh space transform of every bone in its current pose
+    pub fn global_matrices(&self) -> Vec<Matrix4> {
+        let mut globals : Vec<Mat4> = Vec::with_capacity(self.bones.len());
+
+        for bone in &self.bones {
+            let local = Mat4::from_cols_array_2d(&bone.local_transform.matrix());
+            let global = match usize::try_from(bone.parent_index) {
+                Ok(parent) => globals[parent] * local,
+                Err(_) => local,
+            };
+            globals.push(global);
+        }
+
+        globals.iter().map(Mat4::to_cols_array_2d).collect()
+    }
+
+    // Bind pose vertices to their current pose, one matrix per bone for the skinning pass
+    pub fn compute_skinning_matrices(&self) -> Vec<Matrix4> {
+        self.global_matrices()
+        .iter()
+        .zip(&self.bones)
+        .map(|(global, bone)| (Mat4::from_cols_array_2d(global) * Mat4::from_cols_array_2d(&bone.bind_pose_inverse)).to_cols_array_2d())
+        .collect()
+    }
+}
@@ -0,0 +1,125 @@
+// Matrices are column major with Vulkan clip space: Y pointing down and depth in 0..1
+pub type Matrix4 = [[f32; 4]; 4];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective { fov_y : f32, near : f32, far : f32 },
+    // Half of the visible height in world units, the width follows the aspect ratio
+    Orthographic { half_height : f32, near : f32, far : f32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub position : [f32; 3],
+    pub target : [f32; 3],
+    pub up : [f32; 3],
+    pub projection : Projection,
+}
+
+impl Camera {
+    pub fn perspective(position : [f32; 3], target : [f32; 3], fov_y : f32) -> Camera {
+        Camera {
+            position,
+            target,
+            up : [0.0, 1.0, 0.0],
+            projection : Projection::Perspective { fov_y, near : 0.1, far : 100.0 },
+        }
+    }
+
+    // Looks straight down the Y axis with -Z at the top of the screen
+    pub fn top_down(height : f32, half_extent : f32) -> Camera {
+        Camera {
+            position : [0.0, height, 0.0],
+            target : [0.0, 0.0, 0.0],
+            up : [0.0, 0.0, -1.0],
+            projection : Projection::Orthographic { half_height : half_extent, near : 0.0, far : height * 2.0 },
+        }
+    }
+
+    // Looks along a directional light, `direction` points from the light into the scene. Everything within
+    // `radius` of `center` is inside the view and its depth range, as a shadow map needs. Use an aspect of 1.0
+    pub fn directional_light(direction : [f32; 3], center : [f32; 3], radius : f32) -> Camera {
+        let direction = normalize(direction);
+        // Y can't be up for a light shining straight up or down
+        let up = if direction[1].abs() > 0.99 { [0.0, 0.0, -1.0] } else { [0.0, 1.0, 0.0] };
+
+        Camera {
+            position : [0, 1, 2].map(|i| center[i] - direction[i] * radius * 2.0),
+            target : center,
+            up,
+            projection : Projection::Orthographic { half_height : radius, near : radius, far : radius * 3.0 },
+        }
+    }
+
+    pub fn view_matrix(&self) -> Matrix4 {
+        let forward = normalize(sub(self.target, self.position));
+        let right = normalize(cross(forward, self.up));
+        let up = cross(right, forward);
+
+        [
+            [right[0], up[0], -forward[0], 0.0],
+            [right[1], up[1], -forward[1], 0.0],
+            [right[2], up[2], -forward[2], 0.0],
+            [-dot(right, self.position), -dot(up, self.position), dot(forward, self.position), 1.0],
+        ]
+    }
+
+    pub fn projection_matrix(&self, aspect : f32) -> Matrix4 {
+        match self.projection {
+            Projection::Perspective { fov_y, near, far } => {
+                let f = 1.0 / (fov_y / 2.0).tan();
+
+                [
+                    [f / aspect, 0.0, 0.0, 0.0],
+                    [0.0, -f, 0.0, 0.0],
+                    [0.0, 0.0, far / (near - far), -1.0],
+                    [0.0, 0.0, near * far / (near - far), 0.0],
+                ]
+            },
+            Projection::Orthographic { half_height, near, far } => {
+                let half_width = half_height * aspect;
+
+                [
+                    [1.0 / half_width, 0.0, 0.0, 0.0],
+                    [0.0, -1.0 / half_height, 0.0, 0.0],
+                    [0.0, 0.0, 1.0 / (near - far), 0.0],
+                    [0.0, 0.0, near / (near - far), 1.0],
+                ]
+            },
+        }
+    }
+
+    pub fn view_projection(&self, aspect : f32) -> Matrix4 {
+        multiply(&self.projection_matrix(aspect), &self.view_matrix())
+    }
+}
+
+pub fn multiply(a : &Matrix4, b : &Matrix4) -> Matrix4 {
+    let mut result = [[0.0; 4]; 4];
+
+    for (column, b_column) in result.iter_mut().zip(b) {
+        for (row, value) in column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b_column[k]).sum();
+        }
+    }
+
+    result
+}
+
+fn sub(a : [f32; 3], b : [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a : [f32; 3], b : [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a : [f32; 3], b : [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(v : [f32; 3]) -> [f32; 3] {
+    let length = dot(v, v).sqrt();
+
+    [v[0] / length, v[1] / length, v[2] / length]
+}
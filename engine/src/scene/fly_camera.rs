@@ -0,0 +1,84 @@
+use winit::event::VirtualKeyCode;
+
+use crate::input::InputState;
+use super::camera::Camera;
+
+// Keeps the view from flipping over when looking straight up or down
+const MAX_PITCH : f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+// First person controller: WASD moves along the view, space and shift move up and down,
+// the mouse looks around while the cursor is grabbed
+pub struct FlyCameraController {
+    // World units per second
+    pub speed : f32,
+    // Radians per pixel of raw mouse motion
+    pub sensitivity : f32,
+    yaw : f32,
+    pitch : f32,
+}
+
+impl FlyCameraController {
+    // Starts looking where the camera currently looks
+    pub fn new(camera : &Camera) -> FlyCameraController {
+        let [x, y, z] = sub(camera.target, camera.position);
+        let horizontal = (x * x + z * z).sqrt();
+
+        FlyCameraController {
+            speed : 3.0,
+            sensitivity : 0.002,
+            yaw : x.atan2(-z),
+            pitch : y.atan2(horizontal).clamp(-MAX_PITCH, MAX_PITCH),
+        }
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    pub fn update(&mut self, camera : &mut Camera, input : &InputState, delta : f32) {
+        if input.is_cursor_grabbed() {
+            let (dx, dy) = input.mouse_delta();
+            self.yaw += dx as f32 * self.sensitivity;
+            self.pitch = (self.pitch - dy as f32 * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        let forward = self.forward();
+        let right = [self.yaw.cos(), 0.0, self.yaw.sin()];
+
+        let axis = |positive, negative| {
+            input.is_key_pressed(positive) as i32 as f32 - input.is_key_pressed(negative) as i32 as f32
+        };
+        let forward_amount = axis(VirtualKeyCode::W, VirtualKeyCode::S);
+        let right_amount = axis(VirtualKeyCode::D, VirtualKeyCode::A);
+        let up_amount = axis(VirtualKeyCode::Space, VirtualKeyCode::LShift);
+
+        let step = self.speed * delta;
+        for i in 0..3 {
+            camera.position[i] += (forward[i] * forward_amount + right[i] * right_amount) * step;
+        }
+        camera.position[1] += up_amount * step;
+
+        camera.up = [0.0, 1.0, 0.0];
+        camera.target = [
+            camera.position[0] + forward[0],
+            camera.position[1] + forward[1],
+            camera.position[2] + forward[2],
+        ];
+    }
+
+    // Zero yaw looks down -Z, positive yaw turns right
+    fn forward(&self) -> [f32; 3] {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+
+        [cos_pitch * sin_yaw, sin_pitch, -cos_pitch * cos_yaw]
+    }
+}
+
+fn sub(a : [f32; 3], b : [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
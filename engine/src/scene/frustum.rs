@@ -0,0 +1,31 @@
+use super::camera::Matrix4;
+
+// Planes point inwards as [nx, ny, nz, d] with unit normals, in left, right, top, bottom, near, far order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes : [[f32; 4]; 6],
+}
+
+impl Frustum {
+    // Extracts the planes from the rows of a view projection matrix, near is at depth 0
+    pub fn from_view_projection(matrix : &Matrix4) -> Frustum {
+        let row = |i : usize| [matrix[0][i], matrix[1][i], matrix[2][i], matrix[3][i]];
+        let add = |a : [f32; 4], b : [f32; 4]| [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]];
+        let sub = |a : [f32; 4], b : [f32; 4]| [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]];
+
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let planes = [add(w, x), sub(w, x), add(w, y), sub(w, y), z, sub(w, z)].map(|plane| {
+            let length = (plane[0] * plane[0] + plane[1] * plane[1] + plane[2] * plane[2]).sqrt();
+            plane.map(|value| value / length)
+        });
+
+        Frustum { planes }
+    }
+
+    // Conservative, spheres near the corners may pass while being outside
+    pub fn intersects_sphere(&self, center : [f32; 3], radius : f32) -> bool {
+        self.planes.iter().all(|plane| {
+            plane[0] * center[0] + plane[1] * center[1] + plane[2] * center[2] + plane[3] >= -radius
+        })
+    }
+}
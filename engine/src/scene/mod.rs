@@ -0,0 +1,6 @@
+pub mod animation;
+pub mod camera;
+pub mod fly_camera;
+pub mod frustum;
+pub mod terrain;
+pub mod transform;
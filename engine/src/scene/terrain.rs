@@ -0,0 +1,291 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{graphics::{depth_stencil::{DepthState, DepthStencilState}, input_assembly::{InputAssemblyState, PrimitiveTopology}, rasterization::RasterizationState, tessellation::TessellationState, vertex_input::{Vertex, VertexDefinition}, viewport::Viewport}, GraphicsPipeline, Pipeline, PipelineBindPoint},
+    render_pass::Subpass,
+    shader::ShaderModule
+};
+
+use crate::vulkan::{scissor::ScissorState, texture::Texture2D, vulkan::{MultisampleConfig, PipelineStates, VulkanToolset}};
+use super::camera::{Camera, Matrix4};
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 position;
+            layout(location = 1) in vec2 uv;
+
+            layout(location = 0) out vec2 v_uv;
+
+            void main() {
+                gl_Position = vec4(position, 1.0);
+                v_uv = uv;
+            }
+        ",
+    }
+}
+
+mod tcs {
+    vulkano_shaders::shader! {
+        ty: "tess_ctrl",
+        src: "
+            #version 460
+
+            layout(vertices = 3) out;
+
+            layout(push_constant) uniform Constants {
+                mat4 view_projection;
+                vec3 camera_position;
+                float height_scale;
+                float max_level;
+                float lod_distance;
+            } constants;
+
+            layout(location = 0) in vec2 v_uv[];
+            layout(location = 0) out vec2 tc_uv[];
+
+            void main() {
+                gl_out[gl_InvocationID].gl_Position = gl_in[gl_InvocationID].gl_Position;
+                tc_uv[gl_InvocationID] = v_uv[gl_InvocationID];
+
+                if (gl_InvocationID == 0) {
+                    // Patches close to the camera get the most subdivisions
+                    vec3 center = (gl_in[0].gl_Position.xyz + gl_in[1].gl_Position.xyz + gl_in[2].gl_Position.xyz) / 3.0;
+                    float closeness = 1.0 - clamp(distance(center, constants.camera_position) / constants.lod_distance, 0.0, 1.0);
+                    float level = max(1.0, constants.max_level * closeness);
+
+                    gl_TessLevelOuter[0] = level;
+                    gl_TessLevelOuter[1] = level;
+                    gl_TessLevelOuter[2] = level;
+                    gl_TessLevelInner[0] = level;
+                }
+            }
+        ",
+    }
+}
+
+mod tes {
+    vulkano_shaders::shader! {
+        ty: "tess_eval",
+        src: "
+            #version 460
+
+            layout(triangles, equal_spacing, ccw) in;
+
+            layout(push_constant) uniform Constants {
+                mat4 view_projection;
+                vec3 camera_position;
+                float height_scale;
+                float max_level;
+                float lod_distance;
+            } constants;
+
+            layout(set = 0, binding = 0) uniform sampler2D heightmap;
+
+            layout(location = 0) in vec2 tc_uv[];
+            layout(location = 0) out float te_height;
+
+            void main() {
+                vec3 position = gl_TessCoord.x * gl_in[0].gl_Position.xyz
+                    + gl_TessCoord.y * gl_in[1].gl_Position.xyz
+                    + gl_TessCoord.z * gl_in[2].gl_Position.xyz;
+                vec2 uv = gl_TessCoord.x * tc_uv[0] + gl_TessCoord.y * tc_uv[1] + gl_TessCoord.z * tc_uv[2];
+
+                float height = textureLod(heightmap, uv, 0.0).r;
+                position.y += height * constants.height_scale;
+
+                gl_Position = constants.view_projection * vec4(position, 1.0);
+                te_height = height;
+            }
+        ",
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) in float te_height;
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                f_color = vec4(mix(vec3(0.15, 0.35, 0.1), vec3(0.95, 0.95, 0.9), te_height), 1.0);
+            }
+        ",
+    }
+}
+
+#[derive(BufferContents, Vertex, Clone, Copy)]
+#[repr(C)]
+pub struct TerrainVertex {
+    #[format(R32G32B32_SFLOAT)]
+    pub position : [f32; 3],
+    #[format(R32G32_SFLOAT)]
+    pub uv : [f32; 2],
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct TerrainConstants {
+    view_projection : Matrix4,
+    camera_position : [f32; 3],
+    height_scale : f32,
+    max_level : f32,
+    lod_distance : f32,
+}
+
+#[derive(Clone)]
+pub struct Terrain {
+    pub vertex_buffer : Subbuffer<[TerrainVertex]>,
+    pub pipeline : Arc<GraphicsPipeline>,
+    pub height_scale : f32,
+    pub max_tessellation : f32,
+    // Patches further away than this are not subdivided
+    pub lod_distance : f32,
+    descriptor_set : Arc<PersistentDescriptorSet>,
+}
+
+impl Terrain {
+    // Flat grid of `resolution` x `resolution` cells on the XZ plane, centered on the origin
+    pub fn new(toolset : &VulkanToolset, heightmap : Texture2D, resolution : u32, size : f32) -> Terrain {
+        let window = toolset.get_vulkan_window();
+        let subpass = Subpass::from(window.get_render_pass(), 0).unwrap();
+
+        Self::for_subpass(toolset, heightmap, resolution, size, subpass, window.get_window_viewport())
+    }
+
+    pub fn for_subpass(toolset : &VulkanToolset, heightmap : Texture2D, resolution : u32, size : f32, subpass : Subpass, viewport : Viewport) -> Terrain {
+        let vertex_buffer = Buffer::from_iter(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            Self::grid_vertices(resolution, size),
+        ).expect("failed to create terrain buffer");
+
+        let pipeline = Self::create_pipeline(toolset, subpass, viewport);
+
+        let descriptor_set_allocator = StandardDescriptorSetAllocator::new(toolset.logical_device.clone(), Default::default());
+        let descriptor_set = PersistentDescriptorSet::new(
+            &descriptor_set_allocator,
+            pipeline.layout().set_layouts()[0].clone(),
+            [heightmap.write_descriptor(0)],
+            [],
+        ).unwrap();
+
+        Terrain {
+            vertex_buffer,
+            pipeline,
+            height_scale : 1.0,
+            max_tessellation : 16.0,
+            lod_distance : 50.0,
+            descriptor_set,
+        }
+    }
+
+    // Viewport is baked into the pipeline, call after the swapchain was recreated
+    pub fn recreate_pipeline(&mut self, toolset : &VulkanToolset) {
+        let window = toolset.get_vulkan_window();
+        let subpass = Subpass::from(window.get_render_pass(), 0).unwrap();
+
+        self.pipeline = Self::create_pipeline(toolset, subpass, window.get_window_viewport());
+    }
+
+    pub fn record(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, camera : &Camera, aspect : f32) {
+        let constants = TerrainConstants {
+            view_projection : camera.view_projection(aspect),
+            camera_position : camera.position,
+            height_scale : self.height_scale,
+            max_level : self.max_tessellation,
+            lod_distance : self.lod_distance,
+        };
+
+        builder.bind_pipeline_graphics(self.pipeline.clone())
+        .unwrap()
+        .bind_descriptor_sets(PipelineBindPoint::Graphics, self.pipeline.layout().clone(), 0, self.descriptor_set.clone())
+        .unwrap()
+        .push_constants(self.pipeline.layout().clone(), 0, constants)
+        .unwrap()
+        .bind_vertex_buffers(0, self.vertex_buffer.clone())
+        .unwrap()
+        .draw(self.vertex_buffer.len() as u32, 1, 0, 0)
+        .unwrap();
+    }
+
+    fn create_pipeline(toolset : &VulkanToolset, subpass : Subpass, viewport : Viewport) -> Arc<GraphicsPipeline> {
+        let device = &toolset.logical_device;
+        assert!(device.enabled_features().tessellation_shader, "terrain rendering requires the tessellation_shader feature");
+
+        let load = |module : Arc<ShaderModule>| module.entry_point("main").unwrap();
+        let vs = load(vs::load(device.clone()).expect("failed to create shader module"));
+        let tcs = load(tcs::load(device.clone()).expect("failed to create shader module"));
+        let tes = load(tes::load(device.clone()).expect("failed to create shader module"));
+        let fs = load(fs::load(device.clone()).expect("failed to create shader module"));
+
+        let vertex_input_state = TerrainVertex::per_vertex()
+        .definition(&vs.info().input_interface)
+        .unwrap();
+
+        toolset.build_pipeline_for(vec![vs, tcs, tes, fs], PipelineStates {
+            vertex_input_state,
+            input_assembly_state : InputAssemblyState {
+                topology: PrimitiveTopology::PatchList,
+                ..Default::default()
+            },
+            tessellation_state : Some(TessellationState {
+                patch_control_points: 3,
+                ..Default::default()
+            }),
+            rasterization_state : RasterizationState::default(),
+            depth_stencil_state : DepthStencilState {
+                depth: Some(DepthState::simple()),
+                ..Default::default()
+            },
+            push_descriptor_set : None,
+            conservative_raster : None,
+            multisample : MultisampleConfig::default(),
+            dynamic_viewport : false,
+            scissor : ScissorState::default(),
+            color_write_masks : Vec::new(),
+            blend : None,
+            immutable_samplers : Vec::new(),
+            inline_uniform_blocks : Vec::new(),
+        }, subpass, viewport)
+    }
+
+    // Two counter clockwise (seen from above) triangle patches per cell
+    fn grid_vertices(resolution : u32, size : f32) -> Vec<TerrainVertex> {
+        let vertex = |x : u32, z : u32| {
+            let u = x as f32 / resolution as f32;
+            let v = z as f32 / resolution as f32;
+
+            TerrainVertex {
+                position : [(u - 0.5) * size, 0.0, (v - 0.5) * size],
+                uv : [u, v],
+            }
+        };
+
+        (0..resolution)
+        .flat_map(|z| (0..resolution).map(move |x| (x, z)))
+        .flat_map(|(x, z)| [
+            vertex(x, z), vertex(x, z + 1), vertex(x + 1, z + 1),
+            vertex(x + 1, z + 1), vertex(x + 1, z), vertex(x, z),
+        ])
+        .collect()
+    }
+}
@@ -0,0 +1,114 @@
+use std::ops::Mul;
+
+use glam::{Mat3, Mat4};
+use vulkano::buffer::BufferContents;
+
+pub use glam::{Quat, Vec3};
+
+use super::camera::Matrix4;
+
+// Scale, then rotation, then translation. Composition keeps the TRS form, so a parent with
+// non-uniform scale and a rotated child loses the resulting shear, as in most engines
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation : Vec3,
+    pub rotation : Quat,
+    pub scale : Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY : Transform = Transform {
+        translation : Vec3::ZERO,
+        rotation : Quat::IDENTITY,
+        scale : Vec3::ONE,
+    };
+
+    pub fn new(translation : Vec3, rotation : Quat, scale : Vec3) -> Transform {
+        Transform { translation, rotation, scale }
+    }
+
+    pub fn from_translation(translation : Vec3) -> Transform {
+        Transform { translation, ..Transform::IDENTITY }
+    }
+
+    pub fn from_rotation(rotation : Quat) -> Transform {
+        Transform { rotation, ..Transform::IDENTITY }
+    }
+
+    pub fn from_scale(scale : Vec3) -> Transform {
+        Transform { scale, ..Transform::IDENTITY }
+    }
+
+    // Column major model matrix, same layout as the camera matrices
+    pub fn matrix(&self) -> Matrix4 {
+        self.to_mat4().to_cols_array_2d()
+    }
+
+    // Transposed inverse of the model matrix, only the upper 3x3 is meaningful.
+    // Keeps normals perpendicular to their surface under non-uniform scale, use as mat3(normal_matrix)
+    pub fn normal_matrix(&self) -> Matrix4 {
+        let normal = Mat3::from_mat4(self.to_mat4()).inverse().transpose();
+        Mat4::from_mat3(normal).to_cols_array_2d()
+    }
+
+    // Exact for uniform scale, see the note on composition otherwise
+    pub fn inverse(&self) -> Transform {
+        let rotation = self.rotation.inverse();
+        let scale = self.scale.recip();
+
+        Transform {
+            translation : -(scale * (rotation * self.translation)),
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn transform_point(&self, point : Vec3) -> Vec3 {
+        self.translation + self.rotation * (self.scale * point)
+    }
+
+    // Linear for translation and scale, spherical for rotation, `t` in 0..1
+    pub fn lerp(&self, other : &Transform, t : f32) -> Transform {
+        Transform {
+            translation : self.translation.lerp(other.translation, t),
+            rotation : self.rotation.slerp(other.rotation, t),
+            scale : self.scale.lerp(other.scale, t),
+        }
+    }
+
+    fn to_mat4(self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+// `parent * child` places the child in the parent's space
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, child : Transform) -> Transform {
+        Transform {
+            translation : self.transform_point(child.translation),
+            rotation : self.rotation * child.rotation,
+            scale : self.scale * child.scale,
+        }
+    }
+}
+
+// Matches a `mat4` in a push constant block or a std140 uniform
+#[derive(BufferContents, Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct PushTransform {
+    pub model : Matrix4,
+}
+
+impl From<&Transform> for PushTransform {
+    fn from(transform : &Transform) -> Self {
+        PushTransform { model : transform.matrix() }
+    }
+}
@@ -1,3 +0,0 @@
-pub mod compute_test;
-pub mod image_test;
-pub mod window_test;
\ No newline at end of file
@@ -0,0 +1,47 @@
+// Accumulates frame time and hands it out in whole fixed steps, so simulation
+// behaves the same no matter how fast frames are rendered
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedTimestep {
+    step : f32,
+    max_accumulated : f32,
+    accumulator : f32,
+}
+
+impl FixedTimestep {
+    // Up to a quarter second of simulation is caught up after a stall
+    pub fn new(step : f32) -> FixedTimestep {
+        FixedTimestep::with_max_accumulated(step, step.max(0.25))
+    }
+
+    // Time beyond max_accumulated is dropped instead of simulated. Without the cap a slow frame
+    // leads to more steps, which make the next frame slower still (the spiral of death)
+    pub fn with_max_accumulated(step : f32, max_accumulated : f32) -> FixedTimestep {
+        assert!(step > 0.0, "the fixed step must be positive");
+        assert!(max_accumulated >= step, "at least one step must fit into the accumulated time");
+
+        FixedTimestep {
+            step,
+            max_accumulated,
+            accumulator : 0.0,
+        }
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    // Adds the frame delta and returns how many fixed steps to run this frame
+    pub fn advance(&mut self, delta : f32) -> u32 {
+        self.accumulator = (self.accumulator + delta.max(0.0)).min(self.max_accumulated);
+
+        let steps = (self.accumulator / self.step) as u32;
+        self.accumulator -= steps as f32 * self.step;
+        steps
+    }
+
+    // How far the leftover time is into the next step, in 0..1.
+    // Render interpolates between the previous and current fixed state by it
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
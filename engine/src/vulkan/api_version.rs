@@ -0,0 +1,15 @@
+use vulkano::Version;
+
+use crate::error::EngineError;
+
+// Version to create the instance with. Without a request the engine takes everything the library supports,
+// a request above that is an error rather than silently getting less. Vulkan 1.0 is the floor either way
+pub fn negotiate_api_version(requested : Option<Version>, supported : Version) -> Result<Version, EngineError> {
+    let version = requested.unwrap_or(supported);
+
+    if version > supported || version < Version::V1_0 {
+        return Err(EngineError::ApiVersion { requested : version, supported });
+    }
+
+    Ok(version)
+}
@@ -0,0 +1,92 @@
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use vulkano::{command_buffer::PrimaryAutoCommandBuffer, device::{Device, Queue}};
+
+use crate::error::EngineError;
+use super::timeline::TimelineSemaphore;
+
+// Orders work between a graphics and an async compute queue on one timeline semaphore. Every submission waits
+// for a value and signals a larger one, so a frame that renders an image at 1, processes it on compute at 2
+// and composites it at 3 submits (0, 1) to graphics, (1, 2) to compute and (2, 3) to graphics. Values are
+// up to the caller and have to grow with every submission. A wait must be for a value some earlier submission
+// signals, waiting for a later one on the same queue never finishes.
+// Both queues come from the graphics family, see plan_queues, so images need no ownership transfer.
+// Submissions go around vulkano's futures like TimelineSemaphore::submit, so vulkano neither sees nor waits
+// for them. Images and buffers they use must not go through a vulkano future or a host read or write until
+// `wait` reached the last value signaled with them, in_flight drops to 0 once every submission finished
+pub struct AsyncComputeScheduler {
+    graphics_queue : Arc<Queue>,
+    compute_queue : Arc<Queue>,
+    timeline : TimelineSemaphore,
+    // Command buffers of submissions that may still run, with the value they signal. Dropped after the
+    // timeline, which waits for every submission first
+    in_flight : VecDeque<(u64, Arc<PrimaryAutoCommandBuffer>)>,
+}
+
+impl AsyncComputeScheduler {
+    // Fails without timeline semaphore support, see DeviceCapabilities::timeline_semaphores
+    pub fn new(graphics_queue : Arc<Queue>, compute_queue : Arc<Queue>, device : &Arc<Device>) -> Result<AsyncComputeScheduler, EngineError> {
+        Ok(AsyncComputeScheduler {
+            graphics_queue,
+            compute_queue,
+            timeline : TimelineSemaphore::new(device, 0)?,
+            in_flight : VecDeque::new(),
+        })
+    }
+
+    // Runs `command_buffer` on the graphics queue once the timeline reached `wait_value`, then signals `signal_value`
+    pub fn submit_graphics(&mut self, command_buffer : Arc<PrimaryAutoCommandBuffer>, wait_value : u64, signal_value : u64) -> Result<(), EngineError> {
+        let queue = self.graphics_queue.clone();
+        self.submit(&queue, command_buffer, wait_value, signal_value)
+    }
+
+    // Same as submit_graphics on the compute queue
+    pub fn submit_compute(&mut self, command_buffer : Arc<PrimaryAutoCommandBuffer>, wait_value : u64, signal_value : u64) -> Result<(), EngineError> {
+        let queue = self.compute_queue.clone();
+        self.submit(&queue, command_buffer, wait_value, signal_value)
+    }
+
+    fn submit(&mut self, queue : &Arc<Queue>, command_buffer : Arc<PrimaryAutoCommandBuffer>, wait_value : u64, signal_value : u64) -> Result<(), EngineError> {
+        assert!(signal_value > wait_value, "a submission has to signal a value past the one it waits for, got {signal_value} after {wait_value}");
+
+        self.release_finished()?;
+        self.timeline.submit(queue, &[command_buffer.clone()], Some(wait_value), Some(signal_value))?;
+        self.in_flight.push_back((signal_value, command_buffer));
+
+        Ok(())
+    }
+
+    // Drops the command buffers of submissions the timeline has passed
+    fn release_finished(&mut self) -> Result<(), EngineError> {
+        let value = self.timeline.value()?;
+        self.in_flight.retain(|&(signal_value, _)| signal_value > value);
+
+        Ok(())
+    }
+
+    // Blocks until the timeline reached `value`, false when the timeout passed first. None waits forever
+    pub fn wait(&mut self, value : u64, timeout : Option<Duration>) -> Result<bool, EngineError> {
+        let reached = self.timeline.wait(value, timeout)?;
+        self.release_finished()?;
+
+        Ok(reached)
+    }
+
+    // Largest value signaled so far
+    pub fn value(&self) -> Result<u64, EngineError> {
+        self.timeline.value()
+    }
+
+    // Submissions whose command buffers are still held
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    pub fn graphics_queue(&self) -> &Arc<Queue> {
+        &self.graphics_queue
+    }
+
+    pub fn compute_queue(&self) -> &Arc<Queue> {
+        &self.compute_queue
+    }
+}
@@ -0,0 +1,238 @@
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, BlitImageInfo, ClearColorImageInfo, CommandBufferUsage, ImageBlit},
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    format::{ClearColorValue, FormatFeatures},
+    image::{sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo}, view::{ImageView, ImageViewCreateInfo, ImageViewType}, Image, ImageSubresourceLayers, ImageSubresourceRange, ImageTiling, ImageUsage},
+    pipeline::{Pipeline, PipelineBindPoint},
+    sync::{self, GpuFuture}
+};
+
+use crate::error::EngineError;
+use super::vulkan::{ComputeShader, VulkanToolset};
+
+mod resample_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D src;
+            layout(set = 0, binding = 1) uniform writeonly image2D dst;
+
+            layout(push_constant) uniform Region {
+                ivec2 offset;
+                ivec2 extent;
+            } region;
+
+            void main() {
+                ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                if (any(greaterThanEqual(pixel, imageSize(dst)))) {
+                    return;
+                }
+
+                // Letterbox bars stay black, the same color the blit path clears them to
+                ivec2 local = pixel - region.offset;
+                vec4 color = vec4(0.0, 0.0, 0.0, 1.0);
+                if (all(greaterThanEqual(local, ivec2(0))) && all(lessThan(local, region.extent))) {
+                    color = textureLod(src, (vec2(local) + 0.5) / vec2(region.extent), 0.0);
+                }
+
+                imageStore(dst, pixel, color);
+            }
+        ",
+    }
+}
+
+const LOCAL_SIZE : [u32; 3] = [8, 8, 1];
+const BAR_COLOR : [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlitFit {
+    // Fills the destination, distorting the image when the aspect ratios differ
+    #[default]
+    Stretch,
+    // Keeps the aspect ratio, centered between black bars
+    Letterbox,
+}
+
+impl BlitFit {
+    // Offset and extent of the part of `dst` the image is scaled into
+    pub fn region(&self, src : [u32; 2], dst : [u32; 2]) -> ([u32; 2], [u32; 2]) {
+        match self {
+            BlitFit::Stretch => ([0, 0], dst),
+            BlitFit::Letterbox => letterbox_region(src, dst),
+        }
+    }
+}
+
+// Largest centered region of `dst` with the aspect ratio of `src`, rounded to whole pixels
+pub fn letterbox_region(src : [u32; 2], dst : [u32; 2]) -> ([u32; 2], [u32; 2]) {
+    let scaled = |len : u32, numerator : u32, denominator : u32| {
+        let len = (len as u64 * numerator as u64 + denominator as u64 / 2) / denominator.max(1) as u64;
+        len.max(1) as u32
+    };
+
+    // Compare the aspect ratios without dividing, the wider one fills the destination width
+    let extent = match src[0] as u64 * dst[1] as u64 >= src[1] as u64 * dst[0] as u64 {
+        true => [dst[0], scaled(src[1], dst[0], src[0]).min(dst[1])],
+        false => [scaled(src[0], dst[1], src[1]).min(dst[0]), dst[1]],
+    };
+
+    ([(dst[0] - extent[0]) / 2, (dst[1] - extent[1]) / 2], extent)
+}
+
+// Stretching blit_image_fit
+pub fn blit_image(toolset : &VulkanToolset, src : &Arc<Image>, dst : &Arc<Image>, filter : Filter) -> Result<(), EngineError> {
+    blit_image_fit(toolset, src, dst, filter, BlitFit::Stretch)
+}
+
+// Scales the first mip level of `src` into the first mip level of `dst` and waits for it. Uses a
+// blit when both formats support it, which needs TRANSFER_SRC on `src` and TRANSFER_DST on `dst`.
+// Otherwise falls back to resample_image, see there for what it needs
+pub fn blit_image_fit(toolset : &VulkanToolset, src : &Arc<Image>, dst : &Arc<Image>, filter : Filter, fit : BlitFit) -> Result<(), EngineError> {
+    let blit_src = FormatFeatures::BLIT_SRC | filter_features(filter);
+    let blittable = src.usage().intersects(ImageUsage::TRANSFER_SRC)
+        && dst.usage().intersects(ImageUsage::TRANSFER_DST)
+        && supports(toolset, src, blit_src)?
+        && supports(toolset, dst, FormatFeatures::BLIT_DST)?;
+    if !blittable {
+        return resample_image(toolset, src, dst, filter, fit);
+    }
+
+    let layers = src.array_layers().min(dst.array_layers());
+    let subresource = |image : &Arc<Image>| ImageSubresourceLayers {
+        mip_level : 0,
+        array_layers : 0..layers,
+        ..image.subresource_layers()
+    };
+    let [src_width, src_height, _] = src.extent();
+    let [dst_width, dst_height, _] = dst.extent();
+    let (offset, extent) = fit.region([src_width, src_height], [dst_width, dst_height]);
+
+    let queue = &toolset.device_queue;
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )?;
+
+    // Only needed when the image doesn't cover the whole destination
+    if extent != [dst_width, dst_height] {
+        builder.clear_color_image(ClearColorImageInfo {
+            clear_value : ClearColorValue::Float(BAR_COLOR),
+            ..ClearColorImageInfo::image(dst.clone())
+        })?;
+    }
+
+    builder.blit_image(BlitImageInfo {
+        regions : [ImageBlit {
+            src_subresource : subresource(src),
+            src_offsets : [[0, 0, 0], [src_width, src_height, 1]],
+            dst_subresource : subresource(dst),
+            dst_offsets : [[offset[0], offset[1], 0], [offset[0] + extent[0], offset[1] + extent[1], 1]],
+            ..Default::default()
+        }].into_iter().collect(),
+        filter,
+        ..BlitImageInfo::images(src.clone(), dst.clone())
+    })?;
+
+    sync::now(toolset.logical_device.clone())
+    .then_execute(queue.clone(), builder.build()?)?
+    .then_signal_fence_and_flush()?
+    .wait(None)?;
+
+    Ok(())
+}
+
+// Compute shader version of blit_image_fit for formats without blit support. `src` is sampled and
+// needs SAMPLED usage, `dst` is written as a storage image without a format qualifier, which needs
+// STORAGE usage and the shader_storage_image_write_without_format feature. Only the first layer is resampled
+pub fn resample_image(toolset : &VulkanToolset, src : &Arc<Image>, dst : &Arc<Image>, filter : Filter, fit : BlitFit) -> Result<(), EngineError> {
+    let device = &toolset.logical_device;
+    if !src.usage().intersects(ImageUsage::SAMPLED) || !dst.usage().intersects(ImageUsage::STORAGE) {
+        return Err(EngineError::UnsupportedFeature(format!("resampling a {:?} image into a {:?} image, it needs SAMPLED and STORAGE usage", src.usage(), dst.usage())));
+    }
+    if !device.enabled_features().shader_storage_image_write_without_format {
+        return Err(EngineError::UnsupportedFeature("resampling without the shader_storage_image_write_without_format feature".to_owned()));
+    }
+    if !supports(toolset, src, FormatFeatures::SAMPLED_IMAGE | filter_features(filter))? || !supports(toolset, dst, FormatFeatures::STORAGE_IMAGE)? {
+        return Err(EngineError::UnsupportedFeature(format!("resampling {:?} into {:?} with {filter:?} filtering", src.format(), dst.format())));
+    }
+
+    let first_level = |image : &Arc<Image>| ImageView::new(image.clone(), ImageViewCreateInfo {
+        view_type : ImageViewType::Dim2d,
+        subresource_range : ImageSubresourceRange {
+            mip_levels : 0..1,
+            array_layers : 0..1,
+            ..image.subresource_range()
+        },
+        ..ImageViewCreateInfo::from_image(image)
+    });
+    let sampler = Sampler::new(device.clone(), SamplerCreateInfo {
+        mag_filter : filter,
+        min_filter : filter,
+        address_mode : [SamplerAddressMode::ClampToEdge; 3],
+        ..Default::default()
+    })?;
+
+    let module = resample_cs::load(device.clone())?;
+    let shader = ComputeShader::with_entry_point(&module, "main", LOCAL_SIZE, device.clone())?;
+    let layout = shader.pipeline.layout();
+    let descriptor_set = PersistentDescriptorSet::new(
+        &toolset.memory_allocator.descriptor_set_allocator,
+        layout.set_layouts()[0].clone(),
+        [
+            WriteDescriptorSet::image_view_sampler(0, first_level(src)?, sampler),
+            WriteDescriptorSet::image_view(1, first_level(dst)?),
+        ],
+        [],
+    )?;
+
+    let [src_width, src_height, _] = src.extent();
+    let [dst_width, dst_height, _] = dst.extent();
+    let (offset, extent) = fit.region([src_width, src_height], [dst_width, dst_height]);
+    let region = resample_cs::Region {
+        offset : offset.map(|value| value as i32),
+        extent : extent.map(|value| value as i32),
+    };
+
+    let queue = &toolset.device_queue;
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )?;
+    builder.bind_pipeline_compute(shader.pipeline.clone())?
+    .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?
+    .push_constants(layout.clone(), 0, region)?
+    .dispatch(shader.group_counts([dst_width, dst_height, 1]))?;
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build()?)?
+    .then_signal_fence_and_flush()?
+    .wait(None)?;
+
+    Ok(())
+}
+
+fn filter_features(filter : Filter) -> FormatFeatures {
+    match filter {
+        Filter::Linear => FormatFeatures::SAMPLED_IMAGE_FILTER_LINEAR,
+        _ => FormatFeatures::empty(),
+    }
+}
+
+// Whether the image's format has `features` for the image's tiling
+fn supports(toolset : &VulkanToolset, image : &Arc<Image>, features : FormatFeatures) -> Result<bool, EngineError> {
+    let properties = toolset.logical_device.physical_device().format_properties(image.format())?;
+    let supported = match image.tiling() {
+        ImageTiling::Linear => properties.linear_tiling_features,
+        _ => properties.optimal_tiling_features,
+    };
+
+    Ok(supported.contains(features))
+}
@@ -0,0 +1,49 @@
+use vulkano::device::Device;
+
+// Optional device functionality the engine takes advantage of when it was enabled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeviceCapabilities {
+    // khr_push_descriptor, descriptors are written straight into the command buffer
+    pub push_descriptors : bool,
+    // ext_debug_utils on the instance, objects can be given names for debugging tools
+    pub debug_utils : bool,
+    // Several indirect draws from one command, otherwise they are recorded one by one
+    pub multi_draw_indirect : bool,
+    // khr_fragment_shading_rate, fragments can be shaded per block of pixels
+    pub variable_rate_shading : bool,
+    // ext_conservative_rasterization, primitives can cover every pixel they touch, see ConservativeRasterMode
+    pub conservative_rasterization : bool,
+    // ext_inline_uniform_block or Vulkan 1.3, see InlineUniformBinding
+    pub inline_uniform_blocks : bool,
+    // ext_conditional_rendering, see ConditionalRenderingExt
+    pub conditional_rendering : bool,
+    // Occlusion queries count samples, otherwise they only tell whether any passed
+    pub precise_occlusion_queries : bool,
+    // Shaders can dereference buffers by address, see VulkanAllocation::create_buffer_with_device_address
+    pub buffer_device_address : bool,
+    // Vulkan 1.2 semaphores that count up, see TimelineSemaphore
+    pub timeline_semaphores : bool,
+    // ext_vertex_attribute_divisor, per instance bindings can advance every N instances, see AttributeDivisor
+    pub vertex_attribute_divisor : bool,
+    // ext_transform_feedback, vertex outputs can be captured into buffers, see XfbPipeline
+    pub transform_feedback : bool,
+}
+
+impl DeviceCapabilities {
+    pub fn from_device(device : &Device) -> DeviceCapabilities {
+        DeviceCapabilities {
+            push_descriptors : device.enabled_extensions().khr_push_descriptor,
+            debug_utils : device.instance().enabled_extensions().ext_debug_utils,
+            multi_draw_indirect : device.enabled_features().multi_draw_indirect,
+            variable_rate_shading : device.enabled_features().pipeline_fragment_shading_rate,
+            conservative_rasterization : device.enabled_extensions().ext_conservative_rasterization,
+            inline_uniform_blocks : device.enabled_features().inline_uniform_block,
+            conditional_rendering : device.enabled_features().conditional_rendering,
+            precise_occlusion_queries : device.enabled_features().occlusion_query_precise,
+            buffer_device_address : device.enabled_features().buffer_device_address,
+            timeline_semaphores : device.enabled_features().timeline_semaphore,
+            vertex_attribute_divisor : device.enabled_features().vertex_attribute_instance_rate_divisor,
+            transform_feedback : device.enabled_features().transform_feedback,
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::{ffi::CString, mem::MaybeUninit, ptr, sync::Arc};
+
+use ash::vk;
+use vulkano::{
+    device::Device,
+    pipeline::{graphics::{color_blend::LogicOp, depth_stencil::CompareOp, subpass::PipelineSubpassType, vertex_input::VertexInputRate, GraphicsPipelineCreateInfo}, GraphicsPipeline},
+    shader::ShaderStage,
+    VulkanError,
+    VulkanObject
+};
+
+use crate::error::EngineError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConservativeRasterizationMode {
+    // Every pixel the primitive touches is covered, as voxelization needs
+    Overestimate,
+    // Only pixels fully inside the primitive are covered
+    Underestimate,
+}
+
+impl From<ConservativeRasterizationMode> for vk::ConservativeRasterizationModeEXT {
+    fn from(mode : ConservativeRasterizationMode) -> Self {
+        match mode {
+            ConservativeRasterizationMode::Overestimate => vk::ConservativeRasterizationModeEXT::OVERESTIMATE,
+            ConservativeRasterizationMode::Underestimate => vk::ConservativeRasterizationModeEXT::UNDERESTIMATE,
+        }
+    }
+}
+
+// See GraphicsPipelineDesc::conservative_raster
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConservativeRasterMode {
+    pub mode : ConservativeRasterizationMode,
+    // Pixels added around overestimated primitives, up to ConservativeRasterExt::max_extra_overestimation_size
+    pub extra_overestimation_size : f32,
+}
+
+impl ConservativeRasterMode {
+    pub fn overestimate() -> ConservativeRasterMode {
+        ConservativeRasterMode {
+            mode : ConservativeRasterizationMode::Overestimate,
+            extra_overestimation_size : 0.0,
+        }
+    }
+}
+
+// vulkano 0.34's RasterizationState has no conservative state, so these pipelines are created through ash from
+// the create info vulkano would have been given, with VkPipelineRasterizationConservativeStateCreateInfoEXT chained
+// into the rasterization state, and handed back to vulkano with GraphicsPipeline::from_handle.
+// Covers what VulkanToolset::assemble_pipeline builds. vulkano doesn't validate `create_info` on this path, the
+// device support is checked by VulkanToolset::check_conservative_raster
+pub(crate) fn create_conservative_pipeline(device : &Arc<Device>, create_info : GraphicsPipelineCreateInfo, mode : ConservativeRasterMode) -> Result<Arc<GraphicsPipeline>, EngineError> {
+    if create_info.tessellation_state.is_some() {
+        return Err(EngineError::UnsupportedFeature("conservative rasterization is not available for tessellated pipelines".to_owned()));
+    }
+    if create_info.depth_stencil_state.as_ref().is_some_and(|state| state.stencil.is_some() || state.depth_bounds.is_some()) {
+        return Err(EngineError::UnsupportedFeature("conservative rasterization is not available with stencil or depth bounds tests".to_owned()));
+    }
+    let Some(PipelineSubpassType::BeginRenderPass(subpass)) = &create_info.subpass else {
+        return Err(EngineError::UnsupportedFeature("conservative rasterization needs a render pass subpass".to_owned()));
+    };
+
+    // Shader stages, specialization constants are laid out one after another as vulkano does
+    let names = create_info.stages.iter()
+    .map(|stage| CString::new(stage.entry_point.info().name.as_str()).unwrap())
+    .collect::<Vec<_>>();
+    let specializations = create_info.stages.iter()
+    .map(|stage| {
+        let mut data = Vec::new();
+        let entries = stage.entry_point.module().specialization_info().iter()
+        .map(|(&constant_id, value)| {
+            let bytes = value.as_bytes();
+            let entry = vk::SpecializationMapEntry { constant_id, offset : data.len() as u32, size : bytes.len() };
+            data.extend_from_slice(bytes);
+            entry
+        })
+        .collect::<Vec<_>>();
+
+        (entries, data)
+    })
+    .collect::<Vec<_>>();
+    let specialization_infos = specializations.iter()
+    .map(|(entries, data)| vk::SpecializationInfo::builder().map_entries(entries).data(data).build())
+    .collect::<Vec<_>>();
+    let stages = create_info.stages.iter()
+    .zip(&names)
+    .zip(&specialization_infos)
+    .map(|((stage, name), specialization_info)| vk::PipelineShaderStageCreateInfo::builder()
+        .stage(ShaderStage::from(stage.entry_point.info().execution_model).into())
+        .module(stage.entry_point.module().handle())
+        .name(name)
+        .specialization_info(specialization_info)
+        .build())
+    .collect::<Vec<_>>();
+
+    let vertex_input_state = create_info.vertex_input_state.clone().unwrap_or_default();
+    let mut bindings = Vec::new();
+    for (&binding, description) in &vertex_input_state.bindings {
+        if let VertexInputRate::Instance { divisor } = description.input_rate {
+            if divisor != 1 {
+                return Err(EngineError::UnsupportedFeature(format!("binding {binding} advances every {divisor} instances, conservative pipelines only take a divisor of 1")));
+            }
+        }
+        bindings.push(vk::VertexInputBindingDescription { binding, stride : description.stride, input_rate : description.input_rate.into() });
+    }
+    let attributes = vertex_input_state.attributes.iter()
+    .map(|(&location, attribute)| vk::VertexInputAttributeDescription { location, binding : attribute.binding, format : attribute.format.into(), offset : attribute.offset })
+    .collect::<Vec<_>>();
+    let vertex_input_vk = vk::PipelineVertexInputStateCreateInfo::builder()
+    .vertex_binding_descriptions(&bindings)
+    .vertex_attribute_descriptions(&attributes);
+
+    let input_assembly_state = create_info.input_assembly_state.clone().unwrap_or_default();
+    let input_assembly_vk = vk::PipelineInputAssemblyStateCreateInfo::builder()
+    .topology(input_assembly_state.topology.into())
+    .primitive_restart_enable(input_assembly_state.primitive_restart_enable);
+
+    // Dynamic viewports and scissors still need their count here
+    let viewport_state = create_info.viewport_state.clone().unwrap_or_default();
+    let viewports = viewport_state.viewports.iter()
+    .map(|viewport| vk::Viewport {
+        x : viewport.offset[0],
+        y : viewport.offset[1],
+        width : viewport.extent[0],
+        height : viewport.extent[1],
+        min_depth : *viewport.depth_range.start(),
+        max_depth : *viewport.depth_range.end(),
+    })
+    .collect::<Vec<_>>();
+    let scissors = viewport_state.scissors.iter()
+    .map(|scissor| vk::Rect2D {
+        offset : vk::Offset2D { x : scissor.offset[0] as i32, y : scissor.offset[1] as i32 },
+        extent : vk::Extent2D { width : scissor.extent[0], height : scissor.extent[1] },
+    })
+    .collect::<Vec<_>>();
+    let viewport_vk = vk::PipelineViewportStateCreateInfo::builder()
+    .viewports(&viewports)
+    .scissors(&scissors);
+
+    let rasterization_state = create_info.rasterization_state.clone().unwrap_or_default();
+    let depth_bias = rasterization_state.depth_bias.unwrap_or_default();
+    let mut conservative_vk = vk::PipelineRasterizationConservativeStateCreateInfoEXT::builder()
+    .conservative_rasterization_mode(mode.mode.into())
+    .extra_primitive_overestimation_size(mode.extra_overestimation_size);
+    let rasterization_vk = vk::PipelineRasterizationStateCreateInfo::builder()
+    .depth_clamp_enable(rasterization_state.depth_clamp_enable)
+    .rasterizer_discard_enable(rasterization_state.rasterizer_discard_enable)
+    .polygon_mode(rasterization_state.polygon_mode.into())
+    .cull_mode(rasterization_state.cull_mode.into())
+    .front_face(rasterization_state.front_face.into())
+    .depth_bias_enable(rasterization_state.depth_bias.is_some())
+    .depth_bias_constant_factor(depth_bias.constant_factor)
+    .depth_bias_clamp(depth_bias.clamp)
+    .depth_bias_slope_factor(depth_bias.slope_factor)
+    .line_width(rasterization_state.line_width)
+    .push_next(&mut conservative_vk);
+
+    let multisample_state = create_info.multisample_state.clone().unwrap_or_default();
+    let multisample_vk = vk::PipelineMultisampleStateCreateInfo::builder()
+    .rasterization_samples(multisample_state.rasterization_samples.into())
+    .sample_shading_enable(multisample_state.sample_shading.is_some())
+    .min_sample_shading(multisample_state.sample_shading.unwrap_or(0.0))
+    .sample_mask(&multisample_state.sample_mask)
+    .alpha_to_coverage_enable(multisample_state.alpha_to_coverage_enable)
+    .alpha_to_one_enable(multisample_state.alpha_to_one_enable);
+
+    let depth_stencil_vk = create_info.depth_stencil_state.as_ref().map(|state| {
+        vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(state.depth.is_some())
+        .depth_write_enable(state.depth.is_some_and(|depth| depth.write_enable))
+        .depth_compare_op(state.depth.map_or(CompareOp::Always, |depth| depth.compare_op).into())
+        .build()
+    });
+
+    let color_blend_state = create_info.color_blend_state.clone().unwrap_or_default();
+    let color_attachments = color_blend_state.attachments.iter()
+    .map(|attachment| {
+        let builder = vk::PipelineColorBlendAttachmentState::builder()
+        .blend_enable(attachment.blend.is_some())
+        .color_write_mask(attachment.color_write_mask.into());
+
+        match attachment.blend {
+            Some(blend) => builder
+            .src_color_blend_factor(blend.src_color_blend_factor.into())
+            .dst_color_blend_factor(blend.dst_color_blend_factor.into())
+            .color_blend_op(blend.color_blend_op.into())
+            .src_alpha_blend_factor(blend.src_alpha_blend_factor.into())
+            .dst_alpha_blend_factor(blend.dst_alpha_blend_factor.into())
+            .alpha_blend_op(blend.alpha_blend_op.into())
+            .build(),
+            None => builder.build(),
+        }
+    })
+    .collect::<Vec<_>>();
+    let color_blend_vk = vk::PipelineColorBlendStateCreateInfo::builder()
+    .logic_op_enable(color_blend_state.logic_op.is_some())
+    .logic_op(color_blend_state.logic_op.unwrap_or(LogicOp::Copy).into())
+    .attachments(&color_attachments)
+    .blend_constants(color_blend_state.blend_constants);
+
+    let dynamic_states = create_info.dynamic_state.iter()
+    .map(|&state| state.into())
+    .collect::<Vec<vk::DynamicState>>();
+    let dynamic_vk = vk::PipelineDynamicStateCreateInfo::builder()
+    .dynamic_states(&dynamic_states);
+
+    let mut create_info_vk = vk::GraphicsPipelineCreateInfo::builder()
+    .stages(&stages)
+    .vertex_input_state(&vertex_input_vk)
+    .input_assembly_state(&input_assembly_vk)
+    .viewport_state(&viewport_vk)
+    .rasterization_state(&rasterization_vk)
+    .multisample_state(&multisample_vk)
+    .color_blend_state(&color_blend_vk)
+    .dynamic_state(&dynamic_vk)
+    .layout(create_info.layout.handle())
+    .render_pass(subpass.render_pass().handle())
+    .subpass(subpass.index())
+    .build();
+    // Subpasses without a depth attachment take no depth state
+    if let Some(depth_stencil_vk) = &depth_stencil_vk {
+        create_info_vk.p_depth_stencil_state = depth_stencil_vk;
+    }
+
+    let handle = unsafe {
+        let mut output = MaybeUninit::uninit();
+        (device.fns().v1_0.create_graphics_pipelines)(device.handle(), vk::PipelineCache::null(), 1, &create_info_vk, ptr::null(), output.as_mut_ptr())
+        .result()
+        .map_err(VulkanError::from)?;
+
+        output.assume_init()
+    };
+
+    // Safety: `handle` was just created on `device` from `create_info`, vulkano destroys it with the pipeline
+    Ok(unsafe { GraphicsPipeline::from_handle(device.clone(), handle, create_info) })
+}
@@ -0,0 +1,15 @@
+use std::sync::Arc;
+
+use vulkano::{device::{Device, DeviceOwned}, VulkanObject};
+
+use super::extensions::DebugLabelExt;
+
+pub struct DebugUtils;
+
+impl DebugUtils {
+    // For code that only has a device at hand, see DebugLabelExt::name_object.
+    // Does nothing when the instance was created without ext_debug_utils
+    pub fn name_object<T : VulkanObject + DeviceOwned>(device : &Arc<Device>, object : &T, name : &str) {
+        DebugLabelExt::load(device).if_present(|ext| ext.name_object(object, name));
+    }
+}
@@ -0,0 +1,48 @@
+use std::any::Any;
+
+// Keeps resources replaced during a frame alive until the GPU is done with every frame that could still use
+// them. A resource retired in a slot's frame is dropped when that slot is handed out again, by then the frame
+// and every frame before it finished. Frames recorded after the retirement use the replacement instead
+pub struct DeferredDeletionQueue {
+    slots : Vec<Vec<Box<dyn Any>>>,
+    released : u64,
+}
+
+impl DeferredDeletionQueue {
+    pub fn new(frames_in_flight : usize) -> DeferredDeletionQueue {
+        DeferredDeletionQueue {
+            slots : (0..frames_in_flight).map(|_| Vec::new()).collect(),
+            released : 0,
+        }
+    }
+
+    // Held until release is called for `slot`, any Arc<Buffer>, Subbuffer, Arc<ImageView> or whole
+    // mesh works as long as it owns what the GPU reads
+    pub fn retire<T : 'static>(&mut self, slot : usize, resource : T) {
+        self.slots[slot].push(Box::new(resource));
+    }
+
+    // Drops what `slot` held, call once its frame finished. Returns how many resources were dropped
+    pub fn release(&mut self, slot : usize) -> usize {
+        let count = self.slots[slot].len();
+        self.slots[slot].clear();
+        self.released += count as u64;
+
+        count
+    }
+
+    // For when no frame is in flight anymore
+    pub fn release_all(&mut self) -> usize {
+        (0..self.slots.len()).map(|slot| self.release(slot)).sum()
+    }
+
+    // Resources still waiting for their frame
+    pub fn pending(&self) -> usize {
+        self.slots.iter().map(Vec::len).sum()
+    }
+
+    // Resources dropped so far
+    pub fn released(&self) -> u64 {
+        self.released
+    }
+}
@@ -0,0 +1,272 @@
+use std::{collections::{HashMap, HashSet}, sync::{Arc, Mutex, RwLock, Weak}};
+
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferMemory, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferInfo},
+    device::Queue,
+    memory::{allocator::{AllocationCreateInfo, MemoryTypeFilter}, DeviceAlignment, DeviceMemory},
+    sync::{self, GpuFuture},
+    DeviceSize, VulkanObject
+};
+
+use crate::error::EngineError;
+use super::{memory_stats::AllocationCategory, vulkan::VulkanAllocation};
+
+pub type MemoryHandle = <DeviceMemory as VulkanObject>::Handle;
+
+// Result of VulkanAllocation::defragment
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DefragStats {
+    pub bytes_moved : DeviceSize,
+    // Memory blocks no relocatable buffer lives in anymore. Vulkano keeps them for later allocations
+    // instead of handing them back to the driver
+    pub blocks_freed : usize,
+}
+
+// Buffer that VulkanAllocation::defragment may move to another allocation. Fetch the current Subbuffer
+// through get whenever recording, one kept around keeps the old allocation alive and sees no later writes.
+// Buffer device addresses change with a move
+pub struct RelocatableBuffer<T : BufferContents> {
+    slot : Arc<RelocatableSlot<T>>,
+}
+
+// Clones share the slot, all of them see a move
+impl<T : BufferContents> Clone for RelocatableBuffer<T> {
+    fn clone(&self) -> Self {
+        RelocatableBuffer { slot : self.slot.clone() }
+    }
+}
+
+impl<T : BufferContents> RelocatableBuffer<T> {
+    pub(crate) fn new(buffer : Subbuffer<[T]>, registry : &RelocationRegistry) -> RelocatableBuffer<T> {
+        let slot = Arc::new(RelocatableSlot { buffer : RwLock::new(buffer) });
+        let weak : Weak<dyn Relocatable> = Arc::downgrade(&slot);
+        registry.entries.lock().unwrap().push(weak);
+
+        RelocatableBuffer { slot }
+    }
+
+    pub fn get(&self) -> Subbuffer<[T]> {
+        self.slot.buffer.read().unwrap().clone()
+    }
+}
+
+struct RelocatableSlot<T : BufferContents> {
+    buffer : RwLock<Subbuffer<[T]>>,
+}
+
+// Type erased so buffers of any element type can be moved together
+pub(crate) trait Relocatable : Send + Sync {
+    fn bytes(&self) -> Subbuffer<[u8]>;
+    // `to` holds a copy of bytes by now
+    fn relocate(&self, to : Subbuffer<[u8]>);
+}
+
+impl<T : BufferContents> Relocatable for RelocatableSlot<T> {
+    fn bytes(&self) -> Subbuffer<[u8]> {
+        self.buffer.read().unwrap().as_bytes().clone()
+    }
+
+    fn relocate(&self, to : Subbuffer<[u8]>) {
+        *self.buffer.write().unwrap() = to.reinterpret::<[T]>();
+    }
+}
+
+// Relocatable buffers created through VulkanAllocation::create_relocatable_buffer, dropped ones fall out
+#[derive(Default)]
+pub struct RelocationRegistry {
+    entries : Mutex<Vec<Weak<dyn Relocatable>>>,
+}
+
+impl RelocationRegistry {
+    pub(crate) fn live(&self) -> Vec<Arc<dyn Relocatable>> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|entry| entry.strong_count() > 0);
+
+        entries.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    // Relocatable buffers that are still alive
+    pub fn len(&self) -> usize {
+        self.live().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Where a buffer's bytes sit, as far as fragmentation is concerned
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub block : MemoryHandle,
+    pub offset : DeviceSize,
+    pub size : DeviceSize,
+}
+
+impl Placement {
+    pub(crate) fn of(buffer : &Subbuffer<[u8]>) -> Option<Placement> {
+        // Sparse buffers have no memory of their own
+        let BufferMemory::Normal(memory) = buffer.buffer().memory() else { return None };
+
+        Some(Placement {
+            block : memory.device_memory().handle(),
+            offset : memory.offset() + buffer.offset(),
+            size : buffer.size(),
+        })
+    }
+}
+
+// Share of the memory between the first and last byte of each block's buffers that none of them use,
+// 0 for tightly packed buffers. Whatever else lives between them counts as a gap too
+pub fn fragmentation(placements : &[Placement]) -> f32 {
+    let mut blocks = HashMap::<MemoryHandle, (DeviceSize, DeviceSize, DeviceSize)>::new();
+    for placement in placements {
+        let (start, end, used) = blocks.entry(placement.block).or_insert((DeviceSize::MAX, 0, 0));
+        *start = (*start).min(placement.offset);
+        *end = (*end).max(placement.offset + placement.size);
+        *used += placement.size;
+    }
+
+    let (spanned, used) = blocks.values().fold((0 as DeviceSize, 0 as DeviceSize), |(spanned, used), &(start, end, block_used)| (spanned + end - start, used + block_used));
+    match spanned {
+        0 => 0.0,
+        spanned => 1.0 - used.min(spanned) as f32 / spanned as f32,
+    }
+}
+
+// Offsets that place `sizes` back to back, each aligned to `alignment`, and the total size
+pub fn pack(sizes : &[DeviceSize], alignment : DeviceSize) -> (Vec<DeviceSize>, DeviceSize) {
+    let mut end : DeviceSize = 0;
+    let offsets = sizes.iter()
+    .map(|size| {
+        let offset = end.next_multiple_of(alignment);
+        end = offset + size;
+        offset
+    })
+    .collect();
+
+    (offsets, end)
+}
+
+// Buffers can only share an allocation when they agree on these
+fn group_key(buffer : &Subbuffer<[u8]>) -> Option<(BufferUsage, u32)> {
+    let BufferMemory::Normal(memory) = buffer.buffer().memory() else { return None };
+
+    Some((buffer.buffer().usage(), memory.device_memory().memory_type_index()))
+}
+
+impl VulkanAllocation {
+    // Buffer that defragment may move, with TRANSFER_SRC and TRANSFER_DST usage added for that
+    pub fn create_relocatable_buffer<T : BufferContents + Copy>(&self, usage : BufferUsage, memory_type_filter : MemoryTypeFilter, data : &[T]) -> Result<RelocatableBuffer<T>, EngineError> {
+        if data.is_empty() {
+            return Err(EngineError::EmptyBuffer);
+        }
+
+        let usage = usage | BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST;
+        let buffer = Buffer::from_iter(
+            self.general_allocator.clone(),
+            BufferCreateInfo {
+                usage,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter,
+                ..Default::default()
+            },
+            data.iter().copied(),
+        )?;
+        self.tracker.track_buffer(AllocationCategory::from_usage(usage), buffer.buffer());
+
+        Ok(RelocatableBuffer::new(buffer, &self.relocatable))
+    }
+
+    // How scattered the live relocatable buffers are, see fragmentation
+    pub fn fragmentation(&self) -> f32 {
+        let placements = self.relocatable.live().iter()
+        .filter_map(|entry| Placement::of(&entry.bytes()))
+        .collect::<Vec<_>>();
+
+        fragmentation(&placements)
+    }
+
+    // Packs the relocatable buffers that share usage and memory type into one allocation per group, then points
+    // their handles at it. The old allocations are freed once nothing references them anymore. Blocks until
+    // the copies on `queue` finished. Frames still using the buffers must have finished before, as after
+    // FrameSync::wait_all, and nothing may write the buffers meanwhile
+    pub fn defragment(&self, queue : &Arc<Queue>) -> Result<DefragStats, EngineError> {
+        let mut groups = HashMap::<(BufferUsage, u32), Vec<(Arc<dyn Relocatable>, Subbuffer<[u8]>)>>::new();
+        for entry in self.relocatable.live() {
+            let bytes = entry.bytes();
+            if let Some(key) = group_key(&bytes) {
+                groups.entry(key).or_default().push((entry, bytes));
+            }
+        }
+        // A buffer on its own has nothing to be packed with
+        groups.retain(|_, entries| entries.len() > 1);
+        if groups.is_empty() {
+            return Ok(DefragStats::default());
+        }
+
+        let properties = self.general_allocator.device().physical_device().properties();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        let mut moves = Vec::new();
+        let mut stats = DefragStats::default();
+        let mut blocks_before = HashSet::new();
+        let mut blocks_after = HashSet::new();
+        for ((usage, memory_type_index), entries) in groups {
+            // Every buffer's offset has to suit the descriptors it may be bound with
+            let alignment = [
+                usage.intersects(BufferUsage::UNIFORM_TEXEL_BUFFER | BufferUsage::STORAGE_TEXEL_BUFFER).then_some(properties.min_texel_buffer_offset_alignment),
+                usage.intersects(BufferUsage::UNIFORM_BUFFER).then_some(properties.min_uniform_buffer_offset_alignment),
+                usage.intersects(BufferUsage::STORAGE_BUFFER).then_some(properties.min_storage_buffer_offset_alignment),
+                Some(properties.non_coherent_atom_size),
+            ].into_iter()
+            .flatten()
+            .fold(16, |alignment, required : DeviceAlignment| alignment.max(required.as_devicesize()));
+
+            let sizes = entries.iter().map(|(_, bytes)| bytes.size()).collect::<Vec<_>>();
+            let (offsets, total) = pack(&sizes, alignment);
+            let packed = Buffer::new_slice::<u8>(
+                self.general_allocator.clone(),
+                BufferCreateInfo {
+                    usage,
+                    ..Default::default()
+                },
+                AllocationCreateInfo {
+                    memory_type_bits: 1 << memory_type_index,
+                    ..Default::default()
+                },
+                total,
+            )?;
+            self.tracker.track_buffer(AllocationCategory::from_usage(usage), packed.buffer());
+
+            for ((entry, bytes), (offset, size)) in entries.into_iter().zip(offsets.into_iter().zip(sizes)) {
+                let to = packed.clone().slice(offset..offset + size);
+                builder.copy_buffer(CopyBufferInfo::buffers(bytes.clone(), to.clone()))?;
+
+                blocks_before.extend(Placement::of(&bytes).map(|placement| placement.block));
+                blocks_after.extend(Placement::of(&to).map(|placement| placement.block));
+                stats.bytes_moved += size;
+                moves.push((entry, to));
+            }
+        }
+
+        sync::now(queue.device().clone())
+        .then_execute(queue.clone(), builder.build()?)?
+        .then_signal_fence_and_flush()?
+        .wait(None)?;
+
+        for (entry, to) in moves {
+            entry.relocate(to);
+        }
+        stats.blocks_freed = blocks_before.difference(&blocks_after).count();
+
+        Ok(stats)
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use vulkano::descriptor_set::{allocator::StandardDescriptorSetAllocator, layout::DescriptorSetLayout, PersistentDescriptorSet, WriteDescriptorSet};
+
+use crate::error::EngineError;
+
+// One descriptor set per frame in flight, so a set can be rewritten while the others are still
+// bound in command buffers the GPU hasn't finished. N must be at least FrameSync::frames_in_flight
+pub struct PerFrameDescriptorSets<const N : usize> {
+    allocator : Arc<StandardDescriptorSetAllocator>,
+    layout : Arc<DescriptorSetLayout>,
+    sets : [Arc<PersistentDescriptorSet>; N],
+}
+
+impl<const N : usize> PerFrameDescriptorSets<N> {
+    // Every slot starts out with the same writes
+    pub fn new(allocator : Arc<StandardDescriptorSetAllocator>, layout : Arc<DescriptorSetLayout>, writes : &[WriteDescriptorSet]) -> Result<Self, EngineError> {
+        let sets = (0..N)
+        .map(|_| Self::allocate(&allocator, &layout, writes))
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(PerFrameDescriptorSets {
+            allocator,
+            layout,
+            sets : sets.try_into().unwrap_or_else(|_| unreachable!()),
+        })
+    }
+
+    pub fn current(&self, frame_index : usize) -> &Arc<PersistentDescriptorSet> {
+        &self.sets[frame_index % N]
+    }
+
+    // Descriptor sets are immutable once built, so the slot gets a fresh set with the new writes.
+    // Only call after FrameSync::begin_frame returned `frame_index`
+    pub fn update_current(&mut self, frame_index : usize, writes : &[WriteDescriptorSet]) -> Result<(), EngineError> {
+        self.sets[frame_index % N] = Self::allocate(&self.allocator, &self.layout, writes)?;
+        Ok(())
+    }
+
+    fn allocate(allocator : &StandardDescriptorSetAllocator, layout : &Arc<DescriptorSetLayout>, writes : &[WriteDescriptorSet]) -> Result<Arc<PersistentDescriptorSet>, EngineError> {
+        Ok(PersistentDescriptorSet::new(allocator, layout.clone(), writes.iter().cloned(), [])?)
+    }
+}
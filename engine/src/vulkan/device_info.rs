@@ -0,0 +1,191 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use vulkano::device::physical::PhysicalDevice;
+
+use crate::{config::QueueRole, error::EngineError};
+use super::vulkan::{auto_tuned_local_size, VulkanToolset};
+
+const VENDOR_NVIDIA : u32 = 0x10de;
+const VENDOR_INTEL : u32 = 0x8086;
+
+// Limits the engine checks its own work against, read once when the device is created
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeviceLimits {
+    pub max_image_dimension_2d : u32,
+    pub max_compute_work_group_size : [u32; 3],
+    pub max_compute_work_group_invocations : u32,
+    pub max_compute_work_group_count : [u32; 3],
+    pub max_push_constants_size : u32,
+    // 1 without the multi_draw_indirect feature
+    pub max_draw_indirect_count : u32,
+    // None before Vulkan 1.1
+    pub subgroup_size : Option<u32>,
+    // 1 without ext_vertex_attribute_divisor
+    pub max_vertex_attrib_divisor : u32,
+}
+
+impl DeviceLimits {
+    pub fn from_physical_device(physical_device : &PhysicalDevice) -> DeviceLimits {
+        let properties = physical_device.properties();
+
+        DeviceLimits {
+            max_image_dimension_2d : properties.max_image_dimension2_d,
+            max_compute_work_group_size : properties.max_compute_work_group_size,
+            max_compute_work_group_invocations : properties.max_compute_work_group_invocations,
+            max_compute_work_group_count : properties.max_compute_work_group_count,
+            max_push_constants_size : properties.max_push_constants_size,
+            max_draw_indirect_count : properties.max_draw_indirect_count,
+            subgroup_size : properties.subgroup_size,
+            max_vertex_attrib_divisor : properties.max_vertex_attrib_divisor.unwrap_or(1),
+        }
+    }
+
+    // Push constant blocks must end within max_push_constants_size bytes
+    pub fn check_push_constants(&self, offset : u32, size : u32) -> Result<(), EngineError> {
+        let end = offset.saturating_add(size);
+        match end <= self.max_push_constants_size {
+            true => Ok(()),
+            false => Err(EngineError::DeviceLimit { limit : "max_push_constants_size", requested : end, max : self.max_push_constants_size }),
+        }
+    }
+
+    pub fn check_local_size(&self, local_size : [u32; 3]) -> Result<(), EngineError> {
+        for (axis, (&size, &max)) in local_size.iter().zip(&self.max_compute_work_group_size).enumerate() {
+            if size > max {
+                let limit = ["max_compute_work_group_size[0]", "max_compute_work_group_size[1]", "max_compute_work_group_size[2]"][axis];
+                return Err(EngineError::DeviceLimit { limit, requested : size, max });
+            }
+        }
+
+        let invocations = local_size.iter().fold(1u32, |product, &size| product.saturating_mul(size));
+        match invocations <= self.max_compute_work_group_invocations {
+            true => Ok(()),
+            false => Err(EngineError::DeviceLimit { limit : "max_compute_work_group_invocations", requested : invocations, max : self.max_compute_work_group_invocations }),
+        }
+    }
+
+    pub fn check_attribute_divisor(&self, divisor : u32) -> Result<(), EngineError> {
+        match divisor <= self.max_vertex_attrib_divisor {
+            true => Ok(()),
+            false => Err(EngineError::DeviceLimit { limit : "max_vertex_attrib_divisor", requested : divisor, max : self.max_vertex_attrib_divisor }),
+        }
+    }
+
+    // One indirect command may draw up to max_draw_indirect_count times. More than one draw
+    // reads commands `stride` bytes apart, a multiple of 4 no smaller than the command
+    pub fn check_indirect_draws(&self, draw_count : u32, stride : u32, command_size : u32) -> Result<(), EngineError> {
+        if draw_count > self.max_draw_indirect_count {
+            return Err(EngineError::DeviceLimit { limit : "max_draw_indirect_count", requested : draw_count, max : self.max_draw_indirect_count });
+        }
+
+        match draw_count <= 1 || (stride % 4 == 0 && stride >= command_size) {
+            true => Ok(()),
+            false => Err(EngineError::UnsupportedFeature(format!("indirect stride {stride} is not a multiple of 4 of at least {command_size} bytes"))),
+        }
+    }
+
+    // See auto_tuned_local_size
+    pub fn auto_tuned_local_size(&self, target_invocations : u32) -> [u32; 3] {
+        auto_tuned_local_size(target_invocations, self.subgroup_size, self.max_compute_work_group_invocations, self.max_compute_work_group_size)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct QueueInfo {
+    pub role : QueueRole,
+    pub family_index : u32,
+    // Capabilities of the family, such as "GRAPHICS | COMPUTE | TRANSFER"
+    pub flags : String,
+}
+
+// What a bug report needs to know about the device, see VulkanToolset::device_info.
+// Vulkan types are kept as their names, so the report reads the same everywhere
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeviceInfo {
+    pub device_name : String,
+    pub device_type : String,
+    pub vendor_id : u32,
+    pub device_id : u32,
+    pub driver_version : String,
+    pub api_version : String,
+    pub queues : Vec<QueueInfo>,
+    pub enabled_extensions : Vec<String>,
+    pub enabled_features : Vec<String>,
+    pub limits : DeviceLimits,
+}
+
+impl VulkanToolset {
+    pub fn device_info(&self) -> DeviceInfo {
+        let device = &self.logical_device;
+        let physical_device = device.physical_device();
+        let properties = physical_device.properties();
+        let families = physical_device.queue_family_properties();
+
+        // Drivers name themselves from Vulkan 1.2 on, the raw version is decoded either way
+        let version = driver_version_string(properties.vendor_id, properties.driver_version);
+        let driver_version = match (&properties.driver_name, &properties.driver_info) {
+            (Some(name), Some(info)) if !info.is_empty() => format!("{name} {info} ({version})"),
+            (Some(name), _) => format!("{name} {version}"),
+            _ => version,
+        };
+
+        let queues = self.queues.iter()
+        .map(|(&role, queue)| QueueInfo {
+            role,
+            family_index : queue.queue_family_index(),
+            flags : format!("{:?}", families[queue.queue_family_index() as usize].queue_flags),
+        })
+        .collect();
+
+        DeviceInfo {
+            device_name : properties.device_name.clone(),
+            device_type : format!("{:?}", properties.device_type),
+            vendor_id : properties.vendor_id,
+            device_id : properties.device_id,
+            driver_version,
+            api_version : self.api_version().to_string(),
+            queues,
+            enabled_extensions : enabled_names(*device.enabled_extensions()),
+            enabled_features : enabled_names(*device.enabled_features()),
+            limits : self.limits,
+        }
+    }
+}
+
+fn enabled_names(items : impl IntoIterator<Item = (&'static str, bool)>) -> Vec<String> {
+    items.into_iter()
+    .filter(|&(_, enabled)| enabled)
+    .map(|(name, _)| name.to_owned())
+    .collect()
+}
+
+// Driver versions are packed per vendor, the rest follow the Vulkan version layout
+pub fn driver_version_string(vendor_id : u32, raw : u32) -> String {
+    match vendor_id {
+        VENDOR_NVIDIA => format!("{}.{}.{}.{}", raw >> 22, (raw >> 14) & 0xff, (raw >> 6) & 0xff, raw & 0x3f),
+        // Only the Windows driver packs it like this, Mesa's ANV driver uses the Vulkan layout
+        VENDOR_INTEL if cfg!(windows) => format!("{}.{}", raw >> 14, raw & 0x3fff),
+        _ => format!("{}.{}.{}", raw >> 22, (raw >> 12) & 0x3ff, raw & 0xfff),
+    }
+}
+
+// Multi-line report for the log
+impl Display for DeviceInfo {
+    fn fmt(&self, f : &mut Formatter<'_>) -> FmtResult {
+        writeln!(f, "{} ({}, vendor {:#06x}, device {:#06x})", self.device_name, self.device_type, self.vendor_id, self.device_id)?;
+        writeln!(f, "  driver {}, Vulkan {}", self.driver_version, self.api_version)?;
+
+        for queue in &self.queues {
+            writeln!(f, "  {:?} queue: family {} ({})", queue.role, queue.family_index, queue.flags)?;
+        }
+
+        let limits = &self.limits;
+        writeln!(f, "  max image dimension {}, max push constants {} bytes", limits.max_image_dimension_2d, limits.max_push_constants_size)?;
+        writeln!(f, "  max workgroup size {:?}, {} invocations, subgroup size {}", limits.max_compute_work_group_size, limits.max_compute_work_group_invocations, limits.subgroup_size.map_or("unknown".to_owned(), |size| size.to_string()))?;
+        writeln!(f, "  extensions: {}", self.enabled_extensions.join(", "))?;
+        write!(f, "  features: {}", self.enabled_features.join(", "))
+    }
+}
@@ -0,0 +1,314 @@
+use std::{ptr, sync::Arc};
+
+use ash::vk;
+use log::warn;
+use vulkano::{
+    buffer::{BufferUsage, Subbuffer},
+    command_buffer::{sys::UnsafeCommandBufferBuilder, AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::WriteDescriptorSet,
+    device::{Device, DeviceOwned},
+    instance::debug::DebugUtilsLabel,
+    pipeline::{PipelineBindPoint, PipelineLayout},
+    VulkanObject
+};
+
+use crate::error::EngineError;
+
+// Functionality that only exists when its extension or feature was enabled on the device.
+// Callers go through if_present, which does nothing on hardware without it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtensionGuard<T> {
+    extension : Option<T>,
+}
+
+impl<T> ExtensionGuard<T> {
+    pub fn present(extension : T) -> ExtensionGuard<T> {
+        ExtensionGuard { extension : Some(extension) }
+    }
+
+    pub fn absent() -> ExtensionGuard<T> {
+        ExtensionGuard { extension : None }
+    }
+
+    pub fn is_present(&self) -> bool {
+        self.extension.is_some()
+    }
+
+    pub fn get(&self) -> Option<&T> {
+        self.extension.as_ref()
+    }
+
+    // Runs `f` with the extension, None without calling it when the extension is missing
+    pub fn if_present<R>(&self, f : impl FnOnce(&T) -> R) -> Option<R> {
+        self.extension.as_ref().map(f)
+    }
+}
+
+impl<T> Default for ExtensionGuard<T> {
+    fn default() -> Self {
+        Self::absent()
+    }
+}
+
+impl<T> From<Option<T>> for ExtensionGuard<T> {
+    fn from(extension : Option<T>) -> Self {
+        ExtensionGuard { extension }
+    }
+}
+
+// ext_debug_utils on the instance. Failures are only logged, names and labels never change
+// what gets rendered
+#[derive(Clone, Debug)]
+pub struct DebugLabelExt {
+    device : Arc<Device>,
+}
+
+impl DebugLabelExt {
+    pub fn load(device : &Arc<Device>) -> ExtensionGuard<DebugLabelExt> {
+        device.instance().enabled_extensions().ext_debug_utils
+        .then(|| DebugLabelExt { device : device.clone() })
+        .into()
+    }
+
+    // Names show up in validation messages and RenderDoc captures instead of raw handles
+    pub fn name_object<T : VulkanObject + DeviceOwned>(&self, object : &T, name : &str) {
+        if let Err(e) = self.device.set_debug_utils_object_name(object, Some(name)) {
+            warn!("failed to name {name}: {e}");
+        }
+    }
+
+    // Single marker between commands
+    pub fn insert_label(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, name : &str) {
+        if let Err(e) = builder.insert_debug_utils_label(Self::label(name)) {
+            warn!("failed to insert label {name}: {e}");
+        }
+    }
+
+    // Opens a region that must be closed in the same command buffer, see ExtensionGuard::labeled
+    fn begin_label(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, name : &str) -> bool {
+        builder.begin_debug_utils_label(Self::label(name))
+        .map_err(|e| warn!("failed to begin label {name}: {e}"))
+        .is_ok()
+    }
+
+    fn end_label(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) {
+        // Only called after begin_label succeeded on this builder
+        if let Err(e) = unsafe { builder.end_debug_utils_label() } {
+            warn!("failed to end label: {e}");
+        }
+    }
+
+    fn label(name : &str) -> DebugUtilsLabel {
+        DebugUtilsLabel {
+            label_name : name.to_owned(),
+            ..Default::default()
+        }
+    }
+}
+
+impl ExtensionGuard<DebugLabelExt> {
+    // Groups the commands `record` writes under `name` in debugging tools.
+    // Without debug utils `record` still runs, just without the label around it
+    pub fn labeled<R>(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, name : &str, record : impl FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) -> R) -> R {
+        let opened = self.if_present(|ext| ext.begin_label(builder, name)).unwrap_or(false);
+        let result = record(builder);
+        if opened {
+            self.if_present(|ext| ext.end_label(builder));
+        }
+
+        result
+    }
+}
+
+// khr_push_descriptor, descriptors are written straight into the command buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PushDescriptorExt {
+    // Descriptors a push descriptor set layout may hold in total
+    pub max_push_descriptors : u32,
+}
+
+impl PushDescriptorExt {
+    pub fn load(device : &Device) -> ExtensionGuard<PushDescriptorExt> {
+        device.enabled_extensions().khr_push_descriptor
+        .then(|| PushDescriptorExt {
+            max_push_descriptors : device.physical_device().properties().max_push_descriptors.unwrap_or(0),
+        })
+        .into()
+    }
+
+    pub fn push(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, layout : &Arc<PipelineLayout>, set_index : u32, writes : &[WriteDescriptorSet]) -> Result<(), EngineError> {
+        builder.push_descriptor_set(PipelineBindPoint::Graphics, layout.clone(), set_index, writes.iter().cloned().collect())?;
+
+        Ok(())
+    }
+}
+
+// khr_fragment_shading_rate with pipeline shading rates enabled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShadingRateExt {
+    // Pixels covered by one texel of a shading rate image, None without attachment shading rates
+    pub attachment_texel_size : Option<[u32; 2]>,
+}
+
+impl ShadingRateExt {
+    pub fn load(device : &Device) -> ExtensionGuard<ShadingRateExt> {
+        let features = device.enabled_features();
+
+        features.pipeline_fragment_shading_rate
+        .then(|| ShadingRateExt {
+            attachment_texel_size : features.attachment_fragment_shading_rate
+            .then(|| device.physical_device().properties().max_fragment_shading_rate_attachment_texel_size)
+            .flatten(),
+        })
+        .into()
+    }
+}
+
+// ext_conservative_rasterization, see GraphicsPipelineDesc::conservative_raster
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConservativeRasterExt {
+    // Largest ConservativeRasterMode::extra_overestimation_size in pixels
+    pub max_extra_overestimation_size : f32,
+}
+
+impl ConservativeRasterExt {
+    pub fn load(device : &Device) -> ExtensionGuard<ConservativeRasterExt> {
+        device.enabled_extensions().ext_conservative_rasterization
+        .then(|| ConservativeRasterExt {
+            max_extra_overestimation_size : device.physical_device().properties().max_extra_primitive_overestimation_size.unwrap_or(0.0),
+        })
+        .into()
+    }
+}
+
+// ext_inline_uniform_block or Vulkan 1.3, small uniform blocks live in the descriptor set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InlineUniformBlockExt {
+    // Largest inline block in bytes, at least 256
+    pub max_size : u32,
+}
+
+impl InlineUniformBlockExt {
+    pub fn load(device : &Device) -> ExtensionGuard<InlineUniformBlockExt> {
+        device.enabled_features().inline_uniform_block
+        .then(|| InlineUniformBlockExt {
+            max_size : device.physical_device().properties().max_inline_uniform_block_size.unwrap_or(0),
+        })
+        .into()
+    }
+}
+
+// ext_conditional_rendering, draws and dispatches can be skipped by a value the GPU wrote
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConditionalRenderingExt {
+    // Secondary command buffers can be executed inside a conditional block
+    pub inherited : bool,
+}
+
+impl ConditionalRenderingExt {
+    pub fn load(device : &Device) -> ExtensionGuard<ConditionalRenderingExt> {
+        let features = device.enabled_features();
+
+        features.conditional_rendering
+        .then(|| ConditionalRenderingExt { inherited : features.inherited_conditional_rendering })
+        .into()
+    }
+
+    // Skips the commands up to end while the u32 `offset` bytes into `predication_buffer` is zero, or
+    // while it isn't when `inverted`. The buffer needs CONDITIONAL_RENDERING usage, see
+    // OcclusionCuller::predicate. AutoCommandBufferBuilder can't record the block, it goes into a raw
+    // command buffer, see raw_commands.
+    // Safety: the block has to be closed by end in the same command buffer, and `predication_buffer` has to
+    // stay alive and unwritten until the command buffer finished
+    pub unsafe fn begin(&self, builder : &mut UnsafeCommandBufferBuilder, predication_buffer : &Subbuffer<u32>, offset : u64, inverted : bool) -> Result<(), EngineError> {
+        if !predication_buffer.buffer().usage().contains(BufferUsage::CONDITIONAL_RENDERING) {
+            return Err(EngineError::UnsupportedFeature("predication buffers need CONDITIONAL_RENDERING usage".to_owned()));
+        }
+
+        // The predicate is read as one aligned u32
+        let start = predication_buffer.offset() + offset;
+        if start % 4 != 0 || start + 4 > predication_buffer.buffer().size() {
+            return Err(EngineError::BufferRange { offset : start, len : 4, buffer_len : predication_buffer.buffer().size() });
+        }
+
+        let flags = match inverted {
+            true => vk::ConditionalRenderingFlagsEXT::INVERTED,
+            false => vk::ConditionalRenderingFlagsEXT::empty(),
+        };
+        let begin_info = vk::ConditionalRenderingBeginInfoEXT::builder()
+        .buffer(predication_buffer.buffer().handle())
+        .offset(start)
+        .flags(flags);
+        (builder.device().fns().ext_conditional_rendering.cmd_begin_conditional_rendering_ext)(builder.handle(), &*begin_info);
+
+        Ok(())
+    }
+
+    // Closes the block opened by begin.
+    // Safety: begin opened a block in `builder` that wasn't closed yet
+    pub unsafe fn end(&self, builder : &mut UnsafeCommandBufferBuilder) {
+        (builder.device().fns().ext_conditional_rendering.cmd_end_conditional_rendering_ext)(builder.handle());
+    }
+}
+
+// ext_transform_feedback, vertex shader outputs declared with xfb_buffer and xfb_offset are written
+// to buffers, see XfbPipeline. Recorded into raw command buffers like ConditionalRenderingExt
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransformFeedbackExt {
+    // Buffers that can be bound at once, at least 1
+    pub max_buffers : u32,
+    // Largest xfb_stride in bytes
+    pub max_stride : u32,
+}
+
+impl TransformFeedbackExt {
+    pub fn load(device : &Device) -> ExtensionGuard<TransformFeedbackExt> {
+        let properties = device.physical_device().properties();
+
+        device.enabled_features().transform_feedback
+        .then(|| TransformFeedbackExt {
+            max_buffers : properties.max_transform_feedback_buffers.unwrap_or(1),
+            max_stride : properties.max_transform_feedback_buffer_data_stride.unwrap_or(0),
+        })
+        .into()
+    }
+
+    // Binds `buffers` to the xfb_buffer indices from `first_binding` on, each written from its start.
+    // They need TRANSFORM_FEEDBACK_BUFFER usage.
+    // Safety: the buffers have to stay alive and untouched by vulkano until the command buffer finished
+    pub unsafe fn bind_buffers(&self, builder : &mut UnsafeCommandBufferBuilder, first_binding : u32, buffers : &[Subbuffer<[u8]>]) -> Result<(), EngineError> {
+        let requested = first_binding + buffers.len() as u32;
+        if requested > self.max_buffers {
+            return Err(EngineError::DeviceLimit { limit : "max_transform_feedback_buffers", requested, max : self.max_buffers });
+        }
+        if buffers.iter().any(|buffer| !buffer.buffer().usage().contains(BufferUsage::TRANSFORM_FEEDBACK_BUFFER)) {
+            return Err(EngineError::UnsupportedFeature("transform feedback buffers need TRANSFORM_FEEDBACK_BUFFER usage".to_owned()));
+        }
+
+        let handles = buffers.iter().map(|buffer| buffer.buffer().handle()).collect::<Vec<_>>();
+        let offsets = buffers.iter().map(|buffer| buffer.offset()).collect::<Vec<_>>();
+        let sizes = buffers.iter().map(|buffer| buffer.size()).collect::<Vec<_>>();
+        (builder.device().fns().ext_transform_feedback.cmd_bind_transform_feedback_buffers_ext)(
+            builder.handle(),
+            first_binding,
+            handles.len() as u32,
+            handles.as_ptr(),
+            offsets.as_ptr(),
+            sizes.as_ptr(),
+        );
+
+        Ok(())
+    }
+
+    // Starts capturing into the bound buffers, draws up to end write their outputs.
+    // Without counter buffers every begin writes from the start of the buffers again.
+    // Safety: inside a render pass, closed by end in the same subpass, with the buffers bound
+    pub unsafe fn begin(&self, builder : &mut UnsafeCommandBufferBuilder) {
+        (builder.device().fns().ext_transform_feedback.cmd_begin_transform_feedback_ext)(builder.handle(), 0, 0, ptr::null(), ptr::null());
+    }
+
+    // Safety: begin started capturing in `builder` and end wasn't called since
+    pub unsafe fn end(&self, builder : &mut UnsafeCommandBufferBuilder) {
+        (builder.device().fns().ext_transform_feedback.cmd_end_transform_feedback_ext)(builder.handle(), 0, 0, ptr::null(), ptr::null());
+    }
+}
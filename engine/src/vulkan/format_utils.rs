@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use log::warn;
+use vulkano::{device::physical::PhysicalDevice, format::{Format, FormatFeatures, NumericFormat}, image::ImageTiling, swapchain::{ColorSpace, Surface}};
+
+// Ordered by preference, D16_UNORM is guaranteed by the spec
+const DEPTH_CANDIDATES : [Format; 3] = [Format::D32_SFLOAT, Format::D24_UNORM_S8_UINT, Format::D16_UNORM];
+
+// Ordered by preference, HDR10 is what most HDR displays take natively
+const HDR_CANDIDATES : [(Format, ColorSpace); 3] = [
+    (Format::A2B10G10R10_UNORM_PACK32, ColorSpace::Hdr10St2084),
+    (Format::A2R10G10B10_UNORM_PACK32, ColorSpace::Hdr10St2084),
+    (Format::R16G16B16A16_SFLOAT, ColorSpace::ExtendedSrgbLinear),
+];
+
+// How the presented image is encoded for the display, see PostProcessChain::add_output_tonemap
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    // sRGB, values above 1.0 are clipped
+    #[default]
+    Sdr,
+    // Rec. 2020 primaries with the PQ curve, absolute brightness up to 10000 nits
+    Hdr10,
+    // Linear Rec. 709 primaries in half floats, 1.0 is 80 nits and brighter colors go above it
+    ScRgb,
+}
+
+impl OutputMode {
+    pub fn from_color_space(color_space : ColorSpace) -> OutputMode {
+        match color_space {
+            ColorSpace::Hdr10St2084 => OutputMode::Hdr10,
+            ColorSpace::ExtendedSrgbLinear => OutputMode::ScRgb,
+            _ => OutputMode::Sdr,
+        }
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        *self != OutputMode::Sdr
+    }
+}
+
+pub struct FormatNegotiator;
+
+impl FormatNegotiator {
+    pub fn pick_depth_format(physical_device : &Arc<PhysicalDevice>) -> Format {
+        DEPTH_CANDIDATES.into_iter()
+        .find(|&format| Self::is_format_supported(physical_device, format, ImageTiling::Optimal, FormatFeatures::DEPTH_STENCIL_ATTACHMENT))
+        .unwrap_or(Format::D16_UNORM)
+    }
+
+    // Prefers an 8 bit sRGB or UNORM format in the sRGB color space, otherwise takes what the surface lists first
+    pub fn pick_color_format(physical_device : &Arc<PhysicalDevice>, surface : &Surface, want_srgb : bool) -> (Format, ColorSpace) {
+        let formats = physical_device
+        .surface_formats(surface, Default::default())
+        .expect("failed to get surface formats");
+
+        Self::choose_color_format(&formats, want_srgb)
+    }
+
+    // An HDR pair from HDR_CANDIDATES when the surface offers one, otherwise the pick_color_format pair.
+    // HDR color spaces are only listed when the instance enabled ext_swapchain_colorspace
+    pub fn pick_surface_format(physical_device : &Arc<PhysicalDevice>, surface : &Surface, want_hdr : bool) -> (Format, ColorSpace) {
+        let formats = physical_device
+        .surface_formats(surface, Default::default())
+        .expect("failed to get surface formats");
+
+        let hdr = want_hdr.then(|| Self::choose_hdr_format(&formats)).flatten();
+        if want_hdr && hdr.is_none() {
+            warn!("the surface offers no HDR format, falling back to SDR");
+        }
+
+        hdr.unwrap_or_else(|| Self::choose_color_format(&formats, true))
+    }
+
+    pub fn choose_hdr_format(formats : &[(Format, ColorSpace)]) -> Option<(Format, ColorSpace)> {
+        HDR_CANDIDATES.into_iter().find(|candidate| formats.contains(candidate))
+    }
+
+    // pick_color_format for an already queried list of surface formats
+    pub fn choose_color_format(formats : &[(Format, ColorSpace)], want_srgb : bool) -> (Format, ColorSpace) {
+        let wanted_numeric = match want_srgb {
+            true => NumericFormat::SRGB,
+            false => NumericFormat::UNORM,
+        };
+
+        let preferred = formats.iter()
+        .copied()
+        .find(|&(format, color_space)| {
+            color_space == ColorSpace::SrgbNonLinear
+            && format.numeric_format_color() == Some(wanted_numeric)
+            && matches!(format, Format::B8G8R8A8_SRGB | Format::R8G8B8A8_SRGB | Format::B8G8R8A8_UNORM | Format::R8G8B8A8_UNORM)
+        });
+
+        // With ext_swapchain_colorspace the list may start with an HDR pair, which needs an HDR aware output
+        preferred.unwrap_or_else(|| {
+            let fallback = formats.iter()
+            .copied()
+            .find(|&(_, color_space)| color_space == ColorSpace::SrgbNonLinear)
+            .unwrap_or(formats[0]);
+            warn!("no 8 bit {wanted_numeric:?} surface format available, falling back to {:?}", fallback.0);
+            fallback
+        })
+    }
+
+    pub fn is_format_supported(physical_device : &Arc<PhysicalDevice>, format : Format, tiling : ImageTiling, features : FormatFeatures) -> bool {
+        let Ok(properties) = physical_device.format_properties(format) else {
+            return false;
+        };
+
+        let supported = match tiling {
+            ImageTiling::Linear => properties.linear_tiling_features,
+            _ => properties.optimal_tiling_features,
+        };
+
+        supported.contains(features)
+    }
+}
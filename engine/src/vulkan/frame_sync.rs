@@ -0,0 +1,225 @@
+use std::{mem, sync::Arc};
+
+use log::warn;
+use vulkano::{
+    command_buffer::{allocator::StandardCommandBufferAllocator, pool::CommandPoolResetFlags},
+    device::{Device, Queue},
+    sync::{self, future::FenceSignalFuture, GpuFuture}
+};
+
+use crate::{config::CommandBufferOptions, error::EngineError};
+use super::{deferred_deletion::DeferredDeletionQueue, timeline::TimelineSemaphore, vulkan::VulkanAllocation};
+
+pub type FrameFence = Arc<FenceSignalFuture<Box<dyn GpuFuture>>>;
+
+// Rotates through the frames in flight. A slot is handed out again only once the GPU
+// finished the frame that last used it, so per-frame resources indexed by it are free to change
+pub struct FrameSync {
+    fences : Vec<Option<FrameFence>>,
+    current : usize,
+    // Slot of the most recently submitted frame
+    previous : Option<usize>,
+    // One per slot when created with_command_allocators, otherwise empty
+    command_allocators : Vec<StandardCommandBufferAllocator>,
+    queue_family_index : u32,
+    pool_resets : u64,
+    // See enable_timeline
+    timeline : Option<FrameTimeline>,
+    // See retire
+    deletions : DeferredDeletionQueue,
+}
+
+// Frames signal their number on one semaphore after their submission, so a slot is free once the counter
+// got past the slot's last frame. Frames count from 1, the initial 0 means none finished
+struct FrameTimeline {
+    semaphore : TimelineSemaphore,
+    queue : Arc<Queue>,
+    submitted : u64,
+    // Number of each slot's last submitted frame, 0 for none
+    slot_frames : Vec<u64>,
+}
+
+impl FrameSync {
+    pub fn new(frames_in_flight : usize) -> FrameSync {
+        assert!(frames_in_flight > 0, "at least one frame must be in flight");
+
+        FrameSync {
+            fences : vec![None; frames_in_flight],
+            current : frames_in_flight - 1,
+            previous : None,
+            command_allocators : Vec::new(),
+            queue_family_index : 0,
+            pool_resets : 0,
+            timeline : None,
+            deletions : DeferredDeletionQueue::new(frames_in_flight),
+        }
+    }
+
+    // Every slot records from its own command pool, which begin_frame resets as a whole once the slot's
+    // last frame finished. Frames then reuse the same command buffer memory instead of growing new pools
+    pub fn with_command_allocators(frames_in_flight : usize, device : &Arc<Device>, queue_family_index : u32, options : CommandBufferOptions) -> FrameSync {
+        FrameSync {
+            command_allocators : (0..frames_in_flight)
+                .map(|_| VulkanAllocation::create_command_buffer_allocator(device.clone(), options))
+                .collect(),
+            queue_family_index,
+            ..FrameSync::new(frames_in_flight)
+        }
+    }
+
+    // Waits for frames on one timeline semaphore counter instead of their fences. `queue` is the one frames
+    // are submitted to, end_frame signals each frame's number on it. Frames already in flight are still waited
+    // for through their fences, and on failure nothing changes
+    pub fn enable_timeline(&mut self, queue : &Arc<Queue>) -> Result<(), EngineError> {
+        self.timeline = Some(FrameTimeline {
+            semaphore : TimelineSemaphore::new(queue.device(), 0)?,
+            queue : queue.clone(),
+            submitted : 0,
+            slot_frames : vec![0; self.frames_in_flight()],
+        });
+
+        Ok(())
+    }
+
+    // Frames the GPU finished so far, None without a timeline
+    pub fn completed_frames(&self) -> Option<u64> {
+        let timeline = self.timeline.as_ref()?;
+
+        match timeline.semaphore.value() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("failed to read the frame timeline: {e}");
+                None
+            },
+        }
+    }
+
+    // Keeps `resource` alive until the current frame and every frame before it finished, for buffers and
+    // images a frame replaced while earlier frames may still read them
+    pub fn retire<T : 'static>(&mut self, resource : T) {
+        self.deletions.retire(self.current, resource);
+    }
+
+    pub fn deletions(&self) -> &DeferredDeletionQueue {
+        &self.deletions
+    }
+
+    // Allocator of the current slot, for command buffers submitted with this frame's fence
+    pub fn command_allocator(&self) -> Option<&StandardCommandBufferAllocator> {
+        self.command_allocators.get(self.current)
+    }
+
+    // How often begin_frame managed to reset a slot's command pool
+    pub fn pool_resets(&self) -> u64 {
+        self.pool_resets
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.fences.len()
+    }
+
+    pub fn current_frame(&self) -> usize {
+        self.current
+    }
+
+    // Moves to the next slot and waits for its previous frame, returns the slot index
+    pub fn begin_frame(&mut self) -> usize {
+        self.try_begin_frame().unwrap()
+    }
+
+    // Like begin_frame, but a failed wait such as EngineError::DeviceLost is returned. The slot's frame then
+    // stays in flight, abandon_frames gets rid of it without waiting again
+    pub fn try_begin_frame(&mut self) -> Result<usize, EngineError> {
+        self.current = (self.current + 1) % self.fences.len();
+
+        if let Some(timeline) = &self.timeline {
+            timeline.semaphore.wait(timeline.slot_frames[self.current], None)?;
+        }
+        // With a timeline the fence already signaled, waiting on it only releases what the frame held
+        if let Some(fence) = &self.fences[self.current] {
+            fence.wait(None)?;
+        }
+        self.fences[self.current] = None;
+        self.deletions.release(self.current);
+        self.reset_command_pool();
+
+        Ok(self.current)
+    }
+
+    // Waiting on the fence released the slot's command buffers, unless something else still holds one
+    fn reset_command_pool(&mut self) {
+        let Some(allocator) = self.command_allocators.get(self.current) else { return };
+
+        match allocator.try_reset_pool(self.queue_family_index, CommandPoolResetFlags::empty()) {
+            Ok(()) => self.pool_resets += 1,
+            // The allocator moves on to another pool once this one is full, nothing breaks
+            Err(e) => warn!("failed to reset the command pool of frame slot {}: {e}", self.current),
+        }
+    }
+
+    // The current frame's work is chained after the previously submitted frame
+    pub fn previous_future(&self, device : &Arc<Device>) -> Box<dyn GpuFuture> {
+        match self.previous.and_then(|slot| self.fences[slot].clone()) {
+            Some(fence) => fence.boxed(),
+            None => {
+                let mut now = sync::now(device.clone());
+                now.cleanup_finished();
+
+                now.boxed()
+            },
+        }
+    }
+
+    // Waits for every frame still in flight, afterwards nothing they used is busy on the GPU. A failed wait
+    // such as EngineError::DeviceLost is returned with the frames it didn't get to still in flight,
+    // abandon_frames gets rid of them without waiting again
+    pub fn wait_all(&mut self) -> Result<(), EngineError> {
+        if let Some(timeline) = &self.timeline {
+            timeline.semaphore.wait(timeline.submitted, None)?;
+        }
+        for slot in self.fences.iter_mut() {
+            if let Some(fence) = slot {
+                fence.wait(None)?;
+            }
+            *slot = None;
+        }
+        self.previous = None;
+        self.deletions.release_all();
+
+        Ok(())
+    }
+
+    // Frames submitted that begin_frame or wait_all would still wait for
+    pub fn pending_frames(&self) -> usize {
+        self.fences.iter().flatten().count()
+    }
+
+    // Forgets every frame in flight without waiting, for when the device was lost. Dropping a fence waits
+    // on it, which a lost device may answer with an error, so the fences and what their frames held are
+    // leaked. The timeline goes too, nothing signals it anymore. Retired resources are dropped, the
+    // frames still referencing them hold references of their own
+    pub fn abandon_frames(&mut self) {
+        self.fences.iter_mut().filter_map(Option::take).for_each(mem::forget);
+        self.previous = None;
+        self.timeline = None;
+        self.deletions.release_all();
+    }
+
+    // None when the submission failed, the slot is then free right away
+    pub fn end_frame(&mut self, fence : Option<FrameFence>) {
+        if let Some(timeline) = self.timeline.as_mut().filter(|_| fence.is_some()) {
+            // Signal operations wait for everything submitted to the queue before them, the frame included
+            let frame = timeline.submitted + 1;
+            match timeline.semaphore.signal_submit(&timeline.queue, frame) {
+                Ok(()) => {
+                    timeline.submitted = frame;
+                    timeline.slot_frames[self.current] = frame;
+                },
+                Err(e) => warn!("failed to signal frame {frame} on the timeline, its fence is waited for instead: {e}"),
+            }
+        }
+
+        self.fences[self.current] = fence;
+        self.previous = Some(self.current);
+    }
+}
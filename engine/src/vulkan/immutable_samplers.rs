@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use vulkano::{descriptor_set::layout::{DescriptorSetLayoutBinding, DescriptorType}, image::sampler::Sampler, pipeline::layout::PipelineDescriptorSetLayoutCreateInfo};
+
+use crate::error::EngineError;
+
+// Samplers baked into a descriptor set layout binding. Drivers can then inline them instead of reading
+// them from every descriptor, which is worth it for samplers that never change between draws.
+// Descriptors of the binding are written without a sampler, see Texture2D::write_view_descriptor
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImmutableSamplers {
+    pub set : u32,
+    pub binding : u32,
+    // One per descriptor of the binding
+    pub samplers : Vec<Arc<Sampler>>,
+}
+
+impl ImmutableSamplers {
+    pub fn new(set : u32, binding : u32, samplers : &[Arc<Sampler>]) -> ImmutableSamplers {
+        ImmutableSamplers {
+            set,
+            binding,
+            samplers : samplers.to_vec(),
+        }
+    }
+
+    // Bakes the samplers into the layout binding they name, which the shaders must declare
+    pub fn apply(&self, layout_info : &mut PipelineDescriptorSetLayoutCreateInfo) -> Result<(), EngineError> {
+        let binding = layout_info.set_layouts.get_mut(self.set as usize)
+        .ok_or(EngineError::UnknownDescriptorSet(self.set))?
+        .bindings.get_mut(&self.binding)
+        .ok_or(EngineError::UndeclaredBinding { set : self.set, binding : self.binding })?;
+
+        with_immutable_samplers(binding, &self.samplers)
+    }
+}
+
+// Only sampler and combined image sampler bindings take immutable samplers, one for each of their descriptors
+pub fn with_immutable_samplers(binding : &mut DescriptorSetLayoutBinding, samplers : &[Arc<Sampler>]) -> Result<(), EngineError> {
+    let sampler_type = matches!(binding.descriptor_type, DescriptorType::Sampler | DescriptorType::CombinedImageSampler);
+    if !sampler_type || binding.descriptor_count as usize != samplers.len() {
+        return Err(EngineError::UnsupportedFeature(format!("{} immutable samplers on a {:?} binding of {} descriptors", samplers.len(), binding.descriptor_type, binding.descriptor_count)));
+    }
+
+    binding.immutable_samplers = samplers.to_vec();
+    Ok(())
+}
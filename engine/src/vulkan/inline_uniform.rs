@@ -0,0 +1,87 @@
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    descriptor_set::{layout::DescriptorType, WriteDescriptorSet},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::layout::PipelineDescriptorSetLayoutCreateInfo
+};
+
+use crate::error::EngineError;
+use super::{memory_stats::AllocationCategory, vulkan::VulkanToolset};
+
+// Small uniform data stored in the descriptor set itself, without a buffer to allocate and align.
+// Shaders declare an ordinary uniform block of `size` bytes. With ext_inline_uniform_block the layout
+// stores it inline, without it the binding stays a uniform buffer. write handles both
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InlineUniformBinding {
+    pub set : u32,
+    pub binding : u32,
+    // A multiple of 4
+    pub size : u32,
+}
+
+impl InlineUniformBinding {
+    pub fn new(set : u32, binding : u32, size_bytes : u32) -> InlineUniformBinding {
+        InlineUniformBinding {
+            set,
+            binding,
+            size : size_bytes,
+        }
+    }
+
+    // Whether the block is stored inline on this device, blocks past the size limit never are
+    pub fn is_inline(&self, toolset : &VulkanToolset) -> bool {
+        toolset.inline_uniform_blocks.if_present(|ext| self.size <= ext.max_size).unwrap_or(false)
+    }
+
+    // Turns the uniform buffer binding the shaders declare into an inline block when `inline` is set
+    pub fn apply(&self, layout_info : &mut PipelineDescriptorSetLayoutCreateInfo, inline : bool) -> Result<(), EngineError> {
+        let binding = layout_info.set_layouts.get_mut(self.set as usize)
+        .ok_or(EngineError::UnknownDescriptorSet(self.set))?
+        .bindings.get_mut(&self.binding)
+        .ok_or(EngineError::UndeclaredBinding { set : self.set, binding : self.binding })?;
+
+        if binding.descriptor_type != DescriptorType::UniformBuffer || binding.descriptor_count != 1 || self.size % 4 != 0 {
+            return Err(EngineError::UnsupportedFeature(format!("a {} byte inline uniform block on a {:?} binding of {} descriptors", self.size, binding.descriptor_type, binding.descriptor_count)));
+        }
+
+        if inline {
+            binding.descriptor_type = DescriptorType::InlineUniformBlock;
+            binding.descriptor_count = self.size;
+        }
+
+        Ok(())
+    }
+
+    // Write for a layout created through VulkanToolset::create_pipeline_layout. Shorter data leaves
+    // the rest of the block zeroed, without inline blocks it is copied into a new uniform buffer
+    pub fn write(&self, toolset : &VulkanToolset, data : &[u8]) -> Result<WriteDescriptorSet, EngineError> {
+        if data.len() > self.size as usize {
+            return Err(EngineError::BufferRange { offset : 0, len : data.len() as u64, buffer_len : self.size as u64 });
+        }
+
+        let mut bytes = data.to_vec();
+        if self.is_inline(toolset) {
+            // Inline writes come in whole words
+            bytes.resize(data.len().next_multiple_of(4), 0);
+            return Ok(WriteDescriptorSet::inline_uniform_block(self.binding, 0, bytes));
+        }
+
+        bytes.resize(self.size as usize, 0);
+        let buffer = Buffer::from_iter(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            bytes,
+        )?;
+        toolset.memory_allocator.tracker.track_buffer(AllocationCategory::Uniform, buffer.buffer());
+
+        Ok(WriteDescriptorSet::buffer(self.binding, buffer))
+    }
+}
@@ -0,0 +1,174 @@
+use std::{collections::BTreeMap, fmt::{Display, Formatter, Result as FmtResult}, sync::{Arc, Mutex, Weak}};
+
+use vulkano::{
+    buffer::{Buffer, BufferMemory, BufferUsage},
+    image::{Image, ImageMemory},
+    memory::{MemoryHeapFlags, MemoryProperties, ResourceMemory},
+    DeviceSize
+};
+
+const MIB : f64 = (1 << 20) as f64;
+
+// What an allocation made through the engine's helpers is used for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AllocationCategory {
+    // Vertex and index buffers
+    Vertex,
+    Uniform,
+    // Storage and indirect buffers
+    Storage,
+    Texture,
+    // Buffers that only carry data to or from the device
+    Staging,
+}
+
+impl AllocationCategory {
+    pub fn from_usage(usage : BufferUsage) -> AllocationCategory {
+        if usage.intersects(BufferUsage::VERTEX_BUFFER | BufferUsage::INDEX_BUFFER) {
+            AllocationCategory::Vertex
+        } else if usage.intersects(BufferUsage::UNIFORM_BUFFER | BufferUsage::UNIFORM_TEXEL_BUFFER) {
+            AllocationCategory::Uniform
+        } else if usage.intersects(BufferUsage::STORAGE_BUFFER | BufferUsage::STORAGE_TEXEL_BUFFER | BufferUsage::INDIRECT_BUFFER) {
+            AllocationCategory::Storage
+        } else {
+            AllocationCategory::Staging
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CategoryStats {
+    pub count : usize,
+    pub bytes : DeviceSize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeapStats {
+    pub size : DeviceSize,
+    pub device_local : bool,
+    // Bytes of the tracked allocations living in this heap
+    pub tracked_bytes : DeviceSize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    // In heap index order
+    pub heaps : Vec<HeapStats>,
+    // Categories without live allocations are left out
+    pub categories : BTreeMap<AllocationCategory, CategoryStats>,
+}
+
+impl MemoryStats {
+    pub fn total(&self) -> CategoryStats {
+        self.categories.values().fold(CategoryStats::default(), |total, category| CategoryStats {
+            count : total.count + category.count,
+            bytes : total.bytes + category.bytes,
+        })
+    }
+
+    // Tracked bytes and total size of the device local heaps, the VRAM on discrete GPUs
+    pub fn device_local(&self) -> (DeviceSize, DeviceSize) {
+        self.heaps.iter()
+        .filter(|heap| heap.device_local)
+        .fold((0, 0), |(tracked, size), heap| (tracked + heap.tracked_bytes, size + heap.size))
+    }
+}
+
+// Summary for the log, one line per category and heap
+impl Display for MemoryStats {
+    fn fmt(&self, f : &mut Formatter<'_>) -> FmtResult {
+        let total = self.total();
+        write!(f, "{} tracked allocations, {:.2} MiB", total.count, total.bytes as f64 / MIB)?;
+
+        for (category, stats) in &self.categories {
+            write!(f, "\n  {category:?}: {} allocations, {:.2} MiB", stats.count, stats.bytes as f64 / MIB)?;
+        }
+
+        for (index, heap) in self.heaps.iter().enumerate() {
+            let kind = if heap.device_local { "device local" } else { "host" };
+            write!(f, "\n  heap {index} ({kind}): {:.2} of {:.2} MiB tracked", heap.tracked_bytes as f64 / MIB, heap.size as f64 / MIB)?;
+        }
+
+        Ok(())
+    }
+}
+
+enum TrackedResource {
+    Buffer(Weak<Buffer>),
+    Image(Weak<Image>),
+}
+
+impl TrackedResource {
+    fn is_alive(&self) -> bool {
+        match self {
+            TrackedResource::Buffer(buffer) => buffer.strong_count() > 0,
+            TrackedResource::Image(image) => image.strong_count() > 0,
+        }
+    }
+}
+
+struct TrackedAllocation {
+    category : AllocationCategory,
+    bytes : DeviceSize,
+    memory_type_index : u32,
+    resource : TrackedResource,
+}
+
+// Allocations made through the engine's buffer and image helpers. They drop out of the tally once
+// the last reference to their buffer or image is gone, memory allocated elsewhere isn't counted
+#[derive(Default)]
+pub struct AllocationTracker {
+    allocations : Mutex<Vec<TrackedAllocation>>,
+}
+
+impl AllocationTracker {
+    pub fn track_buffer(&self, category : AllocationCategory, buffer : &Arc<Buffer>) {
+        // Sparse buffers have no memory of their own
+        if let BufferMemory::Normal(memory) = buffer.memory() {
+            self.track(category, TrackedResource::Buffer(Arc::downgrade(buffer)), std::slice::from_ref(memory));
+        }
+    }
+
+    pub fn track_image(&self, category : AllocationCategory, image : &Arc<Image>) {
+        // Swapchain images belong to the swapchain
+        if let ImageMemory::Normal(memory) = image.memory() {
+            self.track(category, TrackedResource::Image(Arc::downgrade(image)), memory);
+        }
+    }
+
+    fn track(&self, category : AllocationCategory, resource : TrackedResource, memory : &[ResourceMemory]) {
+        let Some(first) = memory.first() else { return };
+
+        self.allocations.lock().unwrap().push(TrackedAllocation {
+            category,
+            bytes : memory.iter().map(ResourceMemory::size).sum(),
+            memory_type_index : first.device_memory().memory_type_index(),
+            resource,
+        });
+    }
+
+    pub fn stats(&self, properties : &MemoryProperties) -> MemoryStats {
+        let mut heaps = properties.memory_heaps.iter()
+        .map(|heap| HeapStats {
+            size : heap.size,
+            device_local : heap.flags.intersects(MemoryHeapFlags::DEVICE_LOCAL),
+            tracked_bytes : 0,
+        })
+        .collect::<Vec<_>>();
+        let mut categories = BTreeMap::<AllocationCategory, CategoryStats>::new();
+
+        let mut allocations = self.allocations.lock().unwrap();
+        allocations.retain(|allocation| allocation.resource.is_alive());
+
+        for allocation in allocations.iter() {
+            let category = categories.entry(allocation.category).or_default();
+            category.count += 1;
+            category.bytes += allocation.bytes;
+
+            let heap_index = properties.memory_types[allocation.memory_type_index as usize].heap_index;
+            heaps[heap_index as usize].tracked_bytes += allocation.bytes;
+        }
+
+        MemoryStats { heaps, categories }
+    }
+}
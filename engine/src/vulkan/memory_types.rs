@@ -0,0 +1,41 @@
+use std::cmp::Reverse;
+
+use vulkano::{memory::{MemoryProperties, MemoryPropertyFlags}, DeviceSize};
+
+// What choosing a memory type looks at, one per memory type of the physical device
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryTypeCandidate {
+    pub property_flags : MemoryPropertyFlags,
+    // Size of the heap the type allocates from
+    pub heap_size : DeviceSize,
+}
+
+impl MemoryTypeCandidate {
+    pub fn new(property_flags : MemoryPropertyFlags, heap_size : DeviceSize) -> MemoryTypeCandidate {
+        MemoryTypeCandidate { property_flags, heap_size }
+    }
+
+    // In memory type index order
+    pub fn from_properties(properties : &MemoryProperties) -> Vec<MemoryTypeCandidate> {
+        properties.memory_types.iter()
+        .map(|memory_type| Self::new(memory_type.property_flags, properties.memory_heaps[memory_type.heap_index as usize].size))
+        .collect()
+    }
+}
+
+// Index of the memory type to allocate from, None when no type in `allowed_bits` has every required flag.
+// Types with more of the preferred flags win, then the ones with fewer flags nobody asked for, then the
+// larger heap, then the lower index. ReBAR shows up as a DEVICE_LOCAL | HOST_VISIBLE type on the device
+// heap, require HOST_VISIBLE and prefer DEVICE_LOCAL to write straight into video memory where it exists
+pub fn pick_memory_type(candidates : &[MemoryTypeCandidate], allowed_bits : u32, required : MemoryPropertyFlags, preferred : MemoryPropertyFlags) -> Option<u32> {
+    candidates.iter()
+    .enumerate()
+    .filter(|&(index, candidate)| allowed_bits & (1 << index) != 0 && candidate.property_flags.contains(required))
+    .max_by_key(|&(index, candidate)| {
+        let flags = candidate.property_flags;
+        let extra = flags.difference(required).difference(preferred);
+
+        (flags.intersection(preferred).count(), Reverse(extra.count()), candidate.heap_size, Reverse(index))
+    })
+    .map(|(index, _)| index as u32)
+}
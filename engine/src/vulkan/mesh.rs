@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{BufferContents, BufferUsage, IndexBuffer, IndexType, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    device::Queue,
+    pipeline::graphics::vertex_input::Vertex
+};
+
+use crate::error::EngineError;
+use super::{staging::upload_buffer, vulkan::VulkanAllocation};
+
+#[derive(BufferContents, Vertex, Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct Vertex3D {
+    #[format(R32G32B32_SFLOAT)]
+    pub position : [f32; 3],
+    #[format(R32G32B32_SFLOAT)]
+    pub normal : [f32; 3],
+    #[format(R32G32_SFLOAT)]
+    pub uv : [f32; 2],
+}
+
+// Index value that starts a new strip when primitive restart is enabled, all bits set for the type
+pub fn primitive_restart_index(index_type : IndexType) -> u32 {
+    u32::MAX >> (32 - 8 * index_type.size())
+}
+
+// Indices before upload. 16 bit indices halve the index buffer for meshes with few vertices
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MeshIndices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl MeshIndices {
+    // The narrowest type that addresses `vertex_count` vertices. The last u16 value is left
+    // free so that a real index is never mistaken for the restart index
+    pub fn fit(indices : Vec<u32>, vertex_count : usize) -> MeshIndices {
+        if vertex_count <= u16::MAX as usize {
+            MeshIndices::U16(indices.into_iter().map(|index| index as u16).collect())
+        } else {
+            MeshIndices::U32(indices)
+        }
+    }
+
+    // 16 bit indices or an error naming the first one that doesn't fit
+    pub fn narrow(indices : &[u32]) -> Result<MeshIndices, EngineError> {
+        indices.iter()
+        .map(|&index| u16::try_from(index).map_err(|_| EngineError::IndexOverflow { index, index_type : IndexType::U16 }))
+        .collect::<Result<Vec<_>, _>>()
+        .map(MeshIndices::U16)
+    }
+
+    // Strips joined into one index list, each followed by the restart index of the fitting type.
+    // Draw it with a strip topology and primitive_restart_enable set
+    pub fn strips(strips : &[Vec<u32>], vertex_count : usize) -> MeshIndices {
+        let restart = primitive_restart_index(IndexType::U32);
+        let joined = strips.iter()
+        .flat_map(|strip| strip.iter().copied().chain([restart]))
+        .collect::<Vec<_>>();
+
+        // Narrowing truncates the u32 restart index to the u16 one
+        Self::fit(joined, vertex_count)
+    }
+
+    pub fn index_type(&self) -> IndexType {
+        match self {
+            MeshIndices::U16(_) => IndexType::U16,
+            MeshIndices::U32(_) => IndexType::U32,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            MeshIndices::U16(indices) => indices.len(),
+            MeshIndices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn upload(self, allocator : &VulkanAllocation, queue : &Arc<Queue>) -> Result<IndexBuffer, EngineError> {
+        Ok(match self {
+            MeshIndices::U16(indices) => upload_buffer(allocator, queue, BufferUsage::INDEX_BUFFER, indices)?.into(),
+            MeshIndices::U32(indices) => upload_buffer(allocator, queue, BufferUsage::INDEX_BUFFER, indices)?.into(),
+        })
+    }
+}
+
+impl From<Vec<u16>> for MeshIndices {
+    fn from(indices : Vec<u16>) -> Self {
+        MeshIndices::U16(indices)
+    }
+}
+
+impl From<Vec<u32>> for MeshIndices {
+    fn from(indices : Vec<u32>) -> Self {
+        MeshIndices::U32(indices)
+    }
+}
+
+pub struct Mesh {
+    pub vertex_buffer : Subbuffer<[Vertex3D]>,
+    // Either index width, bind_index_buffer picks the matching IndexType
+    pub index_buffer : IndexBuffer,
+}
+
+impl Mesh {
+    pub fn new(allocator : &VulkanAllocation, queue : &Arc<Queue>, vertices : Vec<Vertex3D>, indices : impl Into<MeshIndices>) -> Result<Mesh, EngineError> {
+        Ok(Mesh {
+            vertex_buffer : upload_buffer(allocator, queue, BufferUsage::VERTEX_BUFFER, vertices)?,
+            index_buffer : indices.into().upload(allocator, queue)?,
+        })
+    }
+
+    pub fn index_count(&self) -> u32 {
+        self.index_buffer.len() as u32
+    }
+
+    pub fn index_type(&self) -> IndexType {
+        self.index_buffer.index_type()
+    }
+
+    // Binds both buffers, ready for draw_indexed with index_count indices
+    pub fn bind(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) -> Result<(), EngineError> {
+        builder.bind_vertex_buffers(0, self.vertex_buffer.clone())?
+        .bind_index_buffer(self.index_buffer.clone())?;
+
+        Ok(())
+    }
+}
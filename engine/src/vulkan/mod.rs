@@ -1,2 +1,44 @@
+pub mod api_version;
+pub mod async_compute;
+pub mod blit;
+pub mod capabilities;
+pub mod conservative_raster;
+pub mod debug_utils;
+pub mod deferred_deletion;
+pub mod defrag;
+pub mod descriptor_ring;
+pub mod device_info;
+pub mod extensions;
+pub mod format_utils;
+pub mod frame_sync;
+pub mod immutable_samplers;
+pub mod inline_uniform;
+pub mod memory_stats;
+pub mod memory_types;
+pub mod mesh;
+pub mod occlusion;
+pub mod pipeline_desc;
+pub mod pipeline_stats;
+pub mod point_cloud;
+pub mod queues;
+pub mod raw_commands;
+pub mod recording;
+pub mod reduce;
+pub mod render_pass;
+pub mod render_target_pool;
+pub mod scan;
+pub mod scissor;
+pub mod screenshot;
+pub mod shader_interface;
+pub mod shading_rate;
+pub mod staging;
+pub mod storage_buffer;
+pub mod swapchain;
+pub mod texture;
+pub mod timeline;
+pub mod transform_feedback;
+pub mod vertex;
+pub mod vertex_divisor;
+pub mod vertex_pull;
 pub mod vulkan;
-pub mod vulkan_window;
\ No newline at end of file
+pub mod vulkan_window;
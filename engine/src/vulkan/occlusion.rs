@@ -0,0 +1,171 @@
+use std::{collections::HashMap, ops::Range, sync::{Arc, Mutex}};
+
+use vulkano::{
+    buffer::Subbuffer,
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    device::Device,
+    query::{QueryControlFlags, QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType}
+};
+
+use crate::error::EngineError;
+
+// Queried draws per frame in flight, later ones are drawn without a query
+pub const MAX_OCCLUSION_QUERIES : u32 = 256;
+
+// One occlusion query per object. A query counts whether any sample of the draws between begin and
+// end passed the depth test, exact counts would need the occlusion_query_precise feature
+pub struct OcclusionQueryPool {
+    pool : Arc<QueryPool>,
+}
+
+impl OcclusionQueryPool {
+    pub fn new(device : Arc<Device>, query_count : u32) -> Result<OcclusionQueryPool, EngineError> {
+        let pool = QueryPool::new(
+            device,
+            QueryPoolCreateInfo {
+                query_count,
+                ..QueryPoolCreateInfo::query_type(QueryType::Occlusion)
+            },
+        )?;
+
+        Ok(OcclusionQueryPool { pool })
+    }
+
+    pub fn query_count(&self) -> u32 {
+        self.pool.query_count()
+    }
+
+    // Record outside of a render pass, before the queries are used again
+    pub fn reset(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) -> Result<(), EngineError> {
+        // Safe as long as no other command buffer still has one of the queries active
+        unsafe {
+            builder.reset_query_pool(self.pool.clone(), 0..self.query_count())?;
+        }
+
+        Ok(())
+    }
+
+    // Record inside a render pass, around the draws of object `query`
+    pub fn begin(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, query : u32) -> Result<(), EngineError> {
+        // Safe as the query was reset since it was last ended, see reset
+        unsafe {
+            builder.begin_query(self.pool.clone(), query, QueryControlFlags::empty())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn end(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, query : u32) -> Result<(), EngineError> {
+        builder.end_query(self.pool.clone(), query)?;
+
+        Ok(())
+    }
+
+    // Writes one sample count per query into `results`, which needs TRANSFER_DST usage. Every query
+    // must have been ended since the last reset, the copy waits for their results on the GPU
+    pub fn copy_results(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, results : &Subbuffer<[u32]>) -> Result<(), EngineError> {
+        builder.copy_query_pool_results(self.pool.clone(), 0..self.query_count(), results.clone(), QueryResultFlags::WAIT)?;
+
+        Ok(())
+    }
+}
+
+// Occlusion queries around selected draws of the frame recorder, see Frame::draw_queried. Each frame in
+// flight owns a range of MAX_OCCLUSION_QUERIES queries, read once the slot's fence signaled
+pub struct OcclusionQuerySet {
+    pool : Arc<QueryPool>,
+    precise : bool,
+    // Ids queried in each slot since its last reset, in query order
+    recorded : Mutex<Vec<Vec<u32>>>,
+}
+
+impl OcclusionQuerySet {
+    // Counts samples exactly when occlusion_query_precise was enabled
+    pub fn new(device : Arc<Device>, frames_in_flight : u32) -> Result<OcclusionQuerySet, EngineError> {
+        let precise = device.enabled_features().occlusion_query_precise;
+        let pool = QueryPool::new(
+            device,
+            QueryPoolCreateInfo {
+                query_count: frames_in_flight * MAX_OCCLUSION_QUERIES,
+                ..QueryPoolCreateInfo::query_type(QueryType::Occlusion)
+            },
+        )?;
+
+        Ok(OcclusionQuerySet {
+            pool,
+            precise,
+            recorded : Mutex::new(vec![Vec::new(); frames_in_flight as usize]),
+        })
+    }
+
+    // False when a draw only reports whether any sample passed, its count is then 0 or some positive value
+    pub fn is_precise(&self) -> bool {
+        self.precise
+    }
+
+    fn slot_range(slot : u32) -> Range<u32> {
+        slot * MAX_OCCLUSION_QUERIES..(slot + 1) * MAX_OCCLUSION_QUERIES
+    }
+
+    // Record outside of a render pass, before the slot's first begin. Results not read by then are lost
+    pub fn reset(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, slot : u32) -> Result<(), EngineError> {
+        // Safe as the slot's previous frame finished before its command buffer is recorded again
+        unsafe {
+            builder.reset_query_pool(self.pool.clone(), Self::slot_range(slot))?;
+        }
+        self.recorded.lock().unwrap()[slot as usize].clear();
+
+        Ok(())
+    }
+
+    // Record inside a render pass. Returns the query to end after the draw, None once the slot is full
+    pub fn begin(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, slot : u32, id : u32) -> Result<Option<u32>, EngineError> {
+        let mut recorded = self.recorded.lock().unwrap();
+        let ids = &mut recorded[slot as usize];
+        if ids.len() as u32 == MAX_OCCLUSION_QUERIES {
+            return Ok(None);
+        }
+
+        let query = Self::slot_range(slot).start + ids.len() as u32;
+        let flags = match self.precise {
+            true => QueryControlFlags::PRECISE,
+            false => QueryControlFlags::empty(),
+        };
+        // Safe as every query of the slot was reset before the frame, see reset
+        unsafe {
+            builder.begin_query(self.pool.clone(), query, flags)?;
+        }
+        ids.push(id);
+
+        Ok(Some(query))
+    }
+
+    pub fn end(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, query : u32) -> Result<(), EngineError> {
+        builder.end_query(self.pool.clone(), query)?;
+
+        Ok(())
+    }
+
+    // Samples per id of the slot's last frame, summed when an id was queried more than once. Doesn't wait,
+    // ids whose results aren't available yet are left out instead
+    pub fn read(&self, slot : u32) -> Result<HashMap<u32, u64>, EngineError> {
+        let recorded = self.recorded.lock().unwrap();
+        let ids = &recorded[slot as usize];
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        // Each query's count is followed by its availability
+        let start = Self::slot_range(slot).start;
+        let mut values = vec![0u64; ids.len() * 2];
+        self.pool.get_results(start..start + ids.len() as u32, &mut values, QueryResultFlags::WITH_AVAILABILITY)?;
+
+        let available = ids.iter().zip(values.chunks(2)).filter(|(_, result)| result[1] != 0);
+        let mut samples = HashMap::new();
+        for (&id, result) in available {
+            *samples.entry(id).or_default() += result[0];
+        }
+
+        Ok(samples)
+    }
+}
@@ -0,0 +1,195 @@
+use std::{fmt::{Display, Formatter, Result as FmtResult}, sync::Arc};
+
+use vulkano::{
+    format::Format,
+    image::{ImageAspects, SampleCount},
+    pipeline::graphics::{color_blend::{AttachmentBlend, ColorComponents}, depth_stencil::DepthState, input_assembly::PrimitiveTopology, rasterization::PolygonMode, vertex_input::{Vertex, VertexBufferDescription}, viewport::Viewport},
+    render_pass::{RenderPass, Subpass},
+    shader::{ShaderModule, SpecializationConstant}
+};
+
+use super::{conservative_raster::ConservativeRasterMode, immutable_samplers::ImmutableSamplers, inline_uniform::InlineUniformBinding, scissor::ScissorState, vertex::VulkanVertex, vulkan::{DepthBias, EntryPointNames, FaceCull, MultisampleConfig, PipelineOptions, Winding}};
+
+// Everything VulkanToolset::create_pipeline needs. The default plus a vertex and fragment shader is the
+// pipeline create_graphics_pipeline has always built, fill in the rest with ..Default::default()
+#[derive(Clone, Debug)]
+pub struct GraphicsPipelineDesc {
+    // Required, only optional so the struct has a default
+    pub vs : Option<Arc<ShaderModule>>,
+    // Depth-only subpasses may leave it out
+    pub fs : Option<Arc<ShaderModule>>,
+    pub entry_points : EntryPointNames,
+    // Specialization constants of the vertex shader, such as the fixed size of a point pipeline
+    pub vertex_specialization : Vec<(u32, SpecializationConstant)>,
+    // Buffer the vertex shader's inputs are read from, a VulkanVertex per vertex by default
+    pub vertex_layout : VertexBufferDescription,
+    pub topology : PrimitiveTopology,
+    pub cull : FaceCull,
+    pub front_face : Winding,
+    pub polygon_mode : PolygonMode,
+    // Depth offset of the rasterized fragments, see DepthBias
+    pub depth_bias : Option<DepthBias>,
+    // Applied to every color attachment, None replaces what is there
+    pub blend : Option<AttachmentBlend>,
+    // None tests and writes depth in depth-only subpasses and leaves it off in the others.
+    // Anything else needs a subpass with a depth attachment
+    pub depth : Option<DepthState>,
+    // None takes the subpass's sample count, anything else has to match it
+    pub samples : Option<SampleCount>,
+    pub multisample : MultisampleConfig,
+    // Channels written per color attachment of the subpass, attachments past the end write all of them
+    pub color_write_masks : Vec<ColorComponents>,
+    // None takes the window's viewport
+    pub viewport : Option<Viewport>,
+    // The viewport and scissor are set while recording, as Frame::set_viewport_regions does
+    pub dynamic_viewport : bool,
+    pub scissor : ScissorState,
+    // Samplers baked into the layout, for textures that are always sampled the same way
+    pub immutable_samplers : Vec<ImmutableSamplers>,
+    // Uniform blocks stored in the descriptor set where the device supports it
+    pub inline_uniform_blocks : Vec<InlineUniformBinding>,
+    // None draws into the window's render pass
+    pub render_pass_override : Option<Arc<RenderPass>>,
+    pub subpass_index : u32,
+    // Rasterizes every pixel a primitive touches, for voxelization. Needs
+    // DeviceCapabilities::conservative_rasterization
+    pub conservative_raster : Option<ConservativeRasterMode>,
+}
+
+impl Default for GraphicsPipelineDesc {
+    fn default() -> GraphicsPipelineDesc {
+        GraphicsPipelineDesc {
+            vs : None,
+            fs : None,
+            entry_points : EntryPointNames::default(),
+            vertex_specialization : Vec::new(),
+            vertex_layout : VulkanVertex::per_vertex(),
+            topology : PrimitiveTopology::TriangleList,
+            cull : FaceCull::None,
+            front_face : Winding::CounterClockwise,
+            polygon_mode : PolygonMode::Fill,
+            depth_bias : None,
+            blend : None,
+            depth : None,
+            samples : None,
+            multisample : MultisampleConfig::default(),
+            color_write_masks : Vec::new(),
+            viewport : None,
+            dynamic_viewport : false,
+            scissor : ScissorState::default(),
+            immutable_samplers : Vec::new(),
+            inline_uniform_blocks : Vec::new(),
+            render_pass_override : None,
+            subpass_index : 0,
+            conservative_raster : None,
+        }
+    }
+}
+
+impl GraphicsPipelineDesc {
+    pub fn new(vs : Arc<ShaderModule>, fs : Arc<ShaderModule>) -> GraphicsPipelineDesc {
+        GraphicsPipelineDesc {
+            vs : Some(vs),
+            fs : Some(fs),
+            ..Default::default()
+        }
+    }
+
+    // The window pipeline create_graphics_pipeline_with builds from `options`
+    pub fn with_options(vs : Option<Arc<ShaderModule>>, fs : Option<Arc<ShaderModule>>, options : PipelineOptions) -> GraphicsPipelineDesc {
+        GraphicsPipelineDesc {
+            vs,
+            fs,
+            entry_points : options.entry_points,
+            cull : options.cull.cull,
+            front_face : options.cull.front_face,
+            depth_bias : options.depth_bias,
+            multisample : options.multisample,
+            color_write_masks : options.color_write_masks,
+            scissor : options.scissor,
+            immutable_samplers : options.immutable_samplers,
+            inline_uniform_blocks : options.inline_uniform_blocks,
+            ..Default::default()
+        }
+    }
+
+    // Combinations the subpass can't draw, checked before anything is handed to the driver
+    pub fn check(&self, subpass : &SubpassLayout) -> Result<(), PipelineDescError> {
+        if self.depth.is_some() && !subpass.depth_attachment {
+            return Err(PipelineDescError::NoDepthAttachment);
+        }
+        if self.blend.is_some() && subpass.color_attachments == 0 {
+            return Err(PipelineDescError::NoColorAttachments);
+        }
+        if let (Some(requested), Some(subpass)) = (self.samples, subpass.samples) {
+            if requested != subpass {
+                return Err(PipelineDescError::Samples { requested, subpass });
+            }
+        }
+        if self.color_write_masks.len() > subpass.color_attachments as usize {
+            return Err(PipelineDescError::ColorWriteMasks { masks : self.color_write_masks.len() as u32, color_attachments : subpass.color_attachments });
+        }
+
+        Ok(())
+    }
+}
+
+// What GraphicsPipelineDesc::check needs to know about the subpass a pipeline draws in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubpassLayout {
+    pub color_attachments : u32,
+    // Stencil-only attachments don't count
+    pub depth_attachment : bool,
+    // None for subpasses without attachments
+    pub samples : Option<SampleCount>,
+}
+
+impl SubpassLayout {
+    pub fn of(subpass : &Subpass) -> SubpassLayout {
+        let attachments = subpass.render_pass().attachments();
+        let depth_attachment = subpass.subpass_desc()
+        .depth_stencil_attachment
+        .as_ref()
+        .is_some_and(|reference| has_depth(attachments[reference.attachment as usize].format));
+
+        SubpassLayout {
+            color_attachments : subpass.num_color_attachments(),
+            depth_attachment,
+            samples : subpass.num_samples(),
+        }
+    }
+}
+
+fn has_depth(format : Format) -> bool {
+    format.aspects().intersects(ImageAspects::DEPTH)
+}
+
+// Why a pipeline can't be created, reported instead of failing in the driver
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PipelineDescError {
+    MissingVertexShader,
+    // Headless toolsets have no window render pass to fall back to
+    NoRenderPass,
+    // Headless toolsets have no window viewport either, their pipelines need a dynamic one
+    NoViewport,
+    NoSubpass { index : u32, subpasses : u32 },
+    NoDepthAttachment,
+    NoColorAttachments,
+    Samples { requested : SampleCount, subpass : SampleCount },
+    ColorWriteMasks { masks : u32, color_attachments : u32 },
+}
+
+impl Display for PipelineDescError {
+    fn fmt(&self, f : &mut Formatter<'_>) -> FmtResult {
+        match self {
+            PipelineDescError::MissingVertexShader => write!(f, "no vertex shader given"),
+            PipelineDescError::NoRenderPass => write!(f, "a headless toolset has no window render pass, a render pass override is needed"),
+            PipelineDescError::NoViewport => write!(f, "a headless toolset has no window viewport, a dynamic viewport is needed"),
+            PipelineDescError::NoSubpass { index, subpasses } => write!(f, "subpass {index} requested from a render pass with {subpasses} subpasses"),
+            PipelineDescError::NoDepthAttachment => write!(f, "depth state given for a subpass without a depth attachment"),
+            PipelineDescError::NoColorAttachments => write!(f, "blending given for a subpass without color attachments"),
+            PipelineDescError::Samples { requested, subpass } => write!(f, "{} samples requested for a subpass with {} samples", *requested as u32, *subpass as u32),
+            PipelineDescError::ColorWriteMasks { masks, color_attachments } => write!(f, "{masks} color write masks for {color_attachments} color attachments"),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::{collections::{BTreeSet, HashMap}, sync::{Arc, Mutex}};
+
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    device::Device,
+    query::{QueryControlFlags, QueryPipelineStatisticFlags, QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType}
+};
+
+// One slot per frame in flight is plenty
+pub const MAX_STATS_QUERIES : u32 = 16;
+
+const STATISTICS_BIT_ORDER : [QueryPipelineStatisticFlags; 11] = [
+    QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES,
+    QueryPipelineStatisticFlags::INPUT_ASSEMBLY_PRIMITIVES,
+    QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS,
+    QueryPipelineStatisticFlags::GEOMETRY_SHADER_INVOCATIONS,
+    QueryPipelineStatisticFlags::GEOMETRY_SHADER_PRIMITIVES,
+    QueryPipelineStatisticFlags::CLIPPING_INVOCATIONS,
+    QueryPipelineStatisticFlags::CLIPPING_PRIMITIVES,
+    QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS,
+    QueryPipelineStatisticFlags::TESSELLATION_CONTROL_SHADER_PATCHES,
+    QueryPipelineStatisticFlags::TESSELLATION_EVALUATION_SHADER_INVOCATIONS,
+    QueryPipelineStatisticFlags::COMPUTE_SHADER_INVOCATIONS,
+];
+
+pub type PipelineStats = HashMap<QueryPipelineStatisticFlags, u64>;
+
+// Needs the pipeline_statistics_query feature, which the toolset enables when supported
+pub struct PipelineStatsPool {
+    pool : Arc<QueryPool>,
+    stats : QueryPipelineStatisticFlags,
+    // Queries ended since they were last read
+    pending : Mutex<BTreeSet<u32>>,
+}
+
+impl PipelineStatsPool {
+    pub fn new(device : Arc<Device>, stats : QueryPipelineStatisticFlags) -> PipelineStatsPool {
+        let pool = QueryPool::new(
+            device,
+            QueryPoolCreateInfo {
+                query_count: MAX_STATS_QUERIES,
+                ..QueryPoolCreateInfo::query_type(QueryType::PipelineStatistics(stats))
+            },
+        ).expect("failed to create pipeline statistics query pool");
+
+        PipelineStatsPool {
+            pool,
+            stats,
+            pending : Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    // Record outside of a render pass. The previous submission using this query must have finished
+    pub fn begin_stats(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, query_id : u32) {
+        assert!(query_id < MAX_STATS_QUERIES, "query {query_id} is out of range");
+
+        // Safe as long as no other command buffer still has the query active, see above
+        unsafe {
+            builder.reset_query_pool(self.pool.clone(), query_id..query_id + 1)
+            .unwrap()
+            .begin_query(self.pool.clone(), query_id, QueryControlFlags::empty())
+            .unwrap();
+        }
+    }
+
+    pub fn end_stats(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, query_id : u32) {
+        builder.end_query(self.pool.clone(), query_id).unwrap();
+        self.pending.lock().unwrap().insert(query_id);
+    }
+
+    // Results of one query, None if it was not ended since it was last read.
+    // Blocks until the command buffer that recorded it has finished
+    pub fn read(&self, query_id : u32) -> Option<PipelineStats> {
+        if !self.pending.lock().unwrap().remove(&query_id) {
+            return None;
+        }
+
+        Some(self.query_results(query_id))
+    }
+
+    // Sum of every query ended since it was last read, call once their fences signaled
+    pub fn read_all(&self) -> PipelineStats {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+
+        let mut totals = PipelineStats::new();
+        for query_id in pending {
+            for (flag, value) in self.query_results(query_id) {
+                *totals.entry(flag).or_default() += value;
+            }
+        }
+
+        totals
+    }
+
+    fn query_results(&self, query_id : u32) -> PipelineStats {
+        let mut values = vec![0u64; self.stats.count() as usize];
+        self.pool.get_results(query_id..query_id + 1, &mut values, QueryResultFlags::WAIT)
+        .expect("failed to read pipeline statistics");
+
+        // Values come back in the bit order of the requested statistics
+        STATISTICS_BIT_ORDER.into_iter()
+        .filter(|&flag| self.stats.intersects(flag))
+        .zip(values)
+        .collect()
+    }
+}
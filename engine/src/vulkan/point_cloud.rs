@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use vulkano::{buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer}, memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter}, pipeline::graphics::vertex_input::Vertex};
+
+// Specialization constant the point vertex shader reads its fixed size from
+pub const POINT_SIZE_CONSTANT_ID : u32 = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointSizeSource {
+    // Baked into the vertex shader through specialization constant 0
+    Fixed(f32),
+    // Vertex shader writes gl_PointSize itself
+    ShaderControlled,
+}
+
+#[derive(BufferContents, Vertex, Clone, Copy)]
+#[repr(C)]
+pub struct PointVertex {
+    #[format(R32G32B32_SFLOAT)]
+    pub position : [f32; 3],
+    #[format(R32G32B32A32_SFLOAT)]
+    pub color : [f32; 4],
+}
+
+pub struct PointCloud {
+    pub vertex_buffer : Subbuffer<[PointVertex]>,
+}
+
+impl PointCloud {
+    pub fn from_positions_colors(positions : &[[f32; 3]], colors : &[[f32; 4]], allocator : Arc<dyn MemoryAllocator>) -> PointCloud {
+        assert_eq!(positions.len(), colors.len(), "point cloud needs one color per position");
+
+        // Interleave attributes into a single vertex buffer
+        let points = positions.iter()
+        .zip(colors)
+        .map(|(&position, &color)| PointVertex { position, color });
+
+        let vbo = Buffer::from_iter(
+            allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            points,
+        ).expect("failed to create point cloud buffer");
+
+        PointCloud {
+            vertex_buffer : vbo,
+        }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.vertex_buffer.len() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertex_buffer.len() == 0
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::BTreeMap;
+
+use crate::config::{QueueRequest, QueueRole};
+
+// Queues to create in one family and which of them serves each requested role
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueuePlan {
+    // One per queue, in creation order
+    pub priorities : Vec<f32>,
+    pub roles : BTreeMap<QueueRole, usize>,
+}
+
+// Graphics always gets the first queue, at 1.0 unless it was requested with another priority.
+// Every other role gets a queue of its own while the family has one left, past that it shares the
+// graphics queue, as everything does on single queue families. A role requested twice keeps its first request
+pub fn plan_queues(requests : &[QueueRequest], queue_count : u32) -> QueuePlan {
+    let graphics_priority = requests.iter()
+    .find(|request| request.role == QueueRole::Graphics)
+    .map_or(1.0, |request| request.priority);
+
+    let mut priorities = vec![graphics_priority.clamp(0.0, 1.0)];
+    let mut roles = BTreeMap::from([(QueueRole::Graphics, 0)]);
+
+    for request in requests {
+        if roles.contains_key(&request.role) {
+            continue;
+        }
+
+        let index = if priorities.len() < queue_count as usize {
+            priorities.push(request.priority.clamp(0.0, 1.0));
+            priorities.len() - 1
+        } else {
+            0
+        };
+        roles.insert(request.role, index);
+    }
+
+    QueuePlan { priorities, roles }
+}
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use ash::vk;
+use vulkano::{
+    command_buffer::{sys::{CommandBufferBeginInfo, UnsafeCommandBuffer, UnsafeCommandBufferBuilder}, CommandBufferLevel, CommandBufferUsage},
+    device::{DeviceOwned, Queue},
+    sync::fence::{Fence, FenceCreateInfo},
+    VulkanError,
+    VulkanObject
+};
+
+use crate::error::EngineError;
+use super::vulkan::VulkanToolset;
+
+// Command buffer for commands AutoCommandBufferBuilder can't record in vulkano 0.34, such as the ones of
+// ConditionalRenderingExt and TransformFeedbackExt. vulkano tracks nothing in it: barriers, image layouts
+// and keeping what it uses alive until it finished are up to the caller
+pub fn begin_raw_commands(toolset : &VulkanToolset) -> Result<UnsafeCommandBufferBuilder, EngineError> {
+    let builder = unsafe {
+        UnsafeCommandBufferBuilder::new(
+            &toolset.memory_allocator.buffer_allocator,
+            toolset.device_queue.queue_family_index(),
+            CommandBufferLevel::Primary,
+            CommandBufferBeginInfo {
+                usage: CommandBufferUsage::OneTimeSubmit,
+                ..Default::default()
+            },
+        )?
+    };
+
+    Ok(builder)
+}
+
+// Runs `command_buffer` on `queue` and blocks until it finished, so whatever it used can go back to vulkano
+pub fn submit_raw_and_wait(queue : &Arc<Queue>, command_buffer : &UnsafeCommandBuffer) -> Result<(), EngineError> {
+    let device = queue.device();
+    let fence = Fence::new(device.clone(), FenceCreateInfo::default())?;
+    let handles = [command_buffer.handle()];
+    let submit_info = vk::SubmitInfo::builder()
+    .command_buffers(&handles);
+
+    // Holding the queue's lock keeps vulkano from submitting to it at the same time
+    queue.with(|_guard| unsafe {
+        (device.fns().v1_0.queue_submit)(queue.handle(), 1, &*submit_info, fence.handle())
+        .result()
+        .map_err(VulkanError::from)
+    })?;
+    fence.wait(None)?;
+
+    Ok(())
+}
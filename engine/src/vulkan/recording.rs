@@ -0,0 +1,282 @@
+use std::{fs, path::{Path, PathBuf}, sync::{mpsc::{self, SyncSender, TrySendError}, Arc}, thread::{self, JoinHandle}};
+
+use log::{info, warn};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage, CopyImageToBufferInfo, PrimaryAutoCommandBuffer},
+    format::Format,
+    image::{Image, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter}
+};
+
+use crate::error::EngineError;
+use super::{memory_stats::AllocationCategory, screenshot::save_png, vulkan::VulkanToolset};
+
+// Frames waiting for the writer thread before new ones are dropped, about 30 MB at 1080p
+pub const MAX_QUEUED_FRAMES : usize = 4;
+
+// Name of a recording's `index`th file, zero padded so the files sort in order
+pub fn frame_file_name(index : u32) -> String {
+    format!("frame_{index:06}.png")
+}
+
+// RGBA8 bytes of a recorded image, red and blue are swapped for BGRA surface formats
+pub fn to_rgba8(format : Format, mut bytes : Vec<u8>) -> Result<Vec<u8>, EngineError> {
+    match format {
+        Format::R8G8B8A8_UNORM | Format::R8G8B8A8_SRGB => (),
+        Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB => bytes.chunks_exact_mut(4).for_each(|texel| texel.swap(0, 2)),
+        _ => return Err(EngineError::UnsupportedFeature(format!("{format:?} frames can't be recorded"))),
+    }
+
+    Ok(bytes)
+}
+
+struct RecordedFrame {
+    index : u32,
+    width : u32,
+    height : u32,
+    format : Format,
+    bytes : Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RecordingSummary {
+    pub written : u32,
+    // Frames the writer couldn't keep up with
+    pub dropped : u32,
+}
+
+// Converts and writes numbered PNGs on a background thread. Frames are numbered in the order they were
+// accepted, so a recording has no gaps even when some were dropped
+pub struct FrameWriter {
+    dir : PathBuf,
+    sender : Option<SyncSender<RecordedFrame>>,
+    thread : Option<JoinHandle<Result<u32, EngineError>>>,
+    next_index : u32,
+    dropped : u32,
+}
+
+impl FrameWriter {
+    // Creates `dir` if needed, existing files of the same names are overwritten
+    pub fn new(dir : &Path, queue_len : usize) -> Result<FrameWriter, EngineError> {
+        fs::create_dir_all(dir)?;
+
+        let (sender, receiver) = mpsc::sync_channel::<RecordedFrame>(queue_len);
+        let thread_dir = dir.to_owned();
+        let thread = thread::Builder::new()
+        .name("frame writer".to_owned())
+        .spawn(move || {
+            let mut written = 0;
+            for frame in receiver {
+                let pixels = to_rgba8(frame.format, frame.bytes)?;
+                save_png(&pixels, frame.width, frame.height, &thread_dir.join(frame_file_name(frame.index)))?;
+                written += 1;
+            }
+
+            Ok(written)
+        })?;
+
+        Ok(FrameWriter {
+            dir : dir.to_owned(),
+            sender : Some(sender),
+            thread : Some(thread),
+            next_index : 0,
+            dropped : 0,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Never blocks. Returns false when the frame was dropped because the queue is full, or because
+    // the writer stopped on an error, which finish returns
+    pub fn submit(&mut self, width : u32, height : u32, format : Format, bytes : Vec<u8>) -> bool {
+        let Some(sender) = &self.sender else { return false };
+
+        let frame = RecordedFrame { index : self.next_index, width, height, format, bytes };
+        match sender.try_send(frame) {
+            Ok(()) => {
+                self.next_index += 1;
+                true
+            },
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                warn!("frame recording can't keep up, dropped a frame ({} so far)", self.dropped);
+                false
+            },
+            Err(TrySendError::Disconnected(_)) => {
+                self.dropped += 1;
+                false
+            },
+        }
+    }
+
+    // Waits until every accepted frame was written
+    pub fn finish(mut self) -> Result<RecordingSummary, EngineError> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<RecordingSummary, EngineError> {
+        // Closing the channel ends the thread's loop once the queue is empty
+        self.sender = None;
+        let written = match self.thread.take() {
+            Some(thread) => thread.join().expect("frame writer thread panicked")?,
+            None => 0,
+        };
+
+        Ok(RecordingSummary { written, dropped : self.dropped })
+    }
+}
+
+impl Drop for FrameWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.join() {
+            warn!("frame recording to {} failed: {e}", self.dir.display());
+        }
+    }
+}
+
+// Image of one frame copied into host memory, read once the frame's fence signaled
+struct PendingCapture {
+    buffer : Subbuffer<[u8]>,
+    extent : [u32; 2],
+    format : Format,
+}
+
+// Copies every nth frame into host memory and hands the pixels to a FrameWriter once the frame in flight
+// finished, so the render loop never waits on the disk. Each frame is recorded at its own size, a recording
+// follows the window across resizes
+pub struct FrameRecorder {
+    writer : Option<FrameWriter>,
+    every_nth_frame : u32,
+    frame_count : u64,
+    // No new captures, the writer is finished once the ones in flight were collected
+    stopping : bool,
+    pending : Vec<Option<PendingCapture>>,
+}
+
+impl FrameRecorder {
+    pub fn new(frames_in_flight : usize) -> FrameRecorder {
+        FrameRecorder {
+            writer : None,
+            every_nth_frame : 1,
+            frame_count : 0,
+            stopping : false,
+            pending : (0..frames_in_flight).map(|_| None).collect(),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.writer.is_some() && !self.stopping
+    }
+
+    // The first frame after start is recorded, then every `every_nth_frame`th
+    pub fn start(&mut self, dir : &Path, every_nth_frame : u32) -> Result<(), EngineError> {
+        if self.writer.is_some() {
+            return Err(EngineError::UnsupportedFeature("a recording is already running, stop it first".to_owned()));
+        }
+
+        self.writer = Some(FrameWriter::new(dir, MAX_QUEUED_FRAMES)?);
+        self.every_nth_frame = every_nth_frame.max(1);
+        self.frame_count = 0;
+        self.stopping = false;
+
+        Ok(())
+    }
+
+    // Frames already copied are still written, see collect
+    pub fn stop(&mut self) {
+        self.stopping = true;
+        self.finish_if_stopped();
+    }
+
+    // Records a copy of `image` to submit after the frame that draws it, with the same fence. None when this
+    // frame isn't recorded. The image needs TRANSFER_SRC usage
+    pub fn capture(&mut self, toolset : &VulkanToolset, allocator : &StandardCommandBufferAllocator, image : &Arc<Image>, slot : usize) -> Result<Option<Arc<PrimaryAutoCommandBuffer>>, EngineError> {
+        if !self.is_recording() {
+            return Ok(None);
+        }
+
+        let frame = self.frame_count;
+        self.frame_count += 1;
+        if frame % self.every_nth_frame as u64 != 0 {
+            return Ok(None);
+        }
+
+        if !image.usage().intersects(ImageUsage::TRANSFER_SRC) {
+            return Err(EngineError::UnsupportedFeature("the recorded image can't be copied from, it lacks TRANSFER_SRC usage".to_owned()));
+        }
+
+        let [width, height, _] = image.extent();
+        let len = width as u64 * height as u64 * image.format().block_size();
+        let buffer = Buffer::new_slice::<u8>(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            len,
+        )?;
+        toolset.memory_allocator.tracker.track_buffer(AllocationCategory::Staging, buffer.buffer());
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            allocator,
+            toolset.device_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        builder.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(image.clone(), buffer.clone()))?;
+
+        self.pending[slot] = Some(PendingCapture { buffer, extent : [width, height], format : image.format() });
+
+        Ok(Some(builder.build()?))
+    }
+
+    // Call once the slot's previous frame finished, such as after FrameSync::begin_frame
+    pub fn collect(&mut self, slot : usize) -> Result<(), EngineError> {
+        self.submit_pending(slot)?;
+        self.finish_if_stopped();
+
+        Ok(())
+    }
+
+    // Writes what is still in flight and ends the recording. Every frame submitted with a capture
+    // must have finished on the GPU
+    pub fn finish(&mut self) -> Result<Option<RecordingSummary>, EngineError> {
+        for slot in 0..self.pending.len() {
+            self.submit_pending(slot)?;
+        }
+
+        let Some(writer) = self.writer.take() else { return Ok(None) };
+        self.stopping = false;
+        let dir = writer.dir().to_owned();
+        let summary = writer.finish()?;
+        info!("recorded {} frames to {}, {} dropped", summary.written, dir.display(), summary.dropped);
+
+        Ok(Some(summary))
+    }
+
+    fn submit_pending(&mut self, slot : usize) -> Result<(), EngineError> {
+        let Some(capture) = self.pending[slot].take() else { return Ok(()) };
+
+        let bytes = capture.buffer.read()?.to_vec();
+        if let Some(writer) = self.writer.as_mut() {
+            writer.submit(capture.extent[0], capture.extent[1], capture.format, bytes);
+        }
+
+        Ok(())
+    }
+
+    fn finish_if_stopped(&mut self) {
+        if self.stopping && self.pending.iter().all(Option::is_none) {
+            if let Err(e) = self.finish() {
+                warn!("failed to finish frame recording: {e}");
+            }
+        }
+    }
+}
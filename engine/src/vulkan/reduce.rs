@@ -0,0 +1,253 @@
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{Pipeline, PipelineBindPoint},
+    sync::{self, GpuFuture},
+    DeviceSize
+};
+
+use crate::error::EngineError;
+use super::{memory_stats::AllocationCategory, staging::read_back_range, vulkan::{ComputeShader, VulkanToolset}};
+
+mod reduce_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) readonly buffer Input {
+                uint values[];
+            } src;
+
+            layout(set = 0, binding = 1) writeonly buffer Partials {
+                uint values[];
+            } dst;
+
+            // Elements are 32 bit words, reinterpreted as floats when is_float is set
+            layout(push_constant) uniform Params {
+                uint count;
+                uint op;
+                uint is_float;
+            } params;
+
+            const uint SUM = 0;
+            const uint MIN = 1;
+
+            shared uint partial[gl_WorkGroupSize.x];
+
+            uint identity() {
+                // Zero bits are 0.0 as well
+                if (params.op == SUM) {
+                    return 0u;
+                }
+                // Positive and negative infinity
+                if (params.is_float != 0) {
+                    return params.op == MIN ? 0x7F800000u : 0xFF800000u;
+                }
+                return params.op == MIN ? 0xFFFFFFFFu : 0u;
+            }
+
+            uint combine(uint a, uint b) {
+                if (params.is_float != 0) {
+                    float x = uintBitsToFloat(a);
+                    float y = uintBitsToFloat(b);
+                    return floatBitsToUint(params.op == SUM ? x + y : (params.op == MIN ? min(x, y) : max(x, y)));
+                }
+                return params.op == SUM ? a + b : (params.op == MIN ? min(a, b) : max(a, b));
+            }
+
+            void main() {
+                uint local = gl_LocalInvocationID.x;
+                uint size = gl_WorkGroupSize.x;
+
+                // Two elements per invocation, striding over the input when it needs more workgroups than
+                // a dispatch can have. The tail past count folds in the identity
+                uint value = identity();
+                for (uint i = gl_WorkGroupID.x * size * 2 + local; i < params.count; i += size * 2 * gl_NumWorkGroups.x) {
+                    value = combine(value, src.values[i]);
+                    if (i + size < params.count) {
+                        value = combine(value, src.values[i + size]);
+                    }
+                }
+
+                partial[local] = value;
+                barrier();
+
+                for (uint active = size / 2; active > 0; active /= 2) {
+                    if (local < active) {
+                        partial[local] = combine(partial[local], partial[local + active]);
+                    }
+                    barrier();
+                }
+
+                if (local == 0) {
+                    dst.values[gl_WorkGroupID.x] = partial[0];
+                }
+            }
+        ",
+    }
+}
+
+// Must stay a power of two for the shared memory tree
+const LOCAL_SIZE : [u32; 3] = [256, 1, 1];
+const ELEMENTS_PER_GROUP : u32 = LOCAL_SIZE[0] * 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReduceOp {
+    // Wraps around for u32
+    Sum,
+    Min,
+    Max,
+}
+
+// Element types the reduction shader understands, both are handled as 32 bit words
+pub trait ReduceElement : BufferContents + Copy {
+    const IS_FLOAT : bool;
+
+    fn from_bits(bits : u32) -> Self;
+}
+
+impl ReduceElement for f32 {
+    const IS_FLOAT : bool = true;
+
+    fn from_bits(bits : u32) -> Self {
+        f32::from_bits(bits)
+    }
+}
+
+impl ReduceElement for u32 {
+    const IS_FLOAT : bool = false;
+
+    fn from_bits(bits : u32) -> Self {
+        bits
+    }
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct ReduceParams {
+    count : u32,
+    op : u32,
+    is_float : u32,
+}
+
+// Workgroups of every pass over `len` elements, each pass writes one partial result per workgroup
+// and the last one a single value. Passes are capped at `max_groups` workgroups, see the shader's stride
+pub fn reduce_pass_sizes(len : u32, max_groups : u32) -> Vec<u32> {
+    let mut passes = Vec::new();
+    let mut count = len.max(1);
+
+    loop {
+        let groups = count.div_ceil(ELEMENTS_PER_GROUP).clamp(1, max_groups.max(1));
+        passes.push(groups);
+
+        if groups == 1 {
+            return passes;
+        }
+        count = groups;
+    }
+}
+
+// Reduces buffers to a single value with a shared memory tree per workgroup, repeated over the
+// partial results until one is left. Float sums are added in a different order than a CPU loop would
+pub struct GpuReducer {
+    shader : ComputeShader,
+    descriptor_set_allocator : StandardDescriptorSetAllocator,
+}
+
+impl GpuReducer {
+    pub fn new(toolset : &VulkanToolset) -> GpuReducer {
+        let device = &toolset.logical_device;
+        let module = reduce_cs::load(device.clone()).expect("failed to create shader module");
+
+        GpuReducer {
+            shader : ComputeShader::new(&module, LOCAL_SIZE, device.clone()),
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+        }
+    }
+
+    // `buffer` needs STORAGE_BUFFER usage. Blocks until the result is read back
+    pub fn reduce<T : ReduceElement>(&self, toolset : &VulkanToolset, buffer : &Subbuffer<[T]>, op : ReduceOp) -> Result<T, EngineError> {
+        if buffer.len() == 0 {
+            return Err(EngineError::EmptyBuffer);
+        }
+        let len = u32::try_from(buffer.len())
+        .map_err(|_| EngineError::UnsupportedFeature(format!("reducing {} elements, at most {} are supported", buffer.len(), u32::MAX)))?;
+
+        let max_groups = toolset.limits.max_compute_work_group_count[0];
+        let passes = reduce_pass_sizes(len, max_groups);
+
+        // Partial results ping-pong between two buffers, the first pass writes the most of them
+        let scratch = [
+            self.create_scratch(toolset, passes[0])?,
+            self.create_scratch(toolset, passes.get(1).copied().unwrap_or(1))?,
+        ];
+
+        let queue = &toolset.device_queue;
+        let pipeline = &self.shader.pipeline;
+        let layout = pipeline.layout();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &toolset.memory_allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        builder.bind_pipeline_compute(pipeline.clone())?;
+
+        let mut src = buffer.clone().reinterpret::<[u32]>();
+        let mut count = len;
+        for (pass, &groups) in passes.iter().enumerate() {
+            let dst = scratch[pass % 2].clone();
+            let descriptor_set = PersistentDescriptorSet::new(
+                &self.descriptor_set_allocator,
+                layout.set_layouts()[0].clone(),
+                [
+                    WriteDescriptorSet::buffer(0, src),
+                    WriteDescriptorSet::buffer(1, dst.clone()),
+                ],
+                [],
+            )?;
+
+            builder.bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?
+            .push_constants(layout.clone(), 0, ReduceParams { count, op : op as u32, is_float : T::IS_FLOAT as u32 })?
+            .dispatch([groups, 1, 1])?;
+
+            src = dst;
+            count = groups;
+        }
+
+        sync::now(queue.device().clone())
+        .then_execute(queue.clone(), builder.build()?)?
+        .then_signal_fence_and_flush()?
+        .wait(None)?;
+
+        Ok(T::from_bits(read_back_range(toolset, &src, 0, 1)?[0]))
+    }
+
+    fn create_scratch(&self, toolset : &VulkanToolset, len : u32) -> Result<Subbuffer<[u32]>, EngineError> {
+        let buffer = Buffer::new_slice::<u32>(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+            len as DeviceSize,
+        )?;
+        toolset.memory_allocator.tracker.track_buffer(AllocationCategory::Storage, buffer.buffer());
+
+        Ok(buffer)
+    }
+}
+
+// One-off reduction, keep a GpuReducer around when reducing repeatedly
+pub fn gpu_reduce<T : ReduceElement>(toolset : &VulkanToolset, buffer : &Subbuffer<[T]>, op : ReduceOp) -> Result<T, EngineError> {
+    GpuReducer::new(toolset).reduce(toolset, buffer, op)
+}
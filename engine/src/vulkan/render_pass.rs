@@ -0,0 +1,254 @@
+use std::{collections::HashMap, fmt::{Display, Formatter, Result as FmtResult}, sync::Arc};
+
+use vulkano::{
+    device::Device,
+    format::Format,
+    image::{view::ImageView, Image, ImageAspects, ImageLayout, SampleCount},
+    render_pass::{AttachmentDescription, AttachmentReference, AttachmentStoreOp, Framebuffer, FramebufferCreateInfo, RenderPass, RenderPassCreateInfo, SubpassDependency, SubpassDescription},
+    sync::{AccessFlags, DependencyFlags, PipelineStages},
+    VulkanObject
+};
+
+use crate::error::EngineError;
+use super::vulkan_window::AttachmentConfig;
+
+// Attachment indices used by one subpass, in the order the attachments were added
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SubpassConfig {
+    pub color : Vec<usize>,
+    pub depth : Option<usize>,
+    // Depth is only tested, so the same attachment can also be read as an input attachment
+    pub depth_read_only : bool,
+    // Read in the fragment shader through subpassInput, written by an earlier subpass
+    pub input : Vec<usize>,
+    // Single sample targets the color attachments are resolved into, empty or one per color attachment
+    pub resolve : Vec<usize>,
+}
+
+// Declares attachments and subpasses, then builds a render pass for them.
+// Depth-only passes simply leave the color attachments empty
+#[derive(Clone, Debug, Default)]
+pub struct RenderPassBuilder {
+    attachments : Vec<AttachmentConfig>,
+    subpasses : Vec<SubpassConfig>,
+    // Replace the conservative defaults when given
+    dependencies : Vec<SubpassDependency>,
+}
+
+impl RenderPassBuilder {
+    pub fn new() -> RenderPassBuilder {
+        RenderPassBuilder::default()
+    }
+
+    pub fn attachment(mut self, attachment : AttachmentConfig) -> RenderPassBuilder {
+        self.attachments.push(attachment);
+        self
+    }
+
+    pub fn subpass(mut self, color : &[usize], depth : Option<usize>) -> RenderPassBuilder {
+        self.subpasses.push(SubpassConfig {
+            color : color.to_vec(),
+            depth,
+            depth_read_only : false,
+            input : Vec::new(),
+            resolve : Vec::new(),
+        });
+        self
+    }
+
+    pub fn subpass_config(mut self, subpass : SubpassConfig) -> RenderPassBuilder {
+        self.subpasses.push(subpass);
+        self
+    }
+
+    // Without any, each subpass waits for every graphics stage of the previous one
+    pub fn dependency(mut self, dependency : SubpassDependency) -> RenderPassBuilder {
+        self.dependencies.push(dependency);
+        self
+    }
+
+    pub fn build(&self, device : &Arc<Device>) -> Result<Arc<RenderPass>, EngineError> {
+        assert!(!self.subpasses.is_empty(), "a render pass needs at least one subpass");
+
+        for (i, attachment) in self.attachments.iter().enumerate() {
+            assert!(
+                attachment.store_op != AttachmentStoreOp::DontCare || attachment.can_discard(),
+                "attachment {i} ({:?}) discards its contents but is neither transient nor depth-only",
+                attachment.format,
+            );
+        }
+
+        let attachments = self.attachments.iter()
+        .map(|attachment| AttachmentDescription {
+            format: attachment.format,
+            samples: SampleCount::try_from(attachment.samples).expect("unsupported attachment sample count"),
+            load_op: attachment.load_op,
+            store_op: attachment.store_op,
+            initial_layout: attachment.initial_layout,
+            final_layout: attachment.final_layout,
+            ..Default::default()
+        }).collect();
+
+        let reference = |attachment : usize, layout : ImageLayout| AttachmentReference {
+            attachment: attachment as u32,
+            layout,
+            ..Default::default()
+        };
+
+        // Depth read in a shader stays in a depth layout, so it can be tested in the same subpass
+        let input_layout = |attachment : usize| match self.attachments[attachment].format.aspects().intersects(ImageAspects::DEPTH | ImageAspects::STENCIL) {
+            true => ImageLayout::DepthStencilReadOnlyOptimal,
+            false => ImageLayout::ShaderReadOnlyOptimal,
+        };
+
+        let subpasses = self.subpasses.iter()
+        .map(|subpass| SubpassDescription {
+            color_attachments: subpass.color.iter()
+                .map(|&i| Some(reference(i, ImageLayout::ColorAttachmentOptimal)))
+                .collect(),
+            color_resolve_attachments: subpass.resolve.iter()
+                .map(|&i| Some(reference(i, ImageLayout::ColorAttachmentOptimal)))
+                .collect(),
+            depth_stencil_attachment: subpass.depth.map(|i| reference(i, match subpass.depth_read_only {
+                true => ImageLayout::DepthStencilReadOnlyOptimal,
+                false => ImageLayout::DepthStencilAttachmentOptimal,
+            })),
+            input_attachments: subpass.input.iter()
+                .map(|&i| Some(reference(i, input_layout(i))))
+                .collect(),
+            // Attachments a subpass doesn't touch keep their contents for the later ones
+            preserve_attachments: (0..self.attachments.len())
+                .filter(|i| !subpass.color.contains(i) && subpass.depth != Some(*i) && !subpass.input.contains(i) && !subpass.resolve.contains(i))
+                .map(|i| i as u32)
+                .collect(),
+            ..Default::default()
+        }).collect::<Vec<_>>();
+
+        // Conservative, like the ordered_passes_renderpass macro: each subpass waits for the previous one
+        let dependencies = match self.dependencies.is_empty() {
+            false => self.dependencies.clone(),
+            true => (1..subpasses.len() as u32)
+                .map(|dst| SubpassDependency {
+                    src_subpass: Some(dst - 1),
+                    dst_subpass: Some(dst),
+                    src_stages: PipelineStages::ALL_GRAPHICS,
+                    dst_stages: PipelineStages::ALL_GRAPHICS,
+                    src_access: AccessFlags::MEMORY_READ | AccessFlags::MEMORY_WRITE,
+                    dst_access: AccessFlags::MEMORY_READ | AccessFlags::MEMORY_WRITE,
+                    dependency_flags: DependencyFlags::BY_REGION,
+                    ..Default::default()
+                })
+                .collect(),
+        };
+
+        Ok(RenderPass::new(
+            device.clone(),
+            RenderPassCreateInfo {
+                attachments,
+                subpasses,
+                dependencies,
+                ..Default::default()
+            },
+        )?)
+    }
+}
+
+// How a pipeline's render pass differs from the one a framebuffer was created for, see
+// VulkanToolset::validate_pipeline_framebuffer_compatibility
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderPassMismatch {
+    // The pipeline was created for dynamic rendering and can't be used in a render pass
+    NoRenderPass,
+    AttachmentCount { pipeline : usize, framebuffer : usize },
+    Format { attachment : u32, pipeline : Format, framebuffer : Format },
+    Samples { attachment : u32, pipeline : SampleCount, framebuffer : SampleCount },
+}
+
+impl Display for RenderPassMismatch {
+    fn fmt(&self, f : &mut Formatter<'_>) -> FmtResult {
+        match self {
+            RenderPassMismatch::NoRenderPass => write!(f, "the pipeline was created for dynamic rendering"),
+            RenderPassMismatch::AttachmentCount { pipeline, framebuffer } => write!(f, "the pipeline's render pass has {pipeline} attachments, the framebuffer's has {framebuffer}"),
+            RenderPassMismatch::Format { attachment, pipeline, framebuffer } => write!(f, "attachment {attachment} is {pipeline:?} in the pipeline's render pass and {framebuffer:?} in the framebuffer's"),
+            RenderPassMismatch::Samples { attachment, pipeline, framebuffer } => write!(f, "attachment {attachment} has {} samples in the pipeline's render pass and {} in the framebuffer's", *pipeline as u32, *framebuffer as u32),
+        }
+    }
+}
+
+// Attachments of two render passes that a pipeline and a framebuffer could be used together with.
+// The first difference is reported, load and store ops and layouts don't affect compatibility
+pub fn compare_attachments(pipeline : &[AttachmentDescription], framebuffer : &[AttachmentDescription]) -> Result<(), RenderPassMismatch> {
+    if pipeline.len() != framebuffer.len() {
+        return Err(RenderPassMismatch::AttachmentCount { pipeline : pipeline.len(), framebuffer : framebuffer.len() });
+    }
+
+    for (attachment, (ours, theirs)) in (0..).zip(pipeline.iter().zip(framebuffer)) {
+        if ours.format != theirs.format {
+            return Err(RenderPassMismatch::Format { attachment, pipeline : ours.format, framebuffer : theirs.format });
+        }
+        if ours.samples != theirs.samples {
+            return Err(RenderPassMismatch::Samples { attachment, pipeline : ours.samples, framebuffer : theirs.samples });
+        }
+    }
+
+    Ok(())
+}
+
+// Views are given in attachment order, the framebuffer takes its extent from them
+pub fn create_framebuffer(render_pass : &Arc<RenderPass>, attachments : Vec<Arc<ImageView>>) -> Result<Arc<Framebuffer>, EngineError> {
+    Ok(Framebuffer::new(
+        render_pass.clone(),
+        FramebufferCreateInfo {
+            attachments,
+            ..Default::default()
+        },
+    )?)
+}
+
+type ImageHandle = <Image as VulkanObject>::Handle;
+type RenderPassHandle = <RenderPass as VulkanObject>::Handle;
+
+// Framebuffers of one swapchain generation, keyed by render pass and image set.
+// Asking for a different image set means the swapchain was recreated, so older entries are dropped
+#[derive(Default)]
+pub struct FramebufferCache {
+    images : Vec<ImageHandle>,
+    framebuffers : HashMap<RenderPassHandle, Vec<Arc<Framebuffer>>>,
+}
+
+impl FramebufferCache {
+    pub fn new() -> FramebufferCache {
+        FramebufferCache::default()
+    }
+
+    // `create` runs once per image, only when nothing is cached for these images and render pass
+    pub fn get_or_create<F>(&mut self, render_pass : &Arc<RenderPass>, images : &[Arc<Image>], create : F) -> Vec<Arc<Framebuffer>>
+    where
+        F : FnMut(&Arc<Image>) -> Arc<Framebuffer>,
+    {
+        let handles = images.iter().map(|image| image.handle()).collect::<Vec<_>>();
+        if handles != self.images {
+            self.invalidate();
+            self.images = handles;
+        }
+
+        self.framebuffers
+        .entry(render_pass.handle())
+        .or_insert_with(|| images.iter().map(create).collect())
+        .clone()
+    }
+
+    pub fn invalidate(&mut self) {
+        self.images.clear();
+        self.framebuffers.clear();
+    }
+
+    // Number of render passes with cached framebuffers
+    pub fn len(&self) -> usize {
+        self.framebuffers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.framebuffers.is_empty()
+    }
+}
@@ -0,0 +1,123 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+
+use vulkano::{
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage, SampleCount},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator}
+};
+
+use crate::error::EngineError;
+
+// Images are only ever reused for the exact same description
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderTargetKey {
+    pub format : Format,
+    pub extent : [u32; 2],
+    pub usage : ImageUsage,
+    pub samples : SampleCount,
+}
+
+type Buckets = HashMap<RenderTargetKey, Vec<Arc<ImageView>>>;
+
+// Intermediate images handed out per frame and recycled instead of created again. Idle images
+// stay allocated until trimmed, call trim after a resize so the old extents are freed
+pub struct RenderTargetPool {
+    memory_allocator : Arc<StandardMemoryAllocator>,
+    idle : Arc<Mutex<Buckets>>,
+}
+
+impl RenderTargetPool {
+    pub fn new(memory_allocator : Arc<StandardMemoryAllocator>) -> RenderTargetPool {
+        RenderTargetPool {
+            memory_allocator,
+            idle : Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    // An idle image of this description, or a new one when all of them are in use.
+    // Contents are undefined, a reused image still holds whatever was last written to it
+    pub fn acquire(&self, format : Format, extent : [u32; 2], usage : ImageUsage, sample_count : SampleCount) -> Result<PooledRenderTarget, EngineError> {
+        let key = RenderTargetKey {
+            format,
+            extent,
+            usage,
+            samples : sample_count,
+        };
+
+        let reused = self.idle.lock().unwrap()
+        .get_mut(&key)
+        .and_then(Vec::pop);
+
+        let view = match reused {
+            Some(view) => view,
+            None => self.allocate(key)?,
+        };
+
+        Ok(PooledRenderTarget {
+            view : Some(view),
+            key,
+            idle : self.idle.clone(),
+        })
+    }
+
+    // Number of images waiting to be acquired again
+    pub fn idle_count(&self) -> usize {
+        self.idle.lock().unwrap().values().map(Vec::len).sum()
+    }
+
+    // Frees every idle image, targets still held are returned to the pool as usual
+    pub fn trim(&self) {
+        self.idle.lock().unwrap().clear();
+    }
+
+    fn allocate(&self, key : RenderTargetKey) -> Result<Arc<ImageView>, EngineError> {
+        let image = Image::new(
+            self.memory_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: key.format,
+                extent: [key.extent[0], key.extent[1], 1],
+                usage: key.usage,
+                samples: key.samples,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        )?;
+
+        Ok(ImageView::new_default(image)?)
+    }
+}
+
+// Goes back to its pool when dropped. Command buffers keep their own reference to the image,
+// so dropping right after recording is fine, vulkano orders later uses of it with barriers
+pub struct PooledRenderTarget {
+    // Only None while being dropped
+    view : Option<Arc<ImageView>>,
+    key : RenderTargetKey,
+    idle : Arc<Mutex<Buckets>>,
+}
+
+impl PooledRenderTarget {
+    pub fn view(&self) -> &Arc<ImageView> {
+        self.view.as_ref().unwrap()
+    }
+
+    pub fn image(&self) -> &Arc<Image> {
+        self.view().image()
+    }
+
+    pub fn key(&self) -> RenderTargetKey {
+        self.key
+    }
+}
+
+impl Drop for PooledRenderTarget {
+    fn drop(&mut self) {
+        if let Some(view) = self.view.take() {
+            self.idle.lock().unwrap().entry(self.key).or_default().push(view);
+        }
+    }
+}
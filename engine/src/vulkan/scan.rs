@@ -0,0 +1,284 @@
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{Pipeline, PipelineBindPoint},
+    sync::{self, GpuFuture},
+    DeviceSize
+};
+
+use crate::error::EngineError;
+use super::{memory_stats::AllocationCategory, vulkan::{ComputeShader, VulkanToolset}};
+
+mod scan_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) readonly buffer Input {
+                uint values[];
+            } src;
+
+            layout(set = 0, binding = 1) writeonly buffer Output {
+                uint values[];
+            } dst;
+
+            layout(set = 0, binding = 2) writeonly buffer BlockSums {
+                uint values[];
+            } sums;
+
+            layout(push_constant) uniform Params {
+                uint count;
+            } params;
+
+            shared uint temp[gl_WorkGroupSize.x * 2];
+
+            // Blelloch scan of one block of two elements per invocation, elements past count scan as zero
+            void main() {
+                uint size = gl_WorkGroupSize.x;
+                uint local = gl_LocalInvocationID.x;
+                uint block = gl_WorkGroupID.y * gl_NumWorkGroups.x + gl_WorkGroupID.x;
+                uint base = block * size * 2;
+
+                // Leftover workgroups of a 2D dispatch, the whole group leaves together
+                if (base >= params.count) {
+                    return;
+                }
+
+                uint a = base + local;
+                uint b = a + size;
+                temp[local] = a < params.count ? src.values[a] : 0;
+                temp[local + size] = b < params.count ? src.values[b] : 0;
+
+                // Up-sweep builds partial sums in place, the last element ends up with the block total
+                uint offset = 1;
+                for (uint d = size; d > 0; d >>= 1) {
+                    barrier();
+                    if (local < d) {
+                        temp[offset * (2 * local + 2) - 1] += temp[offset * (2 * local + 1) - 1];
+                    }
+                    offset <<= 1;
+                }
+
+                if (local == 0) {
+                    sums.values[block] = temp[size * 2 - 1];
+                    temp[size * 2 - 1] = 0;
+                }
+
+                // Down-sweep turns them into the exclusive scan
+                for (uint d = 1; d < size * 2; d <<= 1) {
+                    offset >>= 1;
+                    barrier();
+                    if (local < d) {
+                        uint left = offset * (2 * local + 1) - 1;
+                        uint right = offset * (2 * local + 2) - 1;
+                        uint value = temp[left];
+                        temp[left] = temp[right];
+                        temp[right] += value;
+                    }
+                }
+                barrier();
+
+                if (a < params.count) {
+                    dst.values[a] = temp[local];
+                }
+                if (b < params.count) {
+                    dst.values[b] = temp[local + size];
+                }
+            }
+        ",
+    }
+}
+
+mod add_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) buffer Data {
+                uint values[];
+            } data;
+
+            layout(set = 0, binding = 1) readonly buffer BlockOffsets {
+                uint values[];
+            } offsets;
+
+            layout(push_constant) uniform Params {
+                uint count;
+            } params;
+
+            // Adds the scanned total of all previous blocks to every element of a block
+            void main() {
+                uint size = gl_WorkGroupSize.x;
+                uint block = gl_WorkGroupID.y * gl_NumWorkGroups.x + gl_WorkGroupID.x;
+                uint base = block * size * 2;
+                if (base >= params.count) {
+                    return;
+                }
+
+                uint offset = offsets.values[block];
+                for (uint i = base + gl_LocalInvocationID.x; i < min(base + size * 2, params.count); i += size) {
+                    data.values[i] += offset;
+                }
+            }
+        ",
+    }
+}
+
+// Must stay a power of two for the shared memory tree
+const LOCAL_SIZE : [u32; 3] = [256, 1, 1];
+const ELEMENTS_PER_BLOCK : u32 = LOCAL_SIZE[0] * 2;
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct ScanParams {
+    count : u32,
+}
+
+// Element counts of every level of the scan. Each level scans its blocks and hands their totals to
+// the next one, which has one element per block, until a level fits a single block
+pub fn scan_levels(len : u32) -> Vec<u32> {
+    let mut levels = vec![len.max(1)];
+
+    while let Some(&count) = levels.last().filter(|&&count| count > ELEMENTS_PER_BLOCK) {
+        levels.push(count.div_ceil(ELEMENTS_PER_BLOCK));
+    }
+
+    levels
+}
+
+// Workgroups for `blocks` blocks, wrapped into rows once a row hits the dispatch limit
+pub fn block_dispatch(blocks : u32, max_groups : u32) -> [u32; 3] {
+    let row = blocks.clamp(1, max_groups.max(1));
+
+    [row, blocks.div_ceil(row).max(1), 1]
+}
+
+// Exclusive prefix sum: element i of the result is the sum of elements 0..i of the input, wrapping
+// around on overflow. Work-efficient Blelloch scan per block, the block totals are scanned the same
+// way and added back. Vulkano inserts the barriers between the passes
+pub struct GpuScanner {
+    scan : ComputeShader,
+    add : ComputeShader,
+    descriptor_set_allocator : StandardDescriptorSetAllocator,
+}
+
+impl GpuScanner {
+    pub fn new(toolset : &VulkanToolset) -> GpuScanner {
+        let device = &toolset.logical_device;
+        let scan = scan_cs::load(device.clone()).expect("failed to create shader module");
+        let add = add_cs::load(device.clone()).expect("failed to create shader module");
+
+        GpuScanner {
+            scan : ComputeShader::new(&scan, LOCAL_SIZE, device.clone()),
+            add : ComputeShader::new(&add, LOCAL_SIZE, device.clone()),
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+        }
+    }
+
+    // `buffer` needs STORAGE_BUFFER usage. The result is a new device local buffer of the same length,
+    // with STORAGE_BUFFER and TRANSFER_SRC usage. Blocks until it is written
+    pub fn prefix_sum(&self, toolset : &VulkanToolset, buffer : &Subbuffer<[u32]>) -> Result<Subbuffer<[u32]>, EngineError> {
+        if buffer.len() == 0 {
+            return Err(EngineError::EmptyBuffer);
+        }
+        let len = u32::try_from(buffer.len())
+        .map_err(|_| EngineError::UnsupportedFeature(format!("scanning {} elements, at most {} are supported", buffer.len(), u32::MAX)))?;
+
+        let levels = scan_levels(len);
+        let outputs = levels.iter()
+        .map(|&count| Self::create_buffer(toolset, count))
+        .collect::<Result<Vec<_>, _>>()?;
+        // Block totals of every level, the input of the next one. The last level is a single block
+        let sums = levels[1..].iter()
+        .chain([&1])
+        .map(|&count| Self::create_buffer(toolset, count))
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let queue = &toolset.device_queue;
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &toolset.memory_allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        let max_groups = toolset.limits.max_compute_work_group_count[0];
+        for (level, &count) in levels.iter().enumerate() {
+            let input = match level {
+                0 => buffer.clone(),
+                _ => sums[level - 1].clone(),
+            };
+            let writes = [
+                WriteDescriptorSet::buffer(0, input),
+                WriteDescriptorSet::buffer(1, outputs[level].clone()),
+                WriteDescriptorSet::buffer(2, sums[level].clone()),
+            ];
+            self.record_pass(&mut builder, &self.scan, writes, count, max_groups)?;
+        }
+
+        // Back down, each level adds the scanned totals of the level above to its blocks
+        for level in (0..levels.len() - 1).rev() {
+            let writes = [
+                WriteDescriptorSet::buffer(0, outputs[level].clone()),
+                WriteDescriptorSet::buffer(1, outputs[level + 1].clone()),
+            ];
+            self.record_pass(&mut builder, &self.add, writes, levels[level], max_groups)?;
+        }
+
+        sync::now(queue.device().clone())
+        .then_execute(queue.clone(), builder.build()?)?
+        .then_signal_fence_and_flush()?
+        .wait(None)?;
+
+        Ok(outputs[0].clone())
+    }
+
+    fn record_pass<const N : usize>(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, shader : &ComputeShader, writes : [WriteDescriptorSet; N], count : u32, max_groups : u32) -> Result<(), EngineError> {
+        let pipeline = &shader.pipeline;
+        let layout = pipeline.layout();
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.set_layouts()[0].clone(),
+            writes,
+            [],
+        )?;
+
+        builder.bind_pipeline_compute(pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?
+        .push_constants(layout.clone(), 0, ScanParams { count })?
+        .dispatch(block_dispatch(count.div_ceil(ELEMENTS_PER_BLOCK), max_groups))?;
+
+        Ok(())
+    }
+
+    fn create_buffer(toolset : &VulkanToolset, len : u32) -> Result<Subbuffer<[u32]>, EngineError> {
+        let buffer = Buffer::new_slice::<u32>(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+            len as DeviceSize,
+        )?;
+        toolset.memory_allocator.tracker.track_buffer(AllocationCategory::Storage, buffer.buffer());
+
+        Ok(buffer)
+    }
+}
+
+// One-off scan, keep a GpuScanner around when scanning repeatedly
+pub fn gpu_prefix_sum(toolset : &VulkanToolset, buffer : &Subbuffer<[u32]>) -> Result<Subbuffer<[u32]>, EngineError> {
+    GpuScanner::new(toolset).prefix_sum(toolset, buffer)
+}
@@ -0,0 +1,39 @@
+use vulkano::pipeline::graphics::viewport::Scissor;
+
+// Where a pipeline gets its scissor rectangle from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScissorState {
+    // Everything the viewport covers is drawn
+    #[default]
+    FullFramebuffer,
+    // Baked into the pipeline and clamped to its viewport, recreate it after a resize like the viewport
+    Fixed(Scissor),
+    // Set per draw while recording, see Frame::draw_clipped
+    Dynamic,
+}
+
+// Part of `scissor` inside `bounds`, None when nothing is left to draw
+pub fn clip_scissor(scissor : Scissor, bounds : Scissor) -> Option<Scissor> {
+    let start = |axis : usize| scissor.offset[axis].max(bounds.offset[axis]);
+    let end = |axis : usize| {
+        let scissor_end = scissor.offset[axis] as u64 + scissor.extent[axis] as u64;
+        let bounds_end = bounds.offset[axis] as u64 + bounds.extent[axis] as u64;
+        scissor_end.min(bounds_end)
+    };
+
+    let offset = [start(0), start(1)];
+    let extent = [
+        end(0).saturating_sub(offset[0] as u64) as u32,
+        end(1).saturating_sub(offset[1] as u64) as u32,
+    ];
+
+    (extent[0] > 0 && extent[1] > 0).then_some(Scissor { offset, extent })
+}
+
+// The whole of a framebuffer or viewport of this size
+pub fn full_scissor(extent : [u32; 2]) -> Scissor {
+    Scissor {
+        offset : [0, 0],
+        extent,
+    }
+}
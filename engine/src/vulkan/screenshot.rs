@@ -0,0 +1,157 @@
+use std::{path::Path, sync::Arc};
+
+use image::{DynamicImage, ImageBuffer, ImageFormat, Rgba};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyImageToBufferInfo},
+    device::Queue,
+    format::Format,
+    image::Image,
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    sync::{self, GpuFuture}
+};
+
+use crate::error::EngineError;
+use super::{memory_stats::AllocationCategory, vulkan::VulkanToolset};
+
+impl VulkanToolset {
+    // Copies the first mip level of an image created with TRANSFER_SRC usage into CPU memory
+    pub fn readback_image(&self, image : &Arc<Image>, queue : &Arc<Queue>) -> Result<Vec<u8>, EngineError> {
+        let allocator = &self.memory_allocator;
+        let [width, height, depth] = image.extent();
+        let len = width as u64 * height as u64 * depth as u64 * image.array_layers() as u64 * image.format().block_size();
+
+        let staging = Buffer::new_slice::<u8>(
+            allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            len,
+        )?;
+        allocator.tracker.track_buffer(AllocationCategory::Staging, staging.buffer());
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        builder.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(image.clone(), staging.clone()))?;
+
+        let command_buffer = builder.build()?;
+
+        sync::now(self.logical_device.clone())
+        .then_execute(queue.clone(), command_buffer)?
+        .then_signal_fence_and_flush()?
+        .wait(None)?;
+
+        let pixels = staging.read()?.to_vec();
+
+        Ok(pixels)
+    }
+
+    // readback_image for 2D images, with what it takes to interpret or save the bytes
+    pub fn readback_image_data(&self, image : &Arc<Image>, queue : &Arc<Queue>) -> Result<ImageData, EngineError> {
+        let [width, height, _] = image.extent();
+
+        ImageData::new(width, height, image.format(), self.readback_image(image, queue)?)
+    }
+}
+
+// File formats ImageData::save encodes to. JPEG drops alpha, EXR keeps float data as is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveFormat {
+    Png,
+    Jpeg,
+    Exr,
+}
+
+impl SaveFormat {
+    fn image_format(self) -> ImageFormat {
+        match self {
+            SaveFormat::Png => ImageFormat::Png,
+            SaveFormat::Jpeg => ImageFormat::Jpeg,
+            SaveFormat::Exr => ImageFormat::OpenExr,
+        }
+    }
+}
+
+// Tightly packed rows of a 2D image in one of the formats compute shaders write for saving,
+// R8G8B8A8_UNORM for colors and R32G32B32A32_SFLOAT for HDR data
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageData {
+    pub width : u32,
+    pub height : u32,
+    pub format : Format,
+    pub bytes : Vec<u8>,
+}
+
+impl ImageData {
+    pub const FORMATS : [Format; 2] = [Format::R8G8B8A8_UNORM, Format::R32G32B32A32_SFLOAT];
+
+    pub fn new(width : u32, height : u32, format : Format, bytes : Vec<u8>) -> Result<ImageData, EngineError> {
+        if !Self::FORMATS.contains(&format) {
+            return Err(EngineError::UnsupportedFeature(format!("{format:?} images can't be saved, use one of {:?}", Self::FORMATS)));
+        }
+
+        let expected = width as u64 * height as u64 * format.block_size();
+        if bytes.len() as u64 != expected {
+            return Err(EngineError::ImageDataSize { expected, len : bytes.len() });
+        }
+
+        Ok(ImageData { width, height, format, bytes })
+    }
+
+    // Bytes of one texel, 4 for RGBA8 and 16 for RGBA32F
+    pub fn texel_size(&self) -> usize {
+        self.format.block_size() as usize
+    }
+
+    pub fn texel(&self, x : u32, y : u32) -> &[u8] {
+        let start = (y as usize * self.width as usize + x as usize) * self.texel_size();
+        &self.bytes[start..start + self.texel_size()]
+    }
+
+    pub fn to_dynamic_image(&self) -> DynamicImage {
+        let (width, height) = (self.width, self.height);
+
+        // The sizes were checked in new
+        match self.format {
+            Format::R32G32B32A32_SFLOAT => {
+                let texels = self.bytes.chunks_exact(4).map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap())).collect();
+                DynamicImage::ImageRgba32F(ImageBuffer::from_raw(width, height, texels).unwrap())
+            },
+            _ => DynamicImage::ImageRgba8(ImageBuffer::from_raw(width, height, self.bytes.clone()).unwrap()),
+        }
+    }
+
+    // Converts to what the file format stores, float data is clamped to 0..1 for PNG and JPEG
+    pub fn save(&self, path : &Path, format : SaveFormat) -> Result<(), EngineError> {
+        let image = self.to_dynamic_image();
+        let image = match format {
+            SaveFormat::Png => DynamicImage::ImageRgba8(image.to_rgba8()),
+            SaveFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()),
+            SaveFormat::Exr => DynamicImage::ImageRgba32F(image.to_rgba32f()),
+        };
+
+        image.save_with_format(path, format.image_format())?;
+
+        Ok(())
+    }
+}
+
+// Pixels are tightly packed RGBA8 rows, as returned by readback_image for RGBA8 images
+pub fn save_png(pixels : &[u8], width : u32, height : u32, path : &Path) -> Result<(), EngineError> {
+    let image = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, pixels)
+    .ok_or(EngineError::PixelCount { width, height, len : pixels.len() })?;
+
+    image.save(path)?;
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use std::{fmt::{Display, Formatter, Result as FmtResult}, sync::Arc};
+
+use vulkano::{
+    descriptor_set::layout::DescriptorType,
+    format::{Format, NumericType},
+    shader::{EntryPoint, ShaderInterfaceEntryType, ShaderModule, ShaderStage}
+};
+
+use crate::error::EngineError;
+use super::vulkan::find_entry_point;
+
+// What an entry point expects to have bound, copied out of vulkano's reflection into plain data
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ShaderInfo {
+    pub entry_point : String,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_name"))]
+    pub stage : ShaderStage,
+    // Sorted by set, then binding
+    pub descriptor_bindings : Vec<DescriptorBindingInfo>,
+    // Push constant bytes the entry point reads, if any
+    pub push_constants : Option<PushConstantInfo>,
+    // One per location, only vertex shaders have any
+    pub vertex_inputs : Vec<VertexInputInfo>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DescriptorBindingInfo {
+    pub set : u32,
+    pub binding : u32,
+    // Types a write may use, buffers also accept their dynamic variant
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_names"))]
+    pub descriptor_types : Vec<DescriptorType>,
+    // None for runtime sized arrays
+    pub array_size : Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PushConstantInfo {
+    pub offset : u32,
+    pub size : u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct VertexInputInfo {
+    pub location : u32,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_name"))]
+    pub format : Format,
+    pub name : Option<String>,
+}
+
+// vulkano's types can't be serialized, they are written as their names like in DeviceInfo
+#[cfg(feature = "serde")]
+fn serialize_name<T : std::fmt::Debug, S : serde::Serializer>(value : &T, serializer : S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("{value:?}"))
+}
+
+#[cfg(feature = "serde")]
+fn serialize_names<T : std::fmt::Debug, S : serde::Serializer>(values : &[T], serializer : S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(|value| format!("{value:?}")))
+}
+
+// Reflection of shader modules, for wiring descriptors by hand or logging what a shader expects
+pub struct ShaderInterface;
+
+impl ShaderInterface {
+    pub fn inspect(module : &Arc<ShaderModule>, entry : &str) -> Result<ShaderInfo, EngineError> {
+        Ok(Self::inspect_entry_point(&find_entry_point(module, entry)?))
+    }
+
+    pub fn inspect_entry_point(entry_point : &EntryPoint) -> ShaderInfo {
+        let info = entry_point.info();
+        let stage = ShaderStage::from(info.execution_model);
+
+        let mut descriptor_bindings = info.descriptor_binding_requirements.iter()
+        .map(|(&(set, binding), requirements)| DescriptorBindingInfo {
+            set,
+            binding,
+            descriptor_types : requirements.descriptor_types.clone(),
+            array_size : requirements.descriptor_count,
+        })
+        .collect::<Vec<_>>();
+        descriptor_bindings.sort_by_key(|binding| (binding.set, binding.binding));
+
+        let push_constants = info.push_constant_requirements.map(|range| PushConstantInfo {
+            offset : range.offset,
+            size : range.size,
+        });
+
+        // Arrays and matrices take one location per element
+        let mut vertex_inputs = Vec::new();
+        if stage == ShaderStage::Vertex {
+            for element in info.input_interface.elements() {
+                let Some(format) = interface_format(element.ty) else { continue };
+
+                vertex_inputs.extend((0..element.ty.num_elements).map(|index| VertexInputInfo {
+                    location : element.location + index,
+                    format,
+                    name : element.name.as_ref().map(|name| name.to_string()),
+                }));
+            }
+            vertex_inputs.sort_by_key(|input| input.location);
+        }
+
+        ShaderInfo {
+            entry_point : info.name.clone(),
+            stage,
+            descriptor_bindings,
+            push_constants,
+            vertex_inputs,
+        }
+    }
+}
+
+// Vertex buffer format that feeds an input of this type one to one
+fn interface_format(ty : ShaderInterfaceEntryType) -> Option<Format> {
+    use Format::*;
+
+    let formats = match (ty.base_type, ty.is_64bit) {
+        (NumericType::Float, false) => [R32_SFLOAT, R32G32_SFLOAT, R32G32B32_SFLOAT, R32G32B32A32_SFLOAT],
+        (NumericType::Int, false) => [R32_SINT, R32G32_SINT, R32G32B32_SINT, R32G32B32A32_SINT],
+        (NumericType::Uint, false) => [R32_UINT, R32G32_UINT, R32G32B32_UINT, R32G32B32A32_UINT],
+        (NumericType::Float, true) => [R64_SFLOAT, R64G64_SFLOAT, R64G64B64_SFLOAT, R64G64B64A64_SFLOAT],
+        (NumericType::Int, true) => [R64_SINT, R64G64_SINT, R64G64B64_SINT, R64G64B64A64_SINT],
+        (NumericType::Uint, true) => [R64_UINT, R64G64_UINT, R64G64B64_UINT, R64G64B64A64_UINT],
+    };
+
+    formats.get(ty.num_components.checked_sub(1)? as usize).copied()
+}
+
+impl DescriptorBindingInfo {
+    fn type_names(&self) -> String {
+        self.descriptor_types.iter()
+        .map(|ty| format!("{ty:?}"))
+        .collect::<Vec<_>>()
+        .join(" | ")
+    }
+
+    fn count(&self) -> String {
+        self.array_size.map_or("runtime".to_owned(), |size| size.to_string())
+    }
+}
+
+impl Display for DescriptorBindingInfo {
+    fn fmt(&self, f : &mut Formatter<'_>) -> FmtResult {
+        write!(f, "set {} binding {}: {} x{}", self.set, self.binding, self.type_names(), self.count())
+    }
+}
+
+// Readable table for logging, one row per binding and vertex input
+impl Display for ShaderInfo {
+    fn fmt(&self, f : &mut Formatter<'_>) -> FmtResult {
+        writeln!(f, "{} ({:?})", self.entry_point, self.stage)?;
+
+        if !self.descriptor_bindings.is_empty() {
+            writeln!(f, "  {:<4} {:<8} {:<8} type", "set", "binding", "count")?;
+        }
+        for binding in &self.descriptor_bindings {
+            writeln!(f, "  {:<4} {:<8} {:<8} {}", binding.set, binding.binding, binding.count(), binding.type_names())?;
+        }
+
+        if let Some(PushConstantInfo { offset, size }) = self.push_constants {
+            writeln!(f, "  push constants: {size} bytes at offset {offset}")?;
+        }
+
+        if !self.vertex_inputs.is_empty() {
+            writeln!(f, "  {:<8} {:<20} name", "location", "format")?;
+        }
+        for input in &self.vertex_inputs {
+            writeln!(f, "  {:<8} {:<20} {}", input.location, format!("{:?}", input.format), input.name.as_deref().unwrap_or("-"))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use super::vulkan::VulkanToolset;
+
+// Size of the pixel block a single fragment shader invocation covers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FragmentShadingRate {
+    Rate1x1,
+    Rate2x2,
+    Rate4x4,
+}
+
+impl FragmentShadingRate {
+    pub fn size(self) -> [u32; 2] {
+        match self {
+            FragmentShadingRate::Rate1x1 => [1, 1],
+            FragmentShadingRate::Rate2x2 => [2, 2],
+            FragmentShadingRate::Rate4x4 => [4, 4],
+        }
+    }
+
+    // Texel value in a shading rate image: log2(width) << 2 | log2(height)
+    pub fn encoded(self) -> u8 {
+        let [width, height] = self.size();
+        (width.trailing_zeros() << 2 | height.trailing_zeros()) as u8
+    }
+
+    // Invocations needed to shade an extent, partially covered blocks count fully
+    pub fn invocations(self, extent : [u32; 2]) -> u64 {
+        let [width, height] = self.size();
+        extent[0].div_ceil(width) as u64 * extent[1].div_ceil(height) as u64
+    }
+}
+
+pub struct VariableRateShading;
+
+impl VariableRateShading {
+    pub fn is_supported(toolset : &VulkanToolset) -> bool {
+        toolset.shading_rate.is_present()
+    }
+
+    // Pixels covered by one texel of a shading rate image, None without attachment shading rates
+    pub fn attachment_texel_size(toolset : &VulkanToolset) -> Option<[u32; 2]> {
+        toolset.shading_rate.if_present(|ext| ext.attachment_texel_size).flatten()
+    }
+}
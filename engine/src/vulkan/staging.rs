@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, BufferImageCopy, CommandBufferExecFuture, CommandBufferUsage, CopyBufferToImageInfo, PrimaryAutoCommandBuffer},
+    device::{DeviceOwned, Queue},
+    image::{Image, ImageSubresourceLayers},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    sync::{self, future::{FenceSignalFuture, NowFuture}, GpuFuture},
+    DeviceSize
+};
+
+use crate::error::EngineError;
+use super::{memory_stats::AllocationCategory, texture::ImageRegion, vulkan::{VulkanAllocation, VulkanToolset}};
+
+// Copies data into a device local buffer through a host visible staging buffer, blocking until done.
+// The buffer keeps TRANSFER_SRC usage so it can be inspected with read_back_buffer
+pub fn upload_buffer<T, I>(allocator : &VulkanAllocation, queue : &Arc<Queue>, usage : BufferUsage, data : I) -> Result<Subbuffer<[T]>, EngineError>
+where
+    T : BufferContents,
+    I : IntoIterator<Item = T>,
+    I::IntoIter : ExactSizeIterator,
+{
+    let data = data.into_iter();
+    if data.len() == 0 {
+        return Err(EngineError::EmptyBuffer);
+    }
+
+    let staging = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        data,
+    )?;
+    allocator.tracker.track_buffer(AllocationCategory::Staging, staging.buffer());
+
+    let buffer = Buffer::new_slice::<T>(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: usage | BufferUsage::TRANSFER_DST | BufferUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        },
+        staging.len(),
+    )?;
+    allocator.tracker.track_buffer(AllocationCategory::from_usage(usage), buffer.buffer());
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )?;
+
+    VulkanAllocation::record_copy_buffer(&mut builder, &staging, &buffer)?;
+
+    let command_buffer = builder.build()?;
+
+    sync::now(queue.device().clone())
+    .then_execute(queue.clone(), command_buffer)?
+    .then_signal_fence_and_flush()?
+    .wait(None)?;
+
+    Ok(buffer)
+}
+
+// Copies a buffer created with TRANSFER_SRC usage into CPU memory, blocking until done
+pub fn read_back_buffer<T : BufferContents + Copy>(toolset : &VulkanToolset, src : &Subbuffer<[T]>) -> Result<Vec<T>, EngineError> {
+    read_back_buffer_async(toolset, src)?.wait()
+}
+
+// Like read_back_buffer for `len` elements starting at element `offset`, huge buffers don't need a full copy
+pub fn read_back_range<T : BufferContents + Copy>(toolset : &VulkanToolset, src : &Subbuffer<[T]>, offset : DeviceSize, len : DeviceSize) -> Result<Vec<T>, EngineError> {
+    let end = offset.checked_add(len).filter(|&end| end <= src.len() && len > 0);
+    let Some(end) = end else {
+        return Err(EngineError::BufferRange { offset, len, buffer_len : src.len() });
+    };
+
+    read_back_buffer(toolset, &src.clone().slice(offset..end))
+}
+
+// Submits the copy without waiting, poll is_ready from the render loop and wait once it is
+pub fn read_back_buffer_async<T : BufferContents + Copy>(toolset : &VulkanToolset, src : &Subbuffer<[T]>) -> Result<PendingReadback<T>, EngineError> {
+    let allocator = &toolset.memory_allocator;
+    let queue = &toolset.device_queue;
+
+    let staging = Buffer::new_slice::<T>(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        src.len(),
+    )?;
+    allocator.tracker.track_buffer(AllocationCategory::Staging, staging.buffer());
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )?;
+
+    VulkanAllocation::record_copy_buffer(&mut builder, src, &staging)?;
+
+    let command_buffer = builder.build()?;
+
+    let fence = sync::now(queue.device().clone())
+    .then_execute(queue.clone(), command_buffer)?
+    .then_signal_fence_and_flush()?;
+
+    Ok(PendingReadback { fence, staging })
+}
+
+pub struct PendingReadback<T : BufferContents> {
+    fence : FenceSignalFuture<CommandBufferExecFuture<NowFuture>>,
+    staging : Subbuffer<[T]>,
+}
+
+impl<T : BufferContents + Copy> PendingReadback<T> {
+    pub fn is_ready(&self) -> Result<bool, EngineError> {
+        Ok(self.fence.is_signaled()?)
+    }
+
+    // Blocks until the copy finished, returns immediately once is_ready reported true
+    pub fn wait(self) -> Result<Vec<T>, EngineError> {
+        self.fence.wait(None)?;
+        let data = self.staging.read()?.to_vec();
+
+        Ok(data)
+    }
+}
+
+// Image uploads recorded into a frame's command buffer instead of submitted and waited on one by one,
+// such as tiles a TextureStreamer decided to load. Staging buffers are filled when an upload is queued,
+// the command buffer keeps them alive until the copies ran
+#[derive(Default)]
+pub struct UploadQueue {
+    pending : Vec<PendingUpload>,
+}
+
+struct PendingUpload {
+    staging : Subbuffer<[u8]>,
+    image : Arc<Image>,
+    region : ImageRegion,
+}
+
+impl UploadQueue {
+    pub fn new() -> UploadQueue {
+        UploadQueue::default()
+    }
+
+    // `bytes` are tightly packed, `region.row_pitch` is ignored. The image needs TRANSFER_DST usage
+    pub fn push_image(&mut self, allocator : &VulkanAllocation, image : &Arc<Image>, bytes : &[u8], region : ImageRegion) -> Result<(), EngineError> {
+        let expected = region.packed_size(image.format());
+        if bytes.len() as u64 != expected {
+            return Err(EngineError::ImageDataSize { expected, len : bytes.len() });
+        }
+
+        let staging = Buffer::from_iter(
+            allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            bytes.iter().copied(),
+        )?;
+        allocator.tracker.track_buffer(AllocationCategory::Staging, staging.buffer());
+
+        self.pending.push(PendingUpload { staging, image : image.clone(), region });
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    // Record outside of a render pass, before the commands that read the images. Returns how many
+    // uploads were recorded, the queue is empty afterwards
+    pub fn record(&mut self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) -> Result<usize, EngineError> {
+        let count = self.pending.len();
+
+        for upload in self.pending.drain(..) {
+            let format = upload.image.format();
+            let copy = BufferImageCopy {
+                image_subresource: ImageSubresourceLayers {
+                    mip_level: upload.region.mip_level,
+                    array_layers: upload.region.array_layers,
+                    ..ImageSubresourceLayers::from_parameters(format, 1)
+                },
+                image_extent: upload.region.extent,
+                ..Default::default()
+            };
+
+            builder.copy_buffer_to_image(CopyBufferToImageInfo {
+                regions: [copy].into(),
+                ..CopyBufferToImageInfo::buffer_image(upload.staging, upload.image)
+            })?;
+        }
+
+        Ok(count)
+    }
+}
@@ -0,0 +1,77 @@
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    descriptor_set::WriteDescriptorSet,
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter}
+};
+
+use crate::error::EngineError;
+use super::{memory_stats::AllocationCategory, vulkan::VulkanAllocation};
+
+// Host writable array of `T` that shaders read as a storage buffer, `T` has to follow the std430 layout
+// the shader declares. The GPU must be done with the buffer before it is written again, so per frame
+// data takes one buffer per frame in flight
+pub struct StorageBuffer<T : BufferContents> {
+    buffer : Subbuffer<[T]>,
+}
+
+impl<T : BufferContents + Copy> StorageBuffer<T> {
+    // `initial` fills the start of the buffer, the rest is zeroed. Longer data than `count` is an error
+    pub fn new(allocator : &VulkanAllocation, count : usize, initial : Option<&[T]>) -> Result<StorageBuffer<T>, EngineError> {
+        if count == 0 {
+            return Err(EngineError::EmptyBuffer);
+        }
+        let initial = initial.unwrap_or(&[]);
+        if initial.len() > count {
+            return Err(EngineError::BufferRange { offset : 0, len : initial.len() as u64, buffer_len : count as u64 });
+        }
+
+        let buffer = Buffer::new_slice::<T>(
+            allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            count as u64,
+        )?;
+        allocator.tracker.track_buffer(AllocationCategory::Storage, buffer.buffer());
+
+        // New allocations aren't guaranteed to be zeroed
+        buffer.as_bytes().write()?.fill(0);
+        buffer.write()?[..initial.len()].copy_from_slice(initial);
+
+        Ok(StorageBuffer { buffer })
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len() as usize
+    }
+
+    // Never true, new rejects empty buffers
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn buffer(&self) -> &Subbuffer<[T]> {
+        &self.buffer
+    }
+
+    // Fails with HostAccess while a submission that reads the buffer is still in flight
+    pub fn write_element(&self, index : usize, value : T) -> Result<(), EngineError> {
+        if index >= self.len() {
+            return Err(EngineError::BufferRange { offset : index as u64, len : 1, buffer_len : self.len() as u64 });
+        }
+
+        *self.buffer.clone().index(index as u64).write()? = value;
+
+        Ok(())
+    }
+
+    pub fn write_descriptor(&self, binding : u32) -> WriteDescriptorSet {
+        WriteDescriptorSet::buffer(binding, self.buffer.clone())
+    }
+}
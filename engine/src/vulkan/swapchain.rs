@@ -0,0 +1,157 @@
+use std::{sync::Arc, time::Duration};
+
+use vulkano::{command_buffer::PrimaryAutoCommandBuffer, device::Queue, image::Image, render_pass::Framebuffer, swapchain::{self, Swapchain, SwapchainAcquireFuture, SwapchainCreateInfo, SwapchainPresentInfo}, sync::GpuFuture, Validated, VulkanError};
+use winit::dpi::PhysicalSize;
+
+use crate::error::EngineError;
+use super::{frame_sync::FrameFence, vulkan::VulkanToolset, vulkan_window::VulkanWindow};
+
+// Swapchain image acquired for a frame, handed back to SwapchainManager::try_present
+pub struct AcquiredImage {
+    pub index : u32,
+    // The image can still be presented, but the swapchain no longer matches the surface
+    pub suboptimal : bool,
+    future : SwapchainAcquireFuture,
+}
+
+// Why no image could be acquired, see SwapchainManager::acquire
+#[derive(Debug)]
+pub enum AcquireFailure {
+    // The swapchain has to be recreated before anything can be presented
+    OutOfDate,
+    // No image became available within the timeout, the frame is skipped
+    Timeout,
+    // Nothing can be rendered anymore, the loop ends with EngineError::DeviceLost
+    DeviceLost,
+    // Any other error ends the loop as well, including invalid arguments
+    Fatal(EngineError),
+}
+
+impl From<VulkanError> for AcquireFailure {
+    fn from(e : VulkanError) -> Self {
+        match e {
+            VulkanError::OutOfDate => AcquireFailure::OutOfDate,
+            // NotReady is the timeout of a zero timeout
+            VulkanError::Timeout | VulkanError::NotReady => AcquireFailure::Timeout,
+            VulkanError::DeviceLost => AcquireFailure::DeviceLost,
+            e => AcquireFailure::Fatal(e.into()),
+        }
+    }
+}
+
+impl From<Validated<VulkanError>> for AcquireFailure {
+    fn from(e : Validated<VulkanError>) -> Self {
+        match e {
+            Validated::Error(e) => e.into(),
+            e => AcquireFailure::Fatal(e.into()),
+        }
+    }
+}
+
+impl From<AcquireFailure> for EngineError {
+    fn from(failure : AcquireFailure) -> Self {
+        match failure {
+            AcquireFailure::OutOfDate => EngineError::Vulkan(VulkanError::OutOfDate),
+            AcquireFailure::Timeout => EngineError::Vulkan(VulkanError::Timeout),
+            AcquireFailure::DeviceLost => EngineError::DeviceLost,
+            AcquireFailure::Fatal(e) => e,
+        }
+    }
+}
+
+pub enum PresentResult {
+    // Presented, the fence signals once the frame finished
+    Ok(FrameFence),
+    // Presented to a suboptimal swapchain, which should be recreated
+    Resized(FrameFence),
+    // Nothing was submitted, the swapchain has to be recreated before the next frame
+    OutOfDate,
+}
+
+// The window's swapchain with the framebuffers drawing into it. Both are only ever replaced together,
+// so a frame never pairs the images of one swapchain with the framebuffers of another
+pub struct SwapchainManager {
+    window : Arc<VulkanWindow>,
+    swapchain : Arc<Swapchain>,
+    images : Vec<Arc<Image>>,
+    framebuffers : Vec<Arc<Framebuffer>>,
+}
+
+impl SwapchainManager {
+    pub fn new(toolset : &VulkanToolset, initial_swapchain : Arc<Swapchain>, initial_images : Vec<Arc<Image>>) -> SwapchainManager {
+        let window = toolset.get_vulkan_window().clone();
+        let framebuffers = window.create_framebuffers(&initial_images);
+
+        SwapchainManager {
+            window,
+            swapchain : initial_swapchain,
+            images : initial_images,
+            framebuffers,
+        }
+    }
+
+    pub fn swapchain(&self) -> &Arc<Swapchain> {
+        &self.swapchain
+    }
+
+    pub fn images(&self) -> &[Arc<Image>] {
+        &self.images
+    }
+
+    pub fn framebuffers(&self) -> &[Arc<Framebuffer>] {
+        &self.framebuffers
+    }
+
+    // Recreates the swapchain and its framebuffers at `new_dimensions`, then calls `on_recreated` to rebuild
+    // whatever depends on them, such as pipelines with a baked in viewport or recorded command buffers.
+    // Frames in flight keep the old swapchain alive until they finished
+    pub fn handle_resize<F>(&mut self, new_dimensions : PhysicalSize<u32>, on_recreated : F) -> Result<(), EngineError>
+    where
+        F : FnOnce(&SwapchainManager) -> Result<(), EngineError>,
+    {
+        let (swapchain, images) = self.swapchain.recreate(SwapchainCreateInfo {
+            image_extent: new_dimensions.into(),
+            ..self.swapchain.create_info()
+        })?;
+        let framebuffers = self.window.create_framebuffers(&images);
+
+        self.swapchain = swapchain;
+        self.images = images;
+        self.framebuffers = framebuffers;
+        self.window.update_viewport(new_dimensions);
+
+        on_recreated(self)
+    }
+
+    // Waits at most `timeout` for an image, None waits until one is available. After OutOfDate
+    // handle_resize has to run before a frame can be presented
+    pub fn acquire(&self, timeout : Option<Duration>) -> Result<AcquiredImage, AcquireFailure> {
+        let (index, suboptimal, future) = swapchain::acquire_next_image(self.swapchain.clone(), timeout)
+        .map_err(AcquireFailure::from)?;
+
+        Ok(AcquiredImage { index, suboptimal, future })
+    }
+
+    // Runs `command_buffers` in order after `previous` and the acquire, then presents the acquired image
+    pub fn try_present(&self, acquired : AcquiredImage, previous : Box<dyn GpuFuture>, command_buffers : Vec<Arc<PrimaryAutoCommandBuffer>>, queue : &Arc<Queue>) -> Result<PresentResult, EngineError> {
+        let mut future = previous.join(acquired.future).boxed();
+        for command_buffer in command_buffers {
+            future = future.then_execute(queue.clone(), command_buffer)?.boxed();
+        }
+
+        let fence = future
+        .then_swapchain_present(
+            queue.clone(),
+            SwapchainPresentInfo::swapchain_image_index(self.swapchain.clone(), acquired.index),
+        )
+        .boxed()
+        .then_signal_fence_and_flush();
+
+        match fence {
+            Ok(fence) if acquired.suboptimal => Ok(PresentResult::Resized(Arc::new(fence))),
+            Ok(fence) => Ok(PresentResult::Ok(Arc::new(fence))),
+            Err(Validated::Error(VulkanError::OutOfDate)) => Ok(PresentResult::OutOfDate),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
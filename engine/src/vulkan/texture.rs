@@ -0,0 +1,313 @@
+use std::{ops::Range, sync::Arc};
+
+use image::{imageops, RgbaImage};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, BufferImageCopy, CommandBufferUsage, CopyBufferToImageInfo},
+    descriptor_set::WriteDescriptorSet,
+    format::Format,
+    image::{sampler::{Sampler, SamplerAddressMode, SamplerCreateInfo}, view::{ImageView, ImageViewCreateInfo, ImageViewType}, Image, ImageCreateFlags, ImageCreateInfo, ImageSubresourceLayers, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    sync::{self, GpuFuture}
+};
+
+use crate::error::EngineError;
+use super::{memory_stats::AllocationCategory, vulkan::VulkanToolset};
+
+// Part of an image written by copy_bytes_to_image
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageRegion {
+    // Size of the written part, mip levels are smaller than the base extent
+    pub extent : [u32; 3],
+    // Bytes from the start of one source row to the next, None when rows are tightly packed
+    pub row_pitch : Option<u32>,
+    pub mip_level : u32,
+    // Layers follow each other in the source data
+    pub array_layers : Range<u32>,
+}
+
+impl ImageRegion {
+    // Tightly packed rows into the first mip level and layer
+    pub fn new(extent : [u32; 3]) -> ImageRegion {
+        ImageRegion {
+            extent,
+            row_pitch : None,
+            mip_level : 0,
+            array_layers : 0..1,
+        }
+    }
+
+    // Bytes of the region's texels with tightly packed rows, counted in texel blocks
+    pub fn packed_size(&self, format : Format) -> u64 {
+        let block_extent = format.block_extent();
+        let blocks = (0..3).map(|axis| self.extent[axis].div_ceil(block_extent[axis]) as u64).product::<u64>();
+
+        blocks * self.array_layers.len() as u64 * format.block_size()
+    }
+}
+
+// Creates a sampled 2D image from pixel bytes and returns its view.
+// TRANSFER_SRC is included so the image can be read back or blitted into its own mip levels
+pub fn upload_image_view(toolset : &VulkanToolset, format : Format, extent : [u32; 2], bytes : &[u8], row_pitch : Option<u32>) -> Result<Arc<ImageView>, EngineError> {
+    let image = Image::new(
+        toolset.memory_allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format,
+            extent: [extent[0], extent[1], 1],
+            usage: ImageUsage::TRANSFER_DST | ImageUsage::TRANSFER_SRC | ImageUsage::SAMPLED,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        },
+    )?;
+    toolset.memory_allocator.tracker.track_image(AllocationCategory::Texture, &image);
+
+    copy_bytes_to_image(toolset, &image, bytes, ImageRegion {
+        row_pitch,
+        ..ImageRegion::new([extent[0], extent[1], 1])
+    })?;
+
+    Ok(ImageView::new_default(image)?)
+}
+
+// Copies pixel bytes into a region of an image created with TRANSFER_DST usage, blocking until done.
+// Vulkano moves the image into the transfer layout and back, so it can be sampled right away
+pub fn copy_bytes_to_image(toolset : &VulkanToolset, image : &Arc<Image>, bytes : &[u8], region : ImageRegion) -> Result<(), EngineError> {
+    let allocator = &toolset.memory_allocator;
+    let queue = &toolset.device_queue;
+
+    // Rows are counted in texel blocks so compressed formats work too
+    let format = image.format();
+    let block_extent = format.block_extent();
+    let block_size = format.block_size();
+    let row_bytes = region.extent[0].div_ceil(block_extent[0]) as u64 * block_size;
+    let row_count = region.extent[1].div_ceil(block_extent[1]) as u64
+        * region.extent[2].div_ceil(block_extent[2]) as u64
+        * region.array_layers.len() as u64;
+    let row_pitch = region.row_pitch.map_or(row_bytes, u64::from).max(row_bytes);
+
+    let expected = row_pitch * row_count.saturating_sub(1) + row_bytes;
+    if (bytes.len() as u64) < expected {
+        return Err(EngineError::ImageDataSize { expected, len : bytes.len() });
+    }
+    let bytes = &bytes[..expected as usize];
+
+    // The copy can skip padding of whole blocks, anything else is repacked first
+    let (data, buffer_row_length) = if row_pitch % block_size == 0 {
+        (bytes.to_vec(), (row_pitch / block_size) as u32 * block_extent[0])
+    } else {
+        let packed = bytes.chunks(row_pitch as usize)
+        .flat_map(|row| &row[..row_bytes as usize])
+        .copied()
+        .collect();
+        (packed, 0)
+    };
+
+    let staging = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        data,
+    )?;
+    allocator.tracker.track_buffer(AllocationCategory::Staging, staging.buffer());
+
+    let copy = BufferImageCopy {
+        buffer_row_length,
+        image_subresource: ImageSubresourceLayers {
+            mip_level: region.mip_level,
+            array_layers: region.array_layers,
+            ..ImageSubresourceLayers::from_parameters(format, 1)
+        },
+        image_extent: region.extent,
+        ..Default::default()
+    };
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )?;
+
+    builder.copy_buffer_to_image(CopyBufferToImageInfo {
+        regions: [copy].into(),
+        ..CopyBufferToImageInfo::buffer_image(staging, image.clone())
+    })?;
+
+    let command_buffer = builder.build()?;
+
+    sync::now(toolset.logical_device.clone())
+    .then_execute(queue.clone(), command_buffer)?
+    .then_signal_fence_and_flush()?
+    .wait(None)?;
+
+    Ok(())
+}
+
+// Splits a cube map stored as one image into its faces in +X, -X, +Y, -Y, +Z, -Z order.
+// Takes 6:1 and 1:6 strips in that order, or a 4:3 cross with +Z in the middle:
+//      +Y
+//  -X  +Z  +X  -Z
+//      -Y
+pub fn split_cube_faces(image : &RgbaImage) -> Result<[RgbaImage; 6], EngineError> {
+    let (width, height) = image.dimensions();
+
+    // Column and row of every face, in face sized cells
+    let (size, cells) = if width == height * 6 {
+        (height, [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)])
+    } else if height == width * 6 {
+        (width, [(0, 0), (0, 1), (0, 2), (0, 3), (0, 4), (0, 5)])
+    } else if width % 4 == 0 && width * 3 == height * 4 {
+        (width / 4, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)])
+    } else {
+        (0, [(0, 0); 6])
+    };
+
+    if size == 0 {
+        return Err(EngineError::UnsupportedFeature(format!("a {width}x{height} image is neither a strip nor a cross of cube map faces")));
+    }
+
+    Ok(cells.map(|(column, row)| imageops::crop_imm(image, column * size, row * size, size, size).to_image()))
+}
+
+pub struct Texture2D {
+    pub image : Arc<Image>,
+    pub view : Arc<ImageView>,
+    pub sampler : Arc<Sampler>,
+}
+
+impl Texture2D {
+    pub fn from_rgba_bytes(toolset : &VulkanToolset, width : u32, height : u32, bytes : &[u8]) -> Texture2D {
+        let image = Self::upload(toolset, ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_SRGB,
+            extent: [width, height, 1],
+            usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+            ..Default::default()
+        }, bytes);
+
+        let view = ImageView::new_default(image.clone()).unwrap();
+        let sampler = Sampler::new(toolset.logical_device.clone(), SamplerCreateInfo::simple_repeat_linear_no_mipmap()).unwrap();
+
+        Texture2D {
+            image,
+            view,
+            sampler,
+        }
+    }
+
+    // Single channel linear data such as heightmaps, one byte per texel
+    pub fn from_grayscale_bytes(toolset : &VulkanToolset, width : u32, height : u32, bytes : &[u8]) -> Texture2D {
+        let image = Self::upload(toolset, ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8_UNORM,
+            extent: [width, height, 1],
+            usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+            ..Default::default()
+        }, bytes);
+
+        let view = ImageView::new_default(image.clone()).unwrap();
+        let sampler = Sampler::new(
+            toolset.logical_device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+            },
+        ).unwrap();
+
+        Texture2D {
+            image,
+            view,
+            sampler,
+        }
+    }
+
+    // Faces are tightly packed RGBA8 in +X, -X, +Y, -Y, +Z, -Z order
+    pub fn cube_from_rgba_faces(toolset : &VulkanToolset, size : u32, faces : [&[u8]; 6]) -> Texture2D {
+        let bytes = faces.concat();
+        let image = Self::upload(toolset, ImageCreateInfo {
+            flags: ImageCreateFlags::CUBE_COMPATIBLE,
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_SRGB,
+            extent: [size, size, 1],
+            array_layers: 6,
+            usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+            ..Default::default()
+        }, &bytes);
+
+        let view = ImageView::new(
+            image.clone(),
+            ImageViewCreateInfo {
+                view_type: ImageViewType::Cube,
+                ..ImageViewCreateInfo::from_image(&image)
+            },
+        ).unwrap();
+
+        // Clamp to avoid seams between the faces
+        let sampler = Sampler::new(
+            toolset.logical_device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+            },
+        ).unwrap();
+
+        Texture2D {
+            image,
+            view,
+            sampler,
+        }
+    }
+
+    // Same as cube_from_rgba_faces for decoded images, which must all be square and the same size
+    pub fn cube_from_face_images(toolset : &VulkanToolset, faces : &[RgbaImage; 6]) -> Result<Texture2D, EngineError> {
+        let size = faces[0].width();
+
+        for (face, image) in faces.iter().enumerate() {
+            if image.dimensions() != (size, size) {
+                return Err(EngineError::CubeFaceSize { face, size : [image.width(), image.height()], expected : [size, size] });
+            }
+        }
+
+        Ok(Self::cube_from_rgba_faces(toolset, size, faces.each_ref().map(|face| face.as_raw().as_slice())))
+    }
+
+    pub fn write_descriptor(&self, binding : u32) -> WriteDescriptorSet {
+        WriteDescriptorSet::image_view_sampler(binding, self.view.clone(), self.sampler.clone())
+    }
+
+    // For bindings whose layout holds the sampler already, see ImmutableSamplers
+    pub fn write_view_descriptor(&self, binding : u32) -> WriteDescriptorSet {
+        WriteDescriptorSet::image_view(binding, self.view.clone())
+    }
+
+    fn upload(toolset : &VulkanToolset, create_info : ImageCreateInfo, bytes : &[u8]) -> Arc<Image> {
+        let region = ImageRegion {
+            array_layers : 0..create_info.array_layers,
+            ..ImageRegion::new(create_info.extent)
+        };
+
+        let image = Image::new(
+            toolset.memory_allocator.general_allocator.clone(),
+            create_info,
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        ).unwrap();
+        toolset.memory_allocator.tracker.track_image(AllocationCategory::Texture, &image);
+
+        copy_bytes_to_image(toolset, &image, bytes, region).expect("failed to upload texture");
+
+        image
+    }
+}
@@ -0,0 +1,152 @@
+use std::{mem::MaybeUninit, ptr, sync::{atomic::{AtomicU64, Ordering}, Arc}, time::Duration};
+
+use ash::vk;
+use log::warn;
+use vulkano::{command_buffer::PrimaryAutoCommandBuffer, device::{Device, Queue}, Version, VulkanError, VulkanObject};
+
+use crate::error::EngineError;
+
+// Semaphore whose payload counts up. A wait is for a value or anything past it, so one semaphore orders any
+// number of submissions across queues, and the host can read, wait for and signal it as well. Needs Vulkan 1.2,
+// see DeviceCapabilities::timeline_semaphores. vulkano doesn't wrap them yet, submissions here go around its
+// futures: it neither tracks the resources they use nor keeps their command buffers alive
+pub struct TimelineSemaphore {
+    device : Arc<Device>,
+    handle : vk::Semaphore,
+    // Largest value signaled or submitted to be signaled, dropping waits for it
+    last_signal : AtomicU64,
+}
+
+impl TimelineSemaphore {
+    pub fn new(device : &Arc<Device>, initial_value : u64) -> Result<TimelineSemaphore, EngineError> {
+        if device.api_version() < Version::V1_2 || !device.enabled_features().timeline_semaphore {
+            return Err(EngineError::UnsupportedFeature("timeline semaphores need Vulkan 1.2 and the timeline_semaphore feature".to_owned()));
+        }
+
+        let mut type_info = vk::SemaphoreTypeCreateInfo::builder()
+        .semaphore_type(vk::SemaphoreType::TIMELINE)
+        .initial_value(initial_value);
+        let create_info = vk::SemaphoreCreateInfo::builder()
+        .push_next(&mut type_info);
+
+        let handle = unsafe {
+            let mut output = MaybeUninit::uninit();
+            (device.fns().v1_0.create_semaphore)(device.handle(), &*create_info, ptr::null(), output.as_mut_ptr())
+            .result()
+            .map_err(VulkanError::from)?;
+
+            output.assume_init()
+        };
+
+        Ok(TimelineSemaphore {
+            device : device.clone(),
+            handle,
+            last_signal : AtomicU64::new(initial_value),
+        })
+    }
+
+    // Largest value signaled so far
+    pub fn value(&self) -> Result<u64, EngineError> {
+        let mut value = 0;
+        unsafe {
+            (self.device.fns().v1_2.get_semaphore_counter_value)(self.device.handle(), self.handle, &mut value)
+            .result()
+            .map_err(VulkanError::from)?;
+        }
+
+        Ok(value)
+    }
+
+    // Blocks until the semaphore reached `value`, false when the timeout passed first. None waits forever
+    pub fn wait(&self, value : u64, timeout : Option<Duration>) -> Result<bool, EngineError> {
+        let timeout_ns = timeout.map_or(u64::MAX, |timeout| timeout.as_nanos().min(u64::MAX as u128) as u64);
+        let semaphores = [self.handle];
+        let values = [value];
+        let wait_info = vk::SemaphoreWaitInfo::builder()
+        .semaphores(&semaphores)
+        .values(&values);
+
+        let result = unsafe { (self.device.fns().v1_2.wait_semaphores)(self.device.handle(), &*wait_info, timeout_ns) };
+        match result {
+            vk::Result::SUCCESS => Ok(true),
+            vk::Result::TIMEOUT => Ok(false),
+            e => Err(VulkanError::from(e).into()),
+        }
+    }
+
+    // Sets the value from the host, it has to be larger than the current one and than any pending signal
+    pub fn signal(&self, value : u64) -> Result<(), EngineError> {
+        let signal_info = vk::SemaphoreSignalInfo::builder()
+        .semaphore(self.handle)
+        .value(value);
+
+        unsafe {
+            (self.device.fns().v1_2.signal_semaphore)(self.device.handle(), &*signal_info)
+            .result()
+            .map_err(VulkanError::from)?;
+        }
+        self.last_signal.fetch_max(value, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    // Signals `value` once everything submitted to `queue` so far finished, including submissions made
+    // through vulkano's futures
+    pub fn signal_submit(&self, queue : &Arc<Queue>, value : u64) -> Result<(), EngineError> {
+        self.submit(queue, &[], None, Some(value))
+    }
+
+    // Runs `command_buffers` on `queue` once the semaphore reached `value`. A wait only holds back the work
+    // submitted with it, not later submissions to the queue, which is why the command buffers are part of it
+    pub fn wait_submit(&self, queue : &Arc<Queue>, value : u64, command_buffers : &[Arc<PrimaryAutoCommandBuffer>]) -> Result<(), EngineError> {
+        self.submit(queue, command_buffers, Some(value), None)
+    }
+
+    // Runs `command_buffers` on `queue` after `wait` was reached, then signals `signal`. The command buffers
+    // must stay alive until a signal submitted with or after them was reached
+    pub fn submit(&self, queue : &Arc<Queue>, command_buffers : &[Arc<PrimaryAutoCommandBuffer>], wait : Option<u64>, signal : Option<u64>) -> Result<(), EngineError> {
+        let handles = command_buffers.iter()
+        .map(|command_buffer| command_buffer.handle())
+        .collect::<Vec<_>>();
+        let wait_values = wait.into_iter().collect::<Vec<_>>();
+        let signal_values = signal.into_iter().collect::<Vec<_>>();
+        let wait_semaphores = vec![self.handle; wait_values.len()];
+        let wait_stages = vec![vk::PipelineStageFlags::ALL_COMMANDS; wait_values.len()];
+        let signal_semaphores = vec![self.handle; signal_values.len()];
+
+        let mut timeline_info = vk::TimelineSemaphoreSubmitInfo::builder()
+        .wait_semaphore_values(&wait_values)
+        .signal_semaphore_values(&signal_values);
+        let submit_info = vk::SubmitInfo::builder()
+        .wait_semaphores(&wait_semaphores)
+        .wait_dst_stage_mask(&wait_stages)
+        .command_buffers(&handles)
+        .signal_semaphores(&signal_semaphores)
+        .push_next(&mut timeline_info);
+
+        // Holding the queue's lock keeps vulkano from submitting to it at the same time
+        queue.with(|_guard| unsafe {
+            (self.device.fns().v1_0.queue_submit)(queue.handle(), 1, &*submit_info, vk::Fence::null())
+            .result()
+            .map_err(VulkanError::from)
+        })?;
+        if let Some(value) = signal {
+            self.last_signal.fetch_max(value, Ordering::Relaxed);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for TimelineSemaphore {
+    // The semaphore can only be destroyed once no submission signals it anymore
+    fn drop(&mut self) {
+        if let Err(e) = self.wait(self.last_signal.load(Ordering::Relaxed), None) {
+            warn!("failed to wait for a timeline semaphore before destroying it: {e}");
+        }
+
+        unsafe {
+            (self.device.fns().v1_0.destroy_semaphore)(self.device.handle(), self.handle, ptr::null());
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::{slice, sync::Arc};
+
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{graphics::{input_assembly::{InputAssemblyState, PrimitiveTopology}, rasterization::RasterizationState, vertex_input::{Vertex, VertexDefinition}, GraphicsPipelineCreateInfo}, GraphicsPipeline, PipelineShaderStageCreateInfo},
+    render_pass::{Framebuffer, FramebufferCreateInfo, Subpass},
+    shader::ShaderModule,
+    sync::{AccessFlags, DependencyInfo, MemoryBarrier, PipelineStages},
+    DeviceSize
+};
+
+use crate::error::EngineError;
+use super::{debug_utils::DebugUtils, extensions::TransformFeedbackExt, raw_commands::{begin_raw_commands, submit_raw_and_wait}, render_pass::RenderPassBuilder, vertex::VulkanVertex, vulkan::{find_entry_point, VulkanToolset}};
+
+// One xfb_buffer the vertex shader writes to, `stride` has to match its xfb_stride
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XfbBinding {
+    pub buffer_index : u32,
+    pub stride : u32,
+}
+
+// Vertex shader without a fragment stage whose outputs are captured instead of rasterized.
+// Reads VulkanVertex input as a point list, so every vertex is written once and in order.
+// Which outputs land where comes from the xfb_buffer, xfb_offset and xfb_stride qualifiers
+pub struct XfbPipeline {
+    pub pipeline : Arc<GraphicsPipeline>,
+    pub bindings : Vec<XfbBinding>,
+    ext : TransformFeedbackExt,
+    // Draws need a render pass even with rasterization discarded, this one has no attachments
+    framebuffer : Arc<Framebuffer>,
+}
+
+impl XfbPipeline {
+    // Fails without ext_transform_feedback or when a binding is past the device's limits
+    pub fn new(toolset : &VulkanToolset, vs : &Arc<ShaderModule>, xfb_bindings : &[XfbBinding]) -> Result<XfbPipeline, EngineError> {
+        let ext = *toolset.transform_feedback.get()
+        .ok_or_else(|| EngineError::UnsupportedFeature("transform feedback needs ext_transform_feedback".to_owned()))?;
+
+        for binding in xfb_bindings {
+            if binding.buffer_index >= ext.max_buffers {
+                return Err(EngineError::DeviceLimit { limit : "max_transform_feedback_buffers", requested : binding.buffer_index + 1, max : ext.max_buffers });
+            }
+            if binding.stride > ext.max_stride {
+                return Err(EngineError::DeviceLimit { limit : "max_transform_feedback_buffer_data_stride", requested : binding.stride, max : ext.max_stride });
+            }
+        }
+
+        let device = &toolset.logical_device;
+        let vs = find_entry_point(vs, "main")?;
+        let vertex_input_state = VulkanVertex::per_vertex().definition(&vs.info().input_interface)?;
+
+        let render_pass = RenderPassBuilder::new()
+        .subpass(&[], None)
+        .build(device)?;
+        let framebuffer = Framebuffer::new(
+            render_pass.clone(),
+            FramebufferCreateInfo {
+                extent: [1, 1],
+                layers: 1,
+                ..Default::default()
+            },
+        )?;
+
+        let stages = [PipelineShaderStageCreateInfo::new(vs)];
+        let layout = toolset.create_pipeline_layout(&stages, None, &[], &[])?;
+
+        // Discarding rasterization leaves out viewports, multisampling and the fragment stage
+        let pipeline = GraphicsPipeline::new(
+            device.clone(),
+            None,
+            GraphicsPipelineCreateInfo {
+                stages: stages.into_iter().collect(),
+                vertex_input_state: Some(vertex_input_state),
+                input_assembly_state: Some(InputAssemblyState {
+                    topology: PrimitiveTopology::PointList,
+                    ..Default::default()
+                }),
+                rasterization_state: Some(RasterizationState {
+                    rasterizer_discard_enable: true,
+                    ..Default::default()
+                }),
+                subpass: Some(Subpass::from(render_pass, 0).unwrap().into()),
+                ..GraphicsPipelineCreateInfo::layout(layout)
+            },
+        )?;
+        DebugUtils::name_object(device, &pipeline, "transform feedback pipeline");
+
+        Ok(XfbPipeline {
+            pipeline,
+            bindings : xfb_bindings.to_vec(),
+            ext,
+            framebuffer,
+        })
+    }
+
+    // Host readable buffer fitting `vertex_count` vertices of `binding`
+    pub fn create_output(&self, toolset : &VulkanToolset, binding : &XfbBinding, vertex_count : u32) -> Result<Subbuffer<[u8]>, EngineError> {
+        Ok(Buffer::new_slice::<u8>(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFORM_FEEDBACK_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            binding.stride as DeviceSize * vertex_count as DeviceSize,
+        )?)
+    }
+
+    // Runs the vertex shader over `vertices` and waits for it, returns one output per binding in the
+    // order they were given to new. Goes through a raw command buffer, see raw_commands
+    pub fn capture(&self, toolset : &VulkanToolset, vertices : &Subbuffer<[VulkanVertex]>) -> Result<Vec<Subbuffer<[u8]>>, EngineError> {
+        let vertex_count = vertices.len() as u32;
+        let outputs = self.bindings.iter()
+        .map(|binding| self.create_output(toolset, binding, vertex_count))
+        .collect::<Result<Vec<_>, _>>()?;
+
+        // The outputs are read on the host once the fence signaled
+        let host_barrier = DependencyInfo {
+            memory_barriers: [MemoryBarrier {
+                src_stages: PipelineStages::TRANSFORM_FEEDBACK,
+                src_access: AccessFlags::TRANSFORM_FEEDBACK_WRITE,
+                dst_stages: PipelineStages::HOST,
+                dst_access: AccessFlags::HOST_READ,
+                ..Default::default()
+            }].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let mut builder = begin_raw_commands(toolset)?;
+        // Safety: `vertices` and the outputs outlive the submission, which is waited for below
+        unsafe {
+            builder.begin_render_pass(
+                &RenderPassBeginInfo::framebuffer(self.framebuffer.clone()),
+                &SubpassBeginInfo {
+                    contents: SubpassContents::Inline,
+                    ..Default::default()
+                },
+            )?
+            .bind_pipeline_graphics(&self.pipeline)?
+            .bind_vertex_buffers(0, &[vertices.as_bytes().clone()])?;
+
+            for (binding, output) in self.bindings.iter().zip(&outputs) {
+                self.ext.bind_buffers(&mut builder, binding.buffer_index, slice::from_ref(output))?;
+            }
+            self.ext.begin(&mut builder);
+            builder.draw(vertex_count, 1, 0, 0)?;
+            self.ext.end(&mut builder);
+
+            builder.end_render_pass(&SubpassEndInfo::default())?
+            .pipeline_barrier(&host_barrier)?;
+        }
+        let command_buffer = builder.build()?;
+        submit_raw_and_wait(&toolset.device_queue, &command_buffer)?;
+
+        Ok(outputs)
+    }
+}
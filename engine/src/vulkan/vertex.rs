@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use vulkano::{buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer}, device::Device, memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter}, pipeline::graphics::vertex_input::Vertex, shader::ShaderModule};
+
+use crate::error::EngineError;
+
+#[derive(BufferContents, Vertex, Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct VulkanVertex {
+    #[format(R32G32_SFLOAT)]
+    pub position : [f32; 2],
+}
+
+impl VulkanVertex {
+    pub fn new(x : f32, y : f32) -> VulkanVertex {
+        VulkanVertex {
+            position : [x, y]
+        }
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec2 position;
+
+            void main() {
+                gl_Position = vec4(position, 0.0, 1.0);
+            }
+        ",
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                f_color = vec4(1.0, 0.0, 0.0, 1.0);
+            }
+        ",
+    }
+}
+
+pub struct Triangle {
+    pub vertex_buffer : Subbuffer<[VulkanVertex]>,
+    pub vertex_shader : Arc<ShaderModule>,
+    pub fragment_shader : Arc<ShaderModule>,
+}
+
+impl Triangle {
+    pub fn new(memory_allocator : Arc<dyn MemoryAllocator>, device : &Arc<Device>) -> Result<Triangle, EngineError> {
+        let vertices = vec![
+            VulkanVertex::new(-0.5, -0.5),
+            VulkanVertex::new( 0.0,  0.5),
+            VulkanVertex::new( 0.5, -0.25),
+        ];
+
+        Self::with_vertices(memory_allocator, device, vertices)
+    }
+
+    // Any triangle list drawn with the same flat red shaders. Fails on an empty list,
+    // Vulkan has no zero sized buffers
+    pub fn with_vertices(memory_allocator : Arc<dyn MemoryAllocator>, device : &Arc<Device>, vertices : Vec<VulkanVertex>) -> Result<Triangle, EngineError> {
+        if vertices.is_empty() {
+            return Err(EngineError::EmptyBuffer);
+        }
+
+        let vbo = Buffer::from_iter(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            vertices,
+        )?;
+
+        Ok(Triangle {
+            vertex_buffer : vbo,
+            vertex_shader : vs::load(device.clone())?,
+            fragment_shader : fs::load(device.clone())?,
+        })
+    }
+}
@@ -0,0 +1,35 @@
+use ash::vk;
+use vulkano::pipeline::graphics::vertex_input::{VertexInputRate, VertexInputState};
+
+// Per instance bindings that advance once every `divisor` instances instead of every instance, so a group
+// of instances can share one element. Needs DeviceCapabilities::vertex_attribute_divisor unless the divisor
+// is 1, see VulkanToolset::check_attribute_divisors
+pub trait AttributeDivisor {
+    // Turns `binding` into a per instance binding with the given divisor. 0 repeats the first element for
+    // every instance. Panics when the state has no such binding
+    fn with_attribute_divisor(self, binding : u32, divisor : u32) -> Self;
+}
+
+impl AttributeDivisor for VertexInputState {
+    fn with_attribute_divisor(mut self, binding : u32, divisor : u32) -> VertexInputState {
+        let description = self.bindings.get_mut(&binding)
+        .unwrap_or_else(|| panic!("vertex input state has no binding {binding}"));
+        description.input_rate = VertexInputRate::Instance { divisor };
+
+        self
+    }
+}
+
+// The VkVertexInputBindingDivisorDescriptionEXT entries a pipeline with `state` is created with, sorted by
+// binding. Bindings that advance every instance or every vertex need none
+pub fn divisor_descriptions(state : &VertexInputState) -> Vec<vk::VertexInputBindingDivisorDescriptionEXT> {
+    let mut descriptions = state.bindings.iter()
+    .filter_map(|(&binding, description)| match description.input_rate {
+        VertexInputRate::Instance { divisor } if divisor != 1 => Some(vk::VertexInputBindingDivisorDescriptionEXT { binding, divisor }),
+        _ => None,
+    })
+    .collect::<Vec<_>>();
+    descriptions.sort_by_key(|description| description.binding);
+
+    descriptions
+}
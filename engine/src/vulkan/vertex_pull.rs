@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::Subbuffer,
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{layout::DescriptorType, PersistentDescriptorSet, WriteDescriptorSet},
+    pipeline::{graphics::{depth_stencil::DepthStencilState, input_assembly::{InputAssemblyState, PrimitiveTopology}, rasterization::RasterizationState, vertex_input::VertexInputState, viewport::Viewport}, GraphicsPipeline, Pipeline, PipelineBindPoint},
+    render_pass::Subpass,
+    shader::ShaderModule
+};
+
+use crate::{engine::Frame, error::EngineError};
+use super::{debug_utils::DebugUtils, scissor::ScissorState, shader_interface::ShaderInterface, vulkan::{find_entry_point, MultisampleConfig, PipelineStates, VulkanToolset}};
+
+// Point list pipeline without vertex buffers. The vertex shader reads its points from a storage buffer
+// in set 0, indexed with gl_VertexIndex, so data written by compute passes is drawn without a copy
+// or a buffer with VERTEX_BUFFER usage
+pub struct VertexPullPipeline {
+    pub pipeline : Arc<GraphicsPipeline>,
+    // Storage buffer binding in set 0 the vertices are read from
+    pub binding : u32,
+    // Points drawn, one per element of the buffer
+    pub count : u32,
+}
+
+impl VertexPullPipeline {
+    // Fails when the vertex shader doesn't declare a storage buffer at `binding` of set 0
+    pub fn new(toolset : &VulkanToolset, subpass : Subpass, viewport : Viewport, binding : u32, count : u32, vs : &Arc<ShaderModule>, fs : &Arc<ShaderModule>) -> Result<VertexPullPipeline, EngineError> {
+        let vs = find_entry_point(vs, "main")?;
+        let fs = find_entry_point(fs, "main")?;
+
+        let declared = ShaderInterface::inspect_entry_point(&vs).descriptor_bindings.iter()
+        .any(|info| info.set == 0 && info.binding == binding && info.descriptor_types.contains(&DescriptorType::StorageBuffer));
+        if !declared {
+            return Err(EngineError::UndeclaredBinding { set : 0, binding });
+        }
+
+        let pipeline = toolset.build_pipeline_for(vec![vs, fs], PipelineStates {
+            // No bindings or attributes, nothing is fetched before the vertex shader runs
+            vertex_input_state : VertexInputState::new(),
+            input_assembly_state : InputAssemblyState {
+                topology : PrimitiveTopology::PointList,
+                ..Default::default()
+            },
+            tessellation_state : None,
+            rasterization_state : RasterizationState::default(),
+            depth_stencil_state : DepthStencilState::default(),
+            push_descriptor_set : None,
+            conservative_raster : None,
+            multisample : MultisampleConfig::default(),
+            dynamic_viewport : false,
+            scissor : ScissorState::default(),
+            color_write_masks : Vec::new(),
+            blend : None,
+            immutable_samplers : Vec::new(),
+            inline_uniform_blocks : Vec::new(),
+        }, subpass, viewport);
+        DebugUtils::name_object(&toolset.logical_device, &pipeline, "vertex pull pipeline");
+
+        Ok(VertexPullPipeline {
+            pipeline,
+            binding,
+            count,
+        })
+    }
+
+    // Set 0 with `vertices` at the pulled binding, the buffer needs STORAGE_BUFFER usage
+    pub fn descriptor_set<T : ?Sized>(&self, toolset : &VulkanToolset, vertices : &Subbuffer<T>) -> Result<Arc<PersistentDescriptorSet>, EngineError> {
+        let layout = self.pipeline.layout().set_layouts()[0].clone();
+
+        Ok(PersistentDescriptorSet::new(
+            &toolset.memory_allocator.descriptor_set_allocator,
+            layout,
+            [WriteDescriptorSet::buffer(self.binding, vertices.as_bytes().clone())],
+            [],
+        )?)
+    }
+
+    // Record inside a render pass compatible with the pipeline's subpass
+    pub fn record(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, descriptor_set : Arc<PersistentDescriptorSet>) -> Result<(), EngineError> {
+        builder.bind_pipeline_graphics(self.pipeline.clone())?
+        .bind_descriptor_sets(PipelineBindPoint::Graphics, self.pipeline.layout().clone(), 0, descriptor_set)?
+        .draw(self.count, 1, 0, 0)?;
+
+        Ok(())
+    }
+
+    // Same as record for the window's render pass
+    pub fn draw(&self, frame : &mut Frame, descriptor_set : Arc<PersistentDescriptorSet>) {
+        frame.draw_pulled(self.pipeline.clone(), descriptor_set, self.count);
+    }
+}
@@ -1,95 +1,608 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, mem::size_of, sync::{atomic::{AtomicBool, Ordering}, Arc}};
 use vulkano::{
-    buffer::Subbuffer, command_buffer::{allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo}, AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo}, device::*, instance::*, memory::allocator::{FreeListAllocator, GenericMemoryAllocator, StandardMemoryAllocator}, pipeline::{compute::ComputePipelineCreateInfo, graphics::{color_blend::{ColorBlendAttachmentState, ColorBlendState}, input_assembly::InputAssemblyState, multisample::MultisampleState, rasterization::RasterizationState, vertex_input::{Vertex, VertexDefinition}, viewport::ViewportState, GraphicsPipelineCreateInfo}, layout::PipelineDescriptorSetLayoutCreateInfo, ComputePipeline, GraphicsPipeline, PipelineLayout, PipelineShaderStageCreateInfo}, render_pass::{Framebuffer, Subpass}, shader::{EntryPoint, ShaderModule}, swapchain::Surface, VulkanLibrary
+    buffer::{view::{BufferView, BufferViewCreateInfo}, Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer}, command_buffer::{allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo}, AutoCommandBufferBuilder, ClearAttachment, ClearColorImageInfo, ClearDepthStencilImageInfo, ClearRect, CommandBufferUsage, CopyBufferInfo, CopyImageInfo, DrawIndexedIndirectCommand, DrawIndirectCommand, ImageCopy, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo}, descriptor_set::{allocator::StandardDescriptorSetAllocator, layout::DescriptorSetLayoutCreateFlags, PersistentDescriptorSet, WriteDescriptorSet}, device::*, instance::*, format::{ClearColorValue, ClearDepthStencilValue, ClearValue, Format, FormatFeatures}, image::{view::{ImageView, ImageViewCreateInfo, ImageViewType}, Image, ImageLayout, ImageSubresourceRange, ImageType, SampleCount}, memory::{allocator::{AllocationCreateInfo, FreeListAllocator, GenericMemoryAllocator, MemoryTypeFilter, StandardMemoryAllocator}, MemoryPropertyFlags, MemoryRequirements, MemoryType}, pipeline::{compute::ComputePipelineCreateInfo, graphics::{color_blend::{AttachmentBlend, ColorBlendAttachmentState, ColorBlendState, ColorComponents}, depth_stencil::{DepthState, DepthStencilState}, input_assembly::{InputAssemblyState, PrimitiveTopology}, multisample::MultisampleState, rasterization::{CullMode, DepthBiasState, FrontFace, PolygonMode, RasterizationState}, tessellation::TessellationState, vertex_input::{Vertex, VertexDefinition, VertexInputState}, viewport::{Scissor, Viewport, ViewportState}, GraphicsPipelineCreateInfo, subpass::PipelineSubpassType}, layout::PipelineDescriptorSetLayoutCreateInfo, ComputePipeline, DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout, PipelineShaderStageCreateInfo}, render_pass::{Framebuffer, Subpass}, shader::{spirv::ExecutionModel, EntryPoint, ShaderModule, SpecializedShaderModule}, swapchain::Surface, sync::{self, GpuFuture}, Version, VulkanLibrary
 };
+use log::{info, warn};
 use winit::event_loop::EventLoop;
 
-use crate::tests::window_test::VulkanVertex;
-use super::vulkan_window::VulkanWindow;
+use crate::{config::{AppConfig, CommandBufferOptions, DeviceSelection, QueueRequest, QueueRole}, error::EngineError, render::{post_process::PostProcessChain, split_screen::{takes_region_constants, RegionConstants, ViewportRegion}}};
+use super::{api_version::negotiate_api_version, capabilities::DeviceCapabilities, conservative_raster::{create_conservative_pipeline, ConservativeRasterMode, ConservativeRasterizationMode}, debug_utils::DebugUtils, defrag::RelocationRegistry, device_info::DeviceLimits, extensions::{ConditionalRenderingExt, ConservativeRasterExt, DebugLabelExt, ExtensionGuard, InlineUniformBlockExt, PushDescriptorExt, ShadingRateExt, TransformFeedbackExt}, immutable_samplers::ImmutableSamplers, inline_uniform::InlineUniformBinding, memory_stats::{AllocationCategory, AllocationTracker, MemoryStats}, memory_types::{pick_memory_type, MemoryTypeCandidate}, mesh::Vertex3D, occlusion::OcclusionQuerySet, pipeline_desc::{GraphicsPipelineDesc, PipelineDescError, SubpassLayout}, pipeline_stats::PipelineStatsPool, point_cloud::{PointSizeSource, PointVertex, POINT_SIZE_CONSTANT_ID}, queues::plan_queues, render_pass::{compare_attachments, RenderPassMismatch}, render_target_pool::RenderTargetPool, scissor::{clip_scissor, full_scissor, ScissorState}, shader_interface::{ShaderInfo, ShaderInterface}, vertex::VulkanVertex, vertex_divisor::divisor_descriptions, vulkan_window::VulkanWindow};
+
+const VALIDATION_LAYER : &str = "VK_LAYER_KHRONOS_validation";
+// Set once a pipeline drawn in viewport regions couldn't take the region camera, see takes_region_constants
+static REGION_CONSTANTS_WARNED : AtomicBool = AtomicBool::new(false);
 
 pub struct VulkanToolset {
     pub instance : Arc<Instance>,
     pub logical_device : Arc<Device>,
+    // The graphics queue, also reachable as queue(QueueRole::Graphics)
     pub device_queue : Arc<Queue>,
+    // Queue of every role in AppConfig::queues
+    pub queues : BTreeMap<QueueRole, Arc<Queue>>,
     pub memory_allocator : Arc<VulkanAllocation>,
-    pub window : Arc<VulkanWindow>,
+    // None for headless toolsets
+    pub window : Option<Arc<VulkanWindow>>,
+    pub config : AppConfig,
+    pub capabilities : DeviceCapabilities,
+    // Limits checked before work is handed to the device, see device_info
+    pub limits : DeviceLimits,
+    // Extension entry points, absent when the device came up without them
+    pub debug_labels : ExtensionGuard<DebugLabelExt>,
+    pub push_descriptors : ExtensionGuard<PushDescriptorExt>,
+    pub shading_rate : ExtensionGuard<ShadingRateExt>,
+    pub conservative_raster : ExtensionGuard<ConservativeRasterExt>,
+    pub inline_uniform_blocks : ExtensionGuard<InlineUniformBlockExt>,
+    pub conditional_rendering : ExtensionGuard<ConditionalRenderingExt>,
+    pub transform_feedback : ExtensionGuard<TransformFeedbackExt>,
 }
 
 impl VulkanToolset {
     pub fn new(event_loop : &EventLoop<()>) -> VulkanToolset {
+        Self::with_config(event_loop, AppConfig::default())
+    }
+
+    pub fn with_config(event_loop : &EventLoop<()>, config : AppConfig) -> VulkanToolset {
+        Self::try_with_config(event_loop, config)
+        .unwrap_or_else(|e| panic!("failed to create toolset: {e}"))
+    }
+
+    // Same as with_config, returning EngineError::ApiVersion when InstanceConfig::max_api_version can't be met
+    pub fn try_with_config(event_loop : &EventLoop<()>, mut config : AppConfig) -> Result<VulkanToolset, EngineError> {
         // Create basic instances
-        let vulkan_instance = Self::create_instance(event_loop);
-        let mut window_instance = VulkanWindow::new(&vulkan_instance, event_loop);
+        let vulkan_instance = Self::create_instance(Some(event_loop), &config)?;
+        let mut window_instance = VulkanWindow::new(&vulkan_instance, event_loop, &config.window);
 
         // Create logical device
         let surface = window_instance.get_window_surface();
-        let (device, queue) = Self::create_logical_device(&vulkan_instance, &surface);
+        let (device, queues) = Self::create_logical_device(&vulkan_instance, Some(&surface), &config.device, &config.queues);
+        let queue = queues[&QueueRole::Graphics].clone();
+
+        // Create vulkan allocator
+        let allocator = Arc::new(VulkanAllocation::with_options(device.clone(), config.command_buffers));
 
         // Create vulkan window
-        window_instance.create_swapchain(&device);
+        config.msaa_samples = Self::pick_msaa_samples(device.physical_device(), config.msaa_samples);
+        window_instance.create_swapchain(&device, allocator.general_allocator.clone(), config.present, config.frames_in_flight, config.msaa_samples, config.hdr);
         let vulkan_window = Arc::new(window_instance);
 
-        // Create vulkan allocator
-        let allocator = Arc::new(VulkanAllocation::new(device.clone()));
+        let toolset = VulkanToolset {
+            instance: vulkan_instance,
+            capabilities : DeviceCapabilities::from_device(&device),
+            limits : DeviceLimits::from_physical_device(device.physical_device()),
+            debug_labels : DebugLabelExt::load(&device),
+            push_descriptors : PushDescriptorExt::load(&device),
+            shading_rate : ShadingRateExt::load(&device),
+            conservative_raster : ConservativeRasterExt::load(&device),
+            inline_uniform_blocks : InlineUniformBlockExt::load(&device),
+            conditional_rendering : ConditionalRenderingExt::load(&device),
+            transform_feedback : TransformFeedbackExt::load(&device),
+            logical_device : device,
+            device_queue : queue,
+            queues,
+            memory_allocator : allocator,
+            window: Some(vulkan_window),
+            config,
+        };
+        info!("running on {}", toolset.device_info());
 
-        VulkanToolset {
+        Ok(toolset)
+    }
+
+    // Vulkan version the device runs at, the lowest of the library, the physical device and
+    // InstanceConfig::max_api_version. Features newer than 1.0 should check it before use
+    pub fn api_version(&self) -> Version {
+        self.logical_device.api_version()
+    }
+
+    // Queue created for `role`, the graphics queue when the role wasn't requested
+    pub fn queue(&self, role : QueueRole) -> &Arc<Queue> {
+        self.queues.get(&role).unwrap_or(&self.device_queue)
+    }
+
+    // Toolset without a window or swapchain, for compute and offscreen work
+    pub fn headless(config : AppConfig) -> VulkanToolset {
+        Self::try_headless(config)
+        .unwrap_or_else(|e| panic!("failed to create toolset: {e}"))
+    }
+
+    // Same as headless, returning EngineError::ApiVersion when InstanceConfig::max_api_version can't be met
+    pub fn try_headless(config : AppConfig) -> Result<VulkanToolset, EngineError> {
+        let vulkan_instance = Self::create_instance(None, &config)?;
+        let (device, queues) = Self::create_logical_device(&vulkan_instance, None, &config.device, &config.queues);
+        let queue = queues[&QueueRole::Graphics].clone();
+        let allocator = Arc::new(VulkanAllocation::with_options(device.clone(), config.command_buffers));
+
+        let toolset = VulkanToolset {
             instance: vulkan_instance,
+            capabilities : DeviceCapabilities::from_device(&device),
+            limits : DeviceLimits::from_physical_device(device.physical_device()),
+            debug_labels : DebugLabelExt::load(&device),
+            push_descriptors : PushDescriptorExt::load(&device),
+            shading_rate : ShadingRateExt::load(&device),
+            conservative_raster : ConservativeRasterExt::load(&device),
+            inline_uniform_blocks : InlineUniformBlockExt::load(&device),
+            conditional_rendering : ConditionalRenderingExt::load(&device),
+            transform_feedback : TransformFeedbackExt::load(&device),
             logical_device : device,
             device_queue : queue,
+            queues,
             memory_allocator : allocator,
-            window: vulkan_window
-        }
+            window: None,
+            config,
+        };
+        info!("running on {}", toolset.device_info());
+
+        Ok(toolset)
     }
   
+    // Shorthand for create_pipeline with GraphicsPipelineDesc::new, panics where it would fail
     pub fn create_graphics_pipeline(&self, vs : &Arc<ShaderModule>, fs : &Arc<ShaderModule>) -> Arc<GraphicsPipeline> {
-        let render_pass = self.window.get_render_pass();
-        let viewport = self.window.get_window_viewport();
+        self.create_pipeline(GraphicsPipelineDesc::new(vs.clone(), fs.clone()))
+        .unwrap_or_else(|e| panic!("failed to create graphics pipeline: {e}"))
+    }
 
-        let vs = vs.entry_point("main").unwrap();
-        let fs = fs.entry_point("main").unwrap();
+    // Pipeline built from `desc`. Everything the device or the subpass can't handle is reported here,
+    // before anything reaches the driver. The other vertex pipeline constructors go through it as well
+    pub fn create_pipeline(&self, desc : GraphicsPipelineDesc) -> Result<Arc<GraphicsPipeline>, EngineError> {
+        let render_pass = match (&desc.render_pass_override, &self.window) {
+            (Some(render_pass), _) => render_pass.clone(),
+            (None, Some(window)) => window.get_render_pass(),
+            (None, None) => return Err(EngineError::InvalidPipeline(PipelineDescError::NoRenderPass)),
+        };
+        let subpasses = render_pass.subpasses().len() as u32;
+        let subpass = Subpass::from(render_pass, desc.subpass_index)
+        .ok_or(EngineError::InvalidPipeline(PipelineDescError::NoSubpass { index : desc.subpass_index, subpasses }))?;
+        // A dynamic viewport ignores the baked one
+        let viewport = match (desc.viewport.clone(), &self.window, desc.dynamic_viewport) {
+            (Some(viewport), _, _) => viewport,
+            (None, Some(window), _) => window.get_window_viewport(),
+            (None, None, true) => Viewport::default(),
+            (None, None, false) => return Err(EngineError::InvalidPipeline(PipelineDescError::NoViewport)),
+        };
+        desc.check(&SubpassLayout::of(&subpass))
+        .map_err(EngineError::InvalidPipeline)?;
 
-        let vertex_input_state = VulkanVertex::per_vertex()
-        .definition(&vs.info().input_interface)
-        .unwrap();
+        let vs = desc.vs.as_ref().ok_or(EngineError::InvalidPipeline(PipelineDescError::MissingVertexShader))?;
+        let vs = match desc.vertex_specialization.is_empty() {
+            true => find_entry_point(vs, &desc.entry_points.vertex)?,
+            false => find_specialized_entry_point(&vs.specialize(desc.vertex_specialization.iter().copied().collect())?, &desc.entry_points.vertex)?,
+        };
+        let fs = desc.fs.as_ref()
+        .map(|fs| find_entry_point(fs, &desc.entry_points.fragment))
+        .transpose()?;
 
-        let stages = [
-            PipelineShaderStageCreateInfo::new(vs),
-            PipelineShaderStageCreateInfo::new(fs),
-        ];
+        let vertex_input_state = desc.vertex_layout.definition(&vs.info().input_interface)?;
 
-        let layout = PipelineLayout::new(
-            self.logical_device.clone(),
-            PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
-                .into_pipeline_layout_create_info(self.logical_device.clone())
-                .unwrap(),
-        ).unwrap();
+        // Depth-only subpasses exist to write depth, the others keep depth testing off unless asked for
+        let depth_stencil_state = DepthStencilState {
+            depth: desc.depth.or_else(|| (subpass.num_color_attachments() == 0).then(DepthState::simple)),
+            ..Default::default()
+        };
 
-        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
+        let stages = [Some(vs), fs].into_iter()
+        .flatten()
+        .collect();
 
-        GraphicsPipeline::new(
-            self.logical_device.clone(),
-            None,
-            GraphicsPipelineCreateInfo {
+        let cull = CullConfig { cull : desc.cull, front_face : desc.front_face };
+        let states = PipelineStates {
+            vertex_input_state,
+            input_assembly_state : InputAssemblyState {
+                topology: desc.topology,
+                ..Default::default()
+            },
+            tessellation_state : None,
+            rasterization_state : RasterizationState {
+                polygon_mode: desc.polygon_mode,
+                depth_bias: desc.depth_bias.map(|bias| bias.state(self.logical_device.enabled_features().depth_bias_clamp)),
+                ..cull.rasterization_state()
+            },
+            depth_stencil_state,
+            push_descriptor_set : None,
+            conservative_raster : desc.conservative_raster,
+            multisample : desc.multisample,
+            dynamic_viewport : desc.dynamic_viewport,
+            scissor : desc.scissor,
+            color_write_masks : desc.color_write_masks,
+            blend : desc.blend,
+            immutable_samplers : desc.immutable_samplers,
+            inline_uniform_blocks : desc.inline_uniform_blocks,
+        };
+        self.check_pipeline_states(&states)?;
+
+        let pipeline = self.assemble_pipeline(stages, states, subpass, viewport)?;
+        DebugUtils::name_object(&self.logical_device, &pipeline, "graphics pipeline");
+
+        Ok(pipeline)
+    }
+
+    // Same as create_graphics_pipeline for any render pass, such as one from RenderPassBuilder.
+    // Depth-only subpasses may leave out the fragment shader
+    pub fn create_graphics_pipeline_for(&self, vs : &Arc<ShaderModule>, fs : Option<&Arc<ShaderModule>>, subpass : Subpass, viewport : Viewport) -> Arc<GraphicsPipeline> {
+        self.create_multisampled_pipeline_for(vs, fs, subpass, viewport, MultisampleConfig::default())
+    }
+
+    // Like create_graphics_pipeline_for, with control over how multisampled subpasses are shaded
+    pub fn create_multisampled_pipeline_for(&self, vs : &Arc<ShaderModule>, fs : Option<&Arc<ShaderModule>>, subpass : Subpass, viewport : Viewport, multisample : MultisampleConfig) -> Arc<GraphicsPipeline> {
+        self.create_configured_pipeline_for(vs, fs, subpass, viewport, PipelineOptions { multisample, ..Default::default() })
+    }
+
+    // Like create_graphics_pipeline_for, clipped by a fixed rectangle or one given per draw with Frame::draw_clipped
+    pub fn create_scissored_pipeline_for(&self, vs : &Arc<ShaderModule>, fs : Option<&Arc<ShaderModule>>, subpass : Subpass, viewport : Viewport, scissor : ScissorState) -> Arc<GraphicsPipeline> {
+        self.create_configured_pipeline_for(vs, fs, subpass, viewport, PipelineOptions { scissor, ..Default::default() })
+    }
+
+    // Window pipeline with every option at hand, PipelineOptions::default() is create_graphics_pipeline
+    pub fn create_graphics_pipeline_with(&self, vs : &Arc<ShaderModule>, fs : &Arc<ShaderModule>, options : PipelineOptions) -> Arc<GraphicsPipeline> {
+        self.create_pipeline(GraphicsPipelineDesc::with_options(Some(vs.clone()), Some(fs.clone()), options))
+        .unwrap_or_else(|e| panic!("failed to create graphics pipeline: {e}"))
+    }
+
+    pub fn create_configured_pipeline_for(&self, vs : &Arc<ShaderModule>, fs : Option<&Arc<ShaderModule>>, subpass : Subpass, viewport : Viewport, options : PipelineOptions) -> Arc<GraphicsPipeline> {
+        self.create_pipeline(GraphicsPipelineDesc {
+            render_pass_override : Some(subpass.render_pass().clone()),
+            subpass_index : subpass.index(),
+            viewport : Some(viewport),
+            ..GraphicsPipelineDesc::with_options(Some(vs.clone()), fs.cloned(), options)
+        })
+        .unwrap_or_else(|e| panic!("failed to create graphics pipeline: {e}"))
+    }
+
+    // Window pipeline whose viewport and scissor are set while recording, as Frame::set_viewport_regions does.
+    // Nothing is baked in, so it survives resizes
+    pub fn create_dynamic_viewport_pipeline(&self, vs : &Arc<ShaderModule>, fs : &Arc<ShaderModule>) -> Arc<GraphicsPipeline> {
+        self.create_pipeline(GraphicsPipelineDesc {
+            dynamic_viewport : true,
+            scissor : ScissorState::Dynamic,
+            ..GraphicsPipelineDesc::new(vs.clone(), fs.clone())
+        })
+        .unwrap_or_else(|e| panic!("failed to create graphics pipeline: {e}"))
+    }
+
+    pub fn create_point_pipeline(&self, vs : &Arc<ShaderModule>, fs : &Arc<ShaderModule>, point_size_source : PointSizeSource) -> Arc<GraphicsPipeline> {
+        let vertex_specialization = match point_size_source {
+            PointSizeSource::Fixed(size) => vec![(POINT_SIZE_CONSTANT_ID, self.clamp_point_size(size).into())],
+            PointSizeSource::ShaderControlled => {
+                if !self.logical_device.enabled_features().large_points {
+                    warn!("large_points is not supported, shader controlled point sizes are clamped to 1.0");
+                }
+
+                Vec::new()
+            },
+        };
+
+        let pipeline = self.create_pipeline(GraphicsPipelineDesc {
+            vertex_layout : PointVertex::per_vertex(),
+            topology : PrimitiveTopology::PointList,
+            vertex_specialization,
+            ..GraphicsPipelineDesc::new(vs.clone(), fs.clone())
+        })
+        .unwrap_or_else(|e| panic!("failed to create point pipeline: {e}"));
+        DebugUtils::name_object(&self.logical_device, &pipeline, "point pipeline");
+
+        pipeline
+    }
+
+    // Depth tested, back face culled pipeline for indexed Vertex3D meshes
+    pub fn create_mesh_pipeline(&self, vs : &Arc<ShaderModule>, fs : &Arc<ShaderModule>) -> Arc<GraphicsPipeline> {
+        let window = self.get_vulkan_window();
+
+        self.create_mesh_pipeline_for(vs, fs, Subpass::from(window.get_render_pass(), 0).unwrap(), window.get_window_viewport(), CullConfig::back_faces())
+    }
+
+    // Same as create_mesh_pipeline for any render pass, meshes that aren't closed may need CullConfig::default()
+    pub fn create_mesh_pipeline_for(&self, vs : &Arc<ShaderModule>, fs : &Arc<ShaderModule>, subpass : Subpass, viewport : Viewport, cull : CullConfig) -> Arc<GraphicsPipeline> {
+        let pipeline = self.create_pipeline(GraphicsPipelineDesc {
+            vertex_layout : Vertex3D::per_vertex(),
+            cull : cull.cull,
+            front_face : cull.front_face,
+            depth : Some(DepthState::simple()),
+            viewport : Some(viewport),
+            render_pass_override : Some(subpass.render_pass().clone()),
+            subpass_index : subpass.index(),
+            ..GraphicsPipelineDesc::new(vs.clone(), fs.clone())
+        })
+        .unwrap_or_else(|e| panic!("failed to create mesh pipeline: {e}"));
+        DebugUtils::name_object(&self.logical_device, &pipeline, "mesh pipeline");
+
+        pipeline
+    }
+
+    fn clamp_point_size(&self, size : f32) -> f32 {
+        let range = self.logical_device.physical_device().properties().point_size_range;
+
+        // Without large_points only 1.0 is guaranteed
+        let max_size = match self.logical_device.enabled_features().large_points {
+            true => range[1],
+            false => 1.0,
+        };
+
+        if size > max_size {
+            warn!("point size {size} exceeds the supported maximum {max_size}, clamping");
+            return max_size;
+        }
+
+        size
+    }
+
+    // Reports pipeline options the device cannot run, portability subset devices lack some core features
+    pub fn check_pipeline_support(&self, input_assembly_state : &InputAssemblyState, rasterization_state : &RasterizationState) -> Result<(), EngineError> {
+        let portability_subset = self.logical_device.enabled_extensions().khr_portability_subset;
+        let features = self.logical_device.enabled_features();
+
+        if portability_subset && input_assembly_state.topology == PrimitiveTopology::TriangleFan && !features.triangle_fans {
+            return Err(EngineError::UnsupportedFeature("triangle fans are not supported on this portability subset device".to_owned()));
+        }
+
+        if portability_subset && rasterization_state.polygon_mode == PolygonMode::Point && !features.point_polygons {
+            return Err(EngineError::UnsupportedFeature("point polygon mode is not supported on this portability subset device".to_owned()));
+        }
+
+        if rasterization_state.line_width != 1.0 && !features.wide_lines {
+            return Err(EngineError::UnsupportedFeature(format!("line width {} requires the wide_lines feature", rasterization_state.line_width)));
+        }
+
+        Ok(())
+    }
+
+    // Divisors other than 1 need DeviceCapabilities::vertex_attribute_divisor, 0 needs its own feature on top
+    pub fn check_attribute_divisors(&self, vertex_input_state : &VertexInputState) -> Result<(), EngineError> {
+        for description in divisor_descriptions(vertex_input_state) {
+            if !self.capabilities.vertex_attribute_divisor {
+                return Err(EngineError::UnsupportedFeature(format!("binding {} advances every {} instances, which requires ext_vertex_attribute_divisor", description.binding, description.divisor)));
+            }
+            if description.divisor == 0 && !self.logical_device.enabled_features().vertex_attribute_instance_rate_zero_divisor {
+                return Err(EngineError::UnsupportedFeature(format!("binding {} has a divisor of 0, which requires the vertex_attribute_instance_rate_zero_divisor feature", description.binding)));
+            }
+            self.limits.check_attribute_divisor(description.divisor)?;
+        }
+
+        Ok(())
+    }
+
+    // Conservative rasterization needs ext_conservative_rasterization, overestimating past the primitive
+    // is limited to ConservativeRasterExt::max_extra_overestimation_size
+    pub fn check_conservative_raster(&self, mode : &ConservativeRasterMode) -> Result<(), EngineError> {
+        if !self.capabilities.conservative_rasterization {
+            return Err(EngineError::UnsupportedFeature("conservative rasterization is not supported on this device".to_owned()));
+        }
+
+        let max_size = self.conservative_raster.if_present(|ext| ext.max_extra_overestimation_size).unwrap_or(0.0);
+
+        if mode.mode == ConservativeRasterizationMode::Overestimate && mode.extra_overestimation_size > max_size {
+            return Err(EngineError::UnsupportedFeature(format!("extra overestimation size {} exceeds the supported maximum {max_size}", mode.extra_overestimation_size)));
+        }
+
+        Ok(())
+    }
+
+    pub fn check_multisample_support(&self, multisample : &MultisampleConfig) -> Result<(), EngineError> {
+        let Some(min_fraction) = multisample.sample_shading else {
+            return Ok(());
+        };
+
+        if !self.logical_device.enabled_features().sample_rate_shading {
+            return Err(EngineError::UnsupportedFeature("sample shading requires the sample_rate_shading feature".to_owned()));
+        }
+
+        if !(0.0..=1.0).contains(&min_fraction) {
+            return Err(EngineError::UnsupportedFeature(format!("minimum sample shading fraction {min_fraction} is outside of 0..1")));
+        }
+
+        Ok(())
+    }
+
+    // Pipelines with states GraphicsPipelineDesc doesn't cover, such as tessellation or push descriptors.
+    // Panics where create_pipeline would return an error
+    pub(crate) fn build_pipeline_for(&self, stages : Vec<EntryPoint>, states : PipelineStates, subpass : Subpass, viewport : Viewport) -> Arc<GraphicsPipeline> {
+        self.check_pipeline_states(&states)
+        .and_then(|_| self.assemble_pipeline(stages, states, subpass, viewport))
+        .unwrap_or_else(|e| panic!("failed to create graphics pipeline: {e}"))
+    }
+
+    // What the device has to support for `states`, checks against the subpass are GraphicsPipelineDesc::check
+    fn check_pipeline_states(&self, states : &PipelineStates) -> Result<(), EngineError> {
+        self.check_pipeline_support(&states.input_assembly_state, &states.rasterization_state)?;
+        if let Some(mode) = &states.conservative_raster {
+            self.check_conservative_raster(mode)?;
+        }
+        self.check_multisample_support(&states.multisample)
+    }
+
+    // Hands `states` to vulkano as they are, check_pipeline_states ran before. Conservative rasterization
+    // goes around it, see create_conservative_pipeline
+    fn assemble_pipeline(&self, stages : Vec<EntryPoint>, states : PipelineStates, subpass : Subpass, viewport : Viewport) -> Result<Arc<GraphicsPipeline>, EngineError> {
+        let stages = stages.into_iter()
+        .map(PipelineShaderStageCreateInfo::new)
+        .collect::<Vec<_>>();
+
+        let layout = self.create_pipeline_layout(&stages, states.push_descriptor_set, &states.immutable_samplers, &states.inline_uniform_blocks)?;
+
+        // Depth state is only valid when the subpass has a depth attachment
+        let depth_stencil_state = subpass.subpass_desc()
+        .depth_stencil_attachment
+        .as_ref()
+        .map(|_| states.depth_stencil_state);
+
+        // Fixed rectangles are clamped to the baked viewport, an empty one draws nothing
+        let scissor = match states.scissor {
+            ScissorState::Fixed(rect) => clip_scissor(rect, full_scissor([viewport.extent[0] as u32, viewport.extent[1] as u32]))
+                .unwrap_or(Scissor { offset : [0, 0], extent : [0, 0] }),
+            ScissorState::FullFramebuffer | ScissorState::Dynamic => Scissor::default(),
+        };
+
+        let dynamic_state = [
+            states.dynamic_viewport.then_some(DynamicState::Viewport),
+            (states.dynamic_viewport || states.scissor == ScissorState::Dynamic).then_some(DynamicState::Scissor),
+        ].into_iter().flatten().collect();
+
+        // Likewise for blending, depth-only subpasses have no color attachments to blend
+        let color_blend_state = (subpass.num_color_attachments() > 0).then(|| ColorBlendState {
+            attachments: (0..subpass.num_color_attachments() as usize)
+            .map(|attachment| ColorBlendAttachmentState {
+                color_write_mask: states.color_write_masks.get(attachment).copied().unwrap_or(ColorComponents::all()),
+                blend: states.blend,
+                ..Default::default()
+            })
+            .collect(),
+            ..Default::default()
+        });
+
+        let create_info = GraphicsPipelineCreateInfo {
                 stages: stages.into_iter().collect(),
-                vertex_input_state: Some(vertex_input_state),
-                input_assembly_state: Some(InputAssemblyState::default()),
+                vertex_input_state: Some(states.vertex_input_state),
+                input_assembly_state: Some(states.input_assembly_state),
+                tessellation_state: states.tessellation_state,
                 viewport_state: Some(ViewportState {
-                    viewports: [viewport.clone()].into_iter().collect(),
+                    viewports: [viewport].into_iter().collect(),
+                    scissors: [scissor].into_iter().collect(),
                     ..Default::default()
                 }),
-                rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState::default()),
-                color_blend_state: Some(ColorBlendState::with_attachment_states(
-                    subpass.num_color_attachments(),
-                    ColorBlendAttachmentState::default(),
-                )),
+                dynamic_state,
+                rasterization_state: Some(states.rasterization_state),
+                depth_stencil_state,
+                multisample_state: Some(MultisampleState {
+                    rasterization_samples: subpass.num_samples().unwrap_or(SampleCount::Sample1),
+                    sample_shading: states.multisample.sample_shading,
+                    ..Default::default()
+                }),
+                color_blend_state,
                 subpass: Some(subpass.into()),
                 ..GraphicsPipelineCreateInfo::layout(layout)
-            },
-        ).unwrap()
+            };
+
+        match states.conservative_raster {
+            Some(mode) => create_conservative_pipeline(&self.logical_device, create_info, mode),
+            None => Ok(GraphicsPipeline::new(self.logical_device.clone(), None, create_info)?),
+        }
     }
 
+    // Layout for the given stages. With push descriptor support `push_descriptor_set` is written
+    // through push_descriptors instead of being allocated, otherwise it stays a regular set.
+    // So does a set the shaders don't declare or one with more descriptors than can be pushed
+    pub fn create_pipeline_layout(&self, stages : &[PipelineShaderStageCreateInfo], push_descriptor_set : Option<u32>, immutable_samplers : &[ImmutableSamplers], inline_uniform_blocks : &[InlineUniformBinding]) -> Result<Arc<PipelineLayout>, EngineError> {
+        for stage in stages {
+            if let Some(range) = stage.entry_point.info().push_constant_requirements {
+                self.limits.check_push_constants(range.offset, range.size)?;
+            }
+        }
+
+        let mut layout_info = PipelineDescriptorSetLayoutCreateInfo::from_stages(stages);
+        for samplers in immutable_samplers {
+            samplers.apply(&mut layout_info)?;
+        }
+        for block in inline_uniform_blocks {
+            block.apply(&mut layout_info, block.is_inline(self))?;
+        }
+
+        let push_set = push_descriptor_set.and_then(|set| layout_info.set_layouts.get_mut(set as usize));
+        if let Some(set_layout) = push_set {
+            let descriptor_count = set_layout.bindings.values().map(|binding| binding.descriptor_count).sum::<u32>();
+
+            if self.push_descriptors.if_present(|ext| descriptor_count <= ext.max_push_descriptors).unwrap_or(false) {
+                set_layout.flags |= DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR;
+            }
+        }
+
+        let create_info = layout_info.into_pipeline_layout_create_info(self.logical_device.clone())
+        .map_err(|e| e.error)?;
+
+        Ok(PipelineLayout::new(self.logical_device.clone(), create_info)?)
+    }
+
+    // Binds `writes` as graphics set `set_index`. Sets created as push descriptor sets are pushed
+    // straight into the command buffer, others fall back to allocating a PersistentDescriptorSet
+    pub fn push_descriptors(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, set_index : u32, layout : &Arc<PipelineLayout>, writes : &[WriteDescriptorSet]) -> Result<(), EngineError> {
+        let set_layout = layout.set_layouts()
+        .get(set_index as usize)
+        .ok_or(EngineError::UnknownDescriptorSet(set_index))?;
+
+        if set_layout.flags().intersects(DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR) {
+            if let Some(pushed) = self.push_descriptors.if_present(|ext| ext.push(builder, layout, set_index, writes)) {
+                return pushed;
+            }
+        }
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.memory_allocator.descriptor_set_allocator,
+            set_layout.clone(),
+            writes.iter().cloned(),
+            [],
+        )?;
+        builder.bind_descriptor_sets(PipelineBindPoint::Graphics, layout.clone(), set_index, descriptor_set)?;
+
+        Ok(())
+    }
+
+    // Draws up to max_draw_count commands with the bound pipeline and buffers. Commands past the
+    // GPU side count must have zero instances, IndirectCulling::record clears them for that reason
+    pub fn record_multi_draw_indirect(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, draws : &Subbuffer<[DrawIndirectCommand]>, max_draw_count : u32) -> Result<(), EngineError> {
+        self.record_indirect(builder, draws, max_draw_count, |builder, draws| {
+            builder.draw_indirect(draws)?;
+            Ok(())
+        })
+    }
+
+    // Same as record_multi_draw_indirect with the bound index buffer
+    pub fn record_multi_draw_indexed_indirect(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, draws : &Subbuffer<[DrawIndexedIndirectCommand]>, max_draw_count : u32) -> Result<(), EngineError> {
+        self.record_indirect(builder, draws, max_draw_count, |builder, draws| {
+            builder.draw_indexed_indirect(draws)?;
+            Ok(())
+        })
+    }
+
+    // One command for all draws with multi_draw_indirect, otherwise one command per draw.
+    // Vulkano packs the commands, so the stride is always the command size
+    fn record_indirect<T : BufferContents>(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, draws : &Subbuffer<[T]>, max_draw_count : u32, mut record : impl FnMut(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, Subbuffer<[T]>) -> Result<(), EngineError>) -> Result<(), EngineError> {
+        let draw_count = (max_draw_count as u64).min(draws.len());
+        if draw_count == 0 {
+            return Ok(());
+        }
+        let draws = draws.clone().slice(..draw_count);
+
+        if self.capabilities.multi_draw_indirect {
+            let stride = size_of::<T>() as u32;
+            self.limits.check_indirect_draws(draw_count as u32, stride, stride)?;
+            record(builder, draws)?;
+        } else {
+            for index in 0..draws.len() {
+                record(builder, draws.clone().slice(index..index + 1))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Clears part of a color attachment inside the current render pass, such as the background
+    // behind a HUD. `attachment_index` counts the current subpass's color attachments
+    pub fn record_clear_attachment(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, attachment_index : u32, color : ClearColorValue, rect : ClearRect) -> Result<(), EngineError> {
+        builder.clear_attachments(
+            [ClearAttachment::Color { color_attachment : attachment_index, clear_value : color }].into_iter().collect(),
+            [rect].into_iter().collect(),
+        )?;
+
+        Ok(())
+    }
+
+    // Same as record_clear_attachment for the current subpass's depth attachment
+    pub fn record_clear_depth_attachment(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, depth : f32, rect : ClearRect) -> Result<(), EngineError> {
+        builder.clear_attachments(
+            [ClearAttachment::Depth(depth)].into_iter().collect(),
+            [rect].into_iter().collect(),
+        )?;
+
+        Ok(())
+    }
+
+    // Checks the pipeline against every framebuffer in debug builds, vulkano would only notice at draw time
     pub fn create_command_buffers(&self, vbo : &Subbuffer<[VulkanVertex]>, pipeline : &Arc<GraphicsPipeline>, framebuffers : &Vec<Arc<Framebuffer>>) -> Vec<Arc<PrimaryAutoCommandBuffer>> {
+        if cfg!(debug_assertions) {
+            for framebuffer in framebuffers {
+                if let Err(e) = self.validate_pipeline_framebuffer_compatibility(pipeline, framebuffer) {
+                    panic!("pipeline cannot draw into the framebuffer: {e}");
+                }
+            }
+        }
+
         framebuffers
         .iter()
         .map(|framebuffer| {
@@ -103,7 +616,7 @@ impl VulkanToolset {
             // Fill pipeline with commands
             builder.begin_render_pass(
                 RenderPassBeginInfo {
-                    clear_values: vec![Some([0.1, 0.1, 0.1, 1.0].into())],
+                    clear_values: Self::window_clear_values(framebuffer, self.config.clear_color),
                     ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
                 },
                 SubpassBeginInfo {
@@ -125,31 +638,241 @@ impl VulkanToolset {
         }).collect()
     }
 
+    // Attachment counts, formats and sample counts of the pipeline's render pass against the framebuffer's
+    pub fn validate_pipeline_framebuffer_compatibility(&self, pipeline : &Arc<GraphicsPipeline>, framebuffer : &Arc<Framebuffer>) -> Result<(), EngineError> {
+        let PipelineSubpassType::BeginRenderPass(subpass) = pipeline.subpass() else {
+            return Err(EngineError::IncompatibleRenderPass(RenderPassMismatch::NoRenderPass));
+        };
+
+        compare_attachments(subpass.render_pass().attachments(), framebuffer.render_pass().attachments())
+        .map_err(EngineError::IncompatibleRenderPass)
+    }
+
+    // `stats` wraps the whole frame, compute passes included, in the given query. Draws with an occlusion
+    // query use the given slot of `occlusion`, the slot is reset first.
+    // With viewport regions every draw is replayed once per region, its occlusion query counts the samples of
+    // all of them. Record commands can only run once, after the draws before them were drawn in every region
+    // and with the dynamic state of the last one. They set their own viewport and scissor when they need one
+    pub fn create_frame_command_buffer(&self, allocator : &StandardCommandBufferAllocator, target : FrameTarget, clear_color : [f32; 4], compute_passes : Vec<ComputePass>, commands : Vec<RenderCommand>, regions : &[ViewportRegion], stats : Option<(&PipelineStatsPool, u32)>, occlusion : Option<(&OcclusionQuerySet, u32)>) -> Arc<PrimaryAutoCommandBuffer> {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            allocator,
+            self.device_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+
+        if let Some((pool, query_id)) = stats {
+            pool.begin_stats(&mut builder, query_id);
+        }
+        if let Some((queries, slot)) = occlusion {
+            queries.reset(&mut builder, slot).expect("failed to reset occlusion queries");
+        }
+
+        // Compute work runs first. The builder inserts the barriers against the draws, so buffers written
+        // here can be consumed as vertex or index buffers in the same submission
+        self.debug_labels.labeled(&mut builder, "compute passes", |builder| {
+            for compute_pass in compute_passes {
+                compute_pass(builder);
+            }
+        });
+
+        let framebuffer = match target {
+            FrameTarget::Framebuffer(framebuffer) => framebuffer,
+            FrameTarget::PostProcessed(chain, _) => chain.scene_framebuffer(),
+        };
+        self.debug_labels.labeled(&mut builder, "main pass", |builder| {
+            self.record_main_pass(builder, framebuffer, clear_color, commands, regions, occlusion);
+        });
+
+        if let FrameTarget::PostProcessed(chain, output) = target {
+            self.debug_labels.labeled(&mut builder, "post processing", |builder| {
+                chain.record(self, builder, output).expect("failed to record post processing");
+            });
+        }
+
+        if let Some((pool, query_id)) = stats {
+            pool.end_stats(&mut builder, query_id);
+        }
+
+        builder.build().unwrap()
+    }
+
+    fn record_main_pass(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, framebuffer : &Arc<Framebuffer>, clear_color : [f32; 4], commands : Vec<RenderCommand>, regions : &[ViewportRegion], occlusion : Option<(&OcclusionQuerySet, u32)>) {
+        builder.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: Self::window_clear_values(framebuffer, clear_color),
+                ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
+            },
+            SubpassBeginInfo {
+                contents: SubpassContents::Inline,
+                ..Default::default()
+            },
+        ).unwrap();
+
+        // Replay the draw list collected for this frame, each draw in every region before the next command
+        let extent = framebuffer.extent();
+        let regions : Vec<Option<&ViewportRegion>> = match regions.is_empty() {
+            true => vec![None],
+            false => regions.iter().map(Some).collect(),
+        };
+
+        for command in commands {
+            match command {
+                RenderCommand::Draw(draw) => {
+                    let query = match (draw.occlusion_query, occlusion) {
+                        (Some(id), Some((queries, slot))) => queries.begin(builder, slot, id).unwrap(),
+                        _ => None,
+                    };
+                    for &region in &regions {
+                        self.record_draw(builder, &draw, region, extent);
+                    }
+                    if let (Some(query), Some((queries, _))) = (query, occlusion) {
+                        queries.end(builder, query).unwrap();
+                    }
+                },
+                RenderCommand::Record(record) => record(builder),
+            }
+        }
+
+        builder.end_render_pass(SubpassEndInfo::default()).unwrap();
+    }
+
+    // Dynamic state is set again for every draw, so one draw's scissor doesn't leak into the next
+    fn record_draw(&self, builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, draw : &DrawCall, region : Option<&ViewportRegion>, extent : [u32; 2]) {
+        let dynamic_state = draw.pipeline.dynamic_state();
+        let dynamic_scissor = dynamic_state.contains(&DynamicState::Scissor);
+        assert!(draw.scissor.is_none() || dynamic_scissor, "clipped draws need a pipeline with a dynamic scissor");
+
+        // Clamped to the current framebuffer too, a rectangle from before a resize may reach past it
+        let bounds = region.map_or(full_scissor(extent), |region| region.scissor);
+        let scissor = match dynamic_scissor {
+            true => match clip_scissor(draw.scissor.unwrap_or(bounds), bounds).and_then(|rect| clip_scissor(rect, full_scissor(extent))) {
+                Some(rect) => Some(rect),
+                // Nothing of the draw would be visible
+                None => return,
+            },
+            false => None,
+        };
+
+        builder.bind_pipeline_graphics(draw.pipeline.clone()).unwrap();
+
+        if dynamic_state.contains(&DynamicState::Viewport) {
+            let viewport = region.map_or_else(|| Viewport {
+                offset: [0.0, 0.0],
+                extent: [extent[0] as f32, extent[1] as f32],
+                depth_range: 0.0..=1.0,
+            }, |region| region.viewport.clone());
+            builder.set_viewport(0, [viewport].into_iter().collect()).unwrap();
+        }
+        if let Some(scissor) = scissor {
+            builder.set_scissor(0, [scissor].into_iter().collect()).unwrap();
+        }
+
+        if !draw.descriptor_sets.is_empty() {
+            builder.bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                draw.pipeline.layout().clone(),
+                0,
+                draw.descriptor_sets.clone(),
+            ).unwrap();
+        }
+
+        // Pipelines without push constants don't use the region camera
+        let layout = draw.pipeline.layout();
+        if let Some(region) = region.filter(|_| !layout.push_constant_ranges().is_empty()) {
+            match takes_region_constants(layout.push_constant_ranges()) {
+                true => {
+                    builder.push_constants(layout.clone(), 0, RegionConstants { view_projection : region.camera_uniform }).unwrap();
+                },
+                // Once, it would repeat for every draw of every frame
+                false => if !REGION_CONSTANTS_WARNED.swap(true, Ordering::Relaxed) {
+                    warn!("a pipeline drawn in viewport regions has no vertex stage push constant range at offset 0 for the region camera, drawing it without");
+                },
+            }
+        }
+
+        if let Some(vertex_buffer) = &draw.vertex_buffer {
+            builder.bind_vertex_buffers(0, vertex_buffer.clone()).unwrap();
+        }
+
+        match &draw.indirect {
+            Some(indirect) => self.record_multi_draw_indirect(builder, &indirect.commands, indirect.draw_count).unwrap(),
+            None => {
+                builder.draw(draw.vertex_count, 1, 0, 0).unwrap();
+            },
+        }
+    }
+
+    // Color and depth are cleared, a resolve attachment after them is fully overwritten
+    fn window_clear_values(framebuffer : &Arc<Framebuffer>, clear_color : [f32; 4]) -> Vec<Option<ClearValue>> {
+        let mut clear_values = vec![Some(clear_color.into()), Some(1f32.into())];
+        clear_values.resize(framebuffer.attachments().len(), None);
+        clear_values
+    }
+
     pub fn get_vulkan_window(&self) -> &Arc<VulkanWindow> {
-        &self.window
-    } 
+        self.window.as_ref().expect("toolset is headless, no window available")
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.window.is_none()
+    }
 
-    fn create_instance(event_loop : &EventLoop<()>) -> Arc<Instance> {
+    fn create_instance(event_loop : Option<&EventLoop<()>>, config : &AppConfig) -> Result<Arc<Instance>, EngineError> {
         let library = VulkanLibrary::new().expect("no local Vulkan library/DLL");
-        let required_extensions = Surface::required_extensions(&event_loop);
+        let max_api_version = negotiate_api_version(config.instance.max_api_version, library.api_version())?;
+        let required_extensions = event_loop
+        .map(Surface::required_extensions)
+        .unwrap_or_default();
 
-        Instance::new(
+        // Portability subset drivers (MoltenVK) are only enumerated with these extensions,
+        // debug utils lets validation messages and captures show object names.
+        // Surfaces only list HDR color spaces with swapchain colorspace, it needs no device extension
+        let supported_extensions = library.supported_extensions();
+        let enabled_extensions = InstanceExtensions {
+            khr_portability_enumeration: supported_extensions.khr_portability_enumeration,
+            khr_get_physical_device_properties2: supported_extensions.khr_get_physical_device_properties2,
+            ext_debug_utils: supported_extensions.ext_debug_utils,
+            ext_swapchain_colorspace: config.hdr && supported_extensions.ext_swapchain_colorspace,
+            ..required_extensions
+        };
+
+        // Enable validation layer only when it is installed
+        let mut enabled_layers = Vec::new();
+        if config.validation {
+            let layer_available = library.layer_properties()
+            .map(|mut layers| layers.any(|layer| layer.name() == VALIDATION_LAYER))
+            .unwrap_or(false);
+
+            match layer_available {
+                true => enabled_layers.push(VALIDATION_LAYER.to_owned()),
+                false => warn!("{VALIDATION_LAYER} is not installed, continuing without validation"),
+            }
+        }
+
+        Ok(Instance::new(
             library,
             InstanceCreateInfo {
                 flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
-                enabled_extensions: required_extensions,
+                application_name: Some(config.instance.application_name.clone().unwrap_or_else(|| config.window.title.clone())),
+                application_version: config.instance.application_version,
+                engine_name: Some(config.instance.engine_name.clone()),
+                engine_version: config.instance.engine_version,
+                max_api_version: Some(max_api_version),
+                enabled_extensions,
+                enabled_layers,
                 ..Default::default()
             },
-        ).expect("failed to create instance")
+        ).expect("failed to create instance"))
     }
 
-    fn create_logical_device(instance : &Arc<Instance>, surface : &Arc<Surface>) -> (Arc<Device>, Arc<Queue>) {
+    fn create_logical_device(instance : &Arc<Instance>, surface : Option<&Arc<Surface>>, selection : &DeviceSelection, queue_requests : &[QueueRequest]) -> (Arc<Device>, BTreeMap<QueueRole, Arc<Queue>>) {
+        // Swapchains are only needed when presenting to a surface
         let device_extensions = DeviceExtensions {
-            khr_swapchain: true,
+            khr_swapchain: surface.is_some(),
             ..DeviceExtensions::empty()
         };
 
-        let (physical_device, queue_family_index) = instance
+        let candidates = instance
         .enumerate_physical_devices()
         .expect("could not enumerate devices")
         .filter(|p| p.supported_extensions().contains(&device_extensions))
@@ -159,78 +882,836 @@ impl VulkanToolset {
             .enumerate()
             .position(|(i, q)| {
                 q.queue_flags.contains(QueueFlags::GRAPHICS)
-                && p.surface_support(i as u32, &surface).unwrap_or(false)
+                && surface.map_or(true, |surface| p.surface_support(i as u32, surface).unwrap_or(false))
             })
             .map(|q| (p, q as u32))
-        }).min_by_key(|(p, _)| match  p.properties().device_type {
-            physical::PhysicalDeviceType::DiscreteGpu => 0,
-            physical::PhysicalDeviceType::IntegratedGpu => 1,
-            physical::PhysicalDeviceType::VirtualGpu => 2,
-            physical::PhysicalDeviceType::Cpu => 3,
-            _ => 4,
-        }).expect("no devices available");
+        }).collect::<Vec<_>>();
+
+        let ranked = |low_power : bool| candidates
+        .iter()
+        .min_by_key(|(p, _)| Self::device_rank(p, low_power))
+        .cloned();
+
+        let selected = match selection {
+            DeviceSelection::HighPerformance => ranked(false),
+            DeviceSelection::LowPower => ranked(true),
+            DeviceSelection::Named(name) => candidates
+            .iter()
+            .find(|(p, _)| p.properties().device_name.contains(name.as_str()))
+            .cloned()
+            .or_else(|| {
+                warn!("no suitable device named \"{name}\", falling back to the fastest device");
+                ranked(false)
+            }),
+            DeviceSelection::Index(index) => candidates
+            .get(*index)
+            .cloned()
+            .or_else(|| {
+                warn!("device index {index} is out of range ({} suitable devices), falling back to the fastest device", candidates.len());
+                ranked(false)
+            }),
+        };
 
-        let (device, mut queues) = Device::new(
+        let (physical_device, queue_family_index) = selected.expect("no devices available");
+
+        // The spec requires enabling the subset extension on devices that advertise it
+        let portability_subset = physical_device.supported_extensions().khr_portability_subset;
+        // What the device runs at, the instance may have been capped below the physical device
+        let api_version = physical_device.api_version().min(instance.api_version());
+        // Shading rates depend on render pass 2, which is core from Vulkan 1.2
+        let shading_rate = physical_device.supported_extensions().khr_fragment_shading_rate
+            && api_version >= Version::V1_2;
+        // Inline uniform blocks are core from Vulkan 1.3, the extension is only needed before
+        let inline_uniform_block_ext = api_version < Version::V1_3 && physical_device.supported_extensions().ext_inline_uniform_block;
+        let conditional_rendering = physical_device.supported_extensions().ext_conditional_rendering;
+        let attribute_divisor = physical_device.supported_extensions().ext_vertex_attribute_divisor;
+        let transform_feedback = physical_device.supported_extensions().ext_transform_feedback;
+        // Buffer device addresses are core from Vulkan 1.2, earlier devices would need khr_device_group as well
+        let buffer_device_address = api_version >= Version::V1_2;
+        let device_extensions = DeviceExtensions {
+            khr_portability_subset: portability_subset,
+            khr_push_descriptor: physical_device.supported_extensions().khr_push_descriptor,
+            khr_fragment_shading_rate: shading_rate,
+            ext_conservative_rasterization: physical_device.supported_extensions().ext_conservative_rasterization,
+            ext_inline_uniform_block: inline_uniform_block_ext,
+            ext_conditional_rendering: conditional_rendering,
+            ext_vertex_attribute_divisor: attribute_divisor,
+            ext_transform_feedback: transform_feedback,
+            ..device_extensions
+        };
+
+        // Point clouds need sizes above 1.0, skyboxes need depth clamping, terrain needs tessellation,
+        // blit::resample_image writes storage images without a format qualifier, Frame::draw_queried counts samples,
+        // create_buffer_with_device_address hands shaders buffer references, shadow casters clamp their depth bias,
+        // FrameSync counts finished frames on a TimelineSemaphore, AttributeDivisor shares instance data between instances,
+        // XfbPipeline captures vertex outputs, voxelization writes storage images from fragment shaders.
+        // Subset devices report the parts of core Vulkan they still implement as features
+        let supported_features = physical_device.supported_features();
+        let enabled_features = Features {
+            large_points: supported_features.large_points,
+            depth_clamp: supported_features.depth_clamp,
+            depth_bias_clamp: supported_features.depth_bias_clamp,
+            tessellation_shader: supported_features.tessellation_shader,
+            wide_lines: supported_features.wide_lines,
+            pipeline_statistics_query: supported_features.pipeline_statistics_query,
+            multi_draw_indirect: supported_features.multi_draw_indirect,
+            occlusion_query_precise: supported_features.occlusion_query_precise,
+            sample_rate_shading: supported_features.sample_rate_shading,
+            pipeline_fragment_shading_rate: shading_rate && supported_features.pipeline_fragment_shading_rate,
+            attachment_fragment_shading_rate: shading_rate && supported_features.attachment_fragment_shading_rate,
+            triangle_fans: portability_subset && supported_features.triangle_fans,
+            point_polygons: portability_subset && supported_features.point_polygons,
+            shader_storage_image_write_without_format: supported_features.shader_storage_image_write_without_format,
+            fragment_stores_and_atomics: supported_features.fragment_stores_and_atomics,
+            inline_uniform_block: (inline_uniform_block_ext || api_version >= Version::V1_3) && supported_features.inline_uniform_block,
+            conditional_rendering: conditional_rendering && supported_features.conditional_rendering,
+            inherited_conditional_rendering: conditional_rendering && supported_features.inherited_conditional_rendering,
+            buffer_device_address: buffer_device_address && supported_features.buffer_device_address,
+            timeline_semaphore: api_version >= Version::V1_2 && supported_features.timeline_semaphore,
+            vertex_attribute_instance_rate_divisor: attribute_divisor && supported_features.vertex_attribute_instance_rate_divisor,
+            vertex_attribute_instance_rate_zero_divisor: attribute_divisor && supported_features.vertex_attribute_instance_rate_zero_divisor,
+            transform_feedback: transform_feedback && supported_features.transform_feedback,
+            ..Features::empty()
+        };
+
+        let queue_count = physical_device.queue_family_properties()[queue_family_index as usize].queue_count;
+        let plan = plan_queues(queue_requests, queue_count);
+
+        let (device, queues) = Device::new(
             physical_device,
             DeviceCreateInfo {
                 queue_create_infos: vec![QueueCreateInfo {
                     queue_family_index,
+                    queues: plan.priorities.clone(),
                     ..Default::default()
                 }],
                 enabled_extensions : device_extensions,
+                enabled_features,
                 ..Default::default()
             },
         ).expect("failed to create device");
 
-        let queue = queues.next().unwrap();
+        let queues = queues.collect::<Vec<_>>();
+        let roles = plan.roles.into_iter()
+        .map(|(role, index)| (role, queues[index].clone()))
+        .collect::<BTreeMap<_, _>>();
+
+        // Shared queues are named after the first of their roles
+        for (role, queue) in roles.iter().rev() {
+            DebugUtils::name_object(&device, queue, &format!("{role:?} queue").to_lowercase());
+        }
+
+        (device, roles)
+    }
+
+    // Highest sample count up to `requested` that color and depth attachments both support
+    fn pick_msaa_samples(physical_device : &physical::PhysicalDevice, requested : u32) -> u32 {
+        let properties = physical_device.properties();
+        let supported = properties.framebuffer_color_sample_counts & properties.framebuffer_depth_sample_counts;
+
+        let samples = [64, 32, 16, 8, 4, 2, 1].into_iter()
+        .filter(|&samples| samples <= requested.max(1))
+        .find(|&samples| supported.contains_enum(SampleCount::try_from(samples).unwrap()))
+        .unwrap_or(1);
+
+        if samples != requested.max(1) {
+            warn!("{requested} MSAA samples are not supported, using {samples}");
+        }
+
+        samples
+    }
+
+    fn device_rank(device : &physical::PhysicalDevice, low_power : bool) -> u32 {
+        match (device.properties().device_type, low_power) {
+            (physical::PhysicalDeviceType::DiscreteGpu, false) => 0,
+            (physical::PhysicalDeviceType::IntegratedGpu, false) => 1,
+            (physical::PhysicalDeviceType::IntegratedGpu, true) => 0,
+            (physical::PhysicalDeviceType::DiscreteGpu, true) => 1,
+            (physical::PhysicalDeviceType::VirtualGpu, _) => 2,
+            (physical::PhysicalDeviceType::Cpu, _) => 3,
+            _ => 4,
+        }
+    }
+}
+
+pub(crate) struct PipelineStates {
+    pub vertex_input_state : VertexInputState,
+    pub input_assembly_state : InputAssemblyState,
+    pub tessellation_state : Option<TessellationState>,
+    pub rasterization_state : RasterizationState,
+    pub depth_stencil_state : DepthStencilState,
+    // See create_pipeline_layout
+    pub push_descriptor_set : Option<u32>,
+    // See check_conservative_raster
+    pub conservative_raster : Option<ConservativeRasterMode>,
+    pub multisample : MultisampleConfig,
+    // The viewport is left to set_viewport, the viewport argument is then ignored. Implies a dynamic scissor
+    pub dynamic_viewport : bool,
+    pub scissor : ScissorState,
+    // See PipelineOptions::color_write_masks
+    pub color_write_masks : Vec<ColorComponents>,
+    // Same for every color attachment, see GraphicsPipelineDesc::blend
+    pub blend : Option<AttachmentBlend>,
+    // See PipelineOptions::immutable_samplers
+    pub immutable_samplers : Vec<ImmutableSamplers>,
+    // See PipelineOptions::inline_uniform_blocks
+    pub inline_uniform_blocks : Vec<InlineUniformBinding>,
+}
+
+// Without sample shading a multisampled fragment is shaded once per pixel and only coverage is per sample
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MultisampleConfig {
+    // Minimum fraction of samples shaded separately, 1.0 runs the fragment shader for every sample
+    pub sample_shading : Option<f32>,
+}
+
+impl MultisampleConfig {
+    // Needs the sample_rate_shading feature, see check_multisample_support
+    pub fn with_sample_shading(min_fraction : f32) -> MultisampleConfig {
+        MultisampleConfig {
+            sample_shading : Some(min_fraction),
+        }
+    }
+}
+
+// Which faces are dropped before rasterization. The default culls nothing, as 2D pipelines want
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CullConfig {
+    pub cull : FaceCull,
+    pub front_face : Winding,
+}
+
+impl CullConfig {
+    // Recommended for closed 3D meshes, halves their fill cost and hides inverted winding.
+    // Camera projections flip Y, so faces wound counter clockwise in world space stay counter clockwise
+    pub fn back_faces() -> CullConfig {
+        CullConfig {
+            cull : FaceCull::Back,
+            front_face : Winding::CounterClockwise,
+        }
+    }
+
+    pub(crate) fn rasterization_state(&self) -> RasterizationState {
+        RasterizationState {
+            cull_mode: match self.cull {
+                FaceCull::None => CullMode::None,
+                FaceCull::Back => CullMode::Back,
+                FaceCull::Front => CullMode::Front,
+            },
+            front_face: match self.front_face {
+                Winding::CounterClockwise => FrontFace::CounterClockwise,
+                Winding::Clockwise => FrontFace::Clockwise,
+            },
+            ..Default::default()
+        }
+    }
+}
+
+// Pushes rasterized depth away from the viewer, so surfaces drawn into a shadow map don't shadow themselves
+// when the lit pass compares against it (shadow acne). The constant factor is in steps of the depth format,
+// the slope factor scales with how steeply the primitive's depth changes across a pixel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthBias {
+    pub constant_factor : f32,
+    pub slope_factor : f32,
+    // Largest offset applied, 0.0 leaves it unlimited. Ignored without the depth_bias_clamp feature
+    pub clamp : f32,
+}
 
-        (device, queue)
+impl DepthBias {
+    // A starting point for shadow casters. Raise the slope factor for acne on surfaces at grazing angles,
+    // lower both when shadows detach from their casters (peter panning)
+    pub fn shadow_caster() -> DepthBias {
+        DepthBias {
+            constant_factor : 1.25,
+            slope_factor : 1.75,
+            clamp : 0.0,
+        }
     }
+
+    pub(crate) fn state(&self, clamp_supported : bool) -> DepthBiasState {
+        DepthBiasState {
+            constant_factor: self.constant_factor,
+            clamp: if clamp_supported { self.clamp } else { 0.0 },
+            slope_factor: self.slope_factor,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FaceCull {
+    #[default]
+    None,
+    Back,
+    Front,
+}
+
+// Winding of front faces as seen on screen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Winding {
+    #[default]
+    CounterClockwise,
+    Clockwise,
+}
+
+// Options of the vertex pipelines, the default matches create_graphics_pipeline
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PipelineOptions {
+    pub multisample : MultisampleConfig,
+    pub scissor : ScissorState,
+    pub cull : CullConfig,
+    // Depth offset of the rasterized fragments, see DepthBias
+    pub depth_bias : Option<DepthBias>,
+    // Channels written per color attachment of the subpass, attachments past the end write all of them
+    pub color_write_masks : Vec<ColorComponents>,
+    pub entry_points : EntryPointNames,
+    // Samplers baked into the layout, for textures that are always sampled the same way
+    pub immutable_samplers : Vec<ImmutableSamplers>,
+    // Uniform blocks stored in the descriptor set where the device supports it
+    pub inline_uniform_blocks : Vec<InlineUniformBinding>,
+}
+
+// Entry points the vertex pipelines look up in their shader modules, HLSL and
+// multi-entry SPIR-V often name them something other than "main"
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntryPointNames {
+    pub vertex : String,
+    pub fragment : String,
+}
+
+impl Default for EntryPointNames {
+    fn default() -> Self {
+        EntryPointNames {
+            vertex : "main".to_owned(),
+            fragment : "main".to_owned(),
+        }
+    }
+}
+
+// Execution models searched for the names listed when a lookup fails. vulkano only hands out
+// the entry point of a model when it is the only one, several of one model go unlisted
+const LISTED_EXECUTION_MODELS : [ExecutionModel; 8] = [
+    ExecutionModel::Vertex,
+    ExecutionModel::TessellationControl,
+    ExecutionModel::TessellationEvaluation,
+    ExecutionModel::Geometry,
+    ExecutionModel::Fragment,
+    ExecutionModel::GLCompute,
+    ExecutionModel::TaskEXT,
+    ExecutionModel::MeshEXT,
+];
+
+// Entry point called `name`, the error lists the entry points the module has instead
+pub fn find_entry_point(module : &Arc<ShaderModule>, name : &str) -> Result<EntryPoint, EngineError> {
+    find_specialized_entry_point(&module.specialize(Default::default())?, name)
+}
+
+fn find_specialized_entry_point(module : &Arc<SpecializedShaderModule>, name : &str) -> Result<EntryPoint, EngineError> {
+    module.entry_point(name).ok_or_else(|| EngineError::MissingEntryPoint {
+        name : name.to_owned(),
+        available : LISTED_EXECUTION_MODELS.into_iter()
+        .filter_map(|model| module.single_entry_point_with_execution(model))
+        .map(|entry_point| entry_point.info().name.clone())
+        .collect(),
+    })
+}
+
+pub type ComputePass = Box<dyn FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>)>;
+pub type RecordPass = Box<dyn FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>)>;
+
+// Framebuffer the main pass of a frame renders into
+#[derive(Clone, Copy)]
+pub enum FrameTarget<'a> {
+    Framebuffer(&'a Arc<Framebuffer>),
+    // Into the chain's scene target, its effects then write the framebuffer
+    PostProcessed(&'a PostProcessChain, &'a Arc<Framebuffer>),
+}
+
+pub enum RenderCommand {
+    Draw(DrawCall),
+    // Custom commands recorded inside the render pass
+    Record(RecordPass),
+}
+
+pub struct DrawCall {
+    pub pipeline : Arc<GraphicsPipeline>,
+    // Any vertex type matching the pipeline's vertex input, see Frame::draw
+    pub vertex_buffer : Option<Subbuffer<[u8]>>,
+    pub descriptor_sets : Vec<Arc<PersistentDescriptorSet>>,
+    pub vertex_count : u32,
+    // Needs a pipeline with a dynamic scissor, None covers the whole viewport
+    pub scissor : Option<Scissor>,
+    // Replaces vertex_count with draws read from a buffer, see Frame::draw_indirect
+    pub indirect : Option<IndirectDraws>,
+    // Id the draw's samples are reported under, see Frame::draw_queried
+    pub occlusion_query : Option<u32>,
+}
+
+// Draw parameters that live in a GPU buffer, such as one a compute pass fills
+#[derive(Clone)]
+pub struct IndirectDraws {
+    pub commands : Subbuffer<[DrawIndirectCommand]>,
+    // Commands past the buffer's length are never drawn
+    pub draw_count : u32,
 }
 
 pub struct VulkanAllocation {
     pub general_allocator : Arc<GenericMemoryAllocator<FreeListAllocator>>,
     pub buffer_allocator : StandardCommandBufferAllocator,
+    pub descriptor_set_allocator : StandardDescriptorSetAllocator,
+    // Buffers and images created through the engine's helpers, see stats
+    pub tracker : AllocationTracker,
+    // Buffers defragment may move, see create_relocatable_buffer
+    pub relocatable : RelocationRegistry,
+    // Intermediate images of the post-process chain, bloom and the shadow map
+    pub render_targets : RenderTargetPool,
 }
 
 impl VulkanAllocation {
     pub fn new(device : Arc<Device>) -> VulkanAllocation {
-        let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
+        Self::with_options(device, CommandBufferOptions::default())
+    }
 
-        let command_buffer_allocator = StandardCommandBufferAllocator::new(
-            device.clone(),
-            StandardCommandBufferAllocatorCreateInfo::default(),
-        );
+    pub fn with_options(device : Arc<Device>, command_buffers : CommandBufferOptions) -> VulkanAllocation {
+        let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
+        let command_buffer_allocator = Self::create_command_buffer_allocator(device.clone(), command_buffers);
 
         VulkanAllocation {
+            render_targets : RenderTargetPool::new(memory_allocator.clone()),
             general_allocator : memory_allocator,
             buffer_allocator : command_buffer_allocator,
+            descriptor_set_allocator : StandardDescriptorSetAllocator::new(device, Default::default()),
+            tracker : AllocationTracker::default(),
+            relocatable : RelocationRegistry::default(),
         }
     }
+
+    pub fn create_command_buffer_allocator(device : Arc<Device>, options : CommandBufferOptions) -> StandardCommandBufferAllocator {
+        StandardCommandBufferAllocator::new(
+            device,
+            StandardCommandBufferAllocatorCreateInfo {
+                primary_buffer_count: options.primary_buffer_count,
+                secondary_buffer_count: options.secondary_buffer_count,
+                ..Default::default()
+            },
+        )
+    }
+
+    // Live allocations made through the engine's helpers, by category and heap. The heaps report
+    // their size, vulkano doesn't expose the driver's own usage or budget
+    pub fn stats(&self) -> MemoryStats {
+        self.tracker.stats(self.general_allocator.device().physical_device().memory_properties())
+    }
+
+    // Buffer in the memory type pick_memory_type chooses instead of the one a MemoryTypeFilter would,
+    // for when the caller has to know whether it got ReBAR memory. Host visible types come back mapped
+    pub fn allocate_with_explicit_type(&self, requirements : &MemoryRequirements, usage : BufferUsage, required_props : MemoryPropertyFlags, preferred_props : MemoryPropertyFlags) -> Result<(Subbuffer<[u8]>, MemoryType), EngineError> {
+        let properties = self.general_allocator.device().physical_device().memory_properties();
+        let candidates = MemoryTypeCandidate::from_properties(properties);
+
+        let index = pick_memory_type(&candidates, requirements.memory_type_bits, required_props, preferred_props)
+        .ok_or_else(|| EngineError::UnsupportedFeature(format!("no memory type in {:#b} is {required_props:?}", requirements.memory_type_bits)))?;
+
+        let buffer = Buffer::new(
+            self.general_allocator.clone(),
+            BufferCreateInfo {
+                usage,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter {
+                    required_flags: required_props,
+                    preferred_flags: MemoryPropertyFlags::empty(),
+                    not_preferred_flags: MemoryPropertyFlags::empty(),
+                },
+                memory_type_bits: 1 << index,
+                ..Default::default()
+            },
+            requirements.layout,
+        )?;
+        self.tracker.track_buffer(AllocationCategory::from_usage(usage), &buffer);
+
+        Ok((Subbuffer::new(buffer), properties.memory_types[index as usize].clone()))
+    }
+
+    // Buffer of DrawIndirectCommand or DrawIndexedIndirectCommand filled from the CPU. Compute
+    // passes can write it as a storage buffer, and transfers update it between frames
+    pub fn create_indirect_buffer<T, I>(&self, commands : I) -> Result<Subbuffer<[T]>, EngineError>
+    where
+        T : BufferContents,
+        I : IntoIterator<Item = T>,
+        I::IntoIter : ExactSizeIterator,
+    {
+        let commands = commands.into_iter();
+        if commands.len() == 0 {
+            return Err(EngineError::EmptyBuffer);
+        }
+
+        let usage = BufferUsage::INDIRECT_BUFFER | BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_DST;
+        let buffer = Buffer::from_iter(
+            self.general_allocator.clone(),
+            BufferCreateInfo {
+                usage,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            commands,
+        )?;
+        self.tracker.track_buffer(AllocationCategory::from_usage(usage), buffer.buffer());
+
+        Ok(buffer)
+    }
+
+    // Host written buffer shaders reach through the returned address instead of a descriptor, see
+    // DeviceCapabilities::buffer_device_address. The buffer has to outlive every use of the address
+    pub fn create_buffer_with_device_address<T : BufferContents + Copy>(&self, device : &Arc<Device>, data : &[T]) -> Result<(Subbuffer<[T]>, u64), EngineError> {
+        if !DeviceCapabilities::from_device(device).buffer_device_address {
+            return Err(EngineError::UnsupportedFeature("buffer device addresses need the buffer_device_address feature of Vulkan 1.2".to_owned()));
+        }
+        if data.is_empty() {
+            return Err(EngineError::EmptyBuffer);
+        }
+
+        let usage = BufferUsage::SHADER_DEVICE_ADDRESS | BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST;
+        let buffer = Buffer::from_iter(
+            self.general_allocator.clone(),
+            BufferCreateInfo {
+                usage,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            data.iter().copied(),
+        )?;
+        self.tracker.track_buffer(AllocationCategory::from_usage(usage), buffer.buffer());
+
+        let address = buffer.device_address()?.get();
+
+        Ok((buffer, address))
+    }
+
+    // Formatted view for uniform and storage texel buffer descriptors, see WriteDescriptorSet::buffer_view.
+    // The format has to support every texel buffer usage the buffer was created with
+    pub fn create_buffer_view<T : ?Sized>(&self, buffer : &Subbuffer<T>, format : Format) -> Result<Arc<BufferView>, EngineError> {
+        let usage = buffer.buffer().usage();
+        let required = [
+            (BufferUsage::UNIFORM_TEXEL_BUFFER, FormatFeatures::UNIFORM_TEXEL_BUFFER),
+            (BufferUsage::STORAGE_TEXEL_BUFFER, FormatFeatures::STORAGE_TEXEL_BUFFER),
+        ].into_iter()
+        .filter(|(texel_usage, _)| usage.intersects(*texel_usage))
+        .fold(FormatFeatures::empty(), |features, (_, feature)| features | feature);
+
+        if required.is_empty() {
+            return Err(EngineError::UnsupportedFeature(format!("a {format:?} view of a buffer without texel buffer usage")));
+        }
+        let supported = self.general_allocator.device().physical_device().format_properties(format)?.buffer_features;
+        if !supported.contains(required) {
+            return Err(EngineError::UnsupportedFeature(format!("{format:?} texel buffers with {required:?}, the device supports {supported:?}")));
+        }
+
+        Ok(BufferView::new(buffer.clone(), BufferViewCreateInfo {
+            format,
+            ..Default::default()
+        })?)
+    }
+
+    // Device local buffer of `texel_count` texels with a view of it. `usage` holds the texel buffer
+    // usages, transfers both ways are added for uploads and readback through the view's buffer
+    pub fn create_texel_buffer(&self, format : Format, texel_count : u64, usage : BufferUsage) -> Result<Arc<BufferView>, EngineError> {
+        let usage = usage | BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST;
+        let buffer = Buffer::new_slice::<u8>(
+            self.general_allocator.clone(),
+            BufferCreateInfo {
+                usage,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+            texel_count * format.block_size(),
+        )?;
+        self.tracker.track_buffer(AllocationCategory::from_usage(usage), buffer.buffer());
+
+        self.create_buffer_view(&buffer, format)
+    }
+
+    // View of a single mip level of one array layer, to render into or sample that level alone.
+    // ImageView::new_default covers every level instead
+    pub fn create_mip_view(image : &Arc<Image>, mip_level : u32, array_layer : u32) -> Result<Arc<ImageView>, EngineError> {
+        if mip_level >= image.mip_levels() || array_layer >= image.array_layers() {
+            return Err(EngineError::ImageSubresource { mip_level, array_layer, mip_levels : image.mip_levels(), array_layers : image.array_layers() });
+        }
+
+        let view_type = match image.image_type() {
+            ImageType::Dim1d => ImageViewType::Dim1d,
+            ImageType::Dim2d => ImageViewType::Dim2d,
+            ImageType::Dim3d => ImageViewType::Dim3d,
+        };
+
+        Ok(ImageView::new(image.clone(), ImageViewCreateInfo {
+            view_type,
+            subresource_range: ImageSubresourceRange {
+                aspects: image.format().aspects(),
+                mip_levels: mip_level..mip_level + 1,
+                array_layers: array_layer..array_layer + 1,
+            },
+            ..ImageViewCreateInfo::from_image(image)
+        })?)
+    }
+
+    // Sets every element, the buffer needs TRANSFER_DST usage. Record outside of a render pass
+    pub fn record_fill_buffer(builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, buffer : &Subbuffer<[u32]>, value : u32) -> Result<(), EngineError> {
+        builder.fill_buffer(buffer.clone(), value)?;
+
+        Ok(())
+    }
+
+    // Copies as many elements as the smaller of the two holds, from a TRANSFER_SRC into a TRANSFER_DST buffer
+    pub fn record_copy_buffer<T : BufferContents>(builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, src : &Subbuffer<[T]>, dst : &Subbuffer<[T]>) -> Result<(), EngineError> {
+        builder.copy_buffer(CopyBufferInfo::buffers(src.clone(), dst.clone()))?;
+
+        Ok(())
+    }
+
+    // Clears every mip level and layer of a color image with TRANSFER_DST usage. `layout` is
+    // TransferDstOptimal or General, vulkano transitions the image into it and back
+    pub fn record_clear_image(builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, image : &Arc<Image>, layout : ImageLayout, value : ClearColorValue) -> Result<(), EngineError> {
+        builder.clear_color_image(ClearColorImageInfo {
+            image_layout: layout,
+            clear_value: value,
+            ..ClearColorImageInfo::image(image.clone())
+        })?;
+
+        Ok(())
+    }
+
+    // Same as record_clear_image for depth and stencil images, the stencil value is ignored without a stencil aspect
+    pub fn record_clear_depth_stencil(builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, image : &Arc<Image>, layout : ImageLayout, depth : f32, stencil : u32) -> Result<(), EngineError> {
+        builder.clear_depth_stencil_image(ClearDepthStencilImageInfo {
+            image_layout: layout,
+            clear_value: ClearDepthStencilValue { depth, stencil },
+            ..ClearDepthStencilImageInfo::image(image.clone())
+        })?;
+
+        Ok(())
+    }
+
+    // Copies between images of compatible formats, an empty region list copies the whole of the smaller image
+    pub fn record_copy_image(builder : &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, src : &Arc<Image>, src_layout : ImageLayout, dst : &Arc<Image>, dst_layout : ImageLayout, regions : &[ImageCopy]) -> Result<(), EngineError> {
+        let mut copy_info = CopyImageInfo {
+            src_image_layout: src_layout,
+            dst_image_layout: dst_layout,
+            ..CopyImageInfo::images(src.clone(), dst.clone())
+        };
+        if !regions.is_empty() {
+            copy_info.regions = regions.iter().cloned().collect();
+        }
+
+        builder.copy_image(copy_info)?;
+
+        Ok(())
+    }
+}
+
+// Compute shaders declare `layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;`,
+// the engine specializes the size so dispatches always match the shader
+pub const LOCAL_SIZE_CONSTANT_IDS : [u32; 3] = [0, 1, 2];
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct ElementCount {
+    count : u32,
 }
 
 pub struct ComputeShader {
     pub pipeline : Arc<ComputePipeline>,
+    pub local_size : [u32; 3],
+    pub info : ShaderInfo,
 }
 
 impl ComputeShader {
-    pub fn new(shader : EntryPoint, device : Arc<Device>) -> ComputeShader {
+    pub fn new(module : &Arc<ShaderModule>, local_size : [u32; 3], device : Arc<Device>) -> ComputeShader {
+        Self::with_entry_point(module, "main", local_size, device)
+        .unwrap_or_else(|e| panic!("failed to create compute pipeline: {e}"))
+    }
+
+    // Same as new for modules whose entry point isn't called "main"
+    pub fn with_entry_point(module : &Arc<ShaderModule>, entry_point : &str, local_size : [u32; 3], device : Arc<Device>) -> Result<ComputeShader, EngineError> {
+        DeviceLimits::from_physical_device(device.physical_device()).check_local_size(local_size)?;
+
+        let constants = LOCAL_SIZE_CONSTANT_IDS.into_iter()
+        .zip(local_size)
+        .map(|(id, size)| (id, size.into()))
+        .collect();
+
+        let shader = find_specialized_entry_point(&module.specialize(constants)?, entry_point)?;
+        let info = ShaderInterface::inspect_entry_point(&shader);
+
         let stage = PipelineShaderStageCreateInfo::new(shader);
         let layout = PipelineLayout::new(
             device.clone(),
             PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
                 .into_pipeline_layout_create_info(device.clone())
                 .unwrap(),
-        ).unwrap();
+        )?;
 
         let compute_pipeline = ComputePipeline::new(
             device.clone(),
             None,
             ComputePipelineCreateInfo::stage_layout(stage, layout),
-        ).expect("failed to create compute pipeline");
+        )?;
 
-        ComputeShader {
+        Ok(ComputeShader {
             pipeline : compute_pipeline,
+            local_size,
+            info,
+        })
+    }
+
+    // 2D workgroup sized for this device, see auto_tuned_local_size. Dispatches sized with
+    // group_counts follow whatever size was picked
+    pub fn with_auto_tuned_workgroup(module : &Arc<ShaderModule>, entry_point : &str, device : Arc<Device>, target_invocations : u32) -> Result<ComputeShader, EngineError> {
+        let local_size = DeviceLimits::from_physical_device(device.physical_device()).auto_tuned_local_size(target_invocations);
+
+        Self::with_entry_point(module, entry_point, local_size, device)
+    }
+
+    // Enough workgroups to cover every element, the shader bounds-checks the tail
+    pub fn group_counts(&self, extent : [u32; 3]) -> [u32; 3] {
+        workgroup_counts(extent, self.local_size)
+    }
+
+    // Dispatches one invocation per element and waits for completion.
+    // Shaders with a push constant block receive the element count as its first member
+    pub fn execute(&self, allocator : &VulkanAllocation, queue : &Arc<Queue>, descriptor_set : Arc<PersistentDescriptorSet>, element_count : u32) -> Result<(), EngineError> {
+        self.dispatch(allocator, queue, vec![(0, descriptor_set)], element_count)
+    }
+
+    // Like execute, but for shaders with several sets or bindings. Writes are grouped per set index
+    pub fn execute_with_writes<I>(&self, allocator : &VulkanAllocation, descriptor_set_allocator : &StandardDescriptorSetAllocator, queue : &Arc<Queue>, writes : I, element_count : u32) -> Result<(), EngineError>
+    where
+        I : IntoIterator<Item = (u32, Vec<WriteDescriptorSet>)>,
+    {
+        let descriptor_sets = self.create_descriptor_sets(descriptor_set_allocator, writes)?;
+        self.dispatch(allocator, queue, descriptor_sets, element_count)
+    }
+
+    // Builds one descriptor set per set index, after checking every set and binding
+    // the shader declares was written. Sets with no bindings may be left out
+    pub fn create_descriptor_sets<I>(&self, descriptor_set_allocator : &StandardDescriptorSetAllocator, writes : I) -> Result<Vec<(u32, Arc<PersistentDescriptorSet>)>, EngineError>
+    where
+        I : IntoIterator<Item = (u32, Vec<WriteDescriptorSet>)>,
+    {
+        let set_layouts = self.pipeline.layout().set_layouts();
+
+        let mut grouped : BTreeMap<u32, Vec<WriteDescriptorSet>> = BTreeMap::new();
+        for (set, set_writes) in writes {
+            if set as usize >= set_layouts.len() {
+                return Err(EngineError::UnknownDescriptorSet(set));
+            }
+            grouped.entry(set).or_default().extend(set_writes);
+        }
+
+        let missing_sets = set_layouts.iter()
+        .enumerate()
+        .filter(|(set, layout)| !layout.bindings().is_empty() && !grouped.contains_key(&(*set as u32)))
+        .map(|(set, _)| set as u32)
+        .collect::<Vec<_>>();
+
+        // Taken from the reflected bindings so the error says what each one expects
+        let missing_bindings = self.info.descriptor_bindings.iter()
+        .filter(|declared| grouped.get(&declared.set).is_some_and(|set_writes| !set_writes.iter().any(|write| write.binding() == declared.binding)))
+        .cloned()
+        .collect::<Vec<_>>();
+
+        if !missing_sets.is_empty() || !missing_bindings.is_empty() {
+            return Err(EngineError::MissingDescriptors { sets : missing_sets, bindings : missing_bindings });
+        }
+
+        grouped.into_iter()
+        .map(|(set, set_writes)| {
+            let descriptor_set = PersistentDescriptorSet::new(
+                descriptor_set_allocator,
+                set_layouts[set as usize].clone(),
+                set_writes,
+                [],
+            )?;
+            Ok((set, descriptor_set))
+        })
+        .collect()
+    }
+
+    fn dispatch(&self, allocator : &VulkanAllocation, queue : &Arc<Queue>, descriptor_sets : Vec<(u32, Arc<PersistentDescriptorSet>)>, element_count : u32) -> Result<(), EngineError> {
+        let layout = self.pipeline.layout();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        builder.bind_pipeline_compute(self.pipeline.clone())?;
+
+        // Bound one by one, the set indices don't have to be contiguous
+        for (set, descriptor_set) in descriptor_sets {
+            builder.bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), set, descriptor_set)?;
+        }
+
+        if !layout.push_constant_ranges().is_empty() {
+            builder.push_constants(layout.clone(), 0, ElementCount { count : element_count })?;
+        }
+
+        builder.dispatch(self.group_counts([element_count, 1, 1]))?;
+
+        let command_buffer = builder.build()?;
+
+        sync::now(self.pipeline.device().clone())
+        .then_execute(queue.clone(), command_buffer)?
+        .then_signal_fence_and_flush()?
+        .wait(None)?;
+
+        Ok(())
+    }
+}
+
+// Workgroup of about `target_invocations` invocations laid out as close to square as possible.
+// The count is clamped to the device limit and rounded down to whole subgroups, then shrunk
+// further until it factors into a width and height within the per-dimension limits
+pub fn auto_tuned_local_size(target_invocations : u32, subgroup_size : Option<u32>, max_invocations : u32, max_size : [u32; 3]) -> [u32; 3] {
+    let mut target = target_invocations.clamp(1, max_invocations.max(1));
+    if let Some(subgroup) = subgroup_size.filter(|&subgroup| subgroup > 0 && subgroup <= target) {
+        target -= target % subgroup;
+    }
+
+    for invocations in (1..=target).rev() {
+        // Tallest fitting height up to the square root, width stays the larger side
+        let layout = (1..=invocations)
+        .take_while(|height| height * height <= invocations)
+        .filter(|height| invocations % height == 0)
+        .map(|height| [invocations / height, height, 1])
+        .filter(|size| size[0] <= max_size[0] && size[1] <= max_size[1])
+        .last();
+
+        if let Some(size) = layout {
+            return size;
         }
     }
-}
\ No newline at end of file
+
+    [1, 1, 1]
+}
+
+pub fn workgroup_counts(extent : [u32; 3], local_size : [u32; 3]) -> [u32; 3] {
+    [
+        extent[0].div_ceil(local_size[0]),
+        extent[1].div_ceil(local_size[1]),
+        extent[2].div_ceil(local_size[2]),
+    ]
+}
@@ -1,21 +1,79 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock};
 
-use vulkano::{device::Device, image::{view::ImageView, Image, ImageUsage}, instance::Instance, pipeline::graphics::viewport::Viewport, render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass}, swapchain::{Surface, Swapchain, SwapchainCreateInfo}};
-use winit::{event_loop::EventLoop, window::{Window, WindowBuilder}};
+use log::{info, warn};
+use vulkano::{device::Device, format::Format, image::{view::ImageView, Image, ImageAspects, ImageCreateInfo, ImageLayout, ImageType, ImageUsage, SampleCount}, instance::Instance, memory::{allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator}, MemoryPropertyFlags}, pipeline::graphics::viewport::Viewport, render_pass::{AttachmentLoadOp, AttachmentStoreOp, Framebuffer, FramebufferCreateInfo, RenderPass, SubpassDependency}, swapchain::{PresentMode, Surface, Swapchain, SwapchainCreateInfo}, sync::{AccessFlags, DependencyFlags, PipelineStages}};
+use winit::{dpi::{LogicalSize, PhysicalSize}, event_loop::EventLoop, window::{Window, WindowBuilder}};
+
+use crate::{config::{PresentPreference, WindowConfig}, error::EngineError};
+use super::{debug_utils::DebugUtils, format_utils::{FormatNegotiator, OutputMode}, render_pass::{FramebufferCache, RenderPassBuilder, SubpassConfig}};
+
+// How a render pass attachment is loaded, stored and laid out around the pass
+#[derive(Clone, Copy, Debug)]
+pub struct AttachmentConfig {
+    pub format : Format,
+    pub samples : u32,
+    pub load_op : AttachmentLoadOp,
+    pub store_op : AttachmentStoreOp,
+    pub initial_layout : ImageLayout,
+    pub final_layout : ImageLayout,
+}
+
+impl AttachmentConfig {
+    // Cleared and kept, the main pass color target
+    pub fn color(format : Format) -> AttachmentConfig {
+        AttachmentConfig {
+            format,
+            samples : 1,
+            load_op : AttachmentLoadOp::Clear,
+            store_op : AttachmentStoreOp::Store,
+            initial_layout : ImageLayout::ColorAttachmentOptimal,
+            final_layout : ImageLayout::ColorAttachmentOptimal,
+        }
+    }
+
+    // Cleared and discarded once the pass ends
+    pub fn depth(format : Format) -> AttachmentConfig {
+        AttachmentConfig {
+            format,
+            samples : 1,
+            load_op : AttachmentLoadOp::Clear,
+            store_op : AttachmentStoreOp::DontCare,
+            initial_layout : ImageLayout::DepthStencilAttachmentOptimal,
+            final_layout : ImageLayout::DepthStencilAttachmentOptimal,
+        }
+    }
+
+    // Only depth/stencil and multisampled attachments may drop their contents,
+    // multisampled ones are transient and resolved into another attachment
+    pub fn can_discard(&self) -> bool {
+        self.format.aspects().intersects(ImageAspects::DEPTH | ImageAspects::STENCIL) || self.samples > 1
+    }
+}
 
 pub struct VulkanWindow {
     native_window : Arc<Window>,
     window_surface : Arc<Surface>,
-    window_viewport : Viewport,
+    // Updated from resize and scale factor events, always matches the swapchain extent
+    window_viewport : RwLock<Viewport>,
     window_swapchain : Option<Arc<Swapchain>>,
     window_images : Option<Vec<Arc<Image>>>,
     window_render_pass : Option<Arc<RenderPass>>,
+    window_allocator : Option<Arc<StandardMemoryAllocator>>,
+    window_depth_format : Option<Format>,
+    window_output_mode : OutputMode,
+    // Above 1 the window renders into multisampled images resolved into the swapchain image
+    window_samples : u32,
+    framebuffer_cache : Mutex<FramebufferCache>,
 }
 
 impl VulkanWindow {
-    pub fn new(vulkan_instance : &Arc<Instance>, event_loop : &EventLoop<()>) -> VulkanWindow {
+    pub fn new(vulkan_instance : &Arc<Instance>, event_loop : &EventLoop<()>, config : &WindowConfig) -> VulkanWindow {
         // Create native window
-        let window = Arc::new(WindowBuilder::new().build(&event_loop)
+        let window = Arc::new(WindowBuilder::new()
+        .with_title(config.title.clone())
+        .with_inner_size(LogicalSize::new(config.width, config.height))
+        .with_resizable(config.resizable)
+        .build(&event_loop)
         .unwrap());
 
         // Create window surface
@@ -32,82 +90,257 @@ impl VulkanWindow {
         let vulkan_window = VulkanWindow {
             native_window : window,
             window_surface : surface,
-            window_viewport : viewport,
+            window_viewport : RwLock::new(viewport),
             window_swapchain : None,
             window_images : None,
             window_render_pass : None,
+            window_allocator : None,
+            window_depth_format : None,
+            window_output_mode : OutputMode::Sdr,
+            window_samples : 1,
+            framebuffer_cache : Mutex::new(FramebufferCache::new()),
         };
 
         vulkan_window
     }
 
-    pub fn create_swapchain(&mut self, vulkan_device : &Arc<Device>) -> (Arc<Swapchain>, Vec<Arc<Image>>) {
+    // With `hdr` the swapchain takes an HDR format and color space when the surface offers one, see output_mode
+    pub fn create_swapchain(&mut self, vulkan_device : &Arc<Device>, allocator : Arc<StandardMemoryAllocator>, present : PresentPreference, frames_in_flight : u32, samples : u32, hdr : bool) -> (Arc<Swapchain>, Vec<Arc<Image>>) {
         let caps = vulkan_device.physical_device()
         .surface_capabilities(&self.window_surface, Default::default())
         .expect("failed to get surface capabilities");
 
         let dimensions = self.native_window.inner_size();
         let composite_alpha = caps.supported_composite_alpha.into_iter().next().unwrap();
-        let image_format = vulkan_device.physical_device()
-        .surface_formats(&self.window_surface, Default::default())
-        .unwrap()[0]
-        .0;
+        let (image_format, image_color_space) = FormatNegotiator::pick_surface_format(vulkan_device.physical_device(), &self.window_surface, hdr);
+        let output_mode = OutputMode::from_color_space(image_color_space);
+        info!("presenting {output_mode:?} output as {image_format:?} in {image_color_space:?}");
+        let depth_format = FormatNegotiator::pick_depth_format(vulkan_device.physical_device());
+
+        let present_modes = vulkan_device.physical_device()
+        .surface_present_modes(&self.window_surface, Default::default())
+        .expect("failed to get surface present modes")
+        .collect::<Vec<_>>();
+        let present_mode = Self::pick_present_mode(&present_modes, present);
+
+        // Copied out of for frame recordings where the surface allows it
+        let image_usage = ImageUsage::COLOR_ATTACHMENT | (caps.supported_usage_flags & ImageUsage::TRANSFER_SRC);
+
+        // Keep at least one image per frame in flight
+        let mut min_image_count = (caps.min_image_count + 1).max(frames_in_flight);
+        if let Some(max_image_count) = caps.max_image_count {
+            min_image_count = min_image_count.min(max_image_count);
+        }
 
         let (swapchain, images) = Swapchain::new(
             vulkan_device.clone(),
             self.window_surface.clone(),
             SwapchainCreateInfo {
-                min_image_count: caps.min_image_count + 1, // How many buffers to use in the swapchain
+                min_image_count, // How many buffers to use in the swapchain
                 image_format,
+                image_color_space,
                 image_extent: dimensions.into(),
-                image_usage: ImageUsage::COLOR_ATTACHMENT, // What the images are going to be used for
+                image_usage, // What the images are going to be used for
                 composite_alpha,
+                present_mode,
                 ..Default::default()
             },
         ).unwrap();
 
-        let render_pass = vulkano::single_pass_renderpass!(
-            vulkan_device.clone(),
-            attachments: {
-                color: {
-                    format: swapchain.image_format(),
-                    samples: 1,
-                    load_op: Clear,
-                    store_op: Store,
-                },
-            },
-            pass: {
-                color: [color],
-                depth_stencil: {},
-            },
-        ).unwrap();
+        DebugUtils::name_object(vulkan_device, &swapchain, "swapchain");
+        for (i, image) in images.iter().enumerate() {
+            DebugUtils::name_object(vulkan_device, image.as_ref(), &format!("swapchain image {i}"));
+        }
 
+        let render_pass = Self::render_pass_builder(swapchain.image_format(), depth_format, samples, ImageLayout::PresentSrc)
+        .build(vulkan_device)
+        .expect("failed to create render pass");
+
+        self.framebuffer_cache.get_mut().unwrap().invalidate();
         self.window_swapchain = Some(swapchain.clone());
         self.window_images = Some(images.clone());
         self.window_render_pass = Some(render_pass.clone());
+        self.window_allocator = Some(allocator);
+        self.window_depth_format = Some(depth_format);
+        self.window_output_mode = output_mode;
+        self.window_samples = samples;
 
         (self.window_swapchain.clone().unwrap(), self.window_images.clone().unwrap())
     }
 
-    pub fn create_framebuffers(&self, images : Vec<Arc<Image>>) -> Vec<Arc<Framebuffer>> {
-        images.iter()
-        .map(|image| {
-            let view = ImageView::new_default(image.clone()).unwrap();
+    // Color at 0 and depth at 1. With more than one sample both are multisampled and transient,
+    // and color is resolved into attachment 2, which ends the pass in `resolve_layout`
+    pub fn render_pass_builder(color_format : Format, depth_format : Format, samples : u32, resolve_layout : ImageLayout) -> RenderPassBuilder {
+        // Only the resolved image is kept, the samples are dropped once the pass ends
+        let builder = RenderPassBuilder::new()
+        .attachment(AttachmentConfig {
+            samples,
+            store_op : match samples {
+                1 => AttachmentStoreOp::Store,
+                _ => AttachmentStoreOp::DontCare,
+            },
+            ..AttachmentConfig::color(color_format)
+        })
+        .attachment(AttachmentConfig {
+            samples,
+            ..AttachmentConfig::depth(depth_format)
+        });
+
+        if samples == 1 {
+            return builder.subpass(&[0], Some(1));
+        }
+
+        builder
+        .attachment(AttachmentConfig {
+            load_op : AttachmentLoadOp::DontCare,
+            initial_layout : ImageLayout::Undefined,
+            final_layout : resolve_layout,
+            ..AttachmentConfig::color(color_format)
+        })
+        .subpass_config(SubpassConfig {
+            color : vec![0],
+            depth : Some(1),
+            depth_read_only : false,
+            input : Vec::new(),
+            resolve : vec![2],
+        })
+    }
+
+    // Attachments in render_pass_builder order for a framebuffer presenting `image`
+    pub fn create_attachments(allocator : &Arc<StandardMemoryAllocator>, image : &Arc<Image>, depth_format : Format, samples : u32) -> Vec<Arc<ImageView>> {
+        let sample_count = SampleCount::try_from(samples).expect("unsupported sample count");
+        let transient_image = |format, usage| {
+            let image = Image::new(
+                allocator.clone(),
+                ImageCreateInfo {
+                    image_type: ImageType::Dim2d,
+                    format,
+                    extent: image.extent(),
+                    samples: sample_count,
+                    usage: usage | ImageUsage::TRANSIENT_ATTACHMENT,
+                    ..Default::default()
+                },
+                AllocationCreateInfo::default(),
+            ).unwrap();
+
+            ImageView::new_default(image).unwrap()
+        };
+
+        let view = ImageView::new_default(image.clone()).unwrap();
+        // Every framebuffer owns its depth image, sized after the color image
+        let depth_view = transient_image(depth_format, ImageUsage::DEPTH_STENCIL_ATTACHMENT);
+
+        match samples {
+            1 => vec![view, depth_view],
+            _ => vec![transient_image(image.format(), ImageUsage::COLOR_ATTACHMENT), depth_view, view],
+        }
+    }
+
+    // Color at 0 and depth at 1. Subpass 0 only writes depth, subpass 1 shades color with that depth
+    // tested read-only for early-Z rejection, and readable as input attachment 0. Depth never leaves the
+    // pass, so tile-based GPUs keep it in tile memory instead of storing it and loading it back from DRAM
+    pub fn create_split_depth_color_render_pass(device : &Arc<Device>, color_format : Format, depth_format : Format, color_final_layout : ImageLayout) -> Result<Arc<RenderPass>, EngineError> {
+        RenderPassBuilder::new()
+        .attachment(AttachmentConfig {
+            initial_layout : ImageLayout::Undefined,
+            final_layout : color_final_layout,
+            ..AttachmentConfig::color(color_format)
+        })
+        .attachment(AttachmentConfig {
+            initial_layout : ImageLayout::Undefined,
+            ..AttachmentConfig::depth(depth_format)
+        })
+        .subpass(&[], Some(1))
+        .subpass_config(SubpassConfig {
+            color : vec![0],
+            depth : Some(1),
+            depth_read_only : true,
+            input : vec![1],
+            resolve : Vec::new(),
+        })
+        // Depth written by subpass 0 is complete before subpass 1 tests it and its fragment shaders read it,
+        // per region so a tiler doesn't have to finish the whole image first
+        .dependency(SubpassDependency {
+            src_subpass: Some(0),
+            dst_subpass: Some(1),
+            src_stages: PipelineStages::EARLY_FRAGMENT_TESTS | PipelineStages::LATE_FRAGMENT_TESTS,
+            dst_stages: PipelineStages::EARLY_FRAGMENT_TESTS | PipelineStages::FRAGMENT_SHADER,
+            src_access: AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            dst_access: AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | AccessFlags::INPUT_ATTACHMENT_READ,
+            dependency_flags: DependencyFlags::BY_REGION,
+            ..Default::default()
+        })
+        .build(device)
+    }
+
+    // Depth for create_split_depth_color_render_pass. Its contents are dropped when the pass ends, so it can be
+    // a transient attachment in LAZILY_ALLOCATED memory: tile-based GPUs only commit memory for it if the
+    // tiles overflow, which they usually don't. Devices without such memory fall back to device local memory
+    pub fn create_split_depth_image(allocator : &Arc<StandardMemoryAllocator>, extent : [u32; 2], depth_format : Format) -> Result<Arc<ImageView>, EngineError> {
+        let image = Image::new(
+            allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: depth_format,
+                extent: [extent[0], extent[1], 1],
+                usage: ImageUsage::DEPTH_STENCIL_ATTACHMENT | ImageUsage::INPUT_ATTACHMENT | ImageUsage::TRANSIENT_ATTACHMENT,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter {
+                    preferred_flags: MemoryPropertyFlags::DEVICE_LOCAL | MemoryPropertyFlags::LAZILY_ALLOCATED,
+                    ..MemoryTypeFilter::PREFER_DEVICE
+                },
+                ..Default::default()
+            },
+        )?;
+
+        Ok(ImageView::new_default(image)?)
+    }
+
+    fn pick_present_mode(supported : &[PresentMode], preference : PresentPreference) -> PresentMode {
+        let wanted : &[PresentMode] = match preference {
+            PresentPreference::Fifo => &[PresentMode::Fifo],
+            PresentPreference::Mailbox => &[PresentMode::Mailbox, PresentMode::Fifo],
+            PresentPreference::Immediate => &[PresentMode::Immediate, PresentMode::Mailbox, PresentMode::Fifo],
+        };
+
+        // Fifo support is required by the spec, so there is always a match
+        let present_mode = wanted.iter()
+        .copied()
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(PresentMode::Fifo);
+
+        if present_mode != wanted[0] {
+            warn!("present mode {:?} is not supported, falling back to {:?}", wanted[0], present_mode);
+        }
+
+        present_mode
+    }
+
+    // Cached until the swapchain images change, repeated calls return the same framebuffers
+    pub fn create_framebuffers(&self, images : &[Arc<Image>]) -> Vec<Arc<Framebuffer>> {
+        let allocator = self.window_allocator.clone().expect("Framebuffer retrieve empty allocator!");
+        let depth_format = self.window_depth_format.expect("Framebuffer retrieve empty depth format!");
+        let render_pass = self.window_render_pass.clone().expect("Framebuffer retrieve empty render pass!");
+
+        self.framebuffer_cache.lock().unwrap().get_or_create(&render_pass, images, |image| {
             Framebuffer::new(
-                self.window_render_pass.clone().expect("Framebuffer retrieve empty render pass!"),
+                render_pass.clone(),
                 FramebufferCreateInfo {
-                    attachments: vec![view],
+                    attachments: Self::create_attachments(&allocator, image, depth_format, self.window_samples),
                     ..Default::default()
                 },
             ).unwrap()
-        }).collect::<Vec<_>>()
+        })
     }
 
-    pub fn get_swapchain(&self) -> (Arc<Swapchain>, Vec<Arc<Image>>) {
-        match (self.window_swapchain.clone(), self.window_images.clone()) {
-            (Some(swapchain), Some(images)) => (swapchain, images),
-            _ => panic!("Swapchain is empty!"),
-        }
+    pub fn get_swapchain(&self) -> Arc<Swapchain> {
+        self.window_swapchain.clone().expect("Swapchain is empty!")
+    }
+
+    pub fn get_swapchain_images(&self) -> &[Arc<Image>] {
+        self.window_images.as_deref().expect("Swapchain images retrieve empty!")
     }
 
     pub fn get_render_pass(&self) -> Arc<RenderPass> {
@@ -117,6 +350,22 @@ impl VulkanWindow {
         }
     }
 
+    pub fn get_samples(&self) -> u32 {
+        self.window_samples
+    }
+
+    // How the swapchain images are encoded, the last pass writing them has to match it
+    pub fn output_mode(&self) -> OutputMode {
+        self.window_output_mode
+    }
+
+    pub fn get_depth_format(&self) -> Format {
+        match self.window_depth_format {
+            Some(format) => format,
+            None => panic!("Depth format is empty"),
+        }
+    }
+
     pub fn get_native_window(&self) -> Arc<Window> {
         self.native_window.clone()
     }
@@ -126,6 +375,24 @@ impl VulkanWindow {
     }
 
     pub fn get_window_viewport(&self) -> Viewport {
-        self.window_viewport.clone()
+        self.window_viewport.read().unwrap().clone()
+    }
+
+    pub fn update_viewport(&self, size : PhysicalSize<u32>) {
+        self.window_viewport.write().unwrap().extent = size.into();
+    }
+
+    // Size in device pixels, what the swapchain and viewport use
+    pub fn physical_size(&self) -> PhysicalSize<u32> {
+        self.native_window.inner_size()
+    }
+
+    // Size in scale independent pixels, for laying out UI
+    pub fn logical_size(&self) -> LogicalSize<f64> {
+        self.physical_size().to_logical(self.scale_factor())
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.native_window.scale_factor()
     }
 }
\ No newline at end of file
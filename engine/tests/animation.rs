@@ -0,0 +1,57 @@
+use std::f32::consts::FRAC_PI_2;
+
+use engine::scene::{animation::{Bone, Skeleton}, camera::Matrix4, transform::{Quat, Transform, Vec3}};
+
+fn apply(matrix : &Matrix4, point : Vec3) -> Vec3 {
+    let [x, y, z] = [0, 1, 2].map(|row| matrix[0][row] * point.x + matrix[1][row] * point.y + matrix[2][row] * point.z + matrix[3][row]);
+    Vec3::new(x, y, z)
+}
+
+fn assert_vec(actual : Vec3, expected : Vec3) {
+    assert!(actual.abs_diff_eq(expected, 1e-5), "{actual:?} != {expected:?}");
+}
+
+// Root at the origin and a child one unit up the Y axis, both pointing up at bind time
+fn two_bone_chain() -> Skeleton {
+    Skeleton::new(vec![
+        Bone::new(-1, Transform::IDENTITY.matrix(), Transform::IDENTITY),
+        Bone::new(0, Transform::from_translation(Vec3::new(0.0, -1.0, 0.0)).matrix(), Transform::from_translation(Vec3::Y)),
+    ])
+}
+
+#[test]
+fn bind_pose_skinning_matrices_are_identity() {
+    let skeleton = two_bone_chain();
+
+    for matrix in skeleton.compute_skinning_matrices() {
+        assert_vec(apply(&matrix, Vec3::new(0.3, 1.7, -2.0)), Vec3::new(0.3, 1.7, -2.0));
+    }
+}
+
+#[test]
+fn children_follow_their_parents_chain() {
+    let mut skeleton = two_bone_chain();
+    let quarter_turn = Quat::from_rotation_z(FRAC_PI_2);
+    skeleton.bones[0].local_transform.rotation = quarter_turn;
+    skeleton.bones[1].local_transform.rotation = quarter_turn;
+
+    let globals = skeleton.global_matrices();
+    // The child's joint swings from +Y to -X
+    assert_vec(apply(&globals[1], Vec3::ZERO), Vec3::new(-1.0, 0.0, 0.0));
+
+    let matrices = skeleton.compute_skinning_matrices();
+    // The joint itself, bound to the root, turns a quarter
+    assert_vec(apply(&matrices[0], Vec3::Y), Vec3::new(-1.0, 0.0, 0.0));
+    // The tip of the child turns a half in total, around the moved joint
+    assert_vec(apply(&matrices[1], Vec3::new(0.0, 2.0, 0.0)), Vec3::new(-1.0, -1.0, 0.0));
+    assert_vec(apply(&matrices[1], Vec3::Y), Vec3::new(-1.0, 0.0, 0.0));
+}
+
+#[test]
+#[should_panic(expected = "must come after its parent")]
+fn parents_must_come_first() {
+    Skeleton::new(vec![
+        Bone::new(1, Transform::IDENTITY.matrix(), Transform::IDENTITY),
+        Bone::new(-1, Transform::IDENTITY.matrix(), Transform::IDENTITY),
+    ]);
+}
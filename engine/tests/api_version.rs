@@ -0,0 +1,34 @@
+use engine::{vulkan::api_version::negotiate_api_version, EngineError, InstanceConfig};
+use vulkano::Version;
+
+#[test]
+fn no_request_takes_the_supported_version() {
+    assert_eq!(negotiate_api_version(None, Version::V1_3).unwrap(), Version::V1_3);
+}
+
+#[test]
+fn requests_up_to_the_supported_version_are_kept() {
+    assert_eq!(negotiate_api_version(Some(Version::V1_1), Version::V1_3).unwrap(), Version::V1_1);
+    assert_eq!(negotiate_api_version(Some(Version::V1_3), Version::V1_3).unwrap(), Version::V1_3);
+}
+
+#[test]
+fn requests_past_the_supported_version_fail() {
+    let error = negotiate_api_version(Some(Version::V1_3), Version::V1_2).unwrap_err();
+
+    assert!(matches!(error, EngineError::ApiVersion { requested : Version::V1_3, supported : Version::V1_2 }));
+    assert!(error.to_string().contains("1.3.0"), "{error}");
+}
+
+#[test]
+fn requests_below_vulkan_1_0_fail() {
+    assert!(negotiate_api_version(Some(Version::major_minor(0, 9)), Version::V1_3).is_err());
+}
+
+#[test]
+fn engine_version_follows_the_crate() {
+    let config = InstanceConfig::default();
+
+    assert_eq!(config.engine_name, "RustEngine");
+    assert_eq!(config.engine_version.to_string(), env!("CARGO_PKG_VERSION"));
+}
@@ -0,0 +1,27 @@
+use engine::vulkan::vulkan_window::AttachmentConfig;
+use vulkano::{format::Format, render_pass::AttachmentStoreOp};
+
+#[test]
+fn depth_attachments_may_be_discarded() {
+    assert!(AttachmentConfig::depth(Format::D32_SFLOAT).can_discard());
+    assert!(AttachmentConfig::depth(Format::D24_UNORM_S8_UINT).can_discard());
+}
+
+#[test]
+fn single_sampled_color_must_be_kept() {
+    let color = AttachmentConfig::color(Format::B8G8R8A8_SRGB);
+
+    assert_eq!(color.store_op, AttachmentStoreOp::Store);
+    assert!(!color.can_discard());
+}
+
+#[test]
+fn multisampled_color_is_transient() {
+    let color = AttachmentConfig {
+        samples : 4,
+        store_op : AttachmentStoreOp::DontCare,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    };
+
+    assert!(color.can_discard());
+}
@@ -0,0 +1,31 @@
+use engine::vulkan::blit::{letterbox_region, BlitFit};
+
+#[test]
+fn stretching_fills_the_destination() {
+    assert_eq!(BlitFit::Stretch.region([1920, 1080], [256, 256]), ([0, 0], [256, 256]));
+    assert_eq!(BlitFit::default(), BlitFit::Stretch);
+}
+
+#[test]
+fn wider_sources_get_bars_above_and_below() {
+    assert_eq!(letterbox_region([1920, 1080], [256, 256]), ([0, 56], [256, 144]));
+    assert_eq!(letterbox_region([2048, 1024], [300, 300]), ([0, 75], [300, 150]));
+}
+
+#[test]
+fn taller_sources_get_bars_left_and_right() {
+    assert_eq!(letterbox_region([1024, 1024], [256, 128]), ([64, 0], [128, 128]));
+    assert_eq!(letterbox_region([1080, 1920], [1920, 1080]), ([656, 0], [608, 1080]));
+}
+
+#[test]
+fn matching_aspect_ratios_fill_the_destination() {
+    assert_eq!(letterbox_region([1024, 1024], [256, 256]), ([0, 0], [256, 256]));
+    assert_eq!(BlitFit::Letterbox.region([1920, 1080], [1280, 720]), ([0, 0], [1280, 720]));
+}
+
+#[test]
+fn extreme_ratios_keep_at_least_one_pixel() {
+    assert_eq!(letterbox_region([10_000, 1], [64, 64]), ([0, 31], [64, 1]));
+    assert_eq!(letterbox_region([1, 10_000], [64, 64]), ([31, 0], [1, 64]));
+}
@@ -0,0 +1,12 @@
+use engine::render::bloom::bloom_level_extents;
+
+#[test]
+fn levels_start_at_half_resolution_and_halve_four_times() {
+    assert_eq!(bloom_level_extents([1920, 1080]), vec![[960, 540], [480, 270], [240, 135], [120, 67], [60, 33]]);
+}
+
+#[test]
+fn tiny_images_keep_every_level_at_least_one_pixel() {
+    assert_eq!(bloom_level_extents([8, 2]), vec![[4, 1], [2, 1], [1, 1], [1, 1], [1, 1]]);
+    assert_eq!(bloom_level_extents([1, 1]), vec![[1, 1]; 5]);
+}
@@ -0,0 +1,77 @@
+use engine::scene::camera::{multiply, Camera, Matrix4};
+
+const IDENTITY : Matrix4 = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+fn transform(matrix : &Matrix4, point : [f32; 3]) -> [f32; 3] {
+    let [x, y, z] = point;
+    let clip = (0..4)
+    .map(|row| matrix[0][row] * x + matrix[1][row] * y + matrix[2][row] * z + matrix[3][row])
+    .collect::<Vec<_>>();
+
+    [clip[0] / clip[3], clip[1] / clip[3], clip[2] / clip[3]]
+}
+
+fn assert_close(actual : [f32; 3], expected : [f32; 3]) {
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-5, "{actual:?} != {expected:?}");
+    }
+}
+
+#[test]
+fn identity_is_neutral_for_multiplication() {
+    let camera = Camera::perspective([1.0, 2.0, 3.0], [0.0, 0.0, 0.0], 1.0);
+    let view = camera.view_matrix();
+
+    assert_eq!(multiply(&IDENTITY, &view), view);
+    assert_eq!(multiply(&view, &IDENTITY), view);
+}
+
+#[test]
+fn view_moves_target_onto_negative_z() {
+    let camera = Camera::perspective([0.0, 0.0, 5.0], [0.0, 0.0, 0.0], 1.0);
+
+    assert_close(transform(&camera.view_matrix(), [0.0, 0.0, 0.0]), [0.0, 0.0, -5.0]);
+}
+
+#[test]
+fn top_down_maps_ground_plane_to_screen() {
+    let camera = Camera::top_down(5.0, 1.0);
+    let view_projection = camera.view_projection(1.0);
+
+    // -Z is the top of the screen, which is -Y in Vulkan clip space
+    assert_close(transform(&view_projection, [0.0, 0.0, 0.0]), [0.0, 0.0, 0.5]);
+    assert_close(transform(&view_projection, [1.0, 0.0, -1.0]), [1.0, -1.0, 0.5]);
+
+    // Higher ground is closer to the camera
+    assert!(transform(&view_projection, [0.0, 1.0, 0.0])[2] < 0.5);
+}
+
+#[test]
+fn directional_light_fits_the_sphere_into_clip_space() {
+    let camera = Camera::directional_light([0.0, -1.0, -1.0], [1.0, 0.0, 0.0], 2.0);
+    let view_projection = camera.view_projection(1.0);
+
+    // The center is halfway into the depth range, the sphere's ends along the light touch its limits
+    assert_close(transform(&view_projection, [1.0, 0.0, 0.0]), [0.0, 0.0, 0.5]);
+    let step = 2.0 / 2f32.sqrt();
+    assert_close(transform(&view_projection, [1.0, step, step]), [0.0, 0.0, 0.0]);
+    assert_close(transform(&view_projection, [1.0, -step, -step]), [0.0, 0.0, 1.0]);
+
+    // Across the light the radius maps to the edges
+    assert_close(transform(&view_projection, [3.0, 0.0, 0.0]), [1.0, 0.0, 0.5]);
+}
+
+#[test]
+fn directional_light_straight_down_has_a_valid_view() {
+    let camera = Camera::directional_light([0.0, -1.0, 0.0], [0.0, 0.0, 0.0], 1.0);
+    let view_projection = camera.view_projection(1.0);
+
+    assert!(view_projection.iter().flatten().all(|value| value.is_finite()));
+    assert_close(transform(&view_projection, [0.0, 0.0, 0.0]), [0.0, 0.0, 0.5]);
+    assert_close(transform(&view_projection, [0.0, -1.0, 0.0]), [0.0, 0.0, 1.0]);
+}
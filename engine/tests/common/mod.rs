@@ -0,0 +1,250 @@
+// Shared by the gpu_* tests, each of them only uses part of it
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use engine::{vulkan::vulkan::ComputeShader, AppConfig, ImageData, VulkanToolset};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    instance::{Instance, InstanceCreateFlags, InstanceCreateInfo},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{Pipeline, PipelineBindPoint},
+    sync::{self, GpuFuture},
+    VulkanLibrary
+};
+
+pub const SCREENSHOT_SIZE : u32 = 64;
+
+pub mod multiply_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) buffer Data {
+                uint data[];
+            } buf;
+
+            layout(push_constant) uniform Elements {
+                uint count;
+            } elements;
+
+            void main() {
+                uint idx = gl_GlobalInvocationID.x;
+                if (idx >= elements.count) {
+                    return;
+                }
+
+                buf.data[idx] *= 13;
+            }
+        ",
+    }
+}
+
+pub mod mandelbrot_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        include: ["assets/shaders/include"],
+        src: r#"
+            #version 460
+
+            #include "math.glsl"
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;
+
+            void main() {
+                vec2 norm_coordinates = (gl_GlobalInvocationID.xy + vec2(0.5)) / vec2(imageSize(img));
+                vec2 c = (norm_coordinates - vec2(0.5)) * 2.0 - vec2(1.0, 0.0);
+
+                vec2 z = vec2(0.0, 0.0);
+                float i;
+                for (i = 0.0; i < 1.0; i += 0.005) {
+                    z = complex_square(z) + c;
+
+                    if (length(z) > 4.0) {
+                        break;
+                    }
+                }
+
+                vec4 to_write = vec4(vec3(i), 1.0);
+                imageStore(img, ivec2(gl_GlobalInvocationID.xy), to_write);
+            }
+        "#,
+    }
+}
+
+pub mod gather_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) readonly buffer Source {
+                uint source[];
+            };
+
+            layout(set = 0, binding = 1) readonly buffer Indices {
+                uint indices[];
+            };
+
+            layout(set = 1, binding = 2) writeonly buffer Destination {
+                uint destination[];
+            };
+
+            layout(push_constant) uniform Elements {
+                uint count;
+            } elements;
+
+            void main() {
+                uint idx = gl_GlobalInvocationID.x;
+                if (idx >= elements.count) {
+                    return;
+                }
+
+                destination[idx] = source[indices[idx]];
+            }
+        ",
+    }
+}
+
+pub mod fullscreen_vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            void main() {
+                vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+                gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+            }
+        ",
+    }
+}
+
+pub mod uniform_color_fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(set = 0, binding = 0) uniform Tint {
+                vec4 color;
+            } tint;
+
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                f_color = tint.color;
+            }
+        ",
+    }
+}
+
+// Machines without a Vulkan driver or device skip instead of failing
+pub fn headless_toolset() -> Option<VulkanToolset> {
+    headless_toolset_with(AppConfig::default())
+}
+
+pub fn headless_toolset_with(config : AppConfig) -> Option<VulkanToolset> {
+    let device_available = VulkanLibrary::new()
+    .ok()
+    .and_then(|library| Instance::new(library, InstanceCreateInfo {
+        flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
+        ..Default::default()
+    }).ok())
+    .and_then(|instance| instance.enumerate_physical_devices().ok())
+    .map_or(false, |mut devices| devices.next().is_some());
+
+    if !device_available {
+        eprintln!("skipping: no Vulkan device available");
+        return None;
+    }
+
+    Some(VulkanToolset::headless(config))
+}
+
+// Renders the mandelbrot set into a 1024x1024 image with the given workgroup layout
+pub fn render_mandelbrot(toolset : &VulkanToolset, compute : &ComputeShader) -> ImageData {
+    let image = mandelbrot_image(toolset, compute);
+
+    toolset.readback_image_data(&image, &toolset.device_queue).unwrap()
+}
+
+// The image render_mandelbrot reads back, also usable as a blit or sampling source
+pub fn mandelbrot_image(toolset : &VulkanToolset, compute : &ComputeShader) -> Arc<Image> {
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    let memory_allocator = allocator.general_allocator.clone();
+    let command_buffer_allocator = &allocator.buffer_allocator;
+
+    let image = Image::new(
+        memory_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [1024, 1024, 1],
+            usage: ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC | ImageUsage::SAMPLED,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        },
+    ).unwrap();
+
+    let group_counts = compute.group_counts([1024, 1024, 1]);
+    let compute_pipeline = &compute.pipeline;
+
+    // Setup descriptor sets for our data buffer
+    let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone(), Default::default());
+    let view = ImageView::new_default(image.clone()).unwrap();
+
+    let layout = compute_pipeline.layout().set_layouts().first().unwrap();
+    let set = PersistentDescriptorSet::new(
+        &descriptor_set_allocator,
+        layout.clone(),
+        [WriteDescriptorSet::image_view(0, view.clone())], // 0 is the binding
+        [],
+    ).unwrap();
+    
+    let mut builder = AutoCommandBufferBuilder::primary(
+        command_buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+
+    builder
+    .bind_pipeline_compute(compute_pipeline.clone())
+    .unwrap()
+    .bind_descriptor_sets(
+        PipelineBindPoint::Compute,
+        compute_pipeline.layout().clone(),
+        0,
+        set,
+    ).unwrap()
+    .dispatch(group_counts)
+    .unwrap();
+    
+    let command_buffer = builder.build().unwrap();
+
+    let future = sync::now(device.clone())
+    .then_execute(queue.clone(), command_buffer)
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap();
+
+    future.wait(None).unwrap();
+
+    image
+}
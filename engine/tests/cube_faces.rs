@@ -0,0 +1,42 @@
+use engine::{vulkan::texture::split_cube_faces, EngineError};
+use image::{Rgba, RgbaImage};
+
+// Every face filled with its own index, laid out in `cells` of `size` texels
+fn layout(width : u32, height : u32, size : u32, cells : [(u32, u32); 6]) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        let face = cells.iter().position(|&cell| cell == (x / size, y / size));
+        Rgba([face.map_or(255, |face| face as u8), (x % size) as u8, (y % size) as u8, 255])
+    })
+}
+
+fn assert_faces(faces : &[RgbaImage; 6], size : u32) {
+    for (index, face) in faces.iter().enumerate() {
+        assert_eq!(face.dimensions(), (size, size));
+        for (x, y, pixel) in face.enumerate_pixels() {
+            assert_eq!(pixel.0, [index as u8, x as u8, y as u8, 255], "face {index} at ({x}, {y})");
+        }
+    }
+}
+
+#[test]
+fn strips_split_in_face_order() {
+    let horizontal = layout(24, 4, 4, [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)]);
+    assert_faces(&split_cube_faces(&horizontal).unwrap(), 4);
+
+    let vertical = layout(4, 24, 4, [(0, 0), (0, 1), (0, 2), (0, 3), (0, 4), (0, 5)]);
+    assert_faces(&split_cube_faces(&vertical).unwrap(), 4);
+}
+
+#[test]
+fn crosses_split_around_positive_z() {
+    let cross = layout(16, 12, 4, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)]);
+    assert_faces(&split_cube_faces(&cross).unwrap(), 4);
+}
+
+#[test]
+fn other_aspect_ratios_are_rejected() {
+    for (width, height) in [(8, 8), (20, 4), (15, 12), (0, 0)] {
+        let result = split_cube_faces(&RgbaImage::new(width, height));
+        assert!(matches!(result, Err(EngineError::UnsupportedFeature(_))), "{width}x{height} was split");
+    }
+}
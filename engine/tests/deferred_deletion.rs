@@ -0,0 +1,35 @@
+use std::rc::Rc;
+
+use engine::vulkan::deferred_deletion::DeferredDeletionQueue;
+
+#[test]
+fn resources_live_until_their_slot_is_released() {
+    let mut deletions = DeferredDeletionQueue::new(2);
+    let mesh = Rc::new(());
+
+    deletions.retire(0, mesh.clone());
+    deletions.retire(1, mesh.clone());
+    assert_eq!(deletions.pending(), 2);
+    assert_eq!(Rc::strong_count(&mesh), 3);
+
+    assert_eq!(deletions.release(0), 1);
+    assert_eq!(Rc::strong_count(&mesh), 2);
+    assert_eq!(deletions.release(0), 0);
+
+    assert_eq!(deletions.release_all(), 1);
+    assert_eq!(Rc::strong_count(&mesh), 1);
+    assert_eq!(deletions.pending(), 0);
+    assert_eq!(deletions.released(), 2);
+}
+
+#[test]
+fn any_owned_value_can_be_retired() {
+    let mut deletions = DeferredDeletionQueue::new(1);
+
+    deletions.retire(0, vec![1u32, 2, 3]);
+    deletions.retire(0, String::from("texture"));
+    deletions.retire(0, (Rc::new(0u8), Box::new([0f32; 16])));
+
+    assert_eq!(deletions.pending(), 3);
+    assert_eq!(deletions.release(0), 3);
+}
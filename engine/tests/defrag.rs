@@ -0,0 +1,29 @@
+use ash::vk::{self, Handle};
+use engine::vulkan::defrag::{fragmentation, pack, Placement};
+
+fn placement(block : u64, offset : u64, size : u64) -> Placement {
+    Placement { block : vk::DeviceMemory::from_raw(block), offset, size }
+}
+
+#[test]
+fn packed_offsets_are_aligned_and_back_to_back() {
+    assert_eq!(pack(&[100, 64, 1, 30], 64), (vec![0, 128, 192, 256], 286));
+    assert_eq!(pack(&[], 64), (vec![], 0));
+}
+
+#[test]
+fn tightly_packed_buffers_are_not_fragmented() {
+    assert_eq!(fragmentation(&[]), 0.0);
+    assert_eq!(fragmentation(&[placement(1, 0, 64), placement(1, 64, 64), placement(2, 512, 32)]), 0.0);
+}
+
+#[test]
+fn gaps_count_against_the_span_of_their_block() {
+    // 128 of 256 bytes used in the first block, the second one is full
+    let placements = [placement(1, 0, 64), placement(1, 192, 64), placement(2, 0, 256)];
+    assert_eq!(fragmentation(&placements), 0.25);
+
+    // Order doesn't matter and the offset of the first buffer doesn't either
+    let placements = [placement(1, 1192, 64), placement(1, 1000, 64)];
+    assert_eq!(fragmentation(&placements), 0.5);
+}
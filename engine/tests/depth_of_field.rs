@@ -0,0 +1,50 @@
+use engine::render::depth_of_field::{bokeh_kernel, circle_of_confusion, DofParams, BOKEH_SAMPLES};
+
+// Depth buffer value of a point `distance` in front of the camera
+fn depth_at(distance : f32, params : &DofParams) -> f32 {
+    params.far * (distance - params.near) / ((params.far - params.near) * distance)
+}
+
+#[test]
+fn kernel_fills_the_unit_disc_from_the_center() {
+    let kernel = bokeh_kernel();
+    assert_eq!(kernel.len(), BOKEH_SAMPLES);
+    assert_eq!(kernel[0], [0.0; 4]);
+
+    for [x, y, z, w] in &kernel {
+        assert!((x * x + y * y).sqrt() <= 1.0 + 1e-6, "({x}, {y}) is outside the disc");
+        assert_eq!((*z, *w), (0.0, 0.0));
+    }
+
+    // The outer ring lies on the edge
+    let outer = kernel.iter().filter(|[x, y, ..]| ((x * x + y * y).sqrt() - 1.0).abs() < 1e-5).count();
+    assert_eq!(outer, 24);
+}
+
+#[test]
+fn focused_depth_stays_sharp() {
+    let params = DofParams { focus_distance : 2.0, ..Default::default() };
+
+    assert!(circle_of_confusion(depth_at(2.0, &params), &params) < 1e-3);
+}
+
+#[test]
+fn blur_grows_away_from_the_focus_distance() {
+    let params = DofParams { focus_distance : 2.0, aperture : 4.0, max_radius : 16.0, ..Default::default() };
+    let coc = |distance : f32| circle_of_confusion(depth_at(distance, &params), &params);
+
+    assert!(coc(3.0) < coc(10.0));
+    assert!(coc(10.0) < coc(50.0));
+    // Far behind the focus the radius approaches the aperture
+    assert!((coc(99.0) - 4.0).abs() < 0.1);
+    // In front of it the radius grows past the aperture
+    assert!(coc(0.5) > 4.0);
+}
+
+#[test]
+fn blur_is_clamped_to_the_max_radius() {
+    let params = DofParams { focus_distance : 20.0, aperture : 6.0, max_radius : 5.0, ..Default::default() };
+
+    assert_eq!(circle_of_confusion(depth_at(0.2, &params), &params), 5.0);
+    assert_eq!(circle_of_confusion(depth_at(1.0, &params), &params), 5.0);
+}
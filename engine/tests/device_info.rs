@@ -0,0 +1,64 @@
+use engine::{vulkan::device_info::{driver_version_string, DeviceLimits}, EngineError};
+
+fn limits() -> DeviceLimits {
+    DeviceLimits {
+        max_image_dimension_2d : 4096,
+        max_compute_work_group_size : [128, 128, 64],
+        max_compute_work_group_invocations : 128,
+        max_compute_work_group_count : [65535; 3],
+        max_push_constants_size : 128,
+        max_draw_indirect_count : 1024,
+        subgroup_size : Some(32),
+        max_vertex_attrib_divisor : 1 << 16,
+    }
+}
+
+#[test]
+fn driver_versions_are_decoded_per_vendor() {
+    // NVIDIA 535.104.5.0
+    assert_eq!(driver_version_string(0x10de, (535 << 22) | (104 << 14) | (5 << 6)), "535.104.5.0");
+    // AMD and Mesa drivers use the Vulkan version layout
+    assert_eq!(driver_version_string(0x1002, (2 << 22) | 279), "2.0.279");
+    assert_eq!(driver_version_string(0x10005, (23 << 22) | (3 << 12) | 1), "23.3.1");
+}
+
+#[test]
+fn push_constants_must_end_within_the_limit() {
+    let limits = limits();
+
+    assert!(limits.check_push_constants(0, 128).is_ok());
+    assert!(limits.check_push_constants(64, 64).is_ok());
+    assert!(matches!(limits.check_push_constants(64, 96), Err(EngineError::DeviceLimit { limit : "max_push_constants_size", requested : 160, max : 128 })));
+}
+
+#[test]
+fn local_sizes_are_checked_per_axis_and_in_total() {
+    let limits = limits();
+
+    assert!(limits.check_local_size([128, 1, 1]).is_ok());
+    assert!(limits.check_local_size([8, 8, 2]).is_ok());
+    assert!(matches!(limits.check_local_size([1, 1, 65]), Err(EngineError::DeviceLimit { limit : "max_compute_work_group_size[2]", requested : 65, max : 64 })));
+    assert!(matches!(limits.check_local_size([16, 16, 1]), Err(EngineError::DeviceLimit { limit : "max_compute_work_group_invocations", requested : 256, max : 128 })));
+}
+
+#[test]
+fn auto_tuned_sizes_stay_within_the_limits() {
+    let limits = limits();
+    let size = limits.auto_tuned_local_size(256);
+
+    assert_eq!(size, [16, 8, 1]);
+    assert!(limits.check_local_size(size).is_ok());
+}
+
+#[test]
+fn indirect_draws_are_checked_against_the_count_limit_and_stride() {
+    let limits = limits();
+
+    assert!(limits.check_indirect_draws(1024, 16, 16).is_ok());
+    assert!(limits.check_indirect_draws(3, 32, 20).is_ok());
+    // A single draw never steps to a second command
+    assert!(limits.check_indirect_draws(1, 0, 16).is_ok());
+    assert!(matches!(limits.check_indirect_draws(1025, 16, 16), Err(EngineError::DeviceLimit { limit : "max_draw_indirect_count", requested : 1025, max : 1024 })));
+    assert!(matches!(limits.check_indirect_draws(2, 18, 16), Err(EngineError::UnsupportedFeature(_))));
+    assert!(matches!(limits.check_indirect_draws(2, 12, 16), Err(EngineError::UnsupportedFeature(_))));
+}
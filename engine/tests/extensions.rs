@@ -0,0 +1,20 @@
+use engine::vulkan::extensions::ExtensionGuard;
+
+#[test]
+fn absent_extensions_never_run_the_closure() {
+    let guard = ExtensionGuard::<u32>::absent();
+
+    assert!(!guard.is_present());
+    assert_eq!(guard.if_present(|_| panic!("called without the extension")), None::<()>);
+    assert_eq!(guard, ExtensionGuard::default());
+}
+
+#[test]
+fn present_extensions_hand_out_their_value() {
+    let guard = ExtensionGuard::present(4u32);
+
+    assert!(guard.is_present());
+    assert_eq!(guard.get(), Some(&4));
+    assert_eq!(guard.if_present(|max| max * 2), Some(8));
+    assert_eq!(ExtensionGuard::from(Some(4u32)), guard);
+}
@@ -0,0 +1,52 @@
+use engine::FixedTimestep;
+
+const STEP : f32 = 1.0 / 60.0;
+
+// Bounces a ball under gravity for `seconds` at the given frame rate, returning the
+// interpolated height render would see on the last frame
+fn simulate(fps : f32, seconds : f32) -> f32 {
+    let mut timestep = FixedTimestep::new(STEP);
+    let (mut height, mut velocity) = (1.0f32, 0.0f32);
+    let mut previous = height;
+
+    for _ in 0..(seconds * fps).round() as u32 {
+        for _ in 0..timestep.advance(1.0 / fps) {
+            previous = height;
+            velocity -= 9.81 * STEP;
+            height += velocity * STEP;
+            if height < 0.0 {
+                height = -height;
+                velocity = -velocity;
+            }
+        }
+    }
+
+    previous + (height - previous) * timestep.alpha()
+}
+
+#[test]
+fn motion_is_independent_of_the_frame_rate() {
+    let slow = simulate(30.0, 3.0);
+    let fast = simulate(144.0, 3.0);
+
+    assert!((slow - fast).abs() < 1e-3, "30 FPS ended at {slow}, 144 FPS at {fast}");
+}
+
+#[test]
+fn steps_cover_the_elapsed_time() {
+    let mut timestep = FixedTimestep::new(STEP);
+
+    assert_eq!(timestep.advance(STEP * 0.5), 0);
+    assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+    assert_eq!(timestep.advance(STEP * 2.0), 2);
+    assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+}
+
+#[test]
+fn long_stalls_are_capped() {
+    let mut timestep = FixedTimestep::with_max_accumulated(STEP, STEP * 5.0);
+
+    // A ten second hitch only runs the capped number of steps
+    assert_eq!(timestep.advance(10.0), 5);
+    assert_eq!(timestep.advance(0.0), 0);
+}
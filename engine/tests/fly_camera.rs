@@ -0,0 +1,132 @@
+use std::f32::consts::FRAC_PI_2;
+
+use engine::{input::InputState, scene::{camera::{Camera, Matrix4}, fly_camera::FlyCameraController}};
+use winit::event::{DeviceEvent, DeviceId, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+fn key(input : &mut InputState, key : VirtualKeyCode, state : ElementState) {
+    #[allow(deprecated)]
+    let event = WindowEvent::KeyboardInput {
+        device_id : unsafe { DeviceId::dummy() },
+        input : KeyboardInput {
+            scancode : 0,
+            state,
+            virtual_keycode : Some(key),
+            modifiers : Default::default(),
+        },
+        is_synthetic : false,
+    };
+
+    input.handle_window_event(&event);
+}
+
+fn mouse_motion(input : &mut InputState, dx : f64, dy : f64) {
+    input.handle_device_event(&DeviceEvent::MouseMotion { delta : (dx, dy) });
+}
+
+fn view_space(camera : &Camera, point : [f32; 3]) -> [f32; 3] {
+    let view : Matrix4 = camera.view_matrix();
+    let [x, y, z] = point;
+
+    [0, 1, 2].map(|row| view[0][row] * x + view[1][row] * y + view[2][row] * z + view[3][row])
+}
+
+fn assert_close(actual : [f32; 3], expected : [f32; 3]) {
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-4, "{actual:?} != {expected:?}");
+    }
+}
+
+fn looking_at_origin() -> (Camera, FlyCameraController) {
+    let camera = Camera::perspective([0.0, 0.0, 5.0], [0.0, 0.0, 0.0], 1.0);
+    let controller = FlyCameraController::new(&camera);
+
+    (camera, controller)
+}
+
+#[test]
+fn forward_key_moves_along_the_view() {
+    let (mut camera, mut controller) = looking_at_origin();
+    let mut input = InputState::new();
+
+    key(&mut input, VirtualKeyCode::W, ElementState::Pressed);
+    controller.update(&mut camera, &input, 1.0);
+
+    // Default speed is 3 units per second
+    assert_close(camera.position, [0.0, 0.0, 2.0]);
+    assert_close(view_space(&camera, [0.0, 0.0, 0.0]), [0.0, 0.0, -2.0]);
+}
+
+#[test]
+fn movement_scales_with_delta_time() {
+    let (mut camera, mut controller) = looking_at_origin();
+    let mut input = InputState::new();
+
+    key(&mut input, VirtualKeyCode::Space, ElementState::Pressed);
+    key(&mut input, VirtualKeyCode::D, ElementState::Pressed);
+    controller.update(&mut camera, &input, 0.5);
+
+    assert_close(camera.position, [1.5, 1.5, 5.0]);
+}
+
+#[test]
+fn mouse_is_ignored_while_the_cursor_is_free() {
+    let (mut camera, mut controller) = looking_at_origin();
+    let mut input = InputState::new();
+
+    mouse_motion(&mut input, 500.0, 0.0);
+    controller.update(&mut camera, &input, 0.016);
+
+    assert_eq!(controller.yaw(), 0.0);
+    assert_close(camera.target, [0.0, 0.0, 4.0]);
+}
+
+#[test]
+fn grabbed_mouse_turns_the_view() {
+    let (mut camera, mut controller) = looking_at_origin();
+    let mut input = InputState::new();
+
+    input.set_cursor_grabbed(true);
+    mouse_motion(&mut input, (FRAC_PI_2 / controller.sensitivity) as f64, 0.0);
+    controller.update(&mut camera, &input, 0.016);
+
+    // Turned right, so a point on +X is now straight ahead
+    assert_close(view_space(&camera, [1.0, 0.0, 5.0]), [0.0, 0.0, -1.0]);
+
+    // Motion is consumed once the frame ends
+    input.end_frame();
+    controller.update(&mut camera, &input, 0.016);
+    assert!((controller.yaw() - FRAC_PI_2).abs() < 1e-4);
+}
+
+#[test]
+fn pitch_stops_short_of_straight_up() {
+    let (mut camera, mut controller) = looking_at_origin();
+    let mut input = InputState::new();
+
+    input.set_cursor_grabbed(true);
+    mouse_motion(&mut input, 0.0, -1.0e6);
+    controller.update(&mut camera, &input, 0.016);
+
+    assert!((controller.pitch().to_degrees() - 89.0).abs() < 1e-3);
+    assert!(camera.target[1] > camera.position[1]);
+    assert!(camera.view_matrix().iter().flatten().all(|value| value.is_finite()));
+}
+
+#[test]
+fn focus_loss_stops_look_and_movement() {
+    let (mut camera, mut controller) = looking_at_origin();
+    let mut input = InputState::new();
+
+    input.set_cursor_grabbed(true);
+    key(&mut input, VirtualKeyCode::W, ElementState::Pressed);
+    mouse_motion(&mut input, 300.0, 0.0);
+    input.handle_window_event(&WindowEvent::Focused(false));
+
+    // Motion arriving after the focus was lost is dropped too
+    mouse_motion(&mut input, 300.0, 0.0);
+    controller.update(&mut camera, &input, 1.0);
+
+    assert!(!input.is_cursor_grabbed());
+    assert_eq!(controller.yaw(), 0.0);
+    assert_close(camera.position, [0.0, 0.0, 5.0]);
+}
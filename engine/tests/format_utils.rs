@@ -0,0 +1,43 @@
+use engine::vulkan::format_utils::{FormatNegotiator, OutputMode};
+use vulkano::{format::Format, swapchain::ColorSpace};
+
+const SDR : [(Format, ColorSpace); 2] = [
+    (Format::B8G8R8A8_UNORM, ColorSpace::SrgbNonLinear),
+    (Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear),
+];
+
+#[test]
+fn hdr10_is_preferred_over_scrgb() {
+    let formats = [
+        SDR.as_slice(),
+        &[(Format::R16G16B16A16_SFLOAT, ColorSpace::ExtendedSrgbLinear), (Format::A2B10G10R10_UNORM_PACK32, ColorSpace::Hdr10St2084)],
+    ].concat();
+
+    assert_eq!(FormatNegotiator::choose_hdr_format(&formats), Some((Format::A2B10G10R10_UNORM_PACK32, ColorSpace::Hdr10St2084)));
+    assert_eq!(FormatNegotiator::choose_hdr_format(&formats[..3]), Some((Format::R16G16B16A16_SFLOAT, ColorSpace::ExtendedSrgbLinear)));
+    assert_eq!(FormatNegotiator::choose_hdr_format(&SDR), None);
+}
+
+#[test]
+fn ten_bit_formats_in_the_srgb_color_space_are_not_hdr() {
+    let formats = [(Format::A2B10G10R10_UNORM_PACK32, ColorSpace::SrgbNonLinear), (Format::R16G16B16A16_SFLOAT, ColorSpace::SrgbNonLinear)];
+
+    assert_eq!(FormatNegotiator::choose_hdr_format(&formats), None);
+}
+
+#[test]
+fn sdr_fallback_skips_hdr_color_spaces() {
+    assert_eq!(FormatNegotiator::choose_color_format(&SDR, true), (Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear));
+
+    // No 8 bit format, the first sRGB pair is taken rather than the HDR one listed before it
+    let formats = [(Format::A2B10G10R10_UNORM_PACK32, ColorSpace::Hdr10St2084), (Format::A2B10G10R10_UNORM_PACK32, ColorSpace::SrgbNonLinear)];
+    assert_eq!(FormatNegotiator::choose_color_format(&formats, true), formats[1]);
+}
+
+#[test]
+fn output_modes_follow_the_color_space() {
+    assert_eq!(OutputMode::from_color_space(ColorSpace::Hdr10St2084), OutputMode::Hdr10);
+    assert_eq!(OutputMode::from_color_space(ColorSpace::ExtendedSrgbLinear), OutputMode::ScRgb);
+    assert_eq!(OutputMode::from_color_space(ColorSpace::SrgbNonLinear), OutputMode::Sdr);
+    assert!(!OutputMode::Sdr.is_hdr());
+}
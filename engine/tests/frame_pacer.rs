@@ -0,0 +1,90 @@
+use std::time::{Duration, Instant};
+
+use engine::{FrameLimit, FramePacer, Pacing};
+
+const TARGET : Duration = Duration::from_millis(10);
+
+fn ms(millis : u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn the_first_frame_starts_the_cadence() {
+    let start = Instant::now();
+    let mut pacer = FramePacer::new(TARGET);
+
+    assert_eq!(pacer.end_frame(start), Pacing::Now);
+    // Ahead of time, wait out the rest of the target
+    assert_eq!(pacer.end_frame(start + ms(4)), Pacing::WaitUntil(start + ms(10)));
+    assert_eq!(pacer.end_frame(start + ms(13)), Pacing::WaitUntil(start + ms(20)));
+}
+
+#[test]
+fn deadlines_stay_on_the_cadence_instead_of_drifting() {
+    let start = Instant::now();
+    let mut pacer = FramePacer::new(TARGET);
+    pacer.end_frame(start);
+
+    // Every frame ends a little after its wait, the deadlines still fall on multiples of the target
+    let mut now = start;
+    for frame in 1..=5 {
+        now += ms(3);
+        assert_eq!(pacer.end_frame(now), Pacing::WaitUntil(start + ms(10 * frame)));
+        now = start + ms(10 * frame) + Duration::from_micros(200);
+    }
+}
+
+#[test]
+fn a_slightly_late_frame_is_caught_up_by_the_next() {
+    let start = Instant::now();
+    let mut pacer = FramePacer::new(TARGET);
+    pacer.end_frame(start);
+
+    // 4 ms late, no wait, and the next frame only gets the remaining 6 ms
+    assert_eq!(pacer.end_frame(start + ms(14)), Pacing::Now);
+    assert_eq!(pacer.end_frame(start + ms(16)), Pacing::WaitUntil(start + ms(20)));
+    assert_eq!(pacer.missed_frames(), 0);
+}
+
+#[test]
+fn missing_a_whole_frame_restarts_the_cadence() {
+    let start = Instant::now();
+    let mut pacer = FramePacer::new(TARGET);
+    pacer.end_frame(start);
+
+    // A hitch of 35 ms, the frames it covered are dropped instead of rendered back to back
+    assert_eq!(pacer.end_frame(start + ms(45)), Pacing::Now);
+    assert_eq!(pacer.missed_frames(), 1);
+    assert_eq!(pacer.end_frame(start + ms(47)), Pacing::WaitUntil(start + ms(55)));
+}
+
+#[test]
+fn reset_forgets_the_cadence() {
+    let start = Instant::now();
+    let mut pacer = FramePacer::new(TARGET);
+    pacer.end_frame(start);
+
+    pacer.reset();
+    assert_eq!(pacer.end_frame(start + ms(500)), Pacing::Now);
+    assert_eq!(pacer.missed_frames(), 0);
+    assert_eq!(pacer.end_frame(start + ms(502)), Pacing::WaitUntil(start + ms(510)));
+}
+
+#[test]
+fn vsync_only_replaces_pacing_when_it_is_slower() {
+    // 60 Hz holds frames for 16.7 ms
+    assert!(FramePacer::vsync_is_slower(Some(60_000), FrameLimit::fps(120.0).target_frame_time));
+    assert!(!FramePacer::vsync_is_slower(Some(144_000), FrameLimit::fps(60.0).target_frame_time));
+    assert!(!FramePacer::vsync_is_slower(None, TARGET));
+    assert!(!FramePacer::vsync_is_slower(Some(0), TARGET));
+}
+
+#[test]
+fn sleeping_reaches_the_deadline() {
+    let deadline = Instant::now() + ms(3);
+    FramePacer::sleep_until(deadline);
+    assert!(Instant::now() >= deadline);
+
+    // Past deadlines return right away
+    FramePacer::sleep_until(Instant::now() - ms(1));
+}
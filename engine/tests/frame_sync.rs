@@ -0,0 +1,73 @@
+use std::rc::Rc;
+
+use engine::vulkan::frame_sync::FrameSync;
+
+#[test]
+fn slots_rotate_through_the_frames_in_flight() {
+    let mut frame_sync = FrameSync::new(3);
+
+    let slots = (0..7).map(|_| frame_sync.begin_frame()).collect::<Vec<_>>();
+    assert_eq!(slots, [0, 1, 2, 0, 1, 2, 0]);
+    assert_eq!(frame_sync.current_frame(), 0);
+}
+
+#[test]
+fn unsubmitted_frames_do_not_hold_their_slot() {
+    let mut frame_sync = FrameSync::new(2);
+
+    // A frame that failed to submit leaves no fence behind to wait on
+    assert_eq!(frame_sync.begin_frame(), 0);
+    frame_sync.end_frame(None);
+    assert_eq!(frame_sync.begin_frame(), 1);
+    assert_eq!(frame_sync.begin_frame(), 0);
+}
+
+#[test]
+#[should_panic(expected = "at least one frame must be in flight")]
+fn zero_frames_in_flight_is_rejected() {
+    FrameSync::new(0);
+}
+
+#[test]
+fn waiting_for_all_frames_keeps_the_slot_order() {
+    let mut frame_sync = FrameSync::new(3);
+
+    assert_eq!(frame_sync.begin_frame(), 0);
+    frame_sync.end_frame(None);
+    frame_sync.wait_all().unwrap();
+    assert_eq!(frame_sync.begin_frame(), 1);
+}
+
+#[test]
+fn abandoning_frames_forgets_them_and_keeps_the_slot_order() {
+    let mut frame_sync = FrameSync::new(2);
+
+    assert_eq!(frame_sync.begin_frame(), 0);
+    frame_sync.end_frame(None);
+    frame_sync.abandon_frames();
+    assert_eq!(frame_sync.pending_frames(), 0);
+    assert_eq!(frame_sync.try_begin_frame().unwrap(), 1);
+}
+
+#[test]
+fn retired_resources_are_dropped_when_their_slot_comes_around_again() {
+    let mut frame_sync = FrameSync::new(2);
+    let vertex_buffer = Rc::new(());
+
+    assert_eq!(frame_sync.begin_frame(), 0);
+    frame_sync.retire(vertex_buffer.clone());
+    frame_sync.end_frame(None);
+
+    // The other slot's frame may still read it
+    assert_eq!(frame_sync.begin_frame(), 1);
+    frame_sync.end_frame(None);
+    assert_eq!(Rc::strong_count(&vertex_buffer), 2);
+
+    assert_eq!(frame_sync.begin_frame(), 0);
+    assert_eq!(Rc::strong_count(&vertex_buffer), 1);
+    assert_eq!(frame_sync.deletions().released(), 1);
+
+    frame_sync.retire(vertex_buffer.clone());
+    frame_sync.wait_all().unwrap();
+    assert_eq!(Rc::strong_count(&vertex_buffer), 1);
+}
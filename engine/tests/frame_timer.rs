@@ -0,0 +1,53 @@
+use engine::{render::stats_overlay::stats_text, vulkan::{memory_stats::{HeapStats, MemoryStats}, pipeline_stats::PipelineStats}, FrameTimer};
+use vulkano::query::QueryPipelineStatisticFlags;
+
+#[test]
+fn averages_only_complete_intervals() {
+    let mut timer = FrameTimer::new(0.5);
+    assert_eq!(timer.fps(), None);
+
+    // 0.1 s short of the interval, then one frame past it
+    for _ in 0..4 {
+        assert!(!timer.tick(0.1));
+    }
+    assert!(timer.tick(0.15));
+    assert!((timer.frame_time().unwrap() - 0.11).abs() < 1e-6);
+
+    // The next interval starts over instead of blending with the last one
+    for _ in 0..24 {
+        timer.tick(0.02);
+    }
+    assert!(timer.tick(0.02));
+    assert!((timer.fps().unwrap() - 50.0).abs() < 1e-3);
+}
+
+#[test]
+fn text_lists_pipeline_stats_when_available() {
+    let mut timer = FrameTimer::new(0.5);
+    assert_eq!(stats_text(&timer, 3, None, &MemoryStats::default()), "-- fps | draws 3");
+
+    timer.tick(0.5);
+    let stats = PipelineStats::from([
+        (QueryPipelineStatisticFlags::INPUT_ASSEMBLY_PRIMITIVES, 12),
+        (QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS, 4096),
+    ]);
+    assert_eq!(stats_text(&timer, 2, Some(&stats), &MemoryStats::default()), "2 fps (500.00 ms) | draws 2 | triangles 12 | fragments 4096");
+}
+
+#[test]
+fn text_sums_the_device_local_heaps_as_vram() {
+    let mut timer = FrameTimer::new(0.5);
+    timer.tick(0.5);
+
+    let mib = 1 << 20;
+    let memory = MemoryStats {
+        heaps : vec![
+            HeapStats { size : 4096 * mib, device_local : true, tracked_bytes : 24 * mib },
+            HeapStats { size : 16384 * mib, device_local : false, tracked_bytes : 8 * mib },
+            HeapStats { size : 256 * mib, device_local : true, tracked_bytes : mib / 2 },
+        ],
+        ..Default::default()
+    };
+    assert_eq!(memory.device_local(), (24 * mib + mib / 2, 4352 * mib));
+    assert_eq!(stats_text(&timer, 1, None, &memory), "2 fps (500.00 ms) | draws 1 | vram 24.5 of 4352 MiB");
+}
@@ -0,0 +1,42 @@
+use engine::scene::{camera::Camera, frustum::Frustum};
+
+fn camera_frustum() -> Frustum {
+    let camera = Camera::perspective([0.0, 0.0, 5.0], [0.0, 0.0, 0.0], 1.0);
+    Frustum::from_view_projection(&camera.view_projection(1.0))
+}
+
+#[test]
+fn planes_have_unit_normals() {
+    for plane in camera_frustum().planes {
+        let length = (plane[0] * plane[0] + plane[1] * plane[1] + plane[2] * plane[2]).sqrt();
+        assert!((length - 1.0).abs() < 1e-5, "{plane:?}");
+    }
+}
+
+#[test]
+fn spheres_in_front_of_the_camera_are_visible() {
+    let frustum = camera_frustum();
+
+    assert!(frustum.intersects_sphere([0.0, 0.0, 0.0], 0.5));
+    assert!(frustum.intersects_sphere([0.0, 0.0, -90.0], 0.5));
+}
+
+#[test]
+fn spheres_behind_or_beside_the_camera_are_culled() {
+    let frustum = camera_frustum();
+
+    assert!(!frustum.intersects_sphere([0.0, 0.0, 10.0], 0.5));
+    assert!(!frustum.intersects_sphere([20.0, 0.0, 0.0], 0.5));
+    assert!(!frustum.intersects_sphere([0.0, -20.0, 0.0], 0.5));
+    // Past the far plane at 100 units
+    assert!(!frustum.intersects_sphere([0.0, 0.0, -110.0], 0.5));
+}
+
+#[test]
+fn spheres_straddling_a_plane_are_kept() {
+    let frustum = camera_frustum();
+
+    // Just outside the near plane, but the radius reaches into the frustum
+    assert!(!frustum.intersects_sphere([0.0, 0.0, 5.0], 0.05));
+    assert!(frustum.intersects_sphere([0.0, 0.0, 5.0], 0.5));
+}
@@ -0,0 +1,49 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use std::path::Path;
+
+use common::headless_toolset;
+use engine::assets::{gltf_loader::GltfLoader, obj_loader::ObjLoader};
+use vulkano::{
+    buffer::IndexType,
+    format::Format
+};
+
+#[test]
+fn obj_cube_loads_deduplicated_mesh() {
+    let Some(toolset) = headless_toolset() else { return };
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/cube.obj");
+
+    let meshes = ObjLoader::load(&path, &toolset.memory_allocator, &toolset.device_queue).unwrap();
+    assert_eq!(meshes.len(), 1);
+
+    // Corners are shared within a face but split between faces with different normals
+    let cube = &meshes[0];
+    assert_eq!(cube.vertex_buffer.len(), 24);
+    assert_eq!(cube.index_count(), 36);
+    // 24 vertices fit 16 bit indices
+    assert_eq!(cube.index_type(), IndexType::U16);
+}
+
+#[test]
+fn gltf_cube_loads_mesh_and_textured_material() {
+    let Some(toolset) = headless_toolset() else { return };
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/cube.glb");
+
+    let scene = GltfLoader::load(&path, &toolset).unwrap();
+    assert_eq!(scene.meshes.len(), 1);
+    assert_eq!(scene.mesh_materials, [Some(0)]);
+
+    let cube = &scene.meshes[0];
+    assert_eq!(cube.vertex_buffer.len(), 24);
+    assert_eq!(cube.index_count(), 36);
+    assert_eq!(cube.index_type(), IndexType::U16);
+
+    let material = &scene.materials[0];
+    assert_eq!(material.base_color_factor, [1.0, 0.5, 0.25, 1.0]);
+    let texture = material.base_color_texture.as_ref().unwrap();
+    assert_eq!(texture.image.extent(), [2, 2, 1]);
+    assert_eq!(texture.image.format(), Format::R8G8B8A8_SRGB);
+}
@@ -0,0 +1,181 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::headless_toolset_with;
+use engine::{vulkan::{async_compute::AsyncComputeScheduler, render_pass::{create_framebuffer, RenderPassBuilder}, vulkan::ComputeShader, vulkan_window::AttachmentConfig}, AppConfig, QueueRequest, QueueRole};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, BufferImageCopy, CommandBufferUsage, CopyImageToBufferInfo, RenderPassBeginInfo, SubpassBeginInfo, SubpassEndInfo},
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    device::Queue,
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{Pipeline, PipelineBindPoint},
+    sync::{self, GpuFuture}
+};
+
+// Inverts the color of a storage image in place, keeping it opaque
+mod invert_image_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0, rgba8) uniform image2D image;
+
+            void main() {
+                ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                imageStore(image, pixel, vec4(1.0 - imageLoad(image, pixel).rgb, 1.0));
+            }
+        ",
+    }
+}
+
+#[test]
+fn async_compute_processes_the_graphics_output_between_two_graphics_passes() {
+    let config = AppConfig {
+        queues : vec![QueueRequest::new(QueueRole::Graphics, 1.0), QueueRequest::new(QueueRole::Compute, 0.5)],
+        ..AppConfig::default()
+    };
+    let Some(toolset) = headless_toolset_with(config) else { return };
+    if !toolset.capabilities.timeline_semaphores {
+        eprintln!("skipping: timeline semaphores are not supported");
+        return;
+    }
+    let device = &toolset.logical_device;
+    let allocator = &toolset.memory_allocator;
+    const FRAMES : u64 = 60;
+    const SIZE : u32 = 4;
+
+    let intermediate = ImageView::new_default(Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SIZE, SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap()).unwrap();
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::General,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![intermediate.clone()]).unwrap();
+
+    let shader = invert_image_cs::load(device.clone()).expect("failed to create shader module");
+    let compute = ComputeShader::new(&shader, [SIZE, SIZE, 1], device.clone());
+    let layout = compute.pipeline.layout().clone();
+    let set = PersistentDescriptorSet::new(
+        &allocator.descriptor_set_allocator,
+        layout.set_layouts()[0].clone(),
+        [WriteDescriptorSet::image_view(0, intermediate.clone())],
+        [],
+    ).unwrap();
+
+    // One pixel of every frame's composite
+    let output = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        vec![[0u8; 4]; FRAMES as usize],
+    ).unwrap();
+
+    let builder_for = |queue : &Arc<Queue>| AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+
+    let mut scheduler = AsyncComputeScheduler::new(toolset.queue(QueueRole::Graphics).clone(), toolset.queue(QueueRole::Compute).clone(), device).unwrap();
+    for frame in 0..FRAMES {
+        // The scene renders at 3n + 1, compute processes it at 3n + 2 and the composite follows at 3n + 3
+        let base = 3 * frame;
+
+        let mut scene = builder_for(scheduler.graphics_queue());
+        scene.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![Some([frame as f32 / 255.0, 0.0, 0.0, 1.0].into())],
+                ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
+            },
+            SubpassBeginInfo::default(),
+        ).unwrap()
+        .end_render_pass(SubpassEndInfo::default())
+        .unwrap();
+        scheduler.submit_graphics(scene.build().unwrap(), base, base + 1).unwrap();
+
+        let mut processing = builder_for(scheduler.compute_queue());
+        processing.bind_pipeline_compute(compute.pipeline.clone())
+        .unwrap()
+        .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, set.clone())
+        .unwrap()
+        .dispatch([1, 1, 1])
+        .unwrap();
+        scheduler.submit_compute(processing.build().unwrap(), base + 1, base + 2).unwrap();
+
+        let mut composite = builder_for(scheduler.graphics_queue());
+        composite.copy_image_to_buffer(CopyImageToBufferInfo {
+            regions: [BufferImageCopy {
+                image_subresource: intermediate.image().subresource_layers(),
+                image_extent: [1, 1, 1],
+                ..Default::default()
+            }].into_iter().collect(),
+            ..CopyImageToBufferInfo::image_buffer(intermediate.image().clone(), output.clone().slice(frame..frame + 1))
+        }).unwrap();
+        scheduler.submit_graphics(composite.build().unwrap(), base + 2, base + 3).unwrap();
+    }
+
+    // Nothing of the scheduler's may still run once the image and buffer go back to vulkano's futures or the host
+    assert!(scheduler.wait(3 * FRAMES, Some(Duration::from_secs(10))).unwrap(), "the frames deadlocked");
+    assert_eq!(scheduler.value().unwrap(), 3 * FRAMES);
+    assert_eq!(scheduler.in_flight(), 0);
+
+    // The shared image goes through a vulkano future afterwards, holding the last frame's processed scene
+    let last_frame = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        vec![[0u8; 4]; (SIZE * SIZE) as usize],
+    ).unwrap();
+    let mut readback = builder_for(scheduler.graphics_queue());
+    readback.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(intermediate.image().clone(), last_frame.clone()))
+    .unwrap();
+    sync::now(device.clone())
+    .then_execute(scheduler.graphics_queue().clone(), readback.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+    assert!(last_frame.read().unwrap().iter().all(|&pixel| pixel == [255 - (FRAMES - 1) as u8, 255, 255, 255]));
+
+    // Each composite saw its own frame's scene after compute inverted it, never a neighbouring frame's
+    let output = output.read().unwrap();
+    for (frame, pixel) in output.iter().enumerate() {
+        assert_eq!(*pixel, [255 - frame as u8, 255, 255, 255], "frame {frame}");
+    }
+}
@@ -0,0 +1,285 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::{gather_cs, headless_toolset, mandelbrot_cs, multiply_cs, render_mandelbrot};
+use engine::{vulkan::{staging::read_back_buffer, storage_buffer::StorageBuffer, vulkan::ComputeShader}, EngineError, SaveFormat};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, layout::DescriptorType, PersistentDescriptorSet, WriteDescriptorSet},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::Pipeline,
+    sync::{self, GpuFuture}
+};
+
+mod copy_by_address_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+            #extension GL_EXT_buffer_reference : require
+
+            layout(local_size_x = 64) in;
+
+            layout(buffer_reference, std430, buffer_reference_align = 4) buffer DataRef {
+                uint data[];
+            };
+
+            // No descriptors, both buffers are reached through their addresses
+            layout(push_constant) uniform Pointers {
+                DataRef src;
+                DataRef dst;
+            } pointers;
+
+            void main() {
+                uint idx = gl_GlobalInvocationID.x;
+                pointers.dst.data[idx] = pointers.src.data[idx];
+            }
+        ",
+    }
+}
+
+#[test]
+fn storage_buffer_elements_are_written_from_the_host_and_read_by_shaders() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let allocator = &toolset.memory_allocator;
+
+    assert!(matches!(StorageBuffer::<u32>::new(allocator, 0, None), Err(EngineError::EmptyBuffer)));
+    assert!(matches!(StorageBuffer::new(allocator, 2, Some(&[1u32, 2, 3])), Err(EngineError::BufferRange { len : 3, buffer_len : 2, .. })));
+
+    // What the initial data doesn't cover starts out zeroed
+    let storage = StorageBuffer::new(allocator, 8, Some(&[1u32, 2, 3])).unwrap();
+    assert_eq!(storage.len(), 8);
+    assert_eq!(&*storage.buffer().read().unwrap(), &[1, 2, 3, 0, 0, 0, 0, 0]);
+
+    storage.write_element(5, 7).unwrap();
+    storage.write_element(0, 4).unwrap();
+    assert!(matches!(storage.write_element(8, 1), Err(EngineError::BufferRange { offset : 8, buffer_len : 8, .. })));
+
+    let shader = multiply_cs::load(device.clone()).expect("failed to create shader module");
+    let compute = ComputeShader::new(&shader, [64, 1, 1], device.clone());
+    let descriptor_set = PersistentDescriptorSet::new(
+        &allocator.descriptor_set_allocator,
+        compute.pipeline.layout().set_layouts()[0].clone(),
+        [storage.write_descriptor(0)],
+        [],
+    ).unwrap();
+    compute.execute(allocator, &toolset.device_queue, descriptor_set, storage.len() as u32).unwrap();
+
+    assert_eq!(&*storage.buffer().read().unwrap(), &[52, 26, 39, 0, 0, 91, 0, 0]);
+}
+
+#[test]
+fn compute_multiplies_exactly_the_requested_elements() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    // Not a multiple of the workgroup size, the last group has idle invocations
+    const ELEMENTS : u32 = 1000;
+    const GUARD : u32 = 24;
+
+    let shader = multiply_cs::load(device.clone()).expect("failed to create shader module");
+    let compute = ComputeShader::new(&shader, [64, 1, 1], device.clone());
+    assert_eq!(compute.group_counts([ELEMENTS, 1, 1]), [16, 1, 1]);
+
+    // Trailing guard elements must come back untouched
+    let data_buffer = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        0..ELEMENTS + GUARD,
+    )
+    .expect("failed to create buffer");
+
+    let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone(), Default::default());
+    let layout = compute.pipeline.layout().set_layouts().first().unwrap();
+    let descriptor_set = PersistentDescriptorSet::new(
+        &descriptor_set_allocator,
+        layout.clone(),
+        [WriteDescriptorSet::buffer(0, data_buffer.clone())],
+        [],
+    ).unwrap();
+
+    compute.execute(allocator, queue, descriptor_set, ELEMENTS).unwrap();
+
+    let content = data_buffer.read().unwrap();
+    for (n, val) in content.iter().enumerate() {
+        let expected = match n < ELEMENTS as usize {
+            true => n as u32 * 13,
+            false => n as u32,
+        };
+        assert_eq!(*val, expected, "element {n}");
+    }
+}
+
+#[test]
+fn compute_gathers_across_descriptor_sets() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    const ELEMENTS : u32 = 300;
+
+    let storage_buffer = |data : Vec<u32>| Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        data,
+    ).expect("failed to create buffer");
+
+    let source = storage_buffer((0..ELEMENTS).map(|n| n * 7).collect());
+    let indices = storage_buffer((0..ELEMENTS).rev().collect());
+    let destination = storage_buffer(vec![0; ELEMENTS as usize]);
+
+    let shader = gather_cs::load(device.clone()).expect("failed to create shader module");
+    let compute = ComputeShader::new(&shader, [64, 1, 1], device.clone());
+    let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone(), Default::default());
+
+    // Leaving out set 1 and the index binding is reported before anything is dispatched
+    let missing = compute.create_descriptor_sets(
+        &descriptor_set_allocator,
+        [(0, vec![WriteDescriptorSet::buffer(0, source.clone())])],
+    );
+    match missing {
+        Err(EngineError::MissingDescriptors { sets, bindings }) => {
+            assert_eq!(sets, [1]);
+            assert_eq!(bindings.iter().map(|binding| (binding.set, binding.binding)).collect::<Vec<_>>(), [(0, 1)]);
+            assert_eq!(bindings[0].descriptor_types[0], DescriptorType::StorageBuffer);
+        },
+        Err(e) => panic!("expected missing descriptors, got {e}"),
+        Ok(_) => panic!("expected missing descriptors, got descriptor sets"),
+    }
+
+    compute.execute_with_writes(
+        allocator,
+        &descriptor_set_allocator,
+        queue,
+        [
+            (0, vec![
+                WriteDescriptorSet::buffer(0, source.clone()),
+                WriteDescriptorSet::buffer(1, indices),
+            ]),
+            (1, vec![WriteDescriptorSet::buffer(2, destination.clone())]),
+        ],
+        ELEMENTS,
+    ).unwrap();
+
+    let content = destination.read().unwrap();
+    for (n, val) in content.iter().enumerate() {
+        assert_eq!(*val, (ELEMENTS - 1 - n as u32) * 7, "element {n}");
+    }
+}
+
+#[test]
+fn compute_writes_mandelbrot_image() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+
+    // Create compute shader
+    let shader = mandelbrot_cs::load(device.clone()).expect("failed to create shader module");
+    let compute = ComputeShader::new(&shader, [8, 8, 1], device.clone());
+
+    let data = render_mandelbrot(&toolset, &compute);
+    assert_eq!((data.width, data.height, data.texel_size()), (1024, 1024, 4));
+    assert_eq!(data.bytes.len(), 1024 * 1024 * data.texel_size());
+
+    data.save(&std::env::temp_dir().join("gpu_smoke_mandelbrot.png"), SaveFormat::Png).unwrap();
+}
+
+#[test]
+fn auto_tuned_workgroups_render_the_same_image() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let properties = device.physical_device().properties();
+
+    let shader = mandelbrot_cs::load(device.clone()).expect("failed to create shader module");
+    let fixed = ComputeShader::new(&shader, [8, 8, 1], device.clone());
+    let tuned = ComputeShader::with_auto_tuned_workgroup(&shader, "main", device.clone(), 256).unwrap();
+
+    let [width, height, depth] = tuned.local_size;
+    assert_eq!(depth, 1);
+    assert!(width * height <= properties.max_compute_work_group_invocations);
+    if let Some(subgroup) = properties.subgroup_size.filter(|&subgroup| subgroup <= 256) {
+        assert_eq!(width * height % subgroup, 0, "{width}x{height} is not a multiple of subgroup size {subgroup}");
+    }
+
+    let start = std::time::Instant::now();
+    let expected = render_mandelbrot(&toolset, &fixed);
+    let fixed_time = start.elapsed();
+
+    let start = std::time::Instant::now();
+    let actual = render_mandelbrot(&toolset, &tuned);
+    let tuned_time = start.elapsed();
+
+    eprintln!("{}: 8x8 took {fixed_time:?}, {width}x{height} took {tuned_time:?}", properties.device_name);
+    assert!(expected == actual, "workgroup layout changed the rendered image");
+}
+
+#[test]
+fn compute_copies_between_buffers_through_their_device_addresses() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    let data = (0..1024u32).map(|i| i.wrapping_mul(2654435761)).collect::<Vec<_>>();
+    if !toolset.capabilities.buffer_device_address {
+        assert!(matches!(allocator.create_buffer_with_device_address(device, &data), Err(EngineError::UnsupportedFeature(_))));
+        return;
+    }
+
+    let (src, src_address) = allocator.create_buffer_with_device_address(device, &data).unwrap();
+    let (dst, dst_address) = allocator.create_buffer_with_device_address(device, &vec![0u32; data.len()]).unwrap();
+    assert_ne!(src_address, dst_address);
+    assert!(matches!(allocator.create_buffer_with_device_address::<u32>(device, &[]), Err(EngineError::EmptyBuffer)));
+
+    let shader = copy_by_address_cs::load(device.clone()).expect("failed to create shader module");
+    let compute = ComputeShader::new(&shader, [64, 1, 1], device.clone());
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    builder.bind_pipeline_compute(compute.pipeline.clone())
+    .unwrap()
+    .push_constants(compute.pipeline.layout().clone(), 0, copy_by_address_cs::Pointers { src : src_address, dst : dst_address })
+    .unwrap()
+    .dispatch(compute.group_counts([data.len() as u32, 1, 1]))
+    .unwrap();
+
+    // The buffers are never bound, keep them alive until the copy finished
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let bytes = |buffer : &Subbuffer<[u32]>| read_back_buffer(&toolset, buffer).unwrap()
+    .into_iter()
+    .flat_map(u32::to_ne_bytes)
+    .collect::<Vec<_>>();
+    assert_eq!(bytes(&dst), bytes(&src));
+    assert_eq!(bytes(&src), data.iter().flat_map(|value| value.to_ne_bytes()).collect::<Vec<_>>());
+}
@@ -0,0 +1,312 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::{headless_toolset, SCREENSHOT_SIZE};
+use engine::{render::{culling::{BoundingSphere, FrustumCuller, OcclusionCuller}, indirect::{CullObject, IndirectCulling}}, scene::{camera::Camera, frustum::Frustum}, vulkan::{pipeline_stats::PipelineStatsPool, render_pass::{create_framebuffer, RenderPassBuilder}, staging::{read_back_buffer, upload_buffer}, vertex::Triangle, vulkan_window::AttachmentConfig}};
+use vulkano::{
+    buffer::BufferUsage,
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, DrawIndirectCommand, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage},
+    memory::allocator::AllocationCreateInfo,
+    query::QueryPipelineStatisticFlags,
+    pipeline::graphics::viewport::Viewport,
+    render_pass::Subpass,
+    sync::{self, GpuFuture}
+};
+
+mod occlusion_vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            // A fullscreen triangle for vertices 0 to 2, past them it collapses and covers no samples
+            void main() {
+                uint vertex = gl_VertexIndex % 3;
+                vec2 uv = vec2((vertex << 1) & 2, vertex & 2);
+                float scale = gl_VertexIndex < 3 ? 1.0 : 0.0;
+                gl_Position = vec4((uv * 2.0 - 1.0) * scale, 0.0, 1.0);
+            }
+        ",
+    }
+}
+
+#[test]
+fn indirect_culling_matches_cpu_frustum_test() {
+    let Some(toolset) = headless_toolset() else { return };
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    // A grid of spheres around and behind the camera, about half of them visible
+    let objects = (0..200u32).map(|i| CullObject {
+        center : [(i % 20) as f32 * 2.0 - 19.0, 0.0, (i / 20) as f32 * -4.0 + 10.0],
+        radius : 0.5,
+        command : DrawIndirectCommand { vertex_count : 36, instance_count : 1, first_vertex : 0, first_instance : i },
+    }).collect::<Vec<_>>();
+    let frustum = Frustum::from_view_projection(&Camera::perspective([0.0, 0.0, 5.0], [0.0, 0.0, 0.0], 1.0).view_projection(1.0));
+
+    let mut expected = objects.iter()
+    .filter(|object| frustum.intersects_sphere(object.center, object.radius))
+    .map(|object| object.command.first_instance)
+    .collect::<Vec<_>>();
+    assert!(!expected.is_empty() && expected.len() < objects.len());
+
+    let object_buffer = upload_buffer(allocator, queue, BufferUsage::STORAGE_BUFFER, objects.iter().copied()).unwrap();
+    let draws = IndirectCulling::create_draw_buffer(&toolset, objects.len() as u64).unwrap();
+    let count = IndirectCulling::create_count_buffer(&toolset).unwrap();
+    let culling = IndirectCulling::new(&toolset);
+
+    // Run twice, stale draws from the first pass must not survive the second
+    for _ in 0..2 {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+        culling.record(&mut builder, &object_buffer, &frustum, &draws, &count).unwrap();
+
+        sync::now(toolset.logical_device.clone())
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+    }
+
+    let draw_count = read_back_buffer(&toolset, &count.clone().reinterpret::<[u32]>()).unwrap()[0];
+    let commands = read_back_buffer(&toolset, &draws).unwrap();
+
+    // Compaction order depends on scheduling
+    let mut visible = commands[..draw_count as usize].iter().map(|command| command.first_instance).collect::<Vec<_>>();
+    visible.sort_unstable();
+    expected.sort_unstable();
+    assert_eq!(visible, expected);
+    assert!(commands[draw_count as usize..].iter().all(|command| command.instance_count == 0));
+}
+
+#[test]
+fn frustum_culler_zeroes_culled_slots_and_counts_the_visible_ones() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    // Same grid as the compacting test, every object keeps its own slot here
+    let spheres = (0..200u32).map(|i| BoundingSphere {
+        center : [(i % 20) as f32 * 2.0 - 19.0, 0.0, (i / 20) as f32 * -4.0 + 10.0],
+        radius : 0.5,
+    }).collect::<Vec<_>>();
+    let frustum = Frustum::from_view_projection(&Camera::perspective([0.0, 0.0, 5.0], [0.0, 0.0, 0.0], 1.0).view_projection(1.0));
+    let expected = spheres.iter()
+    .map(|sphere| frustum.intersects_sphere(sphere.center, sphere.radius) as u32)
+    .collect::<Vec<_>>();
+    let visible = expected.iter().sum::<u32>();
+    assert!(visible > 0 && visible < spheres.len() as u32);
+
+    let commands = (0..spheres.len() as u32).map(|i| DrawIndirectCommand { vertex_count : 3, instance_count : 1, first_vertex : 0, first_instance : i });
+    let sphere_buffer = upload_buffer(allocator, queue, BufferUsage::STORAGE_BUFFER, spheres.iter().copied()).unwrap();
+    let culled_draws = upload_buffer(allocator, queue, BufferUsage::STORAGE_BUFFER | BufferUsage::INDIRECT_BUFFER, commands.clone()).unwrap();
+    let all_draws = upload_buffer(allocator, queue, BufferUsage::INDIRECT_BUFFER, commands).unwrap();
+    let count = IndirectCulling::create_count_buffer(&toolset).unwrap();
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    FrustumCuller::new(&toolset).record(&mut builder, &sphere_buffer, &frustum, &culled_draws, &count).unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let instance_counts = read_back_buffer(&toolset, &culled_draws).unwrap()
+    .iter()
+    .map(|command| command.instance_count)
+    .collect::<Vec<_>>();
+    assert_eq!(instance_counts, expected);
+    assert_eq!(read_back_buffer(&toolset, &count.clone().reinterpret::<[u32]>()).unwrap()[0], visible);
+
+    if !device.enabled_features().pipeline_statistics_query {
+        eprintln!("skipping the culled versus all comparison: pipeline statistics queries are not supported");
+        return;
+    }
+
+    // Compare the vertices assembled for the culled slots against drawing every object
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let target = Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(target).unwrap()]).unwrap();
+    let triangle = Triangle::new(allocator.general_allocator.clone(), device).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let pipeline = toolset.create_graphics_pipeline_for(&triangle.vertex_shader, Some(&triangle.fragment_shader), Subpass::from(render_pass, 0).unwrap(), viewport);
+    let stats = PipelineStatsPool::new(device.clone(), QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES);
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    for (query_id, draws) in [&all_draws, &culled_draws].into_iter().enumerate() {
+        stats.begin_stats(&mut builder, query_id as u32);
+        builder.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into())],
+                ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
+            },
+            SubpassBeginInfo {
+                contents: SubpassContents::Inline,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .bind_pipeline_graphics(pipeline.clone())
+        .unwrap()
+        .bind_vertex_buffers(0, triangle.vertex_buffer.clone())
+        .unwrap();
+        toolset.record_multi_draw_indirect(&mut builder, draws, draws.len() as u32).unwrap();
+        builder.end_render_pass(SubpassEndInfo::default()).unwrap();
+        stats.end_stats(&mut builder, query_id as u32);
+    }
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let vertices = |query_id| stats.read(query_id).unwrap()[&QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES];
+    let (all, culled) = (vertices(0), vertices(1));
+    eprintln!("frustum culling: {culled} of {all} vertices assembled, {visible} of {} objects visible", spheres.len());
+    assert_eq!(all, spheres.len() as u64 * 3);
+    assert_eq!(culled, visible as u64 * 3);
+}
+
+#[test]
+fn occlusion_culler_drops_objects_hidden_for_two_frames() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    // Half of the objects in front of the camera and half behind it. Odd objects in front draw
+    // nothing, so their queries never pass a sample
+    const OBJECTS : u32 = 1000;
+    let spheres = (0..OBJECTS).map(|i| BoundingSphere {
+        center : [(i % 25) as f32 * 0.04 - 0.5, (i / 25 % 20) as f32 * 0.04 - 0.4, if i < OBJECTS / 2 { 0.0 } else { 10.0 }],
+        radius : 0.01,
+    }).collect::<Vec<_>>();
+    let frustum = Frustum::from_view_projection(&Camera::perspective([0.0, 0.0, 5.0], [0.0, 0.0, 0.0], 1.0).view_projection(1.0));
+    let in_frustum = |i : u32| frustum.intersects_sphere(spheres[i as usize].center, spheres[i as usize].radius);
+    assert!((0..OBJECTS / 2).all(in_frustum) && !(OBJECTS / 2..OBJECTS).any(in_frustum));
+
+    let commands = (0..OBJECTS).map(|i| DrawIndirectCommand { vertex_count : 3, instance_count : 1, first_vertex : if i % 2 == 0 { 0 } else { 3 }, first_instance : 0 });
+    let sphere_buffer = upload_buffer(allocator, queue, BufferUsage::STORAGE_BUFFER, spheres.iter().copied()).unwrap();
+    let draws = upload_buffer(allocator, queue, BufferUsage::STORAGE_BUFFER | BufferUsage::INDIRECT_BUFFER | BufferUsage::TRANSFER_SRC, commands).unwrap();
+    let count = IndirectCulling::create_count_buffer(&toolset).unwrap();
+    let mut culler = OcclusionCuller::new(&toolset, OBJECTS).unwrap();
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let target = Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(target).unwrap()]).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let vs = occlusion_vs::load(device.clone()).unwrap();
+    let triangle = Triangle::new(allocator.general_allocator.clone(), device).unwrap();
+    let pipeline = toolset.create_graphics_pipeline_for(&vs, Some(&triangle.fragment_shader), Subpass::from(render_pass, 0).unwrap(), viewport);
+
+    // Odd objects still draw on the frame their queries first come back empty and are dropped on the next
+    let mut visible_counts = Vec::new();
+    for _ in 0..4 {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+        culler.record_cull(&mut builder, &sphere_buffer, &frustum, &draws, &count).unwrap();
+        builder.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into())],
+                ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
+            },
+            SubpassBeginInfo {
+                contents: SubpassContents::Inline,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .bind_pipeline_graphics(pipeline.clone())
+        .unwrap();
+        culler.record_draws(&mut builder, &draws).unwrap();
+        builder.end_render_pass(SubpassEndInfo::default()).unwrap();
+
+        sync::now(device.clone())
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+        visible_counts.push(read_back_buffer(&toolset, &count.clone().reinterpret::<[u32]>()).unwrap()[0]);
+    }
+
+    assert_eq!(visible_counts, [OBJECTS / 2, OBJECTS / 2, OBJECTS / 4, OBJECTS / 4]);
+    assert!(visible_counts.iter().all(|&visible| visible <= OBJECTS / 2));
+
+    let instance_counts = read_back_buffer(&toolset, &draws).unwrap()
+    .iter()
+    .map(|command| command.instance_count)
+    .collect::<Vec<_>>();
+    let expected = (0..OBJECTS).map(|i| (i < OBJECTS / 2 && i % 2 == 0) as u32).collect::<Vec<_>>();
+    assert_eq!(instance_counts, expected);
+}
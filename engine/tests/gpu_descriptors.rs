@@ -0,0 +1,595 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{fullscreen_vs, headless_toolset, uniform_color_fs, SCREENSHOT_SIZE};
+use engine::{vulkan::{descriptor_ring::PerFrameDescriptorSets, extensions::ExtensionGuard, frame_sync::FrameSync, immutable_samplers::ImmutableSamplers, inline_uniform::InlineUniformBinding, render_pass::{create_framebuffer, RenderPassBuilder}, staging::{read_back_buffer, upload_buffer}, texture::Texture2D, vulkan::{ComputeShader, PipelineOptions}, vulkan_window::AttachmentConfig}, EngineError, VulkanToolset};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, layout::{DescriptorSetLayoutCreateFlags, DescriptorType}, PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{graphics::{color_blend::{ColorBlendAttachmentState, ColorBlendState}, input_assembly::InputAssemblyState, multisample::MultisampleState, rasterization::RasterizationState, vertex_input::VertexInputState, viewport::{Viewport, ViewportState}, GraphicsPipelineCreateInfo}, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineShaderStageCreateInfo},
+    render_pass::{Framebuffer, FramebufferCreateInfo, Subpass},
+    sync::{self, GpuFuture}
+};
+
+mod camera_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 1) in;
+
+            layout(set = 0, binding = 0) uniform Camera {
+                vec4 position;
+            } camera;
+
+            layout(set = 0, binding = 1) buffer Output {
+                float values[];
+            };
+
+            layout(push_constant) uniform Frame {
+                uint index;
+            } frame;
+
+            void main() {
+                values[frame.index] = camera.position.x;
+            }
+        ",
+    }
+}
+
+mod swizzle_texels_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) uniform samplerBuffer src;
+            layout(set = 0, binding = 1, rgba8) uniform writeonly imageBuffer dst;
+
+            layout(push_constant) uniform Elements {
+                uint count;
+            } elements;
+
+            void main() {
+                uint idx = gl_GlobalInvocationID.x;
+                if (idx >= elements.count) {
+                    return;
+                }
+
+                imageStore(dst, int(idx), texelFetch(src, int(idx)).bgra);
+            }
+        ",
+    }
+}
+
+mod textured_fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(set = 0, binding = 0) uniform sampler2D tex;
+
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                f_color = texture(tex, vec2(0.5));
+            }
+        ",
+    }
+}
+
+#[test]
+fn per_frame_descriptor_sets_follow_a_changing_camera() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    const FRAMES : u32 = 6;
+
+    let uniform = |x : f32| Buffer::from_data(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::UNIFORM_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        [x, 0.0, 0.0, 1.0],
+    ).expect("failed to create uniform buffer");
+
+    let output = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        (0..FRAMES).map(|_| -1.0f32),
+    ).expect("failed to create buffer");
+
+    let shader = camera_cs::load(device.clone()).expect("failed to create shader module");
+    let pipeline = ComputeShader::new(&shader, [1, 1, 1], device.clone()).pipeline;
+    let layout = pipeline.layout().set_layouts()[0].clone();
+
+    let mut frame_sync = FrameSync::new(2);
+    let mut descriptor_sets = PerFrameDescriptorSets::<2>::new(
+        Arc::new(StandardDescriptorSetAllocator::new(device.clone(), Default::default())),
+        layout,
+        &[WriteDescriptorSet::buffer(0, uniform(-1.0)), WriteDescriptorSet::buffer(1, output.clone())],
+    ).unwrap();
+
+    for frame in 0..FRAMES {
+        let frame_index = frame_sync.begin_frame();
+
+        // The camera moves every frame, only this frame's slot is rewritten
+        let other = descriptor_sets.current(frame_index + 1).clone();
+        descriptor_sets.update_current(frame_index, &[
+            WriteDescriptorSet::buffer(0, uniform(frame as f32 * 10.0)),
+            WriteDescriptorSet::buffer(1, output.clone()),
+        ]).unwrap();
+        assert!(Arc::ptr_eq(&other, descriptor_sets.current(frame_index + 1)));
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+
+        builder.bind_pipeline_compute(pipeline.clone())
+        .unwrap()
+        .bind_descriptor_sets(PipelineBindPoint::Compute, pipeline.layout().clone(), 0, descriptor_sets.current(frame_index).clone())
+        .unwrap()
+        .push_constants(pipeline.layout().clone(), 0, frame)
+        .unwrap()
+        .dispatch([1, 1, 1])
+        .unwrap();
+
+        let fence = frame_sync.previous_future(device)
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .boxed()
+        .then_signal_fence_and_flush()
+        .unwrap();
+        frame_sync.end_frame(Some(Arc::new(fence)));
+    }
+
+    // Cycling through every slot waits for all frames still in flight
+    for _ in 0..frame_sync.frames_in_flight() {
+        frame_sync.begin_frame();
+    }
+
+    let values = output.read().unwrap();
+    for (frame, value) in values.iter().enumerate() {
+        assert_eq!(*value, frame as f32 * 10.0, "frame {frame}");
+    }
+}
+
+#[test]
+fn push_descriptors_match_allocated_sets() {
+    let Some(mut toolset) = headless_toolset() else { return };
+    if !toolset.capabilities.push_descriptors {
+        eprintln!("skipping: khr_push_descriptor is not supported");
+        return;
+    }
+
+    let render_with = |toolset : &VulkanToolset| {
+        let device = &toolset.logical_device;
+        let queue = &toolset.device_queue;
+        let allocator = &toolset.memory_allocator;
+
+        let render_pass = vulkano::single_pass_renderpass!(
+            device.clone(),
+            attachments: {
+                color: {
+                    format: Format::R8G8B8A8_UNORM,
+                    samples: 1,
+                    load_op: Clear,
+                    store_op: Store,
+                },
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {},
+            },
+        ).unwrap();
+
+        let image = Image::new(
+            allocator.general_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R8G8B8A8_UNORM,
+                extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        ).unwrap();
+
+        let framebuffer = Framebuffer::new(
+            render_pass.clone(),
+            FramebufferCreateInfo {
+                attachments: vec![ImageView::new_default(image.clone()).unwrap()],
+                ..Default::default()
+            },
+        ).unwrap();
+
+        let stages = [
+            PipelineShaderStageCreateInfo::new(fullscreen_vs::load(device.clone()).unwrap().entry_point("main").unwrap()),
+            PipelineShaderStageCreateInfo::new(uniform_color_fs::load(device.clone()).unwrap().entry_point("main").unwrap()),
+        ];
+        let layout = toolset.create_pipeline_layout(&stages, Some(0), &[], &[]).unwrap();
+        let subpass = Subpass::from(render_pass, 0).unwrap();
+
+        let pipeline = GraphicsPipeline::new(
+            device.clone(),
+            None,
+            GraphicsPipelineCreateInfo {
+                stages: stages.into_iter().collect(),
+                vertex_input_state: Some(VertexInputState::new()),
+                input_assembly_state: Some(InputAssemblyState::default()),
+                viewport_state: Some(ViewportState {
+                    viewports: [Viewport {
+                        offset: [0.0, 0.0],
+                        extent: [SCREENSHOT_SIZE as f32, SCREENSHOT_SIZE as f32],
+                        depth_range: 0.0..=1.0,
+                    }].into_iter().collect(),
+                    ..Default::default()
+                }),
+                rasterization_state: Some(RasterizationState::default()),
+                multisample_state: Some(MultisampleState::default()),
+                color_blend_state: Some(ColorBlendState::with_attachment_states(
+                    subpass.num_color_attachments(),
+                    ColorBlendAttachmentState::default(),
+                )),
+                subpass: Some(subpass.into()),
+                ..GraphicsPipelineCreateInfo::layout(layout.clone())
+            },
+        ).unwrap();
+
+        let tint = Buffer::from_data(
+            allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            [0.0f32, 1.0, 0.0, 1.0],
+        ).unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+
+        builder.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![Some([0.0, 0.0, 1.0, 1.0].into())],
+                ..RenderPassBeginInfo::framebuffer(framebuffer)
+            },
+            SubpassBeginInfo {
+                contents: SubpassContents::Inline,
+                ..Default::default()
+            },
+        ).unwrap()
+        .bind_pipeline_graphics(pipeline)
+        .unwrap();
+        toolset.push_descriptors(&mut builder, 0, &layout, &[WriteDescriptorSet::buffer(0, tint)]).unwrap();
+        builder.draw(3, 1, 0, 0)
+        .unwrap()
+        .end_render_pass(SubpassEndInfo::default())
+        .unwrap();
+
+        sync::now(device.clone())
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+        let pushed = layout.set_layouts()[0].flags().intersects(DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR);
+        (pushed, toolset.readback_image(&image, queue).unwrap())
+    };
+
+    let (pushed, with_push) = render_with(&toolset);
+    assert!(pushed);
+
+    toolset.push_descriptors = ExtensionGuard::absent();
+    let (pushed, with_sets) = render_with(&toolset);
+    assert!(!pushed);
+
+    assert_eq!(&with_push[..4], [0, 255, 0, 255]);
+    assert!(with_push == with_sets, "push descriptor and descriptor set renders differ");
+}
+
+#[test]
+fn immutable_samplers_are_baked_into_the_layout_and_sampled() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    let texture = Texture2D::from_rgba_bytes(&toolset, 1, 1, &[0, 255, 0, 255]);
+    let vs = fullscreen_vs::load(device.clone()).unwrap();
+    let fs = textured_fs::load(device.clone()).unwrap();
+    let stages = [
+        PipelineShaderStageCreateInfo::new(vs.entry_point("main").unwrap()),
+        PipelineShaderStageCreateInfo::new(fs.entry_point("main").unwrap()),
+    ];
+
+    let samplers = ImmutableSamplers::new(0, 0, &[texture.sampler.clone()]);
+    let layout = toolset.create_pipeline_layout(&stages, None, &[samplers.clone()], &[]).unwrap();
+    assert_eq!(layout.set_layouts()[0].bindings()[&0].immutable_samplers, [texture.sampler.clone()]);
+
+    // Bindings that aren't declared, aren't samplers or have another descriptor count are refused
+    let missing = ImmutableSamplers::new(0, 3, &[texture.sampler.clone()]);
+    assert!(matches!(toolset.create_pipeline_layout(&stages, None, &[missing], &[]), Err(EngineError::UndeclaredBinding { set : 0, binding : 3 })));
+    let too_many = ImmutableSamplers::new(0, 0, &[texture.sampler.clone(), texture.sampler.clone()]);
+    assert!(matches!(toolset.create_pipeline_layout(&stages, None, &[too_many], &[]), Err(EngineError::UnsupportedFeature(_))));
+    let uniform_stages = [PipelineShaderStageCreateInfo::new(uniform_color_fs::load(device.clone()).unwrap().entry_point("main").unwrap())];
+    assert!(matches!(toolset.create_pipeline_layout(&uniform_stages, None, &[samplers.clone()], &[]), Err(EngineError::UnsupportedFeature(_))));
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let image = Image::new(
+        allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(image.clone()).unwrap()]).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let pipeline = toolset.create_configured_pipeline_for(&vs, Some(&fs), Subpass::from(render_pass, 0).unwrap(), viewport, PipelineOptions {
+        immutable_samplers : vec![samplers],
+        ..Default::default()
+    });
+
+    // The descriptor only carries the view, the sampler comes from the layout
+    let descriptor_set = PersistentDescriptorSet::new(
+        &toolset.memory_allocator.descriptor_set_allocator,
+        pipeline.layout().set_layouts()[0].clone(),
+        [texture.write_view_descriptor(0)],
+        [],
+    ).unwrap();
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    builder.begin_render_pass(
+        RenderPassBeginInfo {
+            clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into())],
+            ..RenderPassBeginInfo::framebuffer(framebuffer)
+        },
+        SubpassBeginInfo {
+            contents: SubpassContents::Inline,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .bind_pipeline_graphics(pipeline.clone())
+    .unwrap()
+    .bind_descriptor_sets(PipelineBindPoint::Graphics, pipeline.layout().clone(), 0, descriptor_set)
+    .unwrap()
+    .draw(3, 1, 0, 0)
+    .unwrap()
+    .end_render_pass(SubpassEndInfo::default())
+    .unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let pixels = toolset.readback_image(&image, queue).unwrap();
+    assert!(pixels.chunks(4).all(|texel| texel == [0, 255, 0, 255]));
+}
+
+#[test]
+fn inline_uniform_blocks_fall_back_to_uniform_buffers() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    let vs = fullscreen_vs::load(device.clone()).unwrap();
+    let fs = uniform_color_fs::load(device.clone()).unwrap();
+    let stages = [
+        PipelineShaderStageCreateInfo::new(vs.entry_point("main").unwrap()),
+        PipelineShaderStageCreateInfo::new(fs.entry_point("main").unwrap()),
+    ];
+
+    let tint = InlineUniformBinding::new(0, 0, 16);
+    let expected_type = match tint.is_inline(&toolset) {
+        true => DescriptorType::InlineUniformBlock,
+        false => DescriptorType::UniformBuffer,
+    };
+    let layout = toolset.create_pipeline_layout(&stages, None, &[], &[tint]).unwrap();
+    assert_eq!(layout.set_layouts()[0].bindings()[&0].descriptor_type, expected_type);
+
+    // Undeclared bindings, unaligned sizes and oversized writes are refused
+    let missing = InlineUniformBinding::new(0, 2, 16);
+    assert!(matches!(toolset.create_pipeline_layout(&stages, None, &[], &[missing]), Err(EngineError::UndeclaredBinding { set : 0, binding : 2 })));
+    let unaligned = InlineUniformBinding::new(0, 0, 15);
+    assert!(matches!(toolset.create_pipeline_layout(&stages, None, &[], &[unaligned]), Err(EngineError::UnsupportedFeature(_))));
+    assert!(matches!(tint.write(&toolset, &[0; 20]), Err(EngineError::BufferRange { .. })));
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let image = Image::new(
+        allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(image.clone()).unwrap()]).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let pipeline = toolset.create_configured_pipeline_for(&vs, Some(&fs), Subpass::from(render_pass, 0).unwrap(), viewport, PipelineOptions {
+        inline_uniform_blocks : vec![tint],
+        ..Default::default()
+    });
+
+    let color : Vec<u8> = [0.0f32, 1.0, 0.0, 1.0].iter().flat_map(|value| value.to_le_bytes()).collect();
+    let descriptor_set = PersistentDescriptorSet::new(
+        &toolset.memory_allocator.descriptor_set_allocator,
+        pipeline.layout().set_layouts()[0].clone(),
+        [tint.write(&toolset, &color).unwrap()],
+        [],
+    ).unwrap();
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    builder.begin_render_pass(
+        RenderPassBeginInfo {
+            clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into())],
+            ..RenderPassBeginInfo::framebuffer(framebuffer)
+        },
+        SubpassBeginInfo {
+            contents: SubpassContents::Inline,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .bind_pipeline_graphics(pipeline.clone())
+    .unwrap()
+    .bind_descriptor_sets(PipelineBindPoint::Graphics, pipeline.layout().clone(), 0, descriptor_set)
+    .unwrap()
+    .draw(3, 1, 0, 0)
+    .unwrap()
+    .end_render_pass(SubpassEndInfo::default())
+    .unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let pixels = toolset.readback_image(&image, queue).unwrap();
+    assert!(pixels.chunks(4).all(|texel| texel == [0, 255, 0, 255]));
+}
+
+#[test]
+fn texel_buffers_are_read_and_written_through_buffer_views() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    const TEXELS : u32 = 1000;
+    let texels = (0..TEXELS * 4).map(|i| (i * 37 % 256) as u8).collect::<Vec<_>>();
+
+    let input = upload_buffer(allocator, queue, BufferUsage::UNIFORM_TEXEL_BUFFER, texels.iter().copied()).unwrap();
+    let input_view = allocator.create_buffer_view(&input, Format::R8G8B8A8_UNORM).unwrap();
+    let output_view = allocator.create_texel_buffer(Format::R8G8B8A8_UNORM, TEXELS as u64, BufferUsage::STORAGE_TEXEL_BUFFER).unwrap();
+
+    // Views need a buffer with texel buffer usage
+    let plain = upload_buffer(allocator, queue, BufferUsage::STORAGE_BUFFER, texels.iter().copied()).unwrap();
+    assert!(matches!(allocator.create_buffer_view(&plain, Format::R8G8B8A8_UNORM), Err(EngineError::UnsupportedFeature(_))));
+
+    let shader = swizzle_texels_cs::load(device.clone()).unwrap();
+    let compute = ComputeShader::new(&shader, [64, 1, 1], device.clone());
+    let types = compute.info.descriptor_bindings.iter()
+    .map(|binding| binding.descriptor_types.clone())
+    .collect::<Vec<_>>();
+    assert_eq!(types, [vec![DescriptorType::UniformTexelBuffer], vec![DescriptorType::StorageTexelBuffer]]);
+
+    // The storage texel buffer left out is reported as such
+    let missing = compute.create_descriptor_sets(
+        &allocator.descriptor_set_allocator,
+        [(0, vec![WriteDescriptorSet::buffer_view(0, input_view.clone())])],
+    );
+    match missing {
+        Err(EngineError::MissingDescriptors { sets, bindings }) => {
+            assert!(sets.is_empty());
+            assert_eq!(bindings.iter().map(|binding| (binding.set, binding.binding)).collect::<Vec<_>>(), [(0, 1)]);
+            assert_eq!(bindings[0].descriptor_types, [DescriptorType::StorageTexelBuffer]);
+        },
+        Err(e) => panic!("expected missing descriptors, got {e}"),
+        Ok(_) => panic!("expected missing descriptors, got descriptor sets"),
+    }
+
+    compute.execute_with_writes(
+        allocator,
+        &allocator.descriptor_set_allocator,
+        queue,
+        [(0, vec![
+            WriteDescriptorSet::buffer_view(0, input_view),
+            WriteDescriptorSet::buffer_view(1, output_view.clone()),
+        ])],
+        TEXELS,
+    ).unwrap();
+
+    let expected = texels.chunks(4)
+    .flat_map(|texel| [texel[2], texel[1], texel[0], texel[3]])
+    .collect::<Vec<_>>();
+    assert_eq!(read_back_buffer(&toolset, output_view.buffer()).unwrap(), expected);
+}
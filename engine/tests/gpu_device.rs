@@ -0,0 +1,419 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{headless_toolset, headless_toolset_with, multiply_cs};
+use engine::{vulkan::{debug_utils::DebugUtils, device_info::DeviceLimits, extensions::ExtensionGuard, pipeline_desc::GraphicsPipelineDesc, raw_commands::{begin_raw_commands, submit_raw_and_wait}, render_pass::{create_framebuffer, RenderPassBuilder}, vertex::{Triangle, VulkanVertex}, vulkan::{ComputeShader, VulkanAllocation}, vulkan_window::AttachmentConfig}, AppConfig, EngineError, InstanceConfig, QueueRequest, QueueRole, VulkanToolset};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyImageToBufferInfo, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{graphics::viewport::Viewport, Pipeline, PipelineBindPoint},
+    sync::{self, AccessFlags, DependencyInfo, GpuFuture, MemoryBarrier, PipelineStages},
+    Version
+};
+
+#[test]
+fn objects_can_be_named_for_debugging() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+
+    assert_eq!(toolset.capabilities.debug_utils, device.instance().enabled_extensions().ext_debug_utils);
+
+    // Without debug utils this is a no-op, with them the driver must accept the names
+    let buffer = Buffer::from_iter(
+        toolset.memory_allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        },
+        0..16u32,
+    ).unwrap();
+    DebugUtils::name_object(device, buffer.buffer().as_ref(), "named test buffer");
+    DebugUtils::name_object(device, toolset.device_queue.as_ref(), "named test queue");
+}
+
+#[test]
+fn conditional_rendering_skips_dispatches_with_a_zero_predicate() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let allocator = &toolset.memory_allocator;
+    assert_eq!(toolset.conditional_rendering.is_present(), toolset.capabilities.conditional_rendering);
+    let Some(ext) = toolset.conditional_rendering.get() else { return };
+
+    let buffer = |usage, data : Vec<u32>| Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        data,
+    ).unwrap();
+    let predicates = buffer(BufferUsage::CONDITIONAL_RENDERING, vec![0, 1]);
+
+    // Usage and alignment are checked before anything is recorded
+    let mut builder = begin_raw_commands(&toolset).unwrap();
+    let storage = buffer(BufferUsage::STORAGE_BUFFER, vec![0]);
+    unsafe {
+        assert!(matches!(ext.begin(&mut builder, &storage.index(0), 0, false), Err(EngineError::UnsupportedFeature(_))));
+        assert!(matches!(ext.begin(&mut builder, &predicates.clone().index(1), 2, true), Err(EngineError::BufferRange { offset : 6, .. })));
+        assert!(matches!(ext.begin(&mut builder, &predicates.clone().index(1), 4, true), Err(EngineError::BufferRange { offset : 8, .. })));
+    }
+
+    // One buffer per dispatch, so they need no barriers between them
+    let shader = multiply_cs::load(device.clone()).expect("failed to create shader module");
+    let compute = ComputeShader::new(&shader, [64, 1, 1], device.clone());
+    let layout = compute.pipeline.layout();
+    let data : Vec<_> = (0..3).map(|_| buffer(BufferUsage::STORAGE_BUFFER, vec![1])).collect();
+    let sets : Vec<_> = data.iter()
+    .map(|data| PersistentDescriptorSet::new(
+        &allocator.descriptor_set_allocator,
+        layout.set_layouts()[0].clone(),
+        [WriteDescriptorSet::buffer(0, data.clone())],
+        [],
+    ).unwrap())
+    .collect();
+
+    // Zero skips, nonzero runs, inverted flips that
+    let blocks = [(0, false), (1, false), (0, true)];
+    unsafe {
+        builder.bind_pipeline_compute(&compute.pipeline).unwrap();
+        builder.push_constants(layout, 0, &1u32).unwrap();
+        for (set, (predicate, inverted)) in sets.iter().zip(blocks) {
+            builder.bind_descriptor_sets(PipelineBindPoint::Compute, layout, 0, &[set.clone().into()]).unwrap();
+            ext.begin(&mut builder, &predicates.clone().index(predicate), 0, inverted).unwrap();
+            builder.dispatch([1, 1, 1]).unwrap();
+            ext.end(&mut builder);
+        }
+    }
+    let command_buffer = builder.build().unwrap();
+    submit_raw_and_wait(&toolset.device_queue, &command_buffer).unwrap();
+
+    let results : Vec<u32> = data.iter().map(|data| data.read().unwrap()[0]).collect();
+    assert_eq!(results, [1, 13, 13]);
+}
+
+#[test]
+fn conditional_rendering_skips_draws_with_a_zero_predicate() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let allocator = &toolset.memory_allocator.general_allocator;
+    let Some(ext) = toolset.conditional_rendering.get() else { return };
+
+    const SIZE : u32 = 16;
+    let host_memory = || AllocationCreateInfo {
+        memory_type_filter: MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+        ..Default::default()
+    };
+    let predicates = Buffer::from_iter(
+        allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::CONDITIONAL_RENDERING,
+            ..Default::default()
+        },
+        host_memory(),
+        [0u32, 1],
+    ).unwrap();
+
+    // Left and right half of the target, two triangles each
+    let quad = |left : f32, right : f32| [[left, -1.0], [right, -1.0], [left, 1.0], [left, 1.0], [right, -1.0], [right, 1.0]];
+    let vertices = Buffer::from_iter(
+        allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        },
+        host_memory(),
+        quad(-1.0, 0.0).into_iter().chain(quad(0.0, 1.0)).map(|[x, y]| VulkanVertex::new(x, y)),
+    ).unwrap();
+    let readback = Buffer::new_slice::<[u8; 4]>(
+        allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        host_memory(),
+        (SIZE * SIZE) as u64,
+    ).unwrap();
+
+    // Nothing tracks the layouts of the raw command buffer, the render pass leaves the image ready to copy
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let image = Image::new(
+        allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SIZE, SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(image.clone()).unwrap()]).unwrap();
+
+    let triangle = Triangle::new(allocator.clone(), device).unwrap();
+    let pipeline = toolset.create_pipeline(GraphicsPipelineDesc {
+        render_pass_override : Some(render_pass),
+        viewport : Some(Viewport {
+            offset: [0.0, 0.0],
+            extent: [SIZE as f32; 2],
+            depth_range: 0.0..=1.0,
+        }),
+        ..GraphicsPipelineDesc::new(triangle.vertex_shader.clone(), triangle.fragment_shader.clone())
+    }).unwrap();
+
+    let mut builder = begin_raw_commands(&toolset).unwrap();
+    unsafe {
+        builder.begin_render_pass(
+            &RenderPassBeginInfo {
+                clear_values: vec![Some([0.0, 0.0, 0.0, 0.0].into())],
+                ..RenderPassBeginInfo::framebuffer(framebuffer)
+            },
+            &SubpassBeginInfo {
+                contents: SubpassContents::Inline,
+                ..Default::default()
+            },
+        ).unwrap();
+        builder.bind_pipeline_graphics(&pipeline).unwrap();
+        builder.bind_vertex_buffers(0, &[vertices.into_bytes()]).unwrap();
+        // The left half is predicated on the zero, the right half on the one
+        for (half, predicate) in [0, 1].into_iter().enumerate() {
+            ext.begin(&mut builder, &predicates.clone().index(predicate), 0, false).unwrap();
+            builder.draw(6, 1, half as u32 * 6, 0).unwrap();
+            ext.end(&mut builder);
+        }
+        builder.end_render_pass(&SubpassEndInfo::default()).unwrap();
+
+        builder.pipeline_barrier(&DependencyInfo {
+            memory_barriers: [MemoryBarrier {
+                src_stages: PipelineStages::COLOR_ATTACHMENT_OUTPUT,
+                src_access: AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_stages: PipelineStages::ALL_TRANSFER,
+                dst_access: AccessFlags::TRANSFER_READ,
+                ..Default::default()
+            }].into_iter().collect(),
+            ..Default::default()
+        }).unwrap();
+        builder.copy_image_to_buffer(&CopyImageToBufferInfo::image_buffer(image, readback.clone())).unwrap();
+    }
+    let command_buffer = builder.build().unwrap();
+    submit_raw_and_wait(&toolset.device_queue, &command_buffer).unwrap();
+
+    let pixels = readback.read().unwrap();
+    for (index, pixel) in pixels.iter().enumerate() {
+        let x = index as u32 % SIZE;
+        let expected = match x < SIZE / 2 {
+            true => [0, 0, 0, 0],
+            false => [255, 0, 0, 255],
+        };
+        assert_eq!(*pixel, expected, "pixel {x} {}", index as u32 / SIZE);
+    }
+}
+
+#[test]
+fn requested_queues_are_created_by_role_in_the_graphics_family() {
+    let config = AppConfig {
+        queues : vec![QueueRequest::new(QueueRole::Graphics, 1.0), QueueRequest::new(QueueRole::Transfer, 0.2)],
+        ..Default::default()
+    };
+    let Some(toolset) = headless_toolset_with(config) else { return };
+    let device = &toolset.logical_device;
+
+    let graphics = toolset.queue(QueueRole::Graphics);
+    let transfer = toolset.queue(QueueRole::Transfer);
+    assert!(Arc::ptr_eq(graphics, &toolset.device_queue));
+    assert_eq!(transfer.queue_family_index(), graphics.queue_family_index());
+
+    // Single queue families put every role on the graphics queue
+    let queue_count = device.physical_device().queue_family_properties()[graphics.queue_family_index() as usize].queue_count;
+    assert_eq!(Arc::ptr_eq(transfer, graphics), queue_count == 1);
+    // Roles that weren't requested fall back to it as well
+    assert!(Arc::ptr_eq(toolset.queue(QueueRole::Compute), graphics));
+
+    let buffer = |data : Vec<u32>| Buffer::from_iter(
+        toolset.memory_allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        data,
+    ).unwrap();
+    let source = buffer((0..16).collect());
+    let destination = buffer(vec![0; 16]);
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        transfer.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    VulkanAllocation::record_copy_buffer(&mut builder, &source, &destination).unwrap();
+
+    sync::now(device.clone())
+    .then_execute(transfer.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    assert_eq!(&*destination.read().unwrap(), (0..16).collect::<Vec<u32>>().as_slice());
+}
+
+#[test]
+fn extension_guards_follow_the_enabled_extensions() {
+    let Some(mut toolset) = headless_toolset() else { return };
+    let capabilities = toolset.capabilities;
+
+    assert_eq!(toolset.debug_labels.is_present(), capabilities.debug_utils);
+    assert_eq!(toolset.push_descriptors.is_present(), capabilities.push_descriptors);
+    assert_eq!(toolset.shading_rate.is_present(), capabilities.variable_rate_shading);
+    assert_eq!(toolset.inline_uniform_blocks.is_present(), capabilities.inline_uniform_blocks);
+    assert_eq!(toolset.conditional_rendering.is_present(), capabilities.conditional_rendering);
+    assert_eq!(toolset.conservative_raster.is_present(), capabilities.conservative_rasterization);
+
+    // Labeled recording runs the same commands with and without debug utils
+    for labels in [toolset.debug_labels.clone(), ExtensionGuard::absent()] {
+        toolset.debug_labels = labels;
+
+        let output = Buffer::from_iter(
+            toolset.memory_allocator.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            vec![0u32; 64],
+        ).unwrap();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &toolset.memory_allocator.buffer_allocator,
+            toolset.device_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+
+        let recorded = toolset.debug_labels.labeled(&mut builder, "fill", |builder| {
+            toolset.debug_labels.if_present(|ext| ext.insert_label(builder, "before fill"));
+            VulkanAllocation::record_fill_buffer(builder, &output, 7)
+        });
+        recorded.unwrap();
+
+        sync::now(toolset.logical_device.clone())
+        .then_execute(toolset.device_queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+        assert!(output.read().unwrap().iter().all(|&value| value == 7));
+    }
+}
+
+#[test]
+fn instance_is_created_with_the_configured_api_version() {
+    let config = AppConfig {
+        instance : InstanceConfig {
+            application_name : Some(String::from("gpu smoke")),
+            application_version : Version::major_minor(2, 1),
+            max_api_version : Some(Version::V1_1),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let Some(toolset) = headless_toolset_with(config) else { return };
+
+    assert_eq!(toolset.instance.max_api_version(), Version::V1_1);
+    assert!(toolset.instance.api_version() <= Version::V1_1);
+    assert!(toolset.api_version() <= toolset.instance.api_version());
+    assert!(toolset.api_version() >= Version::V1_0);
+}
+
+#[test]
+fn features_past_a_capped_api_version_stay_disabled() {
+    let config = AppConfig {
+        instance : InstanceConfig {
+            max_api_version : Some(Version::V1_1),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let Some(toolset) = headless_toolset_with(config) else { return };
+
+    // Even where the physical device supports 1.2, the capped instance can't use what depends on it
+    assert!(toolset.api_version() <= Version::V1_1);
+    assert!(!toolset.shading_rate.is_present());
+    assert!(!toolset.capabilities.variable_rate_shading);
+    assert!(!toolset.logical_device.enabled_features().timeline_semaphore);
+}
+
+#[test]
+fn api_versions_past_the_library_are_reported_instead_of_panicking() {
+    let Some(toolset) = headless_toolset() else { return };
+    let supported = toolset.instance.library().api_version();
+
+    let config = AppConfig {
+        instance : InstanceConfig {
+            max_api_version : Some(Version::major_minor(supported.major + 1, 0)),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    match VulkanToolset::try_headless(config) {
+        Err(EngineError::ApiVersion { supported : reported, .. }) => assert_eq!(reported, supported),
+        other => panic!("expected an api version error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn device_info_describes_the_device_the_toolset_runs_on() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let properties = device.physical_device().properties();
+
+    let info = toolset.device_info();
+    assert_eq!(info.device_name, properties.device_name);
+    assert_eq!((info.vendor_id, info.device_id), (properties.vendor_id, properties.device_id));
+    assert_eq!(info.api_version, toolset.api_version().to_string());
+    assert!(!info.driver_version.is_empty());
+
+    let graphics = info.queues.iter().find(|queue| queue.role == QueueRole::Graphics).unwrap();
+    assert_eq!(graphics.family_index, toolset.device_queue.queue_family_index());
+    assert!(graphics.flags.contains("GRAPHICS"));
+
+    assert_eq!(info.enabled_extensions.contains(&"khr_swapchain".to_owned()), device.enabled_extensions().khr_swapchain);
+    assert_eq!(info.enabled_features.contains(&"large_points".to_owned()), device.enabled_features().large_points);
+    assert_eq!(info.limits, DeviceLimits::from_physical_device(device.physical_device()));
+    assert!(info.to_string().starts_with(&properties.device_name));
+
+    // Work past the limits fails before it reaches the driver
+    let shader = multiply_cs::load(device.clone()).unwrap();
+    let too_wide = [info.limits.max_compute_work_group_size[0] + 1, 1, 1];
+    assert!(matches!(ComputeShader::with_entry_point(&shader, "main", too_wide, device.clone()), Err(EngineError::DeviceLimit { .. })));
+}
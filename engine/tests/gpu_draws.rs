@@ -0,0 +1,379 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::{headless_toolset, SCREENSHOT_SIZE};
+use engine::{vulkan::{render_pass::{create_framebuffer, RenderPassBuilder}, staging::upload_buffer, vertex::{Triangle, VulkanVertex}, vertex_pull::VertexPullPipeline, vulkan::{ComputePass, ComputeShader, DrawCall, FrameTarget, IndirectDraws, PipelineOptions, RenderCommand}, vulkan_window::AttachmentConfig}, EngineError};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::DrawIndirectCommand,
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{graphics::viewport::Viewport, Pipeline, PipelineBindPoint},
+    render_pass::Subpass,
+    sync::{self, GpuFuture}
+};
+
+mod fullscreen_triangle_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) writeonly buffer Vertices {
+                vec2 positions[];
+            };
+
+            // A triangle covering the whole clip volume
+            void main() {
+                uint idx = gl_GlobalInvocationID.x;
+                if (idx >= 3) {
+                    return;
+                }
+
+                vec2 uv = vec2((idx << 1) & 2, idx & 2);
+                positions[idx] = uv * 2.0 - 1.0;
+            }
+        ",
+    }
+}
+
+mod point_grid_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) writeonly buffer Points {
+                vec4 positions[];
+            };
+
+            layout(push_constant) uniform Grid {
+                uint columns;
+                uint spacing;
+                uint size;
+            } grid;
+
+            // One point at the center of every spacing-th pixel, starting half a spacing in
+            void main() {
+                uint idx = gl_GlobalInvocationID.x;
+                if (idx >= positions.length()) {
+                    return;
+                }
+
+                uvec2 pixel = uvec2(idx % grid.columns, idx / grid.columns) * grid.spacing + grid.spacing / 2;
+                positions[idx] = vec4((vec2(pixel) + 0.5) / float(grid.size) * 2.0 - 1.0, 0.0, 1.0);
+            }
+        ",
+    }
+}
+
+mod pulled_points_vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(set = 0, binding = 0) readonly buffer Points {
+                vec4 positions[];
+            };
+
+            void main() {
+                gl_Position = positions[gl_VertexIndex];
+                gl_PointSize = 1.0;
+            }
+        ",
+    }
+}
+
+#[test]
+fn compute_pre_pass_writes_vertices_drawn_in_the_same_frame() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    // Starts out degenerate, only the compute pass makes the triangle visible
+    let vertices = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER | BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        },
+        [VulkanVertex::new(0.0, 0.0); 3],
+    ).unwrap();
+
+    let shader = fullscreen_triangle_cs::load(device.clone()).unwrap();
+    let compute = ComputeShader::new(&shader, [64, 1, 1], device.clone());
+    let descriptor_set = PersistentDescriptorSet::new(
+        &allocator.descriptor_set_allocator,
+        compute.pipeline.layout().set_layouts()[0].clone(),
+        [WriteDescriptorSet::buffer(0, vertices.clone())],
+        [],
+    ).unwrap();
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let image = Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(image.clone()).unwrap()]).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let triangle = Triangle::new(allocator.general_allocator.clone(), device).unwrap();
+    let pipeline = toolset.create_configured_pipeline_for(&triangle.vertex_shader, Some(&triangle.fragment_shader), Subpass::from(render_pass, 0).unwrap(), viewport, PipelineOptions::default());
+
+    // The same compute pass and draw list a Frame hands to the recorder
+    let group_counts = compute.group_counts([3, 1, 1]);
+    let compute_pipeline = compute.pipeline.clone();
+    let compute_pass : ComputePass = Box::new(move |builder| {
+        builder.bind_pipeline_compute(compute_pipeline.clone())
+        .unwrap()
+        .bind_descriptor_sets(PipelineBindPoint::Compute, compute_pipeline.layout().clone(), 0, descriptor_set)
+        .unwrap()
+        .dispatch(group_counts)
+        .unwrap();
+    });
+    let draw = RenderCommand::Draw(DrawCall {
+        pipeline,
+        vertex_buffer : Some(vertices.clone().into_bytes()),
+        descriptor_sets : Vec::new(),
+        vertex_count : 3,
+        scissor : None,
+        indirect : None,
+        occlusion_query : None,
+    });
+    let command_buffer = toolset.create_frame_command_buffer(&allocator.buffer_allocator, FrameTarget::Framebuffer(&framebuffer), [0.0, 0.0, 1.0, 1.0], vec![compute_pass], vec![draw], &[], None, None);
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), command_buffer)
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let pixels = toolset.readback_image(&image, queue).unwrap();
+    assert!(pixels.chunks(4).all(|texel| texel == [255, 0, 0, 255]));
+}
+
+#[test]
+fn indirect_draws_use_the_vertex_counts_from_the_buffer() {
+    let Some(mut toolset) = headless_toolset() else { return };
+    let device = toolset.logical_device.clone();
+    let queue = toolset.device_queue.clone();
+
+    // One full height quad per third of the target, as two triangles each
+    let third = 2.0 / 3.0;
+    let vertices = (0..3).flat_map(|column| {
+        let (x0, x1) = (-1.0 + column as f32 * third, -1.0 + (column + 1) as f32 * third);
+        [(x0, -1.0), (x1, -1.0), (x0, 1.0), (x1, -1.0), (x1, 1.0), (x0, 1.0)].map(|(x, y)| VulkanVertex::new(x, y))
+    });
+    let vertices = upload_buffer(&toolset.memory_allocator, &queue, BufferUsage::VERTEX_BUFFER, vertices).unwrap();
+
+    // The whole first quad, the upper left triangle of the second, nothing of the third
+    let commands = toolset.memory_allocator.create_indirect_buffer([6, 3, 0].into_iter().enumerate().map(|(column, vertex_count)| DrawIndirectCommand {
+        vertex_count,
+        instance_count : 1,
+        first_vertex : column as u32 * 6,
+        first_instance : 0,
+    })).unwrap();
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(&device)
+    .unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let triangle = Triangle::new(toolset.memory_allocator.general_allocator.clone(), &device).unwrap();
+    let pipeline = toolset.create_graphics_pipeline_for(&triangle.vertex_shader, Some(&triangle.fragment_shader), Subpass::from(render_pass.clone(), 0).unwrap(), viewport);
+
+    // Red where a pixel was drawn, with one multi draw command and with one command per draw
+    let supported = toolset.capabilities.multi_draw_indirect;
+    let mut render = |multi_draw_indirect : bool| {
+        toolset.capabilities.multi_draw_indirect = multi_draw_indirect;
+
+        let image = Image::new(
+            toolset.memory_allocator.general_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R8G8B8A8_UNORM,
+                extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        ).unwrap();
+        let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(image.clone()).unwrap()]).unwrap();
+
+        let draw = RenderCommand::Draw(DrawCall {
+            pipeline : pipeline.clone(),
+            vertex_buffer : Some(vertices.clone().into_bytes()),
+            descriptor_sets : Vec::new(),
+            vertex_count : 0,
+            scissor : None,
+            indirect : Some(IndirectDraws { commands : commands.clone(), draw_count : 3 }),
+            occlusion_query : None,
+        });
+        let command_buffer = toolset.create_frame_command_buffer(&toolset.memory_allocator.buffer_allocator, FrameTarget::Framebuffer(&framebuffer), [0.0, 0.0, 0.0, 1.0], Vec::new(), vec![draw], &[], None, None);
+
+        sync::now(device.clone())
+        .then_execute(queue.clone(), command_buffer)
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+        let pixels = toolset.readback_image(&image, &queue).unwrap();
+        move |x : u32, y : u32| pixels[((y * SCREENSHOT_SIZE + x) * 4) as usize] == 255
+    };
+
+    for multi_draw_indirect in [supported, false] {
+        let red = render(multi_draw_indirect);
+
+        assert!(red(10, 8) && red(10, 56));
+        assert!(red(26, 8));
+        assert!(!red(38, 56));
+        assert!(!red(53, 8) && !red(53, 56));
+    }
+}
+
+#[test]
+fn pulled_points_are_drawn_at_the_positions_a_compute_pass_wrote() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    const COLUMNS : u32 = 4;
+    const SPACING : u32 = SCREENSHOT_SIZE / COLUMNS;
+
+    // Outside of the clip volume until the compute pass moves them, no vertex buffer usage needed
+    let points = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        },
+        [[2.0f32, 2.0, 0.0, 1.0]; (COLUMNS * COLUMNS) as usize],
+    ).unwrap();
+
+    let shader = point_grid_cs::load(device.clone()).unwrap();
+    let compute = ComputeShader::new(&shader, [64, 1, 1], device.clone());
+    let compute_set = PersistentDescriptorSet::new(
+        &allocator.descriptor_set_allocator,
+        compute.pipeline.layout().set_layouts()[0].clone(),
+        [WriteDescriptorSet::buffer(0, points.clone())],
+        [],
+    ).unwrap();
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let image = Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(image.clone()).unwrap()]).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let subpass = Subpass::from(render_pass, 0).unwrap();
+
+    let vs = pulled_points_vs::load(device.clone()).unwrap();
+    let triangle = Triangle::new(allocator.general_allocator.clone(), device).unwrap();
+    let undeclared = VertexPullPipeline::new(&toolset, subpass.clone(), viewport.clone(), 1, COLUMNS * COLUMNS, &vs, &triangle.fragment_shader);
+    assert!(matches!(undeclared, Err(EngineError::UndeclaredBinding { set : 0, binding : 1 })));
+
+    let pull = VertexPullPipeline::new(&toolset, subpass, viewport, 0, COLUMNS * COLUMNS, &vs, &triangle.fragment_shader).unwrap();
+    let draw_set = pull.descriptor_set(&toolset, &points).unwrap();
+
+    let group_counts = compute.group_counts([COLUMNS * COLUMNS, 1, 1]);
+    let compute_pipeline = compute.pipeline.clone();
+    let compute_pass : ComputePass = Box::new(move |builder| {
+        builder.bind_pipeline_compute(compute_pipeline.clone())
+        .unwrap()
+        .bind_descriptor_sets(PipelineBindPoint::Compute, compute_pipeline.layout().clone(), 0, compute_set)
+        .unwrap()
+        .push_constants(compute_pipeline.layout().clone(), 0, point_grid_cs::Grid { columns : COLUMNS, spacing : SPACING, size : SCREENSHOT_SIZE })
+        .unwrap()
+        .dispatch(group_counts)
+        .unwrap();
+    });
+    let draw = RenderCommand::Record(Box::new(move |builder| pull.record(builder, draw_set).unwrap()));
+    let command_buffer = toolset.create_frame_command_buffer(&allocator.buffer_allocator, FrameTarget::Framebuffer(&framebuffer), [0.0, 0.0, 0.0, 1.0], vec![compute_pass], vec![draw], &[], None, None);
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), command_buffer)
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    // Exactly the grid pixels are lit
+    let data = toolset.readback_image_data(&image, queue).unwrap();
+    for y in 0..SCREENSHOT_SIZE {
+        for x in 0..SCREENSHOT_SIZE {
+            let on_grid = x % SPACING == SPACING / 2 && y % SPACING == SPACING / 2;
+            let expected : [u8; 4] = if on_grid { [255, 0, 0, 255] } else { [0, 0, 0, 255] };
+            assert_eq!(data.texel(x, y), expected, "pixel ({x}, {y})");
+        }
+    }
+}
@@ -0,0 +1,353 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::{headless_toolset, headless_toolset_with};
+use engine::{vulkan::{frame_sync::FrameSync, memory_stats::AllocationCategory, staging::{read_back_buffer, upload_buffer}, timeline::TimelineSemaphore, vulkan::{ComputeShader, VulkanAllocation}}, AppConfig, CommandBufferOptions, EngineError, QueueRequest, QueueRole};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferInfo},
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    device::Queue,
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{graphics::vertex_input::Vertex, Pipeline, PipelineBindPoint},
+    sync::GpuFuture
+};
+
+// Appends its push constant to a log, entries show the order in which dispatches ran
+mod append_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) buffer Log {
+                uint count;
+                uint entries[];
+            };
+
+            layout(push_constant) uniform Entry {
+                uint value;
+            };
+
+            void main() {
+                entries[atomicAdd(count, 1)] = value;
+            }
+        ",
+    }
+}
+
+#[test]
+fn timeline_semaphore_alternates_work_between_the_graphics_and_compute_queues() {
+    let config = AppConfig {
+        queues : vec![QueueRequest::new(QueueRole::Graphics, 1.0), QueueRequest::new(QueueRole::Compute, 0.5)],
+        ..AppConfig::default()
+    };
+    let Some(toolset) = headless_toolset_with(config) else { return };
+    let device = &toolset.logical_device;
+    let allocator = &toolset.memory_allocator;
+
+    if !toolset.capabilities.timeline_semaphores {
+        assert!(matches!(TimelineSemaphore::new(device, 0), Err(EngineError::UnsupportedFeature(_))));
+        eprintln!("skipping: timeline semaphores are not supported");
+        return;
+    }
+
+    // The host can read, signal and wait for the counter
+    let semaphore = TimelineSemaphore::new(device, 5).unwrap();
+    assert_eq!(semaphore.value().unwrap(), 5);
+    semaphore.signal(7).unwrap();
+    assert!(semaphore.wait(6, Some(Duration::ZERO)).unwrap());
+    assert!(!semaphore.wait(8, Some(Duration::from_millis(1))).unwrap());
+    drop(semaphore);
+
+    const FRAMES : u32 = 100;
+    let log = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        vec![0u32; 1 + 2 * FRAMES as usize],
+    ).unwrap();
+
+    let shader = append_cs::load(device.clone()).expect("failed to create shader module");
+    let compute = ComputeShader::new(&shader, [1, 1, 1], device.clone());
+    let layout = compute.pipeline.layout().clone();
+    let set = PersistentDescriptorSet::new(
+        &allocator.descriptor_set_allocator,
+        layout.set_layouts()[0].clone(),
+        [WriteDescriptorSet::buffer(0, log.clone())],
+        [],
+    ).unwrap();
+
+    let append = |queue : &Arc<Queue>, value : u32| {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+        builder.bind_pipeline_compute(compute.pipeline.clone())
+        .unwrap()
+        .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, set.clone())
+        .unwrap()
+        .push_constants(layout.clone(), 0, append_cs::Entry { value })
+        .unwrap()
+        .dispatch([1, 1, 1])
+        .unwrap();
+
+        builder.build().unwrap()
+    };
+
+    // Frame n runs on graphics once compute finished frame n - 1 and signals 2n - 1, compute waits for
+    // that and signals 2n. The command buffers have to outlive the submissions
+    let graphics = toolset.queue(QueueRole::Graphics);
+    let compute_queue = toolset.queue(QueueRole::Compute);
+    let semaphore = TimelineSemaphore::new(device, 0).unwrap();
+    let mut in_flight = Vec::new();
+    for frame in 1..=FRAMES as u64 {
+        let rendered = append(graphics, 2 * frame as u32 - 1);
+        semaphore.submit(graphics, &[rendered.clone()], Some(2 * frame - 2), Some(2 * frame - 1)).unwrap();
+
+        let computed = append(compute_queue, 2 * frame as u32);
+        semaphore.submit(compute_queue, &[computed.clone()], Some(2 * frame - 1), Some(2 * frame)).unwrap();
+
+        in_flight.extend([rendered, computed]);
+    }
+
+    assert!(semaphore.wait(2 * FRAMES as u64, Some(Duration::from_secs(10))).unwrap());
+    assert_eq!(semaphore.value().unwrap(), 2 * FRAMES as u64);
+    drop(in_flight);
+
+    let log = log.read().unwrap();
+    assert_eq!(log[0], 2 * FRAMES);
+    assert_eq!(log[1..], (1..=2 * FRAMES).collect::<Vec<_>>());
+}
+
+#[test]
+fn frame_sync_counts_finished_frames_on_its_timeline() {
+    let Some(toolset) = headless_toolset() else { return };
+    if !toolset.capabilities.timeline_semaphores {
+        eprintln!("skipping: timeline semaphores are not supported");
+        return;
+    }
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+
+    let mut frame_sync = FrameSync::new(2);
+    frame_sync.enable_timeline(queue).unwrap();
+    assert_eq!(frame_sync.completed_frames(), Some(0));
+
+    let output = Buffer::from_iter(
+        toolset.memory_allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        vec![0u32; 64],
+    ).unwrap();
+
+    for frame in 1..=100u32 {
+        frame_sync.begin_frame();
+        // Two frames in flight, so the one before the previous frame finished
+        assert!(frame_sync.completed_frames().unwrap() + 2 >= frame as u64, "frame {frame}");
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &toolset.memory_allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+        VulkanAllocation::record_fill_buffer(&mut builder, &output, frame).unwrap();
+
+        let fence = frame_sync.previous_future(device)
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .boxed()
+        .then_signal_fence_and_flush()
+        .unwrap();
+        frame_sync.end_frame(Some(Arc::new(fence)));
+    }
+
+    frame_sync.wait_all().unwrap();
+    assert_eq!(frame_sync.completed_frames(), Some(100));
+    assert!(output.read().unwrap().iter().all(|&value| value == 100));
+}
+
+#[test]
+fn abandoned_frames_are_not_waited_for_again() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+
+    let output = Buffer::from_iter(
+        toolset.memory_allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        },
+        vec![0u32; 64],
+    ).unwrap();
+
+    let mut frame_sync = FrameSync::new(3);
+    for frame in 1..=2u32 {
+        frame_sync.try_begin_frame().unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &toolset.memory_allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+        VulkanAllocation::record_fill_buffer(&mut builder, &output, frame).unwrap();
+
+        let fence = frame_sync.previous_future(device)
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .boxed()
+        .then_signal_fence_and_flush()
+        .unwrap();
+        frame_sync.end_frame(Some(Arc::new(fence)));
+    }
+    assert_eq!(frame_sync.pending_frames(), 2);
+
+    // What a lost device leaves behind, the next frames start without their fences
+    frame_sync.abandon_frames();
+    assert_eq!(frame_sync.pending_frames(), 0);
+    assert_eq!(frame_sync.try_begin_frame().unwrap(), 2);
+    assert_eq!(frame_sync.try_begin_frame().unwrap(), 0);
+    frame_sync.wait_all().unwrap();
+}
+
+#[test]
+fn frame_slots_reuse_their_command_pools_over_a_long_run() {
+    let Some(toolset) = headless_toolset() else { return };
+    const FRAMES : u64 = 10_000;
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+
+    let target = upload_buffer(&toolset.memory_allocator, queue, BufferUsage::STORAGE_BUFFER, vec![0u32; 64]).unwrap();
+    let baseline = toolset.memory_allocator.stats();
+
+    let options = CommandBufferOptions { primary_buffer_count : 1, secondary_buffer_count : 0 };
+    let mut frame_sync = FrameSync::with_command_allocators(2, device, queue.queue_family_index(), options);
+
+    for frame in 0..FRAMES {
+        frame_sync.begin_frame();
+
+        // Re-recorded every frame, like the engine's frame command buffer
+        let mut builder = AutoCommandBufferBuilder::primary(
+            frame_sync.command_allocator().unwrap(),
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+        VulkanAllocation::record_fill_buffer(&mut builder, &target, frame as u32).unwrap();
+
+        let fence = frame_sync.previous_future(device)
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .boxed()
+        .then_signal_fence_and_flush()
+        .unwrap();
+        frame_sync.end_frame(Some(Arc::new(fence)));
+
+        if frame % 1000 == 0 {
+            assert_eq!(toolset.memory_allocator.stats(), baseline, "frame {frame}");
+        }
+    }
+    frame_sync.wait_all().unwrap();
+
+    // Every frame started from a freshly reset pool instead of a new one
+    assert_eq!(frame_sync.pool_resets(), FRAMES);
+    assert_eq!(toolset.memory_allocator.stats(), baseline);
+    assert!(read_back_buffer(&toolset, &target).unwrap().iter().all(|&value| value == FRAMES as u32 - 1));
+}
+
+#[test]
+fn replacing_a_vertex_buffer_every_frame_keeps_a_bounded_number_alive() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocation = &toolset.memory_allocator;
+    const FRAMES : u32 = 3000;
+
+    let vertex_buffer = |frame : u32| {
+        let buffer = Buffer::from_iter(
+            allocation.general_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER | BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            vec![frame; 256],
+        ).unwrap();
+        allocation.tracker.track_buffer(AllocationCategory::Vertex, buffer.buffer());
+        buffer
+    };
+    let output = Buffer::from_iter(
+        allocation.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        vec![0u32; 256],
+    ).unwrap();
+
+    let mut frame_sync = FrameSync::new(2);
+    let mut current = vertex_buffer(0);
+    for frame in 1..=FRAMES {
+        frame_sync.begin_frame();
+
+        // The previous frame may still be reading the old buffer
+        let old = std::mem::replace(&mut current, vertex_buffer(frame));
+        frame_sync.retire(old);
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &allocation.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+        builder.copy_buffer(CopyBufferInfo::buffers(current.clone(), output.clone())).unwrap();
+
+        let fence = frame_sync.previous_future(device)
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .boxed()
+        .then_signal_fence_and_flush()
+        .unwrap();
+        frame_sync.end_frame(Some(Arc::new(fence)));
+
+        // One retired buffer per slot at most, plus the current one
+        assert!(frame_sync.deletions().pending() <= frame_sync.frames_in_flight(), "frame {frame}");
+        let vertex_buffers = allocation.stats().categories.get(&AllocationCategory::Vertex).map_or(0, |stats| stats.count);
+        assert!(vertex_buffers <= frame_sync.frames_in_flight() + 1, "frame {frame}: {vertex_buffers} vertex buffers alive");
+    }
+
+    frame_sync.wait_all().unwrap();
+    assert_eq!(frame_sync.deletions().pending(), 0);
+    assert_eq!(frame_sync.deletions().released(), FRAMES as u64);
+    assert!(output.read().unwrap().iter().all(|&value| value == FRAMES));
+}
@@ -0,0 +1,274 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::{headless_toolset, mandelbrot_cs, mandelbrot_image};
+use engine::{render::shadow_map::ShadowMapPass, vulkan::{blit::{blit_image, blit_image_fit, resample_image, BlitFit}, texture::{copy_bytes_to_image, upload_image_view, ImageRegion, Texture2D}, vulkan::{ComputeShader, VulkanAllocation}}, EngineError, ImageData, SaveFormat};
+use image::{Rgba, RgbaImage};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, BufferImageCopy, CommandBufferUsage, CopyImageToBufferInfo, ImageCopy},
+    format::{ClearColorValue, Format},
+    image::{sampler::Filter, view::ImageViewType, Image, ImageCreateInfo, ImageLayout, ImageSubresourceLayers, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    sync::{self, GpuFuture}
+};
+
+#[test]
+fn image_uploads_handle_row_pitch_and_subresources() {
+    let Some(toolset) = headless_toolset() else { return };
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    let (width, height) = (5u32, 3u32);
+    let packed = (0..width * height * 4).map(|i| i as u8).collect::<Vec<_>>();
+    let padded = |pitch : usize| packed.chunks(width as usize * 4)
+        .flat_map(|row| row.iter().copied().chain(std::iter::repeat(0xAA)).take(pitch))
+        .collect::<Vec<_>>();
+
+    // 24 skips whole texels, 22 has to be repacked
+    for row_pitch in [None, Some(24), Some(22)] {
+        let bytes = row_pitch.map_or(packed.clone(), |pitch| padded(pitch as usize));
+        let view = upload_image_view(&toolset, Format::R8G8B8A8_UNORM, [width, height], &bytes, row_pitch).unwrap();
+        assert_eq!(toolset.readback_image(view.image(), queue).unwrap(), packed, "row pitch {row_pitch:?}");
+    }
+
+    assert!(matches!(
+        upload_image_view(&toolset, Format::R8G8B8A8_UNORM, [width, height], &packed[1..], None),
+        Err(EngineError::ImageDataSize { .. })
+    ));
+
+    // Second mip level of the last layer, read back on its own
+    let image = Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [8, 8, 1],
+            mip_levels: 2,
+            array_layers: 3,
+            usage: ImageUsage::TRANSFER_DST | ImageUsage::TRANSFER_SRC | ImageUsage::SAMPLED,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let mip = (0..4 * 4 * 4).map(|i| 255 - i as u8).collect::<Vec<_>>();
+    copy_bytes_to_image(&toolset, &image, &mip, ImageRegion {
+        mip_level : 1,
+        array_layers : 2..3,
+        ..ImageRegion::new([4, 4, 1])
+    }).unwrap();
+
+    let staging = Buffer::new_slice::<u8>(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        mip.len() as u64,
+    ).unwrap();
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    builder.copy_image_to_buffer(CopyImageToBufferInfo {
+        regions: [BufferImageCopy {
+            image_subresource: ImageSubresourceLayers {
+                mip_level: 1,
+                array_layers: 2..3,
+                ..ImageSubresourceLayers::from_parameters(Format::R8G8B8A8_UNORM, 1)
+            },
+            image_extent: [4, 4, 1],
+            ..Default::default()
+        }].into(),
+        ..CopyImageToBufferInfo::image_buffer(image.clone(), staging.clone())
+    }).unwrap();
+
+    sync::now(toolset.logical_device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    assert_eq!(*staging.read().unwrap(), mip[..]);
+}
+
+#[test]
+fn cube_maps_from_face_images_need_square_faces_of_one_size() {
+    let Some(toolset) = headless_toolset() else { return };
+
+    let mut faces = [(); 6].map(|_| RgbaImage::from_pixel(8, 8, Rgba([40, 80, 120, 255])));
+    let cube = Texture2D::cube_from_face_images(&toolset, &faces).unwrap();
+    assert_eq!(cube.image.extent(), [8, 8, 1]);
+    assert_eq!(cube.image.array_layers(), 6);
+    assert_eq!(cube.view.view_type(), ImageViewType::Cube);
+
+    faces[3] = RgbaImage::new(8, 4);
+    match Texture2D::cube_from_face_images(&toolset, &faces) {
+        Err(EngineError::CubeFaceSize { face, size, expected }) => {
+            assert_eq!((face, size, expected), (3, [8, 4], [8, 8]));
+        }
+        other => panic!("expected a face size error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn image_data_reads_back_rgba8_and_float_images_texel_for_texel() {
+    let Some(toolset) = headless_toolset() else { return };
+
+    // Values past 1.0 only survive in the float image
+    let texels = [[0.0f32, 0.25, 0.5, 1.0], [1.0, 0.0, 0.0, 1.0], [0.0, 4.0, 0.0, 1.0], [0.5, 0.5, 16.0, 0.5]];
+    let float_bytes = texels.iter().flatten().flat_map(|value| value.to_ne_bytes()).collect::<Vec<_>>();
+    let unorm_bytes = texels.iter().flatten().map(|value| (value.min(1.0) * 255.0).round() as u8).collect::<Vec<_>>();
+
+    for (format, bytes) in [(Format::R8G8B8A8_UNORM, unorm_bytes), (Format::R32G32B32A32_SFLOAT, float_bytes)] {
+        let view = upload_image_view(&toolset, format, [2, 2], &bytes, None).unwrap();
+        let data = toolset.readback_image_data(view.image(), &toolset.device_queue).unwrap();
+
+        assert_eq!((data.width, data.height, data.format), (2, 2, format));
+        assert_eq!(data.bytes.len(), 4 * format.block_size() as usize);
+        assert_eq!(data.texel(1, 1), &bytes[3 * data.texel_size()..], "{format:?}");
+
+        for save_format in [SaveFormat::Png, SaveFormat::Jpeg, SaveFormat::Exr] {
+            let path = std::env::temp_dir().join(format!("gpu_smoke_image_data_{format:?}.{save_format:?}"));
+            data.save(&path, save_format).unwrap();
+            assert_eq!(image::open(&path).unwrap().width(), 2);
+        }
+    }
+
+    // Formats without a file format mapping are refused up front
+    let depth = upload_image_view(&toolset, Format::R32_SFLOAT, [2, 2], &[0; 16], None).unwrap();
+    assert!(matches!(toolset.readback_image_data(depth.image(), &toolset.device_queue), Err(EngineError::UnsupportedFeature(_))));
+}
+
+#[test]
+fn image_helpers_clear_and_copy_render_targets() {
+    let Some(toolset) = headless_toolset() else { return };
+
+    let image = |format, usage| Image::new(
+        toolset.memory_allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format,
+            extent: [8, 8, 1],
+            usage: usage | ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let color = image(Format::R8G8B8A8_UNORM, ImageUsage::COLOR_ATTACHMENT);
+    let copy = image(Format::R8G8B8A8_UNORM, ImageUsage::SAMPLED);
+    let depth = image(Format::D32_SFLOAT, ImageUsage::DEPTH_STENCIL_ATTACHMENT);
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        toolset.device_queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    VulkanAllocation::record_clear_image(&mut builder, &color, ImageLayout::TransferDstOptimal, ClearColorValue::Float([0.0, 0.2, 0.6, 1.0])).unwrap();
+    VulkanAllocation::record_clear_depth_stencil(&mut builder, &depth, ImageLayout::TransferDstOptimal, 1.0, 0).unwrap();
+    // Only the top left quarter is copied, the rest keeps its own clear value
+    VulkanAllocation::record_clear_image(&mut builder, &copy, ImageLayout::General, ClearColorValue::Float([0.0; 4])).unwrap();
+    VulkanAllocation::record_copy_image(&mut builder, &color, ImageLayout::TransferSrcOptimal, &copy, ImageLayout::TransferDstOptimal, &[ImageCopy {
+        src_subresource: color.subresource_layers(),
+        dst_subresource: copy.subresource_layers(),
+        extent: [4, 4, 1],
+        ..Default::default()
+    }]).unwrap();
+
+    sync::now(toolset.logical_device.clone())
+    .then_execute(toolset.device_queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let cleared = toolset.readback_image(&color, &toolset.device_queue).unwrap();
+    assert!(cleared.chunks_exact(4).all(|texel| texel == [0, 51, 153, 255]));
+
+    let depth_bytes = toolset.readback_image(&depth, &toolset.device_queue).unwrap();
+    assert!(depth_bytes.chunks_exact(4).all(|texel| texel == 1f32.to_ne_bytes()));
+
+    let copied = toolset.readback_image(&copy, &toolset.device_queue).unwrap();
+    for (index, texel) in copied.chunks_exact(4).enumerate() {
+        let inside = index % 8 < 4 && index / 8 < 4;
+        assert_eq!(texel, if inside { [0, 51, 153, 255] } else { [0; 4] }, "texel {index}");
+    }
+
+    // The shadow map is created at the far plane, nothing is in shadow before it was rendered
+    let shadow_map = ShadowMapPass::new(&toolset, 16);
+    assert!(shadow_map.depth_view().image().usage().contains(ImageUsage::TRANSFER_DST));
+}
+
+#[test]
+fn blits_downscale_like_a_box_filter_and_letterbox_with_black_bars() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+
+    let shader = mandelbrot_cs::load(device.clone()).unwrap();
+    let compute = ComputeShader::new(&shader, [8, 8, 1], device.clone());
+    let source = mandelbrot_image(&toolset, &compute);
+    let full = toolset.readback_image_data(&source, queue).unwrap();
+
+    let target = |extent : [u32; 2]| Image::new(
+        toolset.memory_allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [extent[0], extent[1], 1],
+            usage: ImageUsage::TRANSFER_DST | ImageUsage::TRANSFER_SRC | ImageUsage::STORAGE,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        },
+    ).unwrap();
+    let mean_error = |a : &ImageData, b : &[u8]| {
+        a.bytes.iter().zip(b).map(|(&a, &b)| (a as i32 - b as i32).abs() as f64).sum::<f64>() / b.len() as f64
+    };
+
+    // 4x4 box filter on the CPU, linear filtering only averages 2x2 of each block so edges differ a bit
+    let expected = (0..256 * 256).flat_map(|i| {
+        let (x, y) = (i % 256 * 4, i / 256 * 4);
+        let sum = (0..16).map(|j| full.texel(x + j % 4, y + j / 4)[0] as u32).sum::<u32>();
+        let value = ((sum + 8) / 16) as u8;
+        [value, value, value, 255]
+    }).collect::<Vec<_>>();
+
+    let blitted = target([256, 256]);
+    blit_image(&toolset, &source, &blitted, Filter::Linear).unwrap();
+    let blitted = toolset.readback_image_data(&blitted, queue).unwrap();
+    let error = mean_error(&blitted, &expected);
+    assert!(error < 10.0, "mean error {error} against the box filter");
+
+    // The compute fallback samples the same positions the blit does
+    if device.enabled_features().shader_storage_image_write_without_format {
+        let resampled = target([256, 256]);
+        resample_image(&toolset, &source, &resampled, Filter::Linear, BlitFit::Stretch).unwrap();
+        let resampled = toolset.readback_image_data(&resampled, queue).unwrap();
+        let error = mean_error(&resampled, &blitted.bytes);
+        assert!(error < 2.0, "mean error {error} between the blit and the compute fallback");
+    }
+
+    // A square source in a 2:1 target leaves bars left and right
+    let letterboxed = target([256, 128]);
+    blit_image_fit(&toolset, &source, &letterboxed, Filter::Linear, BlitFit::Letterbox).unwrap();
+    let letterboxed = toolset.readback_image_data(&letterboxed, queue).unwrap();
+    assert_eq!(letterboxed.texel(0, 0), [0, 0, 0, 255]);
+    assert_eq!(letterboxed.texel(63, 127), [0, 0, 0, 255]);
+    assert_eq!(letterboxed.texel(255, 64), [0, 0, 0, 255]);
+    // The center of the source is inside the set, which the shader draws white
+    assert!(letterboxed.texel(128, 64)[0] > 200);
+}
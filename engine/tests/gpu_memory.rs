@@ -0,0 +1,186 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use std::sync::Arc;
+
+use common::headless_toolset;
+use engine::{vulkan::{memory_stats::AllocationCategory, staging::{read_back_buffer, read_back_buffer_async, read_back_range, upload_buffer}, texture::upload_image_view, vertex::{Triangle, VulkanVertex}, vulkan::VulkanAllocation}, EngineError};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    format::Format,
+    memory::{allocator::{AllocationCreateInfo, MemoryTypeFilter}, MemoryPropertyFlags},
+    pipeline::graphics::vertex_input::Vertex,
+    sync::{self, GpuFuture}
+};
+
+#[test]
+fn uploaded_buffers_round_trip_through_readback() {
+    let Some(toolset) = headless_toolset() else { return };
+
+    let data : Vec<u32> = (0..4096).map(|i| i * 3 + 1).collect();
+    let buffer = upload_buffer(&toolset.memory_allocator, &toolset.device_queue, BufferUsage::STORAGE_BUFFER, data.iter().copied()).unwrap();
+
+    assert_eq!(read_back_buffer(&toolset, &buffer).unwrap(), data);
+    assert_eq!(read_back_range(&toolset, &buffer, 1000, 24).unwrap(), data[1000..1024]);
+    assert!(matches!(read_back_range(&toolset, &buffer, 4090, 8), Err(EngineError::BufferRange { .. })));
+
+    let pending = read_back_buffer_async(&toolset, &buffer).unwrap();
+    while !pending.is_ready().unwrap() {
+        std::thread::yield_now();
+    }
+    assert_eq!(pending.wait().unwrap(), data);
+}
+
+#[test]
+fn explicit_memory_type_prefers_device_local_host_visible_memory() {
+    let Some(toolset) = headless_toolset() else { return };
+
+    // Requirements of a buffer like the ones that get written from the CPU every frame
+    let usage = BufferUsage::UNIFORM_BUFFER | BufferUsage::STORAGE_BUFFER;
+    let template = Buffer::new_slice::<u32>(
+        toolset.memory_allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+        256,
+    ).unwrap();
+    let requirements = template.buffer().memory_requirements();
+
+    let (buffer, memory_type) = toolset.memory_allocator.allocate_with_explicit_type(requirements, usage, MemoryPropertyFlags::HOST_VISIBLE, MemoryPropertyFlags::DEVICE_LOCAL).unwrap();
+    assert_eq!(buffer.size(), requirements.layout.size());
+    assert!(memory_type.property_flags.contains(MemoryPropertyFlags::HOST_VISIBLE));
+
+    // ReBAR, or the small BAR window without it, whenever the device has such a type for this buffer
+    let rebar = MemoryPropertyFlags::DEVICE_LOCAL | MemoryPropertyFlags::HOST_VISIBLE;
+    let memory_types = &toolset.logical_device.physical_device().memory_properties().memory_types;
+    let has_rebar = memory_types.iter()
+    .enumerate()
+    .any(|(index, memory_type)| requirements.memory_type_bits & (1 << index) != 0 && memory_type.property_flags.contains(rebar));
+    assert_eq!(memory_type.property_flags.contains(rebar), has_rebar);
+
+    // Mapped, so the host writes into it directly
+    buffer.write().unwrap()[..4].copy_from_slice(&[1, 2, 3, 4]);
+    assert_eq!(buffer.read().unwrap()[..4], [1, 2, 3, 4]);
+}
+
+#[test]
+fn buffer_helpers_fill_and_copy_whole_buffers() {
+    let Some(toolset) = headless_toolset() else { return };
+
+    let buffer = |data : Vec<u32>| Buffer::from_iter(
+        toolset.memory_allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        data,
+    ).unwrap();
+
+    let filled = buffer(vec![0; 1000]);
+    // Bytes that differ from each other, so a shifted or partial copy shows
+    let source = buffer((0..1000).map(|i : u32| i.wrapping_mul(0x9e3779b9)).collect());
+    let copied = buffer(vec![0; 1000]);
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        toolset.device_queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    VulkanAllocation::record_fill_buffer(&mut builder, &filled, 0xdeadbeef).unwrap();
+    VulkanAllocation::record_copy_buffer(&mut builder, &source, &copied).unwrap();
+
+    sync::now(toolset.logical_device.clone())
+    .then_execute(toolset.device_queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    assert!(filled.read().unwrap().iter().all(|&value| value == 0xdeadbeef));
+    assert_eq!(copied.clone().into_bytes().read().unwrap()[..], source.clone().into_bytes().read().unwrap()[..]);
+}
+
+#[test]
+fn allocation_tally_returns_to_baseline_once_buffers_are_dropped() {
+    let Some(toolset) = headless_toolset() else { return };
+    let allocator = &toolset.memory_allocator;
+    let baseline = allocator.stats();
+
+    let buffers = (0..8)
+    .map(|i| upload_buffer(allocator, &toolset.device_queue, BufferUsage::VERTEX_BUFFER, vec![i as f32; 1024]).unwrap())
+    .collect::<Vec<_>>();
+    let texture = upload_image_view(&toolset, Format::R8G8B8A8_UNORM, [16, 16], &[255; 16 * 16 * 4], None).unwrap();
+
+    // The staging buffers went away with the uploads
+    let during = allocator.stats();
+    let vertex = during.categories[&AllocationCategory::Vertex];
+    assert_eq!(vertex.count, baseline.categories.get(&AllocationCategory::Vertex).map_or(0, |stats| stats.count) + 8);
+    assert!(vertex.bytes >= 8 * 4096);
+    assert!(during.categories.contains_key(&AllocationCategory::Texture));
+    assert_eq!(during.categories.get(&AllocationCategory::Staging), baseline.categories.get(&AllocationCategory::Staging));
+    assert_eq!(during.heaps.iter().map(|heap| heap.tracked_bytes).sum::<u64>(), during.total().bytes);
+
+    drop((buffers, texture));
+    assert_eq!(allocator.stats(), baseline);
+}
+
+#[test]
+fn empty_vertex_lists_fail_instead_of_panicking() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let allocator = toolset.memory_allocator.general_allocator.clone();
+
+    assert!(matches!(Triangle::with_vertices(allocator.clone(), device, Vec::new()), Err(EngineError::EmptyBuffer)));
+    assert!(matches!(upload_buffer(&toolset.memory_allocator, &toolset.device_queue, BufferUsage::VERTEX_BUFFER, Vec::<VulkanVertex>::new()), Err(EngineError::EmptyBuffer)));
+
+    let quad = [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [-1.0, 1.0], [1.0, -1.0], [1.0, 1.0]];
+    let triangles = Triangle::with_vertices(allocator, device, quad.map(|[x, y]| VulkanVertex::new(x, y)).to_vec()).unwrap();
+    assert_eq!(triangles.vertex_buffer.len(), 6);
+}
+
+#[test]
+fn defragmenting_packs_relocatable_buffers_and_keeps_their_contents() {
+    let Some(toolset) = headless_toolset() else { return };
+    let allocation = &toolset.memory_allocator;
+    const ELEMENTS : usize = 1024;
+
+    let mut buffers = (0..8u32)
+    .map(|i| allocation.create_relocatable_buffer(
+        BufferUsage::STORAGE_BUFFER,
+        MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+        &vec![i; ELEMENTS],
+    ).unwrap())
+    .collect::<Vec<_>>();
+    // Dropping every other buffer leaves gaps between the rest
+    let mut i = 0;
+    buffers.retain(|_| { i += 1; i % 2 == 1 });
+    let old_handles = buffers.iter().map(|buffer| buffer.get()).collect::<Vec<_>>();
+    assert_eq!(allocation.relocatable.len(), 4);
+
+    let before = allocation.fragmentation();
+    let stats = allocation.defragment(&toolset.device_queue).unwrap();
+    let after = allocation.fragmentation();
+    assert_eq!(stats.bytes_moved, (4 * ELEMENTS * 4) as u64);
+    assert_eq!(after, 0.0);
+    assert!(after < before, "fragmentation went from {before} to {after}");
+
+    for ((i, buffer), old) in buffers.iter().enumerate().zip(old_handles) {
+        let moved = buffer.get();
+        assert!(!Arc::ptr_eq(moved.buffer(), old.buffer()));
+        assert!(moved.read().unwrap().iter().all(|&value| value == 2 * i as u32));
+    }
+
+    // Dropped buffers fall out of the registry
+    drop(buffers);
+    assert!(allocation.relocatable.is_empty());
+    assert_eq!(allocation.defragment(&toolset.device_queue).unwrap(), Default::default());
+}
@@ -0,0 +1,268 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{fullscreen_vs, headless_toolset};
+use engine::{render::mipmaps::MipmapGenerator, vulkan::{texture::{copy_bytes_to_image, ImageRegion}, vulkan::{ComputeShader, VulkanAllocation}}, EngineError, VulkanToolset};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    descriptor_set::WriteDescriptorSet,
+    format::Format,
+    image::{sampler::{Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode, LOD_CLAMP_NONE}, view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{graphics::viewport::Viewport, Pipeline},
+    render_pass::Subpass,
+    sync::{self, GpuFuture}
+};
+
+// The set from mandelbrot_cs drawn over the whole target, darkened by brightness
+mod mip_mandelbrot_fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        include: ["assets/shaders/include"],
+        src: r#"
+            #version 460
+
+            #include "math.glsl"
+
+            layout(location = 0) out vec4 f_color;
+
+            layout(push_constant) uniform Level {
+                vec2 extent;
+                float brightness;
+            };
+
+            void main() {
+                vec2 c = (gl_FragCoord.xy / extent - vec2(0.5)) * 2.0 - vec2(1.0, 0.0);
+
+                vec2 z = vec2(0.0, 0.0);
+                float i;
+                for (i = 0.0; i < 1.0; i += 0.005) {
+                    z = complex_square(z) + c;
+
+                    if (length(z) > 4.0) {
+                        break;
+                    }
+                }
+
+                f_color = vec4(vec3(i * brightness), 1.0);
+            }
+        "#,
+    }
+}
+
+// Samples one point of every mip level, invocation i reads level i
+mod sample_mip_levels_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D tex;
+
+            layout(set = 0, binding = 1) writeonly buffer Samples {
+                vec4 samples[];
+            };
+
+            // Inside the main cardioid, every level is flat around it
+            void main() {
+                uint level = gl_GlobalInvocationID.x;
+                samples[level] = textureLod(tex, vec2(0.9, 0.5), float(level));
+            }
+        ",
+    }
+}
+
+// Samples sample_mip_levels_cs reads from each level of `image`
+fn sample_mip_levels(toolset : &VulkanToolset, image : &Arc<Image>) -> Vec<[f32; 4]> {
+    let device = &toolset.logical_device;
+    let allocator = &toolset.memory_allocator;
+
+    let samples = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        (0..image.mip_levels()).map(|_| [0.0f32; 4]),
+    ).unwrap();
+
+    // Nearest mip selection, so an integer lod reads exactly that level
+    let sampler = Sampler::new(
+        device.clone(),
+        SamplerCreateInfo {
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            mipmap_mode: SamplerMipmapMode::Nearest,
+            lod: 0.0..=LOD_CLAMP_NONE,
+            ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+        },
+    ).unwrap();
+
+    let shader = sample_mip_levels_cs::load(device.clone()).expect("failed to create shader module");
+    let compute = ComputeShader::new(&shader, [1, 1, 1], device.clone());
+    compute.execute_with_writes(
+        allocator,
+        &allocator.descriptor_set_allocator,
+        &toolset.device_queue,
+        [(0, vec![
+            WriteDescriptorSet::image_view_sampler(0, ImageView::new_default(image.clone()).unwrap(), sampler),
+            WriteDescriptorSet::buffer(1, samples.clone()),
+        ])],
+        image.mip_levels(),
+    ).unwrap();
+
+    let samples = samples.read().unwrap().to_vec();
+    samples
+}
+
+#[test]
+fn mip_views_render_a_darker_mandelbrot_into_each_level() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    const LEVELS : u32 = 5;
+    let image = Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [128, 128, 1],
+            mip_levels: LEVELS,
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+
+    let view = VulkanAllocation::create_mip_view(&image, 3, 0).unwrap();
+    assert_eq!(view.subresource_range().mip_levels, 3..4);
+    assert!(matches!(VulkanAllocation::create_mip_view(&image, LEVELS, 0), Err(EngineError::ImageSubresource { mip_levels : LEVELS, .. })));
+    assert!(matches!(VulkanAllocation::create_mip_view(&image, 0, 1), Err(EngineError::ImageSubresource { .. })));
+
+    let generator = MipmapGenerator::new(&toolset, Format::R8G8B8A8_UNORM).unwrap();
+    let vs = fullscreen_vs::load(device.clone()).unwrap();
+    let fs = mip_mandelbrot_fs::load(device.clone()).unwrap();
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    for level in 0..LEVELS {
+        let framebuffer = generator.mip_framebuffer(&image, level, 0).unwrap();
+        let [width, height] = framebuffer.extent();
+        assert_eq!([width, height], [128 >> level; 2]);
+
+        let viewport = Viewport {
+            offset: [0.0, 0.0],
+            extent: [width as f32, height as f32],
+            depth_range: 0.0..=1.0,
+        };
+        let pipeline = toolset.create_graphics_pipeline_for(&vs, Some(&fs), Subpass::from(generator.render_pass().clone(), 0).unwrap(), viewport);
+        let constants = mip_mandelbrot_fs::Level { extent : [width as f32, height as f32], brightness : 1.0 / (level + 1) as f32 };
+
+        builder.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![None],
+                ..RenderPassBeginInfo::framebuffer(framebuffer)
+            },
+            SubpassBeginInfo {
+                contents: SubpassContents::Inline,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .bind_pipeline_graphics(pipeline.clone())
+        .unwrap()
+        .push_constants(pipeline.layout().clone(), 0, constants)
+        .unwrap()
+        .draw(3, 1, 0, 0)
+        .unwrap()
+        .end_render_pass(SubpassEndInfo::default())
+        .unwrap();
+    }
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    // Each level holds its own render rather than a filtered copy of the first
+    let samples = sample_mip_levels(&toolset, &image);
+    for (level, [r, g, b, a]) in samples.into_iter().enumerate() {
+        let expected = 1.0 / (level + 1) as f32;
+        assert!((r - expected).abs() < 0.02, "level {level} is {r}, expected {expected}");
+        assert_eq!((r, g, a), (b, b, 1.0));
+    }
+
+    // Level 0 is the full fractal, dark outside of the set
+    let level0 = toolset.readback_image_data(&image, queue).unwrap();
+    assert!(level0.bytes[(12 * 128 + 12) * 4] < 32);
+}
+
+#[test]
+fn mipmap_generator_averages_each_level_from_the_one_above() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    let image = Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [64, 64, 1],
+            mip_levels: 4,
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED | ImageUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+
+    // Black and white texels alternate, every level below averages to gray
+    let checker = (0..64 * 64)
+    .flat_map(|i| {
+        let value = if (i % 64 + i / 64) % 2 == 0 { 255 } else { 0 };
+        [value, value, value, 255]
+    })
+    .collect::<Vec<u8>>();
+    copy_bytes_to_image(&toolset, &image, &checker, ImageRegion::new([64, 64, 1])).unwrap();
+
+    let generator = MipmapGenerator::new(&toolset, Format::R8G8B8A8_UNORM).unwrap();
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    generator.record(&toolset, &mut builder, &image).unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let samples = sample_mip_levels(&toolset, &image);
+    for (level, [r, _, _, a]) in samples.into_iter().enumerate().skip(1) {
+        assert!((r - 0.5).abs() < 0.02, "level {level} is {r}");
+        assert_eq!(a, 1.0);
+    }
+}
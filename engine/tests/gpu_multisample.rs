@@ -0,0 +1,237 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::{headless_toolset, SCREENSHOT_SIZE};
+use engine::vulkan::{render_pass::{create_framebuffer, RenderPassBuilder}, vertex::Triangle, vulkan::MultisampleConfig, vulkan_window::{AttachmentConfig, VulkanWindow}};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, RenderPassBeginInfo, ResolveImageInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage, SampleCount},
+    memory::allocator::AllocationCreateInfo,
+    pipeline::graphics::viewport::Viewport,
+    render_pass::Subpass,
+    sync::{self, GpuFuture}
+};
+
+mod stripes_vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec2 position;
+            layout(location = 0) out float v_pixel_x;
+
+            void main() {
+                gl_Position = vec4(position, 0.0, 1.0);
+                v_pixel_x = (position.x * 0.5 + 0.5) * 64.0;
+            }
+        ",
+    }
+}
+
+mod stripes_fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            #define PI 3.14159265
+
+            layout(location = 0) in float v_pixel_x;
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                // White at pixel centers, darker anywhere else within the pixel
+                float shade = 0.5 + 0.5 * cos(v_pixel_x * 4.0 * PI);
+                f_color = vec4(vec3(shade), 1.0);
+            }
+        ",
+    }
+}
+
+#[test]
+fn sample_shading_shades_every_sample() {
+    let Some(toolset) = headless_toolset() else { return };
+    if !toolset.logical_device.enabled_features().sample_rate_shading {
+        eprintln!("skipping: sample_rate_shading is not supported");
+        assert!(toolset.check_multisample_support(&MultisampleConfig::with_sample_shading(1.0)).is_err());
+        return;
+    }
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        samples : 4,
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+
+    let triangle = Triangle::new(allocator.clone(), device).unwrap();
+    let vs = stripes_vs::load(device.clone()).unwrap();
+    let fs = stripes_fs::load(device.clone()).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+
+    // Renders the triangle's diagonal edges over a white background and returns the resolved red channel
+    let render = |multisample : MultisampleConfig| {
+        let image = |samples, usage| Image::new(
+            allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R8G8B8A8_UNORM,
+                extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+                samples,
+                usage,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        ).unwrap();
+        let multisampled = image(SampleCount::Sample4, ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC);
+        let resolved = image(SampleCount::Sample1, ImageUsage::TRANSFER_DST | ImageUsage::TRANSFER_SRC);
+
+        let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(multisampled.clone()).unwrap()]).unwrap();
+        let pipeline = toolset.create_multisampled_pipeline_for(&vs, Some(&fs), Subpass::from(render_pass.clone(), 0).unwrap(), viewport.clone(), multisample);
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &toolset.memory_allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+        builder.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![Some([1.0, 1.0, 1.0, 1.0].into())],
+                ..RenderPassBeginInfo::framebuffer(framebuffer)
+            },
+            SubpassBeginInfo {
+                contents: SubpassContents::Inline,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .bind_pipeline_graphics(pipeline)
+        .unwrap()
+        .bind_vertex_buffers(0, triangle.vertex_buffer.clone())
+        .unwrap()
+        .draw(triangle.vertex_buffer.len() as u32, 1, 0, 0)
+        .unwrap()
+        .end_render_pass(SubpassEndInfo::default())
+        .unwrap()
+        .resolve_image(ResolveImageInfo::images(multisampled, resolved.clone()))
+        .unwrap();
+
+        sync::now(device.clone())
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+        let mut values = toolset.readback_image(&resolved, queue).unwrap()
+        .chunks(4)
+        .map(|texel| texel[0])
+        .collect::<Vec<_>>();
+        values.sort_unstable();
+        values.dedup();
+        values
+    };
+
+    // Shaded once per pixel the stripes are only sampled at their white centers
+    let per_pixel = render(MultisampleConfig::default());
+    let per_sample = render(MultisampleConfig::with_sample_shading(1.0));
+    assert!(per_sample.len() > per_pixel.len(), "per sample {per_sample:?}, per pixel {per_pixel:?}");
+}
+
+#[test]
+fn msaa_window_pass_resolves_into_the_presented_image() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    // Headless devices can't present, so the resolve target ends up ready for the readback instead
+    let render_pass = VulkanWindow::render_pass_builder(Format::R8G8B8A8_UNORM, Format::D16_UNORM, 4, ImageLayout::TransferSrcOptimal)
+    .build(device)
+    .unwrap();
+    assert_eq!(render_pass.attachments().len(), 3);
+
+    let presented = Image::new(
+        allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let attachments = VulkanWindow::create_attachments(allocator, &presented, Format::D16_UNORM, 4);
+    assert_eq!(attachments[0].image().samples(), SampleCount::Sample4);
+    assert!(attachments[0].image().usage().contains(ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSIENT_ATTACHMENT));
+    let framebuffer = create_framebuffer(&render_pass, attachments).unwrap();
+
+    let triangle = Triangle::new(allocator.clone(), device).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let pipeline = toolset.create_graphics_pipeline_for(&triangle.vertex_shader, Some(&triangle.fragment_shader), Subpass::from(render_pass.clone(), 0).unwrap(), viewport);
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    builder.begin_render_pass(
+        RenderPassBeginInfo {
+            clear_values: vec![Some([0.0, 0.0, 1.0, 1.0].into()), Some(1f32.into()), None],
+            ..RenderPassBeginInfo::framebuffer(framebuffer)
+        },
+        SubpassBeginInfo {
+            contents: SubpassContents::Inline,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .bind_pipeline_graphics(pipeline)
+    .unwrap()
+    .bind_vertex_buffers(0, triangle.vertex_buffer.clone())
+    .unwrap()
+    .draw(triangle.vertex_buffer.len() as u32, 1, 0, 0)
+    .unwrap()
+    .end_render_pass(SubpassEndInfo::default())
+    .unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let pixels = toolset.readback_image(&presented, queue).unwrap();
+    let pixel = |x : u32, y : u32| {
+        let i = ((y * SCREENSHOT_SIZE + x) * 4) as usize;
+        [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]]
+    };
+
+    let last = SCREENSHOT_SIZE - 1;
+    for (x, y) in [(0, 0), (last, 0), (0, last), (last, last)] {
+        assert_eq!(pixel(x, y), [0, 0, 255, 255], "corner ({x}, {y})");
+    }
+    assert_eq!(pixel(SCREENSHOT_SIZE / 2, SCREENSHOT_SIZE / 2), [255, 0, 0, 255]);
+}
@@ -0,0 +1,911 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use std::path::Path;
+
+use common::{fullscreen_vs, gather_cs, headless_toolset, mandelbrot_cs, SCREENSHOT_SIZE};
+use engine::{assets::obj_loader::ObjLoader, scene::camera::Camera, vulkan::{conservative_raster::ConservativeRasterMode, pipeline_desc::{GraphicsPipelineDesc, PipelineDescError}, render_pass::{create_framebuffer, RenderPassBuilder}, shader_interface::{DescriptorBindingInfo, ShaderInterface}, scissor::ScissorState, vertex::{Triangle, VulkanVertex}, vertex_divisor::AttributeDivisor, vulkan::{find_entry_point, ComputeShader, CullConfig, EntryPointNames, FaceCull, PipelineOptions, Winding}, vulkan_window::AttachmentConfig}, EngineError};
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, ClearColorImageInfo, CommandBufferUsage, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, layout::DescriptorType, PersistentDescriptorSet, WriteDescriptorSet},
+    format::{ClearColorValue, Format},
+    image::{view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage, SampleCount},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{graphics::{color_blend::{AttachmentBlend, ColorBlendAttachmentState, ColorBlendState, ColorComponents}, depth_stencil::DepthState, input_assembly::{InputAssemblyState, PrimitiveTopology}, multisample::MultisampleState, rasterization::RasterizationState, vertex_input::{Vertex, VertexDefinition}, viewport::{Scissor, Viewport, ViewportState}, GraphicsPipelineCreateInfo}, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineShaderStageCreateInfo},
+    render_pass::{Framebuffer, FramebufferCreateInfo, Subpass},
+    shader::{spirv::ExecutionModel, ShaderModule, ShaderModuleCreateInfo, ShaderStage},
+    sync::{self, GpuFuture}
+};
+
+mod facing_vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec3 position;
+            layout(location = 1) in vec3 normal;
+            layout(location = 0) out float v_facing;
+
+            layout(push_constant) uniform View {
+                mat4 view_projection;
+                vec4 eye;
+            } view;
+
+            void main() {
+                gl_Position = view.view_projection * vec4(position, 1.0);
+                // Positive where the face points towards the eye
+                v_facing = dot(normal, view.eye.xyz - position);
+            }
+        ",
+    }
+}
+
+mod facing_fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(location = 0) in float v_facing;
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                // Red for faces seen from the outside, green for the inside of a mesh
+                f_color = v_facing > 0.0 ? vec4(1.0, 0.0, 0.0, 1.0) : vec4(0.0, 1.0, 0.0, 1.0);
+            }
+        ",
+    }
+}
+
+mod group_color_vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec4 group_color;
+            layout(location = 0) out vec4 v_color;
+
+            layout(push_constant) uniform Grid {
+                uint width;
+                uint height;
+            } grid;
+
+            void main() {
+                // One pixel per instance, row by row
+                uvec2 pixel = uvec2(gl_InstanceIndex % grid.width, gl_InstanceIndex / grid.width);
+                gl_Position = vec4((vec2(pixel) + 0.5) / vec2(grid.width, grid.height) * 2.0 - 1.0, 0.0, 1.0);
+                gl_PointSize = 1.0;
+                v_color = group_color;
+            }
+        ",
+    }
+}
+
+mod vertex_color_fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec4 v_color;
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                f_color = v_color;
+            }
+        ",
+    }
+}
+
+mod voxel_vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec2 position;
+
+            void main() {
+                // Halfway through layer 6 of 16
+                gl_Position = vec4(position, 6.5 / 16.0, 1.0);
+            }
+        ",
+    }
+}
+
+mod voxel_fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(set = 0, binding = 0, r32ui) uniform writeonly uimage3D voxels;
+
+            void main() {
+                ivec3 voxel = ivec3(ivec2(gl_FragCoord.xy), int(gl_FragCoord.z * imageSize(voxels).z));
+                imageStore(voxels, voxel, uvec4(1));
+            }
+        ",
+    }
+}
+
+// Hand assembled SPIR-V holding an empty entry point per stage, named the way HLSL sources name them
+fn multi_entry_spirv() -> Vec<u32> {
+    fn instruction(opcode : u32, operands : &[u32]) -> Vec<u32> {
+        [((operands.len() as u32 + 1) << 16) | opcode].into_iter().chain(operands.iter().copied()).collect()
+    }
+
+    // Nul terminated and padded to whole words
+    fn literal(name : &str) -> Vec<u32> {
+        let mut bytes = name.as_bytes().to_vec();
+        bytes.resize(name.len() / 4 * 4 + 4, 0);
+        bytes.chunks(4).map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]])).collect()
+    }
+
+    const VOID : u32 = 1;
+    const FUNCTION_TYPE : u32 = 2;
+    // (execution model, function id, label id, name)
+    const ENTRY_POINTS : [(u32, u32, u32, &str); 3] = [(0, 3, 6, "VSMain"), (4, 4, 7, "PSMain"), (5, 5, 8, "CSMain")];
+
+    let mut words = vec![0x07230203, 0x00010000, 0, 9, 0];
+    words.extend(instruction(17, &[1])); // OpCapability Shader
+    words.extend(instruction(14, &[0, 1])); // OpMemoryModel Logical GLSL450
+    for (model, function, _, name) in ENTRY_POINTS {
+        words.extend(instruction(15, &[[model, function].as_slice(), &literal(name)].concat())); // OpEntryPoint
+    }
+    words.extend(instruction(16, &[4, 7])); // OpExecutionMode PSMain OriginUpperLeft
+    words.extend(instruction(16, &[5, 17, 1, 1, 1])); // OpExecutionMode CSMain LocalSize 1 1 1
+    words.extend(instruction(19, &[VOID])); // OpTypeVoid
+    words.extend(instruction(33, &[FUNCTION_TYPE, VOID])); // OpTypeFunction
+    for (_, function, label, _) in ENTRY_POINTS {
+        words.extend(instruction(54, &[VOID, function, 0, FUNCTION_TYPE])); // OpFunction
+        words.extend(instruction(248, &[label])); // OpLabel
+        words.extend(instruction(253, &[])); // OpReturn
+        words.extend(instruction(56, &[])); // OpFunctionEnd
+    }
+
+    words
+}
+
+#[derive(BufferContents, Vertex, Clone, Copy)]
+#[repr(C)]
+struct GroupColor {
+    #[format(R8G8B8A8_UNORM)]
+    group_color : [u8; 4],
+}
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct Grid {
+    width : u32,
+    height : u32,
+}
+
+#[test]
+fn fixed_scissor_clips_draws_and_degenerate_rects_draw_nothing() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+
+    let triangle = Triangle::new(allocator.clone(), device).unwrap();
+    let vs = fullscreen_vs::load(device.clone()).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+
+    // Covers the whole target in red through the given scissor, returns the red channel
+    let render = |scissor : ScissorState| {
+        let image = Image::new(
+            allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R8G8B8A8_UNORM,
+                extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        ).unwrap();
+
+        let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(image.clone()).unwrap()]).unwrap();
+        let pipeline = toolset.create_scissored_pipeline_for(&vs, Some(&triangle.fragment_shader), Subpass::from(render_pass.clone(), 0).unwrap(), viewport.clone(), scissor);
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &toolset.memory_allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+        builder.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into())],
+                ..RenderPassBeginInfo::framebuffer(framebuffer)
+            },
+            SubpassBeginInfo {
+                contents: SubpassContents::Inline,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .bind_pipeline_graphics(pipeline)
+        .unwrap()
+        .draw(3, 1, 0, 0)
+        .unwrap()
+        .end_render_pass(SubpassEndInfo::default())
+        .unwrap();
+
+        sync::now(device.clone())
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+        toolset.readback_image(&image, queue).unwrap()
+        .chunks(4)
+        .map(|texel| texel[0])
+        .collect::<Vec<_>>()
+    };
+    let red_pixels = |pixels : &[u8]| pixels.iter().filter(|&&red| red == 255).count() as u32;
+
+    assert_eq!(red_pixels(&render(ScissorState::FullFramebuffer)), SCREENSHOT_SIZE * SCREENSHOT_SIZE);
+
+    let pixels = render(ScissorState::Fixed(Scissor { offset : [16, 8], extent : [32, 16] }));
+    assert_eq!(red_pixels(&pixels), 32 * 16);
+    assert_eq!(pixels[(8 * SCREENSHOT_SIZE + 16) as usize], 255);
+    assert_eq!(pixels[(8 * SCREENSHOT_SIZE + 15) as usize], 0);
+    assert_eq!(pixels[(24 * SCREENSHOT_SIZE + 16) as usize], 0);
+
+    // Clamped to the viewport, as after the window shrank
+    let pixels = render(ScissorState::Fixed(Scissor { offset : [48, 48], extent : [1000, 1000] }));
+    assert_eq!(red_pixels(&pixels), 16 * 16);
+
+    assert_eq!(red_pixels(&render(ScissorState::Fixed(Scissor { offset : [8, 8], extent : [0, 32] }))), 0);
+    assert_eq!(red_pixels(&render(ScissorState::Fixed(Scissor { offset : [SCREENSHOT_SIZE, 0], extent : [8, 8] }))), 0);
+}
+
+#[test]
+fn back_face_culled_cube_shows_its_outside_from_every_side() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/cube.obj");
+    let cube = ObjLoader::load(&path, &toolset.memory_allocator, queue).unwrap().remove(0);
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .attachment(AttachmentConfig::depth(Format::D16_UNORM))
+    .subpass(&[0], Some(1))
+    .build(device)
+    .unwrap();
+
+    let image = |format, usage| Image::new(
+        allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let color = image(Format::R8G8B8A8_UNORM, ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC);
+    let framebuffer = create_framebuffer(&render_pass, vec![
+        ImageView::new_default(color.clone()).unwrap(),
+        ImageView::new_default(image(Format::D16_UNORM, ImageUsage::DEPTH_STENCIL_ATTACHMENT)).unwrap(),
+    ]).unwrap();
+
+    let vs = facing_vs::load(device.clone()).unwrap();
+    let fs = facing_fs::load(device.clone()).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let pipeline = toolset.create_mesh_pipeline_for(&vs, &fs, Subpass::from(render_pass, 0).unwrap(), viewport, CullConfig::back_faces());
+
+    #[derive(BufferContents, Clone, Copy)]
+    #[repr(C)]
+    struct View {
+        view_projection : [[f32; 4]; 4],
+        eye : [f32; 4],
+    }
+
+    // Inverted winding would cull the near faces and show the inside of the cube in green
+    for eye in [[0.0, 0.0, 4.0], [4.0, 1.0, 0.5], [-3.0, 2.5, -2.0], [0.5, -4.0, 1.0], [1.0, 3.0, -3.5]] {
+        let camera = Camera::perspective(eye, [0.0, 0.0, 0.0], 1.0);
+        let view = View {
+            view_projection : camera.view_projection(1.0),
+            eye : [eye[0], eye[1], eye[2], 1.0],
+        };
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &toolset.memory_allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+        builder.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into()), Some(1f32.into())],
+                ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
+            },
+            SubpassBeginInfo {
+                contents: SubpassContents::Inline,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .bind_pipeline_graphics(pipeline.clone())
+        .unwrap()
+        .push_constants(pipeline.layout().clone(), 0, view)
+        .unwrap()
+        .bind_vertex_buffers(0, cube.vertex_buffer.clone())
+        .unwrap()
+        .bind_index_buffer(cube.index_buffer.clone())
+        .unwrap()
+        .draw_indexed(cube.index_count(), 1, 0, 0, 0)
+        .unwrap()
+        .end_render_pass(SubpassEndInfo::default())
+        .unwrap();
+
+        sync::now(device.clone())
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+        let pixels = toolset.readback_image(&color, queue).unwrap();
+        let outside = pixels.chunks(4).filter(|texel| texel[0] == 255).count();
+        let inside = pixels.chunks(4).filter(|texel| texel[1] == 255).count();
+        assert!(outside > 0, "the cube seen from {eye:?} has an empty silhouette");
+        assert_eq!(inside, 0, "the cube seen from {eye:?} shows its inside");
+    }
+}
+
+#[test]
+fn color_write_mask_keeps_masked_channels_at_the_clear_value() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+
+    let triangle = Triangle::new(allocator.clone(), device).unwrap();
+    let vs = fullscreen_vs::load(device.clone()).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+
+    // Covers the target in opaque red over a cyan, transparent clear and returns the first pixel
+    let render = |color_write_masks : Vec<ColorComponents>| {
+        let image = Image::new(
+            allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R8G8B8A8_UNORM,
+                extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        ).unwrap();
+
+        let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(image.clone()).unwrap()]).unwrap();
+        let options = PipelineOptions {
+            color_write_masks,
+            ..Default::default()
+        };
+        let pipeline = toolset.create_configured_pipeline_for(&vs, Some(&triangle.fragment_shader), Subpass::from(render_pass.clone(), 0).unwrap(), viewport.clone(), options);
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &toolset.memory_allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+        builder.begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![Some([0.0, 1.0, 1.0, 0.0].into())],
+                ..RenderPassBeginInfo::framebuffer(framebuffer)
+            },
+            SubpassBeginInfo {
+                contents: SubpassContents::Inline,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .bind_pipeline_graphics(pipeline)
+        .unwrap()
+        .draw(3, 1, 0, 0)
+        .unwrap()
+        .end_render_pass(SubpassEndInfo::default())
+        .unwrap();
+
+        sync::now(device.clone())
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+        let pixels = toolset.readback_image(&image, queue).unwrap();
+        assert!(pixels.chunks(4).all(|texel| texel == &pixels[..4]));
+        [pixels[0], pixels[1], pixels[2], pixels[3]]
+    };
+
+    assert_eq!(render(Vec::new()), [255, 0, 0, 255]);
+    assert_eq!(render(vec![ColorComponents::R]), [255, 255, 255, 0]);
+    assert_eq!(render(vec![ColorComponents::A]), [0, 255, 255, 255]);
+    assert_eq!(render(vec![ColorComponents::empty()]), [0, 255, 255, 0]);
+}
+
+#[test]
+fn entry_points_are_selected_by_name_from_a_multi_entry_module() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+
+    let words = multi_entry_spirv();
+    let module = unsafe { ShaderModule::new(device.clone(), ShaderModuleCreateInfo::new(&words)) }.unwrap();
+
+    for (name, model) in [("VSMain", ExecutionModel::Vertex), ("PSMain", ExecutionModel::Fragment), ("CSMain", ExecutionModel::GLCompute)] {
+        assert_eq!(find_entry_point(&module, name).unwrap().info().execution_model, model);
+    }
+
+    // A typo names what the module does have
+    match find_entry_point(&module, "main") {
+        Err(EngineError::MissingEntryPoint { name, available }) => {
+            assert_eq!(name, "main");
+            assert_eq!(available, ["VSMain", "PSMain", "CSMain"]);
+        },
+        other => panic!("expected a missing entry point, got {:?}", other.map(|entry_point| entry_point.info().name.clone())),
+    }
+    assert!(matches!(ComputeShader::with_entry_point(&module, "main", [1, 1, 1], device.clone()), Err(EngineError::MissingEntryPoint { .. })));
+
+    let compute = ComputeShader::with_entry_point(&module, "CSMain", [1, 1, 1], device.clone()).unwrap();
+    assert_eq!(compute.pipeline.num_used_descriptor_sets(), 0);
+
+    // Both graphics stages come out of the same module
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig::color(Format::R8G8B8A8_UNORM))
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let options = PipelineOptions {
+        entry_points : EntryPointNames {
+            vertex : "VSMain".to_owned(),
+            fragment : "PSMain".to_owned(),
+        },
+        ..Default::default()
+    };
+    let pipeline = toolset.create_configured_pipeline_for(&module, Some(&module), Subpass::from(render_pass, 0).unwrap(), viewport, options);
+    // Only set when the pipeline has a fragment stage
+    assert!(pipeline.fragment_tests_stages().is_some());
+}
+
+#[test]
+fn shader_interface_reflects_the_triangle_and_mandelbrot_shaders() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+
+    let triangle = Triangle::new(toolset.memory_allocator.general_allocator.clone(), device).unwrap();
+
+    let vertex = ShaderInterface::inspect(&triangle.vertex_shader, "main").unwrap();
+    assert_eq!(vertex.stage, ShaderStage::Vertex);
+    assert!(vertex.descriptor_bindings.is_empty());
+    assert_eq!(vertex.push_constants, None);
+    let inputs = vertex.vertex_inputs.iter().map(|input| (input.location, input.format)).collect::<Vec<_>>();
+    assert_eq!(inputs, [(0, Format::R32G32_SFLOAT)]);
+
+    // Outputs aren't vertex inputs
+    let fragment = ShaderInterface::inspect(&triangle.fragment_shader, "main").unwrap();
+    assert_eq!(fragment.stage, ShaderStage::Fragment);
+    assert!(fragment.vertex_inputs.is_empty());
+
+    let mandelbrot = ShaderInterface::inspect(&mandelbrot_cs::load(device.clone()).unwrap(), "main").unwrap();
+    assert_eq!(mandelbrot.stage, ShaderStage::Compute);
+    assert_eq!(mandelbrot.descriptor_bindings, [DescriptorBindingInfo { set : 0, binding : 0, descriptor_types : vec![DescriptorType::StorageImage], array_size : Some(1) }]);
+    assert!(mandelbrot.to_string().contains("StorageImage"));
+
+    let gather = ShaderInterface::inspect(&gather_cs::load(device.clone()).unwrap(), "main").unwrap();
+    let bindings = gather.descriptor_bindings.iter().map(|binding| (binding.set, binding.binding)).collect::<Vec<_>>();
+    assert_eq!(bindings, [(0, 0), (0, 1), (1, 2)]);
+    assert_eq!(gather.push_constants.map(|range| range.size), Some(4));
+
+    assert!(matches!(ShaderInterface::inspect(&triangle.vertex_shader, "vs_main"), Err(EngineError::MissingEntryPoint { .. })));
+}
+
+#[test]
+fn instances_in_a_group_share_the_attribute_of_their_divisor() {
+    let Some(toolset) = headless_toolset() else { return };
+    if !toolset.capabilities.vertex_attribute_divisor {
+        eprintln!("skipping: vertex attribute divisors are not supported");
+        return;
+    }
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    const GROUPS : u32 = 100;
+    const GROUP_SIZE : u32 = 10;
+    const GRID : Grid = Grid { width : 40, height : 25 };
+    assert_eq!(GRID.width * GRID.height, GROUPS * GROUP_SIZE);
+
+    // Red counts the groups, so every pixel tells which group color it got
+    let colors = Buffer::from_iter(
+        allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        (0..GROUPS).map(|group| GroupColor { group_color : [group as u8, 255, 0, 255] }),
+    ).unwrap();
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let image = Image::new(
+        allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [GRID.width, GRID.height, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(image.clone()).unwrap()]).unwrap();
+
+    let vs = group_color_vs::load(device.clone()).unwrap().entry_point("main").unwrap();
+    let fs = vertex_color_fs::load(device.clone()).unwrap().entry_point("main").unwrap();
+    let vertex_input_state = GroupColor::per_instance()
+    .definition(&vs.info().input_interface)
+    .unwrap()
+    .with_attribute_divisor(0, GROUP_SIZE);
+    toolset.check_attribute_divisors(&vertex_input_state).unwrap();
+
+    let stages = [
+        PipelineShaderStageCreateInfo::new(vs),
+        PipelineShaderStageCreateInfo::new(fs),
+    ];
+    let layout = toolset.create_pipeline_layout(&stages, None, &[], &[]).unwrap();
+    let subpass = Subpass::from(render_pass, 0).unwrap();
+    let pipeline = GraphicsPipeline::new(
+        device.clone(),
+        None,
+        GraphicsPipelineCreateInfo {
+            stages: stages.into_iter().collect(),
+            vertex_input_state: Some(vertex_input_state),
+            input_assembly_state: Some(InputAssemblyState {
+                topology: PrimitiveTopology::PointList,
+                ..Default::default()
+            }),
+            viewport_state: Some(ViewportState {
+                viewports: [Viewport {
+                    offset: [0.0, 0.0],
+                    extent: [GRID.width as f32, GRID.height as f32],
+                    depth_range: 0.0..=1.0,
+                }].into_iter().collect(),
+                ..Default::default()
+            }),
+            rasterization_state: Some(RasterizationState::default()),
+            multisample_state: Some(MultisampleState::default()),
+            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                subpass.num_color_attachments(),
+                ColorBlendAttachmentState::default(),
+            )),
+            subpass: Some(subpass.into()),
+            ..GraphicsPipelineCreateInfo::layout(layout)
+        },
+    ).unwrap();
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    builder.begin_render_pass(
+        RenderPassBeginInfo {
+            clear_values: vec![Some([0.0, 0.0, 0.0, 0.0].into())],
+            ..RenderPassBeginInfo::framebuffer(framebuffer)
+        },
+        SubpassBeginInfo {
+            contents: SubpassContents::Inline,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .bind_pipeline_graphics(pipeline.clone())
+    .unwrap()
+    .push_constants(pipeline.layout().clone(), 0, GRID)
+    .unwrap()
+    .bind_vertex_buffers(0, colors)
+    .unwrap()
+    .draw(1, GROUPS * GROUP_SIZE, 0, 0)
+    .unwrap()
+    .end_render_pass(SubpassEndInfo::default())
+    .unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    // 100 groups of 10 instances, the color only changes every 10th instance
+    let pixels = toolset.readback_image(&image, queue).unwrap();
+    for (instance, texel) in pixels.chunks(4).enumerate() {
+        assert_eq!(texel, [(instance as u32 / GROUP_SIZE) as u8, 255, 0, 255], "instance {instance}");
+    }
+}
+
+#[test]
+fn pipeline_descriptions_are_validated_before_the_driver_sees_them() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig::color(Format::R8G8B8A8_UNORM))
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let triangle = Triangle::new(toolset.memory_allocator.general_allocator.clone(), device).unwrap();
+    let desc = GraphicsPipelineDesc {
+        render_pass_override : Some(render_pass),
+        dynamic_viewport : true,
+        ..GraphicsPipelineDesc::new(triangle.vertex_shader.clone(), triangle.fragment_shader.clone())
+    };
+    let invalid = |desc : GraphicsPipelineDesc| match toolset.create_pipeline(desc) {
+        Err(EngineError::InvalidPipeline(e)) => e,
+        other => panic!("expected an invalid pipeline, got {:?}", other.map(|_| ())),
+    };
+
+    toolset.create_pipeline(desc.clone()).unwrap();
+    toolset.create_pipeline(GraphicsPipelineDesc {
+        topology : PrimitiveTopology::LineStrip,
+        cull : FaceCull::Back,
+        front_face : Winding::Clockwise,
+        blend : Some(AttachmentBlend::alpha()),
+        samples : Some(SampleCount::Sample1),
+        ..desc.clone()
+    }).unwrap();
+
+    // Headless toolsets have no window to take the render pass and viewport from
+    assert_eq!(invalid(GraphicsPipelineDesc { render_pass_override : None, ..desc.clone() }), PipelineDescError::NoRenderPass);
+    assert_eq!(invalid(GraphicsPipelineDesc { dynamic_viewport : false, ..desc.clone() }), PipelineDescError::NoViewport);
+
+    assert_eq!(invalid(GraphicsPipelineDesc { vs : None, ..desc.clone() }), PipelineDescError::MissingVertexShader);
+    assert_eq!(invalid(GraphicsPipelineDesc { subpass_index : 1, ..desc.clone() }), PipelineDescError::NoSubpass { index : 1, subpasses : 1 });
+    assert_eq!(invalid(GraphicsPipelineDesc { depth : Some(DepthState::simple()), ..desc.clone() }), PipelineDescError::NoDepthAttachment);
+    assert_eq!(
+        invalid(GraphicsPipelineDesc { samples : Some(SampleCount::Sample4), ..desc }),
+        PipelineDescError::Samples { requested : SampleCount::Sample4, subpass : SampleCount::Sample1 },
+    );
+}
+
+// Whether the pixel square at `pixel` and the triangle share more than an edge or corner
+fn pixel_overlaps_triangle(pixel : [u32; 2], triangle : &[[f32; 2]; 3]) -> bool {
+    let corners = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]].map(|[x, y] : [f32; 2]| [pixel[0] as f32 + x, pixel[1] as f32 + y]);
+
+    // Separated along x or y
+    for axis in 0..2 {
+        let min = triangle.iter().map(|vertex| vertex[axis]).fold(f32::MAX, f32::min);
+        let max = triangle.iter().map(|vertex| vertex[axis]).fold(f32::MIN, f32::max);
+        if max <= pixel[axis] as f32 || min >= pixel[axis] as f32 + 1.0 {
+            return false;
+        }
+    }
+
+    // Separated by one of the edges, the square lies on the other side from the third vertex
+    for i in 0..3 {
+        let [a, b, c] = [triangle[i], triangle[(i + 1) % 3], triangle[(i + 2) % 3]];
+        let normal = [a[1] - b[1], b[0] - a[0]];
+        let side = |point : [f32; 2]| normal[0] * (point[0] - a[0]) + normal[1] * (point[1] - a[1]);
+        let inside = side(c).signum();
+        if corners.iter().all(|&corner| side(corner) * inside <= 0.0) {
+            return false;
+        }
+    }
+
+    true
+}
+
+#[test]
+fn conservative_raster_voxelizes_every_voxel_the_triangle_touches() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    // One voxel per pixel, the depth picks the layer
+    const SIZE : u32 = 16;
+    const LAYER : u32 = 6;
+    // In pixels, clear of the pixel edges
+    let triangle = [[2.3, 3.1], [13.7, 5.4], [6.2, 12.9]];
+
+    let render_pass = RenderPassBuilder::new()
+    .subpass(&[], None)
+    .build(device)
+    .unwrap();
+    let vs = voxel_vs::load(device.clone()).unwrap();
+    let fs = voxel_fs::load(device.clone()).unwrap();
+    let desc = GraphicsPipelineDesc {
+        render_pass_override : Some(render_pass.clone()),
+        viewport : Some(Viewport {
+            offset: [0.0, 0.0],
+            extent: [SIZE as f32, SIZE as f32],
+            depth_range: 0.0..=1.0,
+        }),
+        conservative_raster : Some(ConservativeRasterMode::overestimate()),
+        ..GraphicsPipelineDesc::new(vs, fs)
+    };
+
+    // Past the largest overestimation the device supports, or without conservative rasterization at all
+    let too_large = ConservativeRasterMode { extra_overestimation_size : f32::MAX, ..ConservativeRasterMode::overestimate() };
+    assert!(matches!(toolset.check_conservative_raster(&too_large), Err(EngineError::UnsupportedFeature(_))));
+
+    if !toolset.capabilities.conservative_rasterization {
+        assert!(matches!(toolset.create_pipeline(desc), Err(EngineError::UnsupportedFeature(_))));
+        eprintln!("skipping: conservative rasterization is not supported");
+        return;
+    }
+    if !device.enabled_features().fragment_stores_and_atomics {
+        eprintln!("skipping: fragment shaders can't write storage images");
+        return;
+    }
+    let pipeline = toolset.create_pipeline(desc).unwrap();
+
+    let voxels = Image::new(
+        allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim3d,
+            format: Format::R32_UINT,
+            extent: [SIZE, SIZE, SIZE],
+            usage: ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone(), Default::default());
+    let set = PersistentDescriptorSet::new(
+        &descriptor_set_allocator,
+        pipeline.layout().set_layouts()[0].clone(),
+        [WriteDescriptorSet::image_view(0, ImageView::new_default(voxels.clone()).unwrap())],
+        [],
+    ).unwrap();
+    let framebuffer = Framebuffer::new(
+        render_pass,
+        FramebufferCreateInfo {
+            extent: [SIZE, SIZE],
+            layers: 1,
+            ..Default::default()
+        },
+    ).unwrap();
+
+    // Pixels to normalized device coordinates
+    let vertices = Buffer::from_iter(
+        allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        triangle.map(|[x, y]| VulkanVertex::new(x / SIZE as f32 * 2.0 - 1.0, y / SIZE as f32 * 2.0 - 1.0)),
+    ).unwrap();
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    builder.clear_color_image(ClearColorImageInfo {
+        clear_value: ClearColorValue::Uint([0; 4]),
+        ..ClearColorImageInfo::image(voxels.clone())
+    })
+    .unwrap()
+    .begin_render_pass(
+        RenderPassBeginInfo::framebuffer(framebuffer),
+        SubpassBeginInfo {
+            contents: SubpassContents::Inline,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .bind_pipeline_graphics(pipeline.clone())
+    .unwrap()
+    .bind_descriptor_sets(PipelineBindPoint::Graphics, pipeline.layout().clone(), 0, set)
+    .unwrap()
+    .bind_vertex_buffers(0, vertices)
+    .unwrap()
+    .draw(3, 1, 0, 0)
+    .unwrap()
+    .end_render_pass(SubpassEndInfo::default())
+    .unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let filled = toolset.readback_image(&voxels, queue).unwrap()
+    .chunks(4)
+    .map(|texel| u32::from_ne_bytes(texel.try_into().unwrap()) != 0)
+    .collect::<Vec<_>>();
+    let mut required = 0;
+    for z in 0..SIZE {
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let voxel = filled[((z * SIZE + y) * SIZE + x) as usize];
+                if z != LAYER {
+                    assert!(!voxel, "voxel {x} {y} {z} is off the triangle's layer");
+                } else if pixel_overlaps_triangle([x, y], &triangle) {
+                    assert!(voxel, "voxel {x} {y} {z} is crossed by the triangle but empty");
+                    required += 1;
+                }
+            }
+        }
+    }
+    assert!(required > 0);
+}
@@ -0,0 +1,581 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{headless_toolset, mandelbrot_cs, mandelbrot_image, uniform_color_fs, SCREENSHOT_SIZE};
+use engine::{render::{bloom::{BloomConfig, BloomPass}, depth_of_field::{DepthOfFieldPass, DofParams}, post_process::{tonemap, PostProcessChain, TonemapParams, TONEMAP}, sdf::SdfGenerator, ssao::{SsaoCamera, SsaoPass}, ssr::SsrPass}, scene::camera::Camera, vulkan::{render_pass::create_framebuffer, texture::upload_image_view, vulkan::{ComputeShader, FrameTarget}, vulkan_window::VulkanWindow}, EngineError};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    render_pass::Subpass,
+    sync::{self, GpuFuture}
+};
+
+mod swap_channels_fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec2 v_uv;
+            layout(location = 0) out vec4 f_color;
+
+            layout(set = 0, binding = 0) uniform sampler2D source;
+
+            void main() {
+                f_color = texture(source, v_uv).bgra;
+            }
+        ",
+    }
+}
+
+#[test]
+fn jump_flood_matches_brute_force_signed_distances() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+
+    // A rectangular glyph in the middle of the atlas
+    let size = SCREENSHOT_SIZE as i32;
+    let inside = |x : i32, y : i32| (16..48).contains(&x) && (16..40).contains(&y);
+    let coverage = (0..size * size)
+    .map(|i| if inside(i % size, i / size) { 255 } else { 0 })
+    .collect::<Vec<u8>>();
+
+    let atlas = upload_image_view(&toolset, Format::R8_UNORM, [SCREENSHOT_SIZE; 2], &coverage, None).unwrap().image().clone();
+    let output = |format, extent| Image::new(
+        toolset.memory_allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format,
+            extent,
+            usage: ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let sdf = output(Format::R32_SFLOAT, [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1]);
+
+    let generator = SdfGenerator::new(&toolset);
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+
+    assert!(matches!(
+        generator.run(&mut builder, &atlas, &output(Format::R8G8B8A8_UNORM, [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1])),
+        Err(EngineError::UnsupportedFeature(_))
+    ));
+    assert!(matches!(
+        generator.run(&mut builder, &atlas, &output(Format::R32_SFLOAT, [SCREENSHOT_SIZE / 2, SCREENSHOT_SIZE, 1])),
+        Err(EngineError::ImageExtent { .. })
+    ));
+    generator.run(&mut builder, &atlas, &sdf).unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let distances = toolset.readback_image(&sdf, queue).unwrap()
+    .chunks(4)
+    .map(|texel| f32::from_ne_bytes([texel[0], texel[1], texel[2], texel[3]]))
+    .collect::<Vec<_>>();
+
+    for y in 0..size {
+        for x in 0..size {
+            // Distance to the nearest pixel on the other side of the edge, less half a pixel
+            let expected = (0..size * size)
+            .filter(|&i| inside(i % size, i / size) != inside(x, y))
+            .map(|i| (((i % size - x).pow(2) + (i / size - y).pow(2)) as f32).sqrt() - 0.5)
+            .fold(f32::MAX, f32::min);
+            let expected = if inside(x, y) { -expected } else { expected };
+
+            // Jump flooding is approximate, but never by more than a pixel for convex shapes
+            let actual = distances[(y * size + x) as usize];
+            assert!((actual - expected).abs() < 1.0, "pixel ({x}, {y}): {actual}, expected {expected}");
+        }
+    }
+}
+
+#[test]
+fn bloom_spreads_bright_pixels_and_leaves_dim_ones_alone() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+
+    // Half floats: a dim background below the threshold and a bright block in the middle
+    const DIM : u16 = 0x3400;
+    const BRIGHT : u16 = 0x4800;
+    let to_f32 = |half : u16| {
+        let exponent = ((half >> 10) & 0x1f) as i32;
+        let mantissa = (half & 0x3ff) as f32;
+        match exponent {
+            0 => mantissa * 2f32.powi(-24),
+            _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+        }
+    };
+
+    let size = SCREENSHOT_SIZE as usize;
+    let bright = |x : usize, y : usize| (28..36).contains(&x) && (28..36).contains(&y);
+    let pixels = (0..size * size)
+    .flat_map(|i| {
+        let value = if bright(i % size, i / size) { BRIGHT } else { DIM };
+        [value, value, value, 0x3c00].into_iter().flat_map(u16::to_ne_bytes)
+    })
+    .collect::<Vec<u8>>();
+
+    let hdr = upload_image_view(&toolset, Format::R16G16B16A16_SFLOAT, [SCREENSHOT_SIZE; 2], &pixels, None).unwrap().image().clone();
+    let output = Image::new(
+        toolset.memory_allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R16G16B16A16_SFLOAT,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+
+    let bloom = BloomPass::new(&toolset, [SCREENSHOT_SIZE; 2]).unwrap();
+
+    // Red channel of the output
+    let render = |config : BloomConfig| {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &toolset.memory_allocator.buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        ).unwrap();
+        bloom.record(&mut builder, &hdr, &output, config).unwrap();
+
+        sync::now(device.clone())
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+        toolset.readback_image(&output, queue).unwrap()
+        .chunks(8)
+        .map(|texel| to_f32(u16::from_ne_bytes([texel[0], texel[1]])))
+        .collect::<Vec<_>>()
+    };
+
+    let dim = to_f32(DIM);
+
+    // Nothing reaches the threshold, the source comes through untouched
+    let unchanged = render(BloomConfig { threshold : 100.0, ..Default::default() });
+    assert!(unchanged.iter().enumerate().all(|(i, &value)| value == if bright(i % size, i / size) { to_f32(BRIGHT) } else { dim }));
+
+    // The block glows onto its dim surroundings, most strongly next to it
+    let bloomed = render(BloomConfig::default());
+    let near = bloomed[32 * size + 38];
+    let far = bloomed[32 * size + 60];
+    assert!(near > dim + 0.1, "next to the block: {near}");
+    assert!(far >= dim && far < near, "far from the block: {far}, next to it: {near}");
+}
+
+#[test]
+fn ssao_darkens_the_foot_of_a_step_and_leaves_flat_ground_lit() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+
+    let projection = Camera::perspective([0.0, 0.0, 0.0], [0.0, 0.0, -1.0], 1.0).projection_matrix(1.0);
+    let depth_at = |z : f32| {
+        let clip = [projection[2][2] * z + projection[3][2], projection[2][3] * z + projection[3][3]];
+        clip[0] / clip[1]
+    };
+
+    // Facing the camera: the left half half a unit nearer than the right half
+    let size = SCREENSHOT_SIZE as usize;
+    let depths = (0..size * size)
+    .flat_map(|i| depth_at(if i % size < size / 2 { -2.5 } else { -3.0 }).to_ne_bytes())
+    .collect::<Vec<u8>>();
+    let normals = [128u8, 128, 255, 255].repeat(size * size);
+
+    let depth_view = upload_image_view(&toolset, Format::R32_SFLOAT, [SCREENSHOT_SIZE; 2], &depths, None).unwrap();
+    let normal_view = upload_image_view(&toolset, Format::R8G8B8A8_UNORM, [SCREENSHOT_SIZE; 2], &normals, None).unwrap();
+    let camera = Buffer::from_data(
+        toolset.memory_allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::UNIFORM_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        SsaoCamera::new(projection),
+    ).unwrap();
+
+    let ssao = SsaoPass::new(&toolset, SCREENSHOT_SIZE, SCREENSHOT_SIZE, 32).unwrap();
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    let occlusion_map = ssao.record(&mut builder, &depth_view, &normal_view, &camera).unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let occlusion = toolset.readback_image(occlusion_map.image(), queue).unwrap()
+    .chunks(4)
+    .map(|texel| f32::from_ne_bytes([texel[0], texel[1], texel[2], texel[3]]))
+    .collect::<Vec<_>>();
+    let at = |x : usize| occlusion[size / 2 * size + x];
+
+    // Flat ground on either side, well away from the step
+    assert!(at(4) > 0.95, "near plane: {}", at(4));
+    assert!(at(size - 4) > 0.95, "far plane: {}", at(size - 4));
+    // The lower side right next to the step is partly enclosed by it
+    assert!(at(size / 2 + 2) < at(size - 4) - 0.05, "at the step: {}, away from it: {}", at(size / 2 + 2), at(size - 4));
+}
+
+#[test]
+fn depth_of_field_blurs_out_of_focus_pixels_and_keeps_focused_ones() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+
+    let shader = mandelbrot_cs::load(device.clone()).unwrap();
+    let compute = ComputeShader::new(&shader, [8, 8, 1], device.clone());
+    let source = mandelbrot_image(&toolset, &compute);
+    let sharp = toolset.readback_image_data(&source, queue).unwrap();
+
+    // The left half is in focus, the right half far behind it
+    let params = DofParams { focus_distance : 2.0, aperture : 8.0, max_radius : 8.0, ..Default::default() };
+    let depth_at = |distance : f32| params.far * (distance - params.near) / ((params.far - params.near) * distance);
+    let size = 1024usize;
+    let depths = (0..size * size)
+    .flat_map(|i| depth_at(if i % size < size / 2 { 2.0 } else { 50.0 }).to_ne_bytes())
+    .collect::<Vec<u8>>();
+    let depth = upload_image_view(&toolset, Format::R32_SFLOAT, [1024; 2], &depths, None).unwrap().image().clone();
+
+    let output = Image::new(
+        toolset.memory_allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R16G16B16A16_SFLOAT,
+            extent: [1024, 1024, 1],
+            usage: ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+
+    let dof = DepthOfFieldPass::new(&toolset, [1024; 2]).unwrap();
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    dof.record(&mut builder, &source, &depth, &output, params).unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let to_f32 = |half : u16| {
+        let exponent = ((half >> 10) & 0x1f) as i32;
+        let mantissa = (half & 0x3ff) as f32;
+        match exponent {
+            0 => mantissa * 2f32.powi(-24),
+            _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+        }
+    };
+    let blurred = toolset.readback_image(&output, queue).unwrap()
+    .chunks(8)
+    .map(|texel| to_f32(u16::from_ne_bytes([texel[0], texel[1]])))
+    .collect::<Vec<_>>();
+    let source_at = |x : usize, y : usize| sharp.texel(x as u32, y as u32)[0] as f32 / 255.0;
+    let blurred_at = |x : usize, y : usize| blurred[y * size + x];
+
+    // Focused pixels only sample themselves
+    for y in 0..size {
+        for x in 0..size / 2 {
+            assert!((blurred_at(x, y) - source_at(x, y)).abs() < 0.01, "pixel ({x}, {y}) changed");
+        }
+    }
+
+    // Mean difference between horizontal neighbours, away from the focus edge
+    let detail = |at : &dyn Fn(usize, usize) -> f32| {
+        let columns = size / 2 + 64..size - 1;
+        let sum = (0..size)
+        .flat_map(|y| columns.clone().map(move |x| (x, y)))
+        .map(|(x, y)| (at(x + 1, y) - at(x, y)).abs())
+        .sum::<f32>();
+        sum / (size * columns.len()) as f32
+    };
+    let (sharp_detail, blurred_detail) = (detail(&source_at), detail(&blurred_at));
+    assert!(blurred_detail < sharp_detail * 0.5, "detail went from {sharp_detail} to {blurred_detail}");
+}
+
+#[test]
+fn post_process_chain_runs_the_enabled_effects_in_order() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    // Stands in for the window's pass, headless devices can't present
+    let render_pass = VulkanWindow::render_pass_builder(Format::R8G8B8A8_UNORM, Format::D16_UNORM, 1, ImageLayout::TransferSrcOptimal)
+    .build(device)
+    .unwrap();
+    let presented = Image::new(
+        allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let output = create_framebuffer(&render_pass, VulkanWindow::create_attachments(allocator, &presented, Format::D16_UNORM, 1)).unwrap();
+
+    let mut chain = PostProcessChain::new(&toolset, Subpass::from(render_pass, 0).unwrap(), [SCREENSHOT_SIZE; 2], Format::R16G16B16A16_SFLOAT).unwrap();
+    let swap_channels = swap_channels_fs::load(device.clone()).unwrap();
+    chain.add_effect(&toolset, "swap channels", &swap_channels).unwrap();
+    chain.add_tonemap(&toolset, 1.0).unwrap();
+    assert_eq!(chain.effects().iter().map(|effect| effect.name()).collect::<Vec<_>>(), ["swap channels", TONEMAP]);
+
+    // Effects without a source to sample can't be chained
+    let uniform_color = uniform_color_fs::load(device.clone()).unwrap();
+    assert!(matches!(chain.add_effect(&toolset, "uniform color", &uniform_color), Err(EngineError::UndeclaredBinding { set : 0, binding : 0 })));
+
+    // The scene is only the clear color, above 1.0 like HDR lighting
+    let render = |chain : &PostProcessChain| {
+        let command_buffer = toolset.create_frame_command_buffer(&toolset.memory_allocator.buffer_allocator, FrameTarget::PostProcessed(chain, &output), [2.0, 0.5, 0.0, 1.0], Vec::new(), Vec::new(), &[], None, None);
+        sync::now(device.clone())
+        .then_execute(queue.clone(), command_buffer)
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+        let pixels = toolset.readback_image(&presented, queue).unwrap();
+        assert!(pixels.chunks(4).all(|texel| texel == &pixels[..4]), "effects changed pixels unevenly");
+        [pixels[0], pixels[1], pixels[2], pixels[3]]
+    };
+    let close = |actual : [u8; 4], expected : [f32; 3]| {
+        actual.iter().zip(expected).all(|(&actual, expected)| (actual as f32 - expected * 255.0).abs() <= 2.0)
+    };
+
+    // Swapped first, then mapped into 0..1 with the gamma the UNORM output needs
+    let params = TonemapParams::for_format(1.0, Format::R8G8B8A8_UNORM);
+    let mapped = render(&chain);
+    assert!(close(mapped, tonemap([0.0, 0.5, 2.0], &params)), "{mapped:?}");
+
+    // Toggled effects are skipped, the values above 1.0 are then clamped by the output format
+    chain.effect_mut(TONEMAP).unwrap().enabled = false;
+    assert!(close(render(&chain), [0.0, 0.5, 1.0]));
+
+    chain.effect_mut("swap channels").unwrap().enabled = false;
+    assert!(close(render(&chain), [1.0, 0.5, 0.0]));
+
+    chain.effect_mut(TONEMAP).unwrap().enabled = true;
+    assert!(close(render(&chain), tonemap([2.0, 0.5, 0.0], &params)));
+
+    // The output has to match the targets after a resize
+    chain.resize(&toolset, [SCREENSHOT_SIZE / 2; 2]).unwrap();
+    assert_eq!(chain.scene_framebuffer().extent(), [SCREENSHOT_SIZE / 2; 2]);
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    assert!(matches!(chain.record(&toolset, &mut builder, &output), Err(EngineError::ImageExtent { .. })));
+}
+
+#[test]
+fn post_process_chain_blooms_the_scene_before_the_effects() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    let render_pass = VulkanWindow::render_pass_builder(Format::R8G8B8A8_UNORM, Format::D16_UNORM, 1, ImageLayout::TransferSrcOptimal)
+    .build(device)
+    .unwrap();
+    let presented = Image::new(
+        allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let output = create_framebuffer(&render_pass, VulkanWindow::create_attachments(allocator, &presented, Format::D16_UNORM, 1)).unwrap();
+
+    let mut chain = PostProcessChain::new(&toolset, Subpass::from(render_pass, 0).unwrap(), [SCREENSHOT_SIZE; 2], Format::R16G16B16A16_SFLOAT).unwrap();
+    assert!(chain.bloom_mut().is_none());
+    chain.add_bloom(&toolset, BloomConfig::default()).unwrap();
+    chain.add_tonemap(&toolset, 1.0).unwrap();
+
+    // Red of the middle pixel, the clear color's red is past the threshold and green below it
+    let render = |chain : &PostProcessChain| {
+        let command_buffer = toolset.create_frame_command_buffer(&toolset.memory_allocator.buffer_allocator, FrameTarget::PostProcessed(chain, &output), [2.0, 0.5, 0.0, 1.0], Vec::new(), Vec::new(), &[], None, None);
+        sync::now(device.clone())
+        .then_execute(queue.clone(), command_buffer)
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+        let pixels = toolset.readback_image(&presented, queue).unwrap();
+        let middle = ((SCREENSHOT_SIZE / 2 * SCREENSHOT_SIZE + SCREENSHOT_SIZE / 2) * 4) as usize;
+        [pixels[middle], pixels[middle + 1]]
+    };
+
+    // The tonemap sees the glow, so the bloomed scene comes out brighter
+    let bloomed = render(&chain);
+    chain.bloom_mut().unwrap().enabled = false;
+    let plain = render(&chain);
+    assert!(bloomed[0] > plain[0], "bloomed {bloomed:?}, plain {plain:?}");
+
+    // Resizing keeps the bloom, sized for the new extent
+    chain.bloom_mut().unwrap().enabled = true;
+    chain.resize(&toolset, [SCREENSHOT_SIZE / 2; 2]).unwrap();
+    assert!(chain.bloom_mut().is_some_and(|bloom| bloom.enabled));
+}
+
+#[test]
+fn ssr_reflects_the_ceiling_in_a_flat_floor() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+
+    let projection = Camera::perspective([0.0, 0.0, 0.0], [0.0, 0.0, -1.0], 1.0).projection_matrix(1.0);
+    let depth_at = |z : f32| {
+        let clip = [projection[2][2] * z + projection[3][2], projection[2][3] * z + projection[3][3]];
+        clip[0] / clip[1]
+    };
+
+    // A corridor seen from its middle: floor one unit below the camera and ceiling one unit above,
+    // the ceiling red on the left and green on the right. Rows at the horizon stop short of the far plane
+    const RED : [u8; 4] = [255, 0, 0, 255];
+    const GREEN : [u8; 4] = [0, 255, 0, 255];
+    let size = SCREENSHOT_SIZE as usize;
+    let (mut depths, mut normals, mut colors) = (Vec::new(), Vec::new(), Vec::new());
+    for y in 0..size {
+        for x in 0..size {
+            let ndc = [x, y].map(|i| (i as f32 + 0.5) / size as f32 * 2.0 - 1.0);
+            let ray = [ndc[0] / projection[0][0], ndc[1] / projection[1][1]];
+            let ceiling = ray[1] > 0.0;
+            let z = (-1.0 / ray[1].abs()).max(-90.0);
+
+            depths.extend(depth_at(z).to_ne_bytes());
+            normals.extend(if ceiling { [128u8, 0, 128, 255] } else { [128u8, 255, 128, 255] });
+            colors.extend(match (ceiling, x < size / 2) {
+                (true, true) => RED,
+                (true, false) => GREEN,
+                (false, _) => [64, 64, 64, 255],
+            });
+        }
+    }
+
+    let depth_view = upload_image_view(&toolset, Format::R32_SFLOAT, [SCREENSHOT_SIZE; 2], &depths, None).unwrap();
+    let normal_view = upload_image_view(&toolset, Format::R8G8B8A8_UNORM, [SCREENSHOT_SIZE; 2], &normals, None).unwrap();
+    let color_view = upload_image_view(&toolset, Format::R8G8B8A8_UNORM, [SCREENSHOT_SIZE; 2], &colors, None).unwrap();
+    let camera = Buffer::from_data(
+        toolset.memory_allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::UNIFORM_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        SsaoCamera::new(projection),
+    ).unwrap();
+    let composited = ImageView::new_default(Image::new(
+        toolset.memory_allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R16G16B16A16_SFLOAT,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap()).unwrap();
+
+    let ssr = SsrPass::new(&toolset, SCREENSHOT_SIZE, SCREENSHOT_SIZE).unwrap();
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    let reflection_view = ssr.record(&mut builder, &color_view, &depth_view, &normal_view, &camera).unwrap();
+    ssr.composite(&mut builder, &color_view, &composited).unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let to_f32 = |half : u16| {
+        let exponent = ((half >> 10) & 0x1f) as i32;
+        let mantissa = (half & 0x3ff) as f32;
+        match exponent {
+            0 => mantissa * 2f32.powi(-24),
+            _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+        }
+    };
+    let texels = |view : &Arc<ImageView>| toolset.readback_image(view.image(), queue).unwrap()
+    .chunks(8)
+    .map(|texel| [0, 2, 4, 6].map(|i| to_f32(u16::from_ne_bytes([texel[i], texel[i + 1]]))))
+    .collect::<Vec<_>>();
+    let reflection = texels(&reflection_view);
+    let composited = texels(&composited);
+
+    // Near the bottom the floor mirrors the ceiling straight above it
+    let row = size - 8;
+    for (x, expected) in [(8, [1.0, 0.0, 0.0]), (size - 9, [0.0, 1.0, 0.0])] {
+        let [r, g, b, weight] = reflection[row * size + x];
+        assert!(weight > 0.5, "no hit at ({x}, {row}): weight {weight}");
+        assert!([r, g, b].iter().zip(expected).all(|(value, expected)| (value - expected).abs() < 0.2), "reflection at ({x}, {row}) is {:?}", [r, g, b]);
+
+        // Half of the weighted reflection over the gray floor
+        let floor = 64.0 / 255.0;
+        let blend = weight * ssr.strength;
+        let [cr, cg, _, _] = composited[row * size + x];
+        assert!((cr - (floor + (expected[0] - floor) * blend)).abs() < 0.02 && (cg - (floor + (expected[1] - floor) * blend)).abs() < 0.02, "composited at ({x}, {row}) is {cr}, {cg}");
+    }
+
+    // Reflections of the ceiling look down onto the gray floor
+    let [r, g, b, _] = reflection[8 * size + 8];
+    assert!((r - g).abs() < 0.05 && (g - b).abs() < 0.05, "ceiling reflects {:?}", [r, g, b]);
+}
@@ -0,0 +1,186 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::{headless_toolset, multiply_cs, SCREENSHOT_SIZE};
+use engine::vulkan::{mesh::Vertex3D, occlusion::OcclusionQuerySet, pipeline_stats::PipelineStatsPool, render_pass::{create_framebuffer, RenderPassBuilder}, staging::upload_buffer, vertex::Triangle, vulkan::{ComputeShader, CullConfig, DrawCall, FrameTarget, RenderCommand}, vulkan_window::AttachmentConfig};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, WriteDescriptorSet},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    query::QueryPipelineStatisticFlags,
+    pipeline::{graphics::viewport::Viewport, Pipeline, PipelineBindPoint},
+    render_pass::Subpass,
+    sync::{self, GpuFuture}
+};
+
+mod depth_vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec3 position;
+
+            void main() {
+                gl_Position = vec4(position, 1.0);
+            }
+        ",
+    }
+}
+
+#[test]
+fn pipeline_stats_count_compute_invocations() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    if !device.enabled_features().pipeline_statistics_query {
+        eprintln!("skipping: pipeline statistics queries are not supported");
+        return;
+    }
+
+    let data_buffer = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        0..1000u32,
+    ).expect("failed to create buffer");
+
+    let shader = multiply_cs::load(device.clone()).expect("failed to create shader module");
+    let compute = ComputeShader::new(&shader, [64, 1, 1], device.clone());
+    let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone(), Default::default());
+    let descriptor_sets = compute.create_descriptor_sets(
+        &descriptor_set_allocator,
+        [(0, vec![WriteDescriptorSet::buffer(0, data_buffer)])],
+    ).unwrap();
+
+    let stats = PipelineStatsPool::new(device.clone(), QueryPipelineStatisticFlags::COMPUTE_SHADER_INVOCATIONS);
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+
+    stats.begin_stats(&mut builder, 3);
+    builder.bind_pipeline_compute(compute.pipeline.clone())
+    .unwrap()
+    .bind_descriptor_sets(PipelineBindPoint::Compute, compute.pipeline.layout().clone(), 0, descriptor_sets[0].1.clone())
+    .unwrap()
+    .push_constants(compute.pipeline.layout().clone(), 0, 1000u32)
+    .unwrap()
+    .dispatch(compute.group_counts([1000, 1, 1]))
+    .unwrap();
+    stats.end_stats(&mut builder, 3);
+
+    let command_buffer = builder.build().unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), command_buffer)
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    // Whole workgroups run, idle tail invocations included
+    let results = stats.read_all();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[&QueryPipelineStatisticFlags::COMPUTE_SHADER_INVOCATIONS], 16 * 64);
+
+    // Reading consumes the results
+    assert!(stats.read(3).is_none());
+}
+
+#[test]
+fn occlusion_queries_count_no_samples_for_a_hidden_triangle() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    // A fullscreen triangle in front, then a smaller one behind it
+    let vertex = |x, y, z| Vertex3D { position : [x, y, z], normal : [0.0, 0.0, 1.0], uv : [0.0, 0.0] };
+    let front = upload_buffer(allocator, queue, BufferUsage::VERTEX_BUFFER, [vertex(-1.0, -1.0, 0.2), vertex(3.0, -1.0, 0.2), vertex(-1.0, 3.0, 0.2)]).unwrap();
+    let back = upload_buffer(allocator, queue, BufferUsage::VERTEX_BUFFER, [vertex(-0.5, -0.5, 0.8), vertex(0.5, -0.5, 0.8), vertex(0.0, 0.5, 0.8)]).unwrap();
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .attachment(AttachmentConfig::depth(Format::D16_UNORM))
+    .subpass(&[0], Some(1))
+    .build(device)
+    .unwrap();
+    let image = |format, usage| ImageView::new_default(Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap()).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![
+        image(Format::R8G8B8A8_UNORM, ImageUsage::COLOR_ATTACHMENT),
+        image(Format::D16_UNORM, ImageUsage::DEPTH_STENCIL_ATTACHMENT),
+    ]).unwrap();
+
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let vs = depth_vs::load(device.clone()).unwrap();
+    let triangle = Triangle::new(allocator.general_allocator.clone(), device).unwrap();
+    let pipeline = toolset.create_mesh_pipeline_for(&vs, &triangle.fragment_shader, Subpass::from(render_pass, 0).unwrap(), viewport, CullConfig::default());
+
+    let draw = |vertices : &Subbuffer<[Vertex3D]>, id| RenderCommand::Draw(DrawCall {
+        pipeline : pipeline.clone(),
+        vertex_buffer : Some(vertices.clone().into_bytes()),
+        descriptor_sets : Vec::new(),
+        vertex_count : 3,
+        scissor : None,
+        indirect : None,
+        occlusion_query : Some(id),
+    });
+    let queries = OcclusionQuerySet::new(device.clone(), 2).unwrap();
+    assert_eq!(queries.is_precise(), toolset.capabilities.precise_occlusion_queries);
+
+    // Nothing recorded in a slot yet
+    assert!(queries.read(1).unwrap().is_empty());
+
+    let commands = vec![draw(&front, 0), draw(&back, 1)];
+    let command_buffer = toolset.create_frame_command_buffer(&allocator.buffer_allocator, FrameTarget::Framebuffer(&framebuffer), [0.0, 0.0, 0.0, 1.0], Vec::new(), commands, &[], None, Some((&queries, 1)));
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), command_buffer)
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let samples = queries.read(1).unwrap();
+    assert_eq!(samples[&1], 0);
+    match queries.is_precise() {
+        true => assert_eq!(samples[&0], (SCREENSHOT_SIZE * SCREENSHOT_SIZE) as u64),
+        false => assert!(samples[&0] > 0),
+    }
+    assert!(queries.read(0).unwrap().is_empty());
+}
@@ -0,0 +1,106 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::{headless_toolset, SCREENSHOT_SIZE};
+use engine::vulkan::{recording::{frame_file_name, FrameRecorder, RecordingSummary}, render_pass::{create_framebuffer, RenderPassBuilder}, staging::upload_buffer, vertex::{Triangle, VulkanVertex}, vulkan::{DrawCall, FrameTarget, RenderCommand}, vulkan_window::AttachmentConfig};
+use image::Rgba;
+use vulkano::{
+    buffer::BufferUsage,
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage},
+    memory::allocator::AllocationCreateInfo,
+    pipeline::graphics::viewport::Viewport,
+    render_pass::Subpass,
+    sync::{self, GpuFuture}
+};
+
+#[test]
+fn recording_the_animated_triangle_writes_a_numbered_png_sequence() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    // BGRA like most surfaces, the files still have to come out red
+    let format = Format::B8G8R8A8_UNORM;
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(format)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let target = Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(target.clone()).unwrap()]).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let triangle = Triangle::new(allocator.general_allocator.clone(), device).unwrap();
+    let pipeline = toolset.create_graphics_pipeline_for(&triangle.vertex_shader, Some(&triangle.fragment_shader), Subpass::from(render_pass, 0).unwrap(), viewport);
+
+    let dir = std::env::temp_dir().join("gpu_smoke_recording");
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut recorder = FrameRecorder::new(2);
+    recorder.start(&dir, 2).unwrap();
+
+    // The triangle slides right every frame, every other frame is recorded
+    for frame in 0..6 {
+        let offset = frame as f32 * 0.1;
+        let vertices = [(-0.5, -0.5), (0.0, 0.5), (0.5, -0.25)].map(|(x, y)| VulkanVertex::new(x + offset, y));
+        let vertices = upload_buffer(allocator, queue, BufferUsage::VERTEX_BUFFER, vertices).unwrap();
+        let draw = RenderCommand::Draw(DrawCall {
+            pipeline : pipeline.clone(),
+            vertex_buffer : Some(vertices.into_bytes()),
+            descriptor_sets : Vec::new(),
+            vertex_count : 3,
+            scissor : None,
+            indirect : None,
+            occlusion_query : None,
+        });
+        let command_buffer = toolset.create_frame_command_buffer(&allocator.buffer_allocator, FrameTarget::Framebuffer(&framebuffer), [0.0, 0.0, 0.0, 1.0], Vec::new(), vec![draw], &[], None, None);
+
+        let slot = frame % 2;
+        let capture = recorder.capture(&toolset, &allocator.buffer_allocator, &target, slot).unwrap();
+        assert_eq!(capture.is_some(), frame % 2 == 0);
+
+        let mut future = sync::now(device.clone())
+        .then_execute(queue.clone(), command_buffer)
+        .unwrap()
+        .boxed();
+        if let Some(capture) = capture {
+            future = future.then_execute(queue.clone(), capture).unwrap().boxed();
+        }
+        future.then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+        recorder.collect(slot).unwrap();
+    }
+    assert_eq!(recorder.finish().unwrap(), Some(RecordingSummary { written : 3, dropped : 0 }));
+    assert!(!recorder.is_recording());
+
+    let frames = (0..3).map(|index| image::open(dir.join(frame_file_name(index))).unwrap().to_rgba8()).collect::<Vec<_>>();
+    assert!(!dir.join(frame_file_name(3)).exists());
+    for frame in &frames {
+        assert_eq!(frame.dimensions(), (SCREENSHOT_SIZE, SCREENSHOT_SIZE));
+        assert!(frame.pixels().any(|texel| *texel == Rgba([255, 0, 0, 255])));
+        assert!(frame.pixels().all(|texel| texel[2] == 0));
+    }
+    assert!(frames[0] != frames[1] && frames[1] != frames[2]);
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::headless_toolset;
+use engine::vulkan::{reduce::{gpu_reduce, GpuReducer, ReduceOp}, scan::{gpu_prefix_sum, GpuScanner}, staging::{read_back_buffer, upload_buffer}};
+use vulkano::buffer::BufferUsage;
+
+#[test]
+fn gpu_reduction_matches_the_cpu_for_every_op() {
+    let Some(toolset) = headless_toolset() else { return };
+    let reducer = GpuReducer::new(&toolset);
+
+    // Deterministic pseudo random values, no rand dependency needed
+    let mut state = 0x2545_f491u32;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+
+    for len in [1, 255, 512, 513, 100_000, 10_000_000] {
+        let integers = (0..len).map(|_| next() % 1000).collect::<Vec<u32>>();
+        let floats = integers.iter().map(|&value| value as f32 / 1000.0 - 0.25).collect::<Vec<f32>>();
+
+        let integer_buffer = upload_buffer(&toolset.memory_allocator, &toolset.device_queue, BufferUsage::STORAGE_BUFFER, integers.clone()).unwrap();
+        let float_buffer = upload_buffer(&toolset.memory_allocator, &toolset.device_queue, BufferUsage::STORAGE_BUFFER, floats.clone()).unwrap();
+
+        let sum = integers.iter().fold(0u32, |sum, &value| sum.wrapping_add(value));
+        assert_eq!(reducer.reduce(&toolset, &integer_buffer, ReduceOp::Sum).unwrap(), sum, "{len} elements");
+        assert_eq!(reducer.reduce(&toolset, &integer_buffer, ReduceOp::Min).unwrap(), *integers.iter().min().unwrap(), "{len} elements");
+        assert_eq!(reducer.reduce(&toolset, &integer_buffer, ReduceOp::Max).unwrap(), *integers.iter().max().unwrap(), "{len} elements");
+
+        // The GPU adds in a tree, compare against a double precision sum with some slack
+        let expected = floats.iter().map(|&value| value as f64).sum::<f64>();
+        let actual = reducer.reduce(&toolset, &float_buffer, ReduceOp::Sum).unwrap() as f64;
+        assert!((actual - expected).abs() <= 1e-4 * expected.abs().max(1.0), "{len} elements: {actual} != {expected}");
+        assert_eq!(reducer.reduce(&toolset, &float_buffer, ReduceOp::Min).unwrap(), floats.iter().copied().fold(f32::INFINITY, f32::min), "{len} elements");
+        assert_eq!(reducer.reduce(&toolset, &float_buffer, ReduceOp::Max).unwrap(), floats.iter().copied().fold(f32::NEG_INFINITY, f32::max), "{len} elements");
+    }
+
+    let single = upload_buffer(&toolset.memory_allocator, &toolset.device_queue, BufferUsage::STORAGE_BUFFER, [-7.5f32]).unwrap();
+    assert_eq!(gpu_reduce(&toolset, &single, ReduceOp::Sum).unwrap(), -7.5);
+}
+
+#[test]
+fn gpu_prefix_sum_matches_a_cpu_scan() {
+    let Some(toolset) = headless_toolset() else { return };
+    let scanner = GpuScanner::new(&toolset);
+
+    let mut state = 0x9e37_79b9u32;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+
+    // Below, at and past one block, several levels deep and not a power of two
+    for len in [1, 7, 511, 512, 513, 262_145, 10_000_000] {
+        let values = (0..len).map(|_| next() % 100).collect::<Vec<u32>>();
+        let input = upload_buffer(&toolset.memory_allocator, &toolset.device_queue, BufferUsage::STORAGE_BUFFER, values.clone()).unwrap();
+
+        let start = std::time::Instant::now();
+        let output = scanner.prefix_sum(&toolset, &input).unwrap();
+        let elapsed = start.elapsed();
+        eprintln!("prefix sum of {len} elements: {elapsed:?}, {:.1} M elements/s", len as f64 / elapsed.as_secs_f64() / 1e6);
+
+        let expected = values.iter()
+        .scan(0u32, |sum, &value| {
+            let exclusive = *sum;
+            *sum = sum.wrapping_add(value);
+            Some(exclusive)
+        })
+        .collect::<Vec<_>>();
+        assert_eq!(output.len(), len);
+        assert!(read_back_buffer(&toolset, &output).unwrap() == expected, "{len} elements");
+    }
+
+    let input = upload_buffer(&toolset.memory_allocator, &toolset.device_queue, BufferUsage::STORAGE_BUFFER, [5u32, 1, 2]).unwrap();
+    assert_eq!(read_back_buffer(&toolset, &gpu_prefix_sum(&toolset, &input).unwrap()).unwrap(), [0, 5, 6]);
+}
@@ -0,0 +1,565 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{headless_toolset, SCREENSHOT_SIZE};
+use engine::{render::{bloom::BloomConfig, post_process::PostProcessChain, shadow_map::ShadowMapPass}, vulkan::{format_utils::FormatNegotiator, render_pass::{create_framebuffer, FramebufferCache, RenderPassBuilder, RenderPassMismatch, SubpassConfig}, render_target_pool::RenderTargetPool, staging::upload_buffer, vertex::{Triangle, VulkanVertex}, vulkan_window::{AttachmentConfig, VulkanWindow}}, EngineError};
+use vulkano::{
+    buffer::BufferUsage,
+    command_buffer::{AutoCommandBufferBuilder, ClearRect, CommandBufferUsage, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet},
+    format::{ClearColorValue, Format, FormatFeatures},
+    image::{view::ImageView, Image, ImageAspects, ImageCreateInfo, ImageLayout, ImageTiling, ImageType, ImageUsage, SampleCount},
+    memory::allocator::AllocationCreateInfo,
+    pipeline::{graphics::{color_blend::{ColorBlendAttachmentState, ColorBlendState}, depth_stencil::{CompareOp, DepthState, DepthStencilState}, input_assembly::InputAssemblyState, multisample::MultisampleState, rasterization::RasterizationState, vertex_input::{Vertex, VertexDefinition}, viewport::{Viewport, ViewportState}, GraphicsPipelineCreateInfo}, layout::PipelineDescriptorSetLayoutCreateInfo, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout, PipelineShaderStageCreateInfo},
+    render_pass::{AttachmentStoreOp, RenderPass, Subpass},
+    sync::{self, GpuFuture}
+};
+
+mod split_depth_vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec2 position;
+
+            // Depth rises from left to right, from 0.25 to 0.75
+            void main() {
+                gl_Position = vec4(position, position.x * 0.25 + 0.5, 1.0);
+            }
+        ",
+    }
+}
+
+mod split_color_fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: r"
+            #version 460
+
+            layout(input_attachment_index = 0, set = 0, binding = 0) uniform subpassInput depth;
+            layout(location = 0) out vec4 f_color;
+
+            // Depth the first subpass stored next to the depth this one computed
+            void main() {
+                f_color = vec4(subpassLoad(depth).r, gl_FragCoord.z, 0.0, 1.0);
+            }
+        ",
+    }
+}
+
+#[test]
+fn depth_format_supports_depth_attachments() {
+    let Some(toolset) = headless_toolset() else { return };
+    let physical_device = toolset.logical_device.physical_device();
+
+    let format = FormatNegotiator::pick_depth_format(physical_device);
+    assert!(format.aspects().intersects(ImageAspects::DEPTH));
+    assert!(FormatNegotiator::is_format_supported(physical_device, format, ImageTiling::Optimal, FormatFeatures::DEPTH_STENCIL_ATTACHMENT));
+}
+
+#[test]
+fn render_pass_builder_supports_depth_only_passes() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+
+    // D16_UNORM is a required depth attachment format
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        store_op : AttachmentStoreOp::Store,
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::depth(Format::D16_UNORM)
+    })
+    .subpass(&[], Some(0))
+    .build(device)
+    .unwrap();
+
+    let depth_image = Image::new(
+        toolset.memory_allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::D16_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::DEPTH_STENCIL_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(depth_image.clone()).unwrap()]).unwrap();
+
+    // Vertex shader only, there is no color to write
+    let triangle = Triangle::new(toolset.memory_allocator.general_allocator.clone(), device).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let pipeline = toolset.create_graphics_pipeline_for(&triangle.vertex_shader, None, Subpass::from(render_pass.clone(), 0).unwrap(), viewport);
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    builder.begin_render_pass(
+        RenderPassBeginInfo {
+            clear_values: vec![Some(1f32.into())],
+            ..RenderPassBeginInfo::framebuffer(framebuffer)
+        },
+        SubpassBeginInfo {
+            contents: SubpassContents::Inline,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .bind_pipeline_graphics(pipeline)
+    .unwrap()
+    .bind_vertex_buffers(0, triangle.vertex_buffer.clone())
+    .unwrap()
+    .draw(triangle.vertex_buffer.len() as u32, 1, 0, 0)
+    .unwrap()
+    .end_render_pass(SubpassEndInfo::default())
+    .unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let depth = toolset.readback_image(&depth_image, queue).unwrap()
+    .chunks(2)
+    .map(|texel| u16::from_ne_bytes([texel[0], texel[1]]))
+    .collect::<Vec<_>>();
+    let center = (SCREENSHOT_SIZE / 2 * SCREENSHOT_SIZE + SCREENSHOT_SIZE / 2) as usize;
+    assert_eq!(depth[0], u16::MAX, "corner keeps the clear depth");
+    assert_eq!(depth[center], 0, "triangle writes its depth");
+
+    // Later subpasses read earlier results as input attachments
+    let deferred = RenderPassBuilder::new()
+    .attachment(AttachmentConfig::color(Format::R8G8B8A8_UNORM))
+    .attachment(AttachmentConfig::color(Format::R8G8B8A8_UNORM))
+    .subpass(&[0], None)
+    .subpass_config(SubpassConfig { color : vec![1], depth : None, depth_read_only : false, input : vec![0], resolve : Vec::new() })
+    .build(device)
+    .unwrap();
+    assert_eq!(deferred.subpasses().len(), 2);
+    assert_eq!(deferred.dependencies().len(), 1);
+}
+
+#[test]
+fn split_depth_color_pass_reads_the_depth_of_its_first_subpass() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    let render_pass = VulkanWindow::create_split_depth_color_render_pass(device, Format::R32G32B32A32_SFLOAT, Format::D16_UNORM, ImageLayout::TransferSrcOptimal).unwrap();
+    assert_eq!(render_pass.subpasses().len(), 2);
+    assert_eq!(render_pass.dependencies().len(), 1);
+
+    let color = Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R32G32B32A32_SFLOAT,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let depth = VulkanWindow::create_split_depth_image(&allocator.general_allocator, [SCREENSHOT_SIZE; 2], Format::D16_UNORM).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![ImageView::new_default(color.clone()).unwrap(), depth.clone()]).unwrap();
+
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let vs = split_depth_vs::load(device.clone()).unwrap();
+    let depth_pipeline = toolset.create_graphics_pipeline_for(&vs, None, Subpass::from(render_pass.clone(), 0).unwrap(), viewport.clone());
+
+    // Same geometry again, only fragments at the stored depth pass and nothing is written to depth
+    let color_subpass = Subpass::from(render_pass.clone(), 1).unwrap();
+    let vs = vs.entry_point("main").unwrap();
+    let fs = split_color_fs::load(device.clone()).unwrap().entry_point("main").unwrap();
+    let vertex_input_state = VulkanVertex::per_vertex()
+    .definition(&vs.info().input_interface)
+    .unwrap();
+    let stages = [
+        PipelineShaderStageCreateInfo::new(vs),
+        PipelineShaderStageCreateInfo::new(fs),
+    ];
+    let layout = PipelineLayout::new(
+        device.clone(),
+        PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+        .into_pipeline_layout_create_info(device.clone())
+        .unwrap(),
+    ).unwrap();
+    let color_pipeline = GraphicsPipeline::new(
+        device.clone(),
+        None,
+        GraphicsPipelineCreateInfo {
+            stages: stages.into_iter().collect(),
+            vertex_input_state: Some(vertex_input_state),
+            input_assembly_state: Some(InputAssemblyState::default()),
+            viewport_state: Some(ViewportState {
+                viewports: [viewport].into_iter().collect(),
+                ..Default::default()
+            }),
+            rasterization_state: Some(RasterizationState::default()),
+            multisample_state: Some(MultisampleState::default()),
+            depth_stencil_state: Some(DepthStencilState {
+                depth: Some(DepthState {
+                    write_enable: false,
+                    compare_op: CompareOp::LessOrEqual,
+                }),
+                ..Default::default()
+            }),
+            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                color_subpass.num_color_attachments(),
+                ColorBlendAttachmentState::default(),
+            )),
+            subpass: Some(color_subpass.into()),
+            ..GraphicsPipelineCreateInfo::layout(layout)
+        },
+    ).unwrap();
+    let input_set = PersistentDescriptorSet::new(
+        &StandardDescriptorSetAllocator::new(device.clone(), Default::default()),
+        color_pipeline.layout().set_layouts()[0].clone(),
+        [WriteDescriptorSet::image_view(0, depth)],
+        [],
+    ).unwrap();
+
+    // The left three quarters of the target
+    let quad = [(-1.0, -1.0), (0.5, -1.0), (-1.0, 1.0), (0.5, -1.0), (0.5, 1.0), (-1.0, 1.0)].map(|(x, y)| VulkanVertex::new(x, y));
+    let quad = upload_buffer(allocator, queue, BufferUsage::VERTEX_BUFFER, quad).unwrap();
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    builder.begin_render_pass(
+        RenderPassBeginInfo {
+            clear_values: vec![Some([0.0; 4].into()), Some(1f32.into())],
+            ..RenderPassBeginInfo::framebuffer(framebuffer)
+        },
+        SubpassBeginInfo {
+            contents: SubpassContents::Inline,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .bind_pipeline_graphics(depth_pipeline)
+    .unwrap()
+    .bind_vertex_buffers(0, quad.clone())
+    .unwrap()
+    .draw(6, 1, 0, 0)
+    .unwrap()
+    .next_subpass(SubpassEndInfo::default(), SubpassBeginInfo::default())
+    .unwrap()
+    .bind_pipeline_graphics(color_pipeline.clone())
+    .unwrap()
+    .bind_descriptor_sets(PipelineBindPoint::Graphics, color_pipeline.layout().clone(), 0, input_set)
+    .unwrap()
+    .draw(6, 1, 0, 0)
+    .unwrap()
+    .end_render_pass(SubpassEndInfo::default())
+    .unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let texels = toolset.readback_image(&color, queue).unwrap()
+    .chunks(16)
+    .map(|texel| [0, 1, 2, 3].map(|i| f32::from_ne_bytes(texel[i * 4..i * 4 + 4].try_into().unwrap())))
+    .collect::<Vec<_>>();
+    let texel = |x : u32, y : u32| texels[(y * SCREENSHOT_SIZE + x) as usize];
+
+    // Both subpasses ran over the quad and agree on its depth, which is what subpass 0 drew
+    for x in [0, 10, 30, 45] {
+        let [stored, computed, _, alpha] = texel(x, SCREENSHOT_SIZE / 2);
+        let ndc_x = (x as f32 + 0.5) / SCREENSHOT_SIZE as f32 * 2.0 - 1.0;
+        assert_eq!(alpha, 1.0, "subpass 1 didn't shade pixel {x}");
+        assert!((stored - computed).abs() < 1e-3, "pixel {x} stored depth {stored}, subpass 1 computed {computed}");
+        assert!((stored - (ndc_x * 0.25 + 0.5)).abs() < 1e-3, "pixel {x} has depth {stored}");
+    }
+
+    // Outside of the quad nothing is shaded
+    assert_eq!(texel(60, SCREENSHOT_SIZE / 2), [0.0; 4]);
+}
+
+#[test]
+fn framebuffer_cache_reuses_framebuffers_within_a_generation() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig::color(Format::R8G8B8A8_UNORM))
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+
+    // Stand-ins for two generations of swapchain images
+    let create_images = || (0..2).map(|_| Image::new(
+        toolset.memory_allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap()).collect::<Vec<_>>();
+    let first = create_images();
+    let second = create_images();
+
+    let mut created = 0;
+    let mut cache = FramebufferCache::new();
+    let mut framebuffers = |images : &[Arc<Image>]| cache.get_or_create(&render_pass, images, |image| {
+        created += 1;
+        create_framebuffer(&render_pass, vec![ImageView::new_default(image.clone()).unwrap()]).unwrap()
+    });
+
+    let a = framebuffers(&first);
+    let b = framebuffers(&first);
+    assert!(a.iter().zip(&b).all(|(a, b)| Arc::ptr_eq(a, b)));
+
+    // New images start a new generation
+    let c = framebuffers(&second);
+    assert!(a.iter().zip(&c).all(|(a, c)| !Arc::ptr_eq(a, c)));
+    let d = framebuffers(&first);
+    assert!(a.iter().zip(&d).all(|(a, d)| !Arc::ptr_eq(a, d)));
+
+    assert_eq!(created, 6);
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn attachments_can_be_cleared_inside_a_render_pass() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .attachment(AttachmentConfig {
+        store_op : AttachmentStoreOp::Store,
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::depth(Format::D16_UNORM)
+    })
+    .subpass(&[0], Some(1))
+    .build(device)
+    .unwrap();
+
+    let image = |format, usage| Image::new(
+        allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: usage | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let color = image(Format::R8G8B8A8_UNORM, ImageUsage::COLOR_ATTACHMENT);
+    let depth = image(Format::D16_UNORM, ImageUsage::DEPTH_STENCIL_ATTACHMENT);
+    let framebuffer = create_framebuffer(&render_pass, vec![
+        ImageView::new_default(color.clone()).unwrap(),
+        ImageView::new_default(depth.clone()).unwrap(),
+    ]).unwrap();
+
+    let left_half = ClearRect {
+        offset: [0, 0],
+        extent: [SCREENSHOT_SIZE / 2, SCREENSHOT_SIZE],
+        array_layers: 0..1,
+    };
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    builder.begin_render_pass(
+        RenderPassBeginInfo {
+            clear_values: vec![Some([1.0, 0.0, 0.0, 1.0].into()), Some(1f32.into())],
+            ..RenderPassBeginInfo::framebuffer(framebuffer)
+        },
+        SubpassBeginInfo {
+            contents: SubpassContents::Inline,
+            ..Default::default()
+        },
+    ).unwrap();
+    toolset.record_clear_attachment(&mut builder, 0, ClearColorValue::Float([0.0, 0.0, 1.0, 1.0]), left_half.clone()).unwrap();
+    toolset.record_clear_depth_attachment(&mut builder, 0.0, left_half).unwrap();
+    builder.end_render_pass(SubpassEndInfo::default()).unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let pixels = toolset.readback_image(&color, queue).unwrap();
+    let depths = toolset.readback_image(&depth, queue).unwrap()
+    .chunks(2)
+    .map(|texel| u16::from_ne_bytes([texel[0], texel[1]]))
+    .collect::<Vec<_>>();
+
+    for y in [0, SCREENSHOT_SIZE - 1] {
+        for x in [0, SCREENSHOT_SIZE / 2 - 1, SCREENSHOT_SIZE / 2, SCREENSHOT_SIZE - 1] {
+            let i = (y * SCREENSHOT_SIZE + x) as usize;
+            let left = x < SCREENSHOT_SIZE / 2;
+
+            let expected = if left { [0, 0, 255, 255] } else { [255, 0, 0, 255] };
+            assert_eq!(pixels[i * 4..i * 4 + 4], expected, "color at ({x}, {y})");
+            assert_eq!(depths[i], if left { 0 } else { u16::MAX }, "depth at ({x}, {y})");
+        }
+    }
+}
+
+#[test]
+fn render_target_pool_reuses_released_images_of_the_same_description() {
+    let Some(toolset) = headless_toolset() else { return };
+
+    let pool = RenderTargetPool::new(toolset.memory_allocator.general_allocator.clone());
+    let usage = ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED;
+    let acquire = |format, extent, samples| pool.acquire(format, extent, usage, samples).unwrap();
+
+    let first = acquire(Format::R16G16B16A16_SFLOAT, [SCREENSHOT_SIZE; 2], SampleCount::Sample1);
+    let second = acquire(Format::R16G16B16A16_SFLOAT, [SCREENSHOT_SIZE; 2], SampleCount::Sample1);
+    // Targets held at the same time never share an image
+    assert!(!Arc::ptr_eq(first.image(), second.image()));
+
+    let image = first.image().clone();
+    drop(first);
+    assert_eq!(pool.idle_count(), 1);
+
+    // Any other format, extent or sample count gets an image of its own
+    let others = [
+        acquire(Format::R8G8B8A8_UNORM, [SCREENSHOT_SIZE; 2], SampleCount::Sample1),
+        acquire(Format::R16G16B16A16_SFLOAT, [SCREENSHOT_SIZE / 2; 2], SampleCount::Sample1),
+        acquire(Format::R16G16B16A16_SFLOAT, [SCREENSHOT_SIZE; 2], SampleCount::Sample4),
+    ];
+    assert!(others.iter().all(|other| !Arc::ptr_eq(other.image(), &image)));
+    assert_eq!(others[1].image().extent(), [SCREENSHOT_SIZE / 2, SCREENSHOT_SIZE / 2, 1]);
+    assert_eq!(others[2].image().samples(), SampleCount::Sample4);
+    assert_eq!(pool.idle_count(), 1);
+
+    let reused = acquire(Format::R16G16B16A16_SFLOAT, [SCREENSHOT_SIZE; 2], SampleCount::Sample1);
+    assert!(Arc::ptr_eq(reused.image(), &image));
+    assert_eq!(pool.idle_count(), 0);
+
+    drop((reused, second, others));
+    assert_eq!(pool.idle_count(), 5);
+    pool.trim();
+    assert_eq!(pool.idle_count(), 0);
+}
+
+#[test]
+fn shadow_maps_and_post_process_targets_come_from_the_render_target_pool() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let pool = &toolset.memory_allocator.render_targets;
+
+    // The map goes back once the last clone of its pass is gone, the next map of its size reuses it
+    let shadow_map = ShadowMapPass::new(&toolset, 16);
+    let image = shadow_map.depth_view().image().clone();
+    let clone = shadow_map.clone();
+    drop(shadow_map);
+    assert_eq!(pool.idle_count(), 0);
+    drop(clone);
+    assert_eq!(pool.idle_count(), 1);
+    assert!(Arc::ptr_eq(ShadowMapPass::new(&toolset, 16).depth_view().image(), &image));
+    pool.trim();
+
+    let render_pass = VulkanWindow::render_pass_builder(Format::R8G8B8A8_UNORM, Format::D16_UNORM, 1, ImageLayout::TransferSrcOptimal)
+    .build(device)
+    .unwrap();
+    let mut chain = PostProcessChain::new(&toolset, Subpass::from(render_pass, 0).unwrap(), [SCREENSHOT_SIZE; 2], Format::R16G16B16A16_SFLOAT).unwrap();
+    chain.add_bloom(&toolset, BloomConfig::default()).unwrap();
+    assert_eq!(pool.idle_count(), 0);
+
+    // Resizing hands the old targets back and trims them right away
+    chain.resize(&toolset, [SCREENSHOT_SIZE / 2; 2]).unwrap();
+    assert_eq!(pool.idle_count(), 0);
+
+    // Scene, two intermediates, the bloom output and its five levels
+    drop(chain);
+    assert_eq!(pool.idle_count(), 9);
+}
+
+#[test]
+fn pipelines_are_checked_against_the_framebuffer_they_draw_into() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let allocator = &toolset.memory_allocator.general_allocator;
+
+    let color_pass = |format| RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        final_layout : ImageLayout::TransferSrcOptimal,
+        ..AttachmentConfig::color(format)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let framebuffer_for = |render_pass : &Arc<RenderPass>, format| {
+        let image = Image::new(
+            allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format,
+                extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        ).unwrap();
+        create_framebuffer(render_pass, vec![ImageView::new_default(image).unwrap()]).unwrap()
+    };
+
+    let triangle = Triangle::new(allocator.clone(), device).unwrap();
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32; 2],
+        depth_range: 0.0..=1.0,
+    };
+    let unorm_pass = color_pass(Format::R8G8B8A8_UNORM);
+    let pipeline = toolset.create_graphics_pipeline_for(&triangle.vertex_shader, Some(&triangle.fragment_shader), Subpass::from(unorm_pass.clone(), 0).unwrap(), viewport);
+
+    // Another render pass with the same attachments is compatible
+    let same_pass = color_pass(Format::R8G8B8A8_UNORM);
+    toolset.validate_pipeline_framebuffer_compatibility(&pipeline, &framebuffer_for(&same_pass, Format::R8G8B8A8_UNORM)).unwrap();
+
+    let srgb_pass = color_pass(Format::R8G8B8A8_SRGB);
+    let mismatch = toolset.validate_pipeline_framebuffer_compatibility(&pipeline, &framebuffer_for(&srgb_pass, Format::R8G8B8A8_SRGB));
+    assert!(matches!(
+        mismatch,
+        Err(EngineError::IncompatibleRenderPass(RenderPassMismatch::Format { attachment : 0, pipeline : Format::R8G8B8A8_UNORM, framebuffer : Format::R8G8B8A8_SRGB })),
+    ));
+}
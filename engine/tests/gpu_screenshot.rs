@@ -0,0 +1,155 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::{headless_toolset, SCREENSHOT_SIZE};
+use engine::vulkan::vertex::{Triangle, VulkanVertex};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{graphics::{color_blend::{ColorBlendAttachmentState, ColorBlendState}, input_assembly::InputAssemblyState, multisample::MultisampleState, rasterization::RasterizationState, vertex_input::{Vertex, VertexDefinition}, viewport::{Viewport, ViewportState}, GraphicsPipelineCreateInfo}, layout::PipelineDescriptorSetLayoutCreateInfo, GraphicsPipeline, PipelineLayout, PipelineShaderStageCreateInfo},
+    render_pass::{Framebuffer, FramebufferCreateInfo, Subpass},
+    sync::{self, GpuFuture}
+};
+
+#[test]
+fn triangle_screenshot_keeps_clear_color_in_corners() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+    let triangle = Triangle::new(allocator.general_allocator.clone(), device).unwrap();
+
+    // Headless toolsets have no window render pass, so render into a plain color target
+    let render_pass = vulkano::single_pass_renderpass!(
+        device.clone(),
+        attachments: {
+            color: {
+                format: Format::R8G8B8A8_UNORM,
+                samples: 1,
+                load_op: Clear,
+                store_op: Store,
+            },
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {},
+        },
+    ).unwrap();
+
+    let image = Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        },
+    ).unwrap();
+
+    let framebuffer = Framebuffer::new(
+        render_pass.clone(),
+        FramebufferCreateInfo {
+            attachments: vec![ImageView::new_default(image.clone()).unwrap()],
+            ..Default::default()
+        },
+    ).unwrap();
+
+    let vs = triangle.vertex_shader.entry_point("main").unwrap();
+    let fs = triangle.fragment_shader.entry_point("main").unwrap();
+    let vertex_input_state = VulkanVertex::per_vertex()
+    .definition(&vs.info().input_interface)
+    .unwrap();
+    let stages = [
+        PipelineShaderStageCreateInfo::new(vs),
+        PipelineShaderStageCreateInfo::new(fs),
+    ];
+    let layout = PipelineLayout::new(
+        device.clone(),
+        PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+        .into_pipeline_layout_create_info(device.clone())
+        .unwrap(),
+    ).unwrap();
+    let subpass = Subpass::from(render_pass, 0).unwrap();
+
+    let pipeline = GraphicsPipeline::new(
+        device.clone(),
+        None,
+        GraphicsPipelineCreateInfo {
+            stages: stages.into_iter().collect(),
+            vertex_input_state: Some(vertex_input_state),
+            input_assembly_state: Some(InputAssemblyState::default()),
+            viewport_state: Some(ViewportState {
+                viewports: [Viewport {
+                    offset: [0.0, 0.0],
+                    extent: [SCREENSHOT_SIZE as f32, SCREENSHOT_SIZE as f32],
+                    depth_range: 0.0..=1.0,
+                }].into_iter().collect(),
+                ..Default::default()
+            }),
+            rasterization_state: Some(RasterizationState::default()),
+            multisample_state: Some(MultisampleState::default()),
+            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                subpass.num_color_attachments(),
+                ColorBlendAttachmentState::default(),
+            )),
+            subpass: Some(subpass.into()),
+            ..GraphicsPipelineCreateInfo::layout(layout)
+        },
+    ).unwrap();
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+
+    builder.begin_render_pass(
+        RenderPassBeginInfo {
+            clear_values: vec![Some([0.0, 0.0, 1.0, 1.0].into())],
+            ..RenderPassBeginInfo::framebuffer(framebuffer)
+        },
+        SubpassBeginInfo {
+            contents: SubpassContents::Inline,
+            ..Default::default()
+        },
+    ).unwrap()
+    .bind_pipeline_graphics(pipeline)
+    .unwrap()
+    .bind_vertex_buffers(0, triangle.vertex_buffer.clone())
+    .unwrap()
+    .draw(triangle.vertex_buffer.len() as u32, 1, 0, 0)
+    .unwrap()
+    .end_render_pass(SubpassEndInfo::default())
+    .unwrap();
+
+    let command_buffer = builder.build().unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), command_buffer)
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let pixels = toolset.readback_image(&image, queue).unwrap();
+    let pixel = |x : u32, y : u32| {
+        let i = ((y * SCREENSHOT_SIZE + x) * 4) as usize;
+        [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]]
+    };
+
+    // The triangle never reaches the corners but covers the center
+    let last = SCREENSHOT_SIZE - 1;
+    for (x, y) in [(0, 0), (last, 0), (0, last), (last, last)] {
+        assert_eq!(pixel(x, y), [0, 0, 255, 255], "corner ({x}, {y})");
+    }
+    assert_eq!(pixel(SCREENSHOT_SIZE / 2, SCREENSHOT_SIZE / 2), [255, 0, 0, 255]);
+}
@@ -0,0 +1,161 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::headless_toolset;
+use engine::{render::shadow_map::ShadowMapPass, vulkan::{mesh::Vertex3D, vulkan::{ComputeShader, CullConfig, DepthBias}}};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    descriptor_set::WriteDescriptorSet,
+    image::ImageLayout,
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    render_pass::AttachmentStoreOp,
+    sync::{self, GpuFuture}
+};
+
+// Positions are already in the light's clip space
+mod shadow_caster_vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec3 position;
+
+            void main() {
+                gl_Position = vec4(position, 1.0);
+            }
+        ",
+    }
+}
+
+// Three comparisons against a shadow map, 1.0 where the reference depth is lit
+mod shadow_lookup_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z_id = 2) in;
+
+            layout(set = 0, binding = 0) uniform sampler2DShadow shadow_map;
+
+            layout(set = 0, binding = 1) writeonly buffer Lit {
+                float lit[];
+            };
+
+            void main() {
+                lit[0] = texture(shadow_map, vec3(0.25, 0.5, 0.75));
+                lit[1] = texture(shadow_map, vec3(0.75, 0.5, 0.75));
+                lit[2] = texture(shadow_map, vec3(0.25, 0.5, 0.25));
+            }
+        ",
+    }
+}
+
+#[test]
+fn shadow_map_pass_keeps_its_depth_for_sampling() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+
+    let shadow_map = ShadowMapPass::new(&toolset, 256);
+    let attachment = &shadow_map.render_pass.attachments()[0];
+    assert_eq!(attachment.store_op, AttachmentStoreOp::Store);
+    assert_eq!(attachment.final_layout, ImageLayout::DepthStencilReadOnlyOptimal);
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    shadow_map.record(&mut builder, |_| {});
+    let command_buffer = builder.build().unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), command_buffer)
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+}
+
+#[test]
+fn shadow_casters_darken_what_lies_behind_them() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    let shadow_map = ShadowMapPass::new(&toolset, 64);
+    let vs = shadow_caster_vs::load(device.clone()).unwrap();
+    let pipeline = shadow_map.create_caster_pipeline(&toolset, &vs, CullConfig::default(), DepthBias::shadow_caster()).unwrap();
+    assert!(pipeline.rasterization_state().depth_bias.is_some());
+
+    // An occluder halfway into the depth range over the left half of the map
+    let vertex = |x : f32, y : f32| Vertex3D { position : [x, y, 0.5], normal : [0.0, 0.0, -1.0], uv : [0.0, 0.0] };
+    let occluder = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        [vertex(-1.0, -1.0), vertex(0.0, -1.0), vertex(0.0, 1.0), vertex(-1.0, -1.0), vertex(0.0, 1.0), vertex(-1.0, 1.0)],
+    ).unwrap();
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    shadow_map.record(&mut builder, |builder| {
+        builder.bind_pipeline_graphics(pipeline.clone())
+        .unwrap()
+        .bind_vertex_buffers(0, occluder.clone())
+        .unwrap()
+        .draw(6, 1, 0, 0)
+        .unwrap();
+    });
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let lit = Buffer::from_iter(
+        allocator.general_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        [-1.0f32; 3],
+    ).unwrap();
+
+    let shader = shadow_lookup_cs::load(device.clone()).expect("failed to create shader module");
+    let compute = ComputeShader::new(&shader, [1, 1, 1], device.clone());
+    compute.execute_with_writes(
+        allocator,
+        &allocator.descriptor_set_allocator,
+        queue,
+        [(0, vec![shadow_map.write_compare_descriptor(0), WriteDescriptorSet::buffer(1, lit.clone())])],
+        1,
+    ).unwrap();
+
+    // Behind the occluder is in shadow, beside it and in front of it is lit
+    assert_eq!(*lit.read().unwrap(), [0.0, 1.0, 1.0]);
+}
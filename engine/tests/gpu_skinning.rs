@@ -0,0 +1,71 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::headless_toolset;
+use engine::{render::skinning::{SkinnedMesh, SkinnedVertex, SkinningPass}, scene::{animation::{Bone, Skeleton}, transform::{Quat, Transform, Vec3}}, vulkan::staging::read_back_buffer};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    sync::{self, GpuFuture}
+};
+
+#[test]
+fn skinning_pass_poses_a_two_bone_chain() {
+    let Some(toolset) = headless_toolset() else { return };
+    let queue = &toolset.device_queue;
+
+    // Root at the origin and a child one unit up, both turned a quarter around Z
+    let quarter_turn = Quat::from_rotation_z(std::f32::consts::FRAC_PI_2);
+    let skeleton = Skeleton::new(vec![
+        Bone::new(-1, Transform::IDENTITY.matrix(), Transform::from_rotation(quarter_turn)),
+        Bone::new(0, Transform::from_translation(Vec3::new(0.0, -1.0, 0.0)).matrix(), Transform::new(Vec3::Y, quarter_turn, Vec3::ONE)),
+    ]);
+
+    let vertex = |position : [f32; 3], bone_indices : [u8; 4], bone_weights : [f32; 4]| SkinnedVertex {
+        position,
+        normal : [1.0, 0.0, 0.0],
+        uv : [0.25, 0.75],
+        bone_indices,
+        bone_weights,
+    };
+    let vertices = vec![
+        vertex([0.0, 1.0, 0.0], [0; 4], [1.0, 0.0, 0.0, 0.0]),
+        vertex([0.0, 2.0, 0.0], [1, 0, 0, 0], [1.0, 0.0, 0.0, 0.0]),
+        vertex([0.0, 2.0, 0.0], [0, 1, 0, 0], [0.5, 0.5, 0.0, 0.0]),
+        // Indices past the last bone clamp to it
+        vertex([0.0, 2.0, 0.0], [7, 0, 0, 0], [1.0, 0.0, 0.0, 0.0]),
+    ];
+    let skinned = SkinnedMesh::new(&toolset.memory_allocator, queue, vertices, vec![0, 1, 2]).unwrap();
+
+    let pass = SkinningPass::new(&toolset);
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &toolset.memory_allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+    pass.record(&mut builder, &skinned, &skeleton.compute_skinning_matrices()).unwrap();
+
+    sync::now(toolset.logical_device.clone())
+    .then_execute(queue.clone(), builder.build().unwrap())
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    let posed = read_back_buffer(&toolset, &skinned.mesh.vertex_buffer).unwrap();
+    let expected = [
+        ([-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([-1.0, -1.0, 0.0], [-1.0, 0.0, 0.0]),
+        // Halfway between where each bone alone would put it
+        ([-1.5, -0.5, 0.0], [-(0.5f32.sqrt()), 0.5f32.sqrt(), 0.0]),
+        ([-1.0, -1.0, 0.0], [-1.0, 0.0, 0.0]),
+    ];
+
+    for (index, (vertex, (position, normal))) in posed.iter().zip(expected).enumerate() {
+        let close = |actual : [f32; 3], expected : [f32; 3]| actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-5);
+        assert!(close(vertex.position, position), "vertex {index} at {:?}, expected {position:?}", vertex.position);
+        assert!(close(vertex.normal, normal), "vertex {index} normal {:?}, expected {normal:?}", vertex.normal);
+        assert_eq!(vertex.uv, [0.25, 0.75]);
+    }
+}
@@ -0,0 +1,105 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use std::{cell::Cell, rc::Rc};
+
+use common::{headless_toolset, SCREENSHOT_SIZE};
+use engine::{render::split_screen::ViewportRegion, vulkan::{occlusion::OcclusionQuerySet, pipeline_desc::GraphicsPipelineDesc, render_pass::{create_framebuffer, RenderPassBuilder}, scissor::ScissorState, staging::upload_buffer, vertex::{Triangle, VulkanVertex}, vulkan::{DrawCall, FrameTarget, RenderCommand}, vulkan_window::AttachmentConfig}};
+use vulkano::{
+    buffer::BufferUsage,
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage},
+    memory::allocator::AllocationCreateInfo,
+    pipeline::graphics::viewport::Scissor,
+    sync::{self, GpuFuture}
+};
+
+#[test]
+fn draws_are_replayed_in_every_region_and_recorded_commands_run_once() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    let render_pass = RenderPassBuilder::new()
+    .attachment(AttachmentConfig {
+        initial_layout : ImageLayout::Undefined,
+        ..AttachmentConfig::color(Format::R8G8B8A8_UNORM)
+    })
+    .subpass(&[0], None)
+    .build(device)
+    .unwrap();
+    let image = ImageView::new_default(Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_UNORM,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::COLOR_ATTACHMENT,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap()).unwrap();
+    let framebuffer = create_framebuffer(&render_pass, vec![image]).unwrap();
+
+    // The shaders take no region camera, so the regions only differ in viewport and scissor
+    let triangle = Triangle::new(allocator.general_allocator.clone(), device).unwrap();
+    let pipeline = toolset.create_pipeline(GraphicsPipelineDesc {
+        dynamic_viewport : true,
+        scissor : ScissorState::Dynamic,
+        render_pass_override : Some(render_pass),
+        ..GraphicsPipelineDesc::new(triangle.vertex_shader.clone(), triangle.fragment_shader.clone())
+    }).unwrap();
+    let fullscreen = [[-1.0, -1.0], [3.0, -1.0], [-1.0, 3.0]].map(|position| VulkanVertex { position });
+    let vertices = upload_buffer(allocator, queue, BufferUsage::VERTEX_BUFFER, fullscreen).unwrap();
+
+    let half = SCREENSHOT_SIZE / 2;
+    let identity = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+    let regions = [
+        ViewportRegion::new([0, 0], [half, SCREENSHOT_SIZE], identity),
+        ViewportRegion::new([half, 0], [half, SCREENSHOT_SIZE], identity),
+    ];
+
+    // Query 1 is clipped to the right half, it only sees samples if the second region was drawn too
+    let draw = |id, scissor| RenderCommand::Draw(DrawCall {
+        pipeline : pipeline.clone(),
+        vertex_buffer : Some(vertices.clone().into_bytes()),
+        descriptor_sets : Vec::new(),
+        vertex_count : 3,
+        scissor,
+        indirect : None,
+        occlusion_query : Some(id),
+    });
+    let right_half = Scissor { offset : [half, 0], extent : [half, SCREENSHOT_SIZE] };
+    let recorded = Rc::new(Cell::new(0));
+    let counter = recorded.clone();
+    let commands = vec![
+        draw(0, None),
+        draw(1, Some(right_half)),
+        RenderCommand::Record(Box::new(move |_| counter.set(counter.get() + 1))),
+    ];
+
+    let queries = OcclusionQuerySet::new(device.clone(), 1).unwrap();
+    let command_buffer = toolset.create_frame_command_buffer(&allocator.buffer_allocator, FrameTarget::Framebuffer(&framebuffer), [0.0, 0.0, 0.0, 1.0], Vec::new(), commands, &regions, None, Some((&queries, 0)));
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), command_buffer)
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    // Record closures can't be replayed, they run once for all regions
+    assert_eq!(recorded.get(), 1);
+
+    let samples = queries.read(0).unwrap();
+    match queries.is_precise() {
+        true => {
+            assert_eq!(samples[&0], (SCREENSHOT_SIZE * SCREENSHOT_SIZE) as u64);
+            assert_eq!(samples[&1], (half * SCREENSHOT_SIZE) as u64);
+        },
+        false => assert!(samples[&0] > 0 && samples[&1] > 0),
+    }
+}
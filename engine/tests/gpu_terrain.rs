@@ -0,0 +1,130 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::{headless_toolset, SCREENSHOT_SIZE};
+use engine::{scene::{camera::Camera, terrain::Terrain}, vulkan::texture::Texture2D};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents, SubpassEndInfo},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::graphics::viewport::Viewport,
+    render_pass::{Framebuffer, FramebufferCreateInfo, Subpass},
+    sync::{self, GpuFuture}
+};
+
+#[test]
+fn terrain_heightmap_displaces_tessellated_vertices() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+    let allocator = &toolset.memory_allocator;
+
+    if !device.enabled_features().tessellation_shader {
+        eprintln!("skipping: tessellation shaders are not supported");
+        return;
+    }
+
+    // Radial bump, highest in the center and flat at the edges
+    const HEIGHTMAP_SIZE : u32 = 32;
+    let heights = (0..HEIGHTMAP_SIZE * HEIGHTMAP_SIZE)
+    .map(|i| {
+        let x = (i % HEIGHTMAP_SIZE) as f32 / (HEIGHTMAP_SIZE - 1) as f32 - 0.5;
+        let y = (i / HEIGHTMAP_SIZE) as f32 / (HEIGHTMAP_SIZE - 1) as f32 - 0.5;
+        ((1.0 - (x * x + y * y).sqrt() * 2.0).max(0.0) * 255.0) as u8
+    }).collect::<Vec<_>>();
+    let heightmap = Texture2D::from_grayscale_bytes(&toolset, HEIGHTMAP_SIZE, HEIGHTMAP_SIZE, &heights);
+
+    let render_pass = vulkano::single_pass_renderpass!(
+        device.clone(),
+        attachments: {
+            color: {
+                format: Format::R8G8B8A8_UNORM,
+                samples: 1,
+                load_op: Clear,
+                store_op: Store,
+            },
+            depth: {
+                format: Format::D16_UNORM,
+                samples: 1,
+                load_op: Clear,
+                store_op: DontCare,
+            },
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {depth},
+        },
+    ).unwrap();
+
+    let attachment = |format, usage| Image::new(
+        allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        },
+    ).unwrap();
+    let image = attachment(Format::R8G8B8A8_UNORM, ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC);
+    let depth = attachment(Format::D16_UNORM, ImageUsage::DEPTH_STENCIL_ATTACHMENT);
+
+    let framebuffer = Framebuffer::new(
+        render_pass.clone(),
+        FramebufferCreateInfo {
+            attachments: vec![ImageView::new_default(image.clone()).unwrap(), ImageView::new_default(depth).unwrap()],
+            ..Default::default()
+        },
+    ).unwrap();
+
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [SCREENSHOT_SIZE as f32, SCREENSHOT_SIZE as f32],
+        depth_range: 0.0..=1.0,
+    };
+    let terrain = Terrain::for_subpass(&toolset, heightmap, 8, 2.0, Subpass::from(render_pass, 0).unwrap(), viewport);
+    let camera = Camera::top_down(5.0, 1.0);
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &allocator.buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    ).unwrap();
+
+    builder.begin_render_pass(
+        RenderPassBeginInfo {
+            clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into()), Some(1f32.into())],
+            ..RenderPassBeginInfo::framebuffer(framebuffer)
+        },
+        SubpassBeginInfo {
+            contents: SubpassContents::Inline,
+            ..Default::default()
+        },
+    ).unwrap();
+    terrain.record(&mut builder, &camera, 1.0);
+    builder.end_render_pass(SubpassEndInfo::default()).unwrap();
+
+    let command_buffer = builder.build().unwrap();
+
+    sync::now(device.clone())
+    .then_execute(queue.clone(), command_buffer)
+    .unwrap()
+    .then_signal_fence_and_flush()
+    .unwrap()
+    .wait(None)
+    .unwrap();
+
+    // Terrain color follows the displaced height, a flat grid would shade every pixel the same
+    let pixels = toolset.readback_image(&image, queue).unwrap();
+    let red = |x : u32, y : u32| pixels[((y * SCREENSHOT_SIZE + x) * 4) as usize];
+
+    let center = red(SCREENSHOT_SIZE / 2, SCREENSHOT_SIZE / 2);
+    let corner = red(1, 1);
+    assert!(center > corner + 64, "center {center} should be well above corner {corner}");
+}
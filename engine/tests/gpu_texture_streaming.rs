@@ -0,0 +1,87 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::{headless_toolset, SCREENSHOT_SIZE};
+use engine::{render::texture_streaming::{TextureStreamer, NOT_RESIDENT}, vulkan::{staging::read_back_buffer, texture::{copy_bytes_to_image, ImageRegion}}};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::AllocationCreateInfo,
+    sync::{self, GpuFuture}
+};
+
+#[test]
+fn texture_streamer_loads_the_tiles_the_feedback_asks_for() {
+    let Some(toolset) = headless_toolset() else { return };
+    let device = &toolset.logical_device;
+    let queue = &toolset.device_queue;
+
+    // Four solid 8x8 tiles and room for two of them
+    let tile_color = |tile : u32| [tile as u8 * 60, 255 - tile as u8 * 60, 0, 255];
+    let fallback_colors = (0..4).map(|tile| tile_color(tile).map(|channel| channel as f32 / 255.0)).collect::<Vec<_>>();
+    let mut streamer = TextureStreamer::new(&toolset, [8, 8], 2, &fallback_colors, move |tile| Ok(tile_color(tile).repeat(64))).unwrap();
+
+    let tile_ids = Image::new(
+        toolset.memory_allocator.general_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R32_UINT,
+            extent: [SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1],
+            usage: ImageUsage::STORAGE | ImageUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    ).unwrap();
+    let tile_ids_view = ImageView::new_default(tile_ids.clone()).unwrap();
+
+    // Tile index + 1 on the left and right half of the screen
+    let mut show = |left : u32, right : u32| {
+        let ids = (0..SCREENSHOT_SIZE * SCREENSHOT_SIZE)
+        .flat_map(|i| (if i % SCREENSHOT_SIZE < SCREENSHOT_SIZE / 2 { left + 1 } else { right + 1 }).to_ne_bytes())
+        .collect::<Vec<_>>();
+        copy_bytes_to_image(&toolset, &tile_ids, &ids, ImageRegion::new([SCREENSHOT_SIZE, SCREENSHOT_SIZE, 1])).unwrap();
+
+        // Feedback is copied a frame after it was written and read frames_in_flight frames later
+        for _ in 0..toolset.config.frames_in_flight + 6 {
+            let mut builder = AutoCommandBufferBuilder::primary(
+                &toolset.memory_allocator.buffer_allocator,
+                queue.queue_family_index(),
+                CommandBufferUsage::OneTimeSubmit,
+            ).unwrap();
+            streamer.process_feedback(&mut builder).unwrap();
+            streamer.record_feedback(&mut builder, &tile_ids_view).unwrap();
+
+            sync::now(device.clone())
+            .then_execute(queue.clone(), builder.build().unwrap())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        }
+
+        let page_table = read_back_buffer(&toolset, streamer.page_table()).unwrap();
+        let layers = toolset.readback_image(streamer.cache_image(), queue).unwrap();
+        let slots = (0..4).map(|tile| streamer.cache().slot_of(tile)).collect::<Vec<_>>();
+        (page_table, layers, slots)
+    };
+
+    for (left, right) in [(0, 2), (1, 3)] {
+        let (page_table, layers, slots) = show(left, right);
+
+        // The two shown tiles took both slots, evicting the ones shown before
+        for tile in 0..4 {
+            let resident = tile == left || tile == right;
+            assert_eq!(page_table[tile as usize] != NOT_RESIDENT, resident, "tile {tile} in {page_table:?}");
+            assert_eq!(slots[tile as usize].is_some(), resident);
+        }
+
+        for tile in [left, right] {
+            let layer_bytes = 8 * 8 * 4;
+            let slot = page_table[tile as usize] as usize;
+            assert_eq!(layers[slot * layer_bytes..slot * layer_bytes + 4], tile_color(tile));
+        }
+    }
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "gpu-tests")]
+
+mod common;
+
+use common::headless_toolset;
+use engine::{vulkan::{staging::upload_buffer, transform_feedback::{XfbBinding, XfbPipeline}, vertex::VulkanVertex}, EngineError};
+use vulkano::buffer::{BufferUsage, Subbuffer};
+
+mod xfb_vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: r"
+            #version 460
+
+            layout(location = 0) in vec2 position;
+
+            layout(location = 0, xfb_buffer = 0, xfb_offset = 0, xfb_stride = 8) out vec2 moved;
+            layout(location = 1, xfb_buffer = 1, xfb_offset = 0, xfb_stride = 4) out float index;
+
+            void main() {
+                moved = position * 2.0 + vec2(0.5, -1.0);
+                index = float(gl_VertexIndex);
+                gl_Position = vec4(position, 0.0, 1.0);
+            }
+        ",
+    }
+}
+
+fn read_floats(output : &Subbuffer<[u8]>) -> Vec<f32> {
+    let bytes = output.read().unwrap();
+    bytes.chunks(4).map(|word| f32::from_le_bytes([word[0], word[1], word[2], word[3]])).collect()
+}
+
+#[test]
+fn vertex_outputs_are_captured_into_their_xfb_buffers() {
+    let Some(toolset) = headless_toolset() else { return };
+    assert_eq!(toolset.transform_feedback.is_present(), toolset.capabilities.transform_feedback);
+    let Some(ext) = toolset.transform_feedback.get() else {
+        eprintln!("skipping: no transform feedback");
+        return;
+    };
+    if ext.max_buffers < 2 {
+        eprintln!("skipping: only {} transform feedback buffer", ext.max_buffers);
+        return;
+    }
+
+    let vs = xfb_vs::load(toolset.logical_device.clone()).expect("failed to create shader module");
+    let bindings = [XfbBinding { buffer_index : 0, stride : 8 }, XfbBinding { buffer_index : 1, stride : 4 }];
+    let pipeline = XfbPipeline::new(&toolset, &vs, &bindings).unwrap();
+
+    let positions = [[0.0, 0.0], [1.0, -0.5], [-0.25, 2.0], [3.0, 4.0]];
+    let vertices = upload_buffer(&toolset.memory_allocator, &toolset.device_queue, BufferUsage::VERTEX_BUFFER, positions.map(|position| VulkanVertex { position })).unwrap();
+    let outputs = pipeline.capture(&toolset, &vertices).unwrap();
+    assert_eq!(outputs.len(), 2);
+
+    let expected : Vec<f32> = positions.iter()
+    .flat_map(|[x, y]| [x * 2.0 + 0.5, y * 2.0 - 1.0])
+    .collect();
+    assert_eq!(read_floats(&outputs[0]), expected);
+    assert_eq!(read_floats(&outputs[1]), [0.0, 1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn xfb_bindings_are_checked_against_the_device_limits() {
+    let Some(toolset) = headless_toolset() else { return };
+    // Without the feature the shader module itself is rejected
+    let Some(ext) = toolset.transform_feedback.get().copied() else {
+        eprintln!("skipping: no transform feedback");
+        return;
+    };
+    let vs = xfb_vs::load(toolset.logical_device.clone()).expect("failed to create shader module");
+
+    let past_buffers = XfbBinding { buffer_index : ext.max_buffers, stride : 4 };
+    assert!(matches!(
+        XfbPipeline::new(&toolset, &vs, &[past_buffers]),
+        Err(EngineError::DeviceLimit { limit : "max_transform_feedback_buffers", .. }),
+    ));
+    let past_stride = XfbBinding { buffer_index : 0, stride : ext.max_stride + 4 };
+    assert!(matches!(
+        XfbPipeline::new(&toolset, &vs, &[past_stride]),
+        Err(EngineError::DeviceLimit { limit : "max_transform_feedback_buffer_data_stride", .. }),
+    ));
+}
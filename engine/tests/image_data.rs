@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use engine::{EngineError, ImageData, SaveFormat};
+use vulkano::format::Format;
+
+const SIZE : u32 = 16;
+
+// Four solid 8x8 quadrants, so JPEG blocks don't bleed into each other
+const QUADRANTS : [[f32; 4]; 4] = [[1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0], [1.0, 1.0, 1.0, 1.0]];
+
+fn quadrant_texel(x : u32, y : u32) -> [f32; 4] {
+    QUADRANTS[(y / 8 * 2 + x / 8) as usize]
+}
+
+fn quadrants(format : Format, scale : f32) -> ImageData {
+    let texels = (0..SIZE * SIZE).map(|i| quadrant_texel(i % SIZE, i / SIZE).map(|value| value * scale));
+    let bytes = match format {
+        Format::R32G32B32A32_SFLOAT => texels.flatten().flat_map(f32::to_ne_bytes).collect(),
+        _ => texels.flatten().map(|value| (value * 255.0) as u8).collect(),
+    };
+
+    ImageData::new(SIZE, SIZE, format, bytes).unwrap()
+}
+
+fn temp_path(name : &str) -> PathBuf {
+    std::env::temp_dir().join(format!("engine_image_data_{name}"))
+}
+
+#[test]
+fn texels_follow_the_format_size() {
+    let unorm = quadrants(Format::R8G8B8A8_UNORM, 1.0);
+    assert_eq!(unorm.texel_size(), 4);
+    assert_eq!(unorm.texel(9, 1), [0, 255, 0, 255]);
+
+    let float = quadrants(Format::R32G32B32A32_SFLOAT, 1.0);
+    assert_eq!(float.texel_size(), 16);
+    assert_eq!(float.bytes.len(), (SIZE * SIZE * 16) as usize);
+    assert_eq!(float.texel(1, 9)[8..12], 1.0f32.to_ne_bytes());
+}
+
+#[test]
+fn png_round_trips_exactly() {
+    let path = temp_path("round_trip.png");
+    quadrants(Format::R8G8B8A8_UNORM, 1.0).save(&path, SaveFormat::Png).unwrap();
+
+    let loaded = image::open(&path).unwrap().to_rgba8();
+    for (x, y) in [(0, 0), (15, 0), (0, 15), (15, 15)] {
+        assert_eq!(loaded.get_pixel(x, y).0, quadrant_texel(x, y).map(|value| (value * 255.0) as u8));
+    }
+}
+
+#[test]
+fn jpeg_round_trips_within_compression_error() {
+    let path = temp_path("round_trip.jpg");
+    quadrants(Format::R8G8B8A8_UNORM, 1.0).save(&path, SaveFormat::Jpeg).unwrap();
+
+    let loaded = image::open(&path).unwrap().to_rgb8();
+    for (x, y) in [(3, 3), (12, 3), (3, 12), (12, 12)] {
+        let expected = quadrant_texel(x, y);
+        for (channel, value) in loaded.get_pixel(x, y).0.into_iter().enumerate() {
+            assert!((value as f32 - expected[channel] * 255.0).abs() <= 12.0, "({x}, {y}) channel {channel} is {value}");
+        }
+    }
+}
+
+#[test]
+fn exr_keeps_values_past_one() {
+    let path = temp_path("round_trip.exr");
+    quadrants(Format::R32G32B32A32_SFLOAT, 8.0).save(&path, SaveFormat::Exr).unwrap();
+
+    let loaded = image::open(&path).unwrap().to_rgba32f();
+    for (x, y) in [(0, 0), (15, 0), (0, 15), (15, 15)] {
+        assert_eq!(loaded.get_pixel(x, y).0, quadrant_texel(x, y).map(|value| value * 8.0));
+    }
+
+    // Clamped when written to a format without float channels
+    let path = temp_path("clamped.png");
+    quadrants(Format::R32G32B32A32_SFLOAT, 8.0).save(&path, SaveFormat::Png).unwrap();
+    assert_eq!(image::open(&path).unwrap().to_rgba8().get_pixel(15, 15).0, [255; 4]);
+}
+
+#[test]
+fn byte_counts_and_formats_are_checked() {
+    let result = ImageData::new(2, 2, Format::R32G32B32A32_SFLOAT, vec![0; 16]);
+    assert!(matches!(result, Err(EngineError::ImageDataSize { expected : 64, len : 16 })));
+
+    let result = ImageData::new(2, 2, Format::D32_SFLOAT, vec![0; 16]);
+    assert!(matches!(result, Err(EngineError::UnsupportedFeature(_))));
+}
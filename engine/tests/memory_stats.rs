@@ -0,0 +1,37 @@
+use std::collections::BTreeMap;
+
+use engine::vulkan::memory_stats::{AllocationCategory, CategoryStats, HeapStats, MemoryStats};
+use vulkano::buffer::BufferUsage;
+
+#[test]
+fn buffer_usage_picks_the_category() {
+    let cases = [
+        (BufferUsage::VERTEX_BUFFER | BufferUsage::STORAGE_BUFFER, AllocationCategory::Vertex),
+        (BufferUsage::INDEX_BUFFER | BufferUsage::TRANSFER_DST, AllocationCategory::Vertex),
+        (BufferUsage::UNIFORM_BUFFER, AllocationCategory::Uniform),
+        (BufferUsage::UNIFORM_TEXEL_BUFFER | BufferUsage::TRANSFER_DST, AllocationCategory::Uniform),
+        (BufferUsage::STORAGE_TEXEL_BUFFER | BufferUsage::TRANSFER_SRC, AllocationCategory::Storage),
+        (BufferUsage::INDIRECT_BUFFER | BufferUsage::TRANSFER_SRC, AllocationCategory::Storage),
+        (BufferUsage::TRANSFER_SRC, AllocationCategory::Staging),
+        (BufferUsage::TRANSFER_DST, AllocationCategory::Staging),
+    ];
+
+    for (usage, category) in cases {
+        assert_eq!(AllocationCategory::from_usage(usage), category, "{usage:?}");
+    }
+}
+
+#[test]
+fn summary_totals_the_categories() {
+    let stats = MemoryStats {
+        heaps : vec![HeapStats { size : 8 << 20, device_local : true, tracked_bytes : 3 << 20 }],
+        categories : BTreeMap::from([
+            (AllocationCategory::Vertex, CategoryStats { count : 2, bytes : 1 << 20 }),
+            (AllocationCategory::Texture, CategoryStats { count : 1, bytes : 2 << 20 }),
+        ]),
+    };
+
+    assert_eq!(stats.total(), CategoryStats { count : 3, bytes : 3 << 20 });
+    assert_eq!(stats.to_string(), "3 tracked allocations, 3.00 MiB\n  Vertex: 2 allocations, 1.00 MiB\n  Texture: 1 allocations, 2.00 MiB\n  heap 0 (device local): 3.00 of 8.00 MiB tracked");
+    assert_eq!(MemoryStats::default().to_string(), "0 tracked allocations, 0.00 MiB");
+}
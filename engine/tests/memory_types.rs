@@ -0,0 +1,45 @@
+use engine::vulkan::memory_types::{pick_memory_type, MemoryTypeCandidate};
+use vulkano::memory::MemoryPropertyFlags as Flags;
+
+const GIB : u64 = 1 << 30;
+
+// Memory types an AMD card reports, with the host visible device local type on a heap of
+// `bar_size`: 256 MiB without Resizable BAR, all of video memory with it
+fn amd_memory_types(bar_size : u64) -> Vec<MemoryTypeCandidate> {
+    let coherent = Flags::HOST_VISIBLE | Flags::HOST_COHERENT;
+
+    vec![
+        MemoryTypeCandidate::new(Flags::DEVICE_LOCAL, 16 * GIB),
+        MemoryTypeCandidate::new(coherent, 32 * GIB),
+        MemoryTypeCandidate::new(Flags::DEVICE_LOCAL | coherent, bar_size),
+        MemoryTypeCandidate::new(coherent | Flags::HOST_CACHED, 32 * GIB),
+    ]
+}
+
+#[test]
+fn host_visible_device_local_memory_is_chosen_when_preferred() {
+    for bar_size in [256 << 20, 16 * GIB] {
+        let picked = pick_memory_type(&amd_memory_types(bar_size), u32::MAX, Flags::HOST_VISIBLE, Flags::DEVICE_LOCAL);
+        assert_eq!(picked, Some(2), "with a BAR of {bar_size} bytes");
+    }
+}
+
+#[test]
+fn unrequested_flags_and_smaller_heaps_lose_ties() {
+    let types = amd_memory_types(256 << 20);
+
+    // Not the cached type, and not the small BAR heap while plain system memory fits
+    assert_eq!(pick_memory_type(&types, u32::MAX, Flags::HOST_VISIBLE, Flags::empty()), Some(1));
+    assert_eq!(pick_memory_type(&types, u32::MAX, Flags::HOST_VISIBLE, Flags::HOST_CACHED), Some(3));
+    assert_eq!(pick_memory_type(&types, u32::MAX, Flags::empty(), Flags::DEVICE_LOCAL), Some(0));
+}
+
+#[test]
+fn only_allowed_types_with_every_required_flag_are_picked() {
+    let types = amd_memory_types(16 * GIB);
+
+    // The buffer can't live in the ReBAR type, falls back to system memory
+    assert_eq!(pick_memory_type(&types, 0b1011, Flags::HOST_VISIBLE, Flags::DEVICE_LOCAL), Some(1));
+    assert_eq!(pick_memory_type(&types, 0b0001, Flags::HOST_VISIBLE, Flags::DEVICE_LOCAL), None);
+    assert_eq!(pick_memory_type(&types, u32::MAX, Flags::LAZILY_ALLOCATED, Flags::empty()), None);
+}
@@ -0,0 +1,33 @@
+use engine::{vulkan::mesh::{primitive_restart_index, MeshIndices}, EngineError};
+use vulkano::buffer::IndexType;
+
+#[test]
+fn small_meshes_get_16_bit_indices() {
+    assert_eq!(MeshIndices::fit(vec![0, 1, 2], 3), MeshIndices::U16(vec![0, 1, 2]));
+    assert_eq!(MeshIndices::fit(vec![0, 1, 65534], 65535).index_type(), IndexType::U16);
+
+    // Index 65535 would collide with the 16 bit restart index
+    assert_eq!(MeshIndices::fit(vec![0, 1, 65535], 65536), MeshIndices::U32(vec![0, 1, 65535]));
+}
+
+#[test]
+fn narrowing_rejects_indices_past_16_bits() {
+    assert_eq!(MeshIndices::narrow(&[0, 65535]).unwrap(), MeshIndices::U16(vec![0, 65535]));
+
+    let error = MeshIndices::narrow(&[0, 65536, 70000]).unwrap_err();
+    assert!(matches!(error, EngineError::IndexOverflow { index : 65536, index_type : IndexType::U16 }));
+}
+
+#[test]
+fn strips_end_with_the_restart_index_of_their_type() {
+    let strips = [vec![0, 1, 2, 3], vec![4, 5, 6]];
+
+    let narrow = MeshIndices::strips(&strips, 7);
+    assert_eq!(narrow, MeshIndices::U16(vec![0, 1, 2, 3, 0xFFFF, 4, 5, 6, 0xFFFF]));
+
+    let wide = MeshIndices::strips(&strips, 100_000);
+    assert_eq!(wide, MeshIndices::U32(vec![0, 1, 2, 3, u32::MAX, 4, 5, 6, u32::MAX]));
+
+    assert_eq!(primitive_restart_index(IndexType::U16), 0xFFFF);
+    assert_eq!(primitive_restart_index(IndexType::U32), 0xFFFF_FFFF);
+}
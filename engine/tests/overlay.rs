@@ -0,0 +1,101 @@
+use engine::render::overlay::{glyph, Overlay, OverlayPrimitive, GLYPH_ADVANCE, LINE_HEIGHT, TEXT_SCALE};
+
+const RED : [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+#[test]
+fn primitives_keep_their_order_until_cleared() {
+    let mut overlay = Overlay::new();
+    assert!(overlay.is_empty());
+
+    overlay.rect(0.0, 0.0, 10.0, 10.0, RED);
+    overlay.text(2.0, 2.0, "FPS");
+    overlay.line([0.0, 0.0], [10.0, 0.0], RED);
+
+    assert!(matches!(overlay.primitives(), [
+        OverlayPrimitive::Rect { .. },
+        OverlayPrimitive::Text { .. },
+        OverlayPrimitive::Line { .. },
+    ]));
+
+    overlay.clear();
+    assert!(overlay.is_empty());
+    assert!(overlay.vertices().is_empty());
+}
+
+#[test]
+fn rects_are_two_triangles_in_logical_pixels() {
+    let mut overlay = Overlay::new();
+    overlay.rect(10.0, 20.0, 30.0, 40.0, RED);
+
+    let vertices = overlay.vertices();
+    assert_eq!(vertices.len(), 6);
+    assert!(vertices.iter().all(|vertex| vertex.color == RED));
+
+    let xs = vertices.iter().map(|vertex| vertex.position[0]);
+    let ys = vertices.iter().map(|vertex| vertex.position[1]);
+    assert_eq!(xs.clone().fold(f32::MAX, f32::min), 10.0);
+    assert_eq!(xs.fold(f32::MIN, f32::max), 40.0);
+    assert_eq!(ys.clone().fold(f32::MAX, f32::min), 20.0);
+    assert_eq!(ys.fold(f32::MIN, f32::max), 60.0);
+}
+
+#[test]
+fn lines_are_one_pixel_wide_and_empty_lines_draw_nothing() {
+    let mut overlay = Overlay::new();
+    overlay.line([0.0, 5.0], [10.0, 5.0], RED);
+
+    let vertices = overlay.vertices();
+    assert_eq!(vertices.len(), 6);
+    for vertex in &vertices {
+        assert_eq!((vertex.position[1] - 5.0).abs(), 0.5);
+    }
+
+    overlay.clear();
+    overlay.line([3.0, 3.0], [3.0, 3.0], RED);
+    assert!(overlay.vertices().is_empty());
+}
+
+#[test]
+fn text_draws_one_quad_per_run_of_font_pixels() {
+    let mut overlay = Overlay::new();
+    overlay.text(0.0, 0.0, "-");
+
+    // The dash is a single row of five pixels
+    let vertices = overlay.vertices();
+    assert_eq!(vertices.len(), 6);
+    let top = 3.0 * TEXT_SCALE;
+    assert!(vertices.iter().all(|vertex| vertex.position[1] == top || vertex.position[1] == top + TEXT_SCALE));
+    assert_eq!(vertices.iter().map(|vertex| vertex.position[0]).fold(f32::MIN, f32::max), 5.0 * TEXT_SCALE);
+
+    overlay.clear();
+    overlay.text(0.0, 0.0, " ");
+    assert!(overlay.vertices().is_empty());
+}
+
+#[test]
+fn text_advances_per_character_and_line() {
+    let mut overlay = Overlay::new();
+    overlay.text(100.0, 50.0, " -\n -");
+
+    let vertices = overlay.vertices();
+    assert_eq!(vertices.len(), 12);
+
+    let left = vertices.iter().map(|vertex| vertex.position[0]).fold(f32::MAX, f32::min);
+    assert_eq!(left, 100.0 + GLYPH_ADVANCE);
+    let second_line = vertices[6..].iter().map(|vertex| vertex.position[1]).fold(f32::MAX, f32::min);
+    assert_eq!(second_line, 50.0 + LINE_HEIGHT + 3.0 * TEXT_SCALE);
+}
+
+#[test]
+fn lower_case_is_drawn_as_upper_case_and_unknown_characters_as_boxes() {
+    assert_eq!(glyph('a'), glyph('A'));
+    assert_ne!(glyph('A'), glyph('B'));
+    assert_eq!(glyph('~'), glyph('\u{e9}'));
+    assert_ne!(glyph('~'), glyph(' '));
+}
+
+#[test]
+fn text_size_covers_the_longest_line() {
+    assert_eq!(Overlay::text_size("AB"), [2.0 * GLYPH_ADVANCE - TEXT_SCALE, 7.0 * TEXT_SCALE]);
+    assert_eq!(Overlay::text_size("A\nABC"), [3.0 * GLYPH_ADVANCE - TEXT_SCALE, LINE_HEIGHT + 7.0 * TEXT_SCALE]);
+}
@@ -0,0 +1,90 @@
+use engine::{vulkan::{pipeline_desc::{GraphicsPipelineDesc, PipelineDescError, SubpassLayout}, scissor::ScissorState, vulkan::{CullConfig, DepthBias, EntryPointNames, FaceCull, MultisampleConfig, PipelineOptions, Winding}}, EngineError};
+use vulkano::{image::SampleCount, pipeline::graphics::{color_blend::{AttachmentBlend, ColorComponents}, depth_stencil::DepthState, input_assembly::PrimitiveTopology, rasterization::PolygonMode}};
+
+const COLOR_ONLY : SubpassLayout = SubpassLayout { color_attachments : 1, depth_attachment : false, samples : Some(SampleCount::Sample1) };
+const COLOR_AND_DEPTH : SubpassLayout = SubpassLayout { color_attachments : 1, depth_attachment : true, samples : Some(SampleCount::Sample4) };
+const DEPTH_ONLY : SubpassLayout = SubpassLayout { color_attachments : 0, depth_attachment : true, samples : Some(SampleCount::Sample1) };
+
+#[test]
+fn defaults_match_the_window_pipeline() {
+    let desc = GraphicsPipelineDesc::default();
+    assert!(desc.vs.is_none() && desc.fs.is_none());
+    assert_eq!(desc.vertex_layout.stride, 8);
+    assert_eq!(desc.topology, PrimitiveTopology::TriangleList);
+    assert_eq!((desc.cull, desc.front_face), (FaceCull::None, Winding::CounterClockwise));
+    assert_eq!(desc.polygon_mode, PolygonMode::Fill);
+    assert!(desc.blend.is_none() && desc.depth.is_none() && desc.samples.is_none() && desc.depth_bias.is_none());
+    assert_eq!(desc.entry_points, EntryPointNames::default());
+    assert_eq!(desc.multisample, MultisampleConfig::default());
+    assert_eq!(desc.scissor, ScissorState::default());
+    assert!(desc.color_write_masks.is_empty() && desc.immutable_samplers.is_empty() && desc.inline_uniform_blocks.is_empty());
+    assert!(desc.viewport.is_none() && !desc.dynamic_viewport);
+    assert!(desc.render_pass_override.is_none());
+    assert_eq!(desc.subpass_index, 0);
+
+    for subpass in [COLOR_ONLY, COLOR_AND_DEPTH, DEPTH_ONLY] {
+        assert_eq!(desc.check(&subpass), Ok(()));
+    }
+}
+
+#[test]
+fn depth_state_needs_a_depth_attachment() {
+    let desc = GraphicsPipelineDesc { depth : Some(DepthState::simple()), ..Default::default() };
+
+    assert_eq!(desc.check(&COLOR_ONLY), Err(PipelineDescError::NoDepthAttachment));
+    assert_eq!(desc.check(&COLOR_AND_DEPTH), Ok(()));
+    assert_eq!(
+        EngineError::InvalidPipeline(PipelineDescError::NoDepthAttachment).to_string(),
+        "invalid pipeline: depth state given for a subpass without a depth attachment",
+    );
+}
+
+#[test]
+fn blending_needs_a_color_attachment() {
+    let desc = GraphicsPipelineDesc { blend : Some(AttachmentBlend::alpha()), ..Default::default() };
+
+    assert_eq!(desc.check(&DEPTH_ONLY), Err(PipelineDescError::NoColorAttachments));
+    assert_eq!(desc.check(&COLOR_ONLY), Ok(()));
+}
+
+#[test]
+fn sample_counts_have_to_match_the_subpass() {
+    let desc = GraphicsPipelineDesc { samples : Some(SampleCount::Sample4), ..Default::default() };
+
+    let error = desc.check(&COLOR_ONLY).unwrap_err();
+    assert_eq!(error, PipelineDescError::Samples { requested : SampleCount::Sample4, subpass : SampleCount::Sample1 });
+    assert_eq!(error.to_string(), "4 samples requested for a subpass with 1 samples");
+    assert_eq!(desc.check(&COLOR_AND_DEPTH), Ok(()));
+    // Subpasses without attachments take any count
+    assert_eq!(desc.check(&SubpassLayout { color_attachments : 0, depth_attachment : false, samples : None }), Ok(()));
+}
+
+#[test]
+fn color_write_masks_need_as_many_color_attachments() {
+    let desc = GraphicsPipelineDesc { color_write_masks : vec![ColorComponents::R; 2], ..Default::default() };
+
+    assert_eq!(desc.check(&COLOR_ONLY), Err(PipelineDescError::ColorWriteMasks { masks : 2, color_attachments : 1 }));
+    assert_eq!(GraphicsPipelineDesc { color_write_masks : vec![ColorComponents::R], ..desc }.check(&COLOR_ONLY), Ok(()));
+}
+
+#[test]
+fn pipeline_options_carry_over_to_the_description() {
+    let options = PipelineOptions {
+        cull : CullConfig::back_faces(),
+        depth_bias : Some(DepthBias::shadow_caster()),
+        scissor : ScissorState::Dynamic,
+        color_write_masks : vec![ColorComponents::R | ColorComponents::G],
+        entry_points : EntryPointNames { vertex : "vs_main".to_owned(), fragment : "fs_main".to_owned() },
+        ..Default::default()
+    };
+    let desc = GraphicsPipelineDesc::with_options(None, None, options.clone());
+
+    assert_eq!((desc.cull, desc.front_face), (FaceCull::Back, Winding::CounterClockwise));
+    assert_eq!(desc.depth_bias, options.depth_bias);
+    assert_eq!(desc.scissor, ScissorState::Dynamic);
+    assert_eq!(desc.color_write_masks, options.color_write_masks);
+    assert_eq!(desc.entry_points, options.entry_points);
+    // What PipelineOptions has no say in stays at the defaults
+    assert_eq!(desc.topology, PrimitiveTopology::TriangleList);
+    assert!(desc.render_pass_override.is_none());
+}
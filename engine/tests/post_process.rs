@@ -0,0 +1,73 @@
+use engine::{render::post_process::{output_tonemap, pq_encode, rec709_to_rec2020, tonemap, OutputTonemapParams, TonemapParams}, vulkan::format_utils::OutputMode};
+use vulkano::format::Format;
+
+#[test]
+fn srgb_outputs_are_not_gamma_corrected_twice() {
+    assert_eq!(TonemapParams::for_format(1.5, Format::B8G8R8A8_SRGB), TonemapParams { exposure : 1.5, gamma : 1.0 });
+    assert_eq!(TonemapParams::for_format(1.5, Format::B8G8R8A8_UNORM).gamma, 2.2);
+    assert_eq!(<[f32; 4]>::from(TonemapParams { exposure : 2.0, gamma : 2.2 }), [2.0, 2.2, 0.0, 0.0]);
+}
+
+#[test]
+fn tonemap_maps_any_brightness_into_the_unit_range() {
+    let params = TonemapParams { exposure : 1.0, gamma : 1.0 };
+
+    assert_eq!(tonemap([0.0; 3], &params), [0.0; 3]);
+    assert_eq!(tonemap([1000.0; 3], &params), [1.0; 3]);
+
+    // Monotonic, brighter input never comes out darker
+    let mapped = [0.1, 0.5, 1.0, 2.0, 8.0].map(|value| tonemap([value; 3], &params)[0]);
+    assert!(mapped.windows(2).all(|pair| pair[0] < pair[1]), "{mapped:?}");
+}
+
+#[test]
+fn exposure_scales_before_the_curve_and_gamma_brightens_after_it() {
+    let linear = TonemapParams { exposure : 1.0, gamma : 1.0 };
+
+    assert_eq!(tonemap([0.5; 3], &TonemapParams { exposure : 2.0, ..linear }), tonemap([1.0; 3], &linear));
+    assert!(tonemap([0.5; 3], &TonemapParams { gamma : 2.2, ..linear })[0] > tonemap([0.5; 3], &linear)[0]);
+}
+
+#[test]
+fn pq_covers_zero_to_ten_thousand_nits() {
+    assert!(pq_encode(0.0) < 1e-5);
+    assert!((pq_encode(10000.0) - 1.0).abs() < 1e-5);
+    // Reference white of HDR10 sits about halfway
+    assert!((pq_encode(100.0) - 0.508).abs() < 0.005, "{}", pq_encode(100.0));
+    assert_eq!(pq_encode(20000.0), pq_encode(10000.0));
+}
+
+#[test]
+fn rec2020_conversion_keeps_white_and_shrinks_saturated_colors() {
+    rec709_to_rec2020([1.0; 3]).iter().for_each(|channel| assert!((channel - 1.0).abs() < 1e-3));
+
+    // Rec. 709 red lies inside the wider Rec. 2020 gamut, so it mixes in the other primaries
+    let [r, g, b] = rec709_to_rec2020([1.0, 0.0, 0.0]);
+    assert!(r < 1.0 && g > 0.0 && b > 0.0);
+}
+
+#[test]
+fn hdr_output_reaches_the_peak_and_the_sdr_preview_stops_at_paper_white() {
+    let params = OutputTonemapParams::new(1.0, OutputMode::ScRgb);
+    assert_eq!(<[f32; 4]>::from(params), [1.0, 200.0, 1000.0, 2.0]);
+
+    // scRGB is linear with 80 nits at 1.0
+    let [peak, ..] = output_tonemap([1e6; 3], &params);
+    assert!((peak - 1000.0 / 80.0).abs() < 1e-3, "{peak}");
+    let [white, ..] = output_tonemap([1e6; 3], &params.sdr_preview());
+    assert!((white - 200.0 / 80.0).abs() < 1e-3, "{white}");
+
+    let hdr10 = OutputTonemapParams { mode : OutputMode::Hdr10, ..params };
+    let [peak, ..] = output_tonemap([1e6; 3], &hdr10);
+    assert!((peak - pq_encode(1000.0)).abs() < 1e-3, "{peak}");
+}
+
+#[test]
+fn sdr_output_is_the_plain_tonemap_curve() {
+    let params = OutputTonemapParams::new(1.5, OutputMode::Sdr);
+    let color = [0.2, 1.0, 4.0];
+
+    assert_eq!(output_tonemap(color, &params), tonemap(color, &TonemapParams { exposure : 1.5, gamma : 1.0 }));
+    // The peak only matters for HDR
+    assert_eq!(output_tonemap(color, &params.sdr_preview()), output_tonemap(color, &params));
+}
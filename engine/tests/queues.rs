@@ -0,0 +1,58 @@
+use engine::{vulkan::queues::plan_queues, QueueRequest, QueueRole};
+
+#[test]
+fn roles_get_their_own_queues_while_the_family_has_some() {
+    let requests = [
+        QueueRequest::new(QueueRole::Graphics, 1.0),
+        QueueRequest::new(QueueRole::Compute, 0.5),
+        QueueRequest::new(QueueRole::Transfer, 0.2),
+    ];
+    let plan = plan_queues(&requests, 16);
+
+    assert_eq!(plan.priorities, [1.0, 0.5, 0.2]);
+    assert_eq!(plan.roles.into_iter().collect::<Vec<_>>(), [(QueueRole::Graphics, 0), (QueueRole::Compute, 1), (QueueRole::Transfer, 2)]);
+}
+
+#[test]
+fn requests_past_the_queue_count_share_the_graphics_queue() {
+    let requests = [
+        QueueRequest::new(QueueRole::Graphics, 1.0),
+        QueueRequest::new(QueueRole::Transfer, 0.2),
+        QueueRequest::new(QueueRole::Compute, 0.5),
+    ];
+
+    let plan = plan_queues(&requests, 2);
+    assert_eq!(plan.priorities, [1.0, 0.2]);
+    assert_eq!(plan.roles[&QueueRole::Transfer], 1);
+    assert_eq!(plan.roles[&QueueRole::Compute], 0);
+
+    // Single queue families collapse every role onto it
+    let plan = plan_queues(&requests, 1);
+    assert_eq!(plan.priorities, [1.0]);
+    assert!(plan.roles.values().all(|&index| index == 0));
+}
+
+#[test]
+fn graphics_is_always_created_first() {
+    let plan = plan_queues(&[QueueRequest::new(QueueRole::Transfer, 0.2), QueueRequest::new(QueueRole::Graphics, 0.8)], 4);
+    assert_eq!(plan.priorities, [0.8, 0.2]);
+    assert_eq!(plan.roles[&QueueRole::Graphics], 0);
+
+    // Even when it isn't requested at all
+    let plan = plan_queues(&[], 4);
+    assert_eq!(plan.priorities, [1.0]);
+    assert_eq!(plan.roles.into_iter().collect::<Vec<_>>(), [(QueueRole::Graphics, 0)]);
+}
+
+#[test]
+fn priorities_are_clamped_and_duplicates_ignored() {
+    let requests = [
+        QueueRequest::new(QueueRole::Graphics, 2.0),
+        QueueRequest::new(QueueRole::Compute, -1.0),
+        QueueRequest::new(QueueRole::Compute, 0.7),
+    ];
+    let plan = plan_queues(&requests, 4);
+
+    assert_eq!(plan.priorities, [1.0, 0.0]);
+    assert_eq!(plan.roles.len(), 2);
+}
@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use engine::{vulkan::recording::{frame_file_name, to_rgba8, FrameWriter, RecordingSummary}, EngineError};
+use vulkano::format::Format;
+
+fn temp_dir(name : &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("engine_recording_{name}"));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn frame_files_sort_in_recording_order() {
+    assert_eq!(frame_file_name(0), "frame_000000.png");
+    assert_eq!(frame_file_name(42), "frame_000042.png");
+    assert!(frame_file_name(9) < frame_file_name(10));
+}
+
+#[test]
+fn bgra_frames_are_converted_to_rgba() {
+    let bgra = vec![10, 20, 30, 255, 1, 2, 3, 4];
+    assert_eq!(to_rgba8(Format::B8G8R8A8_SRGB, bgra.clone()).unwrap(), [30, 20, 10, 255, 3, 2, 1, 4]);
+    assert_eq!(to_rgba8(Format::R8G8B8A8_UNORM, bgra.clone()).unwrap(), bgra);
+    assert!(matches!(to_rgba8(Format::R16G16B16A16_SFLOAT, bgra), Err(EngineError::UnsupportedFeature(_))));
+}
+
+#[test]
+fn recordings_follow_a_resize_with_the_new_size() {
+    let dir = temp_dir("resize");
+    let mut writer = FrameWriter::new(&dir, 8).unwrap();
+
+    // Blue in BGRA, red once written
+    let texels = |count : usize| [0, 0, 255, 255].repeat(count);
+    assert!(writer.submit(4, 4, Format::B8G8R8A8_UNORM, texels(16)));
+    assert!(writer.submit(4, 4, Format::B8G8R8A8_UNORM, texels(16)));
+    assert!(writer.submit(8, 2, Format::B8G8R8A8_UNORM, texels(16)));
+    assert_eq!(writer.finish().unwrap(), RecordingSummary { written : 3, dropped : 0 });
+
+    let sizes = (0..3).map(|index| {
+        let frame = image::open(dir.join(frame_file_name(index))).unwrap().to_rgba8();
+        assert!(frame.pixels().all(|texel| texel.0 == [255, 0, 0, 255]));
+        frame.dimensions()
+    }).collect::<Vec<_>>();
+    assert_eq!(sizes, [(4, 4), (4, 4), (8, 2)]);
+}
+
+#[test]
+fn a_full_queue_drops_frames_instead_of_blocking() {
+    let dir = temp_dir("backpressure");
+    let mut writer = FrameWriter::new(&dir, 1).unwrap();
+
+    // Encoding a frame takes far longer than submitting all of them
+    const SIZE : u32 = 512;
+    let frames = (0..64).map(|_| vec![0u8; (SIZE * SIZE * 4) as usize]).collect::<Vec<_>>();
+    let accepted = frames.into_iter()
+    .map(|bytes| writer.submit(SIZE, SIZE, Format::R8G8B8A8_UNORM, bytes))
+    .filter(|&accepted| accepted)
+    .count() as u32;
+
+    let summary = writer.finish().unwrap();
+    assert!(summary.dropped > 0);
+    assert_eq!(summary.written, accepted);
+    assert_eq!(summary.written + summary.dropped, 64);
+
+    // Accepted frames are numbered without gaps
+    assert!((0..accepted).all(|index| dir.join(frame_file_name(index)).exists()));
+    assert!(!dir.join(frame_file_name(accepted)).exists());
+}
@@ -0,0 +1,20 @@
+use engine::vulkan::reduce::reduce_pass_sizes;
+
+#[test]
+fn small_inputs_take_a_single_pass() {
+    assert_eq!(reduce_pass_sizes(1, 65535), [1]);
+    assert_eq!(reduce_pass_sizes(512, 65535), [1]);
+}
+
+#[test]
+fn partial_results_are_reduced_until_one_is_left() {
+    assert_eq!(reduce_pass_sizes(513, 65535), [2, 1]);
+    assert_eq!(reduce_pass_sizes(10_000_000, 65535), [19532, 39, 1]);
+}
+
+#[test]
+fn passes_are_capped_at_the_dispatch_limit() {
+    // The capped pass strides over the rest of the input
+    assert_eq!(reduce_pass_sizes(u32::MAX, 65535), [65535, 128, 1]);
+    assert_eq!(reduce_pass_sizes(100_000, 16), [16, 1]);
+}
@@ -0,0 +1,61 @@
+use engine::{vulkan::render_pass::{compare_attachments, RenderPassMismatch}, EngineError};
+use vulkano::{format::Format, image::{ImageLayout, SampleCount}, render_pass::{AttachmentDescription, AttachmentLoadOp}};
+
+fn attachment(format : Format, samples : SampleCount) -> AttachmentDescription {
+    AttachmentDescription {
+        format,
+        samples,
+        final_layout: ImageLayout::ColorAttachmentOptimal,
+        ..Default::default()
+    }
+}
+
+fn color_and_depth() -> Vec<AttachmentDescription> {
+    vec![
+        attachment(Format::B8G8R8A8_SRGB, SampleCount::Sample1),
+        attachment(Format::D32_SFLOAT, SampleCount::Sample1),
+    ]
+}
+
+#[test]
+fn matching_attachments_are_compatible() {
+    assert_eq!(compare_attachments(&color_and_depth(), &color_and_depth()), Ok(()));
+}
+
+#[test]
+fn load_ops_and_layouts_do_not_matter() {
+    let mut framebuffer = color_and_depth();
+    framebuffer[0].load_op = AttachmentLoadOp::Load;
+    framebuffer[0].final_layout = ImageLayout::PresentSrc;
+
+    assert_eq!(compare_attachments(&color_and_depth(), &framebuffer), Ok(()));
+}
+
+#[test]
+fn missing_attachments_are_reported_first() {
+    let framebuffer = vec![attachment(Format::R8G8B8A8_UNORM, SampleCount::Sample4)];
+
+    assert_eq!(
+        compare_attachments(&color_and_depth(), &framebuffer),
+        Err(RenderPassMismatch::AttachmentCount { pipeline : 2, framebuffer : 1 }),
+    );
+}
+
+#[test]
+fn formats_and_sample_counts_name_the_attachment() {
+    let mut framebuffer = color_and_depth();
+    framebuffer[1].format = Format::D24_UNORM_S8_UINT;
+    assert_eq!(
+        compare_attachments(&color_and_depth(), &framebuffer),
+        Err(RenderPassMismatch::Format { attachment : 1, pipeline : Format::D32_SFLOAT, framebuffer : Format::D24_UNORM_S8_UINT }),
+    );
+
+    let mut framebuffer = color_and_depth();
+    framebuffer[0].samples = SampleCount::Sample4;
+    let mismatch = compare_attachments(&color_and_depth(), &framebuffer).unwrap_err();
+    assert_eq!(mismatch, RenderPassMismatch::Samples { attachment : 0, pipeline : SampleCount::Sample1, framebuffer : SampleCount::Sample4 });
+    assert_eq!(
+        EngineError::IncompatibleRenderPass(mismatch).to_string(),
+        "incompatible render passes: attachment 0 has 1 samples in the pipeline's render pass and 4 in the framebuffer's",
+    );
+}
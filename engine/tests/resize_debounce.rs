@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+use engine::ResizeDebouncer;
+
+const QUIET : Duration = Duration::from_millis(100);
+
+fn ms(millis : u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn nothing_to_do_without_requests() {
+    let start = Instant::now();
+    let mut resize = ResizeDebouncer::new(QUIET);
+
+    assert!(!resize.poll(start));
+    assert!(!resize.poll(start + ms(500)));
+    assert_eq!(resize.deadline(), None);
+    assert_eq!(resize.recreations(), 0);
+}
+
+#[test]
+fn dragging_coalesces_into_one_recreation_once_the_extent_holds_still() {
+    let start = Instant::now();
+    let mut resize = ResizeDebouncer::new(QUIET);
+
+    // A new extent every frame, 5 ms apart, several events per frame at times
+    let mut now = start;
+    for frame in 0..20u32 {
+        resize.request([800 + frame, 600], now);
+        resize.request([801 + frame, 600], now + ms(1));
+        now += ms(5);
+        assert!(!resize.poll(now), "frame {frame}");
+    }
+    assert_eq!(resize.recreations(), 0);
+
+    // The drag paused, the extent is the same as in the previous frame
+    now += ms(5);
+    assert!(resize.poll(now));
+    assert_eq!(resize.recreations(), 1);
+    assert!(!resize.is_pending());
+    assert!(!resize.poll(now + ms(5)));
+}
+
+#[test]
+fn the_quiet_period_recreates_without_another_frame_in_between() {
+    let start = Instant::now();
+    let mut resize = ResizeDebouncer::new(QUIET);
+
+    resize.request([1024, 768], start);
+    assert_eq!(resize.deadline(), Some(start + QUIET));
+    assert!(!resize.poll(start + ms(99)));
+
+    // A later request in between restarts the wait, and the extent changed since the last poll
+    resize.request([1280, 720], start + ms(99));
+    assert_eq!(resize.deadline(), Some(start + ms(199)));
+    assert!(resize.poll(start + ms(199)));
+    assert_eq!(resize.deadline(), None);
+}
+
+#[test]
+fn out_of_date_swapchains_recreate_right_away() {
+    let start = Instant::now();
+    let mut resize = ResizeDebouncer::new(QUIET);
+
+    resize.force();
+    assert!(resize.is_pending());
+    assert!(resize.poll(start));
+
+    // Even in the middle of a drag, and the drag's request is carried out along with it
+    resize.request([640, 480], start + ms(10));
+    assert!(!resize.poll(start + ms(11)));
+    resize.request([650, 480], start + ms(12));
+    resize.force();
+    assert!(resize.poll(start + ms(13)));
+    assert!(!resize.is_pending());
+    assert!(!resize.poll(start + ms(200)));
+    assert_eq!(resize.recreations(), 2);
+}
+
+#[test]
+fn a_request_after_a_recreation_waits_again() {
+    let start = Instant::now();
+    let mut resize = ResizeDebouncer::new(QUIET);
+
+    resize.request([800, 600], start);
+    assert!(!resize.poll(start + ms(1)));
+    assert!(resize.poll(start + ms(2)));
+
+    // The extent matches what was polled before the recreation, that doesn't count as stable
+    resize.request([800, 600], start + ms(3));
+    assert!(!resize.poll(start + ms(4)));
+    assert!(resize.poll(start + ms(5)));
+    assert_eq!(resize.recreations(), 2);
+}
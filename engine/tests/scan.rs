@@ -0,0 +1,21 @@
+use engine::vulkan::scan::{block_dispatch, scan_levels};
+
+#[test]
+fn inputs_within_one_block_take_one_level() {
+    assert_eq!(scan_levels(1), [1]);
+    assert_eq!(scan_levels(300), [300]);
+    assert_eq!(scan_levels(512), [512]);
+}
+
+#[test]
+fn block_totals_are_scanned_until_they_fit_one_block() {
+    assert_eq!(scan_levels(513), [513, 2]);
+    assert_eq!(scan_levels(10_000_000), [10_000_000, 19532, 39]);
+}
+
+#[test]
+fn blocks_past_the_dispatch_limit_wrap_into_rows() {
+    assert_eq!(block_dispatch(1, 65535), [1, 1, 1]);
+    assert_eq!(block_dispatch(19532, 65535), [19532, 1, 1]);
+    assert_eq!(block_dispatch(100_000, 65535), [65535, 2, 1]);
+}
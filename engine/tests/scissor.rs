@@ -0,0 +1,53 @@
+use engine::vulkan::scissor::{clip_scissor, full_scissor};
+use vulkano::pipeline::graphics::viewport::Scissor;
+
+fn rect(offset : [u32; 2], extent : [u32; 2]) -> Scissor {
+    Scissor { offset, extent }
+}
+
+#[test]
+fn rects_inside_the_framebuffer_are_kept() {
+    let bounds = full_scissor([800, 600]);
+
+    assert_eq!(clip_scissor(rect([10, 20], [100, 50]), bounds), Some(rect([10, 20], [100, 50])));
+    assert_eq!(clip_scissor(bounds, bounds), Some(bounds));
+}
+
+#[test]
+fn rects_are_clamped_after_the_framebuffer_shrinks() {
+    // Built for 800x600, the window is now 400x300
+    let bounds = full_scissor([400, 300]);
+
+    assert_eq!(clip_scissor(rect([300, 200], [400, 300]), bounds), Some(rect([300, 200], [100, 100])));
+    assert_eq!(clip_scissor(full_scissor([800, 600]), bounds), Some(bounds));
+    assert_eq!(clip_scissor(Scissor::default(), bounds), Some(bounds));
+}
+
+#[test]
+fn degenerate_rects_are_skipped() {
+    let bounds = full_scissor([800, 600]);
+
+    assert_eq!(clip_scissor(rect([10, 10], [0, 50]), bounds), None);
+    assert_eq!(clip_scissor(rect([10, 10], [50, 0]), bounds), None);
+    // Entirely past the edge, as can happen after a resize
+    assert_eq!(clip_scissor(rect([800, 0], [10, 10]), bounds), None);
+    assert_eq!(clip_scissor(rect([0, 900], [10, 10]), bounds), None);
+    // And an empty framebuffer, as when minimized
+    assert_eq!(clip_scissor(rect([0, 0], [10, 10]), full_scissor([0, 0])), None);
+}
+
+#[test]
+fn rects_stay_inside_a_viewport_region() {
+    let region = rect([400, 0], [400, 600]);
+
+    assert_eq!(clip_scissor(rect([350, 100], [100, 100]), region), Some(rect([400, 100], [50, 100])));
+    assert_eq!(clip_scissor(rect([0, 0], [400, 600]), region), None);
+}
+
+#[test]
+fn huge_rects_do_not_overflow() {
+    let bounds = full_scissor([800, 600]);
+
+    assert_eq!(clip_scissor(rect([u32::MAX - 1, 0], [u32::MAX, u32::MAX]), bounds), None);
+    assert_eq!(clip_scissor(rect([100, 100], [u32::MAX, u32::MAX]), bounds), Some(rect([100, 100], [700, 500])));
+}
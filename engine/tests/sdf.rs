@@ -0,0 +1,24 @@
+use engine::render::sdf::jump_distances;
+
+#[test]
+fn jumps_halve_down_to_one_then_repeat_it() {
+    assert_eq!(jump_distances([64, 64]), vec![32, 16, 8, 4, 2, 1, 1]);
+    assert_eq!(jump_distances([256, 32]), vec![128, 64, 32, 16, 8, 4, 2, 1, 1]);
+}
+
+#[test]
+fn odd_sizes_round_up_to_a_power_of_two() {
+    // 100 pixels need the 64 jump to reach across the image
+    assert_eq!(jump_distances([100, 3]), vec![64, 32, 16, 8, 4, 2, 1, 1]);
+    assert_eq!(jump_distances([3, 100]), jump_distances([100, 3]));
+}
+
+#[test]
+fn pass_count_grows_with_the_log_of_the_size() {
+    for exponent in 0..=12 {
+        let size = 1 << exponent;
+        assert_eq!(jump_distances([size, size]).len(), exponent as usize + 1, "{size} pixels");
+    }
+
+    assert_eq!(jump_distances([0, 0]), vec![1]);
+}
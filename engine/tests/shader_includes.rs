@@ -0,0 +1,70 @@
+use std::{fs, path::Path};
+
+// The shader! macro compiles this at build time, so building the test is the compilation check.
+// math.glsl includes common.glsl itself, including it again relies on the include guards
+#[allow(dead_code)]
+mod tonemap_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        include: ["assets/shaders/include"],
+        src: r#"
+            #version 460
+
+            #include "math.glsl"
+            #include "common.glsl"
+
+            layout(location = 0) in vec3 v_normal;
+            layout(location = 1) in vec2 v_detail;
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                vec3 normal = normalize(unpack_normal(v_normal) + unpack_normal_xy(v_detail));
+                float cos_theta = max(dot(normal, vec3(0.0, 0.0, 1.0)), EPSILON);
+                vec3 color = fresnel_schlick(cos_theta, vec3(0.04)) * INV_PI;
+
+                f_color = vec4(mix(tonemap_reinhard(color), tonemap_aces(color), 0.5), 1.0);
+            }
+        "#,
+    }
+}
+
+#[allow(dead_code)]
+mod sdf_text_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        include: ["assets/shaders/include"],
+        src: r#"
+            #version 460
+
+            #include "sdf.glsl"
+
+            layout(location = 0) in vec2 v_uv;
+            layout(location = 0) out vec4 f_color;
+
+            layout(set = 0, binding = 0) uniform sampler2D glyph_sdf;
+
+            void main() {
+                f_color = vec4(vec3(1.0), sdf_coverage(texture(glyph_sdf, v_uv).r));
+            }
+        "#,
+    }
+}
+
+const INCLUDE_DIR : &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/shaders/include");
+
+#[test]
+fn every_include_has_a_guard() {
+    let mut count = 0;
+
+    for entry in fs::read_dir(Path::new(INCLUDE_DIR)).unwrap() {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(&path).unwrap();
+        let guard = path.file_name().unwrap().to_str().unwrap().replace('.', "_").to_uppercase();
+
+        assert!(source.starts_with(&format!("#ifndef {guard}\n#define {guard}\n")), "{} has no include guard", path.display());
+        assert!(source.trim_end().ends_with("#endif"), "{} does not close its guard", path.display());
+        count += 1;
+    }
+
+    assert!(count >= 2);
+}
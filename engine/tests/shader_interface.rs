@@ -0,0 +1,57 @@
+use engine::vulkan::shader_interface::{DescriptorBindingInfo, PushConstantInfo, ShaderInfo, VertexInputInfo};
+use vulkano::{descriptor_set::layout::DescriptorType, format::Format, shader::ShaderStage};
+
+fn binding(set : u32, binding : u32, descriptor_types : Vec<DescriptorType>, array_size : Option<u32>) -> DescriptorBindingInfo {
+    DescriptorBindingInfo { set, binding, descriptor_types, array_size }
+}
+
+#[test]
+fn bindings_print_their_types_and_count() {
+    assert_eq!(binding(0, 1, vec![DescriptorType::StorageImage], Some(1)).to_string(), "set 0 binding 1: StorageImage x1");
+    assert_eq!(
+        binding(2, 0, vec![DescriptorType::UniformBuffer, DescriptorType::UniformBufferDynamic], None).to_string(),
+        "set 2 binding 0: UniformBuffer | UniformBufferDynamic xruntime",
+    );
+}
+
+#[test]
+fn shader_info_prints_a_table_per_section() {
+    let info = ShaderInfo {
+        entry_point : "main".to_owned(),
+        stage : ShaderStage::Vertex,
+        descriptor_bindings : vec![
+            binding(0, 0, vec![DescriptorType::UniformBuffer], Some(1)),
+            binding(1, 3, vec![DescriptorType::CombinedImageSampler], Some(4)),
+        ],
+        push_constants : Some(PushConstantInfo { offset : 0, size : 64 }),
+        vertex_inputs : vec![
+            VertexInputInfo { location : 0, format : Format::R32G32B32_SFLOAT, name : Some("position".to_owned()) },
+            VertexInputInfo { location : 1, format : Format::R32G32_SFLOAT, name : None },
+        ],
+    };
+
+    let expected = [
+        "main (Vertex)",
+        "  set  binding  count    type",
+        "  0    0        1        UniformBuffer",
+        "  1    3        4        CombinedImageSampler",
+        "  push constants: 64 bytes at offset 0",
+        "  location format               name",
+        "  0        R32G32B32_SFLOAT     position",
+        "  1        R32G32_SFLOAT        -",
+    ];
+    assert_eq!(info.to_string().lines().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn empty_sections_are_left_out() {
+    let info = ShaderInfo {
+        entry_point : "CSMain".to_owned(),
+        stage : ShaderStage::Compute,
+        descriptor_bindings : Vec::new(),
+        push_constants : None,
+        vertex_inputs : Vec::new(),
+    };
+
+    assert_eq!(info.to_string(), "CSMain (Compute)\n");
+}
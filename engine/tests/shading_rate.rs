@@ -0,0 +1,23 @@
+use engine::vulkan::shading_rate::FragmentShadingRate;
+
+#[test]
+fn rates_encode_log2_of_their_size() {
+    assert_eq!(FragmentShadingRate::Rate1x1.encoded(), 0);
+    assert_eq!(FragmentShadingRate::Rate2x2.encoded(), 0b0101);
+    assert_eq!(FragmentShadingRate::Rate4x4.encoded(), 0b1010);
+}
+
+#[test]
+fn coarser_rates_need_fewer_invocations() {
+    let extent = [1920, 1080];
+
+    assert_eq!(FragmentShadingRate::Rate1x1.invocations(extent), 1920 * 1080);
+    assert_eq!(FragmentShadingRate::Rate2x2.invocations(extent), 960 * 540);
+    assert_eq!(FragmentShadingRate::Rate4x4.invocations(extent), 480 * 270);
+}
+
+#[test]
+fn partial_blocks_are_shaded_once() {
+    assert_eq!(FragmentShadingRate::Rate4x4.invocations([5, 5]), 4);
+    assert_eq!(FragmentShadingRate::Rate2x2.invocations([1, 1]), 1);
+}
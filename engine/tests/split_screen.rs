@@ -0,0 +1,60 @@
+use engine::{render::split_screen::{split_columns, takes_region_constants, ViewportRegion}, scene::camera::Camera};
+use vulkano::{pipeline::layout::PushConstantRange, shader::ShaderStages};
+
+#[test]
+fn odd_widths_split_without_gaps_or_overlap() {
+    for width in [1, 2, 799, 800, 1001] {
+        for count in 1..=4 {
+            let columns = split_columns([width, 600], count);
+            assert_eq!(columns.len(), count as usize);
+
+            let mut next = 0;
+            for (offset, extent) in &columns {
+                assert_eq!(offset[0], next, "width {width}, {count} columns");
+                assert_eq!(extent[1], 600);
+                next += extent[0];
+            }
+            assert_eq!(next, width, "width {width}, {count} columns");
+
+            // Columns differ by at most a pixel
+            let widths = columns.iter().map(|(_, extent)| extent[0]);
+            assert!(widths.clone().max().unwrap() - widths.min().unwrap() <= 1);
+        }
+    }
+}
+
+#[test]
+fn regions_follow_the_extent_they_were_built_for() {
+    let cameras = [
+        Camera::perspective([0.0, 0.0, 3.0], [0.0, 0.0, 0.0], 1.0),
+        Camera::perspective([3.0, 0.0, 0.0], [0.0, 0.0, 0.0], 1.0),
+    ];
+
+    let regions = ViewportRegion::columns([801, 600], &cameras);
+    assert_eq!(regions[0].scissor.offset, [0, 0]);
+    assert_eq!(regions[0].scissor.extent, [400, 600]);
+    assert_eq!(regions[1].scissor.offset, [400, 0]);
+    assert_eq!(regions[1].scissor.extent, [401, 600]);
+    assert_eq!(regions[1].viewport.offset, [400.0, 0.0]);
+    assert_eq!(regions[1].camera_uniform, cameras[1].view_projection(401.0 / 600.0));
+
+    // After a resize the same cameras split the new extent
+    let resized = ViewportRegion::columns([1024, 768], &cameras);
+    assert_eq!(resized[1].scissor.offset, [512, 0]);
+    assert_eq!(resized[1].viewport.extent, [512.0, 768.0]);
+}
+
+#[test]
+fn region_camera_needs_a_vertex_range_at_offset_zero_it_fits_in() {
+    let range = |stages, offset, size| PushConstantRange { stages, offset, size };
+
+    assert!(takes_region_constants(&[range(ShaderStages::VERTEX, 0, 64)]));
+    assert!(takes_region_constants(&[range(ShaderStages::VERTEX | ShaderStages::FRAGMENT, 0, 80)]));
+    assert!(takes_region_constants(&[range(ShaderStages::FRAGMENT, 64, 16), range(ShaderStages::VERTEX, 0, 64)]));
+
+    // Too small for a matrix, somewhere else than offset 0 or out of reach of the vertex shader
+    assert!(!takes_region_constants(&[]));
+    assert!(!takes_region_constants(&[range(ShaderStages::VERTEX, 0, 16)]));
+    assert!(!takes_region_constants(&[range(ShaderStages::VERTEX, 16, 64)]));
+    assert!(!takes_region_constants(&[range(ShaderStages::FRAGMENT, 0, 64)]));
+}
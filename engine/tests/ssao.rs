@@ -0,0 +1,45 @@
+use engine::{render::ssao::{hemisphere_kernel, SsaoCamera}, scene::camera::{multiply, Camera}};
+
+#[test]
+fn kernel_samples_lie_in_the_unit_hemisphere() {
+    let kernel = hemisphere_kernel(64, 7);
+    assert_eq!(kernel.len(), 64);
+
+    for [x, y, z, w] in kernel {
+        assert!(z >= 0.0, "({x}, {y}, {z}) is below the surface");
+        assert!((x * x + y * y + z * z).sqrt() <= 1.0 + 1e-6);
+        assert_eq!(w, 0.0);
+    }
+}
+
+#[test]
+fn kernel_samples_cluster_towards_the_center() {
+    let kernel = hemisphere_kernel(64, 7);
+    let length = |sample : &[f32; 4]| (sample[0] * sample[0] + sample[1] * sample[1] + sample[2] * sample[2]).sqrt();
+
+    // The first samples are scaled to at most a tenth of the radius and change little
+    assert!(kernel[..4].iter().all(|sample| length(sample) <= 0.11));
+    let inner = kernel[..32].iter().map(length).sum::<f32>();
+    let outer = kernel[32..].iter().map(length).sum::<f32>();
+    assert!(inner < outer);
+}
+
+#[test]
+fn kernels_are_deterministic_per_seed() {
+    assert_eq!(hemisphere_kernel(16, 3), hemisphere_kernel(16, 3));
+    assert_ne!(hemisphere_kernel(16, 3), hemisphere_kernel(16, 4));
+}
+
+#[test]
+fn camera_carries_the_inverse_projection() {
+    let projection = Camera::perspective([0.0, 0.0, 3.0], [0.0, 0.0, 0.0], 1.0).projection_matrix(16.0 / 9.0);
+    let camera = SsaoCamera::new(projection);
+
+    let identity = multiply(&camera.projection, &camera.inverse_projection);
+    for (column, values) in identity.iter().enumerate() {
+        for (row, value) in values.iter().enumerate() {
+            let expected = if row == column { 1.0 } else { 0.0 };
+            assert!((value - expected).abs() < 1e-4, "[{column}][{row}] = {value}");
+        }
+    }
+}
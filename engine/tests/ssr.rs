@@ -0,0 +1,23 @@
+use engine::render::ssr::screen_edge_fade;
+
+#[test]
+fn hits_away_from_the_edges_keep_their_full_weight() {
+    assert_eq!(screen_edge_fade([0.5, 0.5]), 1.0);
+    assert_eq!(screen_edge_fade([0.2, 0.75]), 1.0);
+}
+
+#[test]
+fn hits_fade_out_towards_any_edge() {
+    for uv in [[0.0, 0.5], [1.0, 0.5], [0.5, 0.0], [0.5, 1.0]] {
+        assert_eq!(screen_edge_fade(uv), 0.0, "{uv:?}");
+    }
+
+    assert!((screen_edge_fade([0.05, 0.5]) - 0.5).abs() < 1e-6);
+    assert!(screen_edge_fade([0.02, 0.5]) < screen_edge_fade([0.08, 0.5]));
+}
+
+#[test]
+fn the_nearest_edge_decides() {
+    assert!((screen_edge_fade([0.5, 0.97]) - screen_edge_fade([0.03, 0.5])).abs() < 1e-5);
+    assert!(screen_edge_fade([0.03, 0.03]) <= screen_edge_fade([0.03, 0.5]));
+}
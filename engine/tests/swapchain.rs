@@ -0,0 +1,26 @@
+use engine::{vulkan::swapchain::AcquireFailure, EngineError};
+use vulkano::{Validated, ValidationError, VulkanError};
+
+#[test]
+fn acquire_errors_are_routed_by_what_the_loop_can_do_about_them() {
+    assert!(matches!(AcquireFailure::from(VulkanError::OutOfDate), AcquireFailure::OutOfDate));
+    assert!(matches!(AcquireFailure::from(VulkanError::Timeout), AcquireFailure::Timeout));
+    assert!(matches!(AcquireFailure::from(VulkanError::NotReady), AcquireFailure::Timeout));
+    assert!(matches!(AcquireFailure::from(VulkanError::DeviceLost), AcquireFailure::DeviceLost));
+    assert!(matches!(AcquireFailure::from(VulkanError::SurfaceLost), AcquireFailure::Fatal(EngineError::Vulkan(VulkanError::SurfaceLost))));
+
+    // Invalid arguments end the loop instead of panicking
+    let invalid = Validated::ValidationError(Box::new(ValidationError::default()));
+    assert!(matches!(AcquireFailure::from(invalid), AcquireFailure::Fatal(EngineError::Validation(_))));
+    assert!(matches!(AcquireFailure::from(Validated::Error(VulkanError::OutOfDate)), AcquireFailure::OutOfDate));
+}
+
+#[test]
+fn a_lost_device_ends_the_loop_with_a_typed_error() {
+    assert!(matches!(EngineError::from(AcquireFailure::DeviceLost), EngineError::DeviceLost));
+    assert!(matches!(EngineError::from(AcquireFailure::Fatal(VulkanError::OutOfHostMemory.into())), EngineError::Vulkan(VulkanError::OutOfHostMemory)));
+
+    // Device loss reported anywhere else, such as by a fence or a submission, ends up the same
+    assert!(matches!(EngineError::from(VulkanError::DeviceLost), EngineError::DeviceLost));
+    assert_eq!(EngineError::DeviceLost.to_string(), "the device was lost");
+}
@@ -0,0 +1,57 @@
+use engine::render::texture_streaming::{average_color, feedback_tiles, TileCache, TileLoad, NOT_RESIDENT};
+
+#[test]
+fn missing_tiles_fill_the_free_slots_first() {
+    let mut cache = TileCache::new(3);
+
+    let loads = cache.update(&[7, 2], 8);
+    assert_eq!(loads, [TileLoad { tile : 7, slot : 0, evicted : None }, TileLoad { tile : 2, slot : 1, evicted : None }]);
+    assert_eq!(cache.page_table(8), [NOT_RESIDENT, NOT_RESIDENT, 1, NOT_RESIDENT, NOT_RESIDENT, NOT_RESIDENT, NOT_RESIDENT, 0]);
+
+    // Resident tiles need no load
+    assert!(cache.update(&[7, 2, 7], 8).is_empty());
+}
+
+#[test]
+fn the_least_recently_used_tile_is_evicted() {
+    let mut cache = TileCache::new(2);
+    cache.update(&[0, 1], 8);
+    cache.update(&[0], 8);
+
+    assert_eq!(cache.update(&[5], 8), [TileLoad { tile : 5, slot : 1, evicted : Some(1) }]);
+    assert_eq!(cache.slot_of(1), None);
+    assert_eq!(cache.slot_of(5), Some(1));
+    assert_eq!(cache.resident_count(), 2);
+}
+
+#[test]
+fn tiles_used_this_frame_are_never_evicted() {
+    let mut cache = TileCache::new(2);
+    cache.update(&[0, 1], 8);
+
+    // Three hot tiles for two slots, the third waits
+    assert!(cache.update(&[0, 1, 2], 8).is_empty());
+    assert_eq!(cache.slot_of(2), None);
+}
+
+#[test]
+fn loads_per_frame_are_limited() {
+    let mut cache = TileCache::new(4);
+
+    assert_eq!(cache.update(&[0, 1, 2], 2).len(), 2);
+    assert_eq!(cache.update(&[0, 1, 2], 2), [TileLoad { tile : 2, slot : 2, evicted : None }]);
+}
+
+#[test]
+fn feedback_bits_map_to_tiles() {
+    assert_eq!(feedback_tiles(&[0b1001, 1 << 31, 0b10], 70), [0, 3, 63, 65]);
+    // Bits past the last tile are ignored
+    assert_eq!(feedback_tiles(&[0, 0, u32::MAX], 66), [64, 65]);
+    assert!(feedback_tiles(&[0; 4], 128).is_empty());
+}
+
+#[test]
+fn fallback_colors_average_the_texels() {
+    assert_eq!(average_color(&[255, 0, 0, 255, 0, 0, 255, 255]), [0.5, 0.0, 0.5, 1.0]);
+    assert_eq!(average_color(&[]), [0.0; 4]);
+}
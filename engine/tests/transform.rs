@@ -0,0 +1,120 @@
+use std::f32::consts::FRAC_PI_2;
+
+use engine::scene::{camera::{multiply, Camera, Matrix4}, transform::{PushTransform, Quat, Transform, Vec3}};
+
+fn assert_matrix(actual : Matrix4, expected : Matrix4) {
+    for (column, (a, e)) in actual.iter().zip(expected).enumerate() {
+        for row in 0..4 {
+            assert!((a[row] - e[row]).abs() < 1e-5, "column {column}: {actual:?} != {expected:?}");
+        }
+    }
+}
+
+fn assert_vec(actual : Vec3, expected : Vec3) {
+    assert!(actual.abs_diff_eq(expected, 1e-5), "{actual:?} != {expected:?}");
+}
+
+fn apply(matrix : &Matrix4, point : [f32; 4]) -> [f32; 4] {
+    [0, 1, 2, 3].map(|row| (0..4).map(|column| matrix[column][row] * point[column]).sum())
+}
+
+#[test]
+fn trs_matrix_matches_hand_computed_columns() {
+    // Scale (2, 3, 4), a quarter turn around Y taking +X to -Z, then a move to (5, 6, 7)
+    let transform = Transform::new(
+        Vec3::new(5.0, 6.0, 7.0),
+        Quat::from_rotation_y(FRAC_PI_2),
+        Vec3::new(2.0, 3.0, 4.0),
+    );
+
+    assert_matrix(transform.matrix(), [
+        [0.0, 0.0, -2.0, 0.0],
+        [0.0, 3.0, 0.0, 0.0],
+        [4.0, 0.0, 0.0, 0.0],
+        [5.0, 6.0, 7.0, 1.0],
+    ]);
+}
+
+#[test]
+fn identity_is_the_default() {
+    assert_eq!(Transform::default(), Transform::IDENTITY);
+    assert_matrix(Transform::IDENTITY.matrix(), [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+}
+
+#[test]
+fn composition_matches_the_matrix_product() {
+    let parent = Transform::new(Vec3::new(1.0, 2.0, 3.0), Quat::from_rotation_z(0.7), Vec3::splat(2.0));
+    let child = Transform::new(Vec3::new(-4.0, 0.5, 1.0), Quat::from_rotation_x(-1.1), Vec3::splat(0.5));
+
+    assert_matrix((parent * child).matrix(), multiply(&parent.matrix(), &child.matrix()));
+    assert_vec((parent * child).transform_point(Vec3::X), parent.transform_point(child.transform_point(Vec3::X)));
+}
+
+#[test]
+fn inverse_undoes_the_transform() {
+    let transform = Transform::new(Vec3::new(3.0, -2.0, 8.0), Quat::from_rotation_y(0.4) * Quat::from_rotation_x(1.3), Vec3::splat(2.5));
+    let point = Vec3::new(0.3, -1.0, 4.0);
+
+    assert_vec(transform.inverse().transform_point(transform.transform_point(point)), point);
+    assert_matrix((transform * transform.inverse()).matrix(), Transform::IDENTITY.matrix());
+}
+
+#[test]
+fn lerp_interpolates_every_component() {
+    let from = Transform::IDENTITY;
+    let to = Transform::new(Vec3::new(2.0, 0.0, -4.0), Quat::from_rotation_y(FRAC_PI_2), Vec3::splat(3.0));
+
+    assert_eq!(from.lerp(&to, 0.0), from);
+
+    let half = from.lerp(&to, 0.5);
+    assert_vec(half.translation, Vec3::new(1.0, 0.0, -2.0));
+    assert_vec(half.scale, Vec3::splat(2.0));
+    assert!(half.rotation.angle_between(Quat::from_rotation_y(FRAC_PI_2 / 2.0)) < 1e-5);
+
+    assert_matrix(from.lerp(&to, 1.0).matrix(), to.matrix());
+}
+
+#[test]
+fn normal_matrix_keeps_normals_perpendicular_under_non_uniform_scale() {
+    let transform = Transform::from_scale(Vec3::new(4.0, 1.0, 1.0));
+
+    // The diagonal plane x + y = 0 has normal (1, 1, 0) and contains (1, -1, 0)
+    let tangent = transform.transform_point(Vec3::new(1.0, -1.0, 0.0));
+    let normal = apply(&transform.normal_matrix(), [1.0, 1.0, 0.0, 0.0]);
+    let normal = Vec3::new(normal[0], normal[1], normal[2]);
+    assert!(tangent.dot(normal).abs() < 1e-5);
+    assert_vec(normal, Vec3::new(0.25, 1.0, 0.0));
+
+    // Transforming the normal with the model matrix itself would tilt it off the surface
+    let wrong = apply(&transform.matrix(), [1.0, 1.0, 0.0, 0.0]);
+    assert!(tangent.dot(Vec3::new(wrong[0], wrong[1], wrong[2])).abs() > 1.0);
+}
+
+#[test]
+fn push_transform_is_a_column_major_mat4() {
+    let transform = Transform::from_translation(Vec3::new(1.0, 2.0, 3.0));
+    let push = PushTransform::from(&transform);
+
+    assert_eq!(std::mem::size_of::<PushTransform>(), 64);
+    assert_eq!(push.model[3], [1.0, 2.0, 3.0, 1.0]);
+}
+
+#[test]
+fn model_matrix_follows_vulkan_clip_space() {
+    let camera = Camera::perspective([0.0, 0.0, 5.0], [0.0, 0.0, 0.0], FRAC_PI_2);
+    let model = Transform::from_translation(Vec3::new(1.0, 1.0, 0.0));
+    let mvp = multiply(&camera.view_projection(1.0), &model.matrix());
+
+    let clip = apply(&mvp, [0.0, 0.0, 0.0, 1.0]);
+    let ndc = [clip[0] / clip[3], clip[1] / clip[3], clip[2] / clip[3]];
+
+    // Right stays positive X, up becomes negative Y, depth lands inside 0..1
+    assert!((ndc[0] - 0.2).abs() < 1e-5);
+    assert!((ndc[1] + 0.2).abs() < 1e-5);
+    assert!(ndc[2] > 0.0 && ndc[2] < 1.0);
+}
@@ -0,0 +1,7 @@
+use engine::vulkan::vertex::VulkanVertex;
+
+#[test]
+fn vertices_keep_their_position() {
+    assert_eq!(VulkanVertex::new(-0.5, 0.25).position, [-0.5, 0.25]);
+    assert_eq!(VulkanVertex::new(0.0, 0.0), VulkanVertex { position : [0.0; 2] });
+}
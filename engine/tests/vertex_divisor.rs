@@ -0,0 +1,49 @@
+use engine::vulkan::vertex_divisor::{divisor_descriptions, AttributeDivisor};
+use vulkano::{format::Format, pipeline::graphics::vertex_input::{VertexInputAttributeDescription, VertexInputBindingDescription, VertexInputRate, VertexInputState}};
+
+// Positions per vertex on binding 0, colors per instance on binding 1
+fn input_state() -> VertexInputState {
+    VertexInputState::new()
+    .binding(0, VertexInputBindingDescription { stride : 12, input_rate : VertexInputRate::Vertex })
+    .binding(1, VertexInputBindingDescription { stride : 4, input_rate : VertexInputRate::Instance { divisor : 1 } })
+    .attribute(0, VertexInputAttributeDescription { binding : 0, format : Format::R32G32B32_SFLOAT, offset : 0 })
+    .attribute(1, VertexInputAttributeDescription { binding : 1, format : Format::R8G8B8A8_UNORM, offset : 0 })
+}
+
+#[test]
+fn plain_instancing_needs_no_divisor_descriptions() {
+    assert!(divisor_descriptions(&input_state()).is_empty());
+}
+
+#[test]
+fn divisors_become_binding_divisor_descriptions() {
+    let state = input_state().with_attribute_divisor(1, 10);
+
+    let binding = &state.bindings[&1];
+    assert_eq!(binding.stride, 4);
+    assert!(matches!(binding.input_rate, VertexInputRate::Instance { divisor : 10 }));
+    assert_eq!(state.attributes.len(), 2);
+
+    let descriptions = divisor_descriptions(&state);
+    assert_eq!(descriptions.len(), 1);
+    assert_eq!((descriptions[0].binding, descriptions[0].divisor), (1, 10));
+}
+
+#[test]
+fn per_vertex_bindings_turn_per_instance_and_descriptions_are_sorted() {
+    let state = input_state()
+    .with_attribute_divisor(1, 0)
+    .with_attribute_divisor(0, 3);
+
+    let descriptions = divisor_descriptions(&state)
+    .into_iter()
+    .map(|description| (description.binding, description.divisor))
+    .collect::<Vec<_>>();
+    assert_eq!(descriptions, [(0, 3), (1, 0)]);
+}
+
+#[test]
+#[should_panic(expected = "no binding 2")]
+fn unknown_bindings_panic() {
+    let _ = input_state().with_attribute_divisor(2, 10);
+}
@@ -0,0 +1,43 @@
+use engine::vulkan::vulkan::{auto_tuned_local_size, workgroup_counts};
+
+#[test]
+fn exact_multiples_need_no_extra_group() {
+    assert_eq!(workgroup_counts([65536, 1, 1], [64, 1, 1]), [1024, 1, 1]);
+}
+
+#[test]
+fn partial_groups_are_rounded_up() {
+    assert_eq!(workgroup_counts([1000, 1, 1], [64, 1, 1]), [16, 1, 1]);
+    assert_eq!(workgroup_counts([1, 1, 1], [256, 1, 1]), [1, 1, 1]);
+}
+
+#[test]
+fn every_dimension_is_covered() {
+    assert_eq!(workgroup_counts([1920, 1080, 1], [8, 8, 1]), [240, 135, 1]);
+    assert_eq!(workgroup_counts([10, 10, 10], [4, 4, 4]), [3, 3, 3]);
+}
+
+#[test]
+fn auto_tuned_workgroups_are_close_to_square() {
+    let max_size = [1024, 1024, 64];
+
+    assert_eq!(auto_tuned_local_size(64, Some(32), 1024, max_size), [8, 8, 1]);
+    assert_eq!(auto_tuned_local_size(256, Some(64), 1024, max_size), [16, 16, 1]);
+    assert_eq!(auto_tuned_local_size(128, Some(32), 1024, max_size), [16, 8, 1]);
+}
+
+#[test]
+fn auto_tuned_workgroups_respect_device_limits() {
+    // Clamped to the invocation limit, then rounded down to whole subgroups
+    assert_eq!(auto_tuned_local_size(4096, Some(32), 1024, [1024, 1024, 64]), [32, 32, 1]);
+    assert_eq!(auto_tuned_local_size(100, Some(32), 1024, [1024, 1024, 64]), [12, 8, 1]);
+
+    // A target below one subgroup is kept as is
+    assert_eq!(auto_tuned_local_size(16, Some(32), 1024, [1024, 1024, 64]), [4, 4, 1]);
+
+    // Narrow height limits push the layout wide
+    assert_eq!(auto_tuned_local_size(64, None, 1024, [1024, 2, 64]), [32, 2, 1]);
+    // A prime count that doesn't fit shrinks to the next one that does
+    assert_eq!(auto_tuned_local_size(7, None, 1024, [4, 4, 4]), [3, 2, 1]);
+    assert_eq!(auto_tuned_local_size(0, None, 0, [1, 1, 1]), [1, 1, 1]);
+}